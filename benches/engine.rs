@@ -0,0 +1,172 @@
+//! Benchmarks of the JSONLogic test suites
+//!
+//! Every suite listed in `tests/suites/index.json` is evaluated through both
+//! execution paths: rules parsed with `parse_logic` and evaluated with
+//! `evaluate_in`, and thread-safe rules built with `compile` and evaluated
+//! with `evaluate_compiled_in`. Cases that are expected to fail, or that fail
+//! in either path, are left out.
+//!
+//! Criterion reports the time per suite. Heap allocations and arena bytes per
+//! evaluation are printed before the measurements, counted by a global
+//! allocator wrapper. Run a subset with a filter, such as
+//! `cargo bench -- compiled/arithmetic`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use datalogic_rs::{CompiledRule, DataLogic, DataValue, Logic};
+use serde_json::Value as JsonValue;
+
+/// The system allocator, counting allocations
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The rules and data of the passing cases of one suite
+struct Suite<'a> {
+    name: String,
+    logic: Vec<(Logic<'a>, DataValue<'a>)>,
+    compiled: Vec<(CompiledRule, DataValue<'a>)>,
+}
+
+/// Loads the suites listed in the index, keeping the cases both paths evaluate
+fn load_suites(dl: &DataLogic) -> Vec<Suite<'_>> {
+    let index = fs::read_to_string("tests/suites/index.json").expect("suite index");
+    let index: Vec<String> = serde_json::from_str(&index).expect("suite index");
+
+    index
+        .into_iter()
+        .map(|name| {
+            let source = fs::read_to_string(format!("tests/suites/{}", name)).expect("suite");
+            let cases: Vec<JsonValue> = serde_json::from_str(&source).expect("suite");
+
+            let mut suite = Suite {
+                name: name.trim_end_matches(".json").to_string(),
+                logic: Vec::new(),
+                compiled: Vec::new(),
+            };
+            for case in cases.iter().filter(|case| case.get("error").is_none()) {
+                let Some(rule) = case.get("rule") else {
+                    continue;
+                };
+                let rule = rule.to_string();
+                let data = dl
+                    .parse_data_json(case.get("data").unwrap_or(&JsonValue::Null))
+                    .unwrap();
+                let (Ok(logic), Ok(compiled)) =
+                    (dl.parse_logic(&rule, None), dl.compile(&rule, None))
+                else {
+                    continue;
+                };
+
+                let scratch = dl.scratch_arena();
+                let passes = dl.evaluate_in(&logic, &data, &scratch).is_ok()
+                    && dl.evaluate_compiled_in(&compiled, &data, &scratch).is_ok();
+                if passes {
+                    suite.logic.push((logic, data.clone()));
+                    suite.compiled.push((compiled, data));
+                }
+            }
+            suite
+        })
+        .filter(|suite| !suite.logic.is_empty())
+        .collect()
+}
+
+/// Prints the heap allocations and arena bytes per evaluation of each suite
+fn report_allocations(dl: &DataLogic, suites: &[Suite]) {
+    println!(
+        "{:<40} {:>14} {:>14} {:>14} {:>14}",
+        "suite", "logic allocs", "logic bytes", "compiled allocs", "compiled bytes"
+    );
+    for suite in suites {
+        let cases = suite.logic.len() as f64;
+        let measure = |evaluate: &dyn Fn(&datalogic_rs::arena::DataArena)| {
+            let scratch = dl.scratch_arena();
+            let before = ALLOCATIONS.load(Ordering::Relaxed);
+            evaluate(&scratch);
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+            (
+                allocations as f64 / cases,
+                scratch.memory_usage() as f64 / cases,
+            )
+        };
+
+        let (logic_allocs, logic_bytes) = measure(&|scratch| {
+            for (rule, data) in &suite.logic {
+                let _ = dl.evaluate_in(rule, data, scratch);
+            }
+        });
+        let (compiled_allocs, compiled_bytes) = measure(&|scratch| {
+            for (rule, data) in &suite.compiled {
+                let _ = dl.evaluate_compiled_in(rule, data, scratch);
+            }
+        });
+        println!(
+            "{:<40} {:>14.1} {:>14.1} {:>14.1} {:>14.1}",
+            suite.name, logic_allocs, logic_bytes, compiled_allocs, compiled_bytes
+        );
+    }
+}
+
+fn bench_suites(c: &mut Criterion) {
+    let dl = DataLogic::new();
+    let suites = load_suites(&dl);
+    report_allocations(&dl, &suites);
+
+    let mut group = c.benchmark_group("suites");
+    group
+        .sample_size(20)
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(1));
+
+    for suite in &suites {
+        group.throughput(Throughput::Elements(suite.logic.len() as u64));
+
+        group.bench_function(BenchmarkId::new("logic", &suite.name), |b| {
+            let mut scratch = dl.scratch_arena();
+            b.iter(|| {
+                for (rule, data) in &suite.logic {
+                    let _ = dl.evaluate_in(rule, data, &scratch);
+                }
+                scratch.reset();
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("compiled", &suite.name), |b| {
+            let mut scratch = dl.scratch_arena();
+            b.iter(|| {
+                for (rule, data) in &suite.compiled {
+                    let _ = dl.evaluate_compiled_in(rule, data, &scratch);
+                }
+                scratch.reset();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_suites);
+criterion_main!(benches);
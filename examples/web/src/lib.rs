@@ -1,6 +1,9 @@
 use wasm_bindgen::prelude::*;
 use datalogic_rs::DataLogic;
 
+// Links the `DataLogic` and `CompiledRule` classes of the library into this module
+pub use datalogic_rs::wasm::{WasmCompiledRule, WasmDataLogic};
+
 #[wasm_bindgen]
 pub struct JsJsonLogic {
     inner: DataLogic,
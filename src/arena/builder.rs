@@ -0,0 +1,304 @@
+//! Builders of strings and arrays concatenated from many pieces.
+//!
+//! Operators such as `cat` and `merge` concatenate values whose sizes depend
+//! on the data. Growing the result in a single buffer copies it every time
+//! the buffer is outgrown, and in the arena every outgrown copy stays
+//! allocated until the arena is reset, so big inputs cause large spikes.
+//!
+//! The builders keep a rope of the pieces instead. Pieces that already live
+//! in the arena, such as the strings and arrays of the data, are referenced
+//! rather than copied. Pieces smaller than a spill threshold are copied into
+//! a heap buffer, so that inputs made of many tiny pieces do not need one
+//! reference per piece. The result is allocated in the arena once, at its
+//! final size, and every piece is checked against the [`Limits`] of the
+//! arena before it is added, so an oversized result fails before it is built.
+//!
+//! [`Limits`]: crate::Limits
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::DataArena;
+use crate::logic::Result;
+use crate::value::DataValue;
+
+/// Strings shorter than this many bytes are copied rather than referenced
+const STRING_SPILL_THRESHOLD: usize = 64;
+
+/// Arrays shorter than this many items are copied rather than referenced
+const ARRAY_SPILL_THRESHOLD: usize = 8;
+
+/// A piece of a string rope
+enum StringPiece<'a> {
+    /// A string living in the arena
+    Borrowed(&'a str),
+    /// A range of the spill buffer
+    Spilled(Range<usize>),
+}
+
+/// Builds a string in the arena from pieces, within the string length limit.
+pub(crate) struct StringBuilder<'a> {
+    arena: &'a DataArena,
+    pieces: Vec<StringPiece<'a>>,
+    spilled: String,
+    len: usize,
+}
+
+impl<'a> StringBuilder<'a> {
+    /// Creates an empty builder.
+    pub(crate) fn new(arena: &'a DataArena) -> Self {
+        Self {
+            arena,
+            pieces: Vec::new(),
+            spilled: String::new(),
+            len: 0,
+        }
+    }
+
+    /// Appends a string living in the arena.
+    pub(crate) fn push_str(&mut self, s: &'a str) -> Result<()> {
+        self.reserve(s.len())?;
+        if s.len() < STRING_SPILL_THRESHOLD {
+            let start = self.spilled.len();
+            self.spilled.push_str(s);
+            self.extend_spilled(start);
+        } else {
+            self.pieces.push(StringPiece::Borrowed(s));
+        }
+        Ok(())
+    }
+
+    /// Appends a value converted to a string.
+    pub(crate) fn push_value(&mut self, value: &'a DataValue<'a>) -> Result<()> {
+        if let DataValue::String(s) = value {
+            return self.push_str(s);
+        }
+
+        let start = self.spilled.len();
+        let _ = write!(self.spilled, "{}", value);
+        let added = self.spilled.len() - start;
+        if let Err(error) = self.reserve(added) {
+            self.spilled.truncate(start);
+            return Err(error);
+        }
+        self.extend_spilled(start);
+        Ok(())
+    }
+
+    /// Appends the items of an array, each converted to a string.
+    pub(crate) fn push_values(&mut self, values: &'a [DataValue<'a>]) -> Result<()> {
+        values.iter().try_for_each(|value| self.push_value(value))
+    }
+
+    /// Allocates the string in the arena.
+    pub(crate) fn finish(self) -> &'a str {
+        if let [StringPiece::Borrowed(s)] = self.pieces.as_slice() {
+            return s;
+        }
+        let spilled = &self.spilled;
+        let pieces = self.pieces.iter().map(|piece| match piece {
+            StringPiece::Borrowed(s) => *s,
+            StringPiece::Spilled(range) => &spilled[range.clone()],
+        });
+        self.arena.alloc_str_concat(self.len, pieces)
+    }
+
+    /// Checks that the string can grow by `additional` bytes.
+    fn reserve(&mut self, additional: usize) -> Result<()> {
+        let len = self.len + additional;
+        self.arena.limits().check_string_len(len)?;
+        self.len = len;
+        Ok(())
+    }
+
+    /// Records the spill buffer from `start` as the last piece, merging it
+    /// with a spilled piece right before it.
+    fn extend_spilled(&mut self, start: usize) {
+        let end = self.spilled.len();
+        match self.pieces.last_mut() {
+            Some(StringPiece::Spilled(range)) if range.end == start => range.end = end,
+            _ => self.pieces.push(StringPiece::Spilled(start..end)),
+        }
+    }
+}
+
+/// A piece of an array rope
+enum ArrayPiece<'a> {
+    /// An array living in the arena
+    Borrowed(&'a [DataValue<'a>]),
+    /// A range of the spill buffer
+    Spilled(Range<usize>),
+}
+
+/// Builds an array in the arena from pieces, within the array length limit.
+pub(crate) struct ArrayBuilder<'a> {
+    arena: &'a DataArena,
+    pieces: Vec<ArrayPiece<'a>>,
+    spilled: Vec<DataValue<'a>>,
+    len: usize,
+}
+
+impl<'a> ArrayBuilder<'a> {
+    /// Creates an empty builder.
+    pub(crate) fn new(arena: &'a DataArena) -> Self {
+        Self {
+            arena,
+            pieces: Vec::new(),
+            spilled: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Appends the items of an array living in the arena.
+    pub(crate) fn extend(&mut self, items: &'a [DataValue<'a>]) -> Result<()> {
+        self.reserve(items.len())?;
+        if items.len() < ARRAY_SPILL_THRESHOLD {
+            let start = self.spilled.len();
+            self.spilled.extend_from_slice(items);
+            self.extend_spilled(start);
+        } else {
+            self.pieces.push(ArrayPiece::Borrowed(items));
+        }
+        Ok(())
+    }
+
+    /// Appends a single item.
+    pub(crate) fn push(&mut self, item: DataValue<'a>) -> Result<()> {
+        self.reserve(1)?;
+        let start = self.spilled.len();
+        self.spilled.push(item);
+        self.extend_spilled(start);
+        Ok(())
+    }
+
+    /// Allocates the array in the arena.
+    pub(crate) fn finish(self) -> &'a [DataValue<'a>] {
+        if let [ArrayPiece::Borrowed(items)] = self.pieces.as_slice() {
+            return items;
+        }
+        let spilled = &self.spilled;
+        let mut items = self.pieces.iter().flat_map(|piece| match piece {
+            ArrayPiece::Borrowed(items) => items.iter(),
+            ArrayPiece::Spilled(range) => spilled[range.clone()].iter(),
+        });
+        self.arena.alloc_slice_fill_with(self.len, |_| {
+            items
+                .next()
+                .expect("the pieces hold as many items as counted")
+                .clone()
+        })
+    }
+
+    /// Checks that the array can grow by `additional` items.
+    fn reserve(&mut self, additional: usize) -> Result<()> {
+        let len = self.len + additional;
+        self.arena.limits().check_array_len(len)?;
+        self.len = len;
+        Ok(())
+    }
+
+    /// Records the spill buffer from `start` as the last piece, merging it
+    /// with a spilled piece right before it.
+    fn extend_spilled(&mut self, start: usize) {
+        let end = self.spilled.len();
+        match self.pieces.last_mut() {
+            Some(ArrayPiece::Spilled(range)) if range.end == start => range.end = end,
+            _ => self.pieces.push(ArrayPiece::Spilled(start..end)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::LogicError;
+    use crate::Limits;
+
+    #[test]
+    fn test_string_builder() {
+        let arena = DataArena::new();
+        let long = arena.alloc_str(&"x".repeat(100));
+        let values = [
+            DataValue::integer(1),
+            DataValue::String("-"),
+            DataValue::Bool(true),
+        ];
+        let values = arena.alloc_data_value_slice(&values);
+
+        let mut builder = StringBuilder::new(&arena);
+        builder.push_str("ab").unwrap();
+        builder.push_str(long).unwrap();
+        builder.push_values(values).unwrap();
+        builder.push_value(arena.alloc(DataValue::Null)).unwrap();
+        assert_eq!(builder.finish(), format!("ab{}1-truenull", long));
+
+        // A single long piece is returned without copying it
+        let mut builder = StringBuilder::new(&arena);
+        builder.push_str(long).unwrap();
+        assert_eq!(builder.finish().as_ptr(), long.as_ptr());
+
+        assert_eq!(StringBuilder::new(&arena).finish(), "");
+    }
+
+    #[test]
+    fn test_array_builder() {
+        let arena = DataArena::new();
+        let long: Vec<DataValue> = (0..20).map(DataValue::integer).collect();
+        let long = arena.alloc_data_value_slice(&long);
+        let short = arena.alloc_data_value_slice(&[DataValue::integer(-1)]);
+
+        let mut builder = ArrayBuilder::new(&arena);
+        builder.extend(short).unwrap();
+        builder.push(DataValue::Bool(true)).unwrap();
+        builder.extend(long).unwrap();
+        builder.extend(short).unwrap();
+        let items = builder.finish();
+        assert_eq!(items.len(), 23);
+        assert_eq!(items[0], DataValue::integer(-1));
+        assert_eq!(items[1], DataValue::Bool(true));
+        assert_eq!(&items[2..22], long);
+        assert_eq!(items[22], DataValue::integer(-1));
+
+        let mut builder = ArrayBuilder::new(&arena);
+        builder.extend(long).unwrap();
+        assert_eq!(builder.finish().as_ptr(), long.as_ptr());
+
+        // The result is allocated once, at its final size
+        let big: Vec<DataValue> = (0..10_000).map(DataValue::integer).collect();
+        let big = arena.alloc_data_value_slice(&big);
+        let before = arena.used_bytes();
+        let mut builder = ArrayBuilder::new(&arena);
+        for _ in 0..4 {
+            builder.extend(big).unwrap();
+            builder.push(DataValue::Null).unwrap();
+        }
+        assert_eq!(builder.finish().len(), 40_004);
+        let item_size = std::mem::size_of::<DataValue>();
+        assert!(arena.used_bytes() - before < 40_004 * item_size + 4096);
+    }
+
+    #[test]
+    fn test_builders_check_limits_before_copying() {
+        let arena = DataArena::new();
+        arena.set_limits(Limits::new().with_max_string_len(4).with_max_array_len(2));
+
+        let mut builder = StringBuilder::new(&arena);
+        builder.push_str("abc").unwrap();
+        assert_eq!(
+            builder.push_value(arena.alloc(DataValue::integer(42))),
+            Err(LogicError::LimitExceeded {
+                limit: "max_string_len".to_string(),
+                max: 4,
+                actual: 5
+            })
+        );
+        builder.push_str("d").unwrap();
+        assert_eq!(builder.finish(), "abcd");
+
+        let values = arena.alloc_data_value_slice(&[DataValue::Null, DataValue::Null]);
+        let mut builder = ArrayBuilder::new(&arena);
+        builder.push(DataValue::Null).unwrap();
+        assert!(builder.extend(values).is_err());
+        assert_eq!(builder.finish().len(), 1);
+    }
+}
@@ -7,16 +7,31 @@
 //! The `DataArena` maintains shared references and context for evaluating
 //! logic expressions.
 
+use bumpalo::collections::String as BumpString;
 use bumpalo::collections::Vec as BumpVec;
 use bumpalo::Bump;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 
 use super::custom::{CustomOperator, CustomOperatorRegistry};
+use super::global::has_global_operator;
 use super::interner::StringInterner;
-use crate::logic::Result;
-use crate::value::{DataValue, NumberValue};
+use super::memo::{CacheStats, MemoizeConfig};
+use super::profile::{token_key, CoercionSite, CostProfile, FailureSite};
+use super::timeout::TimeoutConfig;
+use super::version::OperatorMetadata;
+use crate::context::EvaluationContext;
+use crate::limits::{Execution, Limits};
+use crate::logger::Logger;
+use crate::logic::Token;
+use crate::logic::{LogicError, Result};
+use crate::overrides::OperatorOverrides;
+use crate::semantics::{IntegerOverflow, OutOfRangeIndex, Semantics};
+use crate::value::{DataValue, HostHandle, HostObjects, ToJson};
+use std::any::Any;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 /// Maximum number of path components in the fixed-size array
 const PATH_CHAIN_CAPACITY: usize = 16;
@@ -95,6 +110,88 @@ impl fmt::Debug for PathChainVec {
     }
 }
 
+/// The position of an item in the collection an iteration goes over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IterationPosition {
+    /// The index of the item
+    pub(crate) index: usize,
+    /// The number of items of the collection
+    pub(crate) len: usize,
+}
+
+impl IterationPosition {
+    /// Creates the position of the item at `index` among `len` items.
+    pub(crate) fn new(index: usize, len: usize) -> Self {
+        Self { index, len }
+    }
+}
+
+/// The settings deciding how an arena evaluates rules
+///
+/// Scratch arenas and the arenas of compiled rules take the settings of the
+/// arena they are created from as a unit, so that no setting is left behind.
+#[derive(Clone)]
+pub(crate) struct Settings {
+    /// Whether panics of custom operators are converted into errors
+    pub(crate) panic_isolation: bool,
+
+    /// The edge-case behavior of the operators
+    pub(crate) semantics: Semantics,
+
+    /// What variables read at out of range array indices
+    pub(crate) out_of_range_index: OutOfRangeIndex,
+
+    /// What integer arithmetic does on overflow
+    pub(crate) integer_overflow: IntegerOverflow,
+
+    /// Caps on the sizes of values built by operators
+    pub(crate) limits: Limits,
+
+    /// Regional settings of the current evaluation
+    pub(crate) evaluation_context: Option<EvaluationContext>,
+
+    /// The callback receiving the values reported by the `log` operator
+    pub(crate) logger: Option<Logger>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            panic_isolation: true,
+            semantics: Semantics::default(),
+            out_of_range_index: OutOfRangeIndex::default(),
+            integer_overflow: IntegerOverflow::default(),
+            limits: Limits::default(),
+            evaluation_context: None,
+            logger: None,
+        }
+    }
+}
+
+/// The window of items kept by the filter at the root of a windowed
+/// evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FilterWindow {
+    /// The address of the arguments of the root filter, telling it apart
+    /// from the filters nested in it
+    args: usize,
+    /// The number of matching items skipped
+    offset: usize,
+    /// The number of matching items kept
+    limit: usize,
+}
+
+/// A context entered by an evaluation
+#[derive(Debug, Clone, Copy)]
+struct Scope {
+    /// The length of the path chain once the context was entered
+    chain_len: usize,
+    /// The context itself
+    context: &'static DataValue<'static>,
+    /// The position of the context, if it is an item of an iteration
+    position: Option<IterationPosition>,
+}
+
 /// An arena allocator for efficient data allocation.
 ///
 /// The DataArena provides memory management for DataLogic values, with
@@ -107,7 +204,9 @@ pub struct DataArena {
     interner: RefCell<StringInterner>,
 
     /// Custom operator registry for evaluating custom operators
-    custom_operators: RefCell<CustomOperatorRegistry>,
+    ///
+    /// Shared with the scratch arenas created from this arena.
+    custom_operators: Arc<RwLock<CustomOperatorRegistry>>,
 
     /// Chunk size for allocations (in bytes)
     chunk_size: usize,
@@ -141,6 +240,74 @@ pub struct DataArena {
 
     /// Current path chain - represents the path from root to current position
     path_chain: RefCell<PathChainVec>,
+
+    /// Enclosing contexts, innermost last
+    scopes: RefCell<Vec<Scope>>,
+
+    /// Operator overrides active for the current evaluation
+    operator_overrides: RefCell<Option<OperatorOverrides>>,
+
+    /// Host objects created by custom operators during the current evaluation
+    host_objects: RefCell<HostObjects>,
+
+    /// Whether the evaluator records expression costs
+    profiling: Cell<bool>,
+
+    /// Expression costs recorded while profiling
+    profile: RefCell<CostProfile>,
+
+    /// Whether the evaluator records the site of failures
+    error_tracing: Cell<bool>,
+
+    /// The innermost failing expression recorded while tracing errors
+    failure: RefCell<Option<FailureSite>>,
+
+    /// Whether the evaluator records implicit conversions of operands
+    coercion_tracing: Cell<bool>,
+
+    /// The innermost node being evaluated while tracing coercions
+    current_node: Cell<usize>,
+
+    /// Implicit conversions recorded while tracing coercions
+    coercions: RefCell<Vec<CoercionSite>>,
+
+    /// The nodes being evaluated, outermost first, while a logger is set
+    nodes: RefCell<Vec<&'static Token<'static>>>,
+
+    /// Values assigned in the enclosing `do` blocks, latest last
+    bindings: RefCell<Vec<(&'static str, &'static DataValue<'static>)>>,
+
+    /// The number of bindings at the start of each enclosing `do` block
+    blocks: RefCell<Vec<usize>>,
+
+    /// The settings deciding how rules are evaluated
+    settings: RefCell<Settings>,
+
+    /// The work done by the current evaluation, counted when an execution
+    /// limit is set
+    execution: Cell<Option<Execution>>,
+
+    /// The id of the compiled rule being evaluated, if it has one
+    rule_id: RefCell<Option<Arc<str>>>,
+
+    /// The window of items kept by the root filter of a windowed evaluation,
+    /// until that filter takes it
+    filter_window: Cell<Option<FilterWindow>>,
+
+    /// Values owning heap memory that tokens refer to, such as compiled
+    /// regexes, dropped when the arena is reset
+    constants: RefCell<Vec<Box<dyn Any + Send + Sync>>>,
+}
+
+/// Extracts the message of a panic payload.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
 }
 
 impl Default for DataArena {
@@ -195,7 +362,7 @@ impl DataArena {
         Self {
             bump,
             interner: RefCell::new(StringInterner::with_capacity(64)), // Start with reasonable capacity
-            custom_operators: RefCell::new(CustomOperatorRegistry::new()),
+            custom_operators: Arc::new(RwLock::new(CustomOperatorRegistry::new())),
             chunk_size,
             null_value: &NULL_VALUE,
             true_value: &TRUE_VALUE,
@@ -207,6 +374,24 @@ impl DataArena {
             current_context: RefCell::new(None),
             root_context: RefCell::new(None),
             path_chain: RefCell::new(PathChainVec::new()),
+            scopes: RefCell::new(Vec::with_capacity(PATH_CHAIN_CAPACITY)),
+            operator_overrides: RefCell::new(None),
+            host_objects: RefCell::new(HostObjects::default()),
+            profiling: Cell::new(false),
+            profile: RefCell::new(CostProfile::default()),
+            error_tracing: Cell::new(false),
+            failure: RefCell::new(None),
+            coercion_tracing: Cell::new(false),
+            current_node: Cell::new(0),
+            coercions: RefCell::new(Vec::new()),
+            nodes: RefCell::new(Vec::new()),
+            bindings: RefCell::new(Vec::new()),
+            blocks: RefCell::new(Vec::new()),
+            settings: RefCell::new(Settings::default()),
+            execution: Cell::new(None),
+            rule_id: RefCell::new(None),
+            filter_window: Cell::new(None),
+            constants: RefCell::new(Vec::new()),
         }
     }
 
@@ -296,6 +481,21 @@ impl DataArena {
         self.bump.alloc(value)
     }
 
+    /// Stores a value that needs to be dropped, such as a compiled regex.
+    ///
+    /// Values allocated with [`alloc`](Self::alloc) are never dropped, which
+    /// leaks the heap memory they own. Constants are dropped when the arena
+    /// is reset or dropped instead.
+    pub(crate) fn alloc_constant<T: Any + Send + Sync>(&self, value: T) -> &T {
+        let constant = Box::new(value);
+        let pointer: *const T = &*constant;
+        self.constants.borrow_mut().push(constant);
+        // SAFETY: The value is boxed, so it stays in place when the vector
+        // grows, and it is only dropped by `reset` or when the arena is
+        // dropped, both of which require that no borrow of the arena is alive.
+        unsafe { &*pointer }
+    }
+
     /// Allocates a slice in the arena by copying from a slice.
     ///
     /// # Arguments
@@ -327,6 +527,23 @@ impl DataArena {
         self.bump.alloc_str(s)
     }
 
+    /// Allocates the concatenation of string pieces, whose lengths add up to
+    /// `len` bytes, in a single allocation.
+    pub(crate) fn alloc_str_concat<'s>(
+        &self,
+        len: usize,
+        pieces: impl IntoIterator<Item = &'s str>,
+    ) -> &str {
+        if len == 0 {
+            return self.empty_string();
+        }
+        let mut result = BumpString::with_capacity_in(len, &self.bump);
+        for piece in pieces {
+            result.push_str(piece);
+        }
+        result.into_bump_str()
+    }
+
     /// Interns a string, returning a reference to a unique instance.
     ///
     /// This uses the string interner to deduplicate strings, reducing memory usage.
@@ -353,6 +570,8 @@ impl DataArena {
         self.bump.reset();
         self.interner = RefCell::new(StringInterner::with_capacity(64));
         self.clear_contexts_and_paths();
+        self.clear_host_objects();
+        self.constants.get_mut().clear();
     }
 
     /// Clears all contexts and path information.
@@ -361,6 +580,10 @@ impl DataArena {
         self.current_context.replace(None);
         self.root_context.replace(None);
         self.path_chain.replace(PathChainVec::new());
+        self.scopes.get_mut().clear();
+        self.nodes.get_mut().clear();
+        self.bindings.get_mut().clear();
+        self.blocks.get_mut().clear();
     }
 
     /// Returns the current memory usage of the arena in bytes.
@@ -369,6 +592,15 @@ impl DataArena {
         self.bump.allocated_bytes()
     }
 
+    /// Returns the number of bytes handed out by the arena.
+    ///
+    /// Unlike [`memory_usage`](Self::memory_usage), this excludes the unused
+    /// capacity of the current chunk, so it grows with every allocation.
+    #[inline]
+    pub fn used_bytes(&self) -> usize {
+        self.bump.allocated_bytes() - self.bump.chunk_capacity()
+    }
+
     /// Creates a new temporary arena for short-lived allocations.
     ///
     /// This is useful for operations that need temporary allocations
@@ -378,6 +610,19 @@ impl DataArena {
         DataArena::with_chunk_size(self.chunk_size)
     }
 
+    /// Creates an arena for the temporaries of evaluations.
+    ///
+    /// Unlike [`create_temp_arena`](Self::create_temp_arena), the scratch
+    /// arena shares the custom operators of this arena, so rules parsed here
+    /// can be evaluated in it. Resetting it frees the memory of past
+    /// evaluations while the rules stay in this arena.
+    pub fn create_scratch_arena(&self) -> DataArena {
+        let mut scratch = DataArena::with_chunk_size(self.chunk_size);
+        scratch.custom_operators = Arc::clone(&self.custom_operators);
+        scratch.replace_settings(self.settings());
+        scratch
+    }
+
     /// Allocates a slice in the arena and fills it with values generated by a function.
     ///
     /// # Arguments
@@ -550,6 +795,7 @@ impl DataArena {
     /// Sets the current context for the arena.
     ///
     /// This establishes a new current context and records the path component.
+    /// The previous context is restored once the component is popped.
     ///
     /// # Arguments
     ///
@@ -563,6 +809,43 @@ impl DataArena {
 
         self.current_context.replace(Some(static_context));
         self.push_path_key(key);
+        let chain_len = self.path_chain_len();
+        self.scopes.borrow_mut().push(Scope {
+            chain_len,
+            context: static_context,
+            position: None,
+        });
+    }
+
+    /// Sets an item of an iteration as the current context.
+    ///
+    /// Like [`set_current_context`](Self::set_current_context), but also
+    /// records the position of the item, which rules read as the `index`,
+    /// `first` and `last` metadata of the iteration.
+    #[inline]
+    pub(crate) fn set_iteration_context<'a>(
+        &self,
+        item: &'a DataValue<'a>,
+        key: &'a DataValue<'a>,
+        position: IterationPosition,
+    ) {
+        self.set_current_context(item, key);
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.position = Some(position);
+        }
+    }
+
+    /// Returns the position and the key of the item of an enclosing iteration.
+    ///
+    /// A level of 0 is the current context, and every level above it leaves
+    /// one enclosing context. Returns `None` if the context at that level is
+    /// not an item of an iteration.
+    pub(crate) fn iteration(&self, level: usize) -> Option<(IterationPosition, &DataValue<'_>)> {
+        let scopes = self.scopes.borrow();
+        let scope = scopes.get(scopes.len().checked_sub(level + 1)?)?;
+        let position = scope.position?;
+        let key = self.path_chain.borrow().as_slice()[scope.chain_len - 1];
+        Some((position, self.transmute_lifetime(key)))
     }
 
     /// Returns the current context for the arena.
@@ -571,11 +854,14 @@ impl DataArena {
     ///
     /// * `scope_jump` - How many levels to jump up the scope chain (0 means current context)
     ///
+    /// Every two levels leave one enclosing iteration, so a jump of 2 from
+    /// inside a nested `map` reaches the item of the outer `map`.
+    ///
     /// # Returns
     ///
     /// The context data value, or None if no context is set
     #[inline]
-    pub fn current_context(&self, scope_jump: usize) -> Option<&DataValue> {
+    pub fn current_context(&self, scope_jump: usize) -> Option<&DataValue<'_>> {
         // Fast path for the common case (no scope jump)
         if scope_jump == 0 {
             return *self.current_context.borrow();
@@ -587,20 +873,23 @@ impl DataArena {
 
     /// Returns the root context for the arena.
     ///
-    /// This also resets the path chain.
+    /// This also resets the path chain and the enclosing contexts.
     ///
     /// # Returns
     ///
     /// The root context data value, or None if no root context is set
     #[inline]
-    pub fn root_context(&self) -> Option<&DataValue> {
+    pub fn root_context(&self) -> Option<&DataValue<'_>> {
         // Reset the path chain when getting root context
-        self.path_chain.borrow_mut().clear();
+        self.clear_path_chain();
         *self.root_context.borrow()
     }
 
     /// Sets the root context for the arena.
     ///
+    /// This starts a new evaluation, so contexts enclosing a previous one are
+    /// forgotten.
+    ///
     /// # Arguments
     ///
     /// * `context` - The root context data value
@@ -611,6 +900,9 @@ impl DataArena {
             unsafe { mem::transmute::<&'a DataValue<'a>, &'static DataValue<'static>>(context) };
 
         self.root_context.replace(Some(static_context));
+        self.scopes.borrow_mut().clear();
+        self.execution
+            .set(self.settings.borrow().limits.start_execution());
     }
 
     /// Get a context after jumping up the scope chain.
@@ -626,120 +918,25 @@ impl DataArena {
     /// The context data value after jumping up the scope chain
     #[cold]
     #[inline(never)]
-    fn root_context_with_jump(&self, scope_jump: usize) -> Option<&DataValue> {
+    fn root_context_with_jump(&self, scope_jump: usize) -> Option<&DataValue<'_>> {
         if scope_jump == 0 {
             return *self.current_context.borrow();
         }
 
-        // Get the current path chain
-        let chain_len = self.path_chain_len();
-
-        if scope_jump >= chain_len {
-            // If trying to jump beyond the root, return the root context
-            // We must always return a valid context, never None
-            return match *self.root_context.borrow() {
-                Some(ctx) => Some(ctx),
-                None => Some(self.null_value()), // Return null if no root context
-            };
-        }
-
-        // Get the root context, never returning None
-        let root = match *self.root_context.borrow() {
-            Some(ctx) => ctx,
-            None => return Some(self.null_value()), // Return null if no root context
-        };
-
-        // Use an optimization to avoid allocating a new vector when possible
-        let path_chain = self.path_chain.borrow();
-        let path_slice = path_chain.as_slice();
-
-        // Navigate to the correct context without creating intermediate vectors
-        self.navigate_to_context(root, path_slice, chain_len - scope_jump)
-    }
-
-    /// Helper function to navigate through a context without allocating
-    ///
-    /// # Arguments
-    ///
-    /// * `root` - The root context to start from
-    /// * `path_components` - The path components to navigate through
-    /// * `depth` - How many components to include in the navigation
-    ///
-    /// # Returns
-    ///
-    /// The context data value after navigating to the specified depth
-    #[inline(never)]
-    fn navigate_to_context<'a>(
-        &'a self,
-        root: &'a DataValue<'a>,
-        path_components: &[&'a DataValue<'a>],
-        depth: usize,
-    ) -> Option<&'a DataValue<'a>> {
-        let mut current = root;
-
-        // Only navigate to the specified depth
-        for component in path_components.iter().take(depth) {
-            match component {
-                DataValue::String(key) => {
-                    if !self.navigate_by_string_key(&mut current, key) {
-                        return Some(self.null_value());
-                    }
-                }
-                DataValue::Number(n) => {
-                    if !self.navigate_by_array_index(&mut current, n) {
-                        return Some(self.null_value());
-                    }
-                }
-                _ => return Some(self.null_value()),
-            }
-        }
-
-        Some(current)
-    }
-
-    /// Navigate an object by string key
-    ///
-    /// # Returns
-    ///
-    /// `true` if navigation succeeded, `false` if key not found or not an object
-    #[inline]
-    fn navigate_by_string_key<'a>(&'a self, current: &mut &'a DataValue<'a>, key: &str) -> bool {
-        if let DataValue::Object(entries) = *current {
-            for &(k, ref v) in *entries {
-                if k == key {
-                    *current = v;
-                    return true;
-                }
-            }
-            false // Key not found
-        } else {
-            false // Not an object
+        // The last scope is the current context, the ones below it enclose it
+        let levels = scope_jump.div_ceil(2);
+        let scopes = self.scopes.borrow();
+        if levels < scopes.len() {
+            let context = scopes[scopes.len() - 1 - levels].context;
+            return Some(self.transmute_lifetime(context));
         }
-    }
 
-    /// Navigate an array by index
-    ///
-    /// # Returns
-    ///
-    /// `true` if navigation succeeded, `false` if invalid index or not an array
-    #[inline]
-    fn navigate_by_array_index<'a>(
-        &'a self,
-        current: &mut &'a DataValue<'a>,
-        n: &NumberValue,
-    ) -> bool {
-        if let Some(idx) = n.as_i64() {
-            if idx >= 0 {
-                let index = idx as usize;
-                if let DataValue::Array(items) = *current {
-                    if index < items.len() {
-                        *current = &items[index];
-                        return true;
-                    }
-                }
-            }
+        // Jumping beyond the outermost scope reaches the root context
+        // We must always return a valid context, never None
+        match *self.root_context.borrow() {
+            Some(ctx) => Some(ctx),
+            None => Some(self.null_value()), // Return null if no root context
         }
-        false
     }
 
     //
@@ -762,22 +959,44 @@ impl DataArena {
 
     /// Removes the last component from the path chain.
     ///
+    /// If the component was recorded by [`set_current_context`](Self::set_current_context),
+    /// the context it replaced becomes current again.
+    ///
     /// # Returns
     ///
     /// The removed path component, or None if the path chain is empty
     #[inline]
-    pub fn pop_path_component(&self) -> Option<&DataValue> {
+    pub fn pop_path_component(&self) -> Option<&DataValue<'_>> {
         // SAFETY: The static lifetime can be safely narrowed
-        self.path_chain
-            .borrow_mut()
-            .pop()
-            .map(|v| self.transmute_lifetime(v))
+        let popped = self.path_chain.borrow_mut().pop()?;
+        let chain_len = self.path_chain_len();
+
+        let mut scopes = self.scopes.borrow_mut();
+        if scopes
+            .last()
+            .is_some_and(|scope| scope.chain_len > chain_len)
+        {
+            while scopes
+                .last()
+                .is_some_and(|scope| scope.chain_len > chain_len)
+            {
+                scopes.pop();
+            }
+            let enclosing = match scopes.last() {
+                Some(scope) => Some(scope.context),
+                None => *self.root_context.borrow(),
+            };
+            self.current_context.replace(enclosing);
+        }
+
+        Some(self.transmute_lifetime(popped))
     }
 
     /// Clears the path chain.
     #[inline]
     pub fn clear_path_chain(&self) {
         self.path_chain.borrow_mut().clear();
+        self.scopes.borrow_mut().clear();
     }
 
     /// Returns the length of the path chain.
@@ -790,7 +1009,7 @@ impl DataArena {
     ///
     /// This allocates a new vector.
     #[inline]
-    pub fn path_chain_as_slice(&self) -> Vec<&DataValue> {
+    pub fn path_chain_as_slice(&self) -> Vec<&DataValue<'_>> {
         let chain = self.path_chain.borrow();
         chain
             .as_slice()
@@ -827,7 +1046,7 @@ impl DataArena {
     ///
     /// The last path component, or None if the path chain is empty
     #[inline]
-    pub fn last_path_component(&self) -> Option<&DataValue> {
+    pub fn last_path_component(&self) -> Option<&DataValue<'_>> {
         // SAFETY: The static lifetime can be safely narrowed
         self.path_chain
             .borrow()
@@ -860,29 +1079,640 @@ impl DataArena {
 
     /// Register a custom operator
     pub fn register_custom_operator(&self, name: &str, operator: Box<dyn CustomOperator>) {
-        self.custom_operators.borrow_mut().register(name, operator);
+        self.custom_operators_mut().register(name, operator);
+    }
+
+    /// Borrow the registry of custom operators
+    pub(crate) fn custom_operator_registry(&self) -> RwLockReadGuard<'_, CustomOperatorRegistry> {
+        // Operators run while the registry is read, so their panics never poison it
+        self.custom_operators
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 
-    /// Check if a custom operator exists
+    /// Borrow the registry of custom operators for a registration
+    fn custom_operators_mut(&self) -> RwLockWriteGuard<'_, CustomOperatorRegistry> {
+        self.custom_operators
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Check if a custom operator exists, either in this arena or globally
     pub fn has_custom_operator(&self, name: &str) -> bool {
-        self.custom_operators.borrow().get(name).is_some()
+        self.custom_operator_registry().get(name).is_some() || has_global_operator(name)
+    }
+
+    /// Register a version of a namespaced custom operator
+    pub fn register_versioned_custom_operator(
+        &self,
+        name: &str,
+        metadata: OperatorMetadata,
+        operator: Box<dyn CustomOperator>,
+    ) {
+        self.custom_operators_mut()
+            .register_versioned(name, metadata, operator);
+    }
+
+    /// Resolve a possibly versioned custom operator name
+    ///
+    /// Returns the name the best matching version is registered under and
+    /// its metadata, or `None` if the name does not refer to a versioned
+    /// operator.
+    pub fn resolve_versioned_operator(
+        &self,
+        name: &str,
+    ) -> Result<Option<(String, OperatorMetadata)>> {
+        let registry = self.custom_operator_registry();
+        let resolved = registry.resolve_versioned(name)?;
+        Ok(resolved.map(|(key, metadata)| (key, metadata.clone())))
+    }
+
+    /// Copy the versioned operator metadata of another arena
+    pub(crate) fn copy_operator_versions_from(&self, other: &DataArena) {
+        let versions = other.custom_operator_registry();
+        self.custom_operators_mut().copy_versions_from(&versions);
+    }
+
+    /// Copy the settings and versioned operator metadata of another arena,
+    /// so that rules are parsed, optimized and evaluated as they would be
+    /// there
+    pub(crate) fn copy_settings_from(&self, other: &DataArena) {
+        self.copy_operator_versions_from(other);
+        self.replace_settings(other.settings());
+    }
+
+    /// Returns the settings deciding how rules are evaluated
+    pub(crate) fn settings(&self) -> Settings {
+        self.settings.borrow().clone()
+    }
+
+    /// Replaces the settings deciding how rules are evaluated, returning the
+    /// previous ones
+    pub(crate) fn replace_settings(&self, settings: Settings) -> Settings {
+        self.settings.replace(settings)
+    }
+
+    /// Register a custom operator whose results are memoized across evaluations
+    pub fn register_memoized_custom_operator(
+        &self,
+        name: &str,
+        operator: Box<dyn CustomOperator>,
+        config: MemoizeConfig,
+    ) {
+        self.custom_operators_mut()
+            .register_memoized(name, operator, config);
+    }
+
+    /// Returns the cache statistics of a memoized custom operator
+    pub fn custom_operator_cache_stats(&self, name: &str) -> Option<CacheStats> {
+        self.custom_operator_registry().cache_stats(name)
+    }
+
+    /// Clears the cached results of a memoized custom operator
+    pub fn clear_custom_operator_cache(&self, name: &str) {
+        self.custom_operator_registry().clear_cache(name);
+    }
+
+    /// Sets the timeout of the calls of a registered custom operator
+    pub fn set_custom_operator_timeout(&self, name: &str, config: TimeoutConfig) -> Result<()> {
+        self.custom_operators_mut().set_timeout(name, config)
+    }
+
+    /// Returns the timeout of a registered custom operator, if it has one
+    pub fn custom_operator_timeout(&self, name: &str) -> Option<TimeoutConfig> {
+        self.custom_operator_registry().timeout(name).cloned()
     }
 
     /// Evaluate a custom operator with the given name and arguments
+    ///
+    /// Active operator overrides take precedence over registered operators.
+    ///
+    /// With panic isolation enabled, a panic of the operator is caught and
+    /// returned as [`LogicError::OperatorPanicked`](crate::LogicError::OperatorPanicked).
     pub fn evaluate_custom_operator<'a>(
         &'a self,
         name: &str,
         args: &'a [DataValue<'a>],
     ) -> Result<&'a DataValue<'a>> {
-        // Get the custom operator
-        if let Some(op) = self.custom_operators.borrow().get(name) {
-            // Call the custom operator with owned values to get an owned DataValue
-            op.evaluate(args, self)
-        } else {
-            Err(crate::logic::LogicError::OperatorNotFoundError {
+        let evaluate = || {
+            if let Some(operator) = self.operator_override(name) {
+                return operator.evaluate(args, self);
+            }
+            self.evaluate_registered_operator(name, args)
+        };
+
+        if !self.panic_isolation() {
+            return evaluate();
+        }
+        panic::catch_unwind(AssertUnwindSafe(evaluate)).unwrap_or_else(|payload| {
+            Err(LogicError::OperatorPanicked {
                 operator: name.to_string(),
+                message: panic_message(payload.as_ref()),
             })
+        })
+    }
+
+    /// Enable or disable catching panics of custom operators
+    ///
+    /// Isolation is enabled by default, so a panicking operator fails the
+    /// evaluation instead of unwinding through the caller. It has no effect
+    /// when the crate is built with `panic = "abort"`.
+    pub fn set_panic_isolation(&self, enabled: bool) {
+        self.settings.borrow_mut().panic_isolation = enabled;
+    }
+
+    /// Check if panics of custom operators are caught
+    pub fn panic_isolation(&self) -> bool {
+        self.settings.borrow().panic_isolation
+    }
+
+    /// Sets the edge-case behavior of the operators
+    pub fn set_semantics(&self, semantics: Semantics) {
+        self.settings.borrow_mut().semantics = semantics;
+    }
+
+    /// Returns the edge-case behavior of the operators
+    pub fn semantics(&self) -> Semantics {
+        self.settings.borrow().semantics
+    }
+
+    /// Sets what variables read at out of range array indices
+    pub fn set_out_of_range_index(&self, policy: OutOfRangeIndex) {
+        self.settings.borrow_mut().out_of_range_index = policy;
+    }
+
+    /// Returns what variables read at out of range array indices
+    pub fn out_of_range_index(&self) -> OutOfRangeIndex {
+        self.settings.borrow().out_of_range_index
+    }
+
+    /// Sets what integer arithmetic does on overflow
+    pub fn set_integer_overflow(&self, policy: IntegerOverflow) {
+        self.settings.borrow_mut().integer_overflow = policy;
+    }
+
+    /// Returns what integer arithmetic does on overflow
+    pub fn integer_overflow(&self) -> IntegerOverflow {
+        self.settings.borrow().integer_overflow
+    }
+
+    /// Sets the window of matching items kept by the filter taking `args`.
+    ///
+    /// Filters nested in that one, taking other arguments, keep every item.
+    pub(crate) fn set_filter_window(&self, args: &[&Token], offset: usize, limit: usize) {
+        self.filter_window.set(Some(FilterWindow {
+            args: args.as_ptr() as usize,
+            offset,
+            limit,
+        }));
+    }
+
+    /// Takes the offset and limit of the window set for the filter taking
+    /// `args`, if any.
+    pub(crate) fn take_filter_window(&self, args: &[&Token]) -> Option<(usize, usize)> {
+        let window = self.filter_window.get()?;
+        if window.args != args.as_ptr() as usize {
+            return None;
+        }
+        self.filter_window.set(None);
+        Some((window.offset, window.limit))
+    }
+
+    /// Removes the filter window, returning true if no filter took it.
+    pub(crate) fn clear_filter_window(&self) -> bool {
+        self.filter_window.take().is_some()
+    }
+
+    /// Sets the caps on the sizes of values built by operators
+    pub fn set_limits(&self, limits: Limits) {
+        self.settings.borrow_mut().limits = limits;
+    }
+
+    /// Returns the caps on the sizes of values built by operators
+    #[inline]
+    pub fn limits(&self) -> Limits {
+        self.settings.borrow().limits
+    }
+
+    /// Check if the work of the current evaluation is counted
+    #[inline]
+    pub(crate) fn is_execution_limited(&self) -> bool {
+        self.execution.get().is_some()
+    }
+
+    /// Count an operation about to be executed, failing if it exceeds the
+    /// execution limits
+    pub(crate) fn enter_operation(&self) -> Result<()> {
+        if let Some(mut execution) = self.execution.get() {
+            self.settings
+                .borrow()
+                .limits
+                .enter_operation(&mut execution)?;
+            self.execution.set(Some(execution));
+        }
+        Ok(())
+    }
+
+    /// Get the work counted so far by the current evaluation
+    pub(crate) fn execution(&self) -> Option<Execution> {
+        self.execution.get()
+    }
+
+    /// Resume counting the work of an evaluation paused after `execution`
+    pub(crate) fn resume_execution(&self, execution: Option<Execution>) {
+        self.execution.set(execution);
+    }
+
+    /// Mark the end of an operation counted by `enter_operation`
+    pub(crate) fn leave_operation(&self) {
+        if let Some(mut execution) = self.execution.get() {
+            execution.leave_operation();
+            self.execution.set(Some(execution));
+        }
+    }
+
+    /// Count items about to be iterated over, failing if they exceed the
+    /// execution limits
+    #[inline]
+    pub(crate) fn count_iterations(&self, count: usize) -> Result<()> {
+        match self.execution.get() {
+            Some(mut execution) => {
+                self.settings
+                    .borrow()
+                    .limits
+                    .count_iterations(&mut execution, count)?;
+                self.execution.set(Some(execution));
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the id of the compiled rule being evaluated, returning the previous one
+    pub(crate) fn replace_rule_id(&self, rule_id: Option<Arc<str>>) -> Option<Arc<str>> {
+        self.rule_id.replace(rule_id)
+    }
+
+    /// Returns the id of the compiled rule being evaluated, if it has one
+    pub(crate) fn rule_id(&self) -> Option<String> {
+        self.rule_id.borrow().as_deref().map(str::to_string)
+    }
+
+    /// Evaluate a registered custom operator, ignoring active overrides
+    pub fn evaluate_registered_operator<'a>(
+        &'a self,
+        name: &str,
+        args: &'a [DataValue<'a>],
+    ) -> Result<&'a DataValue<'a>> {
+        self.custom_operator_registry().evaluate(name, args, self)
+    }
+
+    /// Store a host object and return a `DataValue::Custom` handle to it
+    ///
+    /// Custom operators use this to pass state that has no JSON
+    /// representation, such as a compiled regex, to other operators of the
+    /// same evaluation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::arena::DataArena;
+    ///
+    /// let arena = DataArena::new();
+    /// let handle = arena.alloc_host_object(vec![1u8, 2, 3]);
+    /// assert_eq!(handle.type_name(), "custom");
+    ///
+    /// let bytes = arena.host_object::<Vec<u8>>(handle).unwrap();
+    /// assert_eq!(bytes.len(), 3);
+    /// assert!(arena.host_object::<String>(handle).is_none());
+    ///
+    /// arena.clear_host_objects();
+    /// assert!(arena.host_object::<Vec<u8>>(handle).is_none());
+    /// ```
+    pub fn alloc_host_object<T: Any + Send + Sync>(&self, object: T) -> &DataValue<'_> {
+        let handle = self.host_objects.borrow_mut().insert(Arc::new(object));
+        self.alloc(DataValue::Custom(handle))
+    }
+
+    /// Get the host object behind a `DataValue::Custom` handle
+    ///
+    /// Returns `None` if the value is not a handle, the handle is stale or
+    /// the object is not of type `T`.
+    pub fn host_object<T: Any + Send + Sync>(&self, value: &DataValue) -> Option<Arc<T>> {
+        let DataValue::Custom(handle) = value else {
+            return None;
+        };
+        let object = self.host_objects.borrow().get(*handle)?;
+        object.downcast().ok()
+    }
+
+    /// Get the host object behind a handle, whatever its type
+    pub(crate) fn shared_host_object(
+        &self,
+        handle: HostHandle,
+    ) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.host_objects.borrow().get(handle)
+    }
+
+    /// Store a host object shared with another arena and return its handle
+    pub(crate) fn alloc_shared_host_object(
+        &self,
+        object: Arc<dyn Any + Send + Sync>,
+    ) -> HostHandle {
+        self.host_objects.borrow_mut().insert(object)
+    }
+
+    /// Drop all host objects, invalidating their handles
+    pub fn clear_host_objects(&self) {
+        self.host_objects.borrow_mut().clear();
+    }
+
+    /// Copy a value allocated in another arena into this one
+    ///
+    /// Only the value and what it refers to are copied, so an evaluation can
+    /// run in a scratch arena that is reset or dropped right after its result
+    /// is copied out, instead of keeping every intermediate value alive as
+    /// long as the result. Object keys are interned, and the host objects of
+    /// `source` the value refers to are shared with this arena under new
+    /// handles. Stale handles are copied as `null`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::arena::DataArena;
+    /// use datalogic_rs::{DataValue, FromJson, ToJson};
+    /// use serde_json::json;
+    ///
+    /// let results = DataArena::new();
+    /// let copied = {
+    ///     let scratch = DataArena::new();
+    ///     let value = DataValue::from_json(&json!({"ids": [1, 2], "ok": true}), &scratch);
+    ///     results.copy_value(&value, &scratch)
+    /// };
+    /// assert_eq!(copied.to_json(), json!({"ids": [1, 2], "ok": true}));
+    /// ```
+    pub fn copy_value<'a>(&'a self, value: &DataValue<'_>, source: &DataArena) -> DataValue<'a> {
+        match value {
+            DataValue::Null => DataValue::Null,
+            DataValue::Bool(b) => DataValue::Bool(*b),
+            DataValue::Number(n) => DataValue::Number(*n),
+            DataValue::String(s) => DataValue::String(self.alloc_str(s)),
+            DataValue::Array(items) => {
+                let items: Vec<DataValue<'a>> = items
+                    .iter()
+                    .map(|item| self.copy_value(item, source))
+                    .collect();
+                DataValue::Array(self.alloc_data_value_slice(&items))
+            }
+            DataValue::Object(entries) => {
+                let entries: Vec<(&'a str, DataValue<'a>)> = entries
+                    .iter()
+                    .map(|(key, item)| (self.intern_str(key), self.copy_value(item, source)))
+                    .collect();
+                DataValue::Object(self.alloc_object_entries(&entries))
+            }
+            DataValue::DateTime(dt) => DataValue::DateTime(*dt),
+            DataValue::Duration(d) => DataValue::Duration(*d),
+            DataValue::Custom(handle) => match source.shared_host_object(*handle) {
+                Some(object) => DataValue::Custom(self.alloc_shared_host_object(object)),
+                None => DataValue::Null,
+            },
+        }
+    }
+
+    /// Install operator overrides, returning the previously active ones
+    pub fn set_operator_overrides(
+        &self,
+        overrides: Option<OperatorOverrides>,
+    ) -> Option<OperatorOverrides> {
+        self.operator_overrides.replace(overrides)
+    }
+
+    /// Check if any operator overrides are active
+    #[inline]
+    pub fn has_operator_overrides(&self) -> bool {
+        self.operator_overrides.borrow().is_some()
+    }
+
+    /// Get the active override of an operator
+    pub fn operator_override(&self, name: &str) -> Option<Arc<dyn CustomOperator>> {
+        self.operator_overrides
+            .borrow()
+            .as_ref()
+            .and_then(|overrides| overrides.get(name))
+    }
+
+    /// Install the regional settings of an evaluation, returning the
+    /// previously active ones
+    pub fn set_evaluation_context(
+        &self,
+        context: Option<EvaluationContext>,
+    ) -> Option<EvaluationContext> {
+        mem::replace(&mut self.settings.borrow_mut().evaluation_context, context)
+    }
+
+    /// Get the regional settings of the current evaluation
+    pub fn evaluation_context(&self) -> Option<EvaluationContext> {
+        self.settings.borrow().evaluation_context.clone()
+    }
+
+    /// Start recording expression costs, discarding any previous profile
+    pub(crate) fn start_profiling(&self) {
+        self.profile.replace(CostProfile::default());
+        self.profiling.set(true);
+    }
+
+    /// Stop recording expression costs and return the profile
+    pub(crate) fn finish_profiling(&self) -> CostProfile {
+        self.profiling.set(false);
+        self.profile.take()
+    }
+
+    /// Check if expression costs are being recorded
+    #[inline]
+    pub(crate) fn is_profiling(&self) -> bool {
+        self.profiling.get()
+    }
+
+    /// Check if the evaluator needs to instrument expression nodes
+    #[inline]
+    pub(crate) fn is_instrumented(&self) -> bool {
+        self.profiling.get()
+            || self.error_tracing.get()
+            || self.coercion_tracing.get()
+            || self.has_logger()
+            || self.is_execution_limited()
+    }
+
+    /// Record one evaluation of an expression
+    pub(crate) fn record_cost(
+        &self,
+        token: &crate::logic::Token,
+        elapsed: std::time::Duration,
+        allocated_bytes: usize,
+    ) {
+        self.profile
+            .borrow_mut()
+            .record(token, elapsed, allocated_bytes);
+    }
+
+    /// Enable or disable recording the site of failures
+    ///
+    /// Enabling discards any previously recorded failure.
+    pub(crate) fn set_error_tracing(&self, enabled: bool) {
+        if enabled {
+            self.failure.replace(None);
         }
+        self.error_tracing.set(enabled);
+    }
+
+    /// Check if the site of failures is being recorded
+    #[inline]
+    pub(crate) fn is_tracing_errors(&self) -> bool {
+        self.error_tracing.get()
+    }
+
+    /// Check if a failure site has been recorded
+    pub(crate) fn has_failure(&self) -> bool {
+        self.failure.borrow().is_some()
+    }
+
+    /// Record or clear the innermost failure site
+    pub(crate) fn set_failure(&self, failure: Option<FailureSite>) {
+        self.failure.replace(failure);
+    }
+
+    /// Take the recorded failure site
+    pub(crate) fn take_failure(&self) -> Option<FailureSite> {
+        self.failure.take()
+    }
+
+    /// Start recording implicit conversions, discarding any previous ones
+    pub(crate) fn start_coercion_tracing(&self) {
+        self.coercions.replace(Vec::new());
+        self.current_node.set(0);
+        self.coercion_tracing.set(true);
+    }
+
+    /// Stop recording implicit conversions and return the recorded ones
+    pub(crate) fn finish_coercion_tracing(&self) -> Vec<CoercionSite> {
+        self.coercion_tracing.set(false);
+        self.coercions.take()
+    }
+
+    /// Check if implicit conversions are being recorded
+    #[inline]
+    pub(crate) fn is_tracing_coercions(&self) -> bool {
+        self.coercion_tracing.get()
+    }
+
+    /// Mark a token as the innermost node being evaluated, returning the
+    /// previous one
+    pub(crate) fn enter_node(&self, token: &crate::logic::Token) -> usize {
+        self.current_node.replace(token_key(token))
+    }
+
+    /// Restore the innermost node being evaluated
+    pub(crate) fn leave_node(&self, previous: usize) {
+        self.current_node.set(previous);
+    }
+
+    /// Set or remove the callback receiving the values reported by `log`
+    pub(crate) fn set_logger(&self, logger: Option<Logger>) {
+        self.settings.borrow_mut().logger = logger;
+    }
+
+    /// Get the callback receiving the values reported by `log`
+    #[inline]
+    pub(crate) fn logger(&self) -> Option<Logger> {
+        self.settings.borrow().logger.clone()
+    }
+
+    /// Check if a callback receives the values reported by `log`
+    #[inline]
+    pub(crate) fn has_logger(&self) -> bool {
+        self.settings.borrow().logger.is_some()
+    }
+
+    /// Push a node whose evaluation starts while a logger is set
+    pub(crate) fn push_node<'a>(&self, token: &'a Token<'a>) {
+        // SAFETY: Widening the lifetime is safe because the node is popped
+        // before its evaluation returns
+        let static_token =
+            unsafe { mem::transmute::<&'a Token<'a>, &'static Token<'static>>(token) };
+        self.nodes.borrow_mut().push(static_token);
+    }
+
+    /// Pop the node whose evaluation ended
+    pub(crate) fn pop_node(&self) {
+        self.nodes.borrow_mut().pop();
+    }
+
+    /// Get the nodes being evaluated, outermost first
+    pub(crate) fn evaluation_nodes<'a>(&'a self) -> Vec<&'a Token<'a>> {
+        self.nodes.borrow().clone()
+    }
+
+    /// Start a `do` block, whose assignments are dropped when it ends
+    pub(crate) fn enter_block(&self) {
+        let start = self.bindings.borrow().len();
+        self.blocks.borrow_mut().push(start);
+    }
+
+    /// End the innermost `do` block, dropping its assignments
+    pub(crate) fn leave_block(&self) {
+        if let Some(start) = self.blocks.borrow_mut().pop() {
+            self.bindings.borrow_mut().truncate(start);
+        }
+    }
+
+    /// Assign a value to a name in the innermost `do` block
+    ///
+    /// Returns false outside of a `do` block.
+    pub(crate) fn assign<'a>(&self, name: &'a str, value: &'a DataValue<'a>) -> bool {
+        if self.blocks.borrow().is_empty() {
+            return false;
+        }
+        // SAFETY: Widening the lifetimes is safe because the binding is
+        // dropped when its block ends, before the evaluation returns
+        let binding = unsafe {
+            (
+                mem::transmute::<&'a str, &'static str>(name),
+                mem::transmute::<&'a DataValue<'a>, &'static DataValue<'static>>(value),
+            )
+        };
+        self.bindings.borrow_mut().push(binding);
+        true
+    }
+
+    /// Get the latest value assigned to a name in the enclosing `do` blocks
+    #[inline]
+    pub(crate) fn binding(&self, name: &str) -> Option<&DataValue<'_>> {
+        let bindings = self.bindings.borrow();
+        if bindings.is_empty() {
+            return None;
+        }
+        bindings
+            .iter()
+            .rev()
+            .find(|(bound, _)| *bound == name)
+            .map(|&(_, value)| self.transmute_lifetime(value))
+    }
+
+    /// Record an implicit conversion of an operand to the given type
+    ///
+    /// Values that already have the target type are ignored.
+    #[inline]
+    pub(crate) fn record_coercion(&self, value: &DataValue, to: &'static str) {
+        if !self.coercion_tracing.get() || value.type_name() == to {
+            return;
+        }
+        self.coercions.borrow_mut().push(CoercionSite {
+            token: self.current_node.get(),
+            from: value.type_name(),
+            to,
+            value: value.to_json(),
+        });
     }
 }
 
@@ -1005,4 +1835,37 @@ mod tests {
         let retrieved_current = arena.current_context(0).unwrap();
         assert!(matches!(retrieved_current, DataValue::Object(_)));
     }
+
+    #[test]
+    fn test_copy_value() {
+        let target = DataArena::new();
+        let (copied, stale) = {
+            let source = DataArena::new();
+            let handle = source.alloc_host_object(String::from("session"));
+            let items = [DataValue::string(&source, "a"), handle.clone()];
+            let value = DataValue::object(
+                &source,
+                &[(
+                    source.intern_str("items"),
+                    DataValue::array(&source, &items),
+                )],
+            );
+            let copied = target.copy_value(&value, &source);
+            let stale = source.alloc_host_object(1u8).clone();
+            source.clear_host_objects();
+            (copied, target.copy_value(&stale, &source))
+        };
+
+        let items = copied.get("items").and_then(DataValue::as_array).unwrap();
+        assert_eq!(items[0].as_str(), Some("a"));
+        assert_eq!(
+            target.host_object::<String>(&items[1]).as_deref(),
+            Some(&"session".to_string())
+        );
+        assert!(stale.is_null());
+
+        // Copying within an arena shares its host objects
+        let copy = target.copy_value(&items[1], &target);
+        assert!(target.host_object::<String>(&copy).is_some());
+    }
 }
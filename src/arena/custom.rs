@@ -1,9 +1,14 @@
+use super::global::global_operator;
+use super::memo::{CacheStats, MemoizeConfig, OperatorCache};
+use super::timeout::{OperatorTimeout, TimeoutConfig};
+use super::version::{resolve_version, split_versioned_name, versioned_key, OperatorMetadata};
 use crate::arena::DataArena;
 use crate::logic::Result;
 use crate::value::DataValue;
 use crate::LogicError;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
 /// Trait for custom JSONLogic operators
 pub trait CustomOperator: fmt::Debug + Send + Sync {
@@ -18,28 +23,227 @@ pub trait CustomOperator: fmt::Debug + Send + Sync {
     ) -> Result<&'a DataValue<'a>>;
 }
 
+/// The id of an operator in a [`CustomOperatorRegistry`]
+///
+/// Ids are dense indices assigned in registration order. Replacing an
+/// operator keeps its id, so an id stays valid for the lifetime of the
+/// registry it was obtained from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OperatorId(usize);
+
+/// A registered operator with its memoization cache and timeout, if any
+struct RegisteredOperator {
+    operator: Arc<dyn CustomOperator>,
+    cache: Option<OperatorCache>,
+    timeout: Option<OperatorTimeout>,
+}
+
 /// Registry for custom operator functions
+///
+/// Operator names are interned into a symbol table of [`OperatorId`]s, so a
+/// call resolves its operator and cache with a single lookup.
 #[derive(Default)]
 pub struct CustomOperatorRegistry {
-    operators: HashMap<String, Box<dyn CustomOperator>>,
+    symbols: HashMap<String, OperatorId>,
+    operators: Vec<RegisteredOperator>,
+    versions: HashMap<String, Vec<OperatorMetadata>>,
 }
 
 impl CustomOperatorRegistry {
     /// Creates a new empty custom operator registry
     pub fn new() -> Self {
         Self {
-            operators: HashMap::new(),
+            symbols: HashMap::new(),
+            operators: Vec::new(),
+            versions: HashMap::new(),
         }
     }
 
     /// Registers a custom operator function
+    ///
+    /// Any memoization or timeout previously configured for this name is
+    /// removed.
     pub fn register(&mut self, name: &str, operator: Box<dyn CustomOperator>) {
-        self.operators.insert(name.to_string(), operator);
+        let registered = RegisteredOperator {
+            operator: Arc::from(operator),
+            cache: None,
+            timeout: None,
+        };
+        match self.symbols.get(name) {
+            Some(&OperatorId(index)) => self.operators[index] = registered,
+            None => {
+                let id = OperatorId(self.operators.len());
+                self.operators.push(registered);
+                self.symbols.insert(name.to_string(), id);
+            }
+        }
+    }
+
+    /// Registers a custom operator whose results are memoized across evaluations
+    pub fn register_memoized(
+        &mut self,
+        name: &str,
+        operator: Box<dyn CustomOperator>,
+        config: MemoizeConfig,
+    ) {
+        self.register(name, operator);
+        let OperatorId(index) = self.symbols[name];
+        self.operators[index].cache = Some(OperatorCache::new(config));
+    }
+
+    /// Sets the timeout of the calls of a registered operator
+    ///
+    /// Fails with [`LogicError::OperatorNotFoundError`] if no operator is
+    /// registered under the name.
+    pub fn set_timeout(&mut self, name: &str, config: TimeoutConfig) -> Result<()> {
+        let Some(&OperatorId(index)) = self.symbols.get(name) else {
+            return Err(LogicError::OperatorNotFoundError {
+                operator: name.to_string(),
+            });
+        };
+        self.operators[index].timeout = Some(OperatorTimeout::new(name, config));
+        Ok(())
+    }
+
+    /// Returns the timeout of a registered operator, if it has one
+    pub fn timeout(&self, name: &str) -> Option<&TimeoutConfig> {
+        let id = self.symbol(name)?;
+        self.registered(id)
+            .timeout
+            .as_ref()
+            .map(OperatorTimeout::config)
+    }
+
+    /// Registers a version of a namespaced custom operator
+    ///
+    /// The operator is stored under `name@version`; rules refer to it by its
+    /// base name, optionally with a version requirement on the namespace.
+    pub fn register_versioned(
+        &mut self,
+        name: &str,
+        metadata: OperatorMetadata,
+        operator: Box<dyn CustomOperator>,
+    ) {
+        self.register(&versioned_key(name, metadata.version()), operator);
+        let versions = self.versions.entry(name.to_string()).or_default();
+        versions.retain(|existing| existing.version() != metadata.version());
+        versions.push(metadata);
+    }
+
+    /// Resolves a possibly versioned operator name to a registered version
+    ///
+    /// Returns the name the resolved version is registered under and its
+    /// metadata, or `None` if no versions are registered for the base name.
+    pub fn resolve_versioned(&self, name: &str) -> Result<Option<(String, &OperatorMetadata)>> {
+        let (base, req) = split_versioned_name(name);
+        let Some(available) = self.versions.get(&base) else {
+            return Ok(None);
+        };
+
+        let metadata = resolve_version(&base, req, available)?;
+        Ok(Some((versioned_key(&base, metadata.version()), metadata)))
+    }
+
+    /// Copies the version metadata of another registry, without its operators
+    ///
+    /// This lets rules compiled against this registry resolve versioned names
+    /// the same way as against the other one.
+    pub(crate) fn copy_versions_from(&mut self, other: &Self) {
+        self.versions.clone_from(&other.versions);
+    }
+
+    /// Returns the names of the registered operators, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.symbols.keys().map(String::as_str)
+    }
+
+    /// Returns the id of a registered operator
+    pub fn symbol(&self, name: &str) -> Option<OperatorId> {
+        self.symbols.get(name).copied()
+    }
+
+    /// Returns the registered operator with an id
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id was obtained from another registry with fewer operators.
+    fn registered(&self, OperatorId(index): OperatorId) -> &RegisteredOperator {
+        &self.operators[index]
     }
 
     /// Returns a reference to a custom operator by name
     pub fn get(&self, name: &str) -> Option<&dyn CustomOperator> {
-        self.operators.get(name).map(|op| op.as_ref())
+        let id = self.symbol(name)?;
+        Some(self.registered(id).operator.as_ref())
+    }
+
+    /// Evaluates a custom operator by name, using its cache if it is memoized
+    pub fn evaluate<'a>(
+        &self,
+        name: &str,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        match self.symbol(name) {
+            Some(id) => self.evaluate_symbol(id, args, arena),
+            // Fall back to the process-wide registry
+            None => match global_operator(name) {
+                Some(operator) => operator.evaluate(args, arena),
+                None => Err(LogicError::OperatorNotFoundError {
+                    operator: name.to_string(),
+                }),
+            },
+        }
+    }
+
+    /// Evaluates a custom operator by id, using its cache if it is memoized
+    /// and within its timeout if it has one
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id was obtained from another registry with fewer operators.
+    pub fn evaluate_symbol<'a>(
+        &self,
+        id: OperatorId,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let registered = self.registered(id);
+        let timed;
+        let operator: &dyn CustomOperator = match &registered.timeout {
+            Some(timeout) => {
+                timed = timeout.wrap(&registered.operator);
+                &timed
+            }
+            None => registered.operator.as_ref(),
+        };
+
+        let result = match &registered.cache {
+            Some(cache) => cache.evaluate(operator, args, arena),
+            None => operator.evaluate(args, arena),
+        };
+        match &registered.timeout {
+            Some(timeout) => timeout.apply_fallback(result, arena),
+            None => result,
+        }
+    }
+
+    /// Returns the memoization cache of an operator, if it is memoized
+    fn cache(&self, name: &str) -> Option<&OperatorCache> {
+        let id = self.symbol(name)?;
+        self.registered(id).cache.as_ref()
+    }
+
+    /// Returns the cache statistics of a memoized operator
+    pub fn cache_stats(&self, name: &str) -> Option<CacheStats> {
+        self.cache(name).map(|cache| cache.stats())
+    }
+
+    /// Clears the cached results of a memoized operator
+    pub fn clear_cache(&self, name: &str) {
+        if let Some(cache) = self.cache(name) {
+            cache.clear();
+        }
     }
 }
 
@@ -108,6 +312,8 @@ impl CustomOperator for SimpleOperatorAdapter {
                     // Handle DateTime and Duration types
                     DataValue::DateTime(dt) => Ok(arena.alloc(DataValue::DateTime(dt))),
                     DataValue::Duration(dur) => Ok(arena.alloc(DataValue::Duration(dur))),
+                    // Host handles refer to the arena's registry and are kept as-is
+                    DataValue::Custom(handle) => Ok(arena.alloc(DataValue::Custom(handle))),
                 }
             }
             Err(msg) => Err(LogicError::ParseError {
@@ -116,3 +322,118 @@ impl CustomOperator for SimpleOperatorAdapter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_symbols() {
+        let mut registry = CustomOperatorRegistry::new();
+        registry.register(
+            "one",
+            Box::new(SimpleOperatorAdapter::new("one", |_, _| {
+                Ok(DataValue::integer(1))
+            })),
+        );
+        registry.register_memoized(
+            "two",
+            Box::new(SimpleOperatorAdapter::new("two", |_, _| {
+                Ok(DataValue::integer(2))
+            })),
+            MemoizeConfig::new(8),
+        );
+
+        let one = registry.symbol("one").unwrap();
+        let two = registry.symbol("two").unwrap();
+        assert_ne!(one, two);
+        assert_eq!(registry.symbol("three"), None);
+
+        let arena = DataArena::new();
+        let result = registry.evaluate_symbol(two, &[], &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(2));
+        assert_eq!(registry.cache_stats("two").unwrap().misses, 1);
+        assert!(registry.cache_stats("one").is_none());
+
+        // Replacing an operator keeps its id and drops its cache
+        registry.register(
+            "two",
+            Box::new(SimpleOperatorAdapter::new("two", |_, _| {
+                Ok(DataValue::integer(22))
+            })),
+        );
+        assert_eq!(registry.symbol("two"), Some(two));
+        assert!(registry.cache_stats("two").is_none());
+        let result = registry.evaluate("two", &[], &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(22));
+
+        let mut names: Vec<_> = registry.names().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["one", "two"]);
+    }
+
+    #[test]
+    fn test_operator_timeouts() {
+        use std::time::Duration;
+
+        let mut registry = CustomOperatorRegistry::new();
+        registry.register_memoized(
+            "slow",
+            Box::new(SimpleOperatorAdapter::new("slow", |args, _| {
+                let millis = args.first().and_then(DataValue::as_i64).unwrap_or(0);
+                std::thread::sleep(Duration::from_millis(millis as u64));
+                Ok(DataValue::integer(millis))
+            })),
+            MemoizeConfig::new(8),
+        );
+        registry.register(
+            "panics",
+            Box::new(SimpleOperatorAdapter::new("panics", |_, _| {
+                panic!("lookup failed")
+            })),
+        );
+        assert_eq!(
+            registry.set_timeout("missing", TimeoutConfig::new(Duration::ZERO)),
+            Err(LogicError::OperatorNotFoundError {
+                operator: "missing".to_string()
+            })
+        );
+
+        let config =
+            TimeoutConfig::new(Duration::from_millis(50)).with_fallback(serde_json::json!(-1));
+        registry.set_timeout("slow", config.clone()).unwrap();
+        // Long enough for the panic to always come before the timeout
+        let patient = TimeoutConfig::new(Duration::from_secs(30));
+        registry.set_timeout("panics", patient).unwrap();
+        assert_eq!(registry.timeout("slow"), Some(&config));
+
+        let arena = DataArena::new();
+        let fast = arena.vec_into_slice(vec![DataValue::integer(0)]);
+        let result = registry.evaluate("slow", fast, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(0));
+
+        // Fallback values are not memoized
+        let slow = arena.vec_into_slice(vec![DataValue::integer(2000)]);
+        for _ in 0..2 {
+            let result = registry.evaluate("slow", slow, &arena).unwrap();
+            assert_eq!(result.as_i64(), Some(-1));
+        }
+        let stats = registry.cache_stats("slow").unwrap();
+        assert_eq!((stats.misses, stats.size), (3, 1));
+
+        // Panics are raised on the evaluating thread
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            registry.evaluate("panics", &[], &arena)
+        }));
+        assert!(panic.is_err());
+
+        // Registering an operator again removes its timeout
+        registry.register(
+            "slow",
+            Box::new(SimpleOperatorAdapter::new("slow", |_, _| {
+                Ok(DataValue::Null)
+            })),
+        );
+        assert_eq!(registry.timeout("slow"), None);
+    }
+}
@@ -0,0 +1,105 @@
+//! Process-wide custom operator registry
+//!
+//! Operators registered here are available to every DataLogic instance, so
+//! crates composing an application can contribute operators at startup
+//! without access to the engine. Operators registered on an instance take
+//! precedence over global ones of the same name.
+//!
+//! Every registration change bumps a process-wide generation, so compiled
+//! rules using a replaced operator can be detected as stale.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+
+use super::custom::{CustomOperator, SimpleOperatorAdapter, SimpleOperatorFn};
+
+lazy_static! {
+    static ref GLOBAL_OPERATORS: RwLock<HashMap<String, Arc<dyn CustomOperator>>> =
+        RwLock::new(HashMap::new());
+
+    /// The generation at which each global operator last changed
+    static ref GLOBAL_OPERATOR_GENERATIONS: RwLock<HashMap<String, u64>> =
+        RwLock::new(HashMap::new());
+}
+
+/// The number of global registration changes so far
+static GLOBAL_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Records a change of a global operator.
+fn bump_generation(name: &str) {
+    let generation = GLOBAL_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    GLOBAL_OPERATOR_GENERATIONS
+        .write()
+        .unwrap()
+        .insert(name.to_string(), generation);
+}
+
+/// Register a custom operator for all DataLogic instances
+///
+/// Replaces any global operator previously registered under the same name.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::{register_global_simple_operator, DataLogic, DataValue};
+/// use serde_json::json;
+///
+/// register_global_simple_operator("vat_rate", |_, _| Ok(DataValue::float(0.2)));
+///
+/// let dl = DataLogic::new();
+/// let result = dl.evaluate_json(&json!({"vat_rate": []}), &json!({}), None).unwrap();
+/// assert_eq!(result, json!(0.2));
+/// ```
+pub fn register_global_operator(name: &str, operator: Box<dyn CustomOperator>) {
+    GLOBAL_OPERATORS
+        .write()
+        .unwrap()
+        .insert(name.to_string(), Arc::from(operator));
+    bump_generation(name);
+}
+
+/// Register a simple function as a custom operator for all DataLogic instances
+pub fn register_global_simple_operator(name: &str, function: SimpleOperatorFn) {
+    register_global_operator(name, Box::new(SimpleOperatorAdapter::new(name, function)));
+}
+
+/// Remove a global custom operator, returning true if it was registered
+pub fn unregister_global_operator(name: &str) -> bool {
+    let removed = GLOBAL_OPERATORS.write().unwrap().remove(name).is_some();
+    if removed {
+        bump_generation(name);
+    }
+    removed
+}
+
+/// Check if a global custom operator is registered
+pub fn has_global_operator(name: &str) -> bool {
+    GLOBAL_OPERATORS.read().unwrap().contains_key(name)
+}
+
+/// Returns the names of the global custom operators, in no particular order
+pub(crate) fn global_operator_names() -> Vec<String> {
+    GLOBAL_OPERATORS.read().unwrap().keys().cloned().collect()
+}
+
+/// Get a global custom operator by name
+pub(crate) fn global_operator(name: &str) -> Option<Arc<dyn CustomOperator>> {
+    GLOBAL_OPERATORS.read().unwrap().get(name).cloned()
+}
+
+/// Returns the number of global registration changes so far
+pub(crate) fn global_generation() -> u64 {
+    GLOBAL_GENERATION.load(Ordering::SeqCst)
+}
+
+/// Returns the generation at which a global operator last changed
+pub(crate) fn global_operator_generation(name: &str) -> Option<u64> {
+    GLOBAL_OPERATOR_GENERATIONS
+        .read()
+        .unwrap()
+        .get(name)
+        .copied()
+}
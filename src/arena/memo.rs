@@ -0,0 +1,287 @@
+//! Memoization for custom operator results.
+//!
+//! This module provides a bounded LRU cache with optional time-to-live that can
+//! be attached to individual custom operators. Cached results outlive a single
+//! evaluation, so they are stored as owned JSON values and re-allocated in the
+//! arena on every hit.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value as JsonValue;
+
+use super::custom::CustomOperator;
+use crate::arena::DataArena;
+use crate::logic::Result;
+use crate::value::{DataValue, FromJson, ToJson};
+
+/// Configuration for memoizing a custom operator.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::MemoizeConfig;
+/// use std::time::Duration;
+///
+/// let config = MemoizeConfig::new(1024).with_ttl(Duration::from_secs(60));
+/// assert_eq!(config.capacity(), 1024);
+/// assert_eq!(config.ttl(), Some(Duration::from_secs(60)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoizeConfig {
+    capacity: usize,
+    ttl: Option<Duration>,
+}
+
+impl MemoizeConfig {
+    /// Creates a configuration holding at most `capacity` results.
+    ///
+    /// A capacity of zero disables caching while still collecting statistics.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ttl: None,
+        }
+    }
+
+    /// Sets the time after which a cached result is considered stale.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the maximum number of cached results.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the time-to-live of cached results, if any.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+}
+
+/// Statistics collected by a memoized custom operator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of evaluations answered from the cache.
+    pub hits: u64,
+    /// Number of evaluations that invoked the operator.
+    pub misses: u64,
+    /// Number of entries removed to respect the capacity.
+    pub evictions: u64,
+    /// Number of entries discarded because their TTL elapsed.
+    pub expirations: u64,
+    /// Number of entries currently cached.
+    pub size: usize,
+}
+
+/// A single cached result.
+struct CacheEntry {
+    value: JsonValue,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+/// Mutable state of an operator cache.
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    /// Maps the last-used tick of each entry to its key, oldest first.
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl CacheState {
+    /// Marks an entry as most recently used.
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = tick;
+            self.recency.insert(tick, key.to_string());
+        }
+    }
+
+    /// Removes an entry from the cache.
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+
+    /// Inserts a result, evicting the least recently used entries if needed.
+    fn insert(&mut self, key: String, value: JsonValue, capacity: usize) {
+        self.remove(&key);
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+
+        self.tick += 1;
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                inserted_at: Instant::now(),
+                last_used: self.tick,
+            },
+        );
+    }
+}
+
+/// A bounded LRU cache of results for a single custom operator.
+pub(crate) struct OperatorCache {
+    config: MemoizeConfig,
+    state: Mutex<CacheState>,
+}
+
+impl OperatorCache {
+    /// Creates an empty cache with the given configuration.
+    pub(crate) fn new(config: MemoizeConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Evaluates the operator, consulting the cache first.
+    ///
+    /// Results are keyed by the serialized arguments, so memoized operators
+    /// must not depend on the data context.
+    pub(crate) fn evaluate<'a>(
+        &self,
+        operator: &dyn CustomOperator,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let key = DataValue::Array(args).to_json().to_string();
+
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let cached = state.entries.get(&key).map(|entry| {
+                let expired = self
+                    .config
+                    .ttl
+                    .is_some_and(|ttl| entry.inserted_at.elapsed() >= ttl);
+                (expired, entry.value.clone())
+            });
+
+            match cached {
+                Some((false, value)) => {
+                    state.touch(&key);
+                    state.stats.hits += 1;
+                    return Ok(arena.alloc(DataValue::from_json(&value, arena)));
+                }
+                Some((true, _)) => {
+                    state.remove(&key);
+                    state.stats.expirations += 1;
+                }
+                None => {}
+            }
+            state.stats.misses += 1;
+        }
+
+        // The lock is released while the operator runs so that it may
+        // evaluate other memoized operators.
+        let result = operator.evaluate(args, arena)?;
+
+        if self.config.capacity > 0 {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.insert(key, result.to_json(), self.config.capacity);
+        }
+
+        Ok(result)
+    }
+
+    /// Returns a snapshot of the cache statistics.
+    pub(crate) fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        CacheStats {
+            size: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    /// Removes all cached results and resets the statistics.
+    pub(crate) fn clear(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = CacheState::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct CountingDouble(Arc<AtomicUsize>);
+
+    impl CustomOperator for CountingDouble {
+        fn evaluate<'a>(
+            &self,
+            args: &'a [DataValue<'a>],
+            arena: &'a DataArena,
+        ) -> Result<&'a DataValue<'a>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let n = args.first().and_then(|v| v.as_i64()).unwrap_or(0);
+            Ok(arena.alloc(DataValue::integer(n * 2)))
+        }
+    }
+
+    #[test]
+    fn test_cache_hits_and_eviction() {
+        let arena = DataArena::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let op = CountingDouble(calls.clone());
+        let cache = OperatorCache::new(MemoizeConfig::new(2));
+
+        let one = [DataValue::integer(1)];
+        let two = [DataValue::integer(2)];
+        let three = [DataValue::integer(3)];
+
+        assert_eq!(cache.evaluate(&op, &one, &arena).unwrap().as_i64(), Some(2));
+        assert_eq!(cache.evaluate(&op, &one, &arena).unwrap().as_i64(), Some(2));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.evaluate(&op, &two, &arena).unwrap();
+        // Touch "1" so that "2" becomes the least recently used entry
+        cache.evaluate(&op, &one, &arena).unwrap();
+        cache.evaluate(&op, &three, &arena).unwrap();
+        cache.evaluate(&op, &one, &arena).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 3);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.size, 2);
+
+        cache.clear();
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let arena = DataArena::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let op = CountingDouble(calls.clone());
+        let cache = OperatorCache::new(MemoizeConfig::new(8).with_ttl(Duration::ZERO));
+
+        let args = [DataValue::integer(5)];
+        cache.evaluate(&op, &args, &arena).unwrap();
+        cache.evaluate(&op, &args, &arena).unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().expirations, 1);
+    }
+}
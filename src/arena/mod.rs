@@ -4,15 +4,46 @@
 //! significantly improving performance by reducing allocation overhead
 //! and improving memory locality.
 
+mod builder;
 mod bump;
 mod custom;
+mod global;
 mod interner;
+mod memo;
+mod profile;
+mod timeout;
+mod version;
 
 // Re-export the main types
 pub use bump::DataArena;
+pub(crate) use bump::{panic_message, IterationPosition, Settings};
+
+// Rope builders of the results of concatenating operators
+pub(crate) use builder::{ArrayBuilder, StringBuilder};
 
 // Re-export the simplified operator types from custom_operator
-pub use custom::{CustomOperator, CustomOperatorRegistry, SimpleOperatorAdapter, SimpleOperatorFn};
+pub use custom::{
+    CustomOperator, CustomOperatorRegistry, OperatorId, SimpleOperatorAdapter, SimpleOperatorFn,
+};
+
+// Re-export the process-wide operator registry functions
+pub(crate) use global::{global_generation, global_operator_generation, global_operator_names};
+pub use global::{
+    has_global_operator, register_global_operator, register_global_simple_operator,
+    unregister_global_operator,
+};
+
+// Re-export the memoization types for custom operators
+pub use memo::{CacheStats, MemoizeConfig};
+
+// Re-export the timeout configuration of custom operators
+pub use timeout::TimeoutConfig;
+
+// Instrumentation used by the explain and diagnostics facilities
+pub(crate) use profile::{token_key, CostProfile, FailureSite};
+
+// Re-export the versioned custom operator types
+pub use version::{OperatorMetadata, OperatorVersion};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,80 @@
+//! Per-expression instrumentation.
+//!
+//! While profiling is enabled on an arena, the evaluator records the wall time
+//! and arena allocations of every operator node it evaluates. Costs are keyed
+//! by the address of the token, so a node evaluated repeatedly, for example
+//! inside `map`, accumulates all of its calls.
+//!
+//! While error tracing is enabled, the evaluator records the innermost node
+//! whose failure is propagating, together with its argument values.
+//!
+//! While coercion tracing is enabled, operators record every implicit
+//! conversion of an operand, keyed by the innermost node being evaluated.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde_json::Value as JsonValue;
+
+use crate::logic::Token;
+
+/// Identifies a token by its address.
+pub(crate) fn token_key(token: &Token) -> usize {
+    token as *const Token as usize
+}
+
+/// Accumulated cost of one expression node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ExpressionCost {
+    /// Number of times the node was evaluated
+    pub(crate) calls: usize,
+    /// Total wall time, including the evaluation of its arguments
+    pub(crate) elapsed: Duration,
+    /// Total arena bytes allocated, including by its arguments
+    pub(crate) allocated_bytes: usize,
+}
+
+/// Costs recorded during a profiled evaluation.
+#[derive(Debug, Default)]
+pub(crate) struct CostProfile {
+    costs: HashMap<usize, ExpressionCost>,
+}
+
+impl CostProfile {
+    /// Adds one evaluation of a token.
+    pub(crate) fn record(&mut self, token: &Token, elapsed: Duration, allocated_bytes: usize) {
+        let cost = self.costs.entry(token_key(token)).or_default();
+        cost.calls += 1;
+        cost.elapsed += elapsed;
+        cost.allocated_bytes += allocated_bytes;
+    }
+
+    /// Returns the accumulated cost of a token, if it was evaluated.
+    pub(crate) fn cost(&self, token: &Token) -> Option<ExpressionCost> {
+        self.costs.get(&token_key(token)).copied()
+    }
+}
+
+/// The innermost expression whose failure is propagating.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FailureSite {
+    /// The address of the failing token
+    pub(crate) token: usize,
+    /// The name of the failing operator, if the token is an operator
+    pub(crate) operator: Option<String>,
+    /// The values of the arguments of the failing token
+    pub(crate) arguments: Vec<JsonValue>,
+}
+
+/// An implicit conversion of an operand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CoercionSite {
+    /// The address of the token performing the conversion
+    pub(crate) token: usize,
+    /// The type of the converted value
+    pub(crate) from: &'static str,
+    /// The type the value was converted to
+    pub(crate) to: &'static str,
+    /// The converted value
+    pub(crate) value: JsonValue,
+}
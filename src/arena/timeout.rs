@@ -0,0 +1,407 @@
+//! Timeouts for custom operator calls.
+//!
+//! A custom operator backed by an external service can stall an evaluation
+//! for as long as the service takes to answer, and the execution limits of an
+//! evaluation are only checked between operations. An operator with a
+//! timeout is called on a worker thread, in an arena of its own, and the
+//! evaluation waits for its answer no longer than the timeout. It then goes
+//! on with a fallback value, or fails with [`LogicError::OperatorTimedOut`].
+//!
+//! Calls are not cancelled: a call that times out runs to completion in the
+//! background and its answer is discarded. Each operator has a bounded pool
+//! of workers, so stalled calls cannot pile up threads: once every worker is
+//! busy, further calls time out at once. Arguments, the data context and
+//! results cross threads as owned JSON values.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value as JsonValue;
+
+use super::custom::CustomOperator;
+use crate::arena::DataArena;
+use crate::logic::{LogicError, Result};
+use crate::value::{DataValue, FromJson, ToJson};
+
+/// Configuration of the timeout of a custom operator.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::TimeoutConfig;
+/// use serde_json::json;
+/// use std::time::Duration;
+///
+/// let config = TimeoutConfig::new(Duration::from_millis(20)).with_fallback(json!(null));
+/// assert_eq!(config.timeout(), Duration::from_millis(20));
+/// assert_eq!(config.fallback(), Some(&json!(null)));
+/// assert_eq!(config.workers(), 8);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeoutConfig {
+    timeout: Duration,
+    fallback: Option<JsonValue>,
+    workers: usize,
+}
+
+impl TimeoutConfig {
+    /// The default number of calls of an operator running at the same time
+    const DEFAULT_WORKERS: usize = 8;
+
+    /// Creates a configuration failing calls that take longer than `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            fallback: None,
+            workers: Self::DEFAULT_WORKERS,
+        }
+    }
+
+    /// Sets how many calls of the operator may run at the same time, at
+    /// least one.
+    ///
+    /// Calls that time out keep their worker until they complete. A call
+    /// made while every worker is busy times out without waiting.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Sets the value of calls that time out, instead of an error.
+    pub fn with_fallback(mut self, fallback: JsonValue) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Returns the longest time a call is waited for.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the value of calls that time out, if any.
+    pub fn fallback(&self) -> Option<&JsonValue> {
+        self.fallback.as_ref()
+    }
+
+    /// Returns how many calls of the operator may run at the same time.
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+}
+
+/// A call made on a worker thread
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The worker threads of an operator, started as calls need them.
+///
+/// Workers exit once the pool is dropped and their current call completes.
+#[derive(Debug)]
+struct WorkerPool {
+    size: usize,
+    /// The calls running or about to run, never more than `size`
+    busy: Arc<AtomicUsize>,
+    /// The number of workers started, with the queue feeding them
+    workers: Mutex<(usize, Sender<Job>)>,
+    jobs: Arc<Mutex<Receiver<Job>>>,
+}
+
+impl WorkerPool {
+    fn new(size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            size,
+            busy: Arc::new(AtomicUsize::new(0)),
+            workers: Mutex::new((0, sender)),
+            jobs: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    /// Runs `job` on a free worker.
+    ///
+    /// Returns `Ok(false)` without running it if every worker is busy.
+    fn try_run(&self, job: impl FnOnce() + Send + 'static) -> std::io::Result<bool> {
+        let size = self.size;
+        let reserved = self
+            .busy
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |busy| {
+                (busy < size).then_some(busy + 1)
+            });
+        if reserved.is_err() {
+            return Ok(false);
+        }
+
+        let busy = Arc::clone(&self.busy);
+        let job: Job = Box::new(move || {
+            job();
+            busy.fetch_sub(1, Ordering::AcqRel);
+        });
+        let mut workers = self.workers.lock().unwrap_or_else(|e| e.into_inner());
+        // Every reserved call has a worker, so calls never wait in the queue
+        if workers.0 < self.busy.load(Ordering::Acquire) {
+            let jobs = Arc::clone(&self.jobs);
+            let spawned = thread::Builder::new().spawn(move || loop {
+                let job = jobs.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                match job {
+                    Ok(job) => job(),
+                    Err(_) => break,
+                }
+            });
+            if let Err(error) = spawned {
+                self.busy.fetch_sub(1, Ordering::AcqRel);
+                return Err(error);
+            }
+            workers.0 += 1;
+        }
+        // The receiver lives as long as the pool, so sending cannot fail
+        let _ = workers.1.send(job);
+        Ok(true)
+    }
+}
+
+/// The timeout of a single registered custom operator.
+#[derive(Debug)]
+pub(crate) struct OperatorTimeout {
+    name: String,
+    config: TimeoutConfig,
+    pool: WorkerPool,
+}
+
+impl OperatorTimeout {
+    /// Creates the timeout of the operator registered under `name`.
+    pub(crate) fn new(name: &str, config: TimeoutConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            pool: WorkerPool::new(config.workers),
+            config,
+        }
+    }
+
+    /// Returns the error of a call that did not answer in time.
+    fn timed_out(&self) -> LogicError {
+        LogicError::OperatorTimedOut {
+            operator: self.name.clone(),
+            timeout: self.config.timeout,
+        }
+    }
+
+    /// Returns the configuration of the timeout.
+    pub(crate) fn config(&self) -> &TimeoutConfig {
+        &self.config
+    }
+
+    /// Wraps an operator so that its calls fail once they time out.
+    ///
+    /// The fallback value is not applied here, so that a memoization cache
+    /// around the wrapped operator never stores it.
+    pub(crate) fn wrap<'r>(&'r self, operator: &'r Arc<dyn CustomOperator>) -> TimedOperator<'r> {
+        TimedOperator {
+            operator,
+            timeout: self,
+        }
+    }
+
+    /// Replaces a timed out result with the fallback value, if any.
+    pub(crate) fn apply_fallback<'a>(
+        &self,
+        result: Result<&'a DataValue<'a>>,
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        match (result, &self.config.fallback) {
+            (Err(LogicError::OperatorTimedOut { .. }), Some(fallback)) => {
+                Ok(arena.alloc(DataValue::from_json(fallback, arena)))
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+/// A custom operator whose calls are made on a worker thread and waited for
+/// no longer than a timeout.
+#[derive(Debug)]
+pub(crate) struct TimedOperator<'r> {
+    operator: &'r Arc<dyn CustomOperator>,
+    timeout: &'r OperatorTimeout,
+}
+
+impl CustomOperator for TimedOperator<'_> {
+    fn evaluate<'a>(
+        &self,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let operator = Arc::clone(self.operator);
+        let args: Vec<JsonValue> = args.iter().map(ToJson::to_json).collect();
+        let data = arena.current_context(0).map(ToJson::to_json);
+        let limits = arena.limits();
+
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let call = move || {
+            let arena = DataArena::new();
+            arena.set_limits(limits);
+            if let Some(data) = &data {
+                let data = arena.alloc(DataValue::from_json(data, &arena));
+                arena.set_current_context(data, arena.alloc(DataValue::String("$")));
+            }
+            let args: Vec<DataValue> = args
+                .iter()
+                .map(|arg| DataValue::from_json(arg, &arena))
+                .collect();
+            let args = arena.vec_into_slice(args);
+            let answer = panic::catch_unwind(AssertUnwindSafe(|| {
+                operator.evaluate(args, &arena).map(ToJson::to_json)
+            }));
+            // The evaluation may have stopped waiting for the answer
+            let _ = sender.send(answer);
+        };
+        match self.timeout.pool.try_run(call) {
+            Ok(true) => {}
+            // Waiting for a worker to free up could take longer than the timeout
+            Ok(false) => return Err(self.timeout.timed_out()),
+            Err(_) => {
+                return Err(LogicError::Custom(format!(
+                    "Could not start a thread for operator '{}'",
+                    self.timeout.name
+                )))
+            }
+        }
+
+        match receiver.recv_timeout(self.timeout.config.timeout) {
+            Ok(Ok(answer)) => Ok(arena.alloc(DataValue::from_json(&answer?, arena))),
+            // Panics are raised again on the evaluating thread, where panic
+            // isolation applies to them
+            Ok(Err(payload)) => panic::resume_unwind(payload),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                Err(self.timeout.timed_out())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[derive(Debug)]
+    struct Sleepy(Duration);
+
+    impl CustomOperator for Sleepy {
+        fn evaluate<'a>(
+            &self,
+            args: &'a [DataValue<'a>],
+            arena: &'a DataArena,
+        ) -> Result<&'a DataValue<'a>> {
+            thread::sleep(self.0);
+            let data = arena.current_context(0).cloned().unwrap_or(DataValue::Null);
+            let items = arena.vec_into_slice(vec![DataValue::Array(args), data]);
+            Ok(arena.alloc(DataValue::Array(items)))
+        }
+    }
+
+    #[test]
+    fn test_timed_operator() {
+        let arena = DataArena::new();
+        let data = arena.alloc(DataValue::from_json(&serde_json::json!({"a": 1}), &arena));
+        arena.set_current_context(data, arena.alloc(DataValue::String("$")));
+        let args = arena.vec_into_slice(vec![DataValue::integer(7)]);
+
+        // Answers in time cross back with the arguments and data they saw
+        let operator: Arc<dyn CustomOperator> = Arc::new(Sleepy(Duration::ZERO));
+        let timeout = OperatorTimeout::new("sleepy", TimeoutConfig::new(Duration::from_secs(5)));
+        let result = timeout.wrap(&operator).evaluate(args, &arena).unwrap();
+        assert_eq!(result.to_json(), serde_json::json!([[7], {"a": 1}]));
+
+        let operator: Arc<dyn CustomOperator> = Arc::new(Sleepy(Duration::from_secs(2)));
+        let config = TimeoutConfig::new(Duration::from_millis(10));
+        let timeout = OperatorTimeout::new("sleepy", config.clone());
+        let start = Instant::now();
+        let result = timeout.wrap(&operator).evaluate(args, &arena);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            result,
+            Err(LogicError::OperatorTimedOut {
+                operator: "sleepy".to_string(),
+                timeout: Duration::from_millis(10)
+            })
+        );
+        assert_eq!(
+            timeout.apply_fallback(result.clone(), &arena),
+            Err(LogicError::OperatorTimedOut {
+                operator: "sleepy".to_string(),
+                timeout: Duration::from_millis(10)
+            })
+        );
+
+        let timeout = OperatorTimeout::new("sleepy", config.with_fallback(serde_json::json!(0)));
+        let result = timeout.apply_fallback(result, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(0));
+    }
+
+    /// Answers once released, counting the calls it started
+    #[derive(Debug)]
+    struct Gated {
+        started: AtomicUsize,
+        release: Mutex<Receiver<()>>,
+    }
+
+    impl CustomOperator for Gated {
+        fn evaluate<'a>(
+            &self,
+            _args: &'a [DataValue<'a>],
+            arena: &'a DataArena,
+        ) -> Result<&'a DataValue<'a>> {
+            self.started.fetch_add(1, Ordering::SeqCst);
+            self.release.lock().unwrap().recv().unwrap();
+            Ok(arena.alloc(DataValue::Bool(true)))
+        }
+    }
+
+    #[test]
+    fn test_saturated_pool_fails_fast() {
+        let (release, receiver) = mpsc::channel();
+        let gated = Arc::new(Gated {
+            started: AtomicUsize::new(0),
+            release: Mutex::new(receiver),
+        });
+        let operator: Arc<dyn CustomOperator> = gated.clone();
+        let config = TimeoutConfig::new(Duration::from_secs(30)).with_workers(1);
+        let timeout = OperatorTimeout::new("gated", config);
+
+        thread::scope(|scope| {
+            let first = scope.spawn(|| {
+                let arena = DataArena::new();
+                timeout
+                    .wrap(&operator)
+                    .evaluate(&[], &arena)
+                    .map(|v| v.to_json())
+            });
+            while gated.started.load(Ordering::SeqCst) == 0 {
+                thread::yield_now();
+            }
+
+            // The only worker is busy, so the call is not started at all
+            let arena = DataArena::new();
+            let result = timeout.wrap(&operator).evaluate(&[], &arena);
+            assert_eq!(result, Err(timeout.timed_out()));
+            assert_eq!(gated.started.load(Ordering::SeqCst), 1);
+
+            release.send(()).unwrap();
+            assert_eq!(first.join().unwrap(), Ok(serde_json::json!(true)));
+        });
+
+        // Once free, the worker takes the next call
+        while timeout.pool.busy.load(Ordering::SeqCst) > 0 {
+            thread::yield_now();
+        }
+        release.send(()).unwrap();
+        let arena = DataArena::new();
+        let result = timeout.wrap(&operator).evaluate(&[], &arena).unwrap();
+        assert_eq!(result.as_bool(), Some(true));
+        assert_eq!(gated.started.load(Ordering::SeqCst), 2);
+        assert_eq!(timeout.pool.workers.lock().unwrap().0, 1);
+    }
+}
@@ -0,0 +1,248 @@
+//! Versioned custom operators.
+//!
+//! Custom operators can be registered under a namespaced name such as
+//! `acme/score` together with a version and an optional argument schema.
+//! Rules then refer to them as `acme/score` (latest version), `acme.v2/score`
+//! (latest 2.x) or `acme.v2.1/score` (2.1 or a later 2.x). The best matching
+//! version is resolved when the rule is compiled.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::logic::{LogicError, Result};
+
+/// A `major.minor.patch` operator version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OperatorVersion {
+    /// Major version; versions with different majors are incompatible.
+    pub major: u32,
+    /// Minor version.
+    pub minor: u32,
+    /// Patch version.
+    pub patch: u32,
+}
+
+impl OperatorVersion {
+    /// Creates a version from its components.
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+/// Parses up to three dot-separated version components, with an optional `v` prefix.
+fn parse_components(s: &str) -> Option<Vec<u32>> {
+    let s = s.strip_prefix('v').unwrap_or(s);
+    let parts = s
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    (1..=3).contains(&parts.len()).then_some(parts)
+}
+
+impl FromStr for OperatorVersion {
+    type Err = LogicError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = parse_components(s).ok_or_else(|| LogicError::ParseError {
+            reason: format!("Invalid operator version: {}", s),
+        })?;
+        Ok(Self::new(
+            parts[0],
+            parts.get(1).copied().unwrap_or(0),
+            parts.get(2).copied().unwrap_or(0),
+        ))
+    }
+}
+
+impl fmt::Display for OperatorVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Registration metadata of a versioned custom operator.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::OperatorMetadata;
+///
+/// let metadata = OperatorMetadata::new("2.1.0")
+///     .unwrap()
+///     .with_arg_types(&["number", "any"]);
+/// assert_eq!(metadata.version().to_string(), "2.1.0");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorMetadata {
+    version: OperatorVersion,
+    arg_types: Option<Vec<String>>,
+}
+
+impl OperatorMetadata {
+    /// Creates metadata for the given version, such as `"2.1.0"`.
+    pub fn new(version: &str) -> Result<Self> {
+        Ok(Self {
+            version: version.parse()?,
+            arg_types: None,
+        })
+    }
+
+    /// Declares the expected argument types.
+    ///
+    /// Types are the names returned by the `type` operator, or `"any"`.
+    /// Rules passing a different number of arguments, or literal arguments of
+    /// another type, are rejected at compile time.
+    pub fn with_arg_types(mut self, arg_types: &[&str]) -> Self {
+        self.arg_types = Some(arg_types.iter().map(|t| t.to_string()).collect());
+        self
+    }
+
+    /// Returns the version of the operator.
+    pub fn version(&self) -> OperatorVersion {
+        self.version
+    }
+
+    /// Returns the expected argument types, if declared.
+    pub fn arg_types(&self) -> Option<&[String]> {
+        self.arg_types.as_deref()
+    }
+}
+
+/// A version requirement parsed from an operator name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct VersionReq {
+    major: u32,
+    minimum: OperatorVersion,
+}
+
+impl VersionReq {
+    /// Returns true if the version satisfies the requirement.
+    pub(crate) fn matches(&self, version: OperatorVersion) -> bool {
+        version.major == self.major && version >= self.minimum
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.minimum)
+    }
+}
+
+/// Splits a namespaced operator name into its base name and version requirement.
+///
+/// `acme.v2/score` becomes `("acme/score", Some(v2))`, and names without a
+/// version suffix on the namespace are returned unchanged.
+pub(crate) fn split_versioned_name(name: &str) -> (String, Option<VersionReq>) {
+    let Some((namespace, operator)) = name.split_once('/') else {
+        return (name.to_string(), None);
+    };
+
+    let versioned = namespace.rfind(".v").and_then(|index| {
+        let parts = parse_components(&namespace[index + 1..])?;
+        let minimum = OperatorVersion::new(
+            parts[0],
+            parts.get(1).copied().unwrap_or(0),
+            parts.get(2).copied().unwrap_or(0),
+        );
+        Some((index, minimum))
+    });
+
+    match versioned {
+        Some((index, minimum)) => (
+            format!("{}/{}", &namespace[..index], operator),
+            Some(VersionReq {
+                major: minimum.major,
+                minimum,
+            }),
+        ),
+        None => (name.to_string(), None),
+    }
+}
+
+/// Returns the name a specific operator version is registered under.
+pub(crate) fn versioned_key(base: &str, version: OperatorVersion) -> String {
+    format!("{}@{}", base, version)
+}
+
+/// Picks the highest registered version that satisfies the requirement.
+///
+/// Fails with the list of available versions if none does.
+pub(crate) fn resolve_version<'m>(
+    base: &str,
+    req: Option<VersionReq>,
+    available: &'m [OperatorMetadata],
+) -> Result<&'m OperatorMetadata> {
+    available
+        .iter()
+        .filter(|metadata| req.is_none_or(|req| req.matches(metadata.version)))
+        .max_by_key(|metadata| metadata.version)
+        .ok_or_else(|| {
+            let mut versions: Vec<_> = available.iter().map(|m| m.version).collect();
+            versions.sort();
+            let versions: Vec<String> = versions.iter().map(ToString::to_string).collect();
+            LogicError::ParseError {
+                reason: format!(
+                    "No version of operator '{}' matches {}; available versions: {}",
+                    base,
+                    req.map_or_else(|| "any".to_string(), |req| req.to_string()),
+                    versions.join(", ")
+                ),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_versioned_name() {
+        let (base, req) = split_versioned_name("acme.v2/score");
+        assert_eq!(base, "acme/score");
+        assert!(req.unwrap().matches(OperatorVersion::new(2, 3, 0)));
+        assert!(!req.unwrap().matches(OperatorVersion::new(3, 0, 0)));
+
+        let (base, req) = split_versioned_name("com.acme.v2.1/score");
+        assert_eq!(base, "com.acme/score");
+        assert!(!req.unwrap().matches(OperatorVersion::new(2, 0, 5)));
+
+        assert_eq!(
+            split_versioned_name("acme/score"),
+            ("acme/score".into(), None)
+        );
+        assert_eq!(split_versioned_name("score"), ("score".into(), None));
+        assert_eq!(
+            split_versioned_name("acme.vip/score"),
+            ("acme.vip/score".into(), None)
+        );
+    }
+
+    #[test]
+    fn test_resolve_version() {
+        let available = vec![
+            OperatorMetadata::new("1.4.0").unwrap(),
+            OperatorMetadata::new("2.0.0").unwrap(),
+            OperatorMetadata::new("2.2.1").unwrap(),
+        ];
+
+        let (_, req) = split_versioned_name("acme.v2/score");
+        let resolved = resolve_version("acme/score", req, &available).unwrap();
+        assert_eq!(resolved.version(), OperatorVersion::new(2, 2, 1));
+
+        let (_, req) = split_versioned_name("acme.v1/score");
+        let resolved = resolve_version("acme/score", req, &available).unwrap();
+        assert_eq!(resolved.version().to_string(), "1.4.0");
+
+        let (_, req) = split_versioned_name("acme.v3/score");
+        let error = resolve_version("acme/score", req, &available).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parse error: No version of operator 'acme/score' matches v3.0.0; \
+             available versions: 1.4.0, 2.0.0, 2.2.1"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! Bounded execution of expensive analyses
+//!
+//! Analyses such as fuzzing search a space that can be made as large as
+//! wanted. To call them interactively, a [`Budget`] bounds the time and the
+//! number of steps they take, and a [`CancellationToken`] lets another thread
+//! stop them. A budgeted analysis returns a [`Partial`] result holding what
+//! it found so far and why it stopped.
+//!
+//! Searches that refine their answer with depth can be run by
+//! [`Budget::deepen`], which searches at increasing depths and keeps the
+//! answer of the deepest search that completed.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A flag stopping the analyses that share it
+///
+/// Clones share the flag, so a token can be handed to another thread to
+/// cancel an analysis running with a [`Budget`] holding it.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the analyses holding the token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks if the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The reason an analysis stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StopReason {
+    /// The analysis ran to completion
+    Completed,
+    /// The time limit was reached
+    TimeLimit,
+    /// The step limit was reached
+    StepLimit,
+    /// The analysis was cancelled through its [`CancellationToken`]
+    Cancelled,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StopReason::Completed => "completed",
+            StopReason::TimeLimit => "time limit reached",
+            StopReason::StepLimit => "step limit reached",
+            StopReason::Cancelled => "cancelled",
+        })
+    }
+}
+
+/// Limits on the work of an analysis
+///
+/// A budget is consumed by the analysis it is passed to, one
+/// [`step`](Budget::step) at a time. What a step is depends on the analysis,
+/// such as one generated document for fuzzing.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::budget::{Budget, CancellationToken, StopReason};
+/// use std::time::Duration;
+///
+/// let token = CancellationToken::new();
+/// let mut budget = Budget::new()
+///     .with_time_limit(Duration::from_secs(1))
+///     .with_max_steps(3)
+///     .with_cancellation(token.clone());
+///
+/// let mut steps = 0;
+/// let stop = loop {
+///     if let Err(reason) = budget.step() {
+///         break reason;
+///     }
+///     steps += 1;
+/// };
+/// assert_eq!((steps, stop), (3, StopReason::StepLimit));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Budget {
+    start: Instant,
+    time_limit: Option<Duration>,
+    max_steps: Option<u64>,
+    steps: u64,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Budget {
+    /// Creates an unlimited budget
+    ///
+    /// The clock of the time limit starts when the budget is created.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            time_limit: None,
+            max_steps: None,
+            steps: 0,
+            cancellation: None,
+        }
+    }
+
+    /// Limits the wall-clock time since the budget was created
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Limits the number of steps
+    pub fn with_max_steps(mut self, max: u64) -> Self {
+        self.max_steps = Some(max);
+        self
+    }
+
+    /// Stops the analysis when a token is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Returns the number of steps taken
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Returns the time elapsed since the budget was created
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Checks if the analysis must stop, without taking a step
+    pub fn check(&self) -> Result<(), StopReason> {
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(StopReason::Cancelled);
+        }
+        if self.max_steps.is_some_and(|max| self.steps >= max) {
+            return Err(StopReason::StepLimit);
+        }
+        if self
+            .time_limit
+            .is_some_and(|limit| self.start.elapsed() >= limit)
+        {
+            return Err(StopReason::TimeLimit);
+        }
+        Ok(())
+    }
+
+    /// Takes a step, unless the analysis must stop
+    pub fn step(&mut self) -> Result<(), StopReason> {
+        self.check()?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Wraps the result of an analysis that stopped for a reason.
+    pub(crate) fn finish<T>(&self, result: T, stop: StopReason) -> Partial<T> {
+        Partial {
+            result,
+            stop,
+            steps: self.steps,
+            depth: None,
+            elapsed: self.elapsed(),
+        }
+    }
+
+    /// Runs a search at increasing depths until it completes or the budget
+    /// runs out
+    ///
+    /// `search` is called with depths from 1 up to `max_depth`, and returns
+    /// its answer at that depth, or the reason it stopped before completing
+    /// the depth. The answer of the deepest completed search is returned, or
+    /// `None` if not even depth 1 completed. The search ends early when it
+    /// reports its answer as [final](Deepening::Final).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::budget::{Budget, Deepening, StopReason};
+    ///
+    /// // Searches for the smallest square above 50, one candidate per step
+    /// let mut budget = Budget::new().with_max_steps(20);
+    /// let partial = budget.deepen(100, |depth, budget| {
+    ///     let candidate = depth * depth;
+    ///     if let Err(reason) = budget.step() {
+    ///         return Deepening::Stopped(reason);
+    ///     }
+    ///     if candidate > 50 {
+    ///         Deepening::Final(candidate)
+    ///     } else {
+    ///         Deepening::Partial(candidate)
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(partial.result, Some(64));
+    /// assert_eq!(partial.stop, StopReason::Completed);
+    /// assert_eq!(partial.depth, Some(8));
+    /// ```
+    pub fn deepen<T, F>(&mut self, max_depth: usize, mut search: F) -> Partial<Option<T>>
+    where
+        F: FnMut(usize, &mut Budget) -> Deepening<T>,
+    {
+        let mut best = None;
+        let mut depth = None;
+        let mut stop = StopReason::Completed;
+
+        for current in 1..=max_depth {
+            if let Err(reason) = self.check() {
+                stop = reason;
+                break;
+            }
+            match search(current, self) {
+                Deepening::Partial(result) => {
+                    best = Some(result);
+                    depth = Some(current);
+                }
+                Deepening::Final(result) => {
+                    best = Some(result);
+                    depth = Some(current);
+                    break;
+                }
+                Deepening::Stopped(reason) => {
+                    stop = reason;
+                    break;
+                }
+            }
+        }
+
+        Partial {
+            depth,
+            ..self.finish(best, stop)
+        }
+    }
+}
+
+/// The outcome of one depth of an iterative deepening search
+#[derive(Debug, Clone, PartialEq)]
+pub enum Deepening<T> {
+    /// The answer at this depth, which a deeper search may improve
+    Partial(T),
+    /// The answer, which no deeper search can improve
+    Final(T),
+    /// The budget ran out before the depth was searched completely
+    Stopped(StopReason),
+}
+
+/// The result of an analysis run with a [`Budget`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partial<T> {
+    /// What the analysis found before it stopped
+    pub result: T,
+    /// Why the analysis stopped
+    pub stop: StopReason,
+    /// The number of steps taken
+    pub steps: u64,
+    /// The deepest depth searched completely, for iterative deepening
+    pub depth: Option<usize>,
+    /// The time the analysis took
+    pub elapsed: Duration,
+}
+
+impl<T> Partial<T> {
+    /// Checks if the analysis ran to completion
+    pub fn is_complete(&self) -> bool {
+        self.stop == StopReason::Completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_limits() {
+        let token = CancellationToken::new();
+        let mut budget = Budget::new().with_cancellation(token.clone());
+        assert!(budget.step().is_ok());
+        token.cancel();
+        assert_eq!(budget.step(), Err(StopReason::Cancelled));
+        assert_eq!(budget.steps(), 1);
+
+        let budget = Budget::new().with_time_limit(Duration::ZERO);
+        assert_eq!(budget.check(), Err(StopReason::TimeLimit));
+        assert!(Budget::new().check().is_ok());
+    }
+
+    #[test]
+    fn test_deepen_keeps_deepest_completed_answer() {
+        // Each depth takes as many steps as its number
+        let search = |depth: usize, budget: &mut Budget| {
+            for _ in 0..depth {
+                if let Err(reason) = budget.step() {
+                    return Deepening::Stopped(reason);
+                }
+            }
+            Deepening::Partial(depth * 10)
+        };
+
+        let partial = Budget::new().with_max_steps(8).deepen(10, search);
+        assert_eq!(partial.result, Some(30));
+        assert_eq!(partial.depth, Some(3));
+        assert_eq!(partial.stop, StopReason::StepLimit);
+        assert_eq!(partial.steps, 8);
+        assert!(!partial.is_complete());
+
+        let partial = Budget::new().deepen(4, search);
+        assert_eq!(partial.result, Some(40));
+        assert!(partial.is_complete());
+
+        let partial = Budget::new().with_max_steps(0).deepen(4, search);
+        assert_eq!(partial.result, None);
+        assert_eq!(partial.depth, None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Runtime description of the engine build
+//!
+//! Services and rule editors talking to a deployed engine can ask it which
+//! optional features were compiled in and which operators it understands,
+//! instead of assuming the capabilities of the build they were written
+//! against.
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::logic::token::OPERATOR_NAMES;
+
+/// Operators handled by the parser rather than parsed into an operator type
+const SPECIAL_FORMS: &[&str] = &["var", "preserve", "obj"];
+
+/// The version, features and operators of the engine
+///
+/// Returned by [`DataLogic::capabilities`](crate::DataLogic::capabilities).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The version of the crate
+    pub version: &'static str,
+    /// The features included in the build, such as `"regex"` or `"predict"`
+    ///
+    /// Features that are not listed, such as `"async"` or `"decimal"`, are
+    /// not available.
+    pub features: Vec<&'static str>,
+    /// The names of the built-in operators, sorted
+    pub operators: Vec<&'static str>,
+    /// The names of the custom operators registered on the instance or
+    /// globally, sorted
+    pub custom_operators: Vec<String>,
+}
+
+impl Capabilities {
+    /// Describes the build, with the given custom operators
+    pub(crate) fn new(custom_operators: impl IntoIterator<Item = String>) -> Self {
+        let mut operators: Vec<&'static str> = OPERATOR_NAMES
+            .iter()
+            .chain(SPECIAL_FORMS)
+            .copied()
+            .collect();
+        operators.sort_unstable();
+
+        let mut custom_operators: Vec<String> = custom_operators.into_iter().collect();
+        custom_operators.sort_unstable();
+        custom_operators.dedup();
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            features: enabled_features(),
+            operators,
+            custom_operators,
+        }
+    }
+
+    /// Check if a feature is included in the build
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// Check if an operator is available, either built in or custom
+    pub fn supports_operator(&self, name: &str) -> bool {
+        self.operators.binary_search(&name).is_ok()
+            || self
+                .custom_operators
+                .binary_search_by(|operator| operator.as_str().cmp(name))
+                .is_ok()
+    }
+
+    /// Converts the capabilities to JSON
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "version": self.version,
+            "features": self.features,
+            "operators": self.operators,
+            "custom_operators": self.custom_operators,
+        })
+    }
+}
+
+/// Returns the features included in the build
+///
+/// Date and time handling and regular expressions are always built in.
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = vec!["datetime", "regex"];
+    if cfg!(feature = "predict") {
+        features.push("predict");
+    }
+    if cfg!(feature = "wasm") {
+        features.push("wasm");
+    }
+    if cfg!(feature = "tz") {
+        features.push("tz");
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = Capabilities::new(vec!["b_op".to_string(), "a_op".to_string()]);
+
+        assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+        assert!(capabilities.has_feature("regex"));
+        assert!(!capabilities.has_feature("async"));
+        assert_eq!(
+            capabilities.has_feature("predict"),
+            cfg!(feature = "predict")
+        );
+        assert_eq!(capabilities.has_feature("tz"), cfg!(feature = "tz"));
+
+        assert!(capabilities.supports_operator("var"));
+        assert!(capabilities.supports_operator("?:"));
+        assert!(capabilities.supports_operator("a_op"));
+        assert!(!capabilities.supports_operator("c_op"));
+        assert_eq!(capabilities.custom_operators, vec!["a_op", "b_op"]);
+
+        let json = capabilities.to_json();
+        assert_eq!(json["features"][1], "regex");
+        assert_eq!(json["custom_operators"], json!(["a_op", "b_op"]));
+    }
+}
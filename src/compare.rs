@@ -0,0 +1,259 @@
+//! Comparison of two versions of a rule
+//!
+//! Before rolling out a modified rule, it can be evaluated side by side with
+//! the current one over a dataset of recorded inputs with
+//! [`DataLogic::compare_rules`](crate::DataLogic::compare_rules). The
+//! [`RuleComparison`] tells how many outcomes change, gives examples of
+//! inputs on which the versions disagree, counts every pair of outcomes, and
+//! for rules that are an `if`, counts the records taking each branch.
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::logic::Result;
+
+/// The number of divergent inputs kept as examples
+pub const MAX_EXAMPLES: usize = 10;
+
+/// An input on which two versions of a rule disagree
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutcomeChange {
+    /// The position of the input in the dataset
+    pub index: usize,
+    /// The input
+    pub data: JsonValue,
+    /// The outcome of the current version
+    pub before: Result<JsonValue>,
+    /// The outcome of the modified version
+    pub after: Result<JsonValue>,
+}
+
+/// The number of inputs with a given pair of outcomes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// The outcome of the current version
+    pub before: Result<JsonValue>,
+    /// The outcome of the modified version
+    pub after: Result<JsonValue>,
+    /// The number of inputs with these outcomes
+    pub count: usize,
+}
+
+/// The branches taken by the inputs in a rule that is an `if`
+///
+/// Branches are numbered in the order of their conditions, the last one
+/// standing for the else branch, or for no branch if there is none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BranchStats {
+    /// The number of inputs taking each branch
+    pub taken: Vec<usize>,
+    /// The number of inputs taking each branch whose outcome changed
+    pub changed: Vec<usize>,
+}
+
+impl BranchStats {
+    /// Creates empty statistics for an `if` of `branches` branches.
+    pub(crate) fn new(branches: usize) -> Self {
+        Self {
+            taken: vec![0; branches],
+            changed: vec![0; branches],
+        }
+    }
+
+    /// Counts an input taking a branch.
+    pub(crate) fn record(&mut self, branch: usize, changed: bool) {
+        if let Some(taken) = self.taken.get_mut(branch) {
+            *taken += 1;
+            self.changed[branch] += usize::from(changed);
+        }
+    }
+}
+
+/// The differences between the outcomes of two versions of a rule
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleComparison {
+    /// The number of inputs evaluated
+    pub total: usize,
+    /// The number of inputs whose outcome changed
+    pub changed: usize,
+    /// The first inputs whose outcome changed, at most [`MAX_EXAMPLES`]
+    pub examples: Vec<OutcomeChange>,
+    /// Every pair of outcomes, in the order they were first seen
+    pub transitions: Vec<Transition>,
+    /// The branches taken in the current version, if it is an `if`
+    pub branches_before: Option<BranchStats>,
+    /// The branches taken in the modified version, if it is an `if`
+    pub branches_after: Option<BranchStats>,
+}
+
+impl RuleComparison {
+    /// Returns the fraction of the inputs whose outcome changed
+    pub fn change_rate(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.changed as f64 / self.total as f64
+        }
+    }
+
+    /// Counts the outcomes of an input.
+    pub(crate) fn record(
+        &mut self,
+        data: &JsonValue,
+        before: Result<JsonValue>,
+        after: Result<JsonValue>,
+    ) {
+        let index = self.total;
+        self.total += 1;
+
+        match self
+            .transitions
+            .iter_mut()
+            .find(|transition| transition.before == before && transition.after == after)
+        {
+            Some(transition) => transition.count += 1,
+            None => self.transitions.push(Transition {
+                before: before.clone(),
+                after: after.clone(),
+                count: 1,
+            }),
+        }
+
+        if before != after {
+            self.changed += 1;
+            if self.examples.len() < MAX_EXAMPLES {
+                self.examples.push(OutcomeChange {
+                    index,
+                    data: data.clone(),
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+
+    /// Converts the comparison to JSON.
+    ///
+    /// Failed outcomes are represented as `{"error": message}`.
+    pub fn to_json(&self) -> JsonValue {
+        let branches = |stats: &Option<BranchStats>| {
+            stats.as_ref().map_or(
+                JsonValue::Null,
+                |stats| json!({"taken": stats.taken, "changed": stats.changed}),
+            )
+        };
+        json!({
+            "total": self.total,
+            "changed": self.changed,
+            "examples": self.examples.iter().map(|example| json!({
+                "index": example.index,
+                "data": example.data,
+                "before": outcome_to_json(&example.before),
+                "after": outcome_to_json(&example.after),
+            })).collect::<Vec<_>>(),
+            "transitions": self.transitions.iter().map(|transition| json!({
+                "before": outcome_to_json(&transition.before),
+                "after": outcome_to_json(&transition.after),
+                "count": transition.count,
+            })).collect::<Vec<_>>(),
+            "branches_before": branches(&self.branches_before),
+            "branches_after": branches(&self.branches_after),
+        })
+    }
+}
+
+/// Converts an outcome to JSON.
+fn outcome_to_json(outcome: &Result<JsonValue>) -> JsonValue {
+    match outcome {
+        Ok(value) => value.clone(),
+        Err(error) => json!({"error": error.to_string()}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DataLogic, LogicError};
+
+    #[test]
+    fn test_compare_rules() {
+        let dl = DataLogic::new();
+        let before = dl
+            .compile(
+                r#"{"if": [{">": [{"var": "score"}, 700]}, "approve", {">": [{"var": "score"}, 600]}, "review", "deny"]}"#,
+                None,
+            )
+            .unwrap();
+        let after = dl
+            .compile(
+                r#"{"if": [{">": [{"var": "score"}, 650]}, "approve", "deny"]}"#,
+                None,
+            )
+            .unwrap();
+        let dataset: Vec<JsonValue> = [800, 680, 620, 500, 900]
+            .into_iter()
+            .map(|score| json!({"score": score}))
+            .chain([json!({"score": "n/a"})])
+            .collect();
+
+        let comparison = dl.compare_rules(&before, &after, &dataset);
+        assert_eq!(comparison.total, 6);
+        assert_eq!(comparison.changed, 2);
+        assert!((comparison.change_rate() - 1.0 / 3.0).abs() < 1e-9);
+
+        assert_eq!(comparison.examples.len(), 2);
+        assert_eq!(comparison.examples[0].index, 1);
+        assert_eq!(comparison.examples[0].before, Ok(json!("review")));
+        assert_eq!(comparison.examples[0].after, Ok(json!("approve")));
+
+        // Identical failures are not changes
+        let transitions: Vec<(JsonValue, JsonValue, usize)> = comparison
+            .transitions
+            .iter()
+            .map(|t| {
+                (
+                    outcome_to_json(&t.before),
+                    outcome_to_json(&t.after),
+                    t.count,
+                )
+            })
+            .collect();
+        let nan = json!({"error": LogicError::NaNError.to_string()});
+        assert_eq!(
+            transitions,
+            [
+                (json!("approve"), json!("approve"), 2),
+                (json!("review"), json!("approve"), 1),
+                (json!("review"), json!("deny"), 1),
+                (json!("deny"), json!("deny"), 1),
+                (nan.clone(), nan, 1),
+            ]
+        );
+
+        // Inputs failing a condition take no branch
+        let branches = comparison.branches_before.as_ref().unwrap();
+        assert_eq!(branches.taken, [2, 2, 1]);
+        assert_eq!(branches.changed, [0, 2, 0]);
+        assert_eq!(comparison.branches_after.as_ref().unwrap().taken, [3, 2]);
+        assert_eq!(comparison.to_json()["changed"], json!(2));
+    }
+
+    #[test]
+    fn test_compare_rules_with_errors() {
+        let dl = DataLogic::new();
+        let before = dl.compile(r#"{"var": "x"}"#, None).unwrap();
+        let after = dl.compile(r#"{"throw": "removed"}"#, None).unwrap();
+
+        let dataset = [json!({"x": 1})];
+        let comparison = dl.compare_rules(&before, &after, &dataset);
+        assert_eq!(comparison.changed, 1);
+        assert!(comparison.branches_before.is_none());
+        assert!(matches!(
+            comparison.examples[0].after,
+            Err(LogicError::ThrownError { .. })
+        ));
+        assert_eq!(
+            comparison.to_json()["examples"][0]["after"],
+            json!({"error": comparison.examples[0].after.clone().unwrap_err().to_string()})
+        );
+    }
+}
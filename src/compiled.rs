@@ -0,0 +1,424 @@
+//! Thread-safe compiled rules
+//!
+//! A [`Logic`](crate::Logic) borrows the arena of the DataLogic instance that
+//! parsed it, so it cannot leave that instance's thread. A CompiledRule owns
+//! the arena holding its tokens instead, and can be shared between threads
+//! and evaluated concurrently by one DataLogic instance per thread.
+//!
+//! A compiled rule remembers the generation of the instance that compiled it,
+//! so [`DataLogic::is_stale`](crate::DataLogic::is_stale) can tell when a
+//! later registration change affects it.
+//!
+//! A rule can also carry an id and metadata, such as its source file or
+//! owner. The id is attached to the errors, `log` reports and fallback events
+//! of its evaluations, so production logs can be traced back to the rule.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::Value as JsonValue;
+
+use crate::arena::DataArena;
+use crate::incremental::Dependencies;
+use crate::logic::{Result, Token};
+use crate::output::OutputType;
+
+/// A parsed and optimized rule that owns its memory
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::DataLogic;
+/// use std::sync::Arc;
+///
+/// let rule = Arc::new(
+///     DataLogic::new()
+///         .compile(r#"{">": [{"var": "temp"}, 100]}"#, None)
+///         .unwrap(),
+/// );
+///
+/// let workers: Vec<_> = [90, 110]
+///     .into_iter()
+///     .map(|temp| {
+///         let rule = Arc::clone(&rule);
+///         std::thread::spawn(move || {
+///             let dl = DataLogic::new();
+///             let data = dl.parse_data(&format!(r#"{{"temp": {}}}"#, temp)).unwrap();
+///             dl.evaluate_compiled(&rule, &data).unwrap().as_bool()
+///         })
+///     })
+///     .collect();
+///
+/// let results: Vec<_> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+/// assert_eq!(results, [Some(false), Some(true)]);
+/// ```
+pub struct CompiledRule {
+    /// The root token, borrowing from `arena`
+    root: &'static Token<'static>,
+
+    /// The arena holding the tokens of the rule
+    ///
+    /// Boxed so that the tokens stay in place when the rule is moved. It is
+    /// never read, only held until the rule is dropped.
+    #[allow(dead_code)]
+    arena: Box<DataArena>,
+
+    /// The number of bytes allocated in the arena, counted once it is built
+    allocated_bytes: usize,
+
+    /// The custom operators used by the rule, by base name
+    custom_operators: BTreeSet<String>,
+
+    /// The data read by the rule
+    dependencies: Dependencies,
+
+    /// The generation of the compiling instance
+    generation: u64,
+
+    /// The generation of the global operator registry
+    global_generation: u64,
+
+    /// The declared type of the result, if any
+    output_type: Option<OutputType>,
+
+    /// The id identifying the rule in errors and traces, if any
+    id: Option<Arc<str>>,
+
+    /// Metadata attached to the rule
+    metadata: BTreeMap<String, JsonValue>,
+}
+
+// SAFETY: The arena is only used to allocate the tokens while the rule is
+// compiled. Afterwards it is never accessed except to be dropped, and the
+// tokens themselves are immutable and Sync, so sharing the rule between
+// threads cannot race on the arena's interior mutability.
+unsafe impl Sync for CompiledRule {}
+
+// Compiled rules are shared between threads, so losing either bound is a bug
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CompiledRule>();
+};
+
+impl CompiledRule {
+    /// Creates a compiled rule from tokens built in a fresh arena.
+    pub(crate) fn build<F>(build: F) -> Result<Self>
+    where
+        F: for<'a> FnOnce(&'a DataArena) -> Result<&'a Token<'a>>,
+    {
+        let arena = Box::new(DataArena::new());
+        let root = build(&arena)?;
+
+        // SAFETY: The tokens live as long as the arena, which is owned by the
+        // rule and only exposed through borrows of the rule.
+        let root = unsafe { std::mem::transmute::<&Token<'_>, &'static Token<'static>>(root) };
+
+        let mut custom_operators = BTreeSet::new();
+        collect_custom_operators(root, &mut custom_operators);
+        Ok(Self {
+            root,
+            allocated_bytes: arena.memory_usage(),
+            arena,
+            custom_operators,
+            dependencies: Dependencies::of(root),
+            generation: 0,
+            global_generation: 0,
+            output_type: None,
+            id: None,
+            metadata: BTreeMap::new(),
+        })
+    }
+
+    /// Stamps the rule with the generations it was compiled at.
+    pub(crate) fn with_generations(mut self, generation: u64, global_generation: u64) -> Self {
+        self.generation = generation;
+        self.global_generation = global_generation;
+        self
+    }
+
+    /// Returns the generations the rule was compiled at.
+    pub(crate) fn generations(&self) -> (u64, u64) {
+        (self.generation, self.global_generation)
+    }
+
+    /// Returns the generation of the compiling instance at compile time.
+    ///
+    /// See [`DataLogic::generation`](crate::DataLogic::generation).
+    pub fn generation(&self) -> u64 {
+        self.generation + self.global_generation
+    }
+
+    /// Returns the custom operators used by the rule.
+    ///
+    /// Versioned operators are listed under their base name.
+    pub fn custom_operators(&self) -> impl Iterator<Item = &str> {
+        self.custom_operators.iter().map(String::as_str)
+    }
+
+    /// Check if the rule uses a custom operator.
+    pub fn uses_operator(&self, name: &str) -> bool {
+        self.custom_operators.contains(name)
+    }
+
+    /// Returns the data read by the rule.
+    ///
+    /// See [`IncrementalRule`](crate::incremental::IncrementalRule).
+    pub fn dependencies(&self) -> &Dependencies {
+        &self.dependencies
+    }
+
+    /// Declares the type of the result of the rule.
+    ///
+    /// Evaluation coerces the result to the type, failing if it does not
+    /// conform. See [`OutputType::coerce`].
+    pub fn with_output_type(mut self, output_type: OutputType) -> Self {
+        self.output_type = Some(output_type);
+        self
+    }
+
+    /// Returns the declared type of the result, if any.
+    pub fn output_type(&self) -> Option<&OutputType> {
+        self.output_type.as_ref()
+    }
+
+    /// Sets the id identifying the rule.
+    ///
+    /// Errors raised by evaluations of the rule are wrapped in
+    /// [`LogicError::InRule`](crate::LogicError::InRule) holding the id, and
+    /// the id is reported in [`LogMetadata`](crate::LogMetadata) and
+    /// [`FallbackEvent`](crate::FallbackEvent)s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, LogicError};
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl
+    ///     .compile(r#"{"/": [{"var": "total"}, {"var": "count"}]}"#, None)
+    ///     .unwrap()
+    ///     .with_id("orders/average")
+    ///     .with_metadata("owner", json!("billing"));
+    /// let data = dl.parse_data(r#"{"total": 10, "count": "none"}"#).unwrap();
+    ///
+    /// let error = dl.evaluate_compiled(&rule, &data).unwrap_err();
+    /// assert_eq!(error.to_string(), "NaN error in rule 'orders/average'");
+    /// assert_eq!(error.rule_id(), Some("orders/average"));
+    /// assert_eq!(error.root_cause(), &LogicError::NaNError);
+    /// assert_eq!(rule.metadata().get("owner"), Some(&json!("billing")));
+    /// ```
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(Arc::from(id.into()));
+        self
+    }
+
+    /// Returns the id of the rule, if any.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Attaches a metadata entry to the rule, replacing any with the same key.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: JsonValue) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+
+    /// Returns the metadata attached to the rule.
+    pub fn metadata(&self) -> &BTreeMap<String, JsonValue> {
+        &self.metadata
+    }
+
+    /// Returns the id of the rule as shared with evaluations.
+    pub(crate) fn shared_id(&self) -> Option<Arc<str>> {
+        self.id.clone()
+    }
+
+    /// Returns the root token of the rule.
+    pub fn root(&self) -> &Token<'_> {
+        self.root
+    }
+
+    /// Returns a fingerprint of the optimized rule.
+    ///
+    /// Rules that compile to the same tokens have the same fingerprint, so it
+    /// can key a cache of compiled rules shared by rules submitted separately.
+    /// See [`Token::fingerprint`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    ///
+    /// let dl = DataLogic::new();
+    /// let a = dl.compile(r#"{"+": [{"var": "x"}, 2, 3]}"#, None).unwrap();
+    /// let b = dl.compile(r#"{ "+" : [ {"var":"x"}, 2, 3 ] }"#, None).unwrap();
+    /// let c = dl.compile(r#"{"+": [{"var": "x"}, 2, 4]}"#, None).unwrap();
+    ///
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// assert_ne!(a.fingerprint(), c.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        self.root.fingerprint()
+    }
+
+    /// Returns a listing of the optimized rule, one token per line.
+    ///
+    /// See [`Token::disassemble`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl
+    ///     .compile(r#"{"if": [{">": [{"var": ["age", 0]}, {"+": [10, 8]}]}, "adult", "minor"]}"#, None)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     rule.disassemble(),
+    ///     [
+    ///         "0000  if",
+    ///         "0001    >",
+    ///         "0002      var \"age\" default",
+    ///         "0003        literal 0",
+    ///         "0004      literal 18",
+    ///         "0005    literal \"adult\"",
+    ///         "0006    literal \"minor\"",
+    ///         "",
+    ///     ]
+    ///     .join("\n")
+    /// );
+    /// ```
+    pub fn disassemble(&self) -> String {
+        self.root.disassemble()
+    }
+
+    /// Returns the number of bytes allocated for the rule.
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes
+    }
+}
+
+/// Collects the base names of the custom operators of a token tree.
+fn collect_custom_operators(token: &Token, names: &mut BTreeSet<String>) {
+    match token {
+        Token::Literal(_) => {}
+        Token::ArrayLiteral(items) => {
+            for item in items {
+                collect_custom_operators(item, names);
+            }
+        }
+        Token::Variable { default, .. } => {
+            if let Some(default) = default {
+                collect_custom_operators(default, names);
+            }
+        }
+        Token::DynamicVariable { path_expr, default } => {
+            collect_custom_operators(path_expr, names);
+            if let Some(default) = default {
+                collect_custom_operators(default, names);
+            }
+        }
+        Token::CustomOperator { name, args } => {
+            // Resolved versioned operators are named `base@version`
+            let base = name.split_once('@').map_or(*name, |(base, _)| base);
+            names.insert(base.to_string());
+            collect_custom_operators(args, names);
+        }
+        Token::Operator { args, .. }
+        | Token::CompiledMatch { args, .. }
+        | Token::CompiledRegex { args, .. }
+        | Token::CaseFolded { args, .. } => {
+            collect_custom_operators(args, names);
+        }
+    }
+}
+
+impl fmt::Debug for CompiledRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledRule")
+            .field("id", &self.id)
+            .field("root", &self.root)
+            .field("generation", &self.generation())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DataLogic, DataValue, Fallback, FallbackPolicy, OperatorMetadata};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_compiled_rule_across_threads() {
+        let mut dl = DataLogic::new();
+        dl.register_versioned_simple_operator(
+            "acme/bonus",
+            OperatorMetadata::new("1.0.0").unwrap(),
+            |_, _| Ok(DataValue::integer(5)),
+        );
+        let rule = dl
+            .compile(
+                r#"{"+": [{"var": "x"}, {"acme.v1/bonus": []}, {"*": [2, 3]}]}"#,
+                None,
+            )
+            .unwrap();
+        drop(dl);
+        let rule = Arc::new(rule);
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let rule = Arc::clone(&rule);
+                std::thread::spawn(move || {
+                    let mut dl = DataLogic::new();
+                    dl.register_versioned_simple_operator(
+                        "acme/bonus",
+                        OperatorMetadata::new("1.0.0").unwrap(),
+                        |_, _| Ok(DataValue::integer(5)),
+                    );
+                    let data = dl.parse_data(&format!(r#"{{"x": {}}}"#, i)).unwrap();
+                    dl.evaluate_compiled(&rule, &data).unwrap().as_i64()
+                })
+            })
+            .collect();
+
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(results, [Some(11), Some(12), Some(13), Some(14)]);
+        assert!(rule.allocated_bytes() > 0);
+    }
+
+    #[test]
+    fn test_rule_id_in_reports() {
+        let logged = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut dl = DataLogic::new();
+        let sink = Arc::clone(&logged);
+        dl.set_logger(move |_, metadata| sink.lock().unwrap().push(metadata.rule_id));
+        dl.set_fallback_policy(FallbackPolicy::new(Fallback::Value(json!(0))));
+
+        let rule = dl
+            .compile(r#"{"+": [{"log": {"var": "x"}}, 1]}"#, None)
+            .unwrap()
+            .with_id("scores/bonus");
+        assert_eq!(rule.id(), Some("scores/bonus"));
+        let data = dl.parse_data(r#"{"x": "abc"}"#).unwrap();
+        assert_eq!(
+            dl.evaluate_compiled(&rule, &data).unwrap().as_i64(),
+            Some(0)
+        );
+
+        let events = dl.take_fallback_events();
+        assert_eq!(events[0].rule_id.as_deref(), Some("scores/bonus"));
+        assert_eq!(events[0].error, crate::LogicError::NaNError);
+        assert_eq!(*logged.lock().unwrap(), [Some("scores/bonus".to_string())]);
+
+        // Evaluations of other rules are not attributed to the rule
+        logged.lock().unwrap().clear();
+        let result = dl.evaluate_json(&json!({"log": 1}), &json!({}), None);
+        assert_eq!(result.unwrap(), json!(1));
+        assert_eq!(*logged.lock().unwrap(), [None]);
+    }
+}
@@ -0,0 +1,321 @@
+//! Compliance of the default behavior with the jsonlogic.com specification
+//!
+//! By default the engine follows json-logic-engine, which reports edge cases
+//! of the specification as errors and provides operators the specification
+//! does not define. Certification of a rule engine asks where it deviates
+//! from the specification: [`compliance_report`] evaluates probes of the edge
+//! cases of every operator of the specification, with the default
+//! [`Semantics`] and with [`Semantics::JsonLogicSpec`], which strictly follows
+//! the specification, and lists the probes on which they disagree.
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::capabilities::Capabilities;
+use crate::datalogic::DataLogic;
+use crate::logic::Result;
+use crate::semantics::{Semantics, SPEC_OPERATORS};
+
+/// Rules exercising the edge cases of the operators of the specification,
+/// with the data they are evaluated against
+const PROBES: &[(&str, &str)] = &[
+    (r#"{"var": "a.b"}"#, r#"{"a": {"b": 1}}"#),
+    (r#"{"var": ["x", 1]}"#, "{}"),
+    (r#"{"var": 1}"#, "[10, 20]"),
+    (r#"{"missing": ["a", "b"]}"#, r#"{"a": 1}"#),
+    (r#"{"missing": []}"#, "{}"),
+    (r#"{"missing_some": [1, ["a", "b"]]}"#, r#"{"b": 1}"#),
+    (r#"{"if": []}"#, "{}"),
+    (r#"{"if": [false, "yes"]}"#, "{}"),
+    (r#"{"if": [true, "yes", "no"]}"#, "{}"),
+    (r#"{"?:": [0, "yes", "no"]}"#, "{}"),
+    (r#"{"==": [1, "1"]}"#, "{}"),
+    (r#"{"==": [null, 0]}"#, "{}"),
+    (r#"{"==": [[], false]}"#, "{}"),
+    (r#"{"==": [1]}"#, "{}"),
+    (r#"{"==": []}"#, "{}"),
+    (r#"{"===": [1, 1]}"#, "{}"),
+    (r#"{"===": [1]}"#, "{}"),
+    (r#"{"!=": [1, "1"]}"#, "{}"),
+    (r#"{"!=": [1]}"#, "{}"),
+    (r#"{"!==": [1]}"#, "{}"),
+    (r#"{"!": []}"#, "{}"),
+    (r#"{"!": [[]]}"#, "{}"),
+    (r#"{"!": [false, true]}"#, "{}"),
+    (r#"{"!!": []}"#, "{}"),
+    (r#"{"!!": ["0"]}"#, "{}"),
+    (r#"{"or": []}"#, "{}"),
+    (r#"{"or": [0, ""]}"#, "{}"),
+    (r#"{"and": []}"#, "{}"),
+    (r#"{"and": [1, 0]}"#, "{}"),
+    (r#"{">": [2, 1]}"#, "{}"),
+    (r#"{">": ["a", 1]}"#, "{}"),
+    (r#"{">": [1]}"#, "{}"),
+    (r#"{">=": [1]}"#, "{}"),
+    (r#"{"<": [1, 2, 3]}"#, "{}"),
+    (r#"{"<": [-1]}"#, "{}"),
+    (r#"{"<=": [1, 1, 3]}"#, "{}"),
+    (r#"{"max": []}"#, "{}"),
+    (r#"{"max": [1, "3"]}"#, "{}"),
+    (r#"{"max": ["a", 1]}"#, "{}"),
+    (r#"{"min": []}"#, "{}"),
+    (r#"{"min": ["1", 2]}"#, "{}"),
+    (r#"{"+": []}"#, "{}"),
+    (r#"{"+": ["1", 1]}"#, "{}"),
+    (r#"{"+": ["1", "x"]}"#, "{}"),
+    (r#"{"-": []}"#, "{}"),
+    (r#"{"-": [2]}"#, "{}"),
+    (r#"{"-": [5, 1, 1]}"#, "{}"),
+    (r#"{"-": ["a", 1]}"#, "{}"),
+    (r#"{"*": []}"#, "{}"),
+    (r#"{"*": ["2", 3]}"#, "{}"),
+    (r#"{"/": [4, 2]}"#, "{}"),
+    (r#"{"/": [4]}"#, "{}"),
+    (r#"{"/": [1, 0]}"#, "{}"),
+    (r#"{"%": [5, 2]}"#, "{}"),
+    (r#"{"%": [5]}"#, "{}"),
+    (r#"{"%": [1, 0]}"#, "{}"),
+    (r#"{"map": [null, 1]}"#, "{}"),
+    (r#"{"map": [[1, 2], {"*": [{"var": ""}, 2]}]}"#, "{}"),
+    (r#"{"map": [[1], 1, 2]}"#, "{}"),
+    (
+        r#"{"reduce": [[1, 2], {"+": [{"var": "current"}, {"var": "accumulator"}]}, 0]}"#,
+        "{}",
+    ),
+    (r#"{"filter": [[1, 2], {">": [{"var": ""}, 1]}]}"#, "{}"),
+    (r#"{"filter": [[1], true, 1]}"#, "{}"),
+    (r#"{"all": [[], true]}"#, "{}"),
+    (r#"{"all": [[1, 2], {">": [{"var": ""}, 0]}]}"#, "{}"),
+    (r#"{"some": [[], true]}"#, "{}"),
+    (r#"{"none": [[], true]}"#, "{}"),
+    (r#"{"merge": []}"#, "{}"),
+    (r#"{"merge": [[1], 2, [[3]]]}"#, "{}"),
+    (r#"{"in": ["a", "abc"]}"#, "{}"),
+    (r#"{"in": ["a", null]}"#, "{}"),
+    (r#"{"in": [1, [1, 2]]}"#, "{}"),
+    (r#"{"cat": []}"#, "{}"),
+    (r#"{"cat": ["a", 1, null, true]}"#, "{}"),
+    (r#"{"cat": [[1, 2]]}"#, "{}"),
+    (r#"{"cat": ["x", {"var": "o"}]}"#, r#"{"o": {"k": 1}}"#),
+    (r#"{"substr": ["abc"]}"#, "{}"),
+    (r#"{"substr": ["abc", 1]}"#, "{}"),
+    (r#"{"substr": ["abc", -1]}"#, "{}"),
+    (r#"{"substr": ["abc", 1, -1]}"#, "{}"),
+    (r#"{"log": ["a"]}"#, "{}"),
+    (r#"{"log": []}"#, "{}"),
+];
+
+/// A probe on which the default behavior deviates from the specification
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deviation {
+    /// The operator probed
+    pub operator: String,
+    /// The rule evaluated
+    pub rule: JsonValue,
+    /// The data the rule was evaluated against
+    pub data: JsonValue,
+    /// The outcome with the default semantics
+    pub native: Result<JsonValue>,
+    /// The outcome required by the specification
+    pub spec: Result<JsonValue>,
+}
+
+/// The deviations of the default behavior from the specification
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplianceReport {
+    /// The number of probes evaluated
+    pub probes: usize,
+    /// The probes whose outcome deviates from the specification
+    pub deviations: Vec<Deviation>,
+    /// The built-in operators the specification does not define, sorted
+    pub extensions: Vec<&'static str>,
+}
+
+impl ComplianceReport {
+    /// Returns the operators with a deviation, sorted
+    pub fn deviating_operators(&self) -> Vec<&str> {
+        let mut operators: Vec<&str> = self
+            .deviations
+            .iter()
+            .map(|deviation| deviation.operator.as_str())
+            .collect();
+        operators.sort_unstable();
+        operators.dedup();
+        operators
+    }
+
+    /// Check if no probe deviates from the specification
+    pub fn is_compliant(&self) -> bool {
+        self.deviations.is_empty()
+    }
+
+    /// Converts the report to JSON.
+    ///
+    /// Failed outcomes are represented as `{"error": message}`.
+    pub fn to_json(&self) -> JsonValue {
+        let outcome = |outcome: &Result<JsonValue>| match outcome {
+            Ok(value) => value.clone(),
+            Err(error) => json!({"error": error.to_string()}),
+        };
+        json!({
+            "probes": self.probes,
+            "deviating_operators": self.deviating_operators(),
+            "deviations": self.deviations.iter().map(|deviation| json!({
+                "operator": deviation.operator,
+                "rule": deviation.rule,
+                "data": deviation.data,
+                "native": outcome(&deviation.native),
+                "spec": outcome(&deviation.spec),
+            })).collect::<Vec<_>>(),
+            "extensions": self.extensions,
+        })
+    }
+}
+
+/// Lists where the default behavior deviates from the specification
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::compliance::compliance_report;
+///
+/// let report = compliance_report();
+/// assert!(report.deviating_operators().contains(&"-"));
+/// assert!(report.extensions.contains(&"throw"));
+/// assert!(!report.extensions.contains(&"var"));
+/// ```
+pub fn compliance_report() -> ComplianceReport {
+    let native = DataLogic::new();
+    let mut spec = DataLogic::new();
+    spec.set_semantics(Semantics::JsonLogicSpec);
+
+    let mut deviations = Vec::new();
+    for (rule, data) in PROBES {
+        let rule: JsonValue = serde_json::from_str(rule).expect("probe rules are valid JSON");
+        let data: JsonValue = serde_json::from_str(data).expect("probe data is valid JSON");
+
+        let native_outcome = native.evaluate_json(&rule, &data, None);
+        let spec_outcome = spec.evaluate_json(&rule, &data, None);
+        if native_outcome != spec_outcome {
+            let operator = rule
+                .as_object()
+                .and_then(|rule| rule.keys().next())
+                .cloned()
+                .unwrap_or_default();
+            deviations.push(Deviation {
+                operator,
+                rule,
+                data,
+                native: native_outcome,
+                spec: spec_outcome,
+            });
+        }
+    }
+
+    let extensions = Capabilities::new(Vec::new())
+        .operators
+        .into_iter()
+        .filter(|operator| !SPEC_OPERATORS.contains(operator))
+        .collect();
+
+    ComplianceReport {
+        probes: PROBES.len(),
+        deviations,
+        extensions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogicError;
+
+    #[test]
+    fn test_spec_semantics() {
+        let mut dl = DataLogic::new();
+        dl.set_semantics(Semantics::JsonLogicSpec);
+        let data = json!({"o": {"k": 1}});
+
+        for (rule, expected) in [
+            (json!({"-": [5, 1, 1]}), json!(4)),
+            (json!({"/": [4]}), json!(null)),
+            (json!({"==": [null]}), json!(true)),
+            (json!({"==": [1]}), json!(false)),
+            (json!({"!==": [1]}), json!(true)),
+            (json!({"<": [-1]}), json!(false)),
+            (json!({"!": []}), json!(true)),
+            (json!({"!": [false, true]}), json!(true)),
+            (json!({"or": []}), json!(null)),
+            (json!({"max": []}), json!(null)),
+            (json!({"max": [1, "3"]}), json!(3)),
+            (json!({"min": ["a", 1]}), json!(null)),
+            (
+                json!({"cat": ["a", [1, null, 2], {"var": "o"}]}),
+                json!("a1,,2[object Object]"),
+            ),
+            (json!({"substr": [12]}), json!("12")),
+            (
+                json!({"map": [[1, 2], {"+": [{"var": ""}, 1]}, "ignored"]}),
+                json!([2, 3]),
+            ),
+            // Behavior shared with json-logic-js
+            (json!({"+": ["1", "x"]}), json!(null)),
+            (json!({"if": [false, 1, 2]}), json!(2)),
+        ] {
+            assert_eq!(
+                dl.evaluate_json(&rule, &data, None),
+                Ok(expected),
+                "{}",
+                rule
+            );
+        }
+        assert_eq!(
+            dl.evaluate_json(&json!({"*": []}), &data, None),
+            Err(LogicError::InvalidArgumentsError)
+        );
+
+        // Only the operators of the specification are built in
+        for rule in [
+            json!({"val": "o"}),
+            json!({"throw": "x"}),
+            json!({"now": []}),
+        ] {
+            assert!(matches!(
+                dl.evaluate_json(&rule, &data, None),
+                Err(LogicError::OperatorNotFoundError { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_compliance_report() {
+        let report = compliance_report();
+        assert_eq!(report.probes, PROBES.len());
+        assert!(!report.is_compliant());
+
+        let operators = report.deviating_operators();
+        for operator in ["-", "==", "max", "cat", "substr"] {
+            assert!(operators.contains(&operator), "{}", operator);
+        }
+        // Operators following the specification by default are not listed
+        for operator in ["var", "if", "missing", "merge", "in"] {
+            assert!(!operators.contains(&operator), "{}", operator);
+        }
+
+        let deviation = report
+            .deviations
+            .iter()
+            .find(|deviation| deviation.rule == json!({"-": [5, 1, 1]}))
+            .unwrap();
+        assert_eq!(deviation.native, Ok(json!(3)));
+        assert_eq!(deviation.spec, Ok(json!(4)));
+
+        assert!(report.extensions.contains(&"val"));
+        assert!(SPEC_OPERATORS
+            .iter()
+            .all(|operator| !report.extensions.contains(operator)));
+        assert_eq!(
+            report.to_json()["deviations"].as_array().unwrap().len(),
+            report.deviations.len()
+        );
+    }
+}
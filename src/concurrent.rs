@@ -0,0 +1,348 @@
+//! Concurrent evaluation of custom operators
+//!
+//! Custom operators backed by external services dominate the latency of the
+//! rules using them, and a rule evaluates them one after another. Calls that
+//! only depend on the data, such as three lookups joined by an `and`, can
+//! instead be made up front on a bounded set of threads. The evaluation then
+//! picks up the prefetched responses through operator overrides.
+//!
+//! The calls see the same arguments and data as they would in the
+//! evaluation, and their responses keep the host objects they refer to.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::arena::{panic_message, CustomOperator, CustomOperatorRegistry, DataArena};
+use crate::logic::{
+    evaluate_arguments, ArrayOp, ControlOp, LogicError, OperatorType, Result, Token,
+};
+use crate::value::{DataValue, NumberValue};
+
+/// A custom operator call whose arguments only depend on the data
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PendingCall<'a> {
+    /// The name the operator is registered under
+    pub(crate) name: String,
+    /// The evaluated arguments
+    pub(crate) args: &'a [DataValue<'a>],
+}
+
+impl PendingCall<'_> {
+    /// Returns the key the response of the call is looked up by.
+    ///
+    /// Unlike their JSON form, the debug form of the arguments tells host
+    /// objects apart.
+    pub(crate) fn key(&self) -> String {
+        call_key(self.args)
+    }
+}
+
+/// Returns the key of a call with the given arguments.
+fn call_key(args: &[DataValue]) -> String {
+    format!("{args:?}")
+}
+
+/// An owned copy of a response, holding the host objects it refers to
+#[derive(Debug, Clone)]
+pub(crate) enum Response {
+    Null,
+    Bool(bool),
+    Number(NumberValue),
+    String(String),
+    Array(Vec<Response>),
+    Object(Vec<(String, Response)>),
+    DateTime(DateTime<Utc>),
+    Duration(Duration),
+    Host(Arc<dyn Any + Send + Sync>),
+}
+
+impl Response {
+    /// Copies a value out of the arena it was allocated in.
+    ///
+    /// Stale host handles are copied as null.
+    fn copy(value: &DataValue, arena: &DataArena) -> Self {
+        match value {
+            DataValue::Null => Response::Null,
+            DataValue::Bool(b) => Response::Bool(*b),
+            DataValue::Number(n) => Response::Number(*n),
+            DataValue::String(s) => Response::String(s.to_string()),
+            DataValue::Array(items) => {
+                Response::Array(items.iter().map(|item| Self::copy(item, arena)).collect())
+            }
+            DataValue::Object(entries) => Response::Object(
+                entries
+                    .iter()
+                    .map(|(key, item)| (key.to_string(), Self::copy(item, arena)))
+                    .collect(),
+            ),
+            DataValue::DateTime(dt) => Response::DateTime(*dt),
+            DataValue::Duration(d) => Response::Duration(*d),
+            DataValue::Custom(handle) => arena
+                .shared_host_object(*handle)
+                .map_or(Response::Null, Response::Host),
+        }
+    }
+
+    /// Allocates the response in an arena, sharing its host objects with it.
+    fn to_value<'a>(&self, arena: &'a DataArena) -> DataValue<'a> {
+        match self {
+            Response::Null => DataValue::Null,
+            Response::Bool(b) => DataValue::Bool(*b),
+            Response::Number(n) => DataValue::Number(*n),
+            Response::String(s) => DataValue::String(arena.alloc_str(s)),
+            Response::Array(items) => {
+                let items: Vec<DataValue<'a>> =
+                    items.iter().map(|item| item.to_value(arena)).collect();
+                DataValue::Array(arena.alloc_data_value_slice(&items))
+            }
+            Response::Object(entries) => {
+                let entries: Vec<(&'a str, DataValue<'a>)> = entries
+                    .iter()
+                    .map(|(key, item)| (arena.intern_str(key), item.to_value(arena)))
+                    .collect();
+                DataValue::Object(arena.alloc_object_entries(&entries))
+            }
+            Response::DateTime(dt) => DataValue::DateTime(*dt),
+            Response::Duration(d) => DataValue::Duration(*d),
+            Response::Host(object) => {
+                DataValue::Custom(arena.alloc_shared_host_object(Arc::clone(object)))
+            }
+        }
+    }
+}
+
+/// Returns true if an operator evaluates some of its arguments in another scope.
+///
+/// Only the first argument of such operators is evaluated against the data.
+fn is_scoped(op_type: OperatorType) -> bool {
+    matches!(
+        op_type,
+        OperatorType::Array(
+            ArrayOp::Map
+                | ArrayOp::Filter
+                | ArrayOp::Reduce
+                | ArrayOp::All
+                | ArrayOp::Some
+                | ArrayOp::None
+                | ArrayOp::Sort
+                | ArrayOp::GroupBy
+                | ArrayOp::Sum
+                | ArrayOp::Avg
+                | ArrayOp::Count
+                | ArrayOp::MinBy
+                | ArrayOp::MaxBy
+        ) | OperatorType::Try
+            | OperatorType::Validate
+    )
+}
+
+/// Returns the argument tokens of an operator.
+fn operator_args<'a>(args: &'a Token<'a>) -> Vec<&'a Token<'a>> {
+    match args {
+        Token::ArrayLiteral(items) => items.clone(),
+        Token::Literal(_) => Vec::new(),
+        _ => vec![args],
+    }
+}
+
+/// Checks if a token tree contains a custom operator.
+fn has_custom_operator(token: &Token) -> bool {
+    match token {
+        Token::Literal(_) => false,
+        Token::ArrayLiteral(items) => items.iter().any(|item| has_custom_operator(item)),
+        Token::Variable { default, .. } => default.is_some_and(has_custom_operator),
+        Token::DynamicVariable { path_expr, default } => {
+            has_custom_operator(path_expr) || default.is_some_and(has_custom_operator)
+        }
+        Token::CustomOperator { .. } => true,
+        Token::Operator { args, .. }
+        | Token::CompiledMatch { args, .. }
+        | Token::CompiledRegex { args, .. }
+        | Token::CaseFolded { args, .. } => has_custom_operator(args),
+    }
+}
+
+/// Returns true if the argument at `index` of an `if` is a branch.
+///
+/// Conditions are at even positions, except for a trailing else branch.
+fn is_if_branch(index: usize, len: usize) -> bool {
+    index % 2 == 1 || index + 1 == len
+}
+
+/// Collects the custom operator calls that can be made before evaluation.
+///
+/// A call qualifies when it is evaluated against the data rather than inside
+/// an iterator, and its arguments contain no other custom operator. Calls in
+/// the branches of an `if` only qualify when `speculative` is set.
+pub(crate) fn independent_calls<'a>(
+    token: &'a Token<'a>,
+    speculative: bool,
+    calls: &mut Vec<&'a Token<'a>>,
+) {
+    match token {
+        Token::Literal(_) => {}
+        Token::ArrayLiteral(items) => {
+            for item in items {
+                independent_calls(item, speculative, calls);
+            }
+        }
+        Token::Variable { default, .. } => {
+            if let Some(default) = default {
+                independent_calls(default, speculative, calls);
+            }
+        }
+        Token::DynamicVariable { path_expr, default } => {
+            independent_calls(path_expr, speculative, calls);
+            if let Some(default) = default {
+                independent_calls(default, speculative, calls);
+            }
+        }
+        Token::CustomOperator { args, .. } => {
+            if has_custom_operator(args) {
+                independent_calls(args, speculative, calls);
+            } else {
+                calls.push(token);
+            }
+        }
+        Token::Operator { op_type, args }
+        | Token::CompiledMatch { op_type, args, .. }
+        | Token::CompiledRegex { op_type, args, .. }
+        | Token::CaseFolded { op_type, args } => {
+            let args = operator_args(args);
+            let len = args.len();
+            for (index, arg) in args.into_iter().enumerate() {
+                if is_scoped(*op_type) && index > 0 {
+                    break;
+                }
+                if *op_type == OperatorType::Control(ControlOp::If)
+                    && !speculative
+                    && is_if_branch(index, len)
+                {
+                    continue;
+                }
+                independent_calls(arg, speculative, calls);
+            }
+        }
+    }
+}
+
+/// Evaluates the arguments of a call against the current context.
+pub(crate) fn pending_call<'a>(
+    token: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Option<PendingCall<'a>> {
+    let Token::CustomOperator { name, args } = token else {
+        return None;
+    };
+    // Calls whose arguments fail are left to the evaluation to report
+    let args = evaluate_arguments(args, arena).ok()?;
+    Some(PendingCall {
+        name: name.to_string(),
+        args,
+    })
+}
+
+/// Makes a call in its own arena, against the data of the evaluation.
+fn make_call<'a>(
+    registry: &CustomOperatorRegistry,
+    call: &PendingCall<'a>,
+    data: &'a DataValue<'a>,
+    arena: &'a DataArena,
+    isolate: bool,
+) -> Result<Response> {
+    arena.set_root_context(data);
+    arena.set_current_context(data, arena.alloc(DataValue::String("$")));
+    let evaluate = || {
+        registry
+            .evaluate(&call.name, call.args, arena)
+            .map(|value| Response::copy(value, arena))
+    };
+
+    if !isolate {
+        return evaluate();
+    }
+    panic::catch_unwind(AssertUnwindSafe(evaluate)).unwrap_or_else(|payload| {
+        Err(LogicError::OperatorPanicked {
+            operator: call.name.clone(),
+            message: panic_message(payload.as_ref()),
+        })
+    })
+}
+
+/// Makes the calls on at most `max_parallelism` threads.
+///
+/// The arguments and the data are read in place, as they are not changed
+/// until every call returns. Returns the responses in the order of the calls.
+pub(crate) fn run_calls(
+    registry: &CustomOperatorRegistry,
+    calls: &[PendingCall],
+    data: &DataValue,
+    max_parallelism: usize,
+    isolate: bool,
+) -> Vec<Result<Response>> {
+    let next = AtomicUsize::new(0);
+    let workers = max_parallelism.clamp(1, calls.len().max(1));
+
+    let mut responses: Vec<(usize, Result<Response>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut arena = DataArena::new();
+                    let mut responses = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(call) = calls.get(index) else {
+                            break;
+                        };
+                        responses.push((index, make_call(registry, call, data, &arena, isolate)));
+                        arena.reset();
+                    }
+                    responses
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+            })
+            .collect()
+    });
+
+    responses.sort_by_key(|(index, _)| *index);
+    responses
+        .into_iter()
+        .map(|(_, response)| response)
+        .collect()
+}
+
+/// An operator answering prefetched calls
+///
+/// Calls that were not prefetched, such as those inside iterators, are
+/// passed on to the registered operator.
+#[derive(Debug)]
+pub(crate) struct Prefetched {
+    pub(crate) name: String,
+    pub(crate) responses: HashMap<String, Result<Response>>,
+}
+
+impl CustomOperator for Prefetched {
+    fn evaluate<'a>(
+        &self,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        match self.responses.get(&call_key(args)) {
+            Some(Ok(response)) => Ok(arena.alloc(response.to_value(arena))),
+            Some(Err(error)) => Err(error.clone()),
+            None => arena.evaluate_registered_operator(&self.name, args),
+        }
+    }
+}
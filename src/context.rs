@@ -0,0 +1,126 @@
+//! Per-evaluation regional settings
+//!
+//! An evaluation context carries the locale and time zone of a single
+//! evaluation, so services handling several regions can share one engine.
+//! The date operators fall back to the context when a rule does not give
+//! these settings explicitly:
+//!
+//! - `format_date` formats dates in the context time zone, with `%B` month
+//!   names in the context locale.
+//! - `parse_date` reads month names in the context locale, and dates without
+//!   a UTC offset in the context time zone.
+//! - `age_years`, `is_anniversary` and `in_schedule` use the context time
+//!   zone when no time zone is given.
+//!
+//! With the `tz` feature, the time zone can be an IANA time zone such as
+//! `Europe/Berlin`, whose offset follows daylight saving time.
+//!
+//! Month names in locales without translations are left in English. The
+//! crate has no number formatting or collation operators, so the locale only
+//! applies to dates.
+//!
+//! Custom operators can read the context with
+//! [`DataArena::evaluation_context`](crate::arena::DataArena::evaluation_context).
+
+use chrono::FixedOffset;
+
+/// The locale and time zone of an evaluation
+///
+/// Time zones are fixed UTC offsets, or with the `tz` feature, IANA time zones
+/// following their daylight saving time rules.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::{DataLogic, EvaluationContext};
+/// use chrono::FixedOffset;
+///
+/// let dl = DataLogic::new();
+/// let rule = dl
+///     .parse_logic(r#"{"format_date": [{"var": "at"}, "%d %B %Y %H:%M"]}"#, None)
+///     .unwrap();
+/// let data = dl.parse_data(r#"{"at": "2024-12-31T23:30:00Z"}"#).unwrap();
+///
+/// let paris = EvaluationContext::new()
+///     .with_locale("fr-FR")
+///     .with_timezone(FixedOffset::east_opt(3600).unwrap());
+/// let result = dl.evaluate_with_context(&rule, &data, &paris).unwrap();
+/// assert_eq!(result.as_str(), Some("01 janvier 2025 00:30"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvaluationContext {
+    locale: Option<String>,
+    timezone: Option<FixedOffset>,
+    #[cfg(feature = "tz")]
+    named_timezone: Option<chrono_tz::Tz>,
+}
+
+impl EvaluationContext {
+    /// Create a context without regional settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the locale, a language tag such as `"fr"` or `"de-CH"`
+    pub fn with_locale(mut self, locale: &str) -> Self {
+        self.locale = Some(locale.to_string());
+        self
+    }
+
+    /// Set the time zone, replacing any time zone set before
+    pub fn with_timezone(mut self, timezone: FixedOffset) -> Self {
+        self.timezone = Some(timezone);
+        #[cfg(feature = "tz")]
+        {
+            self.named_timezone = None;
+        }
+        self
+    }
+
+    /// Set an IANA time zone, replacing any time zone set before
+    ///
+    /// Unlike a fixed offset, the offset of a named time zone follows its
+    /// daylight saving time rules at each date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, EvaluationContext};
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl
+    ///     .parse_logic(r#"{"format_date": [{"var": "at"}, "%H:%M"]}"#, None)
+    ///     .unwrap();
+    /// let berlin = EvaluationContext::new().with_named_timezone(chrono_tz::Europe::Berlin);
+    ///
+    /// let winter = dl.parse_data(r#"{"at": "2024-01-15T12:00:00Z"}"#).unwrap();
+    /// let result = dl.evaluate_with_context(&rule, &winter, &berlin).unwrap();
+    /// assert_eq!(result.as_str(), Some("13:00"));
+    ///
+    /// let summer = dl.parse_data(r#"{"at": "2024-07-15T12:00:00Z"}"#).unwrap();
+    /// let result = dl.evaluate_with_context(&rule, &summer, &berlin).unwrap();
+    /// assert_eq!(result.as_str(), Some("14:00"));
+    /// ```
+    #[cfg(feature = "tz")]
+    pub fn with_named_timezone(mut self, timezone: chrono_tz::Tz) -> Self {
+        self.named_timezone = Some(timezone);
+        self.timezone = None;
+        self
+    }
+
+    /// Get the locale
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Get the time zone, if it is a fixed offset
+    pub fn timezone(&self) -> Option<FixedOffset> {
+        self.timezone
+    }
+
+    /// Get the time zone, if it is an IANA time zone
+    #[cfg(feature = "tz")]
+    pub fn named_timezone(&self) -> Option<chrono_tz::Tz> {
+        self.named_timezone
+    }
+}
@@ -0,0 +1,775 @@
+//! Random data generation for rule testing
+//!
+//! A [`DataGenerator`] produces random data documents matching a [`Schema`],
+//! biased towards the edge cases rules tend to get wrong: nulls, missing
+//! fields, empty strings and arrays, and numbers on either side of the
+//! constants a rule compares against. The schema is either inferred from the
+//! rule itself with [`Schema::infer`], or given as a subset of JSON Schema
+//! with [`Schema::from_json`].
+//!
+//! [`DataLogic::fuzz`](crate::DataLogic::fuzz) evaluates a rule against
+//! generated documents through each engine path and reports where they
+//! disagree.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::lint::{as_operator, is_annotation};
+use crate::logic::{LogicError, Result};
+
+/// The largest magnitude of generated numbers without bounds
+const DEFAULT_RANGE: f64 = 1000.0;
+
+/// The most items generated for an array
+const MAX_ARRAY_LEN: usize = 5;
+
+/// The deepest nesting generated for values of any type
+const MAX_ANY_DEPTH: usize = 2;
+
+/// The shape of a data document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    /// Any value
+    Any,
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Boolean,
+    /// A number
+    Number {
+        /// Whether the number is an integer
+        integer: bool,
+        /// The smallest allowed value
+        minimum: Option<f64>,
+        /// The largest allowed value
+        maximum: Option<f64>,
+        /// Values around which off-by-one mistakes are likely, such as the
+        /// constants a rule compares against
+        boundaries: Vec<f64>,
+    },
+    /// A string
+    String {
+        /// Strings that are meaningful to the rule, generated often
+        examples: Vec<String>,
+    },
+    /// An array of items sharing a schema
+    Array(Box<Schema>),
+    /// An object with the given properties
+    Object(BTreeMap<String, Schema>),
+    /// One of a fixed set of values
+    Enum(Vec<JsonValue>),
+}
+
+impl Schema {
+    /// Creates a schema for numbers without bounds.
+    pub fn number() -> Self {
+        Schema::Number {
+            integer: false,
+            minimum: None,
+            maximum: None,
+            boundaries: Vec::new(),
+        }
+    }
+
+    /// Creates a schema for strings without examples.
+    pub fn string() -> Self {
+        Schema::String {
+            examples: Vec::new(),
+        }
+    }
+
+    /// Infers the schema of the data read by a JSONLogic rule
+    ///
+    /// Every `var` path of the rule becomes a property, typed by how the rule
+    /// uses it: operands of arithmetic are numbers, operands of string
+    /// operators are strings, inputs of iterators are arrays, and so on.
+    /// Constants compared against a path become boundaries or examples.
+    /// Paths used in conflicting ways, or not at all, are [`Schema::Any`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::datagen::Schema;
+    /// use serde_json::json;
+    ///
+    /// let schema = Schema::infer(&json!({"and": [
+    ///     {">=": [{"var": "user.age"}, 18]},
+    ///     {"==": [{"var": "user.country"}, "NL"]}
+    /// ]}));
+    ///
+    /// let Schema::Object(properties) = &schema else { panic!() };
+    /// let Schema::Object(user) = &properties["user"] else { panic!() };
+    /// assert!(matches!(&user["age"], Schema::Number { boundaries, .. } if boundaries == &[18.0]));
+    /// assert!(matches!(&user["country"], Schema::String { examples } if examples == &["NL"]));
+    /// ```
+    pub fn infer(rule: &JsonValue) -> Self {
+        let mut schema = Schema::Object(BTreeMap::new());
+        infer_node(rule, &mut schema);
+        schema
+    }
+
+    /// Parses a schema from a subset of JSON Schema
+    ///
+    /// Supports `type` (including lists of types), `properties`, `items`,
+    /// `enum`, `minimum`, `maximum` and `examples`.
+    pub fn from_json(schema: &JsonValue) -> Result<Self> {
+        let invalid = |reason: &str| LogicError::ParseError {
+            reason: format!("Invalid schema: {}", reason),
+        };
+
+        let JsonValue::Object(schema) = schema else {
+            return match schema {
+                JsonValue::Bool(true) => Ok(Schema::Any),
+                _ => Err(invalid("expected an object")),
+            };
+        };
+
+        if let Some(values) = schema.get("enum") {
+            let values = values
+                .as_array()
+                .ok_or_else(|| invalid("enum must be an array"))?;
+            return Ok(Schema::Enum(values.clone()));
+        }
+
+        let examples = schema
+            .get("examples")
+            .and_then(JsonValue::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let bound = |name: &str| -> Result<Option<f64>> {
+            match schema.get(name) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_f64()
+                    .map(Some)
+                    .ok_or_else(|| invalid(&format!("{} must be a number", name))),
+            }
+        };
+
+        let single = |kind: &str| -> Result<Schema> {
+            Ok(match kind {
+                "null" => Schema::Null,
+                "boolean" => Schema::Boolean,
+                "number" | "integer" => Schema::Number {
+                    integer: kind == "integer",
+                    minimum: bound("minimum")?,
+                    maximum: bound("maximum")?,
+                    boundaries: examples.iter().filter_map(JsonValue::as_f64).collect(),
+                },
+                "string" => Schema::String {
+                    examples: examples
+                        .iter()
+                        .filter_map(|example| example.as_str().map(str::to_string))
+                        .collect(),
+                },
+                "array" => Schema::Array(Box::new(match schema.get("items") {
+                    Some(items) => Schema::from_json(items)?,
+                    None => Schema::Any,
+                })),
+                "object" => {
+                    let mut properties = BTreeMap::new();
+                    if let Some(JsonValue::Object(fields)) = schema.get("properties") {
+                        for (name, field) in fields {
+                            properties.insert(name.clone(), Schema::from_json(field)?);
+                        }
+                    }
+                    Schema::Object(properties)
+                }
+                other => return Err(invalid(&format!("unknown type '{}'", other))),
+            })
+        };
+
+        match schema.get("type") {
+            None => Ok(Schema::Any),
+            Some(JsonValue::String(kind)) => single(kind),
+            // Nullable types are the only unions the generator needs
+            Some(JsonValue::Array(kinds)) => {
+                let kinds: Vec<&str> = kinds.iter().filter_map(JsonValue::as_str).collect();
+                match kinds.as_slice() {
+                    [kind] | ["null", kind] | [kind, "null"] => single(kind),
+                    _ => Ok(Schema::Any),
+                }
+            }
+            Some(_) => Err(invalid("type must be a string or an array")),
+        }
+    }
+
+    /// Combines the schema of two uses of the same path.
+    fn merge(&mut self, other: Schema) {
+        *self = match (std::mem::replace(self, Schema::Any), other) {
+            (Schema::Any, other) => other,
+            (current, Schema::Any) => current,
+            (
+                Schema::Number {
+                    integer,
+                    minimum,
+                    maximum,
+                    mut boundaries,
+                },
+                Schema::Number {
+                    integer: other_integer,
+                    boundaries: other_boundaries,
+                    ..
+                },
+            ) => {
+                for boundary in other_boundaries {
+                    if !boundaries.contains(&boundary) {
+                        boundaries.push(boundary);
+                    }
+                }
+                Schema::Number {
+                    integer: integer && other_integer,
+                    minimum,
+                    maximum,
+                    boundaries,
+                }
+            }
+            (
+                Schema::String { mut examples },
+                Schema::String {
+                    examples: other_examples,
+                },
+            ) => {
+                for example in other_examples {
+                    if !examples.contains(&example) {
+                        examples.push(example);
+                    }
+                }
+                Schema::String { examples }
+            }
+            (Schema::Array(mut items), Schema::Array(other_items)) => {
+                items.merge(*other_items);
+                Schema::Array(items)
+            }
+            (Schema::Object(mut properties), Schema::Object(other_properties)) => {
+                for (name, schema) in other_properties {
+                    properties.entry(name).or_insert(Schema::Any).merge(schema);
+                }
+                Schema::Object(properties)
+            }
+            (current, other) if current == other => current,
+            _ => Schema::Any,
+        };
+    }
+
+    /// Merges `schema` into the property at a dotted path.
+    fn insert_path(&mut self, path: &str, schema: Schema) {
+        let mut nested = schema;
+        for segment in path.rsplit('.') {
+            nested = if segment.parse::<usize>().is_ok() {
+                Schema::Array(Box::new(nested))
+            } else {
+                Schema::Object(BTreeMap::from([(segment.to_string(), nested)]))
+            };
+        }
+        // Numeric segments describe arrays, so the root stays an object
+        if let Schema::Object(_) = nested {
+            self.merge(nested);
+        }
+    }
+}
+
+/// Returns the path of a `var` expression with a constant path.
+fn var_path(value: &JsonValue) -> Option<&str> {
+    let object = value.as_object()?;
+    let path = match object.get("var")? {
+        JsonValue::Array(args) => args.first()?,
+        path => path,
+    };
+    match path {
+        JsonValue::String(path) if !path.is_empty() && object.len() == 1 => Some(path),
+        _ => None,
+    }
+}
+
+/// Returns the schema implied by a literal.
+fn literal_schema(value: &JsonValue) -> Schema {
+    match value {
+        JsonValue::Null => Schema::Any,
+        JsonValue::Bool(_) => Schema::Boolean,
+        JsonValue::Number(n) => Schema::Number {
+            integer: n.is_i64() || n.is_u64(),
+            minimum: None,
+            maximum: None,
+            boundaries: n.as_f64().into_iter().collect(),
+        },
+        JsonValue::String(s) => Schema::String {
+            examples: vec![s.clone()],
+        },
+        JsonValue::Array(_) => Schema::Array(Box::new(Schema::Any)),
+        JsonValue::Object(_) => Schema::Any,
+    }
+}
+
+/// Returns the schema the operator `op` expects of the argument at `index`.
+///
+/// Constants among the other arguments type comparisons.
+fn argument_schema(op: &str, index: usize, args: &[JsonValue]) -> Schema {
+    let literal_sibling = || {
+        args.iter()
+            .enumerate()
+            .filter(|(i, arg)| *i != index && !arg.is_object())
+            .map(|(_, arg)| literal_schema(arg))
+            .next()
+            .unwrap_or(Schema::Any)
+    };
+    match op {
+        "+" | "-" | "*" | "/" | "%" | "min" | "max" | "abs" | "ceil" | "floor" | "pow" | "**"
+        | "sqrt" | "ln" | "log10" | "round" | "trunc" => Schema::number(),
+        "<" | "<=" | ">" | ">=" => match literal_sibling() {
+            constant @ (Schema::String { .. } | Schema::Number { .. }) => constant,
+            _ => Schema::number(),
+        },
+        "==" | "!=" | "===" | "!==" => literal_sibling(),
+        "cat" | "substr" | "upper" | "lower" | "trim" | "starts_with" | "ends_with" | "split" => {
+            if index == 0 || op == "cat" {
+                Schema::string()
+            } else {
+                Schema::Any
+            }
+        }
+        "replace" if index == 0 => Schema::string(),
+        "regex" | "regex_replace" if index == 1 => Schema::string(),
+        "map" | "filter" | "reduce" | "all" | "some" | "none" | "sort" if index == 0 => {
+            Schema::Array(Box::new(Schema::Any))
+        }
+        "merge" => Schema::Array(Box::new(Schema::Any)),
+        "in" if index == 1 => match args.first() {
+            Some(JsonValue::String(_)) => Schema::string(),
+            _ => Schema::Array(Box::new(literal_schema(&args[0]))),
+        },
+        "in" if index == 0 => match args.get(1) {
+            Some(JsonValue::Array(items)) => Schema::Enum(items.clone()),
+            _ => Schema::Any,
+        },
+        _ => Schema::Any,
+    }
+}
+
+/// Collects the paths read by a rule into `schema`.
+fn infer_node(node: &JsonValue, schema: &mut Schema) {
+    match node {
+        JsonValue::Array(items) => {
+            for item in items {
+                infer_node(item, schema);
+            }
+        }
+        JsonValue::Object(object) if object.len() == 1 => {
+            let (op, args) = object.iter().next().expect("object has one entry");
+            // Preserved values are data rather than rules
+            if op == "preserve" {
+                return;
+            }
+            // Notes of annotations are not rules
+            if is_annotation(op) {
+                if let Some((_, args)) = as_operator(node) {
+                    if let Some(rule) = args.get(1) {
+                        infer_node(rule, schema);
+                    }
+                }
+                return;
+            }
+            if op == "var" {
+                if let Some(path) = var_path(node) {
+                    schema.insert_path(path, Schema::Any);
+                }
+            }
+            let args = match args {
+                JsonValue::Array(args) => args.as_slice(),
+                arg => std::slice::from_ref(arg),
+            };
+            for (index, arg) in args.iter().enumerate() {
+                if let Some(path) = var_path(arg) {
+                    schema.insert_path(path, argument_schema(op, index, args));
+                }
+                // Iterator bodies read the items rather than the data
+                let scoped = matches!(
+                    op.as_str(),
+                    "map" | "filter" | "reduce" | "all" | "some" | "none" | "sort"
+                );
+                if !(scoped && index > 0) {
+                    infer_node(arg, schema);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A small deterministic pseudo-random number generator (SplitMix64)
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound.max(1) as u64) as usize
+    }
+
+    /// Returns a number in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns true with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        self.unit() < probability
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// A generator of random data documents
+///
+/// Generation is deterministic for a given seed, so a failing document can
+/// be reproduced. Iterating over the generator yields documents forever.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::datagen::{DataGenerator, Schema};
+/// use serde_json::json;
+///
+/// let schema = Schema::from_json(&json!({
+///     "type": "object",
+///     "properties": {
+///         "qty": {"type": "integer", "minimum": 0, "maximum": 10},
+///         "tags": {"type": "array", "items": {"type": "string"}}
+///     }
+/// }))
+/// .unwrap();
+///
+/// for document in DataGenerator::new(schema).with_seed(7).take(20) {
+///     if let Some(qty) = document.get("qty").and_then(|qty| qty.as_i64()) {
+///         assert!((0..=10).contains(&qty));
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataGenerator {
+    schema: Schema,
+    rng: Rng,
+    edge_case_rate: f64,
+}
+
+impl DataGenerator {
+    /// Creates a generator with a fixed seed and an edge case rate of 0.25.
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            schema,
+            rng: Rng(0),
+            edge_case_rate: 0.25,
+        }
+    }
+
+    /// Creates a generator for the data read by a rule.
+    ///
+    /// See [`Schema::infer`].
+    pub fn for_rule(rule: &JsonValue) -> Self {
+        Self::new(Schema::infer(rule))
+    }
+
+    /// Sets the seed of the generator.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng(seed);
+        self
+    }
+
+    /// Sets the probability of generating an edge case for each value.
+    pub fn with_edge_case_rate(mut self, rate: f64) -> Self {
+        self.edge_case_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns the schema of the generated documents.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Generates a document.
+    pub fn generate(&mut self) -> JsonValue {
+        let schema = std::mem::replace(&mut self.schema, Schema::Any);
+        let document = self.value(&schema, 0);
+        self.schema = schema;
+        document
+    }
+
+    fn edge_case(&mut self) -> bool {
+        self.rng.chance(self.edge_case_rate)
+    }
+
+    fn value(&mut self, schema: &Schema, depth: usize) -> JsonValue {
+        // The root stays an object so the rule can read its fields
+        if depth > 0 && !matches!(schema, Schema::Null | Schema::Enum(_)) && self.edge_case() {
+            if self.rng.chance(0.5) {
+                return JsonValue::Null;
+            }
+            return self.edge_value(schema, depth);
+        }
+
+        match schema {
+            Schema::Any => self.any(depth),
+            Schema::Null => JsonValue::Null,
+            Schema::Boolean => JsonValue::Bool(self.rng.chance(0.5)),
+            Schema::Number {
+                integer,
+                minimum,
+                maximum,
+                boundaries,
+            } => {
+                // Boundaries are the interesting values, so favour them
+                if !boundaries.is_empty() && self.rng.chance(0.5) {
+                    let boundary = *self.rng.pick(boundaries);
+                    let offset = *self.rng.pick(&[-1.0, 0.0, 1.0]);
+                    return self.number(boundary + offset, *integer, *minimum, *maximum);
+                }
+                let low = minimum.unwrap_or(-DEFAULT_RANGE);
+                let high = maximum.unwrap_or(DEFAULT_RANGE).max(low);
+                let value = low + self.rng.unit() * (high - low);
+                self.number(value, *integer, *minimum, *maximum)
+            }
+            Schema::String { examples } => {
+                if !examples.is_empty() && self.rng.chance(0.5) {
+                    return JsonValue::String(self.rng.pick(examples).clone());
+                }
+                let len = 1 + self.rng.below(12);
+                let text = (0..len)
+                    .map(|_| {
+                        *self
+                            .rng
+                            .pick(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJ0123456789 -_")
+                            as char
+                    })
+                    .collect();
+                JsonValue::String(text)
+            }
+            Schema::Array(items) => {
+                let len = 1 + self.rng.below(MAX_ARRAY_LEN);
+                JsonValue::Array((0..len).map(|_| self.value(items, depth + 1)).collect())
+            }
+            Schema::Object(properties) => {
+                let mut object = Map::new();
+                for (name, property) in properties {
+                    // Missing fields are an edge case of their own
+                    if self.edge_case() && self.rng.chance(0.5) {
+                        continue;
+                    }
+                    object.insert(name.clone(), self.value(property, depth + 1));
+                }
+                JsonValue::Object(object)
+            }
+            Schema::Enum(values) if values.is_empty() => JsonValue::Null,
+            Schema::Enum(values) => self.rng.pick(values).clone(),
+        }
+    }
+
+    /// Generates the edge case of a schema, such as an empty array.
+    fn edge_value(&mut self, schema: &Schema, depth: usize) -> JsonValue {
+        match schema {
+            Schema::Number {
+                integer,
+                minimum,
+                maximum,
+                ..
+            } => {
+                let mut candidates = vec![0.0, -1.0, 1.0];
+                candidates.extend(minimum.iter().chain(maximum.iter()));
+                if !integer {
+                    candidates.push(0.5);
+                }
+                let value = *self.rng.pick(&candidates);
+                self.number(value, *integer, *minimum, *maximum)
+            }
+            Schema::String { .. } => JsonValue::String(String::new()),
+            Schema::Array(_) => JsonValue::Array(Vec::new()),
+            Schema::Object(_) => JsonValue::Object(Map::new()),
+            Schema::Boolean => JsonValue::Bool(false),
+            Schema::Any => self.any(depth),
+            Schema::Null | Schema::Enum(_) => self.value(schema, depth),
+        }
+    }
+
+    /// Generates a value of a random type.
+    fn any(&mut self, depth: usize) -> JsonValue {
+        let kinds = if depth >= MAX_ANY_DEPTH { 4 } else { 5 };
+        let schema = match self.rng.below(kinds) {
+            0 => Schema::Null,
+            1 => Schema::Boolean,
+            2 => Schema::number(),
+            3 => Schema::string(),
+            _ => Schema::Array(Box::new(Schema::Any)),
+        };
+        self.value(&schema, depth + 1)
+    }
+
+    fn number(
+        &mut self,
+        value: f64,
+        integer: bool,
+        minimum: Option<f64>,
+        maximum: Option<f64>,
+    ) -> JsonValue {
+        let mut value = value;
+        if let Some(minimum) = minimum {
+            value = value.max(minimum);
+        }
+        if let Some(maximum) = maximum {
+            value = value.min(maximum);
+        }
+        if integer {
+            return JsonValue::from(value.round() as i64);
+        }
+        // Keep the numbers readable when a document is reported
+        let value = (value * 100.0).round() / 100.0;
+        Number::from_f64(value).map_or(JsonValue::Null, JsonValue::Number)
+    }
+}
+
+impl Iterator for DataGenerator {
+    type Item = JsonValue;
+
+    fn next(&mut self) -> Option<JsonValue> {
+        Some(self.generate())
+    }
+}
+
+/// A document on which the engine paths disagree
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// The generated document
+    pub data: JsonValue,
+    /// The result of evaluating the rule as parsed, without optimization
+    pub interpreted: Result<JsonValue>,
+    /// The result of evaluating the optimized rule
+    pub optimized: Result<JsonValue>,
+    /// The result of evaluating the rule as a [`CompiledRule`](crate::CompiledRule)
+    pub compiled: Result<JsonValue>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataLogic;
+    use serde_json::json;
+
+    #[test]
+    fn test_infer_schema() {
+        let rule = json!({"and": [
+            {">": [{"var": "order.total"}, 100]},
+            {"in": [{"var": "order.country"}, ["NL", "BE"]]},
+            {"some": [{"var": "order.items"}, {">": [{"var": "qty"}, 1]}]},
+            {"starts_with": [{"var": "code"}, "X-"]},
+            {"==": [{"var": "code"}, 5]},
+            {"!": {"preserve": {"var": "template"}}},
+            {"//": [{"var": "note"}, {"<": [{"var": "score"}, 10]}]}
+        ]});
+
+        let Schema::Object(properties) = Schema::infer(&rule) else {
+            panic!("root is not an object");
+        };
+        let Schema::Object(order) = &properties["order"] else {
+            panic!("order is not an object");
+        };
+        assert_eq!(
+            order["total"],
+            Schema::Number {
+                integer: true,
+                minimum: None,
+                maximum: None,
+                boundaries: vec![100.0],
+            }
+        );
+        assert_eq!(
+            order["country"],
+            Schema::Enum(vec![json!("NL"), json!("BE")])
+        );
+        assert_eq!(order["items"], Schema::Array(Box::new(Schema::Any)));
+        // The body of `some` reads the items, not the data
+        assert!(!properties.contains_key("qty"));
+        // Preserved values are data rather than rules
+        assert!(!properties.contains_key("template"));
+        // Notes of annotations are not rules
+        assert!(!properties.contains_key("note"));
+        assert!(matches!(properties["score"], Schema::Number { .. }));
+        // Used as both a string and a number
+        assert_eq!(properties["code"], Schema::Any);
+    }
+
+    #[test]
+    fn test_generate_from_schema() {
+        let schema = Schema::from_json(&json!({
+            "type": "object",
+            "properties": {
+                "age": {"type": "integer", "minimum": 0, "maximum": 120, "examples": [18]},
+                "name": {"type": ["string", "null"]},
+                "plan": {"enum": ["free", "pro"]},
+                "scores": {"type": "array", "items": {"type": "number"}}
+            }
+        }))
+        .unwrap();
+
+        let documents: Vec<JsonValue> = DataGenerator::new(schema.clone())
+            .with_seed(42)
+            .take(500)
+            .collect();
+        let again: Vec<JsonValue> = DataGenerator::new(schema).with_seed(42).take(500).collect();
+        assert_eq!(documents, again);
+
+        let mut seen = BTreeMap::<&str, usize>::new();
+        for document in &documents {
+            let object = document.as_object().unwrap();
+            match object.get("age") {
+                None => *seen.entry("missing age").or_default() += 1,
+                Some(JsonValue::Null) => *seen.entry("null age").or_default() += 1,
+                Some(age) => {
+                    let age = age.as_i64().unwrap();
+                    assert!((0..=120).contains(&age));
+                    if (17..=19).contains(&age) {
+                        *seen.entry("boundary age").or_default() += 1;
+                    }
+                }
+            }
+            if let Some(plan) = object.get("plan") {
+                assert!(plan == "free" || plan == "pro");
+            }
+            match object.get("scores") {
+                Some(JsonValue::Array(scores)) if scores.is_empty() => {
+                    *seen.entry("empty scores").or_default() += 1
+                }
+                Some(JsonValue::Array(scores)) => {
+                    assert!(scores.iter().all(|s| s.is_number() || s.is_null()))
+                }
+                _ => {}
+            }
+        }
+        for case in ["missing age", "null age", "boundary age", "empty scores"] {
+            assert!(seen.get(case).copied().unwrap_or_default() > 0, "{}", case);
+        }
+
+        assert!(Schema::from_json(&json!({"type": "date"})).is_err());
+    }
+
+    #[test]
+    fn test_fuzz() {
+        let mut dl = DataLogic::new();
+        let rule = json!({"if": [
+            {">=": [{"var": "age"}, {"+": [10, 8]}]},
+            {"cat": ["adult:", {"var": "name"}]},
+            {"in": [{"var": "tier"}, ["gold", "silver"]]}
+        ]});
+        let mut generator = DataGenerator::for_rule(&rule).with_seed(1);
+        let divergences = dl.fuzz(&rule, &mut generator, 200).unwrap();
+        assert_eq!(divergences, []);
+    }
+}
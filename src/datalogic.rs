@@ -3,13 +3,44 @@
 //! This module provides the DataLogic struct which is the primary entry point
 //! for parsing and evaluating logic expressions.
 
-use crate::arena::DataArena;
-use crate::arena::{SimpleOperatorAdapter, SimpleOperatorFn};
-use crate::logic::{evaluate, optimize, Logic, Result};
-use crate::parser::{ExpressionParser, ParserRegistry};
-use crate::value::{DataValue, FromJson, ToJson};
-use crate::LogicError;
+use crate::arena::{global_generation, global_operator_names, DataArena};
+use crate::arena::{
+    CacheStats, MemoizeConfig, OperatorMetadata, SimpleOperatorAdapter, SimpleOperatorFn,
+    TimeoutConfig,
+};
+use crate::budget::{Budget, Partial};
+use crate::capabilities::Capabilities;
+use crate::compare::{BranchStats, RuleComparison};
+use crate::compiled::CompiledRule;
+use crate::concurrent::{self, PendingCall, Prefetched};
+use crate::context::EvaluationContext;
+use crate::datagen::{DataGenerator, Divergence};
+use crate::editing::{EditableRule, TextEdit};
+use crate::fallback::{Fallback, FallbackEvent, FallbackPolicy};
+use crate::invalidation::{Generations, Invalidation, InvalidationListener, RegistryChange};
+use crate::limits::Limits;
+use crate::logger::LogMetadata;
+use crate::logic::{
+    compilation_report, evaluate, evaluate_score_breakdown, evaluate_with_coercion_trace,
+    evaluate_with_costs, evaluate_with_diagnostics, optimize, ArrayOp, Coercion, CompilationReport,
+    ControlOp, Logic, OperatorType, Result, Token,
+};
+use crate::overrides::OperatorOverrides;
+use crate::parser::jsonlogic::{parse_json_with_config, JsonLogicParser, ParserConfig, RuleList};
+use crate::parser::recovery::parse_errors;
+use crate::parser::{json, ExpressionParser, ParserRegistry};
+#[cfg(feature = "predict")]
+use crate::predict::{ModelRegistry, PredictOperator, PredictionModel};
+use crate::replay::{project_data, FixedClock, Playback, Recorder, ReplayBundle, ReplayConfig};
+use crate::semantics::{IntegerOverflow, OutOfRangeIndex, Semantics};
+use crate::stepping::Evaluation;
+use crate::value::{borrow_json, DataValue, FromJson, ToJson};
+use crate::{DetailedError, LogicError};
+use chrono::Utc;
 use serde_json::Value as JsonValue;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 /// Trait for custom JSONLogic operators
 pub use crate::arena::CustomOperator;
@@ -32,6 +63,13 @@ pub use crate::arena::CustomOperator;
 pub struct DataLogic {
     arena: DataArena,
     parsers: ParserRegistry,
+    parser_config: ParserConfig,
+    fallback: Option<FallbackPolicy>,
+    fallback_events: RefCell<Vec<FallbackEvent>>,
+    generations: Generations,
+    invalidation_listeners: Vec<InvalidationListener>,
+    #[cfg(feature = "predict")]
+    models: Option<ModelRegistry>,
 }
 
 impl DataLogic {
@@ -40,6 +78,13 @@ impl DataLogic {
         Self {
             arena: DataArena::new(),
             parsers: ParserRegistry::new(),
+            parser_config: ParserConfig::default(),
+            fallback: None,
+            fallback_events: RefCell::new(Vec::new()),
+            generations: Generations::default(),
+            invalidation_listeners: Vec::new(),
+            #[cfg(feature = "predict")]
+            models: None,
         }
     }
 
@@ -48,6 +93,13 @@ impl DataLogic {
         Self {
             arena: DataArena::with_chunk_size(chunk_size),
             parsers: ParserRegistry::new(),
+            parser_config: ParserConfig::default(),
+            fallback: None,
+            fallback_events: RefCell::new(Vec::new()),
+            generations: Generations::default(),
+            invalidation_listeners: Vec::new(),
+            #[cfg(feature = "predict")]
+            models: None,
         }
     }
 
@@ -69,12 +121,367 @@ impl DataLogic {
 
     /// Register a parser for a specific expression format
     pub fn register_parser(&mut self, parser: Box<dyn ExpressionParser>) {
+        let format_name = parser.format_name();
         self.parsers.register(parser);
+        self.invalidate(RegistryChange::Parser(format_name.to_string()));
     }
 
     /// Set the default parser
     pub fn set_default_parser(&mut self, format_name: &str) -> Result<()> {
-        self.parsers.set_default(format_name)
+        self.parsers.set_default(format_name)?;
+        self.invalidate(RegistryChange::DefaultParser(format_name.to_string()));
+        Ok(())
+    }
+
+    /// Enable or disable constructing objects from multi-key objects
+    ///
+    /// By default an object with several keys in a JSONLogic rule fails to
+    /// parse as an unknown operator. With object construction enabled it
+    /// builds an object instead, with each value parsed as a rule, as
+    /// supported by several JSONLogic dialects. Objects with a single key are
+    /// still parsed as operators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.set_object_construction(true);
+    ///
+    /// let rule = json!({"map": [{"var": "users"}, {
+    ///     "name": {"var": "first"},
+    ///     "adult": {">=": [{"var": "age"}, 18]}
+    /// }]});
+    /// let data = json!({"users": [{"first": "Ada", "age": 36}]});
+    /// let result = dl.evaluate_json(&rule, &data, None).unwrap();
+    /// assert_eq!(result, json!([{"name": "Ada", "adult": true}]));
+    /// ```
+    pub fn set_object_construction(&mut self, enabled: bool) {
+        self.set_parser_config(ParserConfig {
+            object_construction: enabled,
+            ..self.parser_config
+        });
+    }
+
+    /// Set how a rule that is an array of rules is evaluated
+    ///
+    /// By default a JSONLogic rule that is an array evaluates to the array of
+    /// the results of its items. Systems emitting lists of rules that must
+    /// all pass, or of which one must pass, can have such lists combined
+    /// with `and` or `or` instead of wrapping them first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, RuleList};
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let rules = json!([{">": [{"var": "age"}, 18]}, {"==": [{"var": "country"}, "FR"]}]);
+    /// let data = json!({"age": 30, "country": "DE"});
+    /// assert_eq!(dl.evaluate_json(&rules, &data, None).unwrap(), json!([true, false]));
+    ///
+    /// dl.set_rule_list(RuleList::All);
+    /// assert_eq!(dl.evaluate_json(&rules, &data, None).unwrap(), json!(false));
+    ///
+    /// dl.set_rule_list(RuleList::Any);
+    /// assert_eq!(dl.evaluate_json(&rules, &data, None).unwrap(), json!(true));
+    /// ```
+    pub fn set_rule_list(&mut self, rule_list: RuleList) {
+        self.set_parser_config(ParserConfig {
+            rule_list,
+            ..self.parser_config
+        });
+    }
+
+    /// Reject duplicate keys in the JSON text of rules and data
+    ///
+    /// By default the last value of a key repeated in an object wins, which
+    /// can hide mistakes in hand-written rule files. Strict parsing fails
+    /// instead, with the line and column of the duplicate key, when parsing
+    /// text with [`DataLogic::parse_logic`], [`DataLogic::parse_data`] and
+    /// the methods using them. Trailing data and lone surrogates are always
+    /// rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let rule = r#"{"if": [{"var": "vip"}, 10, 5], "if": [true, 0]}"#;
+    /// assert!(dl.parse_logic(rule, None).is_ok());
+    ///
+    /// dl.set_strict_json(true);
+    /// let error = dl.parse_logic(rule, None).unwrap_err();
+    /// assert!(error.to_string().contains("duplicate key `if` at line 1 column 36"));
+    /// assert!(dl.parse_data(r#"{"id": 1, "id": 2}"#).is_err());
+    /// ```
+    pub fn set_strict_json(&mut self, strict: bool) {
+        self.set_parser_config(ParserConfig {
+            strict_json: strict,
+            ..self.parser_config
+        });
+    }
+
+    /// Replace the JSONLogic parser with one using a new configuration.
+    fn set_parser_config(&mut self, config: ParserConfig) {
+        self.parser_config = config;
+        self.register_parser(Box::new(JsonLogicParser::with_config(config)));
+    }
+
+    /// Get the current registration generation
+    ///
+    /// The generation grows whenever an operator, model or parser is
+    /// registered on this instance, the default parser or the semantics
+    /// change, or a global operator is registered or removed. Caches can compare it to
+    /// [`CompiledRule::generation`] to detect possibly outdated rules.
+    pub fn generation(&self) -> u64 {
+        self.generations.current() + global_generation()
+    }
+
+    /// Register a callback invoked after every registration change
+    ///
+    /// Global operator changes are not reported to callbacks; use
+    /// [`DataLogic::is_stale`] to detect rules affected by them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue, RegistryChange};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let rule = dl.compile(r#"{"tier": [{"var": "spend"}]}"#, None).unwrap();
+    ///
+    /// let changes = Arc::new(Mutex::new(Vec::new()));
+    /// let log = Arc::clone(&changes);
+    /// dl.on_invalidate(move |event| log.lock().unwrap().push(event.clone()));
+    ///
+    /// dl.register_simple_operator("tier", |_, _| Ok(DataValue::integer(2)));
+    /// let changes = changes.lock().unwrap();
+    /// assert_eq!(changes[0].change, RegistryChange::Operator("tier".to_string()));
+    /// assert!(changes[0].affects(&rule));
+    /// assert!(dl.is_stale(&rule));
+    /// ```
+    pub fn on_invalidate<F>(&mut self, callback: F)
+    where
+        F: Fn(&Invalidation) + Send + Sync + 'static,
+    {
+        self.invalidation_listeners.push(Box::new(callback));
+    }
+
+    /// Check if a registration change since compilation affects a rule
+    ///
+    /// Only meaningful for rules compiled by this instance. Changes to
+    /// operators the rule does not use leave it up to date.
+    pub fn is_stale(&self, rule: &CompiledRule) -> bool {
+        self.generations.is_stale(rule)
+    }
+
+    /// Record a registration change and notify the listeners
+    fn invalidate(&mut self, change: RegistryChange) {
+        let generation = self.generations.bump(&change) + global_generation();
+        let event = Invalidation { generation, change };
+        for listener in &self.invalidation_listeners {
+            listener(&event);
+        }
+    }
+
+    /// Set the policy applied when a rule fails to evaluate
+    ///
+    /// With a policy in place, `evaluate` and the methods built on it return
+    /// the fallback result instead of the error, and record a [`FallbackEvent`].
+    pub fn set_fallback_policy(&mut self, policy: FallbackPolicy) {
+        self.fallback = Some(policy);
+    }
+
+    /// Remove the fallback policy, so that evaluation errors are returned
+    pub fn clear_fallback_policy(&mut self) {
+        self.fallback = None;
+    }
+
+    /// Enable or disable catching panics of custom operators
+    ///
+    /// Isolation is enabled by default: a panicking custom operator fails the
+    /// evaluation with [`LogicError::OperatorPanicked`] instead of unwinding
+    /// through the calling thread. The panic is still reported by the panic
+    /// hook. Disable isolation to let panics propagate, for example to get a
+    /// backtrace while debugging an operator.
+    pub fn set_panic_isolation(&mut self, enabled: bool) {
+        self.arena.set_panic_isolation(enabled);
+    }
+
+    /// Select the behavior of the operators in edge cases
+    ///
+    /// Defaults to [`Semantics::JsonLogicEngine`]. Rules compiled before the
+    /// change may have folded constants under the previous profile, so the
+    /// change is reported to invalidation listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, Semantics};
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let rule = json!({"-": [{"var": "a"}, 1]});
+    /// let data = json!({"a": "apple"});
+    /// assert!(dl.evaluate_json(&rule, &data, None).is_err());
+    ///
+    /// dl.set_semantics(Semantics::JsonLogicJs);
+    /// assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(null));
+    /// ```
+    pub fn set_semantics(&mut self, semantics: Semantics) {
+        self.arena.set_semantics(semantics);
+        self.invalidate(RegistryChange::Semantics(semantics));
+    }
+
+    /// Returns the behavior of the operators in edge cases
+    pub fn semantics(&self) -> Semantics {
+        self.arena.semantics()
+    }
+
+    /// Select what variables read at array indices that are out of range
+    ///
+    /// Indices in the paths of `var` and `val` can be negative to count from
+    /// the end of an array, so `{"var": "items.-1"}` reads its last item. An
+    /// index past either end of the array reads the default value of the
+    /// `var` by default, or null if it has none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, LogicError, OutOfRangeIndex};
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let data = json!({"scores": [70, 85, 90]});
+    /// let last = json!({"var": "scores.-1"});
+    /// assert_eq!(dl.evaluate_json(&last, &data, None).unwrap(), json!(90));
+    ///
+    /// let tenth = json!({"var": ["scores.9", 0]});
+    /// assert_eq!(dl.evaluate_json(&tenth, &data, None).unwrap(), json!(0));
+    ///
+    /// dl.set_out_of_range_index(OutOfRangeIndex::Error);
+    /// assert!(matches!(
+    ///     dl.evaluate_json(&tenth, &data, None),
+    ///     Err(LogicError::VariableError { .. })
+    /// ));
+    /// ```
+    pub fn set_out_of_range_index(&mut self, policy: OutOfRangeIndex) {
+        self.arena.set_out_of_range_index(policy);
+    }
+
+    /// Returns what variables read at array indices that are out of range
+    pub fn out_of_range_index(&self) -> OutOfRangeIndex {
+        self.arena.out_of_range_index()
+    }
+
+    /// Select what integer arithmetic does when a result overflows 64 bits
+    ///
+    /// `+`, `-` and `*` compute integer operands exactly. On overflow they
+    /// switch to floating point numbers by default, losing precision but not
+    /// magnitude. They can instead saturate at the smallest or largest
+    /// integer, or fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, IntegerOverflow, LogicError};
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let rule = json!({"+": [{"var": "balance"}, 1]});
+    /// let data = json!({"balance": i64::MAX});
+    /// assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(9.223372036854776e18));
+    ///
+    /// dl.set_integer_overflow(IntegerOverflow::Saturate);
+    /// assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(i64::MAX));
+    ///
+    /// dl.set_integer_overflow(IntegerOverflow::Error);
+    /// assert!(matches!(
+    ///     dl.evaluate_json(&rule, &data, None),
+    ///     Err(LogicError::IntegerOverflow { .. })
+    /// ));
+    /// ```
+    pub fn set_integer_overflow(&mut self, policy: IntegerOverflow) {
+        self.arena.set_integer_overflow(policy);
+    }
+
+    /// Returns what integer arithmetic does when a result overflows 64 bits
+    pub fn integer_overflow(&self) -> IntegerOverflow {
+        self.arena.integer_overflow()
+    }
+
+    /// Cap the sizes of the values built and the work done by evaluations
+    ///
+    /// Operators that would build a larger value, and evaluations that would
+    /// iterate, execute or nest more operations or run longer than allowed,
+    /// fail with [`LogicError::LimitExceeded`]. See [`Limits`] for what each
+    /// limit applies to. Constants folded in rules compiled before the change
+    /// were not checked against the new limits, so the change is reported to
+    /// invalidation listeners.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.arena.set_limits(limits);
+        self.invalidate(RegistryChange::Limits(limits));
+    }
+
+    /// Returns the caps on the sizes of values and the work of evaluations
+    pub fn limits(&self) -> Limits {
+        self.arena.limits()
+    }
+
+    /// Install a callback receiving the values reported by the `log` operator
+    ///
+    /// `{"log": expr}` evaluates to the value of `expr` and hands it to the
+    /// logger together with the [`LogMetadata`] of the `log` expression, so
+    /// values can be reported through the logging facility of the
+    /// application. Without a logger, values are passed through silently.
+    /// Tracking the position of the `log` expressions makes evaluation slower
+    /// while a logger is installed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, ToJson};
+    /// use serde_json::json;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let logged = Arc::new(Mutex::new(Vec::new()));
+    /// let mut dl = DataLogic::new();
+    /// let sink = Arc::clone(&logged);
+    /// dl.set_logger(move |value, metadata| {
+    ///     sink.lock().unwrap().push((value.to_json(), metadata.path));
+    /// });
+    ///
+    /// let rule = json!({"if": [{"log": {"var": "score"}}, "pass", "fail"]});
+    /// let result = dl.evaluate_json(&rule, &json!({"score": 7}), None).unwrap();
+    /// assert_eq!(result, json!("pass"));
+    /// assert_eq!(*logged.lock().unwrap(), vec![(json!(7), "/if/0".to_string())]);
+    /// ```
+    pub fn set_logger<F>(&mut self, logger: F)
+    where
+        F: Fn(&DataValue, LogMetadata) + Send + Sync + 'static,
+    {
+        self.arena.set_logger(Some(Arc::new(logger)));
+    }
+
+    /// Remove the logger, so that the `log` operator reports nothing
+    pub fn clear_logger(&mut self) {
+        self.arena.set_logger(None);
+    }
+
+    /// Get the recorded fallback events
+    pub fn fallback_events(&self) -> Vec<FallbackEvent> {
+        self.fallback_events.borrow().clone()
+    }
+
+    /// Take the recorded fallback events, clearing the log
+    pub fn take_fallback_events(&self) -> Vec<FallbackEvent> {
+        self.fallback_events.take()
     }
 
     /// Register a custom operator implementation
@@ -127,15 +534,269 @@ impl DataLogic {
     /// ```
     pub fn register_custom_operator(&mut self, name: &str, operator: Box<dyn CustomOperator>) {
         self.arena.register_custom_operator(name, operator);
+        self.invalidate(RegistryChange::Operator(name.to_string()));
+    }
+
+    /// Register a version of a namespaced custom operator
+    ///
+    /// Rules refer to the operator by its name, optionally requesting a
+    /// major version on the namespace: with versions 1.0.0 and 2.1.0 of
+    /// `acme/score` registered, `acme/score` and `acme.v2/score` resolve to
+    /// 2.1.0 and `acme.v1/score` to 1.0.0. Rules requesting an unavailable
+    /// version, or passing arguments that do not match the declared types,
+    /// fail to compile with an error listing the available versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue, OperatorMetadata};
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_versioned_simple_operator(
+    ///     "acme/score",
+    ///     OperatorMetadata::new("1.0.0").unwrap(),
+    ///     |_, _| Ok(DataValue::integer(1)),
+    /// );
+    /// dl.register_versioned_simple_operator(
+    ///     "acme/score",
+    ///     OperatorMetadata::new("2.1.0").unwrap().with_arg_types(&["number"]),
+    ///     |args, _| Ok(DataValue::integer(args[0].as_i64().unwrap_or(0) * 2)),
+    /// );
+    ///
+    /// let result = dl.evaluate_str(r#"{"acme.v2/score": [21]}"#, "{}", None).unwrap();
+    /// assert_eq!(result.as_i64(), Some(42));
+    /// let result = dl.evaluate_str(r#"{"acme.v1/score": []}"#, "{}", None).unwrap();
+    /// assert_eq!(result.as_i64(), Some(1));
+    ///
+    /// let error = dl.parse_logic(r#"{"acme.v3/score": []}"#, None).unwrap_err();
+    /// assert!(error.to_string().contains("available versions: 1.0.0, 2.1.0"));
+    /// ```
+    pub fn register_versioned_operator(
+        &mut self,
+        name: &str,
+        metadata: OperatorMetadata,
+        operator: Box<dyn CustomOperator>,
+    ) {
+        self.arena
+            .register_versioned_custom_operator(name, metadata, operator);
+        self.invalidate(RegistryChange::Operator(name.to_string()));
+    }
+
+    /// Register a version of a namespaced simple custom operator
+    ///
+    /// See [`DataLogic::register_versioned_operator`] for the resolution rules.
+    pub fn register_versioned_simple_operator(
+        &mut self,
+        name: &str,
+        metadata: OperatorMetadata,
+        function: SimpleOperatorFn,
+    ) {
+        let adapter = SimpleOperatorAdapter::new(name, function);
+        self.register_versioned_operator(name, metadata, Box::new(adapter));
+    }
+
+    /// Register a model for the `predict` operator
+    ///
+    /// The first registered model installs the `predict` custom operator.
+    /// Registering a model under an existing name replaces it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::predict::LinearModel;
+    /// use datalogic_rs::DataLogic;
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let model = LinearModel::new(vec![0.002, 0.1], -1.0).with_feature_names(&["amount", "velocity"]);
+    /// dl.register_model("risk", Box::new(model));
+    ///
+    /// let rule = json!({"predict": ["risk", [{"var": "amount"}, 5]]});
+    /// let result = dl.evaluate_json(&rule, &json!({"amount": 1000}), None).unwrap();
+    /// assert_eq!(result, json!(1.5));
+    ///
+    /// // Named features can also be picked from an object
+    /// let rule = json!({"predict": ["risk", {"var": "tx"}, {"threshold": 1.0}]});
+    /// let data = json!({"tx": {"velocity": 5, "amount": 1000}});
+    /// assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(true));
+    /// ```
+    #[cfg(feature = "predict")]
+    pub fn register_model(&mut self, name: &str, model: Box<dyn PredictionModel>) {
+        let models = self.models.get_or_insert_with(|| {
+            let models = ModelRegistry::default();
+            let operator = PredictOperator {
+                models: Arc::clone(&models),
+            };
+            self.arena
+                .register_custom_operator("predict", Box::new(operator));
+            models
+        });
+        models
+            .write()
+            .unwrap()
+            .insert(name.to_string(), Arc::from(model));
+        self.invalidate(RegistryChange::Model(name.to_string()));
+    }
+
+    /// Register a custom operator whose results are memoized across evaluations
+    ///
+    /// Results are cached in a bounded LRU keyed by the operator arguments, with
+    /// an optional time-to-live. Only use this for operators whose result depends
+    /// solely on their arguments, such as lookups against slow external services.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue, MemoizeConfig};
+    /// use std::time::Duration;
+    ///
+    /// fn lookup<'r>(args: Vec<DataValue<'r>>, _data: DataValue<'r>) -> std::result::Result<DataValue<'r>, String> {
+    ///     let id = args.first().and_then(|v| v.as_i64()).unwrap_or(0);
+    ///     Ok(DataValue::integer(id * 100))
+    /// }
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_memoized_simple_operator(
+    ///     "lookup",
+    ///     lookup,
+    ///     MemoizeConfig::new(256).with_ttl(Duration::from_secs(30)),
+    /// );
+    ///
+    /// for _ in 0..3 {
+    ///     let result = dl.evaluate_str(r#"{"lookup": 7}"#, r#"{}"#, None).unwrap();
+    ///     assert_eq!(result.as_i64(), Some(700));
+    /// }
+    ///
+    /// let stats = dl.custom_operator_cache_stats("lookup").unwrap();
+    /// assert_eq!((stats.hits, stats.misses), (2, 1));
+    /// ```
+    pub fn register_memoized_operator(
+        &mut self,
+        name: &str,
+        operator: Box<dyn CustomOperator>,
+        config: MemoizeConfig,
+    ) {
+        self.arena
+            .register_memoized_custom_operator(name, operator, config);
+        self.invalidate(RegistryChange::Operator(name.to_string()));
+    }
+
+    /// Register a simple custom operator whose results are memoized across evaluations
+    ///
+    /// See [`DataLogic::register_memoized_operator`] for the caching semantics.
+    pub fn register_memoized_simple_operator(
+        &mut self,
+        name: &str,
+        function: SimpleOperatorFn,
+        config: MemoizeConfig,
+    ) {
+        let adapter = SimpleOperatorAdapter::new(name, function);
+        self.register_memoized_operator(name, Box::new(adapter), config);
+    }
+
+    /// Returns the cache statistics of a memoized custom operator
+    ///
+    /// Returns `None` if the operator is not registered with memoization.
+    pub fn custom_operator_cache_stats(&self, name: &str) -> Option<CacheStats> {
+        self.arena.custom_operator_cache_stats(name)
+    }
+
+    /// Clears the cached results and statistics of a memoized custom operator
+    pub fn clear_custom_operator_cache(&self, name: &str) {
+        self.arena.clear_custom_operator_cache(name);
     }
 
-    /// Check if a custom operator is registered
+    /// Cap the time the calls of a registered custom operator are waited for
+    ///
+    /// Calls of the operator are made on a thread of their own. A call that
+    /// does not answer within the timeout evaluates to the fallback value of
+    /// the configuration, or fails with [`LogicError::OperatorTimedOut`], so
+    /// one slow dependency cannot exhaust the latency budget of a rule. The
+    /// call itself is not cancelled: it runs to completion in the background
+    /// and its answer is discarded. At most [`TimeoutConfig::workers`] calls
+    /// of the operator run at a time, and calls made while all of them are
+    /// running time out at once.
+    ///
+    /// The operator sees its arguments and the data context as values
+    /// converted from JSON. Fallback values are never memoized. Registering
+    /// the operator again removes its timeout.
+    ///
+    /// Fails with [`LogicError::OperatorNotFoundError`] if no operator is
+    /// registered on this instance under the name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue, TimeoutConfig};
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    ///
+    /// fn lookup<'r>(_args: Vec<DataValue<'r>>, _data: DataValue<'r>) -> std::result::Result<DataValue<'r>, String> {
+    ///     std::thread::sleep(Duration::from_secs(2));
+    ///     Ok(DataValue::integer(700))
+    /// }
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_simple_operator("lookup", lookup);
+    /// dl.set_operator_timeout(
+    ///     "lookup",
+    ///     TimeoutConfig::new(Duration::from_millis(20)).with_fallback(json!(0)),
+    /// )
+    /// .unwrap();
+    ///
+    /// let rule = json!({"+": [{"lookup": 7}, 1]});
+    /// assert_eq!(dl.evaluate_json(&rule, &json!({}), None).unwrap(), json!(1));
+    /// ```
+    pub fn set_operator_timeout(&mut self, name: &str, config: TimeoutConfig) -> Result<()> {
+        self.arena.set_custom_operator_timeout(name, config)?;
+        self.invalidate(RegistryChange::Operator(name.to_string()));
+        Ok(())
+    }
+
+    /// Returns the timeout of a registered custom operator, if it has one
+    pub fn operator_timeout(&self, name: &str) -> Option<TimeoutConfig> {
+        self.arena.custom_operator_timeout(name)
+    }
+
+    /// Check if a custom operator is registered on this instance or globally
     pub fn has_custom_operator(&self, name: &str) -> bool {
         self.arena.has_custom_operator(name)
     }
 
+    /// Describe the engine build and the operators this instance understands
+    ///
+    /// Lists the crate version, the optional features compiled in, the
+    /// built-in operators and the custom operators registered on this
+    /// instance or globally, so services and rule editors can adapt to the
+    /// deployed engine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue};
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_simple_operator("tier", |_, _| Ok(DataValue::integer(2)));
+    ///
+    /// let capabilities = dl.capabilities();
+    /// assert!(capabilities.has_feature("regex"));
+    /// assert!(capabilities.supports_operator("map"));
+    /// assert!(capabilities.supports_operator("tier"));
+    /// assert!(!capabilities.supports_operator("rank"));
+    /// ```
+    pub fn capabilities(&self) -> Capabilities {
+        let mut custom_operators = global_operator_names();
+        custom_operators.extend(
+            self.arena
+                .custom_operator_registry()
+                .names()
+                .map(str::to_string),
+        );
+        Capabilities::new(custom_operators)
+    }
+
     /// Parse a logic expression using the specified parser format
-    pub fn parse_logic(&self, source: &str, format: Option<&str>) -> Result<Logic> {
+    pub fn parse_logic(&self, source: &str, format: Option<&str>) -> Result<Logic<'_>> {
         let token = self.parsers.parse(source, format, &self.arena)?;
 
         // Apply static optimization
@@ -144,69 +805,1229 @@ impl DataLogic {
         Ok(Logic::new(optimized_token, &self.arena))
     }
 
-    /// Parse a JSON logic expression into a Token
-    pub fn parse_logic_json(&self, source: &JsonValue, format: Option<&str>) -> Result<Logic> {
-        let token = self.parsers.parse_json(source, format, &self.arena)?;
-        Ok(Logic::new(token, &self.arena))
-    }
+    /// Parse a JSONLogic expression, reporting every error instead of the
+    /// first
+    ///
+    /// Each sub-expression that fails to parse is reported with its path, in
+    /// the format of [`DetailedError::path`], and parsing carries on with the
+    /// rest of the rule, so authoring tools can show all the problems of a
+    /// large rule at once. An operator is only checked once its arguments
+    /// parse, so that no error is a consequence of another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    ///
+    /// let dl = DataLogic::new();
+    /// let errors = dl
+    ///     .parse_logic_recovering(
+    ///         r#"{"if": [{"var": true}, {"obj": "x"}, {"+": [1, {"validate": 2}]}]}"#,
+    ///     )
+    ///     .unwrap_err();
+    ///
+    /// let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+    /// assert_eq!(paths, ["/if/0", "/if/1", "/if/2/+/1"]);
+    ///
+    /// assert!(dl.parse_logic_recovering(r#"{"var": "a"}"#).is_ok());
+    /// ```
+    pub fn parse_logic_recovering(
+        &self,
+        source: &str,
+    ) -> std::result::Result<Logic<'_>, Vec<DetailedError>> {
+        let root_error = |error| DetailedError {
+            error,
+            path: String::new(),
+            operator: None,
+            arguments: Vec::new(),
+        };
 
-    /// Parse a JSON data string into a DataValue
-    pub fn parse_data(&self, source: &str) -> Result<DataValue> {
-        let json = serde_json::from_str(source).map_err(|e| LogicError::ParseError {
-            reason: e.to_string(),
+        let json = json::from_str(source, self.parser_config.strict_json).map_err(|e| {
+            vec![root_error(LogicError::ParseError {
+                reason: format!("Invalid JSON: {}", e),
+            })]
         })?;
-        Ok(DataValue::from_json(&json, &self.arena))
-    }
-
-    /// Parse a JSON data string into a DataValue
-    pub fn parse_data_json(&self, source: &JsonValue) -> Result<DataValue> {
-        Ok(DataValue::from_json(source, &self.arena))
+        let token = parse_json_with_config(&json, self.parser_config, &self.arena)
+            .and_then(|token| optimize(token, &self.arena));
+        match token {
+            Ok(token) => Ok(Logic::new(token, &self.arena)),
+            Err(error) => {
+                let mut errors = parse_errors(&json, self.parser_config, &self.arena);
+                if errors.is_empty() {
+                    errors.push(root_error(error));
+                }
+                Err(errors)
+            }
+        }
     }
 
-    /// Evaluate a rule with the provided data
+    /// Parse and optimize a logic expression, reporting what was compiled
     ///
-    /// This method evaluates a logic rule against the given data context.
-    /// The data is used as both the current context and the root context for evaluation.
+    /// This is a dry run of [`DataLogic::parse_logic`]: the returned logic is
+    /// the same, and the report lists the operators, variables and literals
+    /// of the optimized expression, the applied optimizations, an estimated
+    /// cost and warnings such as unregistered custom operators.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `rule` - The compiled logic rule to evaluate
-    /// * `data` - The data to use as context during evaluation
+    /// ```
+    /// use datalogic_rs::DataLogic;
     ///
-    /// # Returns
+    /// let dl = DataLogic::new();
+    /// let (_logic, report) = dl
+    ///     .compile_with_report(r#"{">": [{"var": "age"}, {"+": [10, 8]}]}"#, None)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(report.operators.get(">"), Some(&1));
+    /// assert!(report.variables.contains("age"));
+    /// assert_eq!(report.optimizations, ["constant folding removed 1 operator(s)"]);
+    /// ```
+    pub fn compile_with_report(
+        &self,
+        source: &str,
+        format: Option<&str>,
+    ) -> Result<(Logic<'_>, CompilationReport)> {
+        let token = self.parsers.parse(source, format, &self.arena)?;
+        let optimized_token = optimize(token, &self.arena)?;
+
+        let report = compilation_report(token, optimized_token, &self.arena);
+        Ok((Logic::new(optimized_token, &self.arena), report))
+    }
+
+    /// Parse and optimize a logic expression into a thread-safe compiled rule
+    ///
+    /// Unlike [`DataLogic::parse_logic`], the result owns its memory, is
+    /// `Send + Sync`, and can be evaluated by any DataLogic instance with
+    /// [`DataLogic::evaluate_compiled`]. Custom operators are looked up in the
+    /// evaluating instance.
+    pub fn compile(&self, source: &str, format: Option<&str>) -> Result<CompiledRule> {
+        let rule = CompiledRule::build(|arena| {
+            // The rule parses and folds as it would in this instance
+            arena.copy_settings_from(&self.arena);
+
+            let token = self.parsers.parse(source, format, arena)?;
+            optimize(token, arena)
+        })?;
+        Ok(rule.with_generations(self.generations.current(), global_generation()))
+    }
+
+    /// Parse a JSON logic expression into a Token
+    pub fn parse_logic_json(&self, source: &JsonValue, format: Option<&str>) -> Result<Logic<'_>> {
+        let token = self.parsers.parse_json(source, format, &self.arena)?;
+        Ok(Logic::new(token, &self.arena))
+    }
+
+    /// Parse the text of a rule being edited
+    ///
+    /// The returned rule is updated by [`DataLogic::reparse`], which parses
+    /// only the part of the text affected by an edit. A text that does not
+    /// parse is kept, and its error is returned by
+    /// [`EditableRule::logic`].
+    pub fn parse_editable(&self, source: &str) -> EditableRule<'_> {
+        EditableRule::parse(source.to_string(), self.parser_config, &self.arena)
+    }
+
+    /// Apply an edit to the text of a rule, parsing only the smallest
+    /// sub-rule enclosing it
+    ///
+    /// The tokens of the rest of the rule are kept. Returns the updated rule,
+    /// or the error parsing its text, in which case the text is still
+    /// updated and parsed whole on the next edit. An edit whose range is not
+    /// in the text is an error and leaves the rule unchanged.
+    ///
+    /// See [`EditableRule`] for an example.
+    pub fn reparse<'a>(
+        &'a self,
+        rule: &mut EditableRule<'a>,
+        edit: &TextEdit,
+    ) -> Result<Logic<'a>> {
+        rule.apply(edit, self.parser_config, &self.arena)?;
+        rule.logic()
+    }
+
+    /// Parse a JSON data string into a DataValue
+    pub fn parse_data(&self, source: &str) -> Result<DataValue<'_>> {
+        let json = json::from_str(source, self.parser_config.strict_json).map_err(|e| {
+            LogicError::ParseError {
+                reason: e.to_string(),
+            }
+        })?;
+        Ok(DataValue::from_json(&json, &self.arena))
+    }
+
+    /// Parse a JSON data string into a DataValue
+    pub fn parse_data_json(&self, source: &JsonValue) -> Result<DataValue<'_>> {
+        Ok(DataValue::from_json(source, &self.arena))
+    }
+
+    /// Evaluate a rule with the provided data
+    ///
+    /// This method evaluates a logic rule against the given data context.
+    /// The data is used as both the current context and the root context for evaluation.
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - The compiled logic rule to evaluate
+    /// * `data` - The data to use as context during evaluation
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a reference to the evaluation result as a DataValue
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{ ">": [{"var": "temp"}, 100] }"#, None).unwrap();
+    /// let data = dl.parse_data(r#"{"temp": 110}"#).unwrap();
+    /// let result = dl.evaluate(&rule, &data).unwrap();
+    /// assert_eq!(result.to_string(), "true");
+    /// ```
+    pub fn evaluate<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+    ) -> Result<&'a DataValue<'a>> {
+        self.evaluate_in(rule, data, &self.arena)
+    }
+
+    /// Evaluate a thread-safe compiled rule with the provided data
+    ///
+    /// See [`CompiledRule`] for sharing rules between threads.
+    pub fn evaluate_compiled<'a>(
+        &'a self,
+        rule: &'a CompiledRule,
+        data: &'a DataValue,
+    ) -> Result<&'a DataValue<'a>> {
+        self.evaluate_compiled_in(rule, data, &self.arena)
+    }
+
+    /// Create an arena for the temporaries of evaluations
+    ///
+    /// Rules parsed by this instance live as long as it does, and so does
+    /// everything [`DataLogic::evaluate`] allocates. Evaluating with
+    /// [`DataLogic::evaluate_in`] allocates in a scratch arena instead, which
+    /// can be reset between evaluations while the rules are kept. The scratch
+    /// arena shares the custom operators of this instance.
+    pub fn scratch_arena(&self) -> DataArena {
+        self.arena.create_scratch_arena()
+    }
+
+    /// Evaluate a rule, allocating the data and the result in a scratch arena
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue, FromJson};
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{"*": [{"var": "price"}, 2]}"#, None).unwrap();
+    /// let mut scratch = dl.scratch_arena();
+    ///
+    /// for price in [10, 20, 30] {
+    ///     let data = DataValue::from_json(&json!({"price": price}), &scratch);
+    ///     let result = dl.evaluate_in(&rule, &data, &scratch).unwrap();
+    ///     assert_eq!(result.as_i64(), Some(price * 2));
+    ///     // Free the evaluation while keeping the rule
+    ///     scratch.reset();
+    /// }
+    /// ```
+    pub fn evaluate_in<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+        scratch: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let result = self.evaluate_root(rule.root(), data, scratch)?;
+        match rule.output_type() {
+            Some(output_type) => output_type.coerce(result, scratch),
+            None => Ok(result),
+        }
+    }
+
+    /// Evaluate a rule against many data items
+    ///
+    /// The items are evaluated lazily in a scratch arena that is reset after
+    /// each of them, so memory use does not grow with the number of items.
+    /// Each result is converted to JSON before the arena is reset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue};
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{">": [{"var": ""}, 1]}"#, None).unwrap();
+    /// let items = (0..3).map(DataValue::integer);
+    ///
+    /// let results: Vec<_> = dl
+    ///     .evaluate_batch(&rule, items)
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// assert_eq!(results, [json!(false), json!(false), json!(true)]);
+    /// ```
+    pub fn evaluate_batch<'a, I>(
+        &'a self,
+        rule: &'a Logic<'a>,
+        data: I,
+    ) -> impl Iterator<Item = Result<JsonValue>> + 'a
+    where
+        I: IntoIterator<Item = DataValue<'a>>,
+        I::IntoIter: 'a,
+    {
+        let mut scratch = self.scratch_arena();
+        let mut items = data.into_iter();
+        std::iter::from_fn(move || {
+            let item = items.next()?;
+            let result = self
+                .evaluate_in(rule, &item, &scratch)
+                .map(|value| value.to_json());
+            scratch.reset();
+            Some(result)
+        })
+    }
+
+    /// Evaluate a compiled rule, allocating the data and the result in a scratch arena
+    ///
+    /// See [`DataLogic::evaluate_in`]. Errors of a rule with an id are
+    /// wrapped in [`LogicError::InRule`].
+    pub fn evaluate_compiled_in<'a>(
+        &'a self,
+        rule: &'a CompiledRule,
+        data: &'a DataValue,
+        scratch: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let previous = scratch.replace_rule_id(rule.shared_id());
+        let result = self
+            .evaluate_root(rule.root(), data, scratch)
+            .and_then(|result| match rule.output_type() {
+                Some(output_type) => output_type.coerce(result, scratch),
+                None => Ok(result),
+            });
+        scratch.replace_rule_id(previous);
+
+        result.map_err(|error| match rule.id() {
+            Some(rule_id) => LogicError::InRule {
+                rule_id: rule_id.to_string(),
+                error: Box::new(error),
+            },
+            None => error,
+        })
+    }
+
+    /// Evaluate a compiled rule, keeping only its result in the caller's arena
+    ///
+    /// The rule is evaluated in a scratch arena dropped before returning, and
+    /// only the result is copied into `target` with
+    /// [`DataArena::copy_value`]. Results held for a long time, such as
+    /// across await points, then keep no intermediate values alive. To reuse
+    /// the scratch arena between evaluations, call
+    /// [`DataLogic::evaluate_compiled_in`] and copy the result before
+    /// resetting it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::arena::DataArena;
+    /// use datalogic_rs::{DataLogic, DataValue, FromJson};
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl
+    ///     .compile(r#"{"filter": [{"var": "xs"}, {">": [{"var": ""}, 1]}]}"#, None)
+    ///     .unwrap();
+    ///
+    /// let results = DataArena::new();
+    /// let data_arena = DataArena::new();
+    /// let data = DataValue::from_json(&json!({"xs": [1, 2, 3]}), &data_arena);
+    /// let result = dl.evaluate_compiled_into(&rule, &data, &results).unwrap();
+    /// assert_eq!(result.to_string(), "[2, 3]");
+    /// ```
+    pub fn evaluate_compiled_into<'r>(
+        &self,
+        rule: &CompiledRule,
+        data: &DataValue,
+        target: &'r DataArena,
+    ) -> Result<DataValue<'r>> {
+        let scratch = self.scratch_arena();
+        let result = self.evaluate_compiled_in(rule, data, &scratch)?;
+        Ok(target.copy_value(result, &scratch))
+    }
+
+    /// Evaluate a rule returning an array, keeping only a window of its items
+    ///
+    /// This is meant for paginating large results: the items before `offset`
+    /// and after the `limit` kept ones are dropped. When the rule is a
+    /// `filter`, the window is applied while filtering, so the full result is
+    /// never built and the items after the window are not evaluated. The
+    /// filter is still dispatched like any other operator, so overrides,
+    /// limits and profiling apply to it. Request one item more than the page
+    /// size to tell whether another page follows.
+    ///
+    /// A rule whose result is not an array fails with
+    /// [`LogicError::OutputTypeError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, ToJson};
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl
+    ///     .parse_logic(r#"{"filter": [{"var": "ids"}, {">": [{"var": ""}, 2]}]}"#, None)
+    ///     .unwrap();
+    /// let data = dl.parse_data(r#"{"ids": [1, 2, 3, 4, 5, 6, 7]}"#).unwrap();
+    ///
+    /// let page = dl.evaluate_window(&rule, &data, 2, 2).unwrap();
+    /// assert_eq!(page.to_json(), json!([5, 6]));
+    /// ```
+    pub fn evaluate_window<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+        offset: usize,
+        limit: usize,
+    ) -> Result<&'a DataValue<'a>> {
+        // A root filter applies the window itself when it is dispatched
+        let windowed = match rule.root() {
+            Token::Operator {
+                op_type: OperatorType::Array(ArrayOp::Filter),
+                args: Token::ArrayLiteral(args),
+            } => {
+                self.arena.set_filter_window(args, offset, limit);
+                true
+            }
+            _ => false,
+        };
+        let result = self.evaluate_root(rule.root(), data, &self.arena);
+        // The window is left over when an override replaced the root filter
+        let applied = windowed && !self.arena.clear_filter_window();
+
+        let result = match result? {
+            DataValue::Array(items) if !applied => {
+                let start = offset.min(items.len());
+                let end = start.saturating_add(limit).min(items.len());
+                self.arena.alloc(DataValue::Array(&items[start..end]))
+            }
+            result @ DataValue::Array(_) => result,
+            other => {
+                return Err(LogicError::OutputTypeError {
+                    path: String::new(),
+                    expected: "array".to_string(),
+                    actual: other.to_string(),
+                })
+            }
+        };
+        match rule.output_type() {
+            Some(output_type) => output_type.coerce(result, &self.arena),
+            None => Ok(result),
+        }
+    }
+
+    /// Evaluate a root token, applying the fallback policy on failure
+    fn evaluate_root<'a>(
+        &'a self,
+        root: &'a Token<'a>,
+        data: &'a DataValue,
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        self.evaluate_root_with(data, arena, |arena| evaluate(root, arena))
+    }
+
+    /// Run an evaluation against the data, applying the fallback policy on failure
+    fn evaluate_root_with<'a, F>(
+        &'a self,
+        data: &'a DataValue,
+        arena: &'a DataArena,
+        evaluate_rule: F,
+    ) -> Result<&'a DataValue<'a>>
+    where
+        F: FnOnce(&'a DataArena) -> Result<&'a DataValue<'a>>,
+    {
+        // Host objects only live for a single evaluation
+        arena.clear_host_objects();
+
+        // Set both current context and root context to the data
+        arena.set_root_context(data);
+        arena.set_current_context(data, &DataValue::String("$"));
+
+        // Evaluate the rule with the data as context
+        match evaluate_rule(arena) {
+            Ok(result) => Ok(result),
+            Err(error) => self.recover(error, data, arena),
+        }
+    }
+
+    /// Apply the fallback policy to the error of an evaluation
+    pub(crate) fn recover<'a>(
+        &'a self,
+        error: LogicError,
+        data: &'a DataValue,
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        match &self.fallback {
+            Some(policy) if policy.applies_to(&error) => {
+                self.evaluate_fallback(policy.fallback(), error, data, arena)
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// Evaluate a rule with some operators replaced for this call only
+    ///
+    /// Overrides take precedence over built-in and registered custom
+    /// operators of the same name, and are removed once the evaluation
+    /// returns. This allows pinning `"now"` or stubbing external lookups when
+    /// testing rules or replaying incidents, without rebuilding the engine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, OperatorOverrides};
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{"format_date": [{"now": []}, "yyyy-MM-dd"]}"#, None).unwrap();
+    /// let data = dl.parse_data("{}").unwrap();
+    ///
+    /// let overrides = OperatorOverrides::new().with_simple_operator("now", |_, _| {
+    ///     let fixed = chrono::DateTime::parse_from_rfc3339("2024-02-29T12:00:00Z").unwrap();
+    ///     Ok(datalogic_rs::DataValue::datetime(fixed.into()))
+    /// });
+    /// let result = dl.evaluate_with_overrides(&rule, &data, &overrides).unwrap();
+    /// assert_eq!(result.as_str(), Some("2024-02-29"));
+    /// ```
+    pub fn evaluate_with_overrides<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+        overrides: &OperatorOverrides,
+    ) -> Result<&'a DataValue<'a>> {
+        let previous = self.arena.set_operator_overrides(Some(overrides.clone()));
+        let result = self.evaluate(rule, data);
+        self.arena.set_operator_overrides(previous);
+        result
+    }
+
+    /// Start an evaluation that runs a bounded number of steps at a time
+    ///
+    /// See [`Evaluation`] for interleaving evaluations with other work.
+    pub fn start_evaluation<'a>(
+        &'a self,
+        rule: &'a Logic<'a>,
+        data: &'a DataValue<'a>,
+    ) -> Evaluation<'a> {
+        Evaluation::new(self, rule, data, &self.arena)
+    }
+
+    /// Evaluate a rule with the regional settings of a single evaluation
+    ///
+    /// Date operators use the locale and time zone of the context when the
+    /// rule does not give them, so services handling several regions can
+    /// share one engine. The context is removed once the evaluation returns.
+    /// See [`EvaluationContext`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, EvaluationContext};
+    /// use chrono::FixedOffset;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl
+    ///     .parse_logic(r#"{"in_schedule": [{"var": "at"}, "Mon-Fri 09:00-17:00"]}"#, None)
+    ///     .unwrap();
+    /// let data = dl.parse_data(r#"{"at": "2024-06-03T07:30:00Z"}"#).unwrap();
+    ///
+    /// // 07:30 UTC is before opening hours in London, but not in Berlin
+    /// let london = EvaluationContext::new().with_timezone(FixedOffset::east_opt(3600).unwrap());
+    /// let berlin = EvaluationContext::new().with_timezone(FixedOffset::east_opt(7200).unwrap());
+    /// assert_eq!(dl.evaluate_with_context(&rule, &data, &london).unwrap().as_bool(), Some(false));
+    /// assert_eq!(dl.evaluate_with_context(&rule, &data, &berlin).unwrap().as_bool(), Some(true));
+    /// ```
+    pub fn evaluate_with_context<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+        context: &EvaluationContext,
+    ) -> Result<&'a DataValue<'a>> {
+        let previous = self.arena.set_evaluation_context(Some(context.clone()));
+        let result = self.evaluate(rule, data);
+        self.arena.set_evaluation_context(previous);
+        result
+    }
+
+    /// Evaluate a rule, making its independent custom operator calls concurrently
+    ///
+    /// Custom operator calls whose arguments only depend on the data, such as
+    /// several external lookups joined by an `and`, are made up front on at
+    /// most `max_parallelism` threads before the rule is evaluated with their
+    /// responses. Calls inside iterators or the branches of an `if`, or taking
+    /// the result of another custom operator, are still made during the
+    /// evaluation.
+    ///
+    /// The calls are made even when short-circuiting would skip them, for
+    /// example in the tail of an `and`, so only use this with operators that
+    /// are free of side effects. Operators run in their own arenas and
+    /// exchange values as JSON, so they cannot share host objects with the
+    /// evaluation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue};
+    /// use std::time::Duration;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_simple_operator("credit_check", |args, _| {
+    ///     std::thread::sleep(Duration::from_millis(50));
+    ///     Ok(DataValue::Bool(args[0].as_i64() != Some(13)))
+    /// });
+    ///
+    /// let rule = dl.parse_logic(r#"{"and": [
+    ///     {"credit_check": [{"var": "applicant"}]},
+    ///     {"credit_check": [{"var": "guarantor"}]},
+    ///     {"credit_check": [{"var": "spouse"}]}
+    /// ]}"#, None).unwrap();
+    /// let data = dl.parse_data(r#"{"applicant": 1, "guarantor": 2, "spouse": 3}"#).unwrap();
+    ///
+    /// // The three checks are made at the same time
+    /// let result = dl.evaluate_concurrent(&rule, &data, 3).unwrap();
+    /// assert_eq!(result.as_bool(), Some(true));
+    /// ```
+    pub fn evaluate_concurrent<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+        max_parallelism: usize,
+    ) -> Result<&'a DataValue<'a>> {
+        self.evaluate_prefetched(rule, data, max_parallelism, false)
+    }
+
+    /// Evaluate a rule, speculatively making the calls of both `if` branches
+    ///
+    /// Like [`evaluate_concurrent`](Self::evaluate_concurrent), but custom
+    /// operator calls in the branches of an `if` are also made up front,
+    /// concurrently with the calls of its condition. The responses of the
+    /// branch that is not taken are discarded. This hides the latency of a
+    /// slow condition when the branches are pure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue};
+    /// use std::time::Duration;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_simple_operator("fraud_score", |_, _| {
+    ///     std::thread::sleep(Duration::from_millis(50));
+    ///     Ok(DataValue::integer(12))
+    /// });
+    /// dl.register_simple_operator("price", |args, _| {
+    ///     std::thread::sleep(Duration::from_millis(50));
+    ///     Ok(DataValue::integer(args[0].as_i64().unwrap_or(0) * 2))
+    /// });
+    ///
+    /// let rule = dl.parse_logic(r#"{"if": [
+    ///     {">": [{"fraud_score": []}, 50]},
+    ///     "review",
+    ///     {"price": [{"var": "amount"}]}
+    /// ]}"#, None).unwrap();
+    /// let data = dl.parse_data(r#"{"amount": 21}"#).unwrap();
+    ///
+    /// // The price is looked up while the fraud score is computed
+    /// let result = dl.evaluate_speculative(&rule, &data, 2).unwrap();
+    /// assert_eq!(result.as_i64(), Some(42));
+    /// ```
+    pub fn evaluate_speculative<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+        max_parallelism: usize,
+    ) -> Result<&'a DataValue<'a>> {
+        self.evaluate_prefetched(rule, data, max_parallelism, true)
+    }
+
+    /// Prefetch the independent custom operator calls of a rule and evaluate it
+    fn evaluate_prefetched<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+        max_parallelism: usize,
+        speculative: bool,
+    ) -> Result<&'a DataValue<'a>> {
+        self.arena.set_root_context(data);
+        self.arena
+            .set_current_context(data, &DataValue::String("$"));
+
+        let mut tokens = Vec::new();
+        concurrent::independent_calls(rule.root(), speculative, &mut tokens);
+        let mut calls: Vec<PendingCall> = Vec::new();
+        for token in tokens {
+            if let Some(call) = concurrent::pending_call(token, &self.arena) {
+                if !calls.contains(&call) {
+                    calls.push(call);
+                }
+            }
+        }
+        if calls.len() < 2 || max_parallelism < 2 {
+            return self.evaluate(rule, data);
+        }
+
+        let responses = concurrent::run_calls(
+            &self.arena.custom_operator_registry(),
+            &calls,
+            data,
+            max_parallelism,
+            self.arena.panic_isolation(),
+        );
+
+        let mut prefetched: HashMap<String, Prefetched> = HashMap::new();
+        for (call, response) in calls.into_iter().zip(responses) {
+            let key = call.key();
+            prefetched
+                .entry(call.name.clone())
+                .or_insert_with(|| Prefetched {
+                    name: call.name,
+                    responses: HashMap::new(),
+                })
+                .responses
+                .insert(key, response);
+        }
+        let overrides = prefetched
+            .into_iter()
+            .fold(OperatorOverrides::new(), |overrides, (name, operator)| {
+                overrides.with_operator(&name, Box::new(operator))
+            });
+        self.evaluate_with_overrides(rule, data, &overrides)
+    }
+
+    /// Evaluate a rule and capture everything it depended on
+    ///
+    /// The returned bundle holds the rule, the data it read, the parser
+    /// format, the time returned by `now` and the responses of registered
+    /// custom operators, along with the outcome. Errors raised while
+    /// evaluating are recorded in the bundle; only parse errors are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue};
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_simple_operator("credit_score", |_, _| Ok(DataValue::integer(720)));
+    ///
+    /// let rule = json!({">=": [{"credit_score": [{"var": "id"}]}, 700]});
+    /// let bundle = dl.capture(&rule, &json!({"id": 7, "notes": "..."}), None).unwrap();
+    /// assert_eq!(bundle.data, json!({"id": 7}));
+    ///
+    /// // The bundle replays without the credit_score operator
+    /// let replayed = DataLogic::new().replay(&bundle).unwrap();
+    /// assert_eq!(Ok(replayed), bundle.outcome);
+    /// ```
+    pub fn capture(
+        &self,
+        rule: &JsonValue,
+        data: &JsonValue,
+        format: Option<&str>,
+    ) -> Result<ReplayBundle> {
+        let (logic, report) = self.compile_with_report(&rule.to_string(), format)?;
+
+        let clock = Utc::now();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut overrides =
+            OperatorOverrides::new().with_operator("now", Box::new(FixedClock(clock)));
+        for name in report.operators.keys() {
+            if self.has_custom_operator(name) {
+                let recorder = Recorder {
+                    name: name.clone(),
+                    calls: Arc::clone(&calls),
+                };
+                overrides = overrides.with_operator(name, Box::new(recorder));
+            }
+        }
+
+        let data_value = self.parse_data_json(data)?;
+        let outcome = self
+            .evaluate_with_overrides(&logic, &data_value, &overrides)
+            .map(|result| result.to_json())
+            .map_err(|error| error.to_string());
+
+        let data = if report.dynamic_access {
+            data.clone()
+        } else {
+            project_data(data, report.variables.iter().map(String::as_str))
+        };
+        let calls = std::mem::take(&mut *calls.lock().unwrap());
+
+        Ok(ReplayBundle {
+            rule: rule.clone(),
+            data,
+            format: format.map(str::to_string),
+            config: ReplayConfig::new(&self.arena.settings(), self.parser_config),
+            clock,
+            calls,
+            outcome,
+        })
+    }
+
+    /// Re-execute a captured evaluation
+    ///
+    /// The clock is pinned to the captured time and custom operators answer
+    /// from the recorded responses, so registered operators are not called.
+    /// A call that was not recorded fails with a custom error. The rule runs
+    /// under the recorded settings, whatever the settings of this engine.
+    pub fn replay(&self, bundle: &ReplayBundle) -> Result<JsonValue> {
+        let previous = self
+            .arena
+            .replace_settings(bundle.config.apply(self.arena.settings()));
+        let result = self.replay_with_settings(bundle);
+        self.arena.replace_settings(previous);
+        result
+    }
+
+    /// Re-execute a captured evaluation under the settings already applied
+    fn replay_with_settings(&self, bundle: &ReplayBundle) -> Result<JsonValue> {
+        let token = match bundle
+            .format
+            .as_deref()
+            .unwrap_or(self.parsers.default_format())
+        {
+            "jsonlogic" => JsonLogicParser::with_config(bundle.config.parser_config())
+                .parse_json(&bundle.rule, &self.arena)?,
+            format => self
+                .parsers
+                .parse_json(&bundle.rule, Some(format), &self.arena)?,
+        };
+        let logic = Logic::new(optimize(token, &self.arena)?, &self.arena);
+
+        let mut overrides =
+            OperatorOverrides::new().with_operator("now", Box::new(FixedClock(bundle.clock)));
+        for call in &bundle.calls {
+            if !overrides.contains(&call.operator) {
+                let playback = Playback::new(&call.operator, &bundle.calls);
+                overrides = overrides.with_operator(&call.operator, Box::new(playback));
+            }
+        }
+
+        let data = self.parse_data_json(&bundle.data)?;
+        let result = self.evaluate_with_overrides(&logic, &data, &overrides)?;
+        Ok(result.to_json())
+    }
+
+    /// Evaluate the fallback of a failed rule and record the event
+    fn evaluate_fallback<'a>(
+        &'a self,
+        fallback: &Fallback,
+        error: LogicError,
+        data: &'a DataValue,
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let result = match fallback {
+            Fallback::Value(value) => Ok(arena.alloc(DataValue::from_json(value, arena))),
+            Fallback::Rule(rule) => self.parsers.parse_json(rule, None, arena).and_then(|root| {
+                // The primary rule may have left a nested context behind
+                arena.set_root_context(data);
+                arena.set_current_context(data, &DataValue::String("$"));
+                evaluate(root, arena)
+            }),
+        };
+
+        let event = FallbackEvent {
+            error: error.clone(),
+            fallback_error: result.as_ref().err().cloned(),
+            rule_id: arena.rule_id(),
+        };
+        self.fallback_events.borrow_mut().push(event);
+
+        result.map_err(|_| error)
+    }
+
+    /// Evaluate a scoring rule and return a per-factor breakdown
+    ///
+    /// Instead of a bare number, this returns an object with the `total` score
+    /// and a `contributions` array holding the value of each argument of the
+    /// top-level `+` operator. Rules that are not a sum are reported as a
+    /// single contribution.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, ToJson};
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{"+": [
+    ///     {"if": [{">": [{"var": "income"}, 5000]}, 40, 0]},
+    ///     {"if": [{"var": "has_default"}, -30, 10]}
+    /// ]}"#, None).unwrap();
+    /// let data = dl.parse_data(r#"{"income": 6000, "has_default": false}"#).unwrap();
+    ///
+    /// let breakdown = dl.evaluate_score_breakdown(&rule, &data).unwrap();
+    /// assert_eq!(breakdown.to_json(), json!({
+    ///     "total": 50,
+    ///     "contributions": [{"index": 0, "value": 40}, {"index": 1, "value": 10}]
+    /// }));
+    /// ```
+    pub fn evaluate_score_breakdown<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+    ) -> Result<&'a DataValue<'a>> {
+        self.arena.set_root_context(data);
+        self.arena
+            .set_current_context(data, &DataValue::String("$"));
+
+        evaluate_score_breakdown(rule.root(), &self.arena)
+    }
+
+    /// Evaluate a rule and report the cost of each of its subexpressions
+    ///
+    /// Returns an object with the `result` of the rule and a `cost` tree
+    /// mirroring the rule. Every operator node holds its number of `calls`,
+    /// its total wall time in `time_ns` and the arena bytes it allocated in
+    /// `allocated_bytes`, including its `args`. This shows which branch of a
+    /// slow rule is expensive, for example to move a cheap test to the front
+    /// of an `and`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, ToJson};
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{"and": [
+    ///     {"var": "active"},
+    ///     {"some": [{"var": "items"}, {">": [{"var": ""}, 10]}]}
+    /// ]}"#, None).unwrap();
+    /// let data = dl.parse_data(r#"{"items": [1, 2, 30], "active": true}"#).unwrap();
+    ///
+    /// let report = dl.evaluate_with_costs(&rule, &data).unwrap().to_json();
+    /// assert_eq!(report["result"], true);
+    ///
+    /// let some = &report["cost"]["args"][1];
+    /// assert_eq!(some["expression"], "some");
+    /// assert_eq!(some["args"][1]["calls"], 3);
+    /// ```
+    pub fn evaluate_with_costs<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+    ) -> Result<&'a DataValue<'a>> {
+        self.arena.set_root_context(data);
+        self.arena
+            .set_current_context(data, &DataValue::String("$"));
+
+        evaluate_with_costs(rule.root(), &self.arena)
+    }
+
+    /// Evaluate a rule, reporting where it failed on error
+    ///
+    /// Behaves like [`evaluate`](Self::evaluate) without a fallback policy,
+    /// but a failure is returned as a [`DetailedError`] holding the path of
+    /// the failing sub-expression within the rule, its operator and the values
+    /// its arguments had when it failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{"if": [
+    ///     {"var": "vip"},
+    ///     {"*": [{"var": "total"}, 0.9]},
+    ///     {"-": [{"var": "total"}, {"var": "coupon"}]}
+    /// ]}"#, None).unwrap();
+    /// let data = dl.parse_data(r#"{"vip": false, "total": 100, "coupon": "SAVE10"}"#).unwrap();
+    ///
+    /// let error = dl.evaluate_with_diagnostics(&rule, &data).unwrap_err();
+    /// assert_eq!(error.path, "/if/2");
+    /// assert_eq!(error.operator.as_deref(), Some("-"));
+    /// assert_eq!(error.arguments, vec![json!(100), json!("SAVE10")]);
+    /// ```
+    pub fn evaluate_with_diagnostics<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+    ) -> std::result::Result<&'a DataValue<'a>, Box<DetailedError>> {
+        self.arena.clear_host_objects();
+        self.arena.set_root_context(data);
+        self.arena
+            .set_current_context(data, &DataValue::String("$"));
+
+        evaluate_with_diagnostics(rule.root(), &self.arena)
+    }
+
+    /// Evaluate a rule, recording every implicit type coercion it performs
+    ///
+    /// Returns the result together with the [`Coercion`]s of operands, such
+    /// as a string converted to a number by `+` or `<`, a `null` converted to
+    /// `0`, or an array converted to a string by `cat`. Each coercion holds
+    /// the path of the sub-expression within the rule, so rules relying on
+    /// loose typing can be audited before being made stricter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::DataLogic;
+    /// use serde_json::json;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl.parse_logic(r#"{"and": [
+    ///     {"var": "active"},
+    ///     {">=": [{"var": "age"}, 18]}
+    /// ]}"#, None).unwrap();
+    /// let data = dl.parse_data(r#"{"active": true, "age": "42"}"#).unwrap();
+    ///
+    /// let (result, coercions) = dl.evaluate_with_coercion_trace(&rule, &data).unwrap();
+    /// assert_eq!(result.as_bool(), Some(true));
+    /// assert_eq!(coercions.len(), 1);
+    /// assert_eq!(coercions[0].path, "/and/1");
+    /// assert_eq!((coercions[0].from, coercions[0].to), ("string", "number"));
+    /// assert_eq!(coercions[0].value, json!("42"));
+    /// ```
+    pub fn evaluate_with_coercion_trace<'a>(
+        &'a self,
+        rule: &'a Logic,
+        data: &'a DataValue,
+    ) -> Result<(&'a DataValue<'a>, Vec<Coercion>)> {
+        self.arena.clear_host_objects();
+        self.arena.set_root_context(data);
+        self.arena
+            .set_current_context(data, &DataValue::String("$"));
+
+        evaluate_with_coercion_trace(rule.root(), &self.arena)
+    }
+
+    /// Evaluate a rule against generated data through every engine path
+    ///
+    /// Each document is evaluated with the rule as parsed, the optimized rule
+    /// and a [`CompiledRule`]. Documents on which the results differ are
+    /// returned. The arena is reset after every document, so rules parsed
+    /// earlier must not be used afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::datagen::DataGenerator;
+    /// use datalogic_rs::DataLogic;
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let rule = json!({"<": [{"var": "qty"}, {"*": [2, 5]}]});
+    /// let mut generator = DataGenerator::for_rule(&rule).with_seed(3);
+    ///
+    /// assert!(dl.fuzz(&rule, &mut generator, 100).unwrap().is_empty());
+    /// ```
+    pub fn fuzz(
+        &mut self,
+        rule: &JsonValue,
+        generator: &mut DataGenerator,
+        iterations: usize,
+    ) -> Result<Vec<Divergence>> {
+        let source = rule.to_string();
+        let compiled = self.compile(&source, None)?;
+
+        let mut divergences = Vec::new();
+        for _ in 0..iterations {
+            divergences.extend(self.fuzz_once(rule, &source, &compiled, generator.generate()));
+        }
+        Ok(divergences)
+    }
+
+    /// Fuzz the engine paths on a rule until a budget runs out
+    ///
+    /// Like [`fuzz`](Self::fuzz), with one generated document per step of
+    /// the budget. The result holds the divergences found before the budget
+    /// ran out, so the search can be bounded in time or cancelled from
+    /// another thread. See [`Budget`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::budget::{Budget, StopReason};
+    /// use datalogic_rs::datagen::DataGenerator;
+    /// use datalogic_rs::DataLogic;
+    /// use serde_json::json;
+    /// use std::time::Duration;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// let rule = json!({"<": [{"var": "qty"}, 10]});
+    /// let mut generator = DataGenerator::for_rule(&rule).with_seed(3);
+    /// let budget = Budget::new()
+    ///     .with_time_limit(Duration::from_secs(5))
+    ///     .with_max_steps(50);
+    ///
+    /// let partial = dl.fuzz_with_budget(&rule, &mut generator, budget).unwrap();
+    /// assert!(partial.result.is_empty());
+    /// assert_eq!((partial.steps, partial.stop), (50, StopReason::StepLimit));
+    /// ```
+    pub fn fuzz_with_budget(
+        &mut self,
+        rule: &JsonValue,
+        generator: &mut DataGenerator,
+        mut budget: Budget,
+    ) -> Result<Partial<Vec<Divergence>>> {
+        let source = rule.to_string();
+        let compiled = self.compile(&source, None)?;
+
+        let mut divergences = Vec::new();
+        let stop = loop {
+            if let Err(reason) = budget.step() {
+                break reason;
+            }
+            divergences.extend(self.fuzz_once(rule, &source, &compiled, generator.generate()));
+        };
+        Ok(budget.finish(divergences, stop))
+    }
+
+    /// Evaluate a rule through every engine path, returning the results if
+    /// they disagree
+    fn fuzz_once(
+        &mut self,
+        rule: &JsonValue,
+        source: &str,
+        compiled: &CompiledRule,
+        data: JsonValue,
+    ) -> Option<Divergence> {
+        let interpreted = self.evaluate_json(rule, &data, None);
+        let optimized = self.parse_logic(source, None).and_then(|logic| {
+            let data = self.parse_data_json(&data)?;
+            self.evaluate(&logic, &data).map(ToJson::to_json)
+        });
+        let compiled = self
+            .parse_data_json(&data)
+            .and_then(|data| self.evaluate_compiled(compiled, &data).map(ToJson::to_json));
+        self.reset_arena();
+
+        (interpreted != optimized || optimized != compiled).then_some(Divergence {
+            data,
+            interpreted,
+            optimized,
+            compiled,
+        })
+    }
+
+    /// Compare the outcomes of two versions of a rule over a dataset
     ///
-    /// A Result containing a reference to the evaluation result as a DataValue
+    /// Both rules are evaluated against every input, and the comparison
+    /// counts the inputs whose outcome changed, keeps the first of them as
+    /// examples and counts every pair of outcomes. For a rule that is an
+    /// `if`, its conditions are evaluated once more to count the inputs
+    /// taking each branch. See [`RuleComparison`].
     ///
     /// # Examples
     ///
     /// ```
     /// use datalogic_rs::DataLogic;
+    /// use serde_json::json;
     ///
     /// let dl = DataLogic::new();
-    /// let rule = dl.parse_logic(r#"{ ">": [{"var": "temp"}, 100] }"#, None).unwrap();
-    /// let data = dl.parse_data(r#"{"temp": 110}"#).unwrap();
-    /// let result = dl.evaluate(&rule, &data).unwrap();
-    /// assert_eq!(result.to_string(), "true");
+    /// let current = dl.compile(r#"{">=": [{"var": "age"}, 18]}"#, None).unwrap();
+    /// let proposed = dl.compile(r#"{">=": [{"var": "age"}, 21]}"#, None).unwrap();
+    /// let dataset = [json!({"age": 16}), json!({"age": 19}), json!({"age": 30})];
+    ///
+    /// let comparison = dl.compare_rules(&current, &proposed, &dataset);
+    /// assert_eq!(comparison.changed, 1);
+    /// assert_eq!(comparison.examples[0].data, json!({"age": 19}));
     /// ```
-    pub fn evaluate<'a>(
+    pub fn compare_rules<'d, I>(
+        &self,
+        before: &CompiledRule,
+        after: &CompiledRule,
+        dataset: I,
+    ) -> RuleComparison
+    where
+        I: IntoIterator<Item = &'d JsonValue>,
+    {
+        let mut comparison = RuleComparison {
+            branches_before: if_branch_count(before.root()).map(BranchStats::new),
+            branches_after: if_branch_count(after.root()).map(BranchStats::new),
+            ..RuleComparison::default()
+        };
+
+        let mut scratch = self.scratch_arena();
+        for record in dataset {
+            let (outcome_before, branch_before) =
+                self.evaluate_for_comparison(before, record, &scratch);
+            scratch.reset();
+            let (outcome_after, branch_after) =
+                self.evaluate_for_comparison(after, record, &scratch);
+            scratch.reset();
+
+            let changed = outcome_before != outcome_after;
+            for (stats, branch) in [
+                (&mut comparison.branches_before, branch_before),
+                (&mut comparison.branches_after, branch_after),
+            ] {
+                if let (Some(stats), Some(branch)) = (stats, branch) {
+                    stats.record(branch, changed);
+                }
+            }
+            comparison.record(record, outcome_before, outcome_after);
+        }
+        comparison
+    }
+
+    /// Evaluate a rule for a comparison, returning its outcome and the
+    /// branch taken if the rule is an `if` whose conditions all evaluate
+    fn evaluate_for_comparison(
+        &self,
+        rule: &CompiledRule,
+        record: &JsonValue,
+        scratch: &DataArena,
+    ) -> (Result<JsonValue>, Option<usize>) {
+        let data = borrow_json(record, scratch);
+        let outcome = self
+            .evaluate_compiled_in(rule, &data, scratch)
+            .map(|result| result.to_json());
+
+        let branch = self.taken_branch(rule.root(), &data, scratch);
+        (outcome, branch)
+    }
+
+    /// Find the branch of an `if` taken for the data, if the rule is an `if`
+    /// whose conditions evaluate
+    fn taken_branch<'a>(
         &'a self,
-        rule: &'a Logic,
+        root: &'a Token<'a>,
         data: &'a DataValue,
-    ) -> Result<&'a DataValue<'a>> {
-        // Set both current context and root context to the data
-        self.arena.set_root_context(data);
-        self.arena
-            .set_current_context(data, &DataValue::String("$"));
+        scratch: &'a DataArena,
+    ) -> Option<usize> {
+        let Token::Operator {
+            op_type: OperatorType::Control(ControlOp::If),
+            args: Token::ArrayLiteral(items),
+        } = root
+        else {
+            return None;
+        };
 
-        // Evaluate the rule with the data as context
-        evaluate(rule.root(), &self.arena)
+        for (branch, pair) in items.chunks_exact(2).enumerate() {
+            if self
+                .evaluate_root(pair[0], data, scratch)
+                .ok()?
+                .coerce_to_bool()
+            {
+                return Some(branch);
+            }
+        }
+        Some(items.len() / 2)
     }
 
     /// Evaluate using JSON values directly
     ///
     /// This method evaluates a logic rule against data, both provided as JSON values.
     /// It parses the logic and data from JSON, evaluates the rule, and returns
-    /// the result as a JSON value.
+    /// the result as a JSON value. The strings of the data are borrowed rather
+    /// than copied into the arena.
     ///
     /// # Arguments
     ///
@@ -237,7 +2058,8 @@ impl DataLogic {
         format: Option<&str>,
     ) -> Result<JsonValue> {
         let rule = self.parse_logic_json(logic, format)?;
-        let data_value = self.parse_data_json(data)?;
+        // The data only needs to live for this call, so its strings are borrowed
+        let data_value = borrow_json(data, &self.arena);
         let result = self.evaluate(&rule, &data_value)?;
         Ok(result.to_json())
     }
@@ -319,6 +2141,18 @@ impl DataLogic {
     }
 }
 
+/// Returns the number of branches of a rule that is an `if`, counting the
+/// else branch, or the lack of one.
+fn if_branch_count(root: &Token) -> Option<usize> {
+    match root {
+        Token::Operator {
+            op_type: OperatorType::Control(ControlOp::If),
+            args: Token::ArrayLiteral(items),
+        } => Some(items.len() / 2 + 1),
+        _ => None,
+    }
+}
+
 impl Default for DataLogic {
     fn default() -> Self {
         Self::new()
@@ -329,8 +2163,43 @@ impl Default for DataLogic {
 mod tests {
     use super::*;
     use crate::arena::DataArena;
+    use crate::logic::Token;
     use crate::value::{DataValue, NumberValue};
     use serde_json::json;
+    use std::sync::{Condvar, Mutex};
+    use std::time::Duration;
+
+    /// Lets the calls of a custom operator wait for each other, proving that
+    /// they overlap without relying on how long they take.
+    struct Rendezvous {
+        arrived: Mutex<usize>,
+        all_arrived: Condvar,
+    }
+
+    impl Rendezvous {
+        const fn new() -> Self {
+            Self {
+                arrived: Mutex::new(0),
+                all_arrived: Condvar::new(),
+            }
+        }
+
+        /// Waits until `count` calls have arrived, failing after a deadline
+        /// that only a sequential evaluation reaches.
+        fn meet(&self, count: usize) -> std::result::Result<(), String> {
+            let mut arrived = self.arrived.lock().unwrap();
+            *arrived += 1;
+            self.all_arrived.notify_all();
+            let (arrived, timeout) = self
+                .all_arrived
+                .wait_timeout_while(arrived, Duration::from_secs(10), |arrived| *arrived < count)
+                .unwrap();
+            if timeout.timed_out() {
+                return Err(format!("only {} of {} calls overlapped", *arrived, count));
+            }
+            Ok(())
+        }
+    }
 
     #[derive(Debug)]
     struct MultiplyAll;
@@ -380,4 +2249,796 @@ mod tests {
 
         assert_eq!(result.as_f64().unwrap(), 24.0);
     }
+
+    #[test]
+    fn test_scratch_arena() {
+        let mut dl = DataLogic::new();
+        dl.register_custom_operator("multiply_all", Box::new(MultiplyAll));
+        let rule = dl
+            .parse_logic(
+                r#"{"map": [{"var": "items"}, {"multiply_all": [{"var": ""}, 2]}]}"#,
+                None,
+            )
+            .unwrap();
+        let rule_memory = dl.arena().used_bytes();
+
+        let mut scratch = dl.scratch_arena();
+        for round in 0..3 {
+            let data = DataValue::from_json(&json!({"items": [1, 2, round]}), &scratch);
+            let result = dl.evaluate_in(&rule, &data, &scratch).unwrap();
+            assert_eq!(result.to_json(), json!([2, 4, round * 2]));
+            scratch.reset();
+        }
+
+        // Evaluations only allocated in the scratch arena
+        assert_eq!(dl.arena().used_bytes(), rule_memory);
+    }
+
+    #[test]
+    fn test_scratch_arena_takes_settings() {
+        let mut dl = DataLogic::new();
+        dl.set_integer_overflow(IntegerOverflow::Error);
+        let context = crate::EvaluationContext::new()
+            .with_locale("fr")
+            .with_timezone(chrono::FixedOffset::east_opt(3600).unwrap());
+        dl.arena.set_evaluation_context(Some(context.clone()));
+
+        let scratch = dl.scratch_arena();
+        assert_eq!(scratch.integer_overflow(), IntegerOverflow::Error);
+        assert_eq!(scratch.evaluation_context(), Some(context));
+
+        let rule = dl
+            .parse_logic(r#"{"format_date": [{"var": "at"}, "%d %B %H:%M"]}"#, None)
+            .unwrap();
+        let data = DataValue::from_json(&json!({"at": "2024-12-31T23:30:00Z"}), &scratch);
+        let result = dl.evaluate_in(&rule, &data, &scratch).unwrap();
+        assert_eq!(result.as_str(), Some("01 janvier 00:30"));
+    }
+
+    #[test]
+    fn test_evaluate_batch() {
+        let dl = DataLogic::new();
+        let rule = dl
+            .parse_logic(r#"{"cat": ["order-", {"var": ""}]}"#, None)
+            .unwrap();
+        let rule_memory = dl.arena().used_bytes();
+
+        let results: Vec<JsonValue> = dl
+            .evaluate_batch(&rule, (0..100).map(DataValue::integer))
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results.len(), 100);
+        assert_eq!(results[42], json!("order-42"));
+
+        // The results were allocated in the scratch arena
+        assert_eq!(dl.arena().used_bytes(), rule_memory);
+    }
+
+    #[test]
+    fn test_evaluate_window() {
+        #[derive(Debug)]
+        struct Counted(Arc<Mutex<usize>>);
+
+        impl CustomOperator for Counted {
+            fn evaluate<'a>(
+                &self,
+                args: &'a [DataValue<'a>],
+                arena: &'a DataArena,
+            ) -> Result<&'a DataValue<'a>> {
+                *self.0.lock().unwrap() += 1;
+                Ok(arena.alloc(args[0].clone()))
+            }
+        }
+
+        let calls = Arc::new(Mutex::new(0));
+        let mut dl = DataLogic::new();
+        dl.register_custom_operator("counted", Box::new(Counted(Arc::clone(&calls))));
+        let data = dl
+            .parse_data_json(&json!({"ids": (1..=100).collect::<Vec<_>>()}))
+            .unwrap();
+
+        // Only the items up to the end of the window are filtered
+        let rule = dl
+            .parse_logic(
+                r#"{"filter": [{"var": "ids"}, {"counted": [{"==": [{"%": [{"var": ""}, 2]}, 0]}]}]}"#,
+                None,
+            )
+            .unwrap();
+        let page = dl.evaluate_window(&rule, &data, 3, 2).unwrap();
+        assert_eq!(page.to_json(), json!([8, 10]));
+        assert_eq!(*calls.lock().unwrap(), 10);
+
+        // Other rules are sliced after evaluation
+        let rule = dl
+            .parse_logic(
+                r#"{"map": [{"var": "ids"}, {"*": [{"var": ""}, 10]}]}"#,
+                None,
+            )
+            .unwrap();
+        let page = dl.evaluate_window(&rule, &data, 98, 5).unwrap();
+        assert_eq!(page.to_json(), json!([990, 1000]));
+        let page = dl.evaluate_window(&rule, &data, 200, 5).unwrap();
+        assert_eq!(page.to_json(), json!([]));
+
+        let rule = dl.parse_logic(r#"{"var": "ids.0"}"#, None).unwrap();
+        let error = dl.evaluate_window(&rule, &data, 0, 5).unwrap_err();
+        assert!(matches!(error, LogicError::OutputTypeError { .. }));
+    }
+
+    #[test]
+    fn test_evaluate_window_dispatches_root_filter() {
+        #[derive(Debug)]
+        struct Tens;
+
+        impl CustomOperator for Tens {
+            fn evaluate<'a>(
+                &self,
+                _args: &'a [DataValue<'a>],
+                arena: &'a DataArena,
+            ) -> Result<&'a DataValue<'a>> {
+                let items = arena.vec_into_slice((10..15).map(DataValue::integer).collect());
+                Ok(arena.alloc(DataValue::Array(items)))
+            }
+        }
+
+        let dl = DataLogic::new();
+        let data = dl.parse_data(r#"{"ids": [1, 2, 3, 4, 5, 6]}"#).unwrap();
+        let rule = dl
+            .parse_logic(
+                r#"{"filter": [{"var": "ids"}, {"!==": [{"var": ""}, 1]}]}"#,
+                None,
+            )
+            .unwrap();
+
+        // An override of the root filter is used, and its result sliced
+        let overrides = crate::OperatorOverrides::new()
+            .with_simple_operator("filter", |_, _| Ok(DataValue::String("overridden")));
+        dl.arena.set_operator_overrides(Some(overrides.clone()));
+        let error = dl.evaluate_window(&rule, &data, 1, 2).unwrap_err();
+        assert!(matches!(error, LogicError::OutputTypeError { .. }));
+        let overrides = overrides.with_operator("filter", Box::new(Tens));
+        dl.arena.set_operator_overrides(Some(overrides));
+        let page = dl.evaluate_window(&rule, &data, 1, 2).unwrap();
+        assert_eq!(page.to_json(), json!([11, 12]));
+        dl.arena.set_operator_overrides(None);
+
+        let page = dl.evaluate_window(&rule, &data, 1, 2).unwrap();
+        assert_eq!(page.to_json(), json!([3, 4]));
+
+        // The root filter counts against the execution limits
+        let rule = dl
+            .parse_logic(r#"{"filter": [{"var": "ids"}, true]}"#, None)
+            .unwrap();
+        dl.arena
+            .set_limits(crate::Limits::new().with_max_operations(0));
+        let page = dl.evaluate_window(&rule, &data, 1, 2);
+        assert!(matches!(page, Err(LogicError::LimitExceeded { .. })));
+        dl.arena.set_limits(crate::Limits::new());
+    }
+
+    #[test]
+    fn test_fallback_policy() {
+        let mut dl = DataLogic::new();
+        let rule = json!({"if": [{"var": "vip"}, {"throw": "scoring_unavailable"}, 10]});
+
+        // Without a policy the error is returned
+        assert!(dl
+            .evaluate_json(&rule, &json!({"vip": true}), None)
+            .is_err());
+
+        // A secondary rule is evaluated against the same data
+        dl.set_fallback_policy(FallbackPolicy::new(Fallback::Rule(
+            json!({"*": [{"var": "base"}, 2]}),
+        )));
+        let result = dl
+            .evaluate_json(&rule, &json!({"vip": true, "base": 21}), None)
+            .unwrap();
+        assert_eq!(result, json!(42));
+
+        // Successful evaluations don't record events
+        let result = dl.evaluate_json(&rule, &json!({"vip": false}), None);
+        assert_eq!(result.unwrap(), json!(10));
+
+        let events = dl.take_fallback_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].error,
+            LogicError::ThrownError {
+                r#type: "scoring_unavailable".to_string()
+            }
+        );
+        assert!(dl.fallback_events().is_empty());
+
+        // If the fallback fails too, the primary error is returned
+        dl.set_fallback_policy(FallbackPolicy::new(Fallback::Rule(
+            json!({"throw": "also_down"}),
+        )));
+        let result = dl.evaluate_json(&rule, &json!({"vip": true}), None);
+        assert!(
+            matches!(result, Err(LogicError::ThrownError { r#type }) if r#type == "scoring_unavailable")
+        );
+        assert!(dl.fallback_events()[0].fallback_error.is_some());
+
+        // Filters restrict the errors the policy applies to
+        dl.set_fallback_policy(
+            FallbackPolicy::new(Fallback::Value(json!(0)))
+                .with_filter(|error| matches!(error, LogicError::NaNError)),
+        );
+        assert!(dl
+            .evaluate_json(&rule, &json!({"vip": true}), None)
+            .is_err());
+        let result = dl.evaluate_json(&json!({"+": ["abc", 1]}), &json!({}), None);
+        assert_eq!(result.unwrap(), json!(0));
+
+        dl.clear_fallback_policy();
+        assert!(dl
+            .evaluate_json(&json!({"+": ["abc", 1]}), &json!({}), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_evaluate_with_overrides() {
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("tier", |_, _| Ok(DataValue::String("bronze")));
+
+        let rule = dl
+            .parse_logic(
+                r#"{"cat": [{"tier": []}, "/", {"lookup_account": [{"var": "id"}]}]}"#,
+                None,
+            )
+            .unwrap();
+        let data = dl.parse_data(r#"{"id": 7}"#).unwrap();
+
+        // Unknown custom operators fail without overrides
+        assert!(dl.evaluate(&rule, &data).is_err());
+
+        let overrides = OperatorOverrides::new()
+            .with_value("lookup_account", json!("acct-7"))
+            .with_simple_operator("tier", |_, _| Ok(DataValue::String("gold")));
+        let result = dl
+            .evaluate_with_overrides(&rule, &data, &overrides)
+            .unwrap();
+        assert_eq!(result.as_str(), Some("gold/acct-7"));
+
+        // Built-in operators can be overridden too
+        let rule = dl.parse_logic(r#"{"+": [{"var": "x"}, 1]}"#, None).unwrap();
+        let data = dl.parse_data(r#"{"x": 1}"#).unwrap();
+        let overrides = OperatorOverrides::new().with_value("+", json!(100));
+        let result = dl
+            .evaluate_with_overrides(&rule, &data, &overrides)
+            .unwrap();
+        assert_eq!(result.as_i64(), Some(100));
+
+        // Overrides do not outlive the call
+        assert!(!dl.arena().has_operator_overrides());
+        assert_eq!(dl.evaluate(&rule, &data).unwrap().as_i64(), Some(2));
+    }
+
+    #[test]
+    fn test_capture_and_replay() {
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("risk", |args, _| {
+            let id = args.first().and_then(|arg| arg.as_i64()).unwrap_or(0);
+            Ok(DataValue::integer(id * 10))
+        });
+
+        let rule = json!({"cat": [
+            {"risk": [{"var": "a"}]},
+            "/",
+            {"risk": [{"var": "b"}]},
+            "/",
+            {"format_date": [{"now": []}, "yyyy"]}
+        ]});
+        let data = json!({"a": 1, "b": 2, "unused": true});
+        let bundle = dl.capture(&rule, &data, None).unwrap();
+
+        assert_eq!(bundle.data, json!({"a": 1, "b": 2}));
+        assert_eq!(bundle.calls.len(), 2);
+        assert_eq!(bundle.calls[1].args, json!([2]));
+        let year = bundle.clock.format("%Y").to_string();
+        assert_eq!(bundle.outcome, Ok(json!(format!("10/20/{}", year))));
+
+        // Replays from storage without the custom operator
+        let stored = ReplayBundle::from_json(&bundle.to_json()).unwrap();
+        let replayer = DataLogic::new();
+        assert_eq!(Ok(replayer.replay(&stored).unwrap()), bundle.outcome);
+
+        // Calls that were not recorded fail
+        let mut tampered = stored;
+        tampered.data = json!({"a": 1, "b": 3});
+        assert!(replayer.replay(&tampered).is_err());
+
+        // Dynamic access keeps the whole data
+        let bundle = dl.capture(&json!({"val": "unused"}), &data, None).unwrap();
+        assert_eq!(bundle.data, data);
+        assert_eq!(bundle.outcome, Ok(json!(true)));
+    }
+
+    #[test]
+    fn test_replay_applies_captured_settings() {
+        let mut dl = DataLogic::new();
+        dl.set_integer_overflow(IntegerOverflow::Saturate);
+        dl.set_rule_list(RuleList::Any);
+
+        let rule = json!([{"var": "flag"}, {"+": [{"var": "max"}, 1]}]);
+        let data = json!({"flag": false, "max": i64::MAX});
+        let bundle = dl.capture(&rule, &data, None).unwrap();
+        assert_eq!(bundle.outcome, Ok(json!(i64::MAX)));
+
+        // An engine at the defaults reproduces the decision
+        let stored = ReplayBundle::from_json(&bundle.to_json()).unwrap();
+        assert_eq!(stored.config, bundle.config);
+        let replayer = DataLogic::new();
+        assert_eq!(Ok(replayer.replay(&stored).unwrap()), bundle.outcome);
+
+        // and keeps its own settings afterwards
+        let logic = replayer
+            .parse_logic(r#"{"+": [{"var": "max"}, 1]}"#, None)
+            .unwrap();
+        let data = replayer.parse_data_json(&data).unwrap();
+        let result = replayer.evaluate(&logic, &data).unwrap();
+        assert_eq!(result.to_json(), json!(i64::MAX as f64 + 1.0));
+    }
+
+    #[test]
+    fn test_global_operators() {
+        crate::register_global_simple_operator("test_global_region", |_, _| {
+            Ok(DataValue::String("eu"))
+        });
+
+        let rule = json!({"test_global_region": []});
+        let mut dl = DataLogic::new();
+        assert!(dl.has_custom_operator("test_global_region"));
+        assert_eq!(
+            dl.evaluate_json(&rule, &json!({}), None).unwrap(),
+            json!("eu")
+        );
+
+        // Instance operators take precedence
+        dl.register_simple_operator("test_global_region", |_, _| Ok(DataValue::String("us")));
+        assert_eq!(
+            dl.evaluate_json(&rule, &json!({}), None).unwrap(),
+            json!("us")
+        );
+        let other = DataLogic::new();
+        assert_eq!(
+            other.evaluate_json(&rule, &json!({}), None).unwrap(),
+            json!("eu")
+        );
+
+        assert!(crate::unregister_global_operator("test_global_region"));
+        assert!(!crate::has_global_operator("test_global_region"));
+        assert!(other.evaluate_json(&rule, &json!({}), None).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_concurrent() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+        static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("test_lookup", |args, _| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            let active = ACTIVE.fetch_add(1, Ordering::SeqCst) + 1;
+            PEAK.fetch_max(active, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            ACTIVE.fetch_sub(1, Ordering::SeqCst);
+            match args[0].as_i64() {
+                Some(n) => Ok(DataValue::integer(n * 10)),
+                None => Err("expected a number".to_string()),
+            }
+        });
+
+        let rule = dl
+            .parse_logic(
+                r#"{"+": [
+                    {"test_lookup": [{"var": "a"}]},
+                    {"test_lookup": [{"var": "b"}]},
+                    {"test_lookup": [{"var": "c"}]},
+                    {"test_lookup": [{"var": "d"}]},
+                    {"test_lookup": [{"var": "a"}]},
+                    {"reduce": [
+                        {"var": "items"},
+                        {"+": [{"var": "accumulator"}, {"test_lookup": [{"var": "current"}]}]},
+                        0
+                    ]}
+                ]}"#,
+                None,
+            )
+            .unwrap();
+        let data = dl
+            .parse_data(r#"{"a": 1, "b": 2, "c": 3, "d": 4, "items": [5]}"#)
+            .unwrap();
+
+        let result = dl.evaluate_concurrent(&rule, &data, 2).unwrap();
+        assert_eq!(result.as_i64(), Some(160));
+        // Duplicate calls are made once, calls inside iterators during evaluation
+        assert_eq!(CALLS.load(Ordering::SeqCst), 5);
+        assert!(PEAK.load(Ordering::SeqCst) <= 2);
+
+        // Failing calls fail the evaluation as they would sequentially
+        let data = dl
+            .parse_data(r#"{"a": 1, "b": "x", "c": 3, "d": 4, "items": []}"#)
+            .unwrap();
+        let error = dl.evaluate_concurrent(&rule, &data, 4).unwrap_err();
+        assert_eq!(Err(error), dl.evaluate(&rule, &data));
+    }
+
+    #[test]
+    fn test_evaluate_concurrent_overlaps_calls() {
+        static LOOKUPS: Rendezvous = Rendezvous::new();
+
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("test_meet", |args, _| {
+            LOOKUPS.meet(2)?;
+            Ok(args[0].clone())
+        });
+        let rule = dl
+            .parse_logic(
+                r#"{"and": [{"test_meet": [{"var": "a"}]}, {"test_meet": [{"var": "b"}]}]}"#,
+                None,
+            )
+            .unwrap();
+        let data = dl.parse_data(r#"{"a": 1, "b": 2}"#).unwrap();
+
+        // Each call only returns once the other one has started
+        let result = dl.evaluate_concurrent(&rule, &data, 2).unwrap();
+        assert_eq!(result.as_i64(), Some(2));
+    }
+
+    /// Returns the field `b` of the data the operator was called with
+    fn read_data<'r>(
+        _args: Vec<DataValue<'r>>,
+        data: DataValue<'r>,
+    ) -> std::result::Result<DataValue<'r>, String> {
+        let DataValue::Object(entries) = data else {
+            return Ok(DataValue::Null);
+        };
+        let field = entries.iter().find(|(key, _)| *key == "b");
+        Ok(field.map_or(DataValue::Null, |(_, value)| value.clone()))
+    }
+
+    /// Wraps its argument into a host object
+    #[derive(Debug)]
+    struct Boxed;
+
+    impl CustomOperator for Boxed {
+        fn evaluate<'a>(
+            &self,
+            args: &'a [DataValue<'a>],
+            arena: &'a DataArena,
+        ) -> Result<&'a DataValue<'a>> {
+            Ok(arena.alloc_host_object(args[0].to_json()))
+        }
+    }
+
+    /// Returns the JSON of a value, with host objects replaced by their content
+    fn unboxed(value: &DataValue, arena: &DataArena) -> JsonValue {
+        match value {
+            DataValue::Array(items) => items.iter().map(|item| unboxed(item, arena)).collect(),
+            DataValue::Custom(_) => json!({
+                "boxed": *arena.host_object::<JsonValue>(value).unwrap()
+            }),
+            value => value.to_json(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_concurrent_matches_evaluate() {
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("test_read_data", read_data);
+        dl.register_custom_operator("test_box", Box::new(Boxed));
+        let rule = dl
+            .parse_logic(
+                r#"{"merge": [[{"test_read_data": []}], [{"test_box": [{"var": "a"}]}]]}"#,
+                None,
+            )
+            .unwrap();
+        let data = dl.parse_data(r#"{"a": 1, "b": 2}"#).unwrap();
+
+        let expected = unboxed(dl.evaluate(&rule, &data).unwrap(), dl.arena());
+        assert_eq!(expected, json!([2, {"boxed": 1}]));
+        let result = dl.evaluate_concurrent(&rule, &data, 2).unwrap();
+        assert_eq!(unboxed(result, dl.arena()), expected);
+    }
+
+    #[test]
+    fn test_evaluate_speculative() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CHECKS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("test_check", |args, _| {
+            CHECKS.fetch_add(1, Ordering::SeqCst);
+            Ok(args[0].clone())
+        });
+        let rule = dl
+            .parse_logic(
+                r#"{"if": [
+                    {"test_check": [{"var": "flag"}]}, {"test_check": ["yes"]},
+                    {"test_check": [{"var": "other"}]}, {"test_check": ["maybe"]},
+                    {"test_check": ["no"]}
+                ]}"#,
+                None,
+            )
+            .unwrap();
+        let data = dl.parse_data(r#"{"flag": true, "other": false}"#).unwrap();
+
+        // Without speculation the branches are called during evaluation only
+        let result = dl.evaluate_concurrent(&rule, &data, 4).unwrap();
+        assert_eq!(result.as_str(), Some("yes"));
+        assert_eq!(CHECKS.swap(0, Ordering::SeqCst), 3);
+
+        // With speculation every condition and branch is called up front
+        let result = dl.evaluate_speculative(&rule, &data, 4).unwrap();
+        assert_eq!(result.as_str(), Some("yes"));
+        assert_eq!(CHECKS.swap(0, Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_evaluate_speculative_matches_evaluate() {
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("test_read_data", read_data);
+        dl.register_custom_operator("test_box", Box::new(Boxed));
+        let rule = dl
+            .parse_logic(
+                r#"{"if": [
+                    {"test_read_data": []},
+                    [{"test_box": [{"var": "a"}]}],
+                    [{"test_read_data": []}, {"test_box": "none"}]
+                ]}"#,
+                None,
+            )
+            .unwrap();
+
+        for (data, expected) in [
+            (r#"{"a": 1, "b": 2}"#, json!([{"boxed": 1}])),
+            (r#"{"a": 1, "b": 0}"#, json!([0, {"boxed": "none"}])),
+        ] {
+            let data = dl.parse_data(data).unwrap();
+            let result = unboxed(dl.evaluate(&rule, &data).unwrap(), dl.arena());
+            assert_eq!(result, expected);
+            let result = dl.evaluate_speculative(&rule, &data, 3).unwrap();
+            assert_eq!(unboxed(result, dl.arena()), expected);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_speculative_overlaps_branches() {
+        static CHECKS: Rendezvous = Rendezvous::new();
+
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("test_meet", |args, _| {
+            CHECKS.meet(2)?;
+            Ok(args[0].clone())
+        });
+        let rule = dl
+            .parse_logic(
+                r#"{"if": [{"test_meet": [{"var": "flag"}]}, {"test_meet": ["yes"]}, "no"]}"#,
+                None,
+            )
+            .unwrap();
+        let data = dl.parse_data(r#"{"flag": true}"#).unwrap();
+
+        // The branch is called while the condition is, not after it
+        let result = dl.evaluate_speculative(&rule, &data, 2).unwrap();
+        assert_eq!(result.as_str(), Some("yes"));
+    }
+
+    #[test]
+    fn test_panicking_operator() {
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("tenant_score", |args, _| {
+            let score = args[0].as_i64().expect("score must be an integer");
+            Ok(DataValue::integer(score * 2))
+        });
+
+        let rule = json!({"tenant_score": [{"var": "score"}]});
+        assert_eq!(
+            dl.evaluate_json(&rule, &json!({"score": 4}), None).unwrap(),
+            json!(8)
+        );
+        assert_eq!(
+            dl.evaluate_json(&rule, &json!({"score": "x"}), None),
+            Err(LogicError::OperatorPanicked {
+                operator: "tenant_score".to_string(),
+                message: "score must be an integer".to_string(),
+            })
+        );
+
+        // The instance stays usable after the panic
+        assert_eq!(
+            dl.evaluate_json(&rule, &json!({"score": 5}), None).unwrap(),
+            json!(10)
+        );
+
+        dl.set_panic_isolation(false);
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            dl.evaluate_json(&rule, &json!({"score": "x"}), None)
+        }));
+        assert!(outcome.is_err());
+    }
+
+    #[test]
+    fn test_versioned_operators() {
+        let mut dl = DataLogic::new();
+        for version in ["1.0.0", "1.2.0", "2.0.3"] {
+            let metadata = OperatorMetadata::new(version).unwrap();
+            dl.register_versioned_simple_operator("acme/version", metadata, |args, _| {
+                Ok(args.first().cloned().unwrap_or(DataValue::Null))
+            });
+        }
+        let metadata = OperatorMetadata::new("1.0.0")
+            .unwrap()
+            .with_arg_types(&["string", "any"]);
+        dl.register_versioned_simple_operator(
+            "acme/label",
+            metadata,
+            |args, _| Ok(args[0].clone()),
+        );
+
+        let rule = dl.parse_logic(r#"{"acme.v1/version": [7]}"#, None).unwrap();
+        assert!(matches!(
+            rule.root(),
+            Token::CustomOperator { name, .. } if *name == "acme/version@1.2.0"
+        ));
+        let rule = dl.parse_logic(r#"{"acme/version": [7]}"#, None).unwrap();
+        assert!(matches!(
+            rule.root(),
+            Token::CustomOperator { name, .. } if *name == "acme/version@2.0.3"
+        ));
+        let data = dl.parse_data("{}").unwrap();
+        assert_eq!(dl.evaluate(&rule, &data).unwrap().as_i64(), Some(7));
+
+        assert!(dl
+            .parse_logic(r#"{"acme.v1.3/version": []}"#, None)
+            .is_err());
+
+        // Argument schema
+        assert!(dl
+            .parse_logic(r#"{"acme/label": ["a", {"var": "x"}]}"#, None)
+            .is_ok());
+        assert!(dl
+            .parse_logic(r#"{"acme/label": [{"var": "x"}, 1]}"#, None)
+            .is_ok());
+        let error = dl
+            .parse_logic(r#"{"acme/label": [1, 2]}"#, None)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parse error: Argument 1 of operator 'acme/label@1.0.0' must be of type string, got number"
+        );
+        assert!(dl.parse_logic(r#"{"acme/label": ["a"]}"#, None).is_err());
+    }
+
+    #[test]
+    fn test_host_objects() {
+        #[derive(Debug)]
+        struct CompileRegex;
+        impl CustomOperator for CompileRegex {
+            fn evaluate<'a>(
+                &self,
+                args: &'a [DataValue<'a>],
+                arena: &'a DataArena,
+            ) -> Result<&'a DataValue<'a>> {
+                let pattern = args.first().and_then(|arg| arg.as_str()).unwrap_or("");
+                let regex =
+                    regex::Regex::new(pattern).map_err(|_| LogicError::InvalidArgumentsError)?;
+                Ok(arena.alloc_host_object(regex))
+            }
+        }
+
+        #[derive(Debug)]
+        struct RegexMatch;
+        impl CustomOperator for RegexMatch {
+            fn evaluate<'a>(
+                &self,
+                args: &'a [DataValue<'a>],
+                arena: &'a DataArena,
+            ) -> Result<&'a DataValue<'a>> {
+                let regex = arena
+                    .host_object::<regex::Regex>(&args[0])
+                    .ok_or(LogicError::InvalidArgumentsError)?;
+                let text = args[1].as_str().unwrap_or("");
+                Ok(arena.alloc(DataValue::Bool(regex.is_match(text))))
+            }
+        }
+
+        let mut dl = DataLogic::new();
+        dl.register_custom_operator("compile_regex", Box::new(CompileRegex));
+        dl.register_custom_operator("regex_match", Box::new(RegexMatch));
+
+        let rule = json!({"map": [
+            {"var": "codes"},
+            {"regex_match": [{"compile_regex": "^[A-Z]{3}-\\d+$"}, {"var": ""}]}
+        ]});
+        let data = json!({"codes": ["ABC-12", "abc-12"]});
+        assert_eq!(
+            dl.evaluate_json(&rule, &data, None).unwrap(),
+            json!([true, false])
+        );
+
+        // Handles have no JSON form and do not outlive their evaluation
+        let rule = dl.parse_logic(r#"{"compile_regex": "a+"}"#, None).unwrap();
+        let data = dl.parse_data("{}").unwrap();
+        let handle = dl.evaluate(&rule, &data).unwrap().clone();
+        assert_eq!(handle.type_name(), "custom");
+        assert_eq!(handle.to_json(), json!(null));
+        assert!(dl.arena().host_object::<regex::Regex>(&handle).is_some());
+        dl.evaluate(&rule, &data).unwrap();
+        assert!(dl.arena().host_object::<regex::Regex>(&handle).is_none());
+    }
+
+    #[test]
+    fn test_semantics() {
+        let mut dl = DataLogic::new();
+        let data = json!({"a": "apple", "n": null, "o": {"apple": 1}});
+        let cases = [
+            (json!({"-": [{"var": "a"}, 1]}), json!(null)),
+            (json!({"/": [1, 0]}), json!(null)),
+            (json!({"==": [1, {"var": "a"}]}), json!(false)),
+            (json!({"!=": [1, {"var": "a"}]}), json!(true)),
+            (json!({"<": [1, {"var": "a"}]}), json!(false)),
+            (json!({"==": [{"var": "n"}, 0]}), json!(false)),
+            (json!({"in": ["apple", {"var": "o"}]}), json!(false)),
+        ];
+
+        for (rule, _) in &cases[..5] {
+            assert_eq!(
+                dl.evaluate_json(rule, &data, None),
+                Err(LogicError::NaNError)
+            );
+        }
+        let rule = &cases[5].0;
+        assert_eq!(dl.evaluate_json(rule, &data, None).unwrap(), json!(true));
+
+        let compiled = dl.compile(r#"{"/": [1, 0]}"#, None).unwrap();
+        dl.set_semantics(Semantics::JsonLogicJs);
+        assert!(dl.is_stale(&compiled));
+        for (rule, expected) in &cases {
+            assert_eq!(&dl.evaluate_json(rule, &data, None).unwrap(), expected);
+        }
+
+        // Values that convert to numbers behave the same in both profiles
+        let rule = json!({"-": ["5", 2]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(3));
+
+        let compiled = dl.compile(r#"{"/": [1, 0]}"#, None).unwrap();
+        assert_eq!(
+            dl.evaluate_compiled(&compiled, &DataValue::Null).unwrap(),
+            &DataValue::Null
+        );
+    }
+
+    #[test]
+    fn test_compile_follows_integer_overflow() {
+        let mut dl = DataLogic::new();
+        let source = r#"{"+": [9223372036854775807, 1]}"#;
+
+        for policy in [
+            IntegerOverflow::Float,
+            IntegerOverflow::Saturate,
+            IntegerOverflow::Error,
+        ] {
+            dl.set_integer_overflow(policy);
+            let logic = dl.parse_logic(source, None).unwrap();
+            let expected = dl
+                .evaluate(&logic, &DataValue::Null)
+                .map(|value| value.to_json());
+            let actual = dl.compile(source, None).and_then(|rule| {
+                dl.evaluate_compiled(&rule, &DataValue::Null)
+                    .map(|v| v.to_json())
+            });
+            assert_eq!(actual, expected, "{policy:?}");
+        }
+        let rule = dl.compile(source, None).unwrap();
+        assert!(matches!(
+            dl.evaluate_compiled(&rule, &DataValue::Null),
+            Err(LogicError::IntegerOverflow { .. })
+        ));
+    }
 }
@@ -0,0 +1,599 @@
+//! Incremental parsing of rules being edited
+//!
+//! A rule editor parses the rule again on every keystroke, which takes a
+//! noticeable time for very large rules. An [`EditableRule`] keeps the text
+//! of a rule along with the position of each of its sub-rules, so that
+//! [`DataLogic::reparse`](crate::DataLogic::reparse) applies a [`TextEdit`]
+//! by parsing only the smallest sub-rule enclosing it. The tokens of the
+//! rest of the rule are kept, and only the operators enclosing the edited
+//! sub-rule are rebuilt.
+//!
+//! Sub-rules are the arguments of the operators that parse each of their
+//! arguments on its own. An edit that does not leave the enclosing sub-rule
+//! a single JSON value, such as adding an argument, parses its parent
+//! instead, and failing that the whole text. Edited rules are not optimized,
+//! so that their tokens follow the text.
+
+use std::ops::Range;
+
+use serde_json::Value as JsonValue;
+
+use crate::arena::DataArena;
+use crate::logic::{Logic, LogicError, Result, Token};
+use crate::parser::json;
+use crate::parser::jsonlogic::{
+    has_rule_arguments, parse_json_with_config, parse_nested_rule, ParserConfig, RuleList,
+};
+
+/// A replacement of a range of the text of a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The range of bytes replaced
+    pub range: Range<usize>,
+    /// The text replacing the range
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Creates an edit replacing a range of bytes by a text
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// Creates an edit inserting a text at a byte offset
+    pub fn insert(offset: usize, text: impl Into<String>) -> Self {
+        Self::new(offset..offset, text)
+    }
+
+    /// Creates an edit deleting a range of bytes
+    pub fn delete(range: Range<usize>) -> Self {
+        Self::new(range, "")
+    }
+}
+
+/// A sub-rule and its position in the text
+#[derive(Debug, Clone)]
+struct Node<'a> {
+    /// The offset of the sub-rule from the start of its parent
+    offset: usize,
+    /// The length of the text of the sub-rule
+    len: usize,
+    /// The token of the sub-rule
+    token: &'a Token<'a>,
+    /// The arguments of the sub-rule that are parsed on their own
+    children: Vec<Node<'a>>,
+}
+
+/// The text of a rule being edited along with its parsed tokens
+///
+/// Created by [`DataLogic::parse_editable`](crate::DataLogic::parse_editable)
+/// and updated by [`DataLogic::reparse`](crate::DataLogic::reparse). The
+/// text is kept when it does not parse, and the next edit parses it whole.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::editing::TextEdit;
+/// use datalogic_rs::DataLogic;
+///
+/// let dl = DataLogic::new();
+/// let mut rule = dl.parse_editable(r#"{"and": [{">": [{"var": "a"}, 1]}, true]}"#);
+///
+/// // Replaces the 1 compared with `a`
+/// let logic = dl.reparse(&mut rule, &TextEdit::new(30..31, "10")).unwrap();
+/// assert_eq!(rule.source(), r#"{"and": [{">": [{"var": "a"}, 10]}, true]}"#);
+/// assert_eq!(rule.reparsed(), 30..32);
+///
+/// let data = dl.parse_data(r#"{"a": 5}"#).unwrap();
+/// assert_eq!(dl.evaluate(&logic, &data).unwrap().as_bool(), Some(false));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EditableRule<'a> {
+    source: String,
+    arena: &'a DataArena,
+    root: Result<Node<'a>>,
+    reparsed: Range<usize>,
+}
+
+impl<'a> EditableRule<'a> {
+    /// Parses the whole text of a rule.
+    pub(crate) fn parse(source: String, config: ParserConfig, arena: &'a DataArena) -> Self {
+        let root = json::from_str(&source, config.strict_json)
+            .map_err(|e| LogicError::ParseError {
+                reason: format!("Invalid JSON: {}", e),
+            })
+            .and_then(|json| {
+                let token = parse_json_with_config(&json, config, arena)?;
+                let builder = NodeBuilder {
+                    source: &source,
+                    config,
+                    arena,
+                };
+                Ok(builder.build(0..source.len(), 0, &json, token, true))
+            });
+        let reparsed = 0..source.len();
+        Self {
+            source,
+            arena,
+            root,
+            reparsed,
+        }
+    }
+
+    /// Returns the text of the rule
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the parsed rule, or the error parsing its text
+    pub fn logic(&self) -> Result<Logic<'a>> {
+        match &self.root {
+            Ok(root) => Ok(Logic::new(root.token, self.arena)),
+            Err(error) => Err(error.clone()),
+        }
+    }
+
+    /// Returns the range of the text parsed by the last parse
+    pub fn reparsed(&self) -> Range<usize> {
+        self.reparsed.clone()
+    }
+
+    /// Applies an edit to the text, parsing the smallest sub-rule enclosing
+    /// it.
+    pub(crate) fn apply(
+        &mut self,
+        edit: &TextEdit,
+        config: ParserConfig,
+        arena: &'a DataArena,
+    ) -> Result<()> {
+        let range = edit.range.clone();
+        if range.start > range.end
+            || !self.source.is_char_boundary(range.start)
+            || !self.source.is_char_boundary(range.end)
+        {
+            return Err(LogicError::ParseError {
+                reason: format!(
+                    "Invalid edit range {}..{} in a text of {} bytes",
+                    range.start,
+                    range.end,
+                    self.source.len()
+                ),
+            });
+        }
+        self.source.replace_range(range.clone(), &edit.text);
+        let delta = edit.text.len() as isize - range.len() as isize;
+
+        if let Ok(root) = &mut self.root {
+            // The sub-rule enclosing the edit, then its parent
+            let path = enclosing_path(root, 0, &range);
+            for depth in [path.len(), path.len().saturating_sub(1)] {
+                if depth == 0 {
+                    break;
+                }
+                if let Some(reparsed) =
+                    reparse_node(root, &path[..depth], &self.source, delta, config, arena)
+                {
+                    self.reparsed = reparsed;
+                    return Ok(());
+                }
+            }
+        }
+
+        *self = Self::parse(std::mem::take(&mut self.source), config, arena);
+        Ok(())
+    }
+}
+
+/// Builds the nodes of the sub-rules of a text
+struct NodeBuilder<'s, 'a> {
+    source: &'s str,
+    config: ParserConfig,
+    arena: &'a DataArena,
+}
+
+impl<'a> NodeBuilder<'_, 'a> {
+    /// Builds the node of a sub-rule from its parsed JSON value and token.
+    ///
+    /// `span` is the text of the sub-rule, which may be surrounded by
+    /// whitespace, and `offset` its offset from the start of its parent.
+    fn build(
+        &self,
+        span: Range<usize>,
+        offset: usize,
+        json: &JsonValue,
+        token: &'a Token<'a>,
+        root: bool,
+    ) -> Node<'a> {
+        let bytes = self.source.as_bytes();
+        let start = skip_whitespace(bytes, span.start);
+
+        // The arguments, as a JSON value and the position of its text
+        let arguments = match (json, token) {
+            (JsonValue::Object(obj), Token::Operator { .. } | Token::CustomOperator { .. })
+                if obj.len() == 1 =>
+            {
+                let (key, value) = obj.iter().next().unwrap();
+                // Duplicate keys leave a single one in the parsed value
+                match item_spans(bytes, start).as_slice() {
+                    [value_span] if has_rule_arguments(key, self.arena) => {
+                        Some((value, value_span.clone()))
+                    }
+                    _ => None,
+                }
+            }
+            // A top-level array combined by an operator
+            (JsonValue::Array(_), Token::Operator { .. })
+                if root && self.config.rule_list != RuleList::Collect =>
+            {
+                Some((json, start..value_end(bytes, start)))
+            }
+            _ => None,
+        };
+
+        let mut children = Vec::new();
+        if let (Some((arguments, arguments_span)), Token::Operator { args, .. })
+        | (Some((arguments, arguments_span)), Token::CustomOperator { args, .. }) =
+            (arguments, token)
+        {
+            match (arguments, args) {
+                (JsonValue::Array(items), Token::ArrayLiteral(tokens))
+                    if items.len() == tokens.len() =>
+                {
+                    let spans = item_spans(bytes, arguments_span.start);
+                    for ((item, token), item_span) in items.iter().zip(tokens).zip(spans) {
+                        let offset = item_span.start - span.start;
+                        children.push(self.build(item_span, offset, item, token, false));
+                    }
+                }
+                (JsonValue::Array(_), _) => {}
+                (argument, _) => {
+                    let offset = arguments_span.start - span.start;
+                    children.push(self.build(arguments_span, offset, argument, args, false));
+                }
+            }
+        }
+
+        Node {
+            offset,
+            len: span.len(),
+            token,
+            children,
+        }
+    }
+}
+
+/// Returns the indices of the children leading to the deepest sub-rule
+/// enclosing a range of the text.
+fn enclosing_path(node: &Node, start: usize, range: &Range<usize>) -> Vec<usize> {
+    let mut path = Vec::new();
+    let mut node = node;
+    let mut start = start;
+    'descend: loop {
+        for (index, child) in node.children.iter().enumerate() {
+            let child_start = start + child.offset;
+            if child_start <= range.start && range.end <= child_start + child.len {
+                path.push(index);
+                node = child;
+                start = child_start;
+                continue 'descend;
+            }
+        }
+        return path;
+    }
+}
+
+/// Parses the edited text of the sub-rule at the end of a path, and rebuilds
+/// the operators enclosing it.
+///
+/// Returns the range of the text parsed, or `None` if the text of the
+/// sub-rule does not parse on its own.
+fn reparse_node<'a>(
+    root: &mut Node<'a>,
+    path: &[usize],
+    source: &str,
+    delta: isize,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Option<Range<usize>> {
+    let mut node = &*root;
+    let mut start = 0;
+    for &index in path {
+        node = &node.children[index];
+        start += node.offset;
+    }
+    let span = start..start + node.len.checked_add_signed(delta)?;
+
+    let json = json::from_str(&source[span.clone()], config.strict_json).ok()?;
+    let token = parse_nested_rule(&json, config, arena).ok()?;
+    let builder = NodeBuilder {
+        source,
+        config,
+        arena,
+    };
+    let replacement = builder.build(span.clone(), node.offset, &json, token, false);
+    splice(root, path, replacement, delta, arena);
+    Some(span)
+}
+
+/// Replaces the sub-rule at the end of a path, rebuilding the operators
+/// enclosing it and moving the sub-rules following it.
+fn splice<'a>(
+    node: &mut Node<'a>,
+    path: &[usize],
+    replacement: Node<'a>,
+    delta: isize,
+    arena: &'a DataArena,
+) {
+    let Some((&index, rest)) = path.split_first() else {
+        *node = replacement;
+        return;
+    };
+    splice(&mut node.children[index], rest, replacement, delta, arena);
+
+    node.token = replace_argument(node.token, index, node.children[index].token, arena);
+    node.len = node.len.saturating_add_signed(delta);
+    for later in &mut node.children[index + 1..] {
+        later.offset = later.offset.saturating_add_signed(delta);
+    }
+}
+
+/// Rebuilds an operator with one of its arguments replaced.
+fn replace_argument<'a>(
+    token: &'a Token<'a>,
+    index: usize,
+    argument: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> &'a Token<'a> {
+    let replace = |args: &'a Token<'a>| match args {
+        Token::ArrayLiteral(items) => {
+            let mut items = items.clone();
+            items[index] = argument;
+            arena.alloc(Token::ArrayLiteral(items))
+        }
+        // A single argument that is not an array
+        _ => argument,
+    };
+    match token {
+        Token::Operator { op_type, args } => arena.alloc(Token::operator(*op_type, replace(args))),
+        Token::CustomOperator { name, args } => {
+            arena.alloc(Token::custom_operator(name, replace(args)))
+        }
+        // Only operators have sub-rules
+        _ => token,
+    }
+}
+
+/// Returns the position of the first byte after whitespace.
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t' | b'\n' | b'\r') {
+        pos += 1;
+    }
+    pos
+}
+
+/// Returns the end of the valid JSON value starting at a position.
+fn value_end(bytes: &[u8], pos: usize) -> usize {
+    match bytes[pos] {
+        b'"' => string_end(bytes, pos),
+        b'[' | b'{' => {
+            let mut depth = 0;
+            let mut pos = pos;
+            loop {
+                match bytes[pos] {
+                    b'"' => {
+                        pos = string_end(bytes, pos);
+                        continue;
+                    }
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return pos + 1;
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+        }
+        _ => {
+            let mut pos = pos;
+            while pos < bytes.len()
+                && !matches!(
+                    bytes[pos],
+                    b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r'
+                )
+            {
+                pos += 1;
+            }
+            pos
+        }
+    }
+}
+
+/// Returns the end of the string starting at a position.
+fn string_end(bytes: &[u8], mut pos: usize) -> usize {
+    pos += 1;
+    while bytes[pos] != b'"' {
+        if bytes[pos] == b'\\' {
+            pos += 1;
+        }
+        pos += 1;
+    }
+    pos + 1
+}
+
+/// Returns the positions of the items of the valid JSON array, or of the
+/// values of the valid JSON object, starting at a position.
+fn item_spans(bytes: &[u8], pos: usize) -> Vec<Range<usize>> {
+    let object = bytes[pos] == b'{';
+    let mut spans = Vec::new();
+    let mut pos = skip_whitespace(bytes, pos + 1);
+    if matches!(bytes[pos], b']' | b'}') {
+        return spans;
+    }
+    loop {
+        if object {
+            // Skips the key and the colon
+            pos = skip_whitespace(bytes, value_end(bytes, pos));
+            pos = skip_whitespace(bytes, pos + 1);
+        }
+        let end = value_end(bytes, pos);
+        spans.push(pos..end);
+        pos = skip_whitespace(bytes, end);
+        if bytes[pos] != b',' {
+            return spans;
+        }
+        pos = skip_whitespace(bytes, pos + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DataLogic, RuleList, ToJson};
+    use serde_json::json;
+
+    /// Evaluates the current rule, checking it against a full parse.
+    fn evaluate(dl: &DataLogic, rule: &EditableRule, data: &JsonValue) -> Result<JsonValue> {
+        let logic = rule.logic()?;
+        let expected = dl.parse_logic_json(&serde_json::from_str(rule.source()).unwrap(), None)?;
+        let data = dl.parse_data_json(data)?;
+        let result = dl.evaluate(&logic, &data).map(|value| value.to_json());
+        assert_eq!(
+            result,
+            dl.evaluate(&expected, &data).map(|value| value.to_json())
+        );
+        result
+    }
+
+    #[test]
+    fn test_reparse_sub_rule() {
+        let dl = DataLogic::new();
+        let source =
+            r#"{"if": [{"<": [{"var": "x"}, 10]}, "low", {"cat": ["high ", {"var": "x"}]}]}"#;
+        let mut rule = dl.parse_editable(source);
+        let data = json!({"x": 12});
+        assert_eq!(evaluate(&dl, &rule, &data), Ok(json!("high 12")));
+        let Token::Operator { args, .. } = rule.logic().unwrap().root() else {
+            panic!("expected an operator");
+        };
+        let Token::ArrayLiteral(branches) = args else {
+            panic!("expected arguments");
+        };
+        let cat = branches[2];
+
+        // Only the literal compared with x is parsed
+        let offset = source.find("10").unwrap();
+        dl.reparse(&mut rule, &TextEdit::new(offset..offset + 2, "20"))
+            .unwrap();
+        assert_eq!(rule.reparsed(), offset..offset + 2);
+        assert_eq!(evaluate(&dl, &rule, &data), Ok(json!("low")));
+
+        // The other branches are kept
+        let Token::Operator { args, .. } = rule.logic().unwrap().root() else {
+            panic!("expected an operator");
+        };
+        let Token::ArrayLiteral(branches) = args else {
+            panic!("expected arguments");
+        };
+        assert!(std::ptr::eq(branches[2], cat));
+
+        // Adding an argument parses the enclosing operator
+        let source = rule.source().to_string();
+        let offset = source.find(r#"{"var": "x"}]}]"#).unwrap() + 12;
+        dl.reparse(&mut rule, &TextEdit::insert(offset, r#", "!""#))
+            .unwrap();
+        let start = source.find(r#"{"cat""#).unwrap();
+        assert_eq!(rule.reparsed(), start..rule.source().len() - 2);
+        assert_eq!(
+            evaluate(&dl, &rule, &json!({"x": 30})),
+            Ok(json!("high 30!"))
+        );
+    }
+
+    #[test]
+    fn test_reparse_invalid_text() {
+        let dl = DataLogic::new();
+        let mut rule = dl.parse_editable(r#"{"+": [1, 2]}"#);
+
+        // Out of range edits leave the rule unchanged
+        assert!(dl.reparse(&mut rule, &TextEdit::insert(20, "3")).is_err());
+        assert_eq!(rule.source(), r#"{"+": [1, 2]}"#);
+
+        // Invalid text is kept, and parsed whole once fixed
+        assert!(dl.reparse(&mut rule, &TextEdit::delete(11..12)).is_err());
+        assert_eq!(rule.source(), r#"{"+": [1, 2}"#);
+        assert!(matches!(rule.logic(), Err(LogicError::ParseError { .. })));
+        dl.reparse(&mut rule, &TextEdit::insert(11, "]")).unwrap();
+        assert_eq!(rule.reparsed(), 0..13);
+        assert_eq!(evaluate(&dl, &rule, &json!({})), Ok(json!(3)));
+    }
+
+    #[test]
+    fn test_reparse_typing() {
+        let dl = DataLogic::new();
+        let mut rule = dl.parse_editable(r#"{"+": [1, {"var": "a"}]}"#);
+        let data = json!({"a": 4});
+
+        // Types an argument one character at a time
+        let start = rule.source().find('1').unwrap() + 1;
+        for (index, c) in r#", {"*": [2, {"var": "a"}]}"#.chars().enumerate() {
+            let result = dl.reparse(&mut rule, &TextEdit::insert(start + index, c));
+            if serde_json::from_str::<JsonValue>(rule.source()).is_ok() {
+                assert!(result.is_ok(), "{}", rule.source());
+                evaluate(&dl, &rule, &data).unwrap();
+            } else {
+                assert!(result.is_err(), "{}", rule.source());
+            }
+        }
+        assert_eq!(evaluate(&dl, &rule, &data), Ok(json!(13)));
+
+        // Then retypes the multiplied number
+        let offset = rule.source().find('2').unwrap();
+        dl.reparse(&mut rule, &TextEdit::delete(offset..offset + 1))
+            .unwrap_err();
+        for (index, c) in "10".chars().enumerate() {
+            dl.reparse(&mut rule, &TextEdit::insert(offset + index, c))
+                .unwrap();
+        }
+        assert_eq!(rule.reparsed(), offset..offset + 2);
+        assert_eq!(evaluate(&dl, &rule, &data), Ok(json!(45)));
+    }
+
+    #[test]
+    fn test_reparse_special_arguments() {
+        let mut dl = DataLogic::new();
+        dl.set_rule_list(RuleList::Any);
+
+        // A top-level list of rules
+        let source = r#"[{"==": [{"var": "a"}, 1]}, {"in": ["b", {"var": "s"}]}]"#;
+        let mut rule = dl.parse_editable(source);
+        let data = json!({"a": 2, "s": "abc"});
+        assert_eq!(evaluate(&dl, &rule, &data), Ok(json!(true)));
+        let offset = source.find("1]").unwrap();
+        dl.reparse(&mut rule, &TextEdit::new(offset..offset + 1, "2"))
+            .unwrap();
+        assert_eq!(rule.reparsed(), offset..offset + 1);
+
+        // The last argument of `in` may be options, so `in` is parsed whole
+        let offset = rule.source().find(r#"{"var": "s"}"#).unwrap();
+        let start = rule.source().find(r#"{"in""#).unwrap();
+        dl.reparse(&mut rule, &TextEdit::new(offset..offset + 12, "\"xyz\""))
+            .unwrap();
+        assert_eq!(rule.reparsed(), start..rule.source().len() - 1);
+        assert_eq!(evaluate(&dl, &rule, &data), Ok(json!(true)));
+
+        // Whitespace and escaped strings keep positions in step
+        let mut rule = dl.parse_editable("[ {\"cat\" : [ \"a\\\"]\" ,\n {\"var\": \"s\"} ] } ]");
+        let start = rule.source().find(r#"{"var""#).unwrap();
+        let offset = rule.source().find("\"s\"").unwrap();
+        dl.reparse(&mut rule, &TextEdit::new(offset..offset + 3, "\"a\""))
+            .unwrap();
+        assert_eq!(rule.reparsed(), start..start + 12);
+        assert_eq!(evaluate(&dl, &rule, &data), Ok(json!("a\"]2")));
+    }
+}
@@ -1,7 +1,7 @@
 //! Unified error handling for the DataLogic library
 
 // Re-export error types from logic module
-pub use crate::logic::error::{LogicError, Result};
+pub use crate::logic::error::{DetailedError, LogicError, Result};
 
 // Note: For backward compatibility, we're keeping the re-exports above.
 // In a future version, consider replacing with a more comprehensive error type:
@@ -0,0 +1,83 @@
+//! Fallback policies for failed evaluations
+//!
+//! A fallback policy lets a DataLogic instance degrade gracefully: when the
+//! primary rule fails, a secondary rule or a constant value is used instead,
+//! and the event is recorded for later inspection.
+
+use crate::LogicError;
+use serde_json::Value as JsonValue;
+
+/// What to evaluate when the primary rule fails
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fallback {
+    /// Return a constant value
+    Value(JsonValue),
+    /// Evaluate a secondary rule against the same data
+    Rule(JsonValue),
+}
+
+/// Engine-level policy applied when a rule fails to evaluate
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::{DataLogic, Fallback, FallbackPolicy};
+/// use serde_json::json;
+///
+/// let mut dl = DataLogic::new();
+/// dl.set_fallback_policy(FallbackPolicy::new(Fallback::Value(json!("manual_review"))));
+///
+/// let result = dl
+///     .evaluate_json(&json!({"throw": "service_down"}), &json!({}), None)
+///     .unwrap();
+/// assert_eq!(result, json!("manual_review"));
+/// assert_eq!(dl.fallback_events().len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FallbackPolicy {
+    fallback: Fallback,
+    filter: Option<fn(&LogicError) -> bool>,
+}
+
+impl FallbackPolicy {
+    /// Create a policy that applies the fallback on any error
+    pub fn new(fallback: Fallback) -> Self {
+        Self {
+            fallback,
+            filter: None,
+        }
+    }
+
+    /// Only apply the fallback to errors accepted by the filter
+    ///
+    /// Other errors are returned to the caller unchanged.
+    pub fn with_filter(mut self, filter: fn(&LogicError) -> bool) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Get the fallback of this policy
+    pub fn fallback(&self) -> &Fallback {
+        &self.fallback
+    }
+
+    /// Check if the policy applies to an error
+    pub fn applies_to(&self, error: &LogicError) -> bool {
+        self.filter.is_none_or(|filter| filter(error))
+    }
+}
+
+/// A record of a fallback being used
+#[derive(Debug, Clone, PartialEq)]
+pub struct FallbackEvent {
+    /// The error raised by the primary rule
+    pub error: LogicError,
+    /// The error raised by the fallback rule, if it failed as well
+    ///
+    /// In that case the primary error is returned to the caller.
+    pub fallback_error: Option<LogicError>,
+    /// The id of the compiled rule that failed, if it has one
+    ///
+    /// See [`CompiledRule::with_id`](crate::CompiledRule::with_id).
+    pub rule_id: Option<String>,
+}
@@ -0,0 +1,568 @@
+//! Incremental re-evaluation of rules
+//!
+//! A rule evaluated again and again against data that changes in a few
+//! fields only needs to be re-evaluated when one of the fields it reads
+//! changed. [`Dependencies`] lists the data paths a compiled rule reads, and
+//! an [`IncrementalRule`] keeps the last data and result of a rule, applying
+//! field-level changes to the data and reusing the result when none of the
+//! changed fields is a dependency.
+//!
+//! Dependencies are computed from the rule alone, so they are conservative:
+//! a rule reading data through computed paths depends on every field, and
+//! one reading the current time is always re-evaluated. Custom operators are
+//! assumed to depend only on their arguments. A reused result skips the side
+//! effects of the evaluation, such as `log` reports.
+
+use std::collections::BTreeSet;
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::compiled::CompiledRule;
+use crate::datalogic::DataLogic;
+use crate::logic::{ArrayOp, DateTimeOp, LogicError, OperatorType, Result, Token};
+use crate::value::{borrow_json, DataValue, ToJson};
+
+/// The data read by a rule
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    paths: BTreeSet<String>,
+    dynamic: bool,
+    time_dependent: bool,
+}
+
+impl Dependencies {
+    /// Computes the dependencies of a token tree.
+    pub(crate) fn of(token: &Token) -> Self {
+        let mut dependencies = Self::default();
+        dependencies.visit(token, 0);
+        dependencies
+    }
+
+    /// Returns the paths of the data read by the rule
+    ///
+    /// Paths are relative to the root of the data, with an empty path
+    /// standing for the whole data. Variables read from the items of an
+    /// iteration are covered by the path of the iterated array.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.paths.iter().map(String::as_str)
+    }
+
+    /// Checks if the rule reads data through paths only known at runtime
+    pub fn is_dynamic(&self) -> bool {
+        self.dynamic
+    }
+
+    /// Checks if the result of the rule depends on the current time
+    pub fn is_time_dependent(&self) -> bool {
+        self.time_dependent
+    }
+
+    /// Checks if a change of the data at `path` can change the result
+    ///
+    /// A change affects a dependency if either path contains the other, so a
+    /// change of `user` affects a rule reading `user.age`, and the other way
+    /// around.
+    pub fn is_affected_by(&self, path: &str) -> bool {
+        self.dynamic
+            || self.time_dependent
+            || self
+                .paths
+                .iter()
+                .any(|dependency| paths_overlap(dependency, path))
+    }
+
+    /// Visits a token, `depth` being the number of enclosing iterations.
+    fn visit(&mut self, token: &Token, depth: usize) {
+        match token {
+            Token::Literal(_) => {}
+            Token::ArrayLiteral(items) => {
+                for item in items {
+                    self.visit(item, depth);
+                }
+            }
+            Token::Variable { path, default } => {
+                self.add_variable(path, depth);
+                if let Some(default) = default {
+                    self.visit(default, depth);
+                }
+            }
+            Token::DynamicVariable { path_expr, default } => {
+                self.dynamic = true;
+                self.visit(path_expr, depth);
+                if let Some(default) = default {
+                    self.visit(default, depth);
+                }
+            }
+            Token::Operator { op_type, args } => {
+                let items = match args {
+                    Token::ArrayLiteral(items) => items.as_slice(),
+                    args => std::slice::from_ref(args),
+                };
+                match op_type {
+                    OperatorType::Missing => self.add_checked_paths(items, depth),
+                    OperatorType::MissingSome => {
+                        self.add_checked_paths(items.get(1..).unwrap_or_default(), depth);
+                        if let Some(min) = items.first() {
+                            self.visit(min, depth);
+                        }
+                        return;
+                    }
+                    OperatorType::Val
+                    | OperatorType::Exists
+                    | OperatorType::Validate
+                    | OperatorType::Unpreserve => self.dynamic = true,
+                    OperatorType::DateTime(DateTimeOp::Now) => self.time_dependent = true,
+                    _ => {}
+                }
+
+                // The second argument of an iterator is evaluated per item
+                let iterates = is_iterator(*op_type);
+                for (index, item) in items.iter().enumerate() {
+                    let depth = if iterates && index == 1 {
+                        depth + 1
+                    } else {
+                        depth
+                    };
+                    self.visit(item, depth);
+                }
+            }
+            Token::CustomOperator { args, .. }
+            | Token::CompiledMatch { args, .. }
+            | Token::CompiledRegex { args, .. }
+            | Token::CaseFolded { args, .. } => self.visit(args, depth),
+        }
+    }
+
+    /// Adds the root path read by a variable, if it reads the root data.
+    fn add_variable(&mut self, path: &str, depth: usize) {
+        let mut levels = 0;
+        let mut rest = path;
+        while let Some(stripped) = rest.strip_prefix("../") {
+            levels += 1;
+            rest = stripped;
+        }
+        if rest == ".." {
+            levels += 1;
+            rest = "";
+        }
+
+        // Variables of an iteration read the items of an array that is
+        // itself a dependency, and those escaping every iteration read null
+        if levels == depth {
+            self.paths.insert(rest.to_string());
+        }
+    }
+
+    /// Adds the literal paths checked by `missing` or `missing_some`.
+    fn add_checked_paths(&mut self, items: &[&Token], depth: usize) {
+        let mut paths = Vec::new();
+        for item in items {
+            if !literal_strings(item, &mut paths) {
+                self.dynamic = true;
+                return;
+            }
+        }
+        if depth == 0 {
+            self.paths.extend(paths);
+        }
+    }
+}
+
+/// Checks if an operator evaluates its second argument once per item.
+fn is_iterator(op_type: OperatorType) -> bool {
+    matches!(
+        op_type,
+        OperatorType::Array(
+            ArrayOp::Map
+                | ArrayOp::Filter
+                | ArrayOp::Reduce
+                | ArrayOp::All
+                | ArrayOp::Some
+                | ArrayOp::None
+                | ArrayOp::Sort
+                | ArrayOp::GroupBy
+                | ArrayOp::Sum
+                | ArrayOp::Avg
+                | ArrayOp::Count
+                | ArrayOp::MinBy
+                | ArrayOp::MaxBy
+        )
+    )
+}
+
+/// Collects the strings of a literal token, returning false if it is not one.
+fn literal_strings(token: &Token, strings: &mut Vec<String>) -> bool {
+    fn collect(value: &DataValue, strings: &mut Vec<String>) -> bool {
+        match value {
+            DataValue::String(s) => {
+                strings.push(s.to_string());
+                true
+            }
+            DataValue::Array(items) => items.iter().all(|item| collect(item, strings)),
+            _ => false,
+        }
+    }
+
+    match token {
+        Token::Literal(value) => collect(value, strings),
+        Token::ArrayLiteral(items) => items.iter().all(|item| literal_strings(item, strings)),
+        _ => false,
+    }
+}
+
+/// Checks if one of two data paths contains the other.
+fn paths_overlap(a: &str, b: &str) -> bool {
+    let contains = |outer: &str, inner: &str| {
+        outer.is_empty()
+            || inner
+                .strip_prefix(outer)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    };
+    contains(a, b) || contains(b, a)
+}
+
+/// A change of one field of the data
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// The dotted path of the field, empty for the whole data
+    pub path: String,
+    /// The new value of the field, or `None` if it was removed
+    pub value: Option<JsonValue>,
+}
+
+impl FieldChange {
+    /// Creates a change setting a field to a value
+    pub fn set(path: impl Into<String>, value: JsonValue) -> Self {
+        Self {
+            path: path.into(),
+            value: Some(value),
+        }
+    }
+
+    /// Creates a change removing a field
+    pub fn remove(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            value: None,
+        }
+    }
+
+    /// Computes the changes turning `old` into `new`
+    ///
+    /// Objects are compared field by field, and any other value that
+    /// differs, including an array, is changed as a whole.
+    pub fn diff(old: &JsonValue, new: &JsonValue) -> Vec<FieldChange> {
+        let mut changes = Vec::new();
+        diff_values(old, new, "", &mut changes);
+        changes
+    }
+
+    /// Applies the change to data.
+    fn apply(&self, data: &mut JsonValue) -> Result<()> {
+        let error = |reason: &str| LogicError::VariableError {
+            path: self.path.clone(),
+            reason: reason.to_string(),
+        };
+
+        if self.path.is_empty() {
+            *data = self.value.clone().unwrap_or(JsonValue::Null);
+            return Ok(());
+        }
+
+        let mut segments: Vec<&str> = self.path.split('.').collect();
+        let last = segments.pop().unwrap_or_default();
+        let mut target = data;
+        for segment in segments {
+            if !target.is_object() && !target.is_array() {
+                *target = JsonValue::Object(Map::new());
+            }
+            target = match target {
+                JsonValue::Object(fields) => fields
+                    .entry(segment)
+                    .or_insert_with(|| JsonValue::Object(Map::new())),
+                JsonValue::Array(items) => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get_mut(index))
+                    .ok_or_else(|| error("array index out of bounds"))?,
+                _ => unreachable!(),
+            };
+        }
+
+        match (target, &self.value) {
+            (JsonValue::Array(items), value) => {
+                let index = last
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&index| index < items.len())
+                    .ok_or_else(|| error("array index out of bounds"))?;
+                items[index] = value.clone().unwrap_or(JsonValue::Null);
+            }
+            (JsonValue::Object(fields), Some(value)) => {
+                fields.insert(last.to_string(), value.clone());
+            }
+            (JsonValue::Object(fields), None) => {
+                fields.remove(last);
+            }
+            (target, Some(value)) => {
+                let mut fields = Map::new();
+                fields.insert(last.to_string(), value.clone());
+                *target = JsonValue::Object(fields);
+            }
+            (_, None) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Collects the changes between two values at a path.
+fn diff_values(old: &JsonValue, new: &JsonValue, path: &str, changes: &mut Vec<FieldChange>) {
+    let (JsonValue::Object(old_fields), JsonValue::Object(new_fields)) = (old, new) else {
+        if old != new {
+            changes.push(FieldChange::set(path, new.clone()));
+        }
+        return;
+    };
+
+    let field_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    for (key, old_value) in old_fields {
+        match new_fields.get(key) {
+            Some(new_value) => diff_values(old_value, new_value, &field_path(key), changes),
+            None => changes.push(FieldChange::remove(field_path(key))),
+        }
+    }
+    for (key, new_value) in new_fields {
+        if !old_fields.contains_key(key) {
+            changes.push(FieldChange::set(field_path(key), new_value.clone()));
+        }
+    }
+}
+
+/// Counts of the evaluations of an [`IncrementalRule`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IncrementalStats {
+    /// Number of updates answered with the previous result.
+    pub reused: u64,
+    /// Number of evaluations of the rule.
+    pub evaluated: u64,
+}
+
+/// A compiled rule together with its last data and result
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::incremental::{FieldChange, IncrementalRule};
+/// use datalogic_rs::DataLogic;
+/// use serde_json::json;
+///
+/// let dl = DataLogic::new();
+/// let rule = dl
+///     .compile(r#"{"all": [{"var": "items"}, {">": [{"var": "qty"}, 0]}]}"#, None)
+///     .unwrap();
+///
+/// let mut incremental = IncrementalRule::new(rule);
+/// let data = json!({"items": [{"qty": 1}], "note": "draft"});
+/// assert_eq!(incremental.evaluate(&dl, data).unwrap(), json!(true));
+///
+/// // The rule doesn't read `note`, so its previous result is reused
+/// let changes = [FieldChange::set("note", json!("final"))];
+/// assert_eq!(incremental.update(&dl, &changes).unwrap(), json!(true));
+/// assert_eq!(incremental.stats().reused, 1);
+///
+/// let changes = [FieldChange::set("items.0.qty", json!(0))];
+/// assert_eq!(incremental.update(&dl, &changes).unwrap(), json!(false));
+/// assert_eq!(incremental.stats().evaluated, 2);
+/// ```
+#[derive(Debug)]
+pub struct IncrementalRule {
+    rule: CompiledRule,
+    data: JsonValue,
+    /// The last result and the generation of the engine that computed it
+    result: Option<(JsonValue, u64)>,
+    stats: IncrementalStats,
+}
+
+impl IncrementalRule {
+    /// Creates an incremental rule, with null data and no result yet
+    pub fn new(rule: CompiledRule) -> Self {
+        Self {
+            rule,
+            data: JsonValue::Null,
+            result: None,
+            stats: IncrementalStats::default(),
+        }
+    }
+
+    /// Returns the compiled rule
+    pub fn rule(&self) -> &CompiledRule {
+        &self.rule
+    }
+
+    /// Returns the current data
+    pub fn data(&self) -> &JsonValue {
+        &self.data
+    }
+
+    /// Returns the evaluation counts
+    pub fn stats(&self) -> IncrementalStats {
+        self.stats
+    }
+
+    /// Replaces the data and evaluates the rule against it
+    pub fn evaluate(&mut self, dl: &DataLogic, data: JsonValue) -> Result<JsonValue> {
+        self.data = data;
+        self.reevaluate(dl)
+    }
+
+    /// Applies changes to the data and returns the result of the rule
+    ///
+    /// The previous result is returned without evaluating the rule if no
+    /// change affects its [`Dependencies`], it was computed by an engine of
+    /// the same [`generation`](DataLogic::generation), and the previous
+    /// evaluation succeeded. The changes are applied even if one of them
+    /// fails, up to the failing one.
+    pub fn update(&mut self, dl: &DataLogic, changes: &[FieldChange]) -> Result<JsonValue> {
+        for change in changes {
+            change.apply(&mut self.data)?;
+        }
+
+        let dependencies = self.rule.dependencies();
+        match &self.result {
+            Some((result, generation))
+                if *generation == dl.generation()
+                    && !changes
+                        .iter()
+                        .any(|change| dependencies.is_affected_by(&change.path)) =>
+            {
+                self.stats.reused += 1;
+                Ok(result.clone())
+            }
+            _ => self.reevaluate(dl),
+        }
+    }
+
+    /// Evaluates the rule against the current data, caching the result.
+    fn reevaluate(&mut self, dl: &DataLogic) -> Result<JsonValue> {
+        self.stats.evaluated += 1;
+        self.result = None;
+
+        let scratch = dl.scratch_arena();
+        let data = borrow_json(&self.data, &scratch);
+        let result = dl
+            .evaluate_compiled_in(&self.rule, &data, &scratch)?
+            .to_json();
+        self.result = Some((result.clone(), dl.generation()));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn dependencies(rule: &str) -> Dependencies {
+        DataLogic::new()
+            .compile(rule, None)
+            .unwrap()
+            .dependencies()
+            .clone()
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let deps = dependencies(
+            r#"{"and": [
+                {"missing": ["user.name"]},
+                {"map": [{"var": "orders"}, {"+": [{"var": "total"}, {"var": "../fee"}]}]},
+                {"reduce": [{"var": "items"}, {"+": [{"var": "current"}, {"var": "accumulator"}]}, {"var": "start"}]}
+            ]}"#,
+        );
+        assert_eq!(
+            deps.paths().collect::<Vec<_>>(),
+            ["fee", "items", "orders", "start", "user.name"]
+        );
+        assert!(!deps.is_dynamic() && !deps.is_time_dependent());
+        assert!(deps.is_affected_by("user"));
+        assert!(deps.is_affected_by("orders.3.total"));
+        assert!(!deps.is_affected_by("total"));
+        assert!(!deps.is_affected_by("user_name"));
+
+        assert!(dependencies(r#"{"var": {"cat": ["a", "b"]}}"#).is_affected_by("x"));
+        assert!(dependencies(r#"{"val": ["a"]}"#).is_dynamic());
+        assert!(dependencies(r#"{"now": []}"#).is_affected_by("x"));
+        assert!(dependencies(r#"{"var": ""}"#).is_affected_by("x"));
+    }
+
+    #[test]
+    fn test_field_changes() {
+        let old = json!({"a": 1, "b": {"c": [1, 2], "d": true}, "e": null});
+        let new = json!({"a": 1, "b": {"c": [1, 3], "d": true}, "f": "x"});
+        let changes = FieldChange::diff(&old, &new);
+        assert_eq!(
+            changes,
+            [
+                FieldChange::set("b.c", json!([1, 3])),
+                FieldChange::remove("e"),
+                FieldChange::set("f", json!("x")),
+            ]
+        );
+
+        let mut data = old.clone();
+        for change in &changes {
+            change.apply(&mut data).unwrap();
+        }
+        assert_eq!(data, new);
+
+        FieldChange::set("b.c.0", json!(5))
+            .apply(&mut data)
+            .unwrap();
+        FieldChange::set("g.h", json!(1)).apply(&mut data).unwrap();
+        assert_eq!(data["b"]["c"], json!([5, 3]));
+        assert_eq!(data["g"], json!({"h": 1}));
+        assert!(FieldChange::set("b.c.2", json!(0))
+            .apply(&mut data)
+            .is_err());
+    }
+
+    #[test]
+    fn test_incremental_rule() {
+        let mut dl = DataLogic::new();
+        let rule = dl
+            .compile(r#"{"/": [{"var": "total"}, {"var": "count"}]}"#, None)
+            .unwrap();
+        let mut incremental = IncrementalRule::new(rule);
+
+        // Without a previous result, the rule is evaluated
+        let changes = [FieldChange::set("total", json!(10))];
+        assert!(incremental.update(&dl, &changes).is_err());
+        let changes = [FieldChange::set("count", json!(4))];
+        assert_eq!(incremental.update(&dl, &changes).unwrap(), json!(2.5));
+        assert_eq!(incremental.data(), &json!({"total": 10, "count": 4}));
+
+        let changes = [FieldChange::set("other", json!(1))];
+        assert_eq!(incremental.update(&dl, &changes).unwrap(), json!(2.5));
+        assert_eq!(
+            incremental.stats(),
+            IncrementalStats {
+                reused: 1,
+                evaluated: 2
+            }
+        );
+
+        // A configuration change of the engine invalidates the result
+        dl.set_limits(crate::Limits::new().with_max_string_len(10));
+        assert_eq!(incremental.update(&dl, &changes).unwrap(), json!(2.5));
+        assert_eq!(incremental.stats().evaluated, 3);
+
+        let changes = [FieldChange::remove("count")];
+        assert!(incremental.update(&dl, &changes).is_err());
+    }
+}
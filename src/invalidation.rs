@@ -0,0 +1,129 @@
+//! Invalidation of compiled rules
+//!
+//! A compiled rule bakes in the operator registrations and parser
+//! configuration of the DataLogic instance that compiled it. Every change to
+//! them bumps the generation of the instance and is reported to the
+//! listeners registered with
+//! [`DataLogic::on_invalidate`](crate::DataLogic::on_invalidate), so caches
+//! can recompile the rules affected by the change.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::arena::global_operator_generation;
+use crate::compiled::CompiledRule;
+use crate::limits::Limits;
+use crate::semantics::Semantics;
+
+/// A change to the registrations of a DataLogic instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryChange {
+    /// A custom operator was registered or replaced
+    ///
+    /// Versioned operators are reported under their base name, such as
+    /// `acme/score`.
+    Operator(String),
+    /// A model of the `predict` operator was registered or replaced
+    Model(String),
+    /// A parser was registered
+    Parser(String),
+    /// The default parser was changed
+    DefaultParser(String),
+    /// The semantics profile was changed
+    Semantics(Semantics),
+    /// The limits on the sizes of built values were changed
+    Limits(Limits),
+}
+
+impl fmt::Display for RegistryChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryChange::Operator(name) => write!(f, "operator '{}' changed", name),
+            RegistryChange::Model(name) => write!(f, "model '{}' changed", name),
+            RegistryChange::Parser(name) => write!(f, "parser '{}' registered", name),
+            RegistryChange::DefaultParser(name) => write!(f, "default parser set to '{}'", name),
+            RegistryChange::Semantics(semantics) => write!(f, "semantics set to '{}'", semantics),
+            RegistryChange::Limits(limits) => write!(f, "limits set to {}", limits),
+        }
+    }
+}
+
+/// A registration change reported to invalidation listeners
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invalidation {
+    /// The generation of the instance after the change
+    pub generation: u64,
+    /// The change that was made
+    pub change: RegistryChange,
+}
+
+impl Invalidation {
+    /// Check if a rule compiled before the change may be affected by it
+    ///
+    /// Operator and model changes only affect rules using the operator,
+    /// while parser, semantics and limits changes affect every rule.
+    pub fn affects(&self, rule: &CompiledRule) -> bool {
+        match &self.change {
+            RegistryChange::Operator(name) => rule.uses_operator(name),
+            RegistryChange::Model(_) => rule.uses_operator("predict"),
+            RegistryChange::Parser(_)
+            | RegistryChange::DefaultParser(_)
+            | RegistryChange::Semantics(_)
+            | RegistryChange::Limits(_) => true,
+        }
+    }
+}
+
+/// A callback invoked on every registration change
+pub(crate) type InvalidationListener = Box<dyn Fn(&Invalidation) + Send + Sync>;
+
+/// Generation counters of a DataLogic instance
+#[derive(Debug, Default)]
+pub(crate) struct Generations {
+    /// The number of changes so far
+    current: u64,
+    /// The generation at which the parser configuration last changed
+    config: u64,
+    /// The generation at which each operator last changed
+    operators: HashMap<String, u64>,
+}
+
+impl Generations {
+    /// Returns the number of changes so far.
+    pub(crate) fn current(&self) -> u64 {
+        self.current
+    }
+
+    /// Records a change, returning the new generation.
+    pub(crate) fn bump(&mut self, change: &RegistryChange) -> u64 {
+        self.current += 1;
+        match change {
+            RegistryChange::Operator(name) => {
+                self.operators.insert(name.clone(), self.current);
+            }
+            RegistryChange::Model(_) => {
+                self.operators.insert("predict".to_string(), self.current);
+            }
+            RegistryChange::Parser(_)
+            | RegistryChange::DefaultParser(_)
+            | RegistryChange::Semantics(_)
+            | RegistryChange::Limits(_) => {
+                self.config = self.current;
+            }
+        }
+        self.current
+    }
+
+    /// Check if a rule was compiled before a change affecting it.
+    pub(crate) fn is_stale(&self, rule: &CompiledRule) -> bool {
+        let (generation, global_generation) = rule.generations();
+        self.config > generation
+            || rule.custom_operators().any(|name| {
+                self.operators
+                    .get(name)
+                    .is_some_and(|&changed| changed > generation)
+                    || global_operator_generation(name)
+                        .is_some_and(|changed| changed > global_generation)
+            })
+    }
+}
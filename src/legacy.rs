@@ -0,0 +1,135 @@
+//! Compatibility with the 2.x API
+//!
+//! Version 2 evaluated rules through a [`JsonLogic`] engine applied to
+//! [`Rule`]s built from `serde_json` values. This module keeps that API on
+//! top of [`DataLogic`]: rules are compiled once into a [`CompiledRule`], and
+//! every application evaluates it in a fresh scratch arena, so results and
+//! errors match those of the current API.
+//!
+//! New code should use [`DataLogic`] directly, which also gives access to
+//! custom operators, parsers and the other configuration of the engine.
+//!
+//! # Examples
+//!
+//! ```
+//! use datalogic_rs::legacy::{JsonLogic, Rule};
+//! use serde_json::json;
+//!
+//! let logic = JsonLogic::new();
+//! let rule = Rule::from_value(&json!({">": [{"var": "temp"}, 100]})).unwrap();
+//!
+//! assert_eq!(logic.apply(&rule, &json!({"temp": 110})).unwrap(), json!(true));
+//! assert_eq!(logic.apply(&rule, &json!({"temp": 90})).unwrap(), json!(false));
+//! ```
+
+use serde_json::Value as JsonValue;
+
+use crate::compiled::CompiledRule;
+use crate::datalogic::DataLogic;
+use crate::logic::Result;
+use crate::value::{borrow_json, ToJson};
+
+/// A rule parsed from a JSON value
+///
+/// The rule is validated and compiled when it is created, so it can be
+/// applied any number of times, from any thread, by any [`JsonLogic`].
+#[derive(Debug)]
+pub struct Rule {
+    source: JsonValue,
+    compiled: CompiledRule,
+}
+
+impl Rule {
+    /// Parses a rule from a JSON value
+    pub fn from_value(value: &JsonValue) -> Result<Self> {
+        let compiled = DataLogic::new().compile(&value.to_string(), None)?;
+        Ok(Self {
+            source: value.clone(),
+            compiled,
+        })
+    }
+
+    /// Returns the JSON value the rule was parsed from
+    pub fn as_value(&self) -> &JsonValue {
+        &self.source
+    }
+
+    /// Returns the compiled form of the rule
+    pub fn compiled(&self) -> &CompiledRule {
+        &self.compiled
+    }
+}
+
+/// An engine applying rules to JSON data
+#[derive(Default)]
+pub struct JsonLogic {
+    datalogic: DataLogic,
+}
+
+impl JsonLogic {
+    /// Creates an engine with the standard operators
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an engine on top of a configured [`DataLogic`]
+    ///
+    /// Its custom operators and limits apply to every rule. Rules are built
+    /// independently of any engine, so the constant parts of a rule are
+    /// folded with the default configuration when the rule is created.
+    pub fn with_datalogic(datalogic: DataLogic) -> Self {
+        Self { datalogic }
+    }
+
+    /// Returns the underlying engine
+    pub fn datalogic(&self) -> &DataLogic {
+        &self.datalogic
+    }
+
+    /// Applies a rule to data
+    pub fn apply(&self, rule: &Rule, data: &JsonValue) -> Result<JsonValue> {
+        let scratch = self.datalogic.scratch_arena();
+        let data = borrow_json(data, &scratch);
+        let result = self
+            .datalogic
+            .evaluate_compiled_in(&rule.compiled, &data, &scratch)?;
+        Ok(result.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogicError;
+    use serde_json::json;
+
+    #[test]
+    fn test_legacy_api() {
+        let logic = JsonLogic::new();
+        let rule =
+            Rule::from_value(&json!({"map": [{"var": "xs"}, {"*": [{"var": ""}, 2]}]})).unwrap();
+        assert_eq!(rule.as_value()["map"][0], json!({"var": "xs"}));
+        assert_eq!(
+            logic.apply(&rule, &json!({"xs": [1, 2, 3]})).unwrap(),
+            json!([2, 4, 6])
+        );
+        assert_eq!(logic.apply(&rule, &json!({})).unwrap(), json!([]));
+
+        // Errors of the evaluation are those of the current API
+        let rule = Rule::from_value(&json!({"throw": "failed"})).unwrap();
+        assert!(matches!(
+            logic.apply(&rule, &json!({})),
+            Err(LogicError::ThrownError { .. })
+        ));
+
+        // Rules are applied with the configuration of the wrapped engine
+        let mut dl = DataLogic::new();
+        dl.set_limits(crate::Limits::new().with_max_array_len(2));
+        let logic = JsonLogic::with_datalogic(dl);
+        let rule = Rule::from_value(&json!({"merge": [{"var": "xs"}, [3]]})).unwrap();
+        assert!(matches!(
+            logic.apply(&rule, &json!({"xs": [1, 2]})),
+            Err(LogicError::LimitExceeded { .. })
+        ));
+    }
+}
@@ -1,18 +1,102 @@
+// Tests use 3.14 as an arbitrary float, not as an approximation of pi
+#![cfg_attr(test, allow(clippy::approx_constant))]
+
 // Core types and functionality
 pub use datalogic::{CustomOperator, DataLogic};
-pub use error::LogicError;
-pub use logic::{Logic, Result};
-pub use value::{DataValue, FromDataValue, FromJson, IntoDataValue, ToJson};
+pub use error::{DetailedError, LogicError};
+pub use logic::{Coercion, CompilationReport, Logic, Result};
+pub use value::{DataValue, FromDataValue, FromJson, HostHandle, IntoDataValue, ToJson};
 
 // Re-export the simple operator types
 pub use arena::{SimpleOperatorAdapter, SimpleOperatorFn};
 
+// Re-export the process-wide operator registry functions
+pub use arena::{
+    has_global_operator, register_global_operator, register_global_simple_operator,
+    unregister_global_operator,
+};
+
+// Re-export the custom operator memoization types
+pub use arena::{CacheStats, MemoizeConfig};
+
+// Re-export the custom operator timeout configuration
+pub use arena::TimeoutConfig;
+
+// Re-export the versioned custom operator types
+pub use arena::{OperatorMetadata, OperatorVersion};
+
+// Re-export the runtime description of the engine build
+pub use capabilities::Capabilities;
+
+// Re-export the thread-safe compiled rule type
+pub use compiled::CompiledRule;
+
+// Re-export the regional settings of evaluations
+pub use context::EvaluationContext;
+
+// Re-export the fallback policy types
+pub use fallback::{Fallback, FallbackEvent, FallbackPolicy};
+
+// Re-export the invalidation types
+pub use invalidation::{Invalidation, RegistryChange};
+
+// Re-export the limits on built values
+pub use limits::Limits;
+
+// Re-export the logging types
+pub use logger::LogMetadata;
+
+// Re-export the operator override types
+pub use overrides::OperatorOverrides;
+
+// Re-export the resumable evaluation types
+pub use stepping::{Evaluation, Step};
+
+// Re-export the replay types
+pub use replay::{RecordedCall, ReplayBundle, ReplayConfig};
+
+// Re-export the semantics profiles
+pub use semantics::{IntegerOverflow, OutOfRangeIndex, Semantics};
+
+// Re-export the combination of top-level rule lists
+pub use parser::jsonlogic::RuleList;
+
+// Re-export the rule registry types
+pub use registry::{PrecompileProgress, PrecompileReport, RuleFailure, RuleRegistry, RuleWarning};
+
 // Internal modules with implementation details
+mod concurrent;
 mod parser;
 
 // Public modules
 pub mod arena;
+pub mod budget;
+pub mod capabilities;
+pub mod compare;
+pub mod compiled;
+pub mod compliance;
+pub mod context;
+pub mod datagen;
 pub mod datalogic;
+pub mod editing;
 pub mod error;
+pub mod fallback;
+pub mod incremental;
+pub mod invalidation;
+pub mod legacy;
+pub mod limits;
+pub mod lint;
+pub mod logger;
 pub mod logic;
+pub mod output;
+pub mod overrides;
+#[cfg(feature = "predict")]
+pub mod predict;
+pub mod registry;
+pub mod replay;
+pub mod semantics;
+pub mod simplify;
+pub mod stepping;
 pub mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
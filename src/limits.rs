@@ -0,0 +1,383 @@
+//! Hard limits on the values built and the work done by an evaluation
+//!
+//! Operators such as `merge`, `map` and `cat` build values whose size depends
+//! on the data, so adversarial data can make a single evaluation allocate
+//! huge arrays or strings in the arena. Likewise, deeply nested rules and
+//! iterations over large arrays let rules or data make an evaluation run for
+//! a long time. [`Limits`] caps those sizes and that work: an evaluation that
+//! would exceed them fails with [`LogicError::LimitExceeded`] instead.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::logic::{LogicError, Result};
+
+/// Caps on the sizes of the values built and the work done by an evaluation
+///
+/// Set with [`DataLogic::set_limits`](crate::DataLogic::set_limits). No limit
+/// is enforced by default.
+///
+/// The results of `cat` and `merge` are checked piece by piece as they are
+/// gathered, before anything is copied, and allocated once at their final
+/// size.
+///
+/// The execution limits, on the operations, iterations, nesting depth and
+/// time of an evaluation, are counted from the start of each evaluation.
+/// Setting any of them makes evaluations go through a slower, checked path.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::{DataLogic, Limits, LogicError};
+/// use serde_json::json;
+///
+/// let mut dl = DataLogic::new();
+/// dl.set_limits(Limits::new().with_max_array_len(3).with_max_string_len(8));
+///
+/// let rule = json!({"merge": [{"var": "a"}, {"var": "b"}]});
+/// let data = json!({"a": [1, 2], "b": [3, 4]});
+/// let error = dl.evaluate_json(&rule, &data, None).unwrap_err();
+/// assert_eq!(
+///     error,
+///     LogicError::LimitExceeded { limit: "max_array_len".to_string(), max: 3, actual: 4 }
+/// );
+///
+/// let rule = json!({"cat": ["abc", "def"]});
+/// assert_eq!(dl.evaluate_json(&rule, &json!({}), None).unwrap(), json!("abcdef"));
+///
+/// // Untrusted rules can be kept from iterating too much
+/// dl.set_limits(Limits::new().with_max_iterations(100));
+/// let rule = json!({"map": [{"var": "xs"}, {"map": [{"var": "../../xs"}, 1]}]});
+/// let data = json!({"xs": (0..20).collect::<Vec<_>>()});
+/// assert!(matches!(
+///     dl.evaluate_json(&rule, &data, None),
+///     Err(LogicError::LimitExceeded { .. })
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Limits {
+    max_array_len: Option<usize>,
+    max_string_len: Option<usize>,
+    max_iterations: Option<usize>,
+    max_operations: Option<usize>,
+    max_depth: Option<usize>,
+    time_limit: Option<Duration>,
+}
+
+impl Limits {
+    /// Creates limits that enforce nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caps the number of items of arrays built by operators
+    ///
+    /// Applies to array literals and to the results of `map`, `filter`,
+    /// `merge`, `sort`, `unique`, `related` and `split`.
+    pub fn with_max_array_len(mut self, max: usize) -> Self {
+        self.max_array_len = Some(max);
+        self
+    }
+
+    /// Caps the length in bytes of strings built by operators
+    ///
+    /// Applies to the results of `cat`, `replace` and `regex_replace`.
+    pub fn with_max_string_len(mut self, max: usize) -> Self {
+        self.max_string_len = Some(max);
+        self
+    }
+
+    /// Caps the number of items iterated over by an evaluation
+    ///
+    /// Counts every item `map`, `filter`, `reduce`, `all`, `some`, `none`,
+    /// `sort`, `group_by` and the other operators taking an expression
+    /// evaluate it for, over the whole evaluation, so nested iterations count
+    /// the product of their lengths.
+    pub fn with_max_iterations(mut self, max: usize) -> Self {
+        self.max_iterations = Some(max);
+        self
+    }
+
+    /// Caps the number of operations executed by an evaluation
+    ///
+    /// Every operator, array literal and computed variable evaluated counts
+    /// as one operation. Literals and plain variables are free.
+    pub fn with_max_operations(mut self, max: usize) -> Self {
+        self.max_operations = Some(max);
+        self
+    }
+
+    /// Caps the nesting depth of the operations of an evaluation
+    ///
+    /// This bounds the stack used by the recursive evaluation of deeply
+    /// nested rules.
+    pub fn with_max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Caps the wall-clock time of an evaluation
+    ///
+    /// The deadline is checked before each operation, so an operator that is
+    /// slow on its own, such as a custom operator, is not interrupted. Cap
+    /// those with [`DataLogic::set_operator_timeout`](crate::DataLogic::set_operator_timeout).
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Returns the maximum number of items of built arrays, if capped
+    pub fn max_array_len(&self) -> Option<usize> {
+        self.max_array_len
+    }
+
+    /// Returns the maximum length in bytes of built strings, if capped
+    pub fn max_string_len(&self) -> Option<usize> {
+        self.max_string_len
+    }
+
+    /// Returns the maximum number of items iterated over, if capped
+    pub fn max_iterations(&self) -> Option<usize> {
+        self.max_iterations
+    }
+
+    /// Returns the maximum number of operations executed, if capped
+    pub fn max_operations(&self) -> Option<usize> {
+        self.max_operations
+    }
+
+    /// Returns the maximum nesting depth of operations, if capped
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Returns the maximum time of an evaluation, if capped
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    /// Starts counting the work of an evaluation.
+    ///
+    /// Returns `None` when no execution limit is set, so nothing needs to be
+    /// counted.
+    pub(crate) fn start_execution(&self) -> Option<Execution> {
+        let limited = self.max_iterations.is_some()
+            || self.max_operations.is_some()
+            || self.max_depth.is_some()
+            || self.time_limit.is_some();
+        limited.then(|| Execution {
+            start: Instant::now(),
+            operations: 0,
+            iterations: 0,
+            depth: 0,
+        })
+    }
+
+    /// Counts an operation about to be executed at one more level of depth.
+    pub(crate) fn enter_operation(&self, execution: &mut Execution) -> Result<()> {
+        let operations = execution.operations + 1;
+        check("max_operations", self.max_operations, operations)?;
+        check("max_depth", self.max_depth, execution.depth + 1)?;
+        if let Some(limit) = self.time_limit {
+            let elapsed = execution.start.elapsed();
+            if elapsed >= limit {
+                return Err(LogicError::LimitExceeded {
+                    limit: "time_limit_ms".to_string(),
+                    max: limit.as_millis() as usize,
+                    actual: elapsed.as_millis() as usize,
+                });
+            }
+        }
+        execution.operations = operations;
+        execution.depth += 1;
+        Ok(())
+    }
+
+    /// Counts items about to be iterated over.
+    pub(crate) fn count_iterations(&self, execution: &mut Execution, count: usize) -> Result<()> {
+        let iterations = execution.iterations.saturating_add(count);
+        check("max_iterations", self.max_iterations, iterations)?;
+        execution.iterations = iterations;
+        Ok(())
+    }
+
+    /// Checks the length of an array about to be built.
+    pub(crate) fn check_array_len(&self, len: usize) -> Result<()> {
+        check("max_array_len", self.max_array_len, len)
+    }
+
+    /// Checks the length of a string about to be built.
+    pub(crate) fn check_string_len(&self, len: usize) -> Result<()> {
+        check("max_string_len", self.max_string_len, len)
+    }
+}
+
+fn check(limit: &str, max: Option<usize>, actual: usize) -> Result<()> {
+    match max {
+        Some(max) if actual > max => Err(LogicError::LimitExceeded {
+            limit: limit.to_string(),
+            max,
+            actual,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The work done so far by an evaluation with execution limits
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Execution {
+    start: Instant,
+    operations: usize,
+    iterations: usize,
+    depth: usize,
+}
+
+impl Execution {
+    /// Leaves a level of depth entered by [`Limits::enter_operation`].
+    pub(crate) fn leave_operation(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+impl fmt::Display for Limits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |max: Option<usize>| max.map_or("none".to_string(), |max| max.to_string());
+        write!(
+            f,
+            "max_array_len: {}, max_string_len: {}, max_iterations: {}, \
+             max_operations: {}, max_depth: {}, time_limit_ms: {}",
+            show(self.max_array_len),
+            show(self.max_string_len),
+            show(self.max_iterations),
+            show(self.max_operations),
+            show(self.max_depth),
+            show(self.time_limit.map(|limit| limit.as_millis() as usize))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataLogic;
+    use serde_json::{json, Value as JsonValue};
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits::new().with_max_array_len(2);
+        assert!(limits.check_array_len(2).is_ok());
+        assert!(limits.check_array_len(3).is_err());
+        assert!(limits.check_string_len(usize::MAX).is_ok());
+        assert_eq!(
+            limits.to_string(),
+            "max_array_len: 2, max_string_len: none, max_iterations: none, \
+             max_operations: none, max_depth: none, time_limit_ms: none"
+        );
+
+        let mut dl = DataLogic::new();
+        dl.set_limits(Limits::new().with_max_array_len(2).with_max_string_len(4));
+        let data = json!({"xs": [1, 2, 3], "s": "a-b-c"});
+        for rule in [
+            json!({"map": [{"var": "xs"}, {"var": ""}]}),
+            json!({"filter": [{"var": "xs"}, true]}),
+            json!({"sort": [{"var": "xs"}]}),
+            json!([1, 2, {"var": "s"}]),
+            json!({"split": [{"var": "s"}, "-"]}),
+            json!({"cat": [{"var": "s"}, "!"]}),
+            json!({"replace": [{"var": "s"}, "-", "+"]}),
+        ] {
+            let error = dl.evaluate_json(&rule, &data, None).unwrap_err();
+            assert!(
+                matches!(error, LogicError::LimitExceeded { .. }),
+                "{} gave {}",
+                rule,
+                error
+            );
+        }
+
+        // Values within the limits, and data that is only read, are allowed
+        for (rule, expected) in [
+            (
+                json!({"filter": [{"var": "xs"}, {">": [{"var": ""}, 1]}]}),
+                json!([2, 3]),
+            ),
+            (json!({"var": "xs"}), json!([1, 2, 3])),
+            (json!({"cat": ["ab", "cd"]}), json!("abcd")),
+        ] {
+            assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_execution_limits() {
+        let limit_of = |dl: &DataLogic, rule: &JsonValue, data: &JsonValue| match dl
+            .evaluate_json(rule, data, None)
+        {
+            Err(LogicError::LimitExceeded { limit, .. }) => Some(limit),
+            Err(error) => panic!("{} gave {}", rule, error),
+            Ok(_) => None,
+        };
+        let data = json!({"xs": [1, 2, 3, 4, 5]});
+
+        // Iterations are counted over the whole evaluation
+        let mut dl = DataLogic::new();
+        dl.set_limits(Limits::new().with_max_iterations(10));
+        let nested = json!({"map": [{"var": "xs"}, {"map": [{"var": "../../xs"}, 1]}]});
+        assert_eq!(
+            limit_of(&dl, &nested, &data).as_deref(),
+            Some("max_iterations")
+        );
+        for rule in [
+            json!({"filter": [{"var": "xs"}, true]}),
+            json!({"reduce": [{"var": "xs"}, {"+": [{"var": "current"}, {"var": "accumulator"}]}, 0]}),
+            json!({"all": [{"var": "xs"}, {">": [{"var": ""}, 0]}]}),
+            json!({"+": [{"reduce": [{"var": "xs"}, {"var": "current"}, 0]}, {"reduce": [{"var": "xs"}, {"var": "current"}, 0]}]}),
+        ] {
+            assert_eq!(limit_of(&dl, &rule, &data), None, "{}", rule);
+        }
+        let twice = json!([{"map": [{"var": "xs"}, 1]}, {"map": [{"var": "xs"}, 1]}, {"map": [{"var": "xs"}, 1]}]);
+        assert_eq!(
+            limit_of(&dl, &twice, &data).as_deref(),
+            Some("max_iterations")
+        );
+
+        // Counters start over with every evaluation
+        let rule = json!({"map": [{"var": "xs"}, 1]});
+        for _ in 0..3 {
+            assert_eq!(limit_of(&dl, &rule, &data), None);
+        }
+
+        dl.set_limits(Limits::new().with_max_operations(5));
+        assert_eq!(limit_of(&dl, &rule, &data).as_deref(), None);
+        let rule = json!({"map": [{"var": "xs"}, {"+": [{"var": ""}, 1]}]});
+        assert_eq!(
+            limit_of(&dl, &rule, &data).as_deref(),
+            Some("max_operations")
+        );
+
+        dl.set_limits(Limits::new().with_max_depth(3));
+        let mut deep = json!({"var": "xs.0"});
+        for _ in 0..3 {
+            deep = json!({"+": [deep, 1]});
+        }
+        assert_eq!(limit_of(&dl, &deep, &data), None);
+        let deeper = json!({"+": [deep, 1]});
+        assert_eq!(limit_of(&dl, &deeper, &data).as_deref(), Some("max_depth"));
+
+        // A limit exceeded while sorting is not mistaken for equal keys
+        dl.set_limits(Limits::new().with_max_operations(3));
+        let rule = json!({"sort": [{"var": "xs"}, {"-": [0, {"var": ""}]}]});
+        assert_eq!(
+            limit_of(&dl, &rule, &data).as_deref(),
+            Some("max_operations")
+        );
+
+        dl.set_limits(Limits::new().with_time_limit(Duration::ZERO));
+        let rule = json!({"map": [{"var": "xs"}, {"+": [{"var": ""}, 1]}]});
+        assert_eq!(
+            limit_of(&dl, &rule, &data).as_deref(),
+            Some("time_limit_ms")
+        );
+        assert_eq!(limit_of(&dl, &json!({"var": "xs"}), &data), None);
+    }
+}
@@ -0,0 +1,597 @@
+//! Rule linting
+//!
+//! The linter inspects rule sources for common anti-patterns, such as
+//! comparisons against NaN or `if` chains nested so deeply that they are hard
+//! to read. Each finding names the sub-expression it applies to, using the
+//! same paths as [`DetailedError`](crate::DetailedError), and many come with
+//! a suggested rewrite that [`Linter::fix`] can apply automatically.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use serde_json::{json, Map, Value as JsonValue};
+
+/// An anti-pattern detected by the linter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintRule {
+    /// A comparison against NaN, which never holds
+    NanComparison,
+    /// An `or` with a truthy literal argument, whose later arguments are dead
+    ConstantOr,
+    /// An `if` whose branches are all identical
+    DuplicateBranches,
+    /// A chain of `if` expressions nested in their else branches
+    NestedIf,
+    /// A path checked by `missing` or `missing_some` that the rule never reads
+    UnusedCheckedVariable,
+}
+
+impl LintRule {
+    /// All lint rules
+    pub const ALL: [LintRule; 5] = [
+        LintRule::NanComparison,
+        LintRule::ConstantOr,
+        LintRule::DuplicateBranches,
+        LintRule::NestedIf,
+        LintRule::UnusedCheckedVariable,
+    ];
+
+    /// Returns the name of the rule, such as `"nested-if"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::NanComparison => "nan-comparison",
+            LintRule::ConstantOr => "constant-or",
+            LintRule::DuplicateBranches => "duplicate-branches",
+            LintRule::NestedIf => "nested-if",
+            LintRule::UnusedCheckedVariable => "unused-checked-variable",
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A finding of the linter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    /// The rule that was violated
+    pub rule: LintRule,
+    /// The path of the offending sub-expression, empty for the root
+    pub path: String,
+    /// A description of the problem
+    pub message: String,
+    /// A suggested replacement for the sub-expression, if one exists
+    pub fix: Option<JsonValue>,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{} at {}: {}", self.rule, path, self.message)?;
+        if let Some(fix) = &self.fix {
+            write!(f, " (suggested: {})", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// A configurable set of lint rules
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::lint::{LintRule, Linter};
+/// use serde_json::json;
+///
+/// let rule = json!({"if": [
+///     {"<": [{"var": "age"}, 13]}, "child",
+///     {"if": [{"<": [{"var": "age"}, 20]}, "teen",
+///         {"if": [{"<": [{"var": "age"}, 65]}, "adult", "senior"]}]}
+/// ]});
+///
+/// let linter = Linter::new();
+/// let lints = linter.lint(&rule);
+/// assert_eq!(lints[0].rule, LintRule::NestedIf);
+///
+/// assert_eq!(linter.fix(&rule), json!({"if": [
+///     {"<": [{"var": "age"}, 13]}, "child",
+///     {"<": [{"var": "age"}, 20]}, "teen",
+///     {"<": [{"var": "age"}, 65]}, "adult",
+///     "senior"
+/// ]}));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Linter {
+    rules: BTreeSet<LintRule>,
+    max_if_depth: usize,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the operator and arguments of an expression, if it is one.
+///
+/// A `preserve` is not treated as an expression, so that the data it holds is
+/// never inspected or rewritten as rules. The arguments of an annotation
+/// given as `{"note": ..., "rule": ...}` are its note and its rule.
+pub(crate) fn as_operator(value: &JsonValue) -> Option<(&str, Vec<&JsonValue>)> {
+    let object = value.as_object()?;
+    if object.len() != 1 {
+        return None;
+    }
+    let (op, args) = object.iter().next()?;
+    if op == "preserve" {
+        return None;
+    }
+    let args = match args {
+        JsonValue::Array(items) => items.iter().collect(),
+        JsonValue::Object(fields) if is_annotation(op) && fields.len() == 2 => {
+            match (fields.get("note"), fields.get("rule")) {
+                (Some(note), Some(rule)) => vec![note, rule],
+                _ => vec![args],
+            }
+        }
+        arg => vec![arg],
+    };
+    Some((op.as_str(), args))
+}
+
+/// Returns true if an operator documents the rule it wraps.
+pub(crate) fn is_annotation(op: &str) -> bool {
+    matches!(op, "annotate" | "//")
+}
+
+/// Builds an expression applying an operator to its arguments.
+pub(crate) fn operator(op: &str, args: JsonValue) -> JsonValue {
+    let mut object = Map::new();
+    object.insert(op.to_string(), args);
+    JsonValue::Object(object)
+}
+
+/// Returns true if a value is a literal rather than an expression.
+pub(crate) fn is_literal(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Object(_) => false,
+        JsonValue::Array(items) => items.iter().all(is_literal),
+        _ => true,
+    }
+}
+
+/// Returns true if a literal is truthy.
+pub(crate) fn is_truthy(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::Bool(b) => *b,
+        JsonValue::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        JsonValue::String(s) => !s.is_empty(),
+        JsonValue::Array(items) => !items.is_empty(),
+        JsonValue::Object(_) => true,
+    }
+}
+
+/// Returns true if a value is NaN or an expression that always yields NaN.
+fn is_nan(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::String(s) => s.eq_ignore_ascii_case("nan"),
+        _ => match as_operator(value) {
+            Some(("/" | "%", args)) => {
+                args.len() == 2 && args.iter().all(|arg| arg.as_f64() == Some(0.0))
+            }
+            _ => false,
+        },
+    }
+}
+
+/// Returns the paths read by the `var` expressions of a rule.
+///
+/// Returns `None` if a path is computed at runtime.
+fn read_paths(value: &JsonValue, paths: &mut BTreeSet<String>) -> Option<()> {
+    match as_operator(value) {
+        Some(("var", args)) => match args.first() {
+            Some(JsonValue::String(path)) => {
+                paths.insert(path.clone());
+            }
+            Some(JsonValue::Number(n)) => {
+                paths.insert(n.to_string());
+            }
+            None => {
+                paths.insert(String::new());
+            }
+            Some(_) => return None,
+        },
+        Some(("missing" | "missing_some", _)) => {}
+        // Notes are not read
+        Some((op, args)) if is_annotation(op) => {
+            if let Some(rule) = args.get(1) {
+                read_paths(rule, paths)?;
+            }
+        }
+        // The rule evaluated is only known at runtime
+        Some(("unpreserve", _)) => return None,
+        Some((_, args)) => {
+            for arg in args {
+                read_paths(arg, paths)?;
+            }
+        }
+        None => {
+            if let JsonValue::Array(items) = value {
+                for item in items {
+                    read_paths(item, paths)?;
+                }
+            }
+        }
+    }
+    Some(())
+}
+
+/// Returns true if reading `read` covers the checked path `checked`.
+fn covers(read: &str, checked: &str) -> bool {
+    read.is_empty()
+        || read == checked
+        || checked
+            .strip_prefix(read)
+            .is_some_and(|rest| rest.starts_with('.'))
+        || read
+            .strip_prefix(checked)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Returns the paths checked by a `missing` or `missing_some` expression.
+fn checked_paths(op: &str, args: &[&JsonValue]) -> Vec<String> {
+    let paths: Vec<&JsonValue> = match op {
+        "missing" => match args {
+            [JsonValue::Array(items)] => items.iter().collect(),
+            _ => args.to_vec(),
+        },
+        "missing_some" => match args.get(1) {
+            Some(JsonValue::Array(items)) => items.iter().collect(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+    paths
+        .into_iter()
+        .filter_map(|path| path.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Flattens the else chain of an `if` into a single argument list.
+fn flatten_if(args: &[&JsonValue]) -> Vec<JsonValue> {
+    let mut flat: Vec<JsonValue> = args.iter().map(|arg| (*arg).clone()).collect();
+    while flat.len() % 2 == 1 {
+        let nested = match flat.last().and_then(as_operator) {
+            Some(("if" | "?:", nested)) => nested.into_iter().cloned().collect::<Vec<_>>(),
+            _ => break,
+        };
+        flat.pop();
+        flat.extend(nested);
+    }
+    flat
+}
+
+/// Returns the depth of the else chain of an `if`.
+fn if_chain_depth(args: &[&JsonValue]) -> usize {
+    let mut depth = 1;
+    let mut args = args.to_vec();
+    while args.len() % 2 == 1 {
+        match args.last().and_then(|last| as_operator(last)) {
+            Some(("if" | "?:", nested)) => {
+                depth += 1;
+                args = nested;
+            }
+            _ => break,
+        }
+    }
+    depth
+}
+
+impl Linter {
+    /// Create a linter with every rule enabled
+    ///
+    /// `if` chains are reported from a nesting depth of three.
+    pub fn new() -> Self {
+        Self {
+            rules: LintRule::ALL.into_iter().collect(),
+            max_if_depth: 3,
+        }
+    }
+
+    /// Enable a rule
+    pub fn with_rule(mut self, rule: LintRule) -> Self {
+        self.rules.insert(rule);
+        self
+    }
+
+    /// Disable a rule
+    pub fn without_rule(mut self, rule: LintRule) -> Self {
+        self.rules.remove(&rule);
+        self
+    }
+
+    /// Report `if` chains nested at least `depth` levels deep
+    pub fn with_max_if_depth(mut self, depth: usize) -> Self {
+        self.max_if_depth = depth.max(2);
+        self
+    }
+
+    /// Check if a rule is enabled
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    /// Lint a rule, returning the findings in the order of the rule
+    pub fn lint(&self, rule: &JsonValue) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        let mut read = BTreeSet::new();
+        let read = read_paths(rule, &mut read).map(|_| read);
+        self.visit(rule, "", read.as_ref(), &mut lints);
+        lints
+    }
+
+    /// Apply every suggested rewrite to a rule
+    ///
+    /// Rewrites are applied bottom-up, so that fixes of nested expressions
+    /// are reflected in the fixes of the expressions containing them.
+    pub fn fix(&self, rule: &JsonValue) -> JsonValue {
+        let fixed = match rule {
+            JsonValue::Array(items) => {
+                JsonValue::Array(items.iter().map(|item| self.fix(item)).collect())
+            }
+            JsonValue::Object(_) => match as_operator(rule) {
+                Some((op, args)) if is_annotation(op) => operator(
+                    op,
+                    JsonValue::Array(args.into_iter().map(|arg| self.fix(arg)).collect()),
+                ),
+                Some((op, _)) => operator(op, self.fix(&rule[op])),
+                None => rule.clone(),
+            },
+            value => value.clone(),
+        };
+
+        match self
+            .check(&fixed, None)
+            .into_iter()
+            .find_map(|lint| lint.fix)
+        {
+            Some(replacement) => self.fix(&replacement),
+            None => fixed,
+        }
+    }
+
+    /// Lints an expression and its arguments.
+    fn visit(
+        &self,
+        value: &JsonValue,
+        path: &str,
+        read: Option<&BTreeSet<String>>,
+        lints: &mut Vec<Lint>,
+    ) {
+        for mut lint in self.check(value, read) {
+            lint.path = path.to_string();
+            lints.push(lint);
+        }
+
+        match as_operator(value) {
+            Some((op, args)) => {
+                for (index, arg) in args.into_iter().enumerate() {
+                    self.visit(arg, &format!("{}/{}/{}", path, op, index), read, lints);
+                }
+            }
+            None => {
+                if let JsonValue::Array(items) = value {
+                    for (index, item) in items.iter().enumerate() {
+                        self.visit(item, &format!("{}/{}", path, index), read, lints);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks a single expression, without its arguments.
+    ///
+    /// `read` holds the paths read by the whole rule, if they are all known.
+    fn check(&self, value: &JsonValue, read: Option<&BTreeSet<String>>) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        let Some((op, args)) = as_operator(value) else {
+            return lints;
+        };
+        let lint = |rule, message: String, fix| Lint {
+            rule,
+            path: String::new(),
+            message,
+            fix,
+        };
+
+        match op {
+            "==" | "===" | "!=" | "!==" | "<" | "<=" | ">" | ">="
+                if self.is_enabled(LintRule::NanComparison)
+                    && args.iter().any(|arg| is_nan(arg)) =>
+            {
+                lints.push(lint(
+                    LintRule::NanComparison,
+                    format!(
+                        "'{}' against NaN never holds; check the type of the value instead",
+                        op
+                    ),
+                    None,
+                ));
+            }
+            "or" if self.is_enabled(LintRule::ConstantOr) => {
+                let truthy = args
+                    .iter()
+                    .position(|arg| is_literal(arg) && is_truthy(arg));
+                if let Some(index) = truthy {
+                    let fix = if index == 0 {
+                        args[0].clone()
+                    } else {
+                        json!({"or": args[..=index].to_vec()})
+                    };
+                    if index + 1 < args.len() || index == 0 {
+                        lints.push(lint(
+                            LintRule::ConstantOr,
+                            format!(
+                                "argument {} is always truthy, so the 'or' never reaches the arguments after it",
+                                index
+                            ),
+                            Some(fix),
+                        ));
+                    }
+                }
+            }
+            "if" | "?:" => {
+                let branches: Vec<&JsonValue> = args
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| index % 2 == 1 || index + 1 == args.len())
+                    .map(|(_, arg)| *arg)
+                    .collect();
+                if self.is_enabled(LintRule::DuplicateBranches)
+                    && args.len() >= 3
+                    && args.len() % 2 == 1
+                    && branches.windows(2).all(|pair| pair[0] == pair[1])
+                {
+                    lints.push(lint(
+                        LintRule::DuplicateBranches,
+                        "every branch evaluates to the same expression".to_string(),
+                        Some(branches[0].clone()),
+                    ));
+                }
+
+                let depth = if_chain_depth(&args);
+                if self.is_enabled(LintRule::NestedIf) && depth >= self.max_if_depth {
+                    lints.push(lint(
+                        LintRule::NestedIf,
+                        format!(
+                            "{} nested 'if' expressions can be a single 'if' with one condition per case",
+                            depth
+                        ),
+                        Some(operator(op, JsonValue::Array(flatten_if(&args)))),
+                    ));
+                }
+            }
+            "missing" | "missing_some" if self.is_enabled(LintRule::UnusedCheckedVariable) => {
+                if let Some(read) = read {
+                    for checked in checked_paths(op, &args) {
+                        if !read.iter().any(|path| covers(path, &checked)) {
+                            lints.push(lint(
+                                LintRule::UnusedCheckedVariable,
+                                format!("'{}' is checked but never read by the rule", checked),
+                                None,
+                            ));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(lints: &[Lint]) -> Vec<(LintRule, &str)> {
+        lints
+            .iter()
+            .map(|lint| (lint.rule, lint.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_anti_patterns() {
+        let rule = json!({"and": [
+            {"==": [{"var": "score"}, "NaN"]},
+            {">": [{"var": "score"}, {"/": [0, 0]}]},
+            {"or": [{"var": "vip"}, true, {"var": "trial"}]},
+            {"if": [{"var": "flag"}, {"var": "a"}, {"var": "a"}]},
+            {"!": {"missing": ["a", "score", "unused"]}}
+        ]});
+
+        let lints = Linter::new().lint(&rule);
+        assert_eq!(
+            rules(&lints),
+            [
+                (LintRule::NanComparison, "/and/0"),
+                (LintRule::NanComparison, "/and/1"),
+                (LintRule::ConstantOr, "/and/2"),
+                (LintRule::DuplicateBranches, "/and/3"),
+                (LintRule::UnusedCheckedVariable, "/and/4/!/0"),
+            ]
+        );
+        assert_eq!(lints[2].fix, Some(json!({"or": [{"var": "vip"}, true]})));
+        assert_eq!(
+            lints[4].to_string(),
+            "unused-checked-variable at /and/4/!/0: 'unused' is checked but never read by the rule"
+        );
+
+        let fixed = Linter::new().fix(&rule);
+        assert_eq!(fixed["and"][2], json!({"or": [{"var": "vip"}, true]}));
+        assert_eq!(fixed["and"][3], json!({"var": "a"}));
+        assert_eq!(fixed["and"][0], rule["and"][0]);
+    }
+
+    #[test]
+    fn test_linter_configuration() {
+        let rule = json!({"if": [
+            {"var": "a"}, 1,
+            {"if": [{"var": "b"}, 2, 3]}
+        ]});
+
+        assert!(Linter::new().lint(&rule).is_empty());
+        let linter = Linter::new().with_max_if_depth(2);
+        assert_eq!(rules(&linter.lint(&rule)), [(LintRule::NestedIf, "")]);
+        assert_eq!(
+            linter.fix(&rule),
+            json!({"if": [{"var": "a"}, 1, {"var": "b"}, 2, 3]})
+        );
+        assert!(linter
+            .without_rule(LintRule::NestedIf)
+            .lint(&rule)
+            .is_empty());
+
+        // Checked paths read through a parent or dynamic path are not reported
+        let rule = json!({"if": [{"missing": ["user.age"]}, "", {"var": "user"}]});
+        assert!(Linter::new().lint(&rule).is_empty());
+        let rule = json!({"if": [{"missing": ["age"]}, "", {"var": {"cat": ["a", "ge"]}}]});
+        assert!(Linter::new().lint(&rule).is_empty());
+
+        // A truthy first argument makes the whole 'or' constant
+        let rule = json!({"or": [true, {"var": "x"}]});
+        assert_eq!(Linter::new().fix(&rule), json!(true));
+
+        // Preserved values are data, and templates may read any path
+        let rule = json!({"preserve": {"or": [true, {"var": "x"}]}});
+        assert!(Linter::new().lint(&rule).is_empty());
+        assert_eq!(Linter::new().fix(&rule), rule);
+        let rule = json!({"if": [{"missing": ["age"]}, "", {"unpreserve": {"var": "t"}}]});
+        assert!(Linter::new().lint(&rule).is_empty());
+
+        // Annotated rules are linted and fixed, their notes kept
+        let rule = json!({"if": [
+            {"missing": ["age"]}, "",
+            {"annotate": {"note": "Adults", "rule": {">=": [{"var": "age"}, 18]}}}
+        ]});
+        assert!(Linter::new().lint(&rule).is_empty());
+        let rule = json!({"annotate": ["Always", {"or": [true, {"var": "x"}]}]});
+        assert_eq!(
+            rules(&Linter::new().lint(&rule)),
+            [(LintRule::ConstantOr, "/annotate/1")]
+        );
+        assert_eq!(
+            Linter::new().fix(&rule),
+            json!({"annotate": ["Always", true]})
+        );
+    }
+}
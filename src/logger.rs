@@ -0,0 +1,28 @@
+//! Reporting of values logged by rules
+//!
+//! The `log` operator hands the value of its argument to the logger installed
+//! with [`DataLogic::set_logger`](crate::DataLogic::set_logger), so rules can
+//! be debugged through the logging facility of the application.
+
+use std::sync::Arc;
+
+use crate::value::DataValue;
+
+/// Where a value reported by the `log` operator was logged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogMetadata {
+    /// The path of the `log` expression within the rule
+    ///
+    /// Paths step through operator arguments in the same way as
+    /// [`DetailedError::path`](crate::DetailedError::path), so `/if/1` is a
+    /// `log` that is the second argument of the root `if`, and the root is
+    /// the empty path.
+    pub path: String,
+    /// The id of the compiled rule holding the `log` expression, if it has one
+    ///
+    /// See [`CompiledRule::with_id`](crate::CompiledRule::with_id).
+    pub rule_id: Option<String>,
+}
+
+/// A callback receiving the values reported by the `log` operator
+pub(crate) type Logger = Arc<dyn Fn(&DataValue, LogMetadata) + Send + Sync>;
@@ -5,6 +5,7 @@
 
 use super::token::{OperatorType, Token};
 use crate::arena::DataArena;
+use crate::output::OutputType;
 use crate::value::DataValue;
 
 /// A logic expression.
@@ -19,18 +20,25 @@ pub struct Logic<'a> {
 
     /// The arena in which the tokens are allocated.
     arena: &'a DataArena,
+
+    /// The declared type of the result, if any.
+    output_type: Option<&'a OutputType>,
 }
 
 impl<'a> Logic<'a> {
     /// Creates a new logic expression.
     pub fn new(root: &'a Token<'a>, arena: &'a DataArena) -> Self {
-        Self { root, arena }
+        Self {
+            root,
+            arena,
+            output_type: None,
+        }
     }
 
     /// Creates a new logic expression from a token.
     pub fn from_token(token: Token<'a>, arena: &'a DataArena) -> Self {
         let root = arena.alloc(token);
-        Self { root, arena }
+        Self::new(root, arena)
     }
 
     /// Returns the root token of the logic expression.
@@ -38,6 +46,34 @@ impl<'a> Logic<'a> {
         self.root
     }
 
+    /// Returns a fingerprint of the logic expression.
+    ///
+    /// See [`Token::fingerprint`].
+    pub fn fingerprint(&self) -> u64 {
+        self.root.fingerprint()
+    }
+
+    /// Returns a listing of the logic expression, one token per line.
+    ///
+    /// See [`Token::disassemble`].
+    pub fn disassemble(&self) -> String {
+        self.root.disassemble()
+    }
+
+    /// Declares the type of the result of the logic expression.
+    ///
+    /// Evaluation coerces the result to the type, failing if it does not
+    /// conform. See [`OutputType::coerce`].
+    pub fn with_output_type(mut self, output_type: OutputType) -> Self {
+        self.output_type = Some(self.arena.alloc_constant(output_type));
+        self
+    }
+
+    /// Returns the declared type of the result, if any.
+    pub fn output_type(&self) -> Option<&'a OutputType> {
+        self.output_type
+    }
+
     /// Returns the arena in which the tokens are allocated.
     pub fn arena(&self) -> &'a DataArena {
         self.arena
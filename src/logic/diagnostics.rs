@@ -0,0 +1,367 @@
+//! Structured error reporting.
+//!
+//! This module evaluates a rule while tracing where a failure originates, so
+//! the error can be reported with the path of the failing sub-expression, its
+//! operator and the values of its arguments.
+
+use super::error::{DetailedError, Result};
+use super::evaluator::evaluate;
+use super::explain::operator_args;
+use super::token::Token;
+use crate::arena::{token_key, DataArena, FailureSite};
+use crate::value::{DataValue, ToJson};
+use serde_json::Value as JsonValue;
+
+/// An implicit conversion of an operand performed while evaluating a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coercion {
+    /// The path of the sub-expression performing the conversion, in the
+    /// format of [`DetailedError::path`]
+    pub path: String,
+    /// The operator performing the conversion
+    pub operator: Option<String>,
+    /// The type of the operand, such as `"string"` or `"null"`
+    pub from: &'static str,
+    /// The type the operand was converted to
+    pub to: &'static str,
+    /// The value of the operand
+    pub value: JsonValue,
+}
+
+/// Returns the name of a token and its child tokens.
+fn children<'a>(
+    token: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> (Option<&'a str>, Vec<&'a Token<'a>>) {
+    match token {
+        Token::Literal(_) => (None, Vec::new()),
+        Token::ArrayLiteral(items) => (None, items.clone()),
+        Token::Variable { default, .. } => (Some("var"), default.iter().copied().collect()),
+        Token::DynamicVariable { path_expr, default } => {
+            let mut children = vec![*path_expr];
+            children.extend(default.iter().copied());
+            (Some("var"), children)
+        }
+        Token::Operator { op_type, args }
+        | Token::CompiledMatch { op_type, args, .. }
+        | Token::CompiledRegex { op_type, args, .. }
+        | Token::CaseFolded { op_type, args } => {
+            (Some(op_type.as_str()), operator_args(args, arena))
+        }
+        Token::CustomOperator { name, args } => (Some(name), operator_args(args, arena)),
+    }
+}
+
+/// Records the outcome of evaluating a token while tracing errors.
+///
+/// The first token to fail becomes the failure site. A later success means
+/// the failure was handled, for example by `try`, and clears the site.
+pub(crate) fn record_outcome<'a>(
+    token: &'a Token<'a>,
+    result: &Result<&'a DataValue<'a>>,
+    arena: &'a DataArena,
+) {
+    if result.is_ok() {
+        if arena.has_failure() {
+            arena.set_failure(None);
+        }
+        return;
+    }
+    if arena.has_failure() {
+        return;
+    }
+
+    // Evaluate the arguments in the context of the failure, without tracing
+    // the failures of the arguments themselves
+    arena.set_error_tracing(false);
+    let (name, args) = children(token, arena);
+    let arguments = args
+        .iter()
+        .map(|arg| {
+            evaluate(arg, arena)
+                .map(|value| value.to_json())
+                .unwrap_or(serde_json::Value::Null)
+        })
+        .collect();
+    arena.set_error_tracing(true);
+
+    arena.set_failure(Some(FailureSite {
+        token: token_key(token),
+        operator: name.map(str::to_string),
+        arguments,
+    }));
+}
+
+/// Finds the path from a token to the token with the given address.
+fn find_path<'a>(
+    token: &'a Token<'a>,
+    target: usize,
+    arena: &'a DataArena,
+    path: &mut String,
+) -> bool {
+    if token_key(token) == target {
+        return true;
+    }
+
+    let (name, args) = children(token, arena);
+    let len = path.len();
+    for (index, arg) in args.into_iter().enumerate() {
+        if let Some(name) = name {
+            path.push('/');
+            path.push_str(name);
+        }
+        path.push('/');
+        path.push_str(&index.to_string());
+        if find_path(arg, target, arena, path) {
+            return true;
+        }
+        path.truncate(len);
+    }
+    false
+}
+
+/// Returns the path of the innermost of the nested nodes being evaluated,
+/// outermost first.
+pub(crate) fn node_path<'a>(nodes: &[&'a Token<'a>], arena: &'a DataArena) -> String {
+    let mut path = String::new();
+    for pair in nodes.windows(2) {
+        let (name, args) = children(pair[0], arena);
+        let Some(index) = args.iter().position(|arg| std::ptr::eq(*arg, pair[1])) else {
+            continue;
+        };
+        if let Some(name) = name {
+            path.push('/');
+            path.push_str(name);
+        }
+        path.push('/');
+        path.push_str(&index.to_string());
+    }
+    path
+}
+
+/// Finds the token with the given address.
+fn find_token<'a>(
+    token: &'a Token<'a>,
+    target: usize,
+    arena: &'a DataArena,
+) -> Option<&'a Token<'a>> {
+    if token_key(token) == target {
+        return Some(token);
+    }
+    children(token, arena)
+        .1
+        .into_iter()
+        .find_map(|arg| find_token(arg, target, arena))
+}
+
+/// Evaluates a rule, recording every implicit conversion of an operand.
+///
+/// Numeric operators report operands converted to numbers, such as `"5"` or
+/// `null` in `+`, loose comparisons report operands converted to numbers or
+/// strings, and `cat` reports operands converted to strings. Conversions are
+/// listed in evaluation order, so an operator evaluated by `map` reports its
+/// conversions once per item.
+pub fn evaluate_with_coercion_trace<'a>(
+    token: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Result<(&'a DataValue<'a>, Vec<Coercion>)> {
+    arena.start_coercion_tracing();
+    let result = evaluate(token, arena);
+    let sites = arena.finish_coercion_tracing();
+    let value = result?;
+
+    let coercions = sites
+        .into_iter()
+        .map(|site| {
+            let mut path = String::new();
+            if !find_path(token, site.token, arena, &mut path) {
+                path.clear();
+            }
+            let operator = find_token(token, site.token, arena)
+                .and_then(|node| children(node, arena).0)
+                .map(str::to_string);
+            Coercion {
+                path,
+                operator,
+                from: site.from,
+                to: site.to,
+                value: site.value,
+            }
+        })
+        .collect();
+    Ok((value, coercions))
+}
+
+/// Evaluates a rule, locating the failing sub-expression on error.
+///
+/// On success this behaves like [`evaluate`]. On failure the error is
+/// returned together with the path of the innermost failing sub-expression,
+/// its operator and the values of its arguments in the context where it
+/// failed. Paths step through operator arguments, so `/if/1/+/0` is the first
+/// argument of the `+` that is the second argument of the root `if`.
+pub fn evaluate_with_diagnostics<'a>(
+    token: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> std::result::Result<&'a DataValue<'a>, Box<DetailedError>> {
+    arena.set_error_tracing(true);
+    let result = evaluate(token, arena);
+    arena.set_error_tracing(false);
+    let failure = arena.take_failure();
+
+    result.map_err(|error| {
+        let mut path = String::new();
+        let (operator, arguments) = match failure {
+            Some(site) if find_path(token, site.token, arena, &mut path) => {
+                (site.operator, site.arguments)
+            }
+            _ => {
+                path.clear();
+                (None, Vec::new())
+            }
+        };
+        Box::new(DetailedError {
+            error,
+            path,
+            operator,
+            arguments,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::jsonlogic::parse_json;
+    use crate::value::FromJson;
+    use crate::LogicError;
+    use serde_json::json;
+
+    fn diagnose(rule: serde_json::Value, data: serde_json::Value) -> DetailedError {
+        let arena = DataArena::new();
+        let token = parse_json(&rule, &arena).unwrap();
+        let data = DataValue::from_json(&data, &arena);
+        arena.set_root_context(&data);
+        arena.set_current_context(&data, &DataValue::String("$"));
+        *evaluate_with_diagnostics(token, &arena).unwrap_err()
+    }
+
+    #[test]
+    fn test_error_path() {
+        let error = diagnose(
+            json!({"if": [
+                {"var": "flag"},
+                {"+": [{"throw": "boom"}, 1]},
+                0
+            ]}),
+            json!({"flag": true}),
+        );
+        assert_eq!(error.error, LogicError::thrown_error("boom"));
+        assert_eq!(error.path, "/if/1/+/0");
+        assert_eq!(error.operator.as_deref(), Some("throw"));
+        assert_eq!(error.arguments, vec![json!("boom")]);
+    }
+
+    #[test]
+    fn test_error_arguments_in_context() {
+        let error = diagnose(
+            json!({"map": [{"var": "items"}, {"-": [{"var": "price"}, {"var": "discount"}]}]}),
+            json!({"items": [{"price": 5, "discount": 1}, {"price": 5, "discount": "abc"}]}),
+        );
+        assert_eq!(error.error, LogicError::NaNError);
+        assert_eq!(error.path, "/map/1");
+        assert_eq!(error.operator.as_deref(), Some("-"));
+        assert_eq!(error.arguments, vec![json!(5), json!("abc")]);
+    }
+
+    fn trace(rule: serde_json::Value, data: serde_json::Value) -> Vec<Coercion> {
+        let arena = DataArena::new();
+        let token = parse_json(&rule, &arena).unwrap();
+        let data = DataValue::from_json(&data, &arena);
+        arena.set_root_context(&data);
+        arena.set_current_context(&data, &DataValue::String("$"));
+        let (_, coercions) = evaluate_with_coercion_trace(token, &arena).unwrap();
+        assert!(!arena.is_instrumented());
+        coercions
+    }
+
+    #[test]
+    fn test_coercion_trace() {
+        let coercions = trace(
+            json!({"if": [
+                {">": [{"var": "age"}, 18]},
+                {"cat": ["Items: ", {"var": "items"}]},
+                {"+": [{"var": "bonus"}, {"var": "missing"}]}
+            ]}),
+            json!({"age": "21", "items": ["a", "b"], "bonus": "5"}),
+        );
+        assert_eq!(
+            coercions,
+            vec![
+                Coercion {
+                    path: "/if/0".to_string(),
+                    operator: Some(">".to_string()),
+                    from: "string",
+                    to: "number",
+                    value: json!("21"),
+                },
+                Coercion {
+                    path: "/if/1".to_string(),
+                    operator: Some("cat".to_string()),
+                    from: "array",
+                    to: "string",
+                    value: json!(["a", "b"]),
+                },
+            ]
+        );
+
+        let coercions = trace(
+            json!({"map": [{"var": "items"}, {"+": [{"var": ""}, 1]}]}),
+            json!({"items": [1, null, "2"]}),
+        );
+        let converted: Vec<_> = coercions
+            .iter()
+            .map(|coercion| {
+                (
+                    coercion.path.as_str(),
+                    coercion.from,
+                    coercion.value.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            converted,
+            vec![
+                ("/map/1", "null", json!(null)),
+                ("/map/1", "string", json!("2"))
+            ]
+        );
+
+        // Operands that already have the expected type are not reported
+        let coercions = trace(
+            json!({"and": [
+                {"==": [{"var": "a"}, 1.0]},
+                {"cat": [{"var": "s"}, "b"]},
+                {"*": [{"var": "a"}, 3]}
+            ]}),
+            json!({"a": 1, "s": "a"}),
+        );
+        assert!(coercions.is_empty());
+    }
+
+    #[test]
+    fn test_handled_errors_are_ignored() {
+        let error = diagnose(
+            json!({"and": [
+                {"try": [{"throw": "ignored"}, true]},
+                {"throw": "boom"}
+            ]}),
+            json!({}),
+        );
+        assert_eq!(error.path, "/and/1");
+        assert_eq!(error.arguments, vec![json!("boom")]);
+        assert_eq!(
+            error.to_string(),
+            "Thrown error: boom at /and/1 in operator 'throw' with arguments [\"boom\"]"
+        );
+    }
+}
@@ -5,6 +5,7 @@
 use std::error::Error;
 use std::fmt;
 use std::result;
+use std::time::Duration;
 
 /// A specialized Result type for logic operations.
 pub type Result<T> = result::Result<T, LogicError>;
@@ -44,6 +45,65 @@ pub enum LogicError {
 
     /// A custom error with a message.
     Custom(String),
+
+    /// A custom operator panicked while being evaluated.
+    OperatorPanicked {
+        /// The name of the operator.
+        operator: String,
+        /// The panic message.
+        message: String,
+    },
+
+    /// A custom operator did not answer within its timeout.
+    ///
+    /// See [`TimeoutConfig`](crate::TimeoutConfig).
+    OperatorTimedOut {
+        /// The name of the operator.
+        operator: String,
+        /// The configured timeout.
+        timeout: Duration,
+    },
+
+    /// The result of a rule does not match its declared output type.
+    OutputTypeError {
+        /// The path of the offending value within the result, empty for the
+        /// result itself.
+        path: String,
+        /// The declared type.
+        expected: String,
+        /// The offending value.
+        actual: String,
+    },
+
+    /// An operator would build a value larger than a configured limit.
+    ///
+    /// See [`Limits`](crate::Limits).
+    LimitExceeded {
+        /// The name of the limit, such as `max_array_len`.
+        limit: String,
+        /// The configured maximum.
+        max: usize,
+        /// The size of the value the operator would have built.
+        actual: usize,
+    },
+
+    /// An integer operation overflowed 64 bits.
+    ///
+    /// See [`IntegerOverflow`](crate::IntegerOverflow).
+    IntegerOverflow {
+        /// The operator that overflowed.
+        operator: String,
+    },
+
+    /// An error raised by a compiled rule that has an id.
+    ///
+    /// See [`CompiledRule::with_id`](crate::CompiledRule::with_id).
+    InRule {
+        /// The id of the rule.
+        rule_id: String,
+        /// The error raised by the rule.
+        error: Box<LogicError>,
+    },
 }
 
 impl fmt::Display for LogicError {
@@ -70,11 +130,101 @@ impl fmt::Display for LogicError {
             LogicError::OperatorNotFoundError { operator } => {
                 write!(f, "Operator '{}' not found", operator)
             }
+            LogicError::OperatorPanicked { operator, message } => {
+                write!(f, "Operator '{}' panicked: {}", operator, message)
+            }
+            LogicError::OperatorTimedOut { operator, timeout } => {
+                write!(
+                    f,
+                    "Operator '{}' timed out after {} ms",
+                    operator,
+                    timeout.as_millis()
+                )
+            }
+            LogicError::OutputTypeError {
+                path,
+                expected,
+                actual,
+            } => {
+                let path = if path.is_empty() { "/" } else { path };
+                write!(
+                    f,
+                    "Output type error at {}: expected {}, got {}",
+                    path, expected, actual
+                )
+            }
+            LogicError::LimitExceeded { limit, max, actual } => {
+                write!(f, "Limit {} of {} exceeded: {}", limit, max, actual)
+            }
+            LogicError::IntegerOverflow { operator } => {
+                write!(f, "Integer overflow in '{}'", operator)
+            }
+            LogicError::InRule { rule_id, error } => {
+                write!(f, "{} in rule '{}'", error, rule_id)
+            }
+        }
+    }
+}
+
+impl Error for LogicError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LogicError::InRule { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// An error located in the rule that raised it.
+///
+/// Returned by [`DataLogic::evaluate_with_diagnostics`](crate::DataLogic::evaluate_with_diagnostics).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetailedError {
+    /// The underlying error.
+    pub error: LogicError,
+    /// The path of the failing sub-expression, such as `/if/1/+/0`.
+    ///
+    /// Each step names an operator and the index of one of its arguments.
+    /// The path is empty when the root expression failed.
+    pub path: String,
+    /// The name of the failing operator, if the sub-expression is an operator.
+    pub operator: Option<String>,
+    /// The values of the arguments of the failing sub-expression.
+    ///
+    /// Arguments that fail to evaluate themselves are reported as null.
+    pub arguments: Vec<serde_json::Value>,
+}
+
+impl fmt::Display for DetailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{} at {}", self.error, path)?;
+        if let Some(operator) = &self.operator {
+            write!(f, " in operator '{}'", operator)?;
+        }
+        if !self.arguments.is_empty() {
+            let arguments: Vec<String> = self.arguments.iter().map(ToString::to_string).collect();
+            write!(f, " with arguments [{}]", arguments.join(", "))?;
         }
+        Ok(())
     }
 }
 
-impl Error for LogicError {}
+impl Error for DetailedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<DetailedError> for LogicError {
+    fn from(error: DetailedError) -> Self {
+        error.error
+    }
+}
 
 /// Extension methods for Result<T, LogicError>.
 pub trait LogicResultExt<T> {
@@ -123,6 +273,22 @@ impl LogicError {
     pub fn custom(message: impl Into<String>) -> Self {
         LogicError::Custom(message.into())
     }
+
+    /// Returns the id of the rule that raised the error, if it has one.
+    pub fn rule_id(&self) -> Option<&str> {
+        match self {
+            LogicError::InRule { rule_id, .. } => Some(rule_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without the id of the rule that raised it.
+    pub fn root_cause(&self) -> &LogicError {
+        match self {
+            LogicError::InRule { error, .. } => error.root_cause(),
+            error => error,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -146,6 +312,34 @@ mod tests {
         assert_eq!(err.to_string(), "Variable 'user.age' error: not found");
     }
 
+    #[test]
+    fn test_rule_error() {
+        let err = LogicError::InRule {
+            rule_id: "pricing/discount".to_string(),
+            error: Box::new(LogicError::NaNError),
+        };
+        assert_eq!(err.to_string(), "NaN error in rule 'pricing/discount'");
+        assert_eq!(err.rule_id(), Some("pricing/discount"));
+        assert_eq!(err.root_cause(), &LogicError::NaNError);
+        assert!(err.source().is_some());
+        assert_eq!(LogicError::NaNError.rule_id(), None);
+    }
+
+    #[test]
+    fn test_detailed_error() {
+        let err = DetailedError {
+            error: LogicError::InvalidArgumentsError,
+            path: "/if/1".to_string(),
+            operator: Some("+".to_string()),
+            arguments: vec![serde_json::json!(1), serde_json::json!("abc")],
+        };
+        assert_eq!(
+            err.to_string(),
+            "Invalid arguments error at /if/1 in operator '+' with arguments [1, \"abc\"]"
+        );
+        assert_eq!(LogicError::from(err), LogicError::InvalidArgumentsError);
+    }
+
     #[test]
     fn test_with_context() {
         let result: Result<()> = Err(LogicError::ParseError {
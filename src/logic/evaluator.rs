@@ -2,14 +2,18 @@
 //!
 //! This module provides functions for evaluating logic expressions.
 
+use super::diagnostics::record_outcome;
 use super::error::{LogicError, Result};
+use super::operators::string::RegexPattern;
 use super::operators::{
-    arithmetic, array, comparison, control, datetime, missing, r#try, string, throw, type_op, val,
-    variable,
+    annotate, arithmetic, array, block, comparison, control, datetime, log, missing, preserve,
+    r#try, range, schedule, spec, string, throw, type_op, val, validate, variable,
 };
 use super::token::{OperatorType, Token};
+use super::trie::StringTrie;
 use crate::arena::DataArena;
 use crate::value::DataValue;
+use std::time::Instant;
 
 /// Helper function to convert a token to a TokenRefs wrapper
 /// This avoids cloning tokens for lazy evaluation
@@ -35,6 +39,19 @@ pub fn evaluate<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a Da
         // Fast path for variables - second most common case
         Token::Variable { path, default } => variable::evaluate_variable(path, default, arena),
 
+        // Record the cost and failures of every other node when instrumented
+        _ if arena.is_instrumented() => evaluate_instrumented(token, arena),
+
+        _ => evaluate_node(token, arena),
+    }
+}
+
+/// Evaluates a token that is not a literal or a plain variable.
+#[inline(always)]
+fn evaluate_node<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    match token {
+        Token::Literal(_) | Token::Variable { .. } => evaluate(token, arena),
+
         // Dynamic variables evaluate the path expression first
         Token::DynamicVariable { path_expr, default } => {
             evaluate_dynamic_variable(path_expr, default, arena)
@@ -44,13 +61,188 @@ pub fn evaluate<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a Da
         Token::ArrayLiteral(items) => evaluate_array_literal(items, arena),
 
         // Operators apply a function to their arguments
-        Token::Operator { op_type, args } => evaluate_operator(*op_type, args, arena),
+        Token::Operator { op_type, args } => {
+            if arena.has_operator_overrides() {
+                if let Some(result) = evaluate_override(*op_type, args, arena) {
+                    return result;
+                }
+            }
+            evaluate_operator(*op_type, args, arena)
+        }
 
         // Custom operators are looked up in a registry
         Token::CustomOperator { name, args } => {
             let data_values = evaluate_arguments(args, arena)?;
             evaluate_custom_operator(name, data_values, arena)
         }
+
+        // Compiled string lists match string needles directly
+        Token::CompiledMatch {
+            op_type,
+            args,
+            trie,
+        } => {
+            if arena.has_operator_overrides() {
+                if let Some(result) = evaluate_override(*op_type, args, arena) {
+                    return result;
+                }
+            }
+            evaluate_compiled_match(*op_type, args, trie, arena)
+        }
+
+        // Compiled regexes skip compiling their pattern
+        Token::CompiledRegex {
+            op_type,
+            args,
+            regex,
+        } => {
+            if arena.has_operator_overrides() {
+                if let Some(result) = evaluate_override(*op_type, args, arena) {
+                    return result;
+                }
+            }
+            evaluate_compiled_regex(*op_type, args, regex, arena)
+        }
+
+        // Case-folded operations skip folding their constant arguments
+        Token::CaseFolded { op_type, args } => {
+            if arena.has_operator_overrides() {
+                if let Some(result) = evaluate_override(*op_type, args, arena) {
+                    return result;
+                }
+            }
+            evaluate_case_folded(*op_type, args, arena)
+        }
+    }
+}
+
+/// Evaluates a token while recording its cost, the site of failures, the
+/// coercions it performs and its position for the logger, and counting it
+/// against the execution limits.
+#[cold]
+fn evaluate_instrumented<'a>(
+    token: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if arena.is_execution_limited() {
+        arena.enter_operation()?;
+    }
+    let previous_node = arena
+        .is_tracing_coercions()
+        .then(|| arena.enter_node(token));
+    let logging = arena.has_logger();
+    if logging {
+        arena.push_node(token);
+    }
+
+    let result = if arena.is_profiling() {
+        let used_before = arena.used_bytes();
+        let start = Instant::now();
+        let result = evaluate_node(token, arena);
+        let elapsed = start.elapsed();
+        let allocated = arena.used_bytes().saturating_sub(used_before);
+        arena.record_cost(token, elapsed, allocated);
+        result
+    } else {
+        evaluate_node(token, arena)
+    };
+
+    if let Some(previous_node) = previous_node {
+        arena.leave_node(previous_node);
+    }
+    if logging {
+        arena.pop_node();
+    }
+    if arena.is_tracing_errors() {
+        record_outcome(token, &result, arena);
+    }
+    if arena.is_execution_limited() {
+        arena.leave_operation();
+    }
+    result
+}
+
+/// Evaluates an operation as json-logic-js does, where it differs from the
+/// regular operator.
+///
+/// Extra arguments are dropped before evaluating the operation.
+#[cold]
+fn evaluate_spec_operator<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Option<Result<&'a DataValue<'a>>> {
+    let token_refs = convert_to_token_refs(args, arena);
+    if let Some(max) = spec::max_arguments(op_type).filter(|&max| token_refs.len() > max) {
+        let args = arena.alloc(Token::ArrayLiteral(token_refs[..max].to_vec()));
+        return Some(evaluate_operator(op_type, args, arena));
+    }
+    spec::evaluate_operation(op_type, token_refs, arena)
+}
+
+/// Evaluates a built-in operator through its active override, if any.
+#[cold]
+fn evaluate_override<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Option<Result<&'a DataValue<'a>>> {
+    let operator = arena.operator_override(op_type.as_str())?;
+    Some(evaluate_arguments(args, arena).and_then(|args| operator.evaluate(args, arena)))
+}
+
+/// Evaluates an `in` or `starts_with` test against a compiled string list.
+///
+/// Needles that are not strings are handled by the regular operator.
+#[inline]
+fn evaluate_compiled_match<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    trie: &'a StringTrie<'a>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let Token::ArrayLiteral(items) = args else {
+        return evaluate_operator(op_type, args, arena);
+    };
+
+    let needle = evaluate(items[0], arena)?;
+    let DataValue::String(needle_str) = needle else {
+        // Other needles take the regular operator, without evaluating them again
+        let mut literal_args = items.clone();
+        literal_args[0] = arena.alloc(Token::literal(needle.clone()));
+        let literal_args = arena.alloc(Token::ArrayLiteral(literal_args));
+        return evaluate_operator(op_type, literal_args, arena);
+    };
+
+    Ok(arena.alloc(DataValue::Bool(trie.contains(needle_str))))
+}
+
+/// Evaluates a `regex` or `regex_replace` operation with a compiled pattern.
+#[inline]
+fn evaluate_compiled_regex<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    regex: &RegexPattern,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let (OperatorType::String(string_op), Token::ArrayLiteral(items)) = (op_type, args) else {
+        return evaluate_operator(op_type, args, arena);
+    };
+    string::eval_compiled_regex(string_op, regex, &items[1..], arena)
+}
+
+/// Evaluates an operation whose constant arguments have been case-folded.
+#[inline]
+fn evaluate_case_folded<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    match op_type {
+        OperatorType::Array(array::ArrayOp::In) => {
+            array::eval_case_folded_in(convert_to_token_refs(args, arena), arena)
+        }
+        _ => evaluate_operator(op_type, args, arena),
     }
 }
 
@@ -110,6 +302,7 @@ fn evaluate_array_literal<'a>(
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
     // Get a vector from the arena's pool
+    arena.limits().check_array_len(items.len())?;
     let mut values = arena.get_data_value_vec_with_capacity(items.len());
 
     // Evaluate each item in the array
@@ -137,7 +330,7 @@ fn evaluate_custom_operator<'a>(
 /// Evaluates arguments and returns them as a slice of DataValues
 /// This function is optimized to avoid unnecessary allocations
 #[inline]
-fn evaluate_arguments<'a>(
+pub(crate) fn evaluate_arguments<'a>(
     args: &'a Token<'a>,
     arena: &'a DataArena,
 ) -> Result<&'a [DataValue<'a>]> {
@@ -187,6 +380,12 @@ fn evaluate_operator<'a>(
     args: &'a Token<'a>,
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
+    if arena.semantics().is_spec() {
+        if let Some(result) = evaluate_spec_operator(op_type, args, arena) {
+            return result;
+        }
+    }
+
     // Get token references for lazy evaluation
     let token_refs = convert_to_token_refs(args, arena);
 
@@ -198,7 +397,15 @@ fn evaluate_operator<'a>(
         OperatorType::Arithmetic(arith_op) => {
             // Evaluate arguments once and pass to the appropriate function
             let args_result = evaluate_arguments(args, arena)?;
-            evaluate_arithmetic_operator(arith_op, args_result, arena)
+            let mut result = evaluate_arithmetic_operator(arith_op, args_result, arena);
+            if arena.semantics().is_js() && is_nan_result(arith_op, &result) {
+                // JavaScript produces NaN or an infinity, serialized as null
+                result = Ok(arena.null_value());
+            }
+            if arena.is_tracing_coercions() {
+                record_arithmetic_coercions(arith_op, args_result, &result, arena);
+            }
+            result
         }
         OperatorType::Control(control_op) => {
             evaluate_control_operator(control_op, args, token_refs, arena)
@@ -209,6 +416,10 @@ fn evaluate_operator<'a>(
             let args_result = evaluate_arguments(args, arena)?;
             evaluate_datetime_operator(datetime_op, args_result, arena)
         }
+        OperatorType::Range(range_op) => {
+            let args_result = evaluate_arguments(args, arena)?;
+            evaluate_range_operator(range_op, args_result, arena)
+        }
         OperatorType::Missing => missing::eval_missing(token_refs, arena),
         OperatorType::MissingSome => missing::eval_missing_some(token_refs, arena),
         OperatorType::Exists => {
@@ -218,9 +429,21 @@ fn evaluate_operator<'a>(
         OperatorType::Coalesce => eval_coalesce(token_refs, arena),
         OperatorType::Throw => throw::eval_throw(token_refs, arena),
         OperatorType::Try => r#try::eval_try(token_refs, arena),
+        OperatorType::Log => log::eval_log(token_refs, arena),
+        OperatorType::Do => block::eval_do(token_refs, arena),
+        OperatorType::Assign => block::eval_assign(token_refs, arena),
         OperatorType::Val => val::eval_val(token_refs, arena),
         OperatorType::Type => type_op::eval_type(token_refs, arena),
+        OperatorType::Empty => type_op::eval_empty(token_refs, arena),
+        OperatorType::Default => type_op::eval_default(token_refs, arena),
+        OperatorType::Unpreserve => preserve::eval_unpreserve(token_refs, arena),
+        OperatorType::Annotate => annotate::eval_annotate(token_refs, arena),
+        OperatorType::TypeCheck(type_check_op) => {
+            type_op::eval_type_check(type_check_op, token_refs, arena)
+        }
+        OperatorType::Validate => validate::eval_validate(token_refs, arena),
         OperatorType::ArrayLiteral => evaluate_array_literal_operator(token_refs, arena),
+        OperatorType::ObjectLiteral => evaluate_object_literal_operator(token_refs, arena),
     }
 }
 
@@ -246,6 +469,7 @@ fn evaluate_comparison_operator<'a>(
         comparison::ComparisonOp::LessThanOrEqual => {
             comparison::eval_less_than_or_equal(token_refs, arena)
         }
+        comparison::ComparisonOp::DeepEqual => comparison::eval_deep_equal(token_refs, arena),
     }
 }
 
@@ -268,6 +492,18 @@ fn evaluate_array_operator<'a>(
         array::ArrayOp::Length => array::eval_length(token_refs, arena),
         array::ArrayOp::Slice => array::eval_slice(token_refs, arena),
         array::ArrayOp::Sort => array::eval_sort(token_refs, arena),
+        array::ArrayOp::Related => array::eval_related(token_refs, arena),
+        array::ArrayOp::Unique => array::eval_unique(token_refs, arena),
+        array::ArrayOp::GroupBy => array::eval_group_by(token_refs, arena),
+        array::ArrayOp::Sum => array::eval_sum(token_refs, arena),
+        array::ArrayOp::Avg => array::eval_avg(token_refs, arena),
+        array::ArrayOp::Count => array::eval_count(token_refs, arena),
+        array::ArrayOp::MinBy => array::eval_extreme_by(token_refs, false, arena),
+        array::ArrayOp::MaxBy => array::eval_extreme_by(token_refs, true, arena),
+        array::ArrayOp::Keys => array::eval_keys(token_refs, arena),
+        array::ArrayOp::Values => array::eval_values(token_refs, arena),
+        array::ArrayOp::Entries => array::eval_entries(token_refs, arena),
+        array::ArrayOp::FromEntries => array::eval_from_entries(token_refs, arena),
     }
 }
 
@@ -289,6 +525,65 @@ fn evaluate_arithmetic_operator<'a>(
         arithmetic::ArithmeticOp::Abs => arithmetic::eval_abs(args_result, arena),
         arithmetic::ArithmeticOp::Ceil => arithmetic::eval_ceil(args_result, arena),
         arithmetic::ArithmeticOp::Floor => arithmetic::eval_floor(args_result, arena),
+        arithmetic::ArithmeticOp::PercentOf => arithmetic::eval_percent_of(args_result, arena),
+        arithmetic::ArithmeticOp::SafeDiv => arithmetic::eval_safe_div(args_result, arena),
+        arithmetic::ArithmeticOp::Bucket => arithmetic::eval_bucket(args_result, arena),
+        arithmetic::ArithmeticOp::Pow => arithmetic::eval_pow(args_result, arena),
+        arithmetic::ArithmeticOp::Sqrt => arithmetic::eval_sqrt(args_result, arena),
+        arithmetic::ArithmeticOp::Ln => arithmetic::eval_ln(args_result, arena),
+        arithmetic::ArithmeticOp::Log10 => arithmetic::eval_log10(args_result, arena),
+        arithmetic::ArithmeticOp::Round => arithmetic::eval_round(args_result, arena),
+        arithmetic::ArithmeticOp::Trunc => arithmetic::eval_trunc(args_result, arena),
+    }
+}
+
+/// Check if an arithmetic operator failed where JavaScript produces a
+/// non-finite number.
+fn is_nan_result(arith_op: arithmetic::ArithmeticOp, result: &Result<&DataValue>) -> bool {
+    use arithmetic::ArithmeticOp;
+
+    matches!(result, Err(LogicError::NaNError))
+        && matches!(
+            arith_op,
+            ArithmeticOp::Add
+                | ArithmeticOp::Subtract
+                | ArithmeticOp::Multiply
+                | ArithmeticOp::Divide
+                | ArithmeticOp::Modulo
+        )
+}
+
+/// Records the operands an arithmetic operator converted to numbers.
+///
+/// Only numeric results are considered, so datetime and duration arithmetic
+/// on strings is not reported.
+#[cold]
+fn record_arithmetic_coercions<'a>(
+    arith_op: arithmetic::ArithmeticOp,
+    args: &'a [DataValue<'a>],
+    result: &Result<&'a DataValue<'a>>,
+    arena: &'a DataArena,
+) {
+    use arithmetic::ArithmeticOp;
+
+    let numeric_operands = matches!(
+        arith_op,
+        ArithmeticOp::Add
+            | ArithmeticOp::Subtract
+            | ArithmeticOp::Multiply
+            | ArithmeticOp::Divide
+            | ArithmeticOp::Modulo
+    );
+    if !numeric_operands || !matches!(result, Ok(DataValue::Number(_))) {
+        return;
+    }
+    for arg in args {
+        if matches!(
+            arg,
+            DataValue::Null | DataValue::Bool(_) | DataValue::String(_)
+        ) {
+            arena.record_coercion(arg, "number");
+        }
     }
 }
 
@@ -335,6 +630,9 @@ fn evaluate_string_operator<'a>(
         string::StringOp::Trim => string::eval_trim(token_refs, arena),
         string::StringOp::Replace => string::eval_replace(token_refs, arena),
         string::StringOp::Split => string::eval_split(token_refs, arena),
+        string::StringOp::Regex | string::StringOp::RegexReplace => {
+            string::eval_regex(string_op, token_refs, arena)
+        }
     }
 }
 
@@ -347,11 +645,36 @@ fn evaluate_datetime_operator<'a>(
 ) -> Result<&'a DataValue<'a>> {
     match datetime_op {
         datetime::DateTimeOp::DateTime => datetime::eval_datetime_operator(args_result, arena),
-        datetime::DateTimeOp::Timestamp => datetime::eval_timestamp_operator(args_result, arena),
+        datetime::DateTimeOp::Timestamp | datetime::DateTimeOp::Duration => {
+            datetime::eval_timestamp_operator(args_result, arena)
+        }
         datetime::DateTimeOp::Now => datetime::eval_now(arena),
         datetime::DateTimeOp::ParseDate => datetime::eval_parse_date(args_result, arena),
         datetime::DateTimeOp::FormatDate => datetime::eval_format_date(args_result, arena),
         datetime::DateTimeOp::DateDiff => datetime::eval_date_diff(args_result, arena),
+        datetime::DateTimeOp::DateAdd => datetime::eval_date_add(args_result, arena),
+        datetime::DateTimeOp::InSchedule => schedule::eval_in_schedule(args_result, arena),
+        datetime::DateTimeOp::AgeYears => datetime::eval_age_years(args_result, arena),
+        datetime::DateTimeOp::IsAnniversary => datetime::eval_is_anniversary(args_result, arena),
+        datetime::DateTimeOp::HumanizeDuration => {
+            datetime::eval_humanize_duration(args_result, arena)
+        }
+        datetime::DateTimeOp::IsoDuration => datetime::eval_iso_duration(args_result, arena),
+    }
+}
+
+/// Evaluates a range operator
+#[inline]
+fn evaluate_range_operator<'a>(
+    range_op: range::RangeOp,
+    args_result: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    match range_op {
+        range::RangeOp::Range => range::eval_range(args_result, arena),
+        range::RangeOp::InRange => range::eval_in_range(args_result, arena),
+        range::RangeOp::RangesOverlap => range::eval_ranges_overlap(args_result, arena),
+        range::RangeOp::RangeIntersect => range::eval_range_intersect(args_result, arena),
     }
 }
 
@@ -362,6 +685,7 @@ fn evaluate_array_literal_operator<'a>(
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
     // Just evaluate all elements as an array
+    arena.limits().check_array_len(token_refs.len())?;
     let mut values = arena.get_data_value_vec();
 
     for token in token_refs {
@@ -374,6 +698,29 @@ fn evaluate_array_literal_operator<'a>(
     Ok(arena.alloc(result))
 }
 
+/// Evaluates an object operator, whose first argument is the literal array of
+/// keys and whose other arguments are the values.
+fn evaluate_object_literal_operator<'a>(
+    token_refs: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let Some((Token::Literal(DataValue::Array(keys)), values)) = token_refs.split_first() else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    if keys.len() != values.len() {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let mut entries = Vec::with_capacity(values.len());
+    for (key, token) in keys.iter().zip(values) {
+        let key = key.as_str().ok_or(LogicError::InvalidArgumentsError)?;
+        let value = evaluate(token, arena)?;
+        entries.push((key, value.clone()));
+    }
+
+    Ok(arena.alloc(DataValue::Object(arena.vec_into_slice(entries))))
+}
+
 /// Evaluates a coalesce operation, which returns the first non-null value.
 fn eval_coalesce<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
     // If no arguments, return null
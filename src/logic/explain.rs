@@ -0,0 +1,297 @@
+//! Explainable evaluation output.
+//!
+//! This module provides helpers that evaluate a rule while recording how the
+//! individual parts of the expression contributed to the final result, or
+//! how much each part cost to evaluate.
+
+use super::error::{LogicError, Result};
+use super::evaluator::evaluate;
+use super::operators::annotate::annotation_note;
+use super::operators::arithmetic::{self, ArithmeticOp};
+use super::token::{OperatorType, Token};
+use crate::arena::{CostProfile, DataArena};
+use crate::value::DataValue;
+
+/// Collects the addends of a score expression.
+///
+/// A rule of the form `{"+": [...]}` contributes one entry per argument. Any
+/// other rule is treated as a single contribution.
+fn collect_addends<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Vec<&'a Token<'a>> {
+    match token {
+        Token::Operator {
+            op_type: OperatorType::Arithmetic(ArithmeticOp::Add),
+            args,
+        } => match args {
+            Token::ArrayLiteral(items) => items.clone(),
+            // A literal array of addends, e.g. {"+": [1, 2, 3]}
+            Token::Literal(DataValue::Array(values)) => values
+                .iter()
+                .map(|value| arena.alloc(Token::literal(value.clone())))
+                .collect(),
+            _ => vec![*args],
+        },
+        _ => vec![token],
+    }
+}
+
+/// Evaluates a score expression and returns a breakdown of its result.
+///
+/// The result is an object of the form:
+///
+/// ```json
+/// {"total": 35, "contributions": [{"index": 0, "value": 20}, {"index": 1, "value": 15}]}
+/// ```
+///
+/// Each contribution corresponds to one argument of the top-level `+`
+/// operator, in the order the arguments appear in the rule. Contributions
+/// must evaluate to numeric values, otherwise a NaN error is returned.
+pub fn evaluate_score_breakdown<'a>(
+    token: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let addends = collect_addends(token, arena);
+
+    let mut values = arena.get_data_value_vec_with_capacity(addends.len());
+    let mut contributions = arena.get_data_value_vec_with_capacity(addends.len());
+
+    for (index, addend) in addends.iter().enumerate() {
+        let value = evaluate(addend, arena)?;
+        let number = value.coerce_to_number().ok_or(LogicError::NaNError)?;
+        let number_value = DataValue::Number(number);
+
+        let entry = [
+            (arena.intern_str("index"), DataValue::integer(index as i64)),
+            (arena.intern_str("value"), number_value.clone()),
+        ];
+        contributions.push(DataValue::object(arena, &entry));
+        values.push(number_value);
+    }
+
+    let values = arena.bump_vec_into_slice(values);
+    let total = arithmetic::eval_add(values, arena)?;
+
+    let result = [
+        (arena.intern_str("total"), total.clone()),
+        (
+            arena.intern_str("contributions"),
+            DataValue::Array(arena.bump_vec_into_slice(contributions)),
+        ),
+    ];
+
+    Ok(arena.alloc(DataValue::object(arena, &result)))
+}
+
+/// Evaluates a rule and annotates every subexpression with its cost.
+///
+/// The result is an object of the form:
+///
+/// ```json
+/// {"result": true, "cost": {"expression": "and", "calls": 1, "time_ns": 5120,
+///   "allocated_bytes": 64, "args": [...]}}
+/// ```
+///
+/// The `cost` tree mirrors the rule. Operator nodes report how often they
+/// were evaluated, their total wall time and the arena bytes they allocated,
+/// both including their arguments. Branches skipped by short-circuiting
+/// report zero calls. Literals and plain variables are listed without costs.
+/// Annotations also report their `note`.
+pub fn evaluate_with_costs<'a>(
+    token: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    arena.start_profiling();
+    let result = evaluate(token, arena);
+    let profile = arena.finish_profiling();
+
+    let entries = [
+        (arena.intern_str("result"), result?.clone()),
+        (arena.intern_str("cost"), cost_tree(token, &profile, arena)),
+    ];
+    Ok(arena.alloc(DataValue::object(arena, &entries)))
+}
+
+/// Returns the argument tokens of an operator.
+pub(super) fn operator_args<'a>(args: &'a Token<'a>, arena: &'a DataArena) -> Vec<&'a Token<'a>> {
+    match args {
+        Token::ArrayLiteral(items) => items.clone(),
+        Token::Literal(DataValue::Array(values)) => values
+            .iter()
+            .map(|value| arena.alloc(Token::literal(value.clone())) as &Token)
+            .collect(),
+        _ => vec![args],
+    }
+}
+
+/// Builds the cost annotation of a token and its arguments.
+fn cost_tree<'a>(
+    token: &'a Token<'a>,
+    profile: &CostProfile,
+    arena: &'a DataArena,
+) -> DataValue<'a> {
+    let (expression, args) = match token {
+        Token::Literal(value) => {
+            let entries = [
+                (arena.intern_str("expression"), DataValue::String("literal")),
+                (arena.intern_str("value"), value.clone()),
+            ];
+            return DataValue::object(arena, &entries);
+        }
+        Token::Variable { path, default } => {
+            let mut entries = vec![
+                (arena.intern_str("expression"), DataValue::String("var")),
+                (arena.intern_str("path"), DataValue::String(path)),
+            ];
+            if let Some(default) = default {
+                let args = [cost_tree(default, profile, arena)];
+                entries.push((arena.intern_str("args"), DataValue::array(arena, &args)));
+            }
+            return DataValue::object(arena, &entries);
+        }
+        Token::DynamicVariable { path_expr, default } => {
+            let mut args = vec![*path_expr];
+            args.extend(default.iter().copied());
+            ("var", args)
+        }
+        Token::ArrayLiteral(items) => ("array", items.clone()),
+        Token::Operator { op_type, args }
+        | Token::CompiledMatch { op_type, args, .. }
+        | Token::CompiledRegex { op_type, args, .. }
+        | Token::CaseFolded { op_type, args } => (op_type.as_str(), operator_args(args, arena)),
+        Token::CustomOperator { name, args } => (*name, operator_args(args, arena)),
+    };
+
+    let cost = profile.cost(token).unwrap_or_default();
+    let args: Vec<DataValue> = args
+        .into_iter()
+        .map(|arg| cost_tree(arg, profile, arena))
+        .collect();
+    let mut entries = vec![
+        (
+            arena.intern_str("expression"),
+            DataValue::String(arena.alloc_str(expression)),
+        ),
+        (
+            arena.intern_str("calls"),
+            DataValue::integer(cost.calls as i64),
+        ),
+        (
+            arena.intern_str("time_ns"),
+            DataValue::integer(cost.elapsed.as_nanos().min(i64::MAX as u128) as i64),
+        ),
+        (
+            arena.intern_str("allocated_bytes"),
+            DataValue::integer(cost.allocated_bytes as i64),
+        ),
+        (arena.intern_str("args"), DataValue::array(arena, &args)),
+    ];
+    if let Some(note) = annotation_note(token) {
+        entries.push((arena.intern_str("note"), note.clone()));
+    }
+    DataValue::object(arena, &entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::jsonlogic::parse_json;
+    use crate::value::{FromJson, ToJson};
+    use serde_json::json;
+
+    #[test]
+    fn test_score_breakdown() {
+        let arena = DataArena::new();
+
+        let rule = json!({"+": [
+            {"if": [{">": [{"var": "age"}, 30]}, 20, 0]},
+            {"if": [{"var": "member"}, 15, 0]},
+            {"if": [{"<": [{"var": "debt"}, 100]}, 5, -10]}
+        ]});
+        let token = parse_json(&rule, &arena).unwrap();
+
+        let data_json = json!({"age": 42, "member": false, "debt": 500});
+        let data = DataValue::from_json(&data_json, &arena);
+        arena.set_root_context(&data);
+        arena.set_current_context(&data, &DataValue::String("$"));
+
+        let result = evaluate_score_breakdown(token, &arena).unwrap();
+        assert_eq!(
+            result.to_json(),
+            json!({
+                "total": 10,
+                "contributions": [
+                    {"index": 0, "value": 20},
+                    {"index": 1, "value": 0},
+                    {"index": 2, "value": -10}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_score_breakdown_non_sum() {
+        let arena = DataArena::new();
+
+        let token = parse_json(&json!({"*": [2, 3]}), &arena).unwrap();
+        let result = evaluate_score_breakdown(token, &arena).unwrap();
+        assert_eq!(
+            result.to_json(),
+            json!({"total": 6, "contributions": [{"index": 0, "value": 6}]})
+        );
+
+        let token = parse_json(&json!({"+": [1, "abc"]}), &arena).unwrap();
+        let result = evaluate_score_breakdown(token, &arena);
+        assert_eq!(result, Err(LogicError::NaNError));
+    }
+
+    #[test]
+    fn test_evaluate_with_costs() {
+        let arena = DataArena::new();
+
+        let rule = json!({"or": [
+            {"var": "fast"},
+            {"map": [{"var": "items"}, {"*": [{"var": ""}, 2]}]}
+        ]});
+        let token = parse_json(&rule, &arena).unwrap();
+
+        let data_json = json!({"fast": false, "items": [1, 2, 3]});
+        let data = DataValue::from_json(&data_json, &arena);
+        arena.set_root_context(&data);
+        arena.set_current_context(&data, &DataValue::String("$"));
+
+        let report = evaluate_with_costs(token, &arena).unwrap().to_json();
+        assert_eq!(report["result"], json!([2, 4, 6]));
+
+        let cost = &report["cost"];
+        assert_eq!(cost["expression"], json!("or"));
+        assert_eq!(cost["calls"], json!(1));
+        assert_eq!(
+            cost["args"][0],
+            json!({"expression": "var", "path": "fast"})
+        );
+
+        let map = &cost["args"][1];
+        assert_eq!(map["expression"], json!("map"));
+        assert!(map["allocated_bytes"].as_i64().unwrap() > 0);
+        let multiply = &map["args"][1];
+        assert_eq!(multiply["calls"], json!(3));
+        assert_eq!(
+            multiply["args"][1],
+            json!({"expression": "literal", "value": 2})
+        );
+        assert!(cost["time_ns"].as_i64() >= multiply["time_ns"].as_i64());
+        assert!(!arena.is_profiling());
+
+        // Short-circuited branches are never evaluated
+        let token = parse_json(&json!({"and": [false, {"+": [1, {"var": "x"}]}]}), &arena).unwrap();
+        let report = evaluate_with_costs(token, &arena).unwrap().to_json();
+        assert_eq!(report["cost"]["args"][1]["calls"], json!(0));
+
+        // Annotations report their notes next to the costs of their rules
+        let rule = json!({"//": ["Double it", {"*": [{"var": "n"}, 2]}]});
+        let token = parse_json(&rule, &arena).unwrap();
+        let report = evaluate_with_costs(token, &arena).unwrap().to_json();
+        assert_eq!(report["cost"]["expression"], json!("annotate"));
+        assert_eq!(report["cost"]["note"], json!("Double it"));
+        assert_eq!(report["cost"]["args"][1]["expression"], json!("*"));
+    }
+}
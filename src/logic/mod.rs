@@ -5,17 +5,28 @@
 
 mod ast;
 mod datalogic_core;
+mod diagnostics;
 pub mod error;
 mod evaluator;
+mod explain;
 mod operators;
 mod optimizer;
+mod report;
 pub mod token;
+mod trie;
 
 pub use ast::Logic;
 pub use datalogic_core::DataLogicCore;
-pub use error::{LogicError, Result};
+pub use diagnostics::{evaluate_with_coercion_trace, evaluate_with_diagnostics, Coercion};
+pub use error::{DetailedError, LogicError, Result};
 pub use evaluator::evaluate;
+pub(crate) use evaluator::evaluate_arguments;
+pub use explain::{evaluate_score_breakdown, evaluate_with_costs};
+pub(crate) use operators::array::UNIQUE_OPTION_KEYS;
+pub(crate) use operators::type_op::{split_default_options, DEFAULT_OPTION_KEYS};
+pub use report::{compilation_report, CompilationReport};
 pub use token::{OperatorType, Token};
+pub use trie::StringTrie;
 
 // Re-export operator types
 pub use operators::arithmetic::ArithmeticOp;
@@ -23,7 +34,9 @@ pub use operators::array::ArrayOp;
 pub use operators::comparison::ComparisonOp;
 pub use operators::control::ControlOp;
 pub use operators::datetime::DateTimeOp;
+pub use operators::range::RangeOp;
 pub use operators::string::StringOp;
+pub use operators::type_op::TypeCheckOp;
 
 /// Make optimizer function public
 pub fn optimize<'a>(
@@ -106,4 +119,201 @@ mod tests {
         // Verify result
         assert_eq!(result.as_bool(), Some(true));
     }
+
+    #[test]
+    fn test_optimized_fold_case_in() {
+        let arena = DataArena::new();
+
+        let rule_json = json!({"in": [{"var": "code"}, ["AB", "Cd"], {"fold_case": true}]});
+        let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+        let optimized_token = optimizer::optimize(token, &arena).unwrap();
+
+        // The constant haystack should be folded at compile time
+        let Token::CaseFolded { args, .. } = optimized_token else {
+            panic!("expected a case-folded token, got {optimized_token:?}");
+        };
+        let items = args.as_array_literal().unwrap();
+        let haystack = items[1].as_literal().unwrap().as_array().unwrap();
+        assert_eq!(haystack[0].as_str(), Some("ab"));
+        assert_eq!(haystack[1].as_str(), Some("cd"));
+
+        let data_json = json!({"code": "cD"});
+        let data = DataValue::from_json(&data_json, &arena);
+        arena.set_current_context(&data, &DataValue::String("$"));
+        let result = evaluate(optimized_token, &arena).unwrap();
+        assert_eq!(result.as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_optimized_string_list_trie() {
+        let arena = DataArena::new();
+
+        let skus: Vec<String> = (0..100).map(|i| format!("SKU-{i:04}")).collect();
+        let rule_json = json!({"in": [{"var": "sku"}, skus]});
+        let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+        let optimized_token = optimizer::optimize(token, &arena).unwrap();
+        assert!(matches!(optimized_token, Token::CompiledMatch { .. }));
+
+        let cases = [
+            (json!({"sku": "SKU-0042"}), true),
+            (json!({"sku": "SKU-0100"}), false),
+            (json!({"sku": 42}), false),
+        ];
+        for (data_json, expected) in cases {
+            let data = DataValue::from_json(&data_json, &arena);
+            arena.set_current_context(&data, &DataValue::String("$"));
+            let result = evaluate(optimized_token, &arena).unwrap();
+            assert_eq!(result.as_bool(), Some(expected));
+        }
+
+        // Lists of prefixes are not compiled, as starts_with takes one prefix
+        let prefixes: Vec<String> = (0..40).map(|i| format!("host{i}")).collect();
+        let prefix_json = json!({"starts_with": [{"var": "url"}, prefixes]});
+        let prefix_token = jsonlogic::parse_json(&prefix_json, &arena).unwrap();
+        let optimized_prefix = optimizer::optimize(prefix_token, &arena).unwrap();
+        assert!(matches!(optimized_prefix, Token::Operator { .. }));
+    }
+
+    #[test]
+    fn test_string_list_trie_evaluates_needle_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut dl = crate::DataLogic::new();
+        dl.register_simple_operator("next_sku", |_, _| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(DataValue::integer(7))
+        });
+
+        // A needle that is not a string takes the regular in operator
+        let skus: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let rule = json!({"in": [{"next_sku": []}, skus]}).to_string();
+        let result = dl.evaluate_str(&rule, "{}", None).unwrap();
+        assert_eq!(result, json!(true));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_overflowing_constants_are_not_folded() {
+        use crate::semantics::IntegerOverflow;
+
+        let arena = DataArena::new();
+        let rule_json = json!({"+": [9223372036854775807i64, 1]});
+        let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+        let optimized_token = optimizer::optimize(token, &arena).unwrap();
+        assert!(matches!(optimized_token, Token::Operator { .. }));
+        assert_eq!(arena.integer_overflow(), IntegerOverflow::Float);
+
+        // The policy set after parsing applies
+        arena.set_integer_overflow(IntegerOverflow::Saturate);
+        let result = evaluate(optimized_token, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(i64::MAX));
+
+        let rule_json = json!({"+": [9223372036854775806i64, 1]});
+        let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+        let optimized_token = optimizer::optimize(token, &arena).unwrap();
+        assert!(matches!(optimized_token, Token::Literal(_)));
+    }
+
+    #[test]
+    fn test_optimized_dead_branches() {
+        let arena = DataArena::new();
+        let data = DataValue::from_json(&json!({"x": "left", "y": "right"}), &arena);
+
+        let optimize_rule = |rule_json: serde_json::Value| {
+            let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+            optimizer::optimize(token, &arena).unwrap()
+        };
+
+        // A truthy literal condition always takes its branch
+        let token = optimize_rule(json!({"if": [true, {"var": "x"}, {"var": "y"}]}));
+        assert!(token.is_variable());
+
+        // Falsy literal conditions are skipped
+        let token = optimize_rule(json!({"if": [0, {"var": "x"}, {"var": "y"}]}));
+        assert!(token.is_variable());
+        arena.set_current_context(&data, &DataValue::String("$"));
+        assert_eq!(evaluate(token, &arena).unwrap().as_str(), Some("right"));
+
+        // Only the reachable pairs of a chain are kept
+        let token = optimize_rule(json!({"?:": [
+            {"var": "a"}, 1,
+            false, 2,
+            "yes", {"var": "x"},
+            {"var": "b"}, 3
+        ]}));
+        let (op_type, args) = token.as_operator().unwrap();
+        assert_eq!(op_type, OperatorType::Control(ControlOp::If));
+        assert_eq!(args.as_array_literal().unwrap().len(), 3);
+        arena.set_current_context(&data, &DataValue::String("$"));
+        assert_eq!(evaluate(token, &arena).unwrap().as_str(), Some("left"));
+
+        // Without an else value an unreachable if is null
+        let token = optimize_rule(json!({"if": [null, {"var": "x"}]}));
+        assert!(token.as_literal().unwrap().is_null());
+
+        // Literals that can't change the result of and/or are dropped
+        let token = optimize_rule(json!({"and": [true, {"var": "x"}]}));
+        assert!(token.is_variable());
+        let token = optimize_rule(json!({"or": [{"var": "x"}, "", {"var": "y"}]}));
+        assert_eq!(
+            token
+                .as_operator()
+                .unwrap()
+                .1
+                .as_array_literal()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // A short-circuiting literal ends the chain
+        let token = optimize_rule(json!({"and": [{"var": "x"}, 0, {"var": "y"}]}));
+        let args = token.as_operator().unwrap().1.as_array_literal().unwrap();
+        assert_eq!(args.len(), 2);
+        assert!(args[1].is_literal());
+        arena.set_current_context(&data, &DataValue::String("$"));
+        assert_eq!(evaluate(token, &arena).unwrap().as_i64(), Some(0));
+
+        // Literals of a do block are only kept in last position
+        let token = optimize_rule(json!({"do": [1, {"var": "x"}, "two", {"var": "y"}]}));
+        let args = token.as_operator().unwrap().1.as_array_literal().unwrap();
+        assert_eq!(args.len(), 2);
+        let token = optimize_rule(json!({"do": ["one", {"var": "x"}]}));
+        assert!(token.is_variable());
+
+        // A do block that assigns stays, even around a single expression
+        let token = optimize_rule(json!({"do": [1, {"if": [{"var": "x"}, {"assign": ["a", 1]}]}]}));
+        assert_eq!(token.as_operator().unwrap().0, OperatorType::Do);
+        let token = optimize_rule(json!({"do": [{"do": [{"assign": ["a", 1]}]}]}));
+        let (op_type, args) = token.as_operator().unwrap();
+        assert_eq!(op_type, OperatorType::Do);
+        assert!(args.as_array_literal().unwrap()[0].is_operator());
+    }
+
+    #[test]
+    fn test_optimized_regex() {
+        let arena = DataArena::new();
+
+        let rule_json = json!({"regex_replace": ["(\\d{3})-(\\d{4})", {"var": "phone"}, "$1$2"]});
+        let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+        let optimized_token = optimizer::optimize(token, &arena).unwrap();
+        assert!(matches!(optimized_token, Token::CompiledRegex { .. }));
+
+        let data = DataValue::from_json(&json!({"phone": "555-1234"}), &arena);
+        arena.set_current_context(&data, &DataValue::String("$"));
+        let result = evaluate(optimized_token, &arena).unwrap();
+        assert_eq!(result.as_str(), Some("5551234"));
+
+        // Patterns computed at runtime are compiled on every evaluation
+        let rule_json = json!({"regex": [{"var": "pattern"}, "abc"]});
+        let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+        let optimized_token = optimizer::optimize(token, &arena).unwrap();
+        assert!(matches!(optimized_token, Token::Operator { .. }));
+
+        // Invalid constant patterns are rejected when the rule is compiled
+        let rule_json = json!({"regex": ["(", {"var": "text"}]});
+        let token = jsonlogic::parse_json(&rule_json, &arena).unwrap();
+        assert!(optimizer::optimize(token, &arena).is_err());
+    }
 }
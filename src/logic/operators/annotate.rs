@@ -0,0 +1,113 @@
+//! Annotate operator implementation.
+//!
+//! Rule authors can document intent inside the rule itself:
+//!
+//! ```json
+//! {"annotate": {"note": "Adults only", "rule": {">=": [{"var": "age"}, 18]}}}
+//! {"//": ["Adults only", {">=": [{"var": "age"}, 18]}]}
+//! {"//": "A comment on its own"}
+//! ```
+//!
+//! The note is kept as a literal and never evaluated. The annotation stays in
+//! the token tree after optimization, so it shows in listings and in the cost
+//! tree of explained evaluations, but evaluation only sees the annotated rule.
+
+use crate::arena::DataArena;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::evaluator::evaluate;
+use crate::logic::token::{OperatorType, Token};
+use crate::value::DataValue;
+
+/// Evaluates an annotation to the value of the rule it documents.
+///
+/// The parser lowers annotations into the arguments `[note, rule]`, or
+/// `[note]` for a comment on its own, which evaluates to null.
+pub fn eval_annotate<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    match args {
+        [_, rule] => evaluate(rule, arena),
+        [_] => Ok(arena.null_value()),
+        _ => Err(LogicError::InvalidArgumentsError),
+    }
+}
+
+/// Returns the note of an annotation token, if the token is one.
+pub(crate) fn annotation_note<'a>(token: &'a Token<'a>) -> Option<&'a DataValue<'a>> {
+    match token {
+        Token::Operator {
+            op_type: OperatorType::Annotate,
+            args: Token::ArrayLiteral(items),
+        } => match items.first() {
+            Some(Token::Literal(note)) => Some(note),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::error::LogicError;
+    use crate::DataLogic;
+    use serde_json::json;
+
+    #[test]
+    fn test_annotate() {
+        let dl = DataLogic::new();
+        let data = json!({"age": 20});
+
+        let rule = json!({"annotate": {
+            "note": "Adults only",
+            "rule": {">=": [{"var": "age"}, 18]}
+        }});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(true));
+
+        // Notes are never evaluated, even when they look like rules
+        let rule = json!({"//": [{"var": "age"}, {"+": [{"var": "age"}, 1]}]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(21));
+
+        // A comment on its own is ignored in a sequence
+        let rule = json!({"do": [{"//": "Compute the total"}, {"*": [2, 3]}]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(6));
+        let rule = json!({"//": {"author": "ops"}});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(null));
+    }
+
+    #[test]
+    fn test_annotations_survive_optimization() {
+        let dl = DataLogic::new();
+
+        let logic = dl
+            .parse_logic(r#"{"//": ["Always six", {"*": [2, 3]}]}"#, None)
+            .unwrap();
+        assert_eq!(
+            logic.disassemble(),
+            "0000  annotate\n0001    literal \"Always six\"\n0002    literal 6\n"
+        );
+
+        let data = dl.parse_data("null").unwrap();
+        assert_eq!(dl.evaluate(&logic, &data).unwrap().as_i64(), Some(6));
+    }
+
+    #[test]
+    fn test_invalid_annotations() {
+        let dl = DataLogic::new();
+
+        for rule in [
+            json!({"annotate": {"note": "no rule"}}),
+            json!({"annotate": {"note": "x", "rule": 1, "extra": 2}}),
+            json!({"annotate": []}),
+            json!({"//": ["a", "b", "c"]}),
+        ] {
+            assert!(
+                matches!(
+                    dl.evaluate_json(&rule, &json!(null), None),
+                    Err(LogicError::ParseError { .. })
+                ),
+                "{rule}"
+            );
+        }
+    }
+}
@@ -9,7 +9,8 @@ use std::cmp::Ordering;
 
 use crate::arena::DataArena;
 use crate::logic::error::{LogicError, Result};
-use crate::value::DataValue;
+use crate::semantics::IntegerOverflow;
+use crate::value::{parse_datetime, parse_duration, DataValue, NumberValue};
 use chrono::{DateTime, Utc};
 
 /// Enumeration of arithmetic operators.
@@ -35,6 +36,24 @@ pub enum ArithmeticOp {
     Ceil,
     /// Floor (round down)
     Floor,
+    /// Percentage of a part relative to a whole
+    PercentOf,
+    /// Division with a fallback for zero or non-numeric operands
+    SafeDiv,
+    /// Label of the bucket a value falls into
+    Bucket,
+    /// Exponentiation (pow, **)
+    Pow,
+    /// Square root
+    Sqrt,
+    /// Natural logarithm
+    Ln,
+    /// Base 10 logarithm
+    Log10,
+    /// Rounding to a number of decimal places
+    Round,
+    /// Truncation toward zero
+    Trunc,
 }
 
 /// Helper function to safely convert a DataValue to f64
@@ -46,8 +65,8 @@ fn safe_to_f64(value: &DataValue) -> Result<f64> {
 }
 
 /// Helper function to create appropriate number type based on value
-fn create_number(value: f64, arena: &DataArena) -> &DataValue<'_> {
-    if value.fract() == 0.0 && value >= i64::MIN as f64 && value <= i64::MAX as f64 {
+pub(crate) fn create_number(value: f64, arena: &DataArena) -> &DataValue<'_> {
+    if value.fract() == 0.0 && value >= i64::MIN as f64 && value < i64::MAX as f64 {
         arena.alloc(DataValue::integer(value as i64))
     } else {
         arena.alloc(DataValue::float(value))
@@ -96,25 +115,69 @@ fn extract_duration<'a>(value: &'a DataValue<'a>, arena: &'a DataArena) -> Optio
     }
 }
 
+/// Returns true if a value is a datetime or a duration
+fn is_temporal<'a>(value: &'a DataValue<'a>, arena: &'a DataArena) -> bool {
+    extract_datetime(value, arena).is_some() || extract_duration(value, arena).is_some()
+}
+
+/// Extracts a datetime operand of `+` or `-`
+///
+/// A string combined with a datetime or a duration is parsed as a datetime,
+/// so that a date read from the data can be subtracted from `now` or moved
+/// by a duration.
+fn datetime_operand<'a>(
+    value: &'a DataValue<'a>,
+    other: &'a DataValue<'a>,
+    arena: &'a DataArena,
+) -> Option<DateTime<Utc>> {
+    match value {
+        DataValue::String(s) if is_temporal(other, arena) => {
+            let dt = parse_datetime(s).ok()?;
+            arena.record_coercion(value, "datetime");
+            Some(dt)
+        }
+        _ => extract_datetime(value, arena),
+    }
+}
+
+/// Extracts a duration operand of `+` or `-`
+///
+/// A string combined with a datetime or a duration is parsed as a duration,
+/// so that a deadline can be computed as `{"+": [{"now": []}, "4h"]}`.
+fn duration_operand<'a>(
+    value: &'a DataValue<'a>,
+    other: &'a DataValue<'a>,
+    arena: &'a DataArena,
+) -> Option<Duration> {
+    match value {
+        DataValue::String(s) if is_temporal(other, arena) => {
+            let dur = parse_duration(s).ok()?;
+            arena.record_coercion(value, "duration");
+            Some(dur)
+        }
+        _ => extract_duration(value, arena),
+    }
+}
+
 /// Process potential datetime and duration operations for addition
 fn process_datetime_duration_add<'a>(
     args: &'a [DataValue<'a>],
     arena: &'a DataArena,
 ) -> Option<&'a DataValue<'a>> {
-    if args.len() != 2 {
+    let [left, right] = args else {
         return None;
-    }
+    };
 
     // Check for datetime + duration
-    let left_dt = extract_datetime(&args[0], arena);
-    let right_dur = extract_duration(&args[1], arena);
+    let left_dt = datetime_operand(left, right, arena);
+    let right_dur = duration_operand(right, left, arena);
     if let (Some(dt), Some(dur)) = (left_dt, right_dur) {
         return Some(arena.alloc(DataValue::datetime(dt + dur)));
     }
 
     // Check for duration + datetime (reverse order)
-    let left_dur = extract_duration(&args[0], arena);
-    let right_dt = extract_datetime(&args[1], arena);
+    let left_dur = duration_operand(left, right, arena);
+    let right_dt = datetime_operand(right, left, arena);
     if let (Some(dur), Some(dt)) = (left_dur, right_dt) {
         return Some(arena.alloc(DataValue::datetime(dt + dur)));
     }
@@ -132,26 +195,26 @@ fn process_datetime_duration_sub<'a>(
     args: &'a [DataValue<'a>],
     arena: &'a DataArena,
 ) -> Option<&'a DataValue<'a>> {
-    if args.len() != 2 {
+    let [left, right] = args else {
         return None;
-    }
+    };
 
     // Check for datetime - datetime = duration
-    let left_dt = extract_datetime(&args[0], arena);
-    let right_dt = extract_datetime(&args[1], arena);
+    let left_dt = datetime_operand(left, right, arena);
+    let right_dt = datetime_operand(right, left, arena);
     if let (Some(dt1), Some(dt2)) = (left_dt, right_dt) {
         let duration = dt1 - dt2;
         return Some(arena.alloc(DataValue::duration(duration)));
     }
 
     // Check for datetime - duration = datetime
-    let right_dur = extract_duration(&args[1], arena);
+    let right_dur = duration_operand(right, left, arena);
     if let (Some(dt), Some(dur)) = (left_dt, right_dur) {
         return Some(arena.alloc(DataValue::datetime(dt - dur)));
     }
 
     // Check for duration - duration = duration
-    let left_dur = extract_duration(&args[0], arena);
+    let left_dur = duration_operand(left, right, arena);
     if let (Some(dur1), Some(dur2)) = (left_dur, right_dur) {
         return Some(arena.alloc(DataValue::duration(dur1 - dur2)));
     }
@@ -231,6 +294,60 @@ fn process_duration_division<'a>(
     None
 }
 
+/// Folds integer operands exactly, following the arena's overflow policy
+///
+/// A single operand to `-` is negated. Returns `None` when an operand is not an integer, when numbers follow
+/// JavaScript semantics, or when the result overflows and the policy is to
+/// compute it with floating point numbers instead.
+fn fold_integers<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+    operator: &str,
+    checked: fn(i64, i64) -> Option<i64>,
+    saturating: fn(i64, i64) -> i64,
+) -> Option<Result<&'a DataValue<'a>>> {
+    if arena.semantics().is_js() {
+        return None;
+    }
+
+    let mut operands = args.iter().map(|arg| match arg.coerce_to_number() {
+        Some(NumberValue::Integer(i)) => Some(i),
+        _ => None,
+    });
+    let first = operands.next()??;
+    let mut overflowed = false;
+    // A single operand to subtraction is negated
+    let mut result = if args.len() == 1 && operator == "-" {
+        first.checked_neg().unwrap_or_else(|| {
+            overflowed = true;
+            i64::MAX
+        })
+    } else {
+        first
+    };
+    for operand in operands {
+        let operand = operand?;
+        match checked(result, operand) {
+            Some(value) if !overflowed => result = value,
+            _ => {
+                overflowed = true;
+                result = saturating(result, operand);
+            }
+        }
+    }
+
+    if !overflowed {
+        return Some(Ok(arena.alloc(DataValue::integer(result))));
+    }
+    match arena.integer_overflow() {
+        IntegerOverflow::Float => None,
+        IntegerOverflow::Saturate => Some(Ok(arena.alloc(DataValue::integer(result)))),
+        IntegerOverflow::Error => Some(Err(LogicError::IntegerOverflow {
+            operator: operator.to_string(),
+        })),
+    }
+}
+
 /// Process numeric addition
 fn process_numeric_add<'a>(
     args: &'a [DataValue<'a>],
@@ -241,6 +358,10 @@ fn process_numeric_add<'a>(
         return Ok(arena.alloc(DataValue::integer(0)));
     }
 
+    if let Some(result) = fold_integers(args, arena, "+", i64::checked_add, i64::saturating_add) {
+        return result;
+    }
+
     let mut sum = 0.0;
     for arg in args {
         if let Some(n) = arg.coerce_to_number() {
@@ -262,6 +383,10 @@ fn process_numeric_sub<'a>(
         return Err(LogicError::InvalidArgumentsError);
     }
 
+    if let Some(result) = fold_integers(args, arena, "-", i64::checked_sub, i64::saturating_sub) {
+        return result;
+    }
+
     // Get first value
     let first_value = match args[0].coerce_to_number() {
         Some(n) => n.as_f64(),
@@ -295,6 +420,10 @@ fn process_numeric_mul<'a>(
         return Ok(arena.alloc(DataValue::integer(1)));
     }
 
+    if let Some(result) = fold_integers(args, arena, "*", i64::checked_mul, i64::saturating_mul) {
+        return result;
+    }
+
     let mut product = 1.0;
     for arg in args {
         match arg.coerce_to_number() {
@@ -597,9 +726,214 @@ pub fn eval_floor<'a>(
     Ok(arena.alloc(DataValue::Array(arena.alloc_data_value_slice(&result))))
 }
 
+/// Returns a finite result as a number, or a NaN error otherwise.
+fn finite_number(value: f64, arena: &DataArena) -> Result<&DataValue<'_>> {
+    if value.is_finite() {
+        Ok(create_number(value, arena))
+    } else {
+        Err(LogicError::NaNError)
+    }
+}
+
+/// Evaluates an exponentiation.
+///
+/// `{"pow": [base, exponent]}`, also written `**`, raises the base to the
+/// exponent. Integer powers with a non-negative exponent are computed exactly,
+/// following the [`IntegerOverflow`] policy of the arena.
+pub fn eval_pow<'a>(args: &'a [DataValue<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    if let Some(NumberValue::Integer(exponent)) = args[1].coerce_to_number() {
+        if u32::try_from(exponent).is_ok() {
+            let pow = fold_integers(
+                args,
+                arena,
+                "pow",
+                |base, exponent| base.checked_pow(exponent as u32),
+                |base, exponent| base.saturating_pow(exponent as u32),
+            );
+            if let Some(result) = pow {
+                return result;
+            }
+        }
+    }
+
+    let base = safe_to_f64(&args[0])?;
+    let exponent = safe_to_f64(&args[1])?;
+    finite_number(base.powf(exponent), arena)
+}
+
+/// Evaluates a square root, failing for negative numbers.
+pub fn eval_sqrt<'a>(args: &'a [DataValue<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    let [value] = args else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    finite_number(safe_to_f64(value)?.sqrt(), arena)
+}
+
+/// Evaluates a natural logarithm, failing for numbers that are not positive.
+pub fn eval_ln<'a>(args: &'a [DataValue<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    let [value] = args else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    finite_number(safe_to_f64(value)?.ln(), arena)
+}
+
+/// Evaluates a base 10 logarithm, failing for numbers that are not positive.
+pub fn eval_log10<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let [value] = args else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    finite_number(safe_to_f64(value)?.log10(), arena)
+}
+
+/// Evaluates a rounding operation.
+///
+/// `{"round": [value, precision]}` rounds to `precision` decimal places, half
+/// away from zero. The precision defaults to 0, and a negative precision
+/// rounds to tens, hundreds and so on.
+pub fn eval_round<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let (value, precision) = match args {
+        [value] => (value, 0),
+        [value, precision] => match precision.coerce_to_number() {
+            Some(NumberValue::Integer(precision)) => (value, precision),
+            _ => return Err(LogicError::InvalidArgumentsError),
+        },
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    let value = safe_to_f64(value)?;
+    let scale = 10f64.powi(precision.clamp(-308, 308) as i32);
+    let scaled = value * scale;
+    if !scaled.is_finite() {
+        // Numbers this large have no digits left to round
+        return finite_number(value, arena);
+    }
+    finite_number(scaled.round() / scale, arena)
+}
+
+/// Evaluates a truncation toward zero.
+pub fn eval_trunc<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let [value] = args else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    finite_number(safe_to_f64(value)?.trunc(), arena)
+}
+
+/// Divides two operands, returning `None` when the division is undefined.
+///
+/// The division is undefined when either operand cannot be coerced to a
+/// number, the divisor is zero, or the result is not finite.
+fn checked_ratio(numerator: &DataValue, denominator: &DataValue) -> Option<f64> {
+    let numerator = numerator.coerce_to_number()?.as_f64();
+    let denominator = denominator.coerce_to_number()?.as_f64();
+    if denominator == 0.0 {
+        return None;
+    }
+
+    let ratio = numerator / denominator;
+    if ratio.is_finite() {
+        Some(ratio)
+    } else {
+        None
+    }
+}
+
+/// Returns the fallback value for an undefined ratio (the third argument, or null).
+fn ratio_fallback<'a>(args: &'a [DataValue<'a>], arena: &'a DataArena) -> &'a DataValue<'a> {
+    args.get(2).unwrap_or_else(|| arena.null_value())
+}
+
+/// Evaluates a percent_of operation.
+///
+/// `{"percent_of": [part, whole]}` returns `part / whole * 100`. When the
+/// percentage is undefined (zero or non-numeric whole, non-numeric part) the
+/// optional third argument is returned, defaulting to null.
+pub fn eval_percent_of<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() < 2 || args.len() > 3 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    match checked_ratio(&args[0], &args[1]) {
+        Some(ratio) => Ok(create_number(ratio * 100.0, arena)),
+        None => Ok(ratio_fallback(args, arena)),
+    }
+}
+
+/// Evaluates a safe_div operation.
+///
+/// `{"safe_div": [a, b, default]}` returns `a / b`, or `default` (null when
+/// omitted) if the divisor is zero or either operand is not numeric.
+pub fn eval_safe_div<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() < 2 || args.len() > 3 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    match checked_ratio(&args[0], &args[1]) {
+        Some(ratio) => Ok(create_number(ratio, arena)),
+        None => Ok(ratio_fallback(args, arena)),
+    }
+}
+
+/// Evaluates a bucket operation.
+///
+/// `{"bucket": [value, boundaries, labels]}` returns the label of the last
+/// boundary that is less than or equal to `value`, or null if the value lies
+/// below the first boundary. Boundaries must be numbers in ascending order,
+/// with exactly one label per boundary.
+pub fn eval_bucket<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 3 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let (DataValue::Array(boundaries), DataValue::Array(labels)) = (&args[1], &args[2]) else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    if boundaries.len() != labels.len() {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let bounds = boundaries
+        .iter()
+        .map(|bound| bound.as_f64().ok_or(LogicError::InvalidArgumentsError))
+        .collect::<Result<Vec<f64>>>()?;
+    if bounds.windows(2).any(|pair| pair[0] > pair[1]) {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let value = safe_to_f64(&args[0])?;
+    let index = bounds.partition_point(|bound| *bound <= value);
+
+    match index {
+        0 => Ok(arena.null_value()),
+        _ => Ok(&labels[index - 1]),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::semantics::Semantics;
     use chrono::{TimeZone, Utc};
 
     #[test]
@@ -632,6 +966,89 @@ mod tests {
         assert_eq!(result.as_f64().unwrap(), 1.0);
     }
 
+    #[test]
+    fn test_integer_overflow() {
+        let arena = DataArena::new();
+
+        // Integers beyond 2^53 are added exactly
+        let args = [DataValue::integer(9007199254740993), DataValue::integer(0)];
+        let result = eval_add(&args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(9007199254740993));
+
+        // Overflow is computed in floating point by default
+        let args = [DataValue::integer(i64::MAX), DataValue::integer(1)];
+        let result = eval_add(&args, &arena).unwrap();
+        assert_eq!(result.as_f64(), Some(9.223372036854776e18));
+        assert!(result.as_i64().is_none());
+
+        arena.set_integer_overflow(IntegerOverflow::Saturate);
+        let result = eval_add(&args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(i64::MAX));
+        let args = [DataValue::integer(i64::MIN), DataValue::integer(1)];
+        let result = eval_sub(&args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(i64::MIN));
+        let args = [DataValue::integer(i64::MIN)];
+        let result = eval_sub(&args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(i64::MAX));
+        let args = [DataValue::integer(i64::MAX), DataValue::integer(-2)];
+        let result = eval_mul(&args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(i64::MIN));
+
+        arena.set_integer_overflow(IntegerOverflow::Error);
+        let args = [DataValue::integer(i64::MAX / 2), DataValue::integer(3)];
+        let error = eval_mul(&args, &arena).unwrap_err();
+        assert_eq!(
+            error,
+            LogicError::IntegerOverflow {
+                operator: "*".to_string()
+            }
+        );
+        // Results within range are unaffected
+        let args = [DataValue::integer(i64::MAX), DataValue::integer(-1)];
+        let result = eval_add(&args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(i64::MAX - 1));
+        // Floating point operands never overflow
+        let args = [DataValue::float(9.3e18), DataValue::integer(1)];
+        assert!(eval_add(&args, &arena).is_ok());
+
+        // JavaScript semantics always compute in floating point
+        arena.set_semantics(Semantics::JsonLogicJs);
+        let args = [DataValue::integer(i64::MAX), DataValue::integer(1)];
+        let result = eval_add(&args, &arena).unwrap();
+        assert_eq!(result.as_f64(), Some(9.223372036854776e18));
+    }
+
+    #[test]
+    fn test_math_operations() {
+        let arena = DataArena::new();
+
+        let args = [DataValue::integer(10), DataValue::integer(18)];
+        let result = eval_pow(&args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(1_000_000_000_000_000_000));
+
+        // Powers beyond the integer range follow the overflow policy
+        let args = [DataValue::integer(10), DataValue::integer(19)];
+        let result = eval_pow(&args, &arena).unwrap();
+        assert_eq!(result.as_f64(), Some(1e19));
+        arena.set_integer_overflow(IntegerOverflow::Error);
+        assert_eq!(
+            eval_pow(&args, &arena).unwrap_err(),
+            LogicError::IntegerOverflow {
+                operator: "pow".to_string()
+            }
+        );
+
+        let args = [DataValue::float(1e300), DataValue::integer(2)];
+        assert_eq!(eval_pow(&args, &arena).unwrap_err(), LogicError::NaNError);
+
+        let args = [DataValue::float(-0.5)];
+        assert_eq!(eval_round(&args, &arena).unwrap().as_i64(), Some(-1));
+        let args = [DataValue::float(1e300), DataValue::integer(400)];
+        assert_eq!(eval_round(&args, &arena).unwrap().as_f64(), Some(1e300));
+        let args = [DataValue::float(0.123), DataValue::integer(-400)];
+        assert_eq!(eval_round(&args, &arena).unwrap().as_i64(), Some(0));
+    }
+
     #[test]
     fn test_datetime_operations() {
         let arena = DataArena::new();
@@ -713,6 +1130,44 @@ mod tests {
         assert_eq!(result_dur.num_hours(), 12);
     }
 
+    #[test]
+    fn test_temporal_string_operands() {
+        let arena = DataArena::new();
+        let dt = Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap();
+
+        // Strings next to a datetime or a duration are parsed
+        let args = [DataValue::datetime(dt), DataValue::String("4h")];
+        let result = eval_add(&args, &arena).unwrap();
+        assert_eq!(result.as_datetime(), Some(&(dt + Duration::hours(4))));
+
+        let args = [
+            DataValue::String("2024-01-15T13:30:00Z"),
+            DataValue::datetime(dt),
+        ];
+        let result = eval_sub(&args, &arena).unwrap();
+        assert_eq!(result.as_duration(), Some(&Duration::minutes(270)));
+
+        let args = [
+            DataValue::String("2024-01-15T09:00:00Z"),
+            DataValue::duration(Duration::days(1)),
+        ];
+        let result = eval_sub(&args, &arena).unwrap();
+        assert_eq!(result.as_datetime(), Some(&(dt - Duration::days(1))));
+
+        let args = [
+            DataValue::String("30m"),
+            DataValue::duration(Duration::hours(1)),
+        ];
+        let result = eval_add(&args, &arena).unwrap();
+        assert_eq!(result.as_duration(), Some(&Duration::minutes(90)));
+
+        // Other strings are still numbers
+        let args = [DataValue::String("4h"), DataValue::String("2h")];
+        assert_eq!(eval_add(&args, &arena), Err(LogicError::NaNError));
+        let args = [DataValue::datetime(dt), DataValue::String("soon")];
+        assert_eq!(eval_add(&args, &arena), Err(LogicError::NaNError));
+    }
+
     #[test]
     fn test_min_max() {
         let _arena = DataArena::new();
@@ -3,10 +3,11 @@
 //! This module provides implementations for array operators
 //! such as map, filter, reduce, etc.
 
-use crate::arena::DataArena;
+use crate::arena::{ArrayBuilder, DataArena, IterationPosition};
 use crate::logic::error::{LogicError, Result};
 use crate::logic::evaluator::evaluate;
-use crate::logic::operators::arithmetic::ArithmeticOp;
+use crate::logic::operators::arithmetic::{create_number, ArithmeticOp};
+use crate::logic::operators::comparison::loose_equals;
 use crate::logic::token::OperatorType;
 use crate::logic::token::Token;
 use crate::value::DataValue;
@@ -36,6 +37,30 @@ pub enum ArrayOp {
     Slice,
     /// Sort operator
     Sort,
+    /// Related operator, traversing nested relationships
+    Related,
+    /// Unique operator, removing duplicate items
+    Unique,
+    /// Group by operator, grouping items by a key
+    GroupBy,
+    /// Sum operator, adding up items or a key of items
+    Sum,
+    /// Average operator, averaging items or a key of items
+    Avg,
+    /// Count operator, counting items matching an optional condition
+    Count,
+    /// Min by operator, finding the item with the smallest key
+    MinBy,
+    /// Max by operator, finding the item with the largest key
+    MaxBy,
+    /// Keys operator, listing the keys of an object
+    Keys,
+    /// Values operator, listing the values of an object
+    Values,
+    /// Entries operator, listing the `[key, value]` pairs of an object
+    Entries,
+    /// From entries operator, building an object from `[key, value]` pairs
+    FromEntries,
 }
 
 /// Enumeration of array predicate operations (all, some, none).
@@ -112,11 +137,14 @@ fn eval_predicate<'a>(
 
     // Evaluate the items
     for (index, item) in items.iter().enumerate() {
+        arena.count_iterations(1)?;
+
         // Store the current path chain length to preserve parent contexts
         let current_chain_len = arena.path_chain_len();
 
         let key = DataValue::Number(crate::value::NumberValue::from_f64(index as f64));
-        arena.set_current_context(item, arena.alloc(key));
+        let position = IterationPosition::new(index, items.len());
+        arena.set_iteration_context(item, arena.alloc(key), position);
 
         // Evaluate the condition with the item as context
         let item_matches = evaluate(condition, arena)?.coerce_to_bool();
@@ -213,7 +241,7 @@ fn get_array_items<'a>(
 /// and properly manage the path chain state.
 fn with_array_item_context<'a, F, T>(
     item: &'a DataValue<'a>,
-    index: usize,
+    position: IterationPosition,
     arena: &'a DataArena,
     callback: F,
 ) -> T
@@ -224,8 +252,8 @@ where
     let current_chain_len = arena.path_chain_len();
 
     // Set the current item as context with the index as key
-    let key = DataValue::Number(crate::value::NumberValue::from_f64(index as f64));
-    arena.set_current_context(item, arena.alloc(key));
+    let key = DataValue::Number(crate::value::NumberValue::from_f64(position.index as f64));
+    arena.set_iteration_context(item, arena.alloc(key), position);
 
     // Call the function with the item as context
     let result = callback();
@@ -280,15 +308,19 @@ pub fn eval_map<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
     match collection {
         // Handle array case
         DataValue::Array(items) => {
+            arena.limits().check_array_len(items.len())?;
             result_values.reserve(items.len());
 
+            arena.count_iterations(items.len())?;
+
             // Apply the function to each item
             for (index, item) in items.iter().enumerate() {
                 // Store the current path chain length to preserve parent contexts
                 let current_chain_len = arena.path_chain_len();
 
                 let key = DataValue::Number(crate::value::NumberValue::from_f64(index as f64));
-                arena.set_current_context(item, arena.alloc(key));
+                let position = IterationPosition::new(index, items.len());
+                arena.set_iteration_context(item, arena.alloc(key), position);
 
                 // Evaluate the function with the item as context
                 let result = evaluate(args[1], arena)?;
@@ -304,6 +336,8 @@ pub fn eval_map<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
 
         // Handle object case
         DataValue::Object(entries) => {
+            arena.limits().check_array_len(entries.len())?;
+            arena.count_iterations(entries.len())?;
             result_values.reserve(entries.len());
 
             // Sort keys alphabetically for consistent iteration order
@@ -312,12 +346,13 @@ pub fn eval_map<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
             entry_refs.sort_by(|a, b| a.0.cmp(b.0));
 
             // Apply the function to each property value
-            for (key, value) in entry_refs {
+            for (index, (key, value)) in entry_refs.into_iter().enumerate() {
                 // Store the current path chain length to preserve parent contexts
                 let current_chain_len = arena.path_chain_len();
 
                 let key_value = DataValue::String(key);
-                arena.set_current_context(value, arena.alloc(key_value));
+                let position = IterationPosition::new(index, entries.len());
+                arena.set_iteration_context(value, arena.alloc(key_value), position);
 
                 // Evaluate the function with the property value as context
                 let result = evaluate(args[1], arena)?;
@@ -333,13 +368,15 @@ pub fn eval_map<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
 
         // Handle single value case - treat as single-element collection
         _ => {
+            arena.count_iterations(1)?;
             result_values.reserve(1);
 
             // Store the current path chain length to preserve parent contexts
             let current_chain_len = arena.path_chain_len();
 
             let key = DataValue::Number(crate::value::NumberValue::from_f64(0.0));
-            arena.set_current_context(collection, arena.alloc(key));
+            let position = IterationPosition::new(0, 1);
+            arena.set_iteration_context(collection, arena.alloc(key), position);
 
             // Evaluate the function with the value as context
             let result = evaluate(args[1], arena)?;
@@ -374,6 +411,23 @@ pub fn eval_map<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
 pub fn eval_filter<'a>(
     args: &'a [&'a Token<'a>],
     arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    // A windowed evaluation may only want some of the matching items
+    match arena.take_filter_window(args) {
+        Some((offset, limit)) => eval_filter_window(args, offset, Some(limit), arena),
+        None => eval_filter_window(args, 0, None, arena),
+    }
+}
+
+/// Evaluates a filter operation, keeping only a window of the matching items.
+///
+/// The first `offset` matches are skipped, and the items after the first
+/// `limit` kept matches are not evaluated at all.
+fn eval_filter_window<'a>(
+    args: &'a [&'a Token<'a>],
+    offset: usize,
+    limit: Option<usize>,
+    arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
     // Fast path for invalid arguments
     if args.len() != 2 {
@@ -393,18 +447,30 @@ pub fn eval_filter<'a>(
 
     // Get a vector from the arena's pool with the estimated capacity
     let mut results = arena.get_data_value_vec();
-    results.reserve(items.len());
+    results.reserve(limit.map_or(items.len(), |limit| limit.min(items.len())));
 
     // Filter the array
+    let mut skipped = 0;
     for (index, item) in items.iter().enumerate() {
+        if limit.is_some_and(|limit| results.len() >= limit) {
+            break;
+        }
+        arena.count_iterations(1)?;
+
         // Evaluate condition with item as context
-        let item_matches = with_array_item_context(item, index, arena, || {
+        let position = IterationPosition::new(index, items.len());
+        let item_matches = with_array_item_context(item, position, arena, || {
             evaluate(condition, arena).map(|v| v.coerce_to_bool())
         })?;
 
         // Add the item to results if it matches the condition
         if item_matches {
-            results.push(item.clone());
+            if skipped < offset {
+                skipped += 1;
+            } else {
+                arena.limits().check_array_len(results.len() + 1)?;
+                results.push(item.clone());
+            }
         }
     }
 
@@ -607,6 +673,8 @@ pub fn eval_reduce<'a>(
     // Cache the function token
     let function = args[1];
 
+    arena.count_iterations(items.len().saturating_sub(start_idx))?;
+
     // Optimization for arithmetic operators - use specialized implementations
     if let Some(arith_op) = is_arithmetic_reduce_pattern(function) {
         return match arith_op {
@@ -618,12 +686,27 @@ pub fn eval_reduce<'a>(
             ArithmeticOp::Min => reduce_min(items, initial, start_idx, arena),
             ArithmeticOp::Max => reduce_max(items, initial, start_idx, arena),
             // These operators don't really make sense in a reduction context
-            ArithmeticOp::Abs | ArithmeticOp::Ceil | ArithmeticOp::Floor => {
+            ArithmeticOp::Abs
+            | ArithmeticOp::Ceil
+            | ArithmeticOp::Floor
+            | ArithmeticOp::PercentOf
+            | ArithmeticOp::SafeDiv
+            | ArithmeticOp::Bucket
+            | ArithmeticOp::Pow
+            | ArithmeticOp::Sqrt
+            | ArithmeticOp::Ln
+            | ArithmeticOp::Log10
+            | ArithmeticOp::Round
+            | ArithmeticOp::Trunc => {
                 return Err(LogicError::InvalidArgumentsError);
             }
         };
     }
 
+    if reads_fields_only(function) {
+        return reduce_with_slots(items, initial, start_idx, function, arena);
+    }
+
     // For the generic case, create a context object with current item and accumulator
     let curr_key = arena.intern_str("current");
     let acc_key = arena.intern_str("accumulator");
@@ -636,23 +719,100 @@ pub fn eval_reduce<'a>(
         let index_key = DataValue::Number(crate::value::NumberValue::from_f64(index as f64));
 
         // Create context object with current item and accumulator
-        let entries = vec![(curr_key, item.clone()), (acc_key, acc.clone())];
-        let context_entries = arena.vec_into_slice(entries);
-        let context = arena.alloc(DataValue::Object(context_entries));
+        let entries = arena.alloc([(curr_key, item.clone()), (acc_key, acc.clone())]);
+        let context = arena.alloc(DataValue::Object(entries));
 
         // Set context and evaluate
-        arena.set_current_context(context, &index_key);
-        acc = evaluate(function, arena)?;
+        let position = IterationPosition::new(index, items.len());
+        arena.set_iteration_context(context, &index_key, position);
+        let result = evaluate(function, arena);
 
         // Restore path chain
         while arena.path_chain_len() > current_chain_len {
             arena.pop_path_component();
         }
+        acc = result?;
     }
 
     Ok(acc)
 }
 
+/// Checks if a reduce function only reads fields of its context.
+///
+/// Such a function never observes the context object itself, only the
+/// values of `current` and `accumulator` it holds, so the object does not
+/// need to outlive the step that evaluates it.
+fn reads_fields_only(token: &Token) -> bool {
+    match token {
+        Token::Literal(_) => true,
+        Token::ArrayLiteral(items) => items.iter().all(|item| reads_fields_only(item)),
+        Token::Variable { path, default } => {
+            !path.is_empty()
+                && *path != ".."
+                && !path.starts_with("../")
+                && default.is_none_or(reads_fields_only)
+        }
+        // Dynamic paths and custom operators may receive the context itself
+        Token::DynamicVariable { .. } | Token::CustomOperator { .. } => false,
+        Token::Operator {
+            op_type: OperatorType::Val,
+            args,
+        } => match args {
+            Token::Literal(DataValue::String(path)) => !path.is_empty(),
+            Token::ArrayLiteral(items) => matches!(
+                items.first(),
+                Some(Token::Literal(DataValue::String(path))) if !path.is_empty()
+            ),
+            _ => false,
+        },
+        Token::Operator {
+            op_type: OperatorType::Missing | OperatorType::MissingSome | OperatorType::Exists,
+            ..
+        } => false,
+        Token::Operator { args, .. }
+        | Token::CompiledMatch { args, .. }
+        | Token::CompiledRegex { args, .. }
+        | Token::CaseFolded { args, .. } => reads_fields_only(args),
+    }
+}
+
+/// Reduces an array, reusing accumulator slots for every step.
+///
+/// The context object of a step lives on the stack, and the accumulator is
+/// copied out of it before the next step, so a reduction over a long array
+/// allocates no context objects in the arena.
+fn reduce_with_slots<'a>(
+    items: &'a [DataValue<'a>],
+    initial: &'a DataValue<'a>,
+    start_idx: usize,
+    function: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let curr_key = arena.intern_str("current");
+    let acc_key = arena.intern_str("accumulator");
+    let mut acc = initial.clone();
+
+    for (index, item) in items.iter().enumerate().skip(start_idx) {
+        let current_chain_len = arena.path_chain_len();
+        let index_key = DataValue::Number(crate::value::NumberValue::from_f64(index as f64));
+
+        let slots = [(curr_key, item.clone()), (acc_key, acc)];
+        let context = DataValue::Object(&slots);
+
+        let position = IterationPosition::new(index, items.len());
+        arena.set_iteration_context(&context, &index_key, position);
+        let result = evaluate(function, arena);
+
+        // Restore the path chain before the context goes out of scope
+        while arena.path_chain_len() > current_chain_len {
+            arena.pop_path_component();
+        }
+        acc = result?.clone();
+    }
+
+    Ok(arena.alloc(acc))
+}
+
 /// Evaluates a merge operation.
 ///
 /// The merge operator combines multiple arrays into a single array.
@@ -671,35 +831,87 @@ pub fn eval_merge<'a>(
         return Ok(arena.empty_array_value());
     }
 
-    // Estimate the capacity based on the number of arguments
-    // This is a heuristic - we don't know the exact size yet
-    let mut result = arena.get_data_value_vec();
-    result.reserve(args.len() * 2); // Assuming average array size > 1
+    // Arrays of the arguments are referenced until the result is allocated
+    let mut result = ArrayBuilder::new(arena);
 
     // Process each argument
     for arg in args {
         let value = evaluate(arg, arena)?;
 
         match value {
-            DataValue::Array(items) => {
-                // For arrays, add all items
-                for item in items.iter() {
-                    result.push(item.clone());
+            // For arrays, add all items
+            DataValue::Array(items) => result.extend(items)?,
+            // Skip null values (treat as empty arrays)
+            DataValue::Null => continue,
+            // For non-array values, add the value itself
+            _ => result.push(value.clone())?,
+        }
+    }
+
+    // Create and return the result array
+    Ok(arena.alloc(DataValue::Array(result.finish())))
+}
+
+/// Options accepted by the in operator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct InOptions {
+    /// Compare strings case-insensitively
+    fold_case: bool,
+    /// The haystack was already case-folded at compile time
+    haystack_folded: bool,
+}
+
+/// Reads the options object of an in operation.
+///
+/// The only option is `fold_case`, and any other key is rejected.
+fn parse_in_options(options: &DataValue) -> Result<InOptions> {
+    match options {
+        DataValue::Null => Ok(InOptions::default()),
+        DataValue::Object(entries) => {
+            let mut options = InOptions::default();
+            for (key, value) in entries.iter() {
+                match (*key, value) {
+                    ("fold_case", DataValue::Bool(b)) => options.fold_case = *b,
+                    _ => return Err(LogicError::InvalidArgumentsError),
                 }
             }
-            DataValue::Null => {
-                // Skip null values (treat as empty arrays)
-                continue;
-            }
-            _ => {
-                // For non-array values, add the value itself
-                result.push(value.clone());
-            }
+            Ok(options)
         }
+        _ => Err(LogicError::InvalidArgumentsError),
     }
+}
 
-    // Create and return the result array
-    Ok(arena.alloc(DataValue::Array(arena.bump_vec_into_slice(result))))
+/// Case-folds every string in a value, leaving other values untouched.
+///
+/// Used at compile time to pre-fold constant haystacks.
+pub(crate) fn fold_case_value<'a>(value: &DataValue<'a>, arena: &'a DataArena) -> DataValue<'a> {
+    match value {
+        DataValue::String(s) => DataValue::String(arena.alloc_str(&s.to_lowercase())),
+        DataValue::Array(items) => {
+            let folded: Vec<DataValue<'a>> = items
+                .iter()
+                .map(|item| match item {
+                    DataValue::String(s) => DataValue::String(arena.alloc_str(&s.to_lowercase())),
+                    _ => item.clone(),
+                })
+                .collect();
+            DataValue::Array(arena.vec_into_slice(folded))
+        }
+        _ => value.clone(),
+    }
+}
+
+/// Compares an array item with the needle for the in operator.
+fn in_item_matches(item: &DataValue, needle: &DataValue) -> bool {
+    // Compare based on types for more accurate matching
+    match (item, needle) {
+        (DataValue::Number(a), DataValue::Number(b)) => a == b,
+        (DataValue::String(a), DataValue::String(b)) => a == b,
+        (DataValue::Bool(a), DataValue::Bool(b)) => a == b,
+        (DataValue::Null, DataValue::Null) => true,
+        // For other types, use the equals method (handles coercion)
+        _ => item.equals(needle),
+    }
 }
 
 /// Evaluates an "in" operation.
@@ -712,14 +924,35 @@ pub fn eval_merge<'a>(
 /// Arguments:
 /// - First argument: The needle value to search for
 /// - Second argument: The haystack to search in (string, array, or object)
+/// - Third argument (optional): An options object, where `fold_case`
+///   compares strings case-insensitively
 ///
 /// Example:
 /// ```json
 /// {"in": ["apple", {"var": "fruits"}]}
+/// {"in": [{"var": "country"}, ["US", "CA", "MX"], {"fold_case": true}]}
 /// ```
 pub fn eval_in<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    eval_in_with(args, false, arena)
+}
+
+/// Evaluates an "in" operation whose constant haystack was case-folded by the
+/// optimizer.
+pub(crate) fn eval_case_folded_in<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    eval_in_with(args, true, arena)
+}
+
+/// Evaluates an "in" operation, knowing whether its haystack is case-folded.
+fn eval_in_with<'a>(
+    args: &'a [&'a Token<'a>],
+    haystack_folded: bool,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
     // Validate arguments
-    if args.len() != 2 {
+    if args.len() != 2 && args.len() != 3 {
         return Err(LogicError::InvalidArgumentsError);
     }
 
@@ -727,6 +960,12 @@ pub fn eval_in<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'
     let needle = evaluate(args[0], arena)?;
     let haystack = evaluate(args[1], arena)?;
 
+    let mut options = match args.get(2) {
+        Some(options) => parse_in_options(evaluate(options, arena)?)?,
+        None => InOptions::default(),
+    };
+    options.haystack_folded = haystack_folded && options.fold_case;
+
     // Search based on haystack type
     let result = match haystack {
         // String haystack: check if needle is a substring
@@ -736,32 +975,46 @@ pub fn eval_in<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'
                 DataValue::String(ns) => *ns,
                 _ => arena.alloc_str(&needle.to_string()),
             };
-            s.contains(needle_str)
+
+            if !options.fold_case {
+                s.contains(needle_str)
+            } else if options.haystack_folded {
+                s.contains(&needle_str.to_lowercase())
+            } else {
+                s.to_lowercase().contains(&needle_str.to_lowercase())
+            }
         }
 
         // Array haystack: check if needle exists in array
-        DataValue::Array(arr) => arr.iter().any(|item| {
-            // Compare based on types for more accurate matching
-            match (item, needle) {
-                (DataValue::Number(a), DataValue::Number(b)) => a == b,
-                (DataValue::String(a), DataValue::String(b)) => a == b,
-                (DataValue::Bool(a), DataValue::Bool(b)) => a == b,
-                (DataValue::Null, DataValue::Null) => true,
-                // For other types, use the equals method (handles coercion)
-                _ => item.equals(needle),
+        DataValue::Array(arr) => match needle {
+            DataValue::String(ns) if options.fold_case => {
+                let folded_needle = ns.to_lowercase();
+                arr.iter().any(|item| match item {
+                    DataValue::String(item_str) if options.haystack_folded => {
+                        *item_str == folded_needle
+                    }
+                    DataValue::String(item_str) => item_str.to_lowercase() == folded_needle,
+                    _ => in_item_matches(item, needle),
+                })
             }
-        }),
+            _ => arr.iter().any(|item| in_item_matches(item, needle)),
+        },
 
-        // Object haystack: check if needle is a key
+        // Object haystack: check if needle is a key, except in json-logic-js
+        DataValue::Object(_) if arena.semantics().is_js() => false,
         DataValue::Object(obj) => {
-            match needle {
+            let key_str = match needle {
                 // If needle is a string, direct key comparison
-                DataValue::String(key) => obj.iter().any(|(k, _)| *k == *key),
+                DataValue::String(key) => *key,
                 // Otherwise, convert needle to string for comparison
-                _ => {
-                    let key_str = needle.to_string();
-                    obj.iter().any(|(k, _)| *k == key_str)
-                }
+                _ => arena.alloc_str(&needle.to_string()),
+            };
+
+            if options.fold_case {
+                let folded_key = key_str.to_lowercase();
+                obj.iter().any(|(k, _)| k.to_lowercase() == folded_key)
+            } else {
+                obj.iter().any(|(k, _)| *k == key_str)
             }
         }
 
@@ -779,8 +1032,8 @@ pub fn eval_in<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'
 
 /// Evaluates a length operation.
 ///
-/// The length operator returns the number of elements in an array or
-/// the number of characters in a string.
+/// The length operator returns the number of elements in an array, the
+/// number of characters in a string or the number of keys of an object.
 ///
 /// Example:
 /// ```json
@@ -818,6 +1071,15 @@ pub fn eval_length<'a>(
                 ))),
             )
         }
+        DataValue::Object(entries) => {
+            // For objects, return the number of keys
+            let key_count = entries.len() as i64;
+            Ok(
+                arena.alloc(DataValue::Number(crate::value::NumberValue::from_i64(
+                    key_count,
+                ))),
+            )
+        }
         DataValue::Null => {
             // For null values, throw an error (following JSONLogic behavior)
             Err(LogicError::InvalidArgumentsError)
@@ -826,45 +1088,81 @@ pub fn eval_length<'a>(
     }
 }
 
-/// Helper function to parse slice indices
-fn parse_slice_index(
+/// Helper function to parse the step of a slice
+///
+/// A missing or null step is 1, and a zero step is invalid.
+fn parse_slice_step(step: Option<&DataValue>) -> Result<isize> {
+    match step {
+        None => Ok(1),
+        Some(s) if s.is_null() => Ok(1),
+        Some(s) => match s.as_i64() {
+            Some(0) => Err(LogicError::InvalidArgumentsError),
+            Some(i) => Ok(i as isize),
+            None => Err(LogicError::NaNError),
+        },
+    }
+}
+
+/// Helper function to parse a slice bound
+///
+/// Negative indices count from the end. Bounds are clamped to the positions a
+/// slice can start or stop at in the given direction: `0..=len` going
+/// forward and `-1..=len - 1` going backward, where `-1` is before the first
+/// item. A missing or null bound is `default`.
+fn parse_slice_bound(
     index_value: Option<&DataValue>,
-    collection_length: usize,
-    default_for_positive_step: usize,
-    default_for_negative_step: usize,
-) -> Result<usize> {
-    match index_value {
-        Some(idx) => {
-            if let Some(i) = idx.as_i64() {
-                if i >= 0 {
-                    // Positive index - bound to collection length
-                    Ok(i.min(collection_length as i64) as usize)
-                } else {
-                    // Negative index - count from end
-                    let from_end = collection_length as i64 + i;
-                    Ok(if from_end < 0 { 0 } else { from_end as usize })
-                }
-            } else if idx.is_null() {
-                // Null index - use default value based on step direction
-                Ok(if default_for_positive_step == 0 {
-                    default_for_negative_step // For negative step
-                } else {
-                    default_for_positive_step // For positive step
-                })
-            } else {
-                // Non-numeric, non-null index is an error
-                Err(LogicError::NaNError)
-            }
-        }
-        None => {
-            // No index provided - use default value based on step direction
-            Ok(if default_for_positive_step == 0 {
-                default_for_negative_step // For negative step
-            } else {
-                default_for_positive_step // For positive step
-            })
+    len: isize,
+    step: isize,
+    default: isize,
+) -> Result<isize> {
+    let index = match index_value {
+        None => return Ok(default),
+        Some(idx) if idx.is_null() => return Ok(default),
+        Some(idx) => idx.as_i64().ok_or(LogicError::NaNError)?,
+    };
+    let index = isize::try_from(index).unwrap_or(if index < 0 { isize::MIN } else { isize::MAX });
+    let index = if index < 0 {
+        index.saturating_add(len)
+    } else {
+        index
+    };
+    Ok(if step > 0 {
+        index.clamp(0, len)
+    } else {
+        index.clamp(-1, len - 1)
+    })
+}
+
+/// Helper function to compute the positions selected by a slice
+///
+/// Follows the semantics of Python slices: `end` is exclusive and the
+/// defaults of `start` and `end` depend on the direction of `step`.
+fn slice_positions(
+    len: usize,
+    start: Option<&DataValue>,
+    end: Option<&DataValue>,
+    step: Option<&DataValue>,
+) -> Result<impl Iterator<Item = usize>> {
+    let step = parse_slice_step(step)?;
+    let len = len as isize;
+    let (default_start, default_end) = if step > 0 { (0, len) } else { (len - 1, -1) };
+    let start = parse_slice_bound(start, len, step, default_start)?;
+    let end = parse_slice_bound(end, len, step, default_end)?;
+
+    let mut position = start;
+    Ok(std::iter::from_fn(move || {
+        let in_range = if step > 0 {
+            position < end
+        } else {
+            position > end
+        };
+        if !in_range {
+            return None;
         }
-    }
+        let current = position as usize;
+        position = position.saturating_add(step);
+        Some(current)
+    }))
 }
 
 /// Evaluates a slice operation on an array
@@ -875,98 +1173,16 @@ fn eval_array_slice<'a>(
     step: Option<&'a DataValue<'a>>,
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
-    let array_len = array.len();
-
-    // Handle empty array case
-    if array_len == 0 {
-        return Ok(arena.empty_array_value());
-    }
-
-    // Parse step value first since start/end defaults depend on step direction
-    let step_val = match step {
-        Some(s) => {
-            if let Some(i) = s.as_i64() {
-                if i == 0 {
-                    return Err(LogicError::InvalidArgumentsError);
-                }
-                i as isize
-            } else if s.is_null() {
-                1
-            } else {
-                return Err(LogicError::NaNError);
-            }
-        }
-        None => 1,
-    };
-
-    // Special case for full array reversal with step -1 (common case)
-    // Check for both None and explicit null values
-    let is_start_empty = start.is_none() || (start.is_some() && start.unwrap().is_null());
-    let is_end_empty = end.is_none() || (end.is_some() && end.unwrap().is_null());
-
-    if is_start_empty && is_end_empty && step_val == -1 {
-        let mut result = arena.get_data_value_vec();
-        result.reserve(array_len);
-        for i in (0..array_len).rev() {
-            result.push(array[i].clone());
-        }
-        return Ok(arena.alloc(DataValue::Array(arena.bump_vec_into_slice(result))));
-    }
-
-    // Set default indices based on step direction
-    let default_start = if step_val < 0 {
-        array_len.saturating_sub(1)
-    } else {
-        0
-    };
-    let default_end = if step_val < 0 { 0 } else { array_len };
-
-    // Parse start and end indices
-    let start_idx = parse_slice_index(start, array_len, 0, default_start)?;
-    let end_idx = parse_slice_index(end, array_len, array_len, default_end)?;
-
-    // Create the result array
     let mut result = arena.get_data_value_vec();
-    result.reserve(array_len); // Over-allocate to be safe
-
-    if step_val > 0 {
-        // Positive step - go forward
-        let mut i = start_idx;
-        while i < end_idx && i < array_len {
-            result.push(array[i].clone());
-
-            // Handle potential overflow
-            if array_len.saturating_sub(i) <= step_val as usize {
-                break;
-            }
-            i = i.saturating_add(step_val as usize);
-        }
-    } else {
-        // Negative step - go backward
-        // Start from start_idx, but if it's beyond the array bounds, use the last element
-        let mut i = if start_idx >= array_len {
-            array_len.saturating_sub(1)
-        } else {
-            start_idx
-        };
-
-        // When going backward, we want to continue until i > end_idx (exclusive end)
-        // But we need to handle the case where end_idx >= start_idx (which would be invalid direction)
-        while i > end_idx && i < array_len {
-            result.push(array[i].clone());
-
-            // Handle potential underflow
-            if i < step_val.unsigned_abs() {
-                break;
-            }
-            i = i.saturating_sub(step_val.unsigned_abs());
-        }
+    for position in slice_positions(array.len(), start, end, step)? {
+        result.push(array[position].clone());
     }
-
     Ok(arena.alloc(DataValue::Array(arena.bump_vec_into_slice(result))))
 }
 
 /// Evaluates a slice operation on a string
+///
+/// Positions count characters rather than bytes.
 fn eval_string_slice<'a>(
     string: &str,
     start: Option<&'a DataValue<'a>>,
@@ -974,92 +1190,10 @@ fn eval_string_slice<'a>(
     step: Option<&'a DataValue<'a>>,
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
-    // Convert string to character array for proper Unicode handling
-    let str_chars: Vec<char> = string.chars().collect();
-    let str_len = str_chars.len();
-
-    // Handle empty string case
-    if str_len == 0 {
-        return Ok(arena.alloc(DataValue::String(arena.alloc_str(""))));
-    }
-
-    // Parse step value first since start/end defaults depend on step direction
-    let step_val = match step {
-        Some(s) => {
-            if let Some(i) = s.as_i64() {
-                if i == 0 {
-                    return Err(LogicError::InvalidArgumentsError);
-                }
-                i as isize
-            } else if s.is_null() {
-                1
-            } else {
-                return Err(LogicError::NaNError);
-            }
-        }
-        None => 1,
-    };
-
-    // Special case for full string reversal with step -1 (common case)
-    // Check for both None and explicit null values
-    let is_start_empty = start.is_none() || (start.is_some() && start.unwrap().is_null());
-    let is_end_empty = end.is_none() || (end.is_some() && end.unwrap().is_null());
-
-    if is_start_empty && is_end_empty && step_val == -1 {
-        let reversed: String = str_chars.iter().rev().collect();
-        return Ok(arena.alloc(DataValue::String(arena.alloc_str(&reversed))));
-    }
-
-    // Set default indices based on step direction
-    let default_start = if step_val < 0 {
-        str_len.saturating_sub(1)
-    } else {
-        0
-    };
-    let default_end = if step_val < 0 { 0 } else { str_len };
-
-    // Parse start and end indices
-    let start_idx = parse_slice_index(start, str_len, 0, default_start)?;
-    let end_idx = parse_slice_index(end, str_len, str_len, default_end)?;
-
-    // Create the result string
-    let mut result = String::new();
-    result.reserve(str_len); // Over-allocate to be safe
-
-    if step_val > 0 {
-        // Positive step - go forward
-        let mut i = start_idx;
-        while i < end_idx && i < str_len {
-            result.push(str_chars[i]);
-
-            // Handle potential overflow
-            if str_len.saturating_sub(i) <= step_val as usize {
-                break;
-            }
-            i = i.saturating_add(step_val as usize);
-        }
-    } else {
-        // Negative step - go backward
-        // Start from start_idx, but if it's beyond the string bounds, use the last character
-        let mut i = if start_idx >= str_len {
-            str_len.saturating_sub(1)
-        } else {
-            start_idx
-        };
-
-        // When going backward, we want to continue until i >= end_idx (inclusive end for negative step)
-        // This is different from positive step case where end is exclusive
-        while i >= end_idx && i < str_len {
-            result.push(str_chars[i]);
-
-            // Handle potential underflow
-            if i < step_val.unsigned_abs() {
-                break;
-            }
-            i = i.saturating_sub(step_val.unsigned_abs());
-        }
-    }
-
+    let chars: Vec<char> = string.chars().collect();
+    let result: String = slice_positions(chars.len(), start, end, step)?
+        .map(|position| chars[position])
+        .collect();
     Ok(arena.alloc(DataValue::String(arena.alloc_str(&result))))
 }
 
@@ -1129,9 +1263,8 @@ fn extract_field_value<'a>(
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
     if let Some(extractor_token) = extractor {
-        // Store current context and key
-        let current_context = arena.current_context(0);
-        let current_key = arena.last_path_component();
+        // Store the current path chain length to preserve parent contexts
+        let current_chain_len = arena.path_chain_len();
 
         // Set the item as the context for field extraction
         arena.set_current_context(item, &DataValue::String(""));
@@ -1139,9 +1272,9 @@ fn extract_field_value<'a>(
         // Evaluate the extractor with the item as context
         let result = evaluate(extractor_token, arena);
 
-        // Restore original context if it exists
-        if let (Some(ctx), Some(key)) = (current_context, current_key) {
-            arena.set_current_context(ctx, key);
+        // Restore the path chain and the original context
+        while arena.path_chain_len() > current_chain_len {
+            arena.pop_path_component();
         }
 
         result
@@ -1166,6 +1299,7 @@ fn compare_values<'a>(a: &'a DataValue<'a>, b: &'a DataValue<'a>) -> std::cmp::O
             DataValue::Object(_) => 6,
             DataValue::DateTime(_) => 7, // Additional types
             DataValue::Duration(_) => 8,
+            DataValue::Custom(_) => 9,
         }
     };
 
@@ -1264,6 +1398,81 @@ fn compare_values<'a>(a: &'a DataValue<'a>, b: &'a DataValue<'a>) -> std::cmp::O
     }
 }
 
+/// Evaluates a related operation.
+///
+/// The related operator follows a field linking nodes to other nodes, such as
+/// the parent of a category or the reports of a manager, and returns the
+/// nodes reached in breadth-first order. The field may hold a single node or
+/// an array of nodes.
+///
+/// Arguments:
+/// - First argument: The start node, or an array of start nodes
+/// - Second argument: The name of the field holding the related nodes
+/// - Third argument (optional): The maximum number of steps to follow
+///
+/// Nodes equal to one already visited are skipped, so repeated or cyclic
+/// relationships are followed only once.
+///
+/// Example:
+/// ```json
+/// {"related": [{"var": "category"}, "parent", 3]}
+/// ```
+pub fn eval_related<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() < 2 || args.len() > 3 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let start = evaluate(args[0], arena)?;
+    let field = match evaluate(args[1], arena)? {
+        DataValue::String(field) => *field,
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+    let max_depth = match args.get(2) {
+        Some(depth) => match evaluate(depth, arena)?.as_i64() {
+            Some(depth) if depth >= 0 => Some(depth as usize),
+            _ => return Err(LogicError::InvalidArgumentsError),
+        },
+        None => None,
+    };
+
+    let mut visited: Vec<&'a DataValue<'a>> = Vec::new();
+    let mut frontier: Vec<&'a DataValue<'a>> = match start {
+        DataValue::Array(items) => items.iter().collect(),
+        DataValue::Null => Vec::new(),
+        node => vec![node],
+    };
+    let starts = frontier.len();
+    visited.extend(frontier.iter().copied());
+
+    let mut depth = 0;
+    while !frontier.is_empty() && max_depth.is_none_or(|max_depth| depth < max_depth) {
+        let mut next = Vec::new();
+        for node in frontier {
+            let linked = match node.get(field) {
+                Some(DataValue::Array(items)) => items.iter().collect(),
+                Some(DataValue::Null) | None => Vec::new(),
+                Some(linked) => vec![linked],
+            };
+            for linked in linked {
+                if !visited.contains(&linked) {
+                    arena.limits().check_array_len(visited.len() + 1 - starts)?;
+                    visited.push(linked);
+                    next.push(linked);
+                }
+            }
+        }
+        frontier = next;
+        depth += 1;
+    }
+
+    let mut result = arena.get_data_value_vec_with_capacity(visited.len() - starts);
+    result.extend(visited[starts..].iter().map(|&node| node.clone()));
+    Ok(arena.alloc(DataValue::Array(arena.bump_vec_into_slice(result))))
+}
+
 /// Evaluates a sort operation.
 ///
 /// The sort operator sorts an array in ascending or descending order,
@@ -1275,9 +1484,13 @@ fn compare_values<'a>(a: &'a DataValue<'a>, b: &'a DataValue<'a>) -> std::cmp::O
 ///   (true/false, "asc"/"desc", etc.)
 /// - Third argument (optional): Field extractor function
 ///
+/// With two arguments, a second argument that is not a literal is the field
+/// extractor, sorting in ascending order.
+///
 /// Example:
 /// ```json
 /// {"sort": [{"var": "myArray"}, false, {"var": "fieldName"}]}
+/// {"sort": [{"var": "products"}, {"var": "price"}]}
 /// ```
 pub fn eval_sort<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
     // Validate arguments
@@ -1311,10 +1524,16 @@ pub fn eval_sort<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<
         return Ok(array_value);
     }
 
+    // A two-argument sort with an expression sorts by that key
+    let (direction, field_extractor) = match args {
+        [_, key] if !key.is_literal() => (None, Some(*key)),
+        _ => (args.get(1), args.get(2).copied()),
+    };
+
     // Parse sort direction from second argument
     let mut ascending = true;
-    if args.len() > 1 {
-        let dir_value = evaluate(args[1], arena)?;
+    if let Some(direction) = direction {
+        let dir_value = evaluate(direction, arena)?;
         if let Some(dir_bool) = dir_value.as_bool() {
             ascending = dir_bool;
         } else if let Some(dir_str) = dir_value.as_str() {
@@ -1323,15 +1542,16 @@ pub fn eval_sort<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<
         }
     }
 
-    // Get field extractor if provided as third argument
-    let field_extractor = if args.len() > 2 { Some(args[2]) } else { None };
-
     // Clone the array to sort it
+    arena.limits().check_array_len(arr.len())?;
     let mut result: Vec<DataValue> = arr.to_vec();
 
     // Sort the array based on field extractor presence
     if let Some(extractor) = field_extractor {
+        arena.count_iterations(result.len())?;
+
         // Sort using extracted field values
+        let mut exceeded = None;
         result.sort_by(|a, b| {
             // Extract field values for comparison
             let a_field = extract_field_value(a, Some(extractor), arena);
@@ -1345,10 +1565,19 @@ pub fn eval_sort<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<
                         compare_values(b_val, a_val)
                     }
                 }
+                // Exceeding a limit aborts the evaluation
+                (Err(error @ LogicError::LimitExceeded { .. }), _)
+                | (_, Err(error @ LogicError::LimitExceeded { .. })) => {
+                    exceeded.get_or_insert(error);
+                    std::cmp::Ordering::Equal
+                }
                 // If extraction fails, treat elements as equal
                 _ => std::cmp::Ordering::Equal,
             }
         });
+        if let Some(error) = exceeded {
+            return Err(error);
+        }
     } else {
         // Direct item comparison without extraction
         if ascending {
@@ -1362,6 +1591,370 @@ pub fn eval_sort<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<
     Ok(arena.alloc(DataValue::Array(arena.vec_into_slice(result))))
 }
 
+/// Option keys accepted in a trailing options object by the unique operator.
+pub(crate) const UNIQUE_OPTION_KEYS: &[&str] = &["strict"];
+
+/// Evaluates a unique operation.
+///
+/// The unique operator returns the items of an array without duplicates,
+/// keeping the first occurrence of each. Items are compared with `===`, or
+/// with `==` when the options object sets `"strict": false`, in which case
+/// `1` and `"1"` are duplicates.
+///
+/// Example:
+/// ```json
+/// {"unique": {"var": "tags"}}
+/// {"unique": [{"var": "codes"}, {"strict": false}]}
+/// ```
+pub fn eval_unique<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let strict = match args {
+        [_] => true,
+        [_, options] => match evaluate(options, arena)? {
+            DataValue::Object(entries) => match entries.iter().find(|(k, _)| *k == "strict") {
+                None => true,
+                Some((_, DataValue::Bool(strict))) => *strict,
+                Some(_) => return Err(LogicError::InvalidArgumentsError),
+            },
+            _ => return Err(LogicError::InvalidArgumentsError),
+        },
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    let array_value = evaluate(args[0], arena)?;
+    let items = match array_value {
+        DataValue::Array(items) => items,
+        DataValue::Null => return Ok(arena.null_value()),
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    let mut result = arena.get_data_value_vec_with_capacity(items.len());
+    for item in items.iter() {
+        let duplicate = result.iter().any(|kept: &DataValue| {
+            if strict {
+                kept.strict_equals(item)
+            } else {
+                let kept = arena.alloc(kept.clone());
+                loose_equals(kept, item, arena)
+            }
+        });
+        if !duplicate {
+            arena.limits().check_array_len(result.len() + 1)?;
+            result.push(item.clone());
+        }
+    }
+
+    if result.len() == items.len() {
+        return Ok(array_value);
+    }
+    Ok(arena.alloc(DataValue::Array(arena.bump_vec_into_slice(result))))
+}
+
+/// Evaluates the key of each item of an array.
+///
+/// Without a key expression, the items are their own keys.
+fn item_keys<'a>(
+    items: &'a [DataValue<'a>],
+    key: Option<&'a Token<'a>>,
+    arena: &'a DataArena,
+) -> Result<Vec<&'a DataValue<'a>>> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| match key {
+            Some(key) => {
+                arena.count_iterations(1)?;
+                let position = IterationPosition::new(index, items.len());
+                with_array_item_context(item, position, arena, || evaluate(key, arena))
+            }
+            None => Ok(item),
+        })
+        .collect()
+}
+
+/// Evaluates a group by operation.
+///
+/// The group by operator evaluates a key expression with each item of an
+/// array as context, and returns an object mapping each key to the array of
+/// items having it. Keys are converted to strings, and groups are listed in
+/// the order their first item appears.
+///
+/// Example:
+/// ```json
+/// {"group_by": [{"var": "orders"}, {"var": "region"}]}
+/// ```
+pub fn eval_group_by<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let Some(items) = get_array_items(args, arena)? else {
+        return Ok(arena.alloc(DataValue::Object(&[])));
+    };
+
+    let mut groups: Vec<(&'a str, Vec<DataValue<'a>>)> = Vec::new();
+    for (item, key) in items.iter().zip(item_keys(items, Some(args[1]), arena)?) {
+        let key = match key {
+            DataValue::String(key) => *key,
+            DataValue::Array(_) | DataValue::Object(_) => {
+                return Err(LogicError::InvalidArgumentsError)
+            }
+            key => arena.alloc_str(&key.to_string()),
+        };
+        match groups.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, group)) => group.push(item.clone()),
+            None => groups.push((key, vec![item.clone()])),
+        }
+    }
+
+    let entries: Vec<(&'a str, DataValue<'a>)> = groups
+        .into_iter()
+        .map(|(key, group)| (key, DataValue::Array(arena.vec_into_slice(group))))
+        .collect();
+    Ok(arena.alloc(DataValue::Object(arena.vec_into_slice(entries))))
+}
+
+/// Evaluates the single argument of an object operator, returning the
+/// entries of the object, or `None` if it is null.
+fn get_object_entries<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<Option<&'a [(&'a str, DataValue<'a>)]>> {
+    if args.len() != 1 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    match evaluate(args[0], arena)? {
+        DataValue::Object(entries) => Ok(Some(entries)),
+        DataValue::Null => Ok(None),
+        _ => Err(LogicError::InvalidArgumentsError),
+    }
+}
+
+/// Evaluates a keys operation.
+///
+/// The keys operator returns the keys of an object in order, and an empty
+/// array for null. Mapping over them evaluates `{"var": ""}` to each key.
+///
+/// Example:
+/// ```json
+/// {"map": [{"keys": {"var": "flags"}}, {"upper": {"var": ""}}]}
+/// ```
+pub fn eval_keys<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    let entries = get_object_entries(args, arena)?.unwrap_or(&[]);
+    let keys: Vec<DataValue<'a>> = entries
+        .iter()
+        .map(|(key, _)| DataValue::String(key))
+        .collect();
+    Ok(arena.alloc(DataValue::Array(arena.vec_into_slice(keys))))
+}
+
+/// Evaluates a values operation.
+///
+/// The values operator returns the values of an object in the order of its
+/// keys, and an empty array for null.
+///
+/// Example:
+/// ```json
+/// {"sum": [{"values": {"var": "scores"}}]}
+/// ```
+pub fn eval_values<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let entries = get_object_entries(args, arena)?.unwrap_or(&[]);
+    let values: Vec<DataValue<'a>> = entries.iter().map(|(_, value)| value.clone()).collect();
+    Ok(arena.alloc(DataValue::Array(arena.vec_into_slice(values))))
+}
+
+/// Evaluates an entries operation.
+///
+/// The entries operator returns the `[key, value]` pairs of an object in
+/// order, and an empty array for null. Mapping over them evaluates
+/// `{"var": 0}` to the key and `{"var": 1}` to the value of each pair.
+///
+/// Example:
+/// ```json
+/// {"map": [{"entries": {"var": "prices"}}, {"cat": [{"var": 0}, "=", {"var": 1}]}]}
+/// ```
+pub fn eval_entries<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let entries = get_object_entries(args, arena)?.unwrap_or(&[]);
+    let pairs: Vec<DataValue<'a>> = entries
+        .iter()
+        .map(|(key, value)| {
+            let pair = arena.vec_into_slice(vec![DataValue::String(key), value.clone()]);
+            DataValue::Array(pair)
+        })
+        .collect();
+    Ok(arena.alloc(DataValue::Array(arena.vec_into_slice(pairs))))
+}
+
+/// Evaluates a from entries operation.
+///
+/// The from entries operator builds an object from an array of
+/// `[key, value]` pairs, such as those returned by `entries`, and an empty
+/// object from null. Keys are converted to strings, and a repeated key
+/// keeps its first position with its last value.
+///
+/// Example:
+/// ```json
+/// {"from_entries": {"map": [{"entries": {"var": "prices"}}, [{"var": 0}, {"*": [{"var": 1}, 2]}]]}}
+/// ```
+pub fn eval_from_entries<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 1 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+    let items: &[DataValue] = match evaluate(args[0], arena)? {
+        DataValue::Array(items) => items,
+        DataValue::Null => &[],
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    let mut entries: Vec<(&'a str, DataValue<'a>)> = Vec::with_capacity(items.len());
+    for item in items {
+        let (key, value) = match item {
+            DataValue::Array([key, value]) => (key, value),
+            _ => return Err(LogicError::InvalidArgumentsError),
+        };
+        let key = match key {
+            DataValue::String(key) => *key,
+            DataValue::Array(_) | DataValue::Object(_) => {
+                return Err(LogicError::InvalidArgumentsError)
+            }
+            key => arena.alloc_str(&key.to_string()),
+        };
+        match entries.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, existing)) => *existing = value.clone(),
+            None => entries.push((key, value.clone())),
+        }
+    }
+    Ok(arena.alloc(DataValue::Object(arena.vec_into_slice(entries))))
+}
+
+/// Adds up the keys of the items of an array, returning the sum and count.
+fn sum_keys<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<(f64, usize)> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let items = get_array_items(args, arena)?.unwrap_or(&[]);
+    let mut sum = 0.0;
+    for key in item_keys(items, args.get(1).copied(), arena)? {
+        sum += key.coerce_to_number().ok_or(LogicError::NaNError)?.as_f64();
+    }
+    Ok((sum, items.len()))
+}
+
+/// Evaluates a sum operation.
+///
+/// The sum operator adds up the items of an array, or the values of a key
+/// expression evaluated with each item as context. Values are converted to
+/// numbers as with `+`, and the sum of no items is 0.
+///
+/// Example:
+/// ```json
+/// {"sum": [{"var": "cart"}, {"*": [{"var": "price"}, {"var": "qty"}]}]}
+/// ```
+pub fn eval_sum<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    let (sum, _) = sum_keys(args, arena)?;
+    Ok(create_number(sum, arena))
+}
+
+/// Evaluates an average operation.
+///
+/// The avg operator averages the items of an array, or the values of a key
+/// expression evaluated with each item as context. The average of no items
+/// is null.
+///
+/// Example:
+/// ```json
+/// {"avg": [{"var": "reviews"}, {"var": "rating"}]}
+/// ```
+pub fn eval_avg<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    match sum_keys(args, arena)? {
+        (_, 0) => Ok(arena.null_value()),
+        (sum, count) => Ok(create_number(sum / count as f64, arena)),
+    }
+}
+
+/// Evaluates a count operation.
+///
+/// The count operator returns the number of items of an array, or the number
+/// of items for which a condition evaluated with the item as context is
+/// truthy.
+///
+/// Example:
+/// ```json
+/// {"count": [{"var": "tasks"}, {"var": "done"}]}
+/// ```
+pub fn eval_count<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let items = get_array_items(args, arena)?.unwrap_or(&[]);
+    let count = match args.get(1) {
+        Some(condition) => item_keys(items, Some(condition), arena)?
+            .into_iter()
+            .filter(|matched| matched.coerce_to_bool())
+            .count(),
+        None => items.len(),
+    };
+    Ok(arena.alloc(DataValue::integer(count as i64)))
+}
+
+/// Evaluates a min by or max by operation.
+///
+/// Returns the item of an array whose key, evaluated with the item as
+/// context, is the smallest or the largest, in the order used by `sort`.
+/// The first such item wins ties, and an empty array gives null.
+///
+/// Example:
+/// ```json
+/// {"min_by": [{"var": "offers"}, {"var": "price"}]}
+/// ```
+pub fn eval_extreme_by<'a>(
+    args: &'a [&'a Token<'a>],
+    largest: bool,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let items = get_array_items(args, arena)?.unwrap_or(&[]);
+    let keys = item_keys(items, Some(args[1]), arena)?;
+    let mut best: Option<(&'a DataValue<'a>, &'a DataValue<'a>)> = None;
+    for (item, key) in items.iter().zip(keys) {
+        let better = best.is_none_or(|(_, best_key)| {
+            let ordering = compare_values(key, best_key);
+            if largest {
+                ordering.is_gt()
+            } else {
+                ordering.is_lt()
+            }
+        });
+        if better {
+            best = Some((item, key));
+        }
+    }
+    Ok(best.map_or(arena.null_value(), |(item, _)| item))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::logic::datalogic_core::DataLogicCore;
@@ -1370,7 +1963,7 @@ mod tests {
     use crate::logic::token::{OperatorType, Token};
     use crate::logic::Logic;
     use crate::parser::jsonlogic::parse_json;
-    use crate::value::DataValue;
+    use crate::value::{DataValue, ToJson};
     use serde_json::json;
 
     #[test]
@@ -1578,6 +2171,51 @@ mod tests {
         assert_eq!(result, json!(20)); // 10 + 1 + 2 + 3 + 4 = 20
     }
 
+    #[test]
+    fn test_reduce_reuses_slots() {
+        let dl = crate::DataLogic::new();
+        let rule = dl
+            .parse_logic(
+                r#"{"reduce": [
+                    {"var": "items"},
+                    {"if": [{">": [{"var": "current.score"}, {"var": "accumulator.score"}]},
+                        {"var": "current"}, {"var": "accumulator"}]},
+                    {"var": "start"}
+                ]}"#,
+                None,
+            )
+            .unwrap();
+        let items: Vec<_> = (0..1000)
+            .map(|i| json!({"id": i, "score": (i * 7) % 1000}))
+            .collect();
+        let data = dl
+            .parse_data_json(&json!({"start": {"score": -1}, "items": items}))
+            .unwrap();
+
+        let before = dl.arena().used_bytes();
+        let result = dl.evaluate(&rule, &data).unwrap();
+        assert_eq!(result.to_json(), json!({"id": 857, "score": 999}));
+
+        // No context object was allocated per item
+        assert!(dl.arena().used_bytes() - before < 1024);
+
+        // Functions reading the context itself still see both fields
+        let result = dl
+            .evaluate_str(
+                r#"{"reduce": [[1, 2], {"merge": [{"var": "accumulator"}, [{"var": ""}]]}, []]}"#,
+                "{}",
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            result,
+            json!([
+                {"current": 1, "accumulator": []},
+                {"current": 2, "accumulator": [{"current": 1, "accumulator": []}]}
+            ])
+        );
+    }
+
     #[test]
     fn test_length_operator() {
         let core = DataLogicCore::new();
@@ -1850,7 +2488,6 @@ mod tests {
         let rule = Logic::new(map_ref, arena);
 
         let result = core.apply(&rule, &data_json).unwrap();
-        println!("Values result: {:?}", result);
 
         // Verify the values are returned
         let values: Vec<serde_json::Value> = serde_json::from_value(result).unwrap();
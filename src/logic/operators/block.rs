@@ -0,0 +1,51 @@
+//! Block and assignment operator implementations.
+//!
+//! `{"do": [expr, ...]}` evaluates its expressions in order and returns the
+//! value of the last one. Within it, `{"assign": ["name", expr]}` stores the
+//! value of `expr` under `name`, and later `var` references to `name`, or to
+//! paths starting with it, read the assigned value instead of the data.
+//! Assignments end with the `do` block that contains them, and the latest
+//! assignment to a name wins.
+
+use crate::arena::DataArena;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::evaluator::evaluate;
+use crate::logic::token::Token;
+use crate::value::DataValue;
+
+/// Evaluates a do block, returning the value of its last expression.
+pub fn eval_do<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    arena.enter_block();
+    let mut result = Ok(arena.null_value());
+    for arg in args {
+        result = evaluate(arg, arena);
+        if result.is_err() {
+            break;
+        }
+    }
+    arena.leave_block();
+    result
+}
+
+/// Evaluates an assignment, returning the assigned value.
+pub fn eval_assign<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let [name, value] = args else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    let DataValue::String(name) = evaluate(name, arena)? else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    if name.is_empty() || name.contains('.') {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let value = evaluate(value, arena)?;
+    if !arena.assign(name, value) {
+        // Assignments are only visible within a do block
+        return Err(LogicError::InvalidArgumentsError);
+    }
+    Ok(value)
+}
@@ -7,7 +7,7 @@ use crate::arena::DataArena;
 use crate::logic::error::{LogicError, Result};
 use crate::logic::evaluator::evaluate;
 use crate::logic::token::Token;
-use crate::value::DataValue;
+use crate::value::{parse_datetime, parse_duration, DataValue, NumberValue};
 use chrono::{DateTime, Duration, Utc};
 
 /// Enumeration of comparison operators.
@@ -29,6 +29,8 @@ pub enum ComparisonOp {
     LessThan,
     /// Less than or equal (<=)
     LessThanOrEqual,
+    /// Structural equality of two values (deep_equal)
+    DeepEqual,
 }
 
 /// Helper function to extract a datetime from a direct DateTime value or an object with a "datetime" key
@@ -55,6 +57,32 @@ fn extract_datetime<'a>(
     }
 }
 
+/// Extracts the datetimes of two operands compared with each other
+///
+/// A string operand compared with a datetime is parsed as a datetime, so that
+/// a date read from the data can be compared with `now` or another datetime.
+fn datetime_operands<'a>(
+    left: &'a DataValue<'a>,
+    right: &'a DataValue<'a>,
+    arena: &'a DataArena,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let coerce = |value: &DataValue, other: Option<&DateTime<Utc>>| {
+        let dt = extract_datetime(value, arena).copied();
+        match (dt, value) {
+            (None, DataValue::String(s)) if other.is_some() => {
+                let dt = parse_datetime(s).ok()?;
+                arena.record_coercion(value, "datetime");
+                Some(dt)
+            }
+            _ => dt,
+        }
+    };
+
+    let left_dt = extract_datetime(left, arena);
+    let right_dt = extract_datetime(right, arena);
+    Some((coerce(left, right_dt)?, coerce(right, left_dt)?))
+}
+
 /// Helper function to extract a duration from a direct Duration value or an object with a "timestamp" key
 fn extract_duration<'a>(value: &'a DataValue<'a>, arena: &'a DataArena) -> Option<&'a Duration> {
     match value {
@@ -76,6 +104,32 @@ fn extract_duration<'a>(value: &'a DataValue<'a>, arena: &'a DataArena) -> Optio
     }
 }
 
+/// Extracts the durations of two operands compared with each other
+///
+/// A string operand compared with a duration is parsed as a duration, so that
+/// the time between two datetimes can be compared with a limit such as `"4h"`.
+fn duration_operands<'a>(
+    left: &'a DataValue<'a>,
+    right: &'a DataValue<'a>,
+    arena: &'a DataArena,
+) -> Option<(Duration, Duration)> {
+    let coerce = |value: &DataValue, other: Option<&Duration>| {
+        let dur = extract_duration(value, arena).copied();
+        match (dur, value) {
+            (None, DataValue::String(s)) if other.is_some() => {
+                let dur = parse_duration(s).ok()?;
+                arena.record_coercion(value, "duration");
+                Some(dur)
+            }
+            _ => dur,
+        }
+    };
+
+    let left_dur = extract_duration(left, arena);
+    let right_dur = extract_duration(right, arena);
+    Some((coerce(left, right_dur)?, coerce(right, left_dur)?))
+}
+
 /// Validate that sufficient arguments are provided for a comparison operation
 fn validate_arguments(args: &[&Token]) -> Result<()> {
     if args.len() < 2 {
@@ -118,21 +172,18 @@ fn values_are_equal<'a>(
         return Ok(true);
     }
 
-    // Try to extract datetime values
-    let left_dt = extract_datetime(left, arena);
-    let right_dt = extract_datetime(right, arena);
+    // In JavaScript, null is only loosely equal to null
+    if arena.semantics().is_js() && left.is_null() != right.is_null() {
+        return Ok(false);
+    }
 
     // If both values are datetimes, compare them
-    if let (Some(left_dt), Some(right_dt)) = (left_dt, right_dt) {
+    if let Some((left_dt, right_dt)) = datetime_operands(left, right, arena) {
         return Ok(left_dt == right_dt);
     }
 
-    // Try to extract duration values
-    let left_dur = extract_duration(left, arena);
-    let right_dur = extract_duration(right, arena);
-
     // If both values are durations, compare them
-    if let (Some(left_dur), Some(right_dur)) = (left_dur, right_dur) {
+    if let Some((left_dur, right_dur)) = duration_operands(left, right, arena) {
         return Ok(left_dur == right_dur);
     }
 
@@ -144,42 +195,46 @@ fn values_are_equal<'a>(
         (DataValue::Number(_), DataValue::String(s)) => {
             // Try to parse the string as a number
             if let Ok(num) = s.parse::<f64>() {
+                arena.record_coercion(right, "number");
                 let left_num = left.coerce_to_number().unwrap();
                 Ok(left_num.as_f64() == num)
             } else {
                 // String is not a valid number
-                Err(LogicError::NaNError)
+                incomparable(arena)
             }
         }
         (DataValue::String(s), DataValue::Number(_)) => {
             // Try to parse the string as a number
             if let Ok(num) = s.parse::<f64>() {
+                arena.record_coercion(left, "number");
                 let right_num = right.coerce_to_number().unwrap();
                 Ok(num == right_num.as_f64())
             } else {
                 // String is not a valid number
-                Err(LogicError::NaNError)
+                incomparable(arena)
             }
         }
         (DataValue::Array(_), DataValue::Array(_)) => {
             // Arrays should be compared by reference, not by value
-            Err(LogicError::NaNError)
+            incomparable(arena)
         }
         (DataValue::Array(_), _) | (_, DataValue::Array(_)) => {
             // Arrays can't be compared with non-arrays
-            Err(LogicError::NaNError)
+            incomparable(arena)
         }
         (DataValue::Object(_), _) | (_, DataValue::Object(_)) => {
             // Objects can't be compared with anything else
             // But we already handled the case where both are datetime objects above
-            Err(LogicError::NaNError)
+            incomparable(arena)
         }
         _ => {
             // Try numeric coercion for other cases
             if let (Some(a), Some(b)) = (left.coerce_to_number(), right.coerce_to_number()) {
+                record_operand_coercions(left, right, "number", arena);
                 Ok(a.as_f64() == b.as_f64())
             } else {
                 // If numeric coercion fails, fall back to string comparison
+                record_operand_coercions(left, right, "string", arena);
                 let left_str = left.coerce_to_string(arena);
                 let right_str = right.coerce_to_string(arena);
 
@@ -193,11 +248,110 @@ fn values_are_equal<'a>(
     }
 }
 
+/// Checks if two values are loosely equal, as with `==`.
+///
+/// Values that cannot be compared are not equal.
+pub(crate) fn loose_equals<'a>(
+    left: &'a DataValue<'a>,
+    right: &'a DataValue<'a>,
+    arena: &'a DataArena,
+) -> bool {
+    values_are_equal(left, right, arena).unwrap_or(false)
+}
+
+/// Result of a loose comparison of values that cannot be compared as numbers.
+///
+/// An error, except under json-logic-js semantics where JavaScript compares
+/// against `NaN`, which is never equal, greater or less.
+fn incomparable(arena: &DataArena) -> Result<bool> {
+    if arena.semantics().is_js() {
+        Ok(false)
+    } else {
+        Err(LogicError::NaNError)
+    }
+}
+
+/// Records the operands of a loose comparison that are converted to a type.
+fn record_operand_coercions(
+    left: &DataValue,
+    right: &DataValue,
+    to: &'static str,
+    arena: &DataArena,
+) {
+    arena.record_coercion(left, to);
+    arena.record_coercion(right, to);
+}
+
 /// Helper for strict equality comparison between two values
 fn values_are_strict_equal<'a>(left: &'a DataValue<'a>, right: &'a DataValue<'a>) -> Result<bool> {
     Ok(left.strict_equals(right))
 }
 
+/// Structural equality of two values, as used by `deep_equal`.
+///
+/// - Values of different types are never equal; nothing is coerced.
+/// - Numbers are equal when they have the same numeric value, so `1` equals
+///   `1.0`. An integer only equals a float that represents it exactly, and
+///   `NaN` equals nothing.
+/// - Arrays are equal when they have the same length and their items are
+///   equal in order.
+/// - Objects are equal when they have the same keys with equal values, in
+///   any order. Of a key repeated in an object, the last value counts, as
+///   when the object is read from JSON.
+/// - Strings, booleans, null, datetimes and durations are equal when they
+///   hold the same value, and host objects when they are the same object.
+fn values_are_deep_equal(left: &DataValue, right: &DataValue) -> bool {
+    match (left, right) {
+        (DataValue::Null, DataValue::Null) => true,
+        (DataValue::Bool(a), DataValue::Bool(b)) => a == b,
+        (DataValue::String(a), DataValue::String(b)) => a == b,
+        (DataValue::Number(a), DataValue::Number(b)) => numbers_are_deep_equal(*a, *b),
+        (DataValue::DateTime(a), DataValue::DateTime(b)) => a == b,
+        (DataValue::Duration(a), DataValue::Duration(b)) => a == b,
+        (DataValue::Custom(a), DataValue::Custom(b)) => a == b,
+        (DataValue::Array(a), DataValue::Array(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(a_item, b_item)| values_are_deep_equal(a_item, b_item))
+        }
+        (DataValue::Object(a), DataValue::Object(b)) => {
+            a.iter()
+                .all(|(key, _)| match (last_value(a, key), last_value(b, key)) {
+                    (Some(value), Some(other)) => values_are_deep_equal(value, other),
+                    _ => false,
+                })
+                && b.iter().all(|(key, _)| last_value(a, key).is_some())
+        }
+        _ => false,
+    }
+}
+
+/// Returns the last value of a key in the entries of an object.
+fn last_value<'v, 'a>(
+    entries: &'v [(&'a str, DataValue<'a>)],
+    key: &str,
+) -> Option<&'v DataValue<'a>> {
+    entries
+        .iter()
+        .rev()
+        .find(|(entry_key, _)| *entry_key == key)
+        .map(|(_, value)| value)
+}
+
+/// Compares two numbers by their exact numeric value.
+fn numbers_are_deep_equal(left: NumberValue, right: NumberValue) -> bool {
+    match (left, right) {
+        (NumberValue::Integer(a), NumberValue::Integer(b)) => a == b,
+        (NumberValue::Float(a), NumberValue::Float(b)) => a == b,
+        (NumberValue::Integer(i), NumberValue::Float(f))
+        | (NumberValue::Float(f), NumberValue::Integer(i)) => {
+            // i64::MAX as f64 rounds up to 2^63, which is out of range
+            f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 && f as i64 == i
+        }
+    }
+}
+
 /// Helper for not-equal comparison between two values with type coercion
 fn values_are_not_equal<'a>(
     left: &'a DataValue<'a>,
@@ -221,21 +375,13 @@ fn value_is_greater_than<'a>(
     right: &'a DataValue<'a>,
     arena: &'a DataArena,
 ) -> Result<bool> {
-    // Try to extract datetime values
-    let left_dt = extract_datetime(left, arena);
-    let right_dt = extract_datetime(right, arena);
-
     // If both values are datetimes, compare them
-    if let (Some(left_dt), Some(right_dt)) = (left_dt, right_dt) {
+    if let Some((left_dt, right_dt)) = datetime_operands(left, right, arena) {
         return Ok(left_dt > right_dt);
     }
 
-    // Try to extract duration values
-    let left_dur = extract_duration(left, arena);
-    let right_dur = extract_duration(right, arena);
-
     // If both values are durations, compare them
-    if let (Some(left_dur), Some(right_dur)) = (left_dur, right_dur) {
+    if let Some((left_dur, right_dur)) = duration_operands(left, right, arena) {
         return Ok(left_dur > right_dur);
     }
 
@@ -245,8 +391,12 @@ fn value_is_greater_than<'a>(
         (DataValue::Bool(a), DataValue::Bool(b)) => Ok(a > b),
         (DataValue::Null, DataValue::Null) => Ok(false),
         _ => {
-            let left_num = left.coerce_to_number().ok_or(LogicError::NaNError)?;
-            let right_num = right.coerce_to_number().ok_or(LogicError::NaNError)?;
+            let (Some(left_num), Some(right_num)) =
+                (left.coerce_to_number(), right.coerce_to_number())
+            else {
+                return incomparable(arena);
+            };
+            record_operand_coercions(left, right, "number", arena);
             Ok(left_num.as_f64() > right_num.as_f64())
         }
     }
@@ -258,21 +408,13 @@ fn value_is_greater_than_or_equal<'a>(
     right: &'a DataValue<'a>,
     arena: &'a DataArena,
 ) -> Result<bool> {
-    // Try to extract datetime values
-    let left_dt = extract_datetime(left, arena);
-    let right_dt = extract_datetime(right, arena);
-
     // If both values are datetimes, compare them
-    if let (Some(left_dt), Some(right_dt)) = (left_dt, right_dt) {
+    if let Some((left_dt, right_dt)) = datetime_operands(left, right, arena) {
         return Ok(left_dt >= right_dt);
     }
 
-    // Try to extract duration values
-    let left_dur = extract_duration(left, arena);
-    let right_dur = extract_duration(right, arena);
-
     // If both values are durations, compare them
-    if let (Some(left_dur), Some(right_dur)) = (left_dur, right_dur) {
+    if let Some((left_dur, right_dur)) = duration_operands(left, right, arena) {
         return Ok(left_dur >= right_dur);
     }
 
@@ -282,8 +424,12 @@ fn value_is_greater_than_or_equal<'a>(
         (DataValue::Bool(a), DataValue::Bool(b)) => Ok(a >= b),
         (DataValue::Null, DataValue::Null) => Ok(true),
         _ => {
-            let left_num = left.coerce_to_number().ok_or(LogicError::NaNError)?;
-            let right_num = right.coerce_to_number().ok_or(LogicError::NaNError)?;
+            let (Some(left_num), Some(right_num)) =
+                (left.coerce_to_number(), right.coerce_to_number())
+            else {
+                return incomparable(arena);
+            };
+            record_operand_coercions(left, right, "number", arena);
             Ok(left_num.as_f64() >= right_num.as_f64())
         }
     }
@@ -295,21 +441,13 @@ fn value_is_less_than<'a>(
     right: &'a DataValue<'a>,
     arena: &'a DataArena,
 ) -> Result<bool> {
-    // Try to extract datetime values
-    let left_dt = extract_datetime(left, arena);
-    let right_dt = extract_datetime(right, arena);
-
     // If both values are datetimes, compare them
-    if let (Some(left_dt), Some(right_dt)) = (left_dt, right_dt) {
+    if let Some((left_dt, right_dt)) = datetime_operands(left, right, arena) {
         return Ok(left_dt < right_dt);
     }
 
-    // Try to extract duration values
-    let left_dur = extract_duration(left, arena);
-    let right_dur = extract_duration(right, arena);
-
     // If both values are durations, compare them
-    if let (Some(left_dur), Some(right_dur)) = (left_dur, right_dur) {
+    if let Some((left_dur, right_dur)) = duration_operands(left, right, arena) {
         return Ok(left_dur < right_dur);
     }
 
@@ -319,8 +457,12 @@ fn value_is_less_than<'a>(
         (DataValue::Bool(a), DataValue::Bool(b)) => Ok(a < b),
         (DataValue::Null, DataValue::Null) => Ok(false),
         _ => {
-            let left_num = left.coerce_to_number().ok_or(LogicError::NaNError)?;
-            let right_num = right.coerce_to_number().ok_or(LogicError::NaNError)?;
+            let (Some(left_num), Some(right_num)) =
+                (left.coerce_to_number(), right.coerce_to_number())
+            else {
+                return incomparable(arena);
+            };
+            record_operand_coercions(left, right, "number", arena);
             Ok(left_num.as_f64() < right_num.as_f64())
         }
     }
@@ -332,21 +474,13 @@ fn value_is_less_than_or_equal<'a>(
     right: &'a DataValue<'a>,
     arena: &'a DataArena,
 ) -> Result<bool> {
-    // Try to extract datetime values
-    let left_dt = extract_datetime(left, arena);
-    let right_dt = extract_datetime(right, arena);
-
     // If both values are datetimes, compare them
-    if let (Some(left_dt), Some(right_dt)) = (left_dt, right_dt) {
+    if let Some((left_dt, right_dt)) = datetime_operands(left, right, arena) {
         return Ok(left_dt <= right_dt);
     }
 
-    // Try to extract duration values
-    let left_dur = extract_duration(left, arena);
-    let right_dur = extract_duration(right, arena);
-
     // If both values are durations, compare them
-    if let (Some(left_dur), Some(right_dur)) = (left_dur, right_dur) {
+    if let Some((left_dur, right_dur)) = duration_operands(left, right, arena) {
         return Ok(left_dur <= right_dur);
     }
 
@@ -356,8 +490,12 @@ fn value_is_less_than_or_equal<'a>(
         (DataValue::Bool(a), DataValue::Bool(b)) => Ok(a <= b),
         (DataValue::Null, DataValue::Null) => Ok(true),
         _ => {
-            let left_num = left.coerce_to_number().ok_or(LogicError::NaNError)?;
-            let right_num = right.coerce_to_number().ok_or(LogicError::NaNError)?;
+            let (Some(left_num), Some(right_num)) =
+                (left.coerce_to_number(), right.coerce_to_number())
+            else {
+                return incomparable(arena);
+            };
+            record_operand_coercions(left, right, "number", arena);
             Ok(left_num.as_f64() <= right_num.as_f64())
         }
     }
@@ -403,6 +541,26 @@ pub fn eval_strict_not_equal<'a>(
     })
 }
 
+/// Evaluates a structural comparison of exactly two values.
+///
+/// See [`values_are_deep_equal`] for the rules.
+pub fn eval_deep_equal<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let left = evaluate(args[0], arena)?;
+    let right = evaluate(args[1], arena)?;
+    if values_are_deep_equal(left, right) {
+        Ok(arena.true_value())
+    } else {
+        Ok(arena.false_value())
+    }
+}
+
 /// Evaluates a greater-than comparison.
 pub fn eval_greater_than<'a>(
     args: &'a [&'a Token<'a>],
@@ -446,6 +604,7 @@ pub fn eval_less_than_or_equal<'a>(
 #[cfg(test)]
 mod tests {
     use crate::logic::datalogic_core::DataLogicCore;
+    use crate::logic::error::LogicError;
     use crate::logic::operators::comparison::ComparisonOp;
     use crate::logic::token::{OperatorType, Token};
     use crate::logic::Logic;
@@ -716,6 +875,47 @@ mod tests {
         assert_eq!(result, json!(false));
     }
 
+    #[test]
+    fn test_deep_equal_rules() {
+        use super::values_are_deep_equal;
+        use crate::arena::DataArena;
+
+        let arena = DataArena::new();
+
+        // Numbers compare by exact value
+        assert!(values_are_deep_equal(
+            &DataValue::integer(1),
+            &DataValue::float(1.0)
+        ));
+        assert!(!values_are_deep_equal(
+            &DataValue::integer(9007199254740993),
+            &DataValue::float(9007199254740992.0)
+        ));
+        assert!(!values_are_deep_equal(
+            &DataValue::integer(i64::MAX),
+            &DataValue::float(i64::MAX as f64)
+        ));
+        assert!(!values_are_deep_equal(
+            &DataValue::float(f64::NAN),
+            &DataValue::float(f64::NAN)
+        ));
+
+        // The last value of a repeated key counts
+        let repeated = DataValue::object(
+            &arena,
+            &[("x", DataValue::integer(1)), ("x", DataValue::integer(2))],
+        );
+        let single = DataValue::object(&arena, &[("x", DataValue::integer(2))]);
+        assert!(values_are_deep_equal(&repeated, &single));
+        assert!(values_are_deep_equal(&single, &repeated));
+
+        // Host objects are equal to themselves only
+        let first = arena.alloc_host_object(1u8);
+        let second = arena.alloc_host_object(1u8);
+        assert!(values_are_deep_equal(first, first));
+        assert!(!values_are_deep_equal(first, second));
+    }
+
     #[test]
     fn test_greater_than() {
         let core = DataLogicCore::new();
@@ -874,4 +1074,54 @@ mod tests {
         let result = core.apply(&rule, &data_json).unwrap();
         assert_eq!(result, json!(true));
     }
+
+    #[test]
+    fn test_datetime_string_comparison() {
+        let dl = crate::DataLogic::new();
+        let data = json!({"expires_at": "2999-01-01T00:00:00Z", "note": "soon"});
+
+        let rule = json!({">": [{"var": "expires_at"}, {"now": []}]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(true));
+
+        let rule = json!({"!=": [{"now": []}, {"var": "expires_at"}]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(true));
+
+        // Strings are only parsed when compared with a datetime
+        let rule = json!({"<": ["2024-01-01T10:00:00+02:00", "2024-01-01T09:00:00Z"]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(false));
+
+        let rule = json!({"<": [{"var": "note"}, {"now": []}]});
+        assert_eq!(
+            dl.evaluate_json(&rule, &data, None),
+            Err(LogicError::NaNError)
+        );
+    }
+
+    #[test]
+    fn test_duration_string_comparison() {
+        let dl = crate::DataLogic::new();
+        let data = json!({
+            "created_at": "2024-01-15T09:00:00Z",
+            "resolved_at": "2024-01-15T12:30:00Z"
+        });
+
+        // Resolved within a four hour SLA
+        let rule = json!({"<=": [
+            {"-": [{"var": "resolved_at"}, {"datetime": {"var": "created_at"}}]},
+            "4h"
+        ]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(true));
+
+        let rule = json!({">": [{"duration": "1d"}, "PT23H"]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(true));
+        let rule = json!({"==": ["90m", {"duration": "1h:30m"}]});
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(true));
+
+        // Strings are only parsed when compared with a duration
+        let rule = json!({"<": [{"duration": "1h"}, "later"]});
+        assert_eq!(
+            dl.evaluate_json(&rule, &data, None),
+            Err(LogicError::NaNError)
+        );
+    }
 }
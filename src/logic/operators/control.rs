@@ -145,6 +145,34 @@ mod tests {
     use crate::value::DataValue;
     use serde_json::json;
 
+    #[test]
+    fn test_if_skips_untaken_branches() {
+        let mut dl = crate::DataLogic::new();
+        let rule = json!({"if": [
+            {"var": "a"}, {"throw": "a"},
+            {"var": "b"}, {"length": null},
+            {"var": "c"}, "c",
+            {"throw": "after"}
+        ]});
+        let data = json!({"a": false, "b": 0, "c": true});
+
+        // The interpreted, optimized and compiled rules all skip the branches
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!("c"));
+        let divergences = dl
+            .fuzz(
+                &rule,
+                &mut crate::datagen::DataGenerator::for_rule(&rule).with_seed(7),
+                50,
+            )
+            .unwrap();
+        assert!(divergences.is_empty());
+
+        let compiled = dl.compile(&rule.to_string(), None).unwrap();
+        let data = dl.parse_data_json(&data).unwrap();
+        let result = dl.evaluate_compiled(&compiled, &data).unwrap();
+        assert_eq!(result.as_str(), Some("c"));
+    }
+
     #[test]
     fn test_and() {
         // Create DataLogic instance with arena
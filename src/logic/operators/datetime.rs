@@ -2,11 +2,15 @@
 //!
 //! This module provides operators for working with datetime and duration values.
 
-use chrono::Utc;
+use chrono::{Datelike, FixedOffset, NaiveDate, NaiveDateTime, Utc};
 
 use crate::arena::DataArena;
 use crate::logic::error::{LogicError, Result};
-use crate::value::{date_diff, parse_datetime, parse_duration, DataValue};
+use crate::logic::operators::schedule::parse_offset;
+use crate::value::{
+    date_add, date_diff, format_iso8601_duration, humanize_duration, parse_datetime,
+    parse_duration, DataValue,
+};
 
 /// Enumeration of datetime operators.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,6 +19,8 @@ pub enum DateTimeOp {
     DateTime,
     /// Duration/timestamp conversion
     Timestamp,
+    /// Duration literal, such as `{"duration": "1d:2h:3m:4s"}`
+    Duration,
     /// Current date and time
     Now,
     /// Parse a date string with a format
@@ -23,6 +29,18 @@ pub enum DateTimeOp {
     FormatDate,
     /// Calculate difference between two dates
     DateDiff,
+    /// Add an amount of time to a date
+    DateAdd,
+    /// Check whether a date falls inside a recurring schedule
+    InSchedule,
+    /// Age in whole years
+    AgeYears,
+    /// Check whether a date is an anniversary of another
+    IsAnniversary,
+    /// Format a duration as a short human-readable string
+    HumanizeDuration,
+    /// Format a duration as an ISO8601 duration string
+    IsoDuration,
 }
 
 /// Validates that exactly n arguments are provided
@@ -45,8 +63,333 @@ fn convert_format_to_chrono(format: &str) -> String {
         .replace("ss", "%S")
 }
 
+/// English month names, used as the parsing target for localized names.
+const ENGLISH_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Localized month names by language code.
+const LOCALE_MONTHS: &[(&str, [&str; 12])] = &[
+    (
+        "de",
+        [
+            "januar",
+            "februar",
+            "märz",
+            "april",
+            "mai",
+            "juni",
+            "juli",
+            "august",
+            "september",
+            "oktober",
+            "november",
+            "dezember",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+    ),
+    (
+        "es",
+        [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+    ),
+    (
+        "it",
+        [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+    ),
+    (
+        "pt",
+        [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+    ),
+    (
+        "nl",
+        [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+    ),
+];
+
+/// Looks up a localized month name or abbreviation and returns the matching
+/// English name, abbreviated if the input was.
+///
+/// Abbreviations must be at least three characters long and identify a single
+/// month, e.g. "févr." or "Okt".
+fn find_localized_month(word: &str, months: &[&str; 12]) -> Option<&'static str> {
+    let word = word.trim_end_matches('.').to_lowercase();
+    if let Some(index) = months.iter().position(|month| *month == word) {
+        return Some(ENGLISH_MONTHS[index]);
+    }
+    if word.chars().count() < 3 {
+        return None;
+    }
+
+    let mut candidates = months
+        .iter()
+        .enumerate()
+        .filter(|(_, month)| month.starts_with(&word));
+    match (candidates.next(), candidates.next()) {
+        (Some((index, _)), None) => Some(&ENGLISH_MONTHS[index][..3]),
+        _ => None,
+    }
+}
+
+/// Returns the lowercase language code of a locale such as `de-CH`.
+fn locale_language(locale: &str) -> String {
+    locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Returns the name of a month, numbered from 1, in a locale.
+///
+/// Returns `None` if the locale is not supported.
+fn localized_month_name(month: u32, locale: &str) -> Option<String> {
+    let index = month as usize - 1;
+    let language = locale_language(locale);
+    if language == "en" {
+        return Some(ENGLISH_MONTHS[index].to_string());
+    }
+    let (_, months) = LOCALE_MONTHS.iter().find(|(code, _)| *code == language)?;
+
+    // German nouns are capitalized
+    let name = months[index];
+    if language == "de" {
+        let mut chars = name.chars();
+        let first = chars.next().unwrap_or_default();
+        return Some(first.to_uppercase().chain(chars).collect());
+    }
+    Some(name.to_string())
+}
+
+/// Returns the UTC offset of the time zone of the evaluation context at an
+/// instant, or UTC.
+pub(crate) fn context_offset_at(arena: &DataArena, dt: &chrono::DateTime<Utc>) -> FixedOffset {
+    TimeZone::of_context(arena).offset_at(dt)
+}
+
+/// A time zone given to a date operator
+#[derive(Debug, Clone, Copy)]
+enum TimeZone {
+    /// A fixed UTC offset, such as `+02:00` or `UTC`
+    Fixed(FixedOffset),
+    /// An IANA time zone, such as `Europe/Berlin`, following its daylight
+    /// saving time rules
+    #[cfg(feature = "tz")]
+    Named(chrono_tz::Tz),
+}
+
+impl TimeZone {
+    /// Parses an IANA time zone name, with the `tz` feature, or a UTC offset.
+    fn parse(name: &str) -> Option<Self> {
+        #[cfg(feature = "tz")]
+        if let Ok(tz) = name.parse::<chrono_tz::Tz>() {
+            return Some(TimeZone::Named(tz));
+        }
+        parse_offset(name).map(TimeZone::Fixed)
+    }
+
+    /// Returns the time zone named by an argument, or that of the evaluation
+    /// context without one.
+    fn from_argument(arg: Option<&DataValue>, arena: &DataArena) -> Result<Self> {
+        match arg {
+            Some(DataValue::String(name)) => {
+                Self::parse(name).ok_or(LogicError::InvalidArgumentsError)
+            }
+            Some(_) => Err(LogicError::InvalidArgumentsError),
+            None => Ok(Self::of_context(arena)),
+        }
+    }
+
+    /// Returns the time zone of the evaluation context, or UTC.
+    fn of_context(arena: &DataArena) -> Self {
+        let context = arena.evaluation_context();
+        #[cfg(feature = "tz")]
+        if let Some(tz) = context
+            .as_ref()
+            .and_then(|context| context.named_timezone())
+        {
+            return TimeZone::Named(tz);
+        }
+        let offset = context.and_then(|context| context.timezone());
+        TimeZone::Fixed(offset.unwrap_or_else(|| FixedOffset::east_opt(0).unwrap()))
+    }
+
+    /// Returns the UTC offset of the time zone at an instant.
+    #[cfg_attr(not(feature = "tz"), allow(unused_variables))]
+    fn offset_at(self, dt: &chrono::DateTime<Utc>) -> FixedOffset {
+        match self {
+            TimeZone::Fixed(offset) => offset,
+            #[cfg(feature = "tz")]
+            TimeZone::Named(tz) => {
+                use chrono::{Offset, TimeZone as _};
+                tz.offset_from_utc_datetime(&dt.naive_utc()).fix()
+            }
+        }
+    }
+
+    /// Formats a datetime in the time zone, with `%B` month names in the
+    /// locale if it is supported.
+    fn format(self, dt: &chrono::DateTime<Utc>, format: &str, locale: Option<&str>) -> String {
+        match self {
+            TimeZone::Fixed(offset) => format_local(dt.with_timezone(&offset), format, locale),
+            #[cfg(feature = "tz")]
+            TimeZone::Named(tz) => format_local(dt.with_timezone(&tz), format, locale),
+        }
+    }
+
+    /// Converts a local date and time of the time zone to UTC.
+    ///
+    /// A time repeated when clocks go back is read as the earlier of the two
+    /// instants. A time skipped when clocks go forward does not exist.
+    fn to_utc(self, naive: NaiveDateTime) -> Option<chrono::DateTime<Utc>> {
+        match self {
+            TimeZone::Fixed(offset) => naive
+                .and_local_timezone(offset)
+                .earliest()
+                .map(|dt| dt.with_timezone(&Utc)),
+            #[cfg(feature = "tz")]
+            TimeZone::Named(tz) => naive
+                .and_local_timezone(tz)
+                .earliest()
+                .map(|dt| dt.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Formats a local datetime, with `%B` month names in the locale if it is
+/// supported.
+fn format_local<Tz: chrono::TimeZone>(
+    dt: chrono::DateTime<Tz>,
+    format: &str,
+    locale: Option<&str>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    // Names in unsupported locales are left in English
+    let month = locale.and_then(|locale| localized_month_name(dt.month(), locale));
+    match month {
+        Some(month) => dt.format(&format.replace("%B", &month)).to_string(),
+        None => dt.format(format).to_string(),
+    }
+}
+
+/// Replaces localized month names in a date string with their English names.
+///
+/// Returns `None` if the locale is not supported.
+fn translate_month_names(date_str: &str, locale: &str) -> Option<String> {
+    let language = locale_language(locale);
+    if language == "en" {
+        return Some(date_str.to_string());
+    }
+    let (_, months) = LOCALE_MONTHS.iter().find(|(code, _)| *code == language)?;
+
+    let mut result = String::with_capacity(date_str.len());
+    let mut rest = date_str;
+    while let Some(start) = rest.find(char::is_alphabetic) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // A word extends over letters and a trailing abbreviation dot
+        let mut end = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        if rest[end..].starts_with('.') {
+            end += 1;
+        }
+
+        let word = &rest[..end];
+        match find_localized_month(word, months) {
+            Some(month) => result.push_str(month),
+            None => result.push_str(word),
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+
+    Some(result)
+}
+
 /// Extracts a datetime from a value, handling both direct and wrapped forms
-fn extract_datetime<'a>(
+pub(crate) fn extract_datetime<'a>(
     value: &'a DataValue<'a>,
     arena: &'a DataArena,
 ) -> Result<&'a chrono::DateTime<Utc>> {
@@ -74,7 +417,56 @@ fn extract_datetime<'a>(
     }
 }
 
+/// Extracts a duration from a value, parsing duration strings.
+fn extract_duration(value: &DataValue) -> Result<chrono::Duration> {
+    match value {
+        DataValue::Duration(duration) => Ok(*duration),
+        DataValue::String(s) => parse_duration(s).map_err(|_| LogicError::InvalidArgumentsError),
+        _ => Err(LogicError::InvalidArgumentsError),
+    }
+}
+
+/// Formats a duration as a short human-readable string like "2d 3h".
+///
+/// An optional second argument limits the number of units shown.
+pub fn eval_humanize_duration<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let duration = extract_duration(&args[0])?;
+    let max_units = match args.get(1) {
+        Some(value) => match value.as_i64() {
+            Some(units) if units > 0 => Some(units as usize),
+            _ => return Err(LogicError::InvalidArgumentsError),
+        },
+        None => None,
+    };
+
+    let formatted = humanize_duration(&duration, max_units);
+    Ok(arena.alloc(DataValue::String(arena.alloc_str(&formatted))))
+}
+
+/// Formats a duration as an ISO8601 duration string like "P2DT3H".
+pub fn eval_iso_duration<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    validate_argument_count(args, 1)?;
+
+    let duration = extract_duration(&args[0])?;
+    let formatted = format_iso8601_duration(&duration);
+    Ok(arena.alloc(DataValue::String(arena.alloc_str(&formatted))))
+}
+
 /// Creates a duration value from a string.
+///
+/// This is the `duration` operator, also known as `timestamp`. It accepts
+/// durations such as `"1d:2h:3m:4s"` or `"4h"` and ISO8601 durations such as
+/// `"PT4H"`.
 pub fn eval_timestamp_operator<'a>(
     args: &'a [DataValue<'a>],
     arena: &'a DataArena,
@@ -104,11 +496,17 @@ pub fn eval_now(arena: &DataArena) -> Result<&DataValue<'_>> {
 }
 
 /// Formats a date according to the specified format string.
+///
+/// The date is shown in the time zone of the optional third argument, such as
+/// `{"format_date": [date, "%Y-%m-%d", "Europe/Berlin"]}`, or else in that of
+/// the evaluation context, and `%B` month names in the locale of the context.
+/// Named time zones require the `tz` feature; UTC offsets such as `+02:00`
+/// are always accepted.
 pub fn eval_format_date<'a>(
     args: &'a [DataValue<'a>],
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
-    if args.len() < 2 {
+    if args.len() != 2 && args.len() != 3 {
         return Err(LogicError::InvalidArgumentsError);
     }
 
@@ -124,22 +522,41 @@ pub fn eval_format_date<'a>(
     // Convert from human-readable format to chrono format
     let chrono_format = convert_format_to_chrono(format_str);
 
-    // Format the datetime
-    let formatted = dt.format(&chrono_format).to_string();
-
-    // Note: Removed special case handling for yyyy-MM-dd format
-    // All format_date operations should return strings, not DateTime objects
+    let zone = TimeZone::from_argument(args.get(2), arena)?;
+    let context = arena.evaluation_context();
+    let locale = context.as_ref().and_then(|context| context.locale());
+    let formatted = zone.format(dt, &chrono_format, locale);
 
     // Return the formatted string
     Ok(arena.alloc(DataValue::String(arena.alloc_str(&formatted))))
 }
 
 /// Parses a string into a date using a specified format.
+///
+/// The format accepts both the `yyyy-MM-dd` style and strptime-like
+/// specifiers such as `%d/%m/%Y`. An optional third argument names the locale
+/// of month names in the input, e.g. `{"parse_date": ["31 décembre 2024",
+/// "%d %B %Y", "fr"]}`, and defaults to the locale of the evaluation context.
+///
+/// Inputs with a UTC offset (`%z`) are converted to UTC. Other inputs are
+/// read in the time zone of the evaluation context, or in a time zone given
+/// as the third argument instead of the locale, or as a fourth argument after
+/// it, e.g. `{"parse_date": ["2024-03-31 09:00", "%Y-%m-%d %H:%M",
+/// "Europe/Berlin"]}`. Named time zones require the `tz` feature; UTC offsets
+/// such as `+02:00` are always accepted.
 pub fn eval_parse_date<'a>(
     args: &'a [DataValue<'a>],
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
-    validate_argument_count(args, 2)?;
+    // A third argument naming a time zone takes the place of the locale
+    let (locale, zone) = match args {
+        [_, _] => (None, None),
+        [_, _, DataValue::String(name)] if TimeZone::parse(name).is_some() => (None, args.get(2)),
+        [_, _, locale] => (Some(locale), None),
+        [_, _, locale, zone] => (Some(locale), Some(zone)),
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+    let zone = TimeZone::from_argument(zone, arena)?;
 
     let date_str = match &args[0] {
         DataValue::String(s) => s,
@@ -151,23 +568,52 @@ pub fn eval_parse_date<'a>(
         _ => return Err(LogicError::InvalidArgumentsError),
     };
 
-    // Convert from our custom format to chrono's format
-    let chrono_format = convert_format_to_chrono(format_str);
-
-    // Use the non-deprecated method
-    match chrono::NaiveDateTime::parse_from_str(date_str, &chrono_format).map(|dt| dt.and_utc()) {
-        Ok(dt) => Ok(arena.alloc(DataValue::datetime(dt))),
-        Err(_) => {
-            // Try as date only
-            match chrono::NaiveDate::parse_from_str(date_str, &chrono_format) {
-                Ok(date) => {
-                    let dt = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
-                    Ok(arena.alloc(DataValue::datetime(dt)))
+    // Without a locale argument, month names are read in the locale of the
+    // evaluation context, unless it is not supported
+    let translated;
+    let date_str = match locale {
+        Some(DataValue::String(locale)) => {
+            translated =
+                translate_month_names(date_str, locale).ok_or(LogicError::InvalidArgumentsError)?;
+            translated.as_str()
+        }
+        Some(_) => return Err(LogicError::InvalidArgumentsError),
+        None => {
+            let context = arena.evaluation_context();
+            match context
+                .as_ref()
+                .and_then(|context| context.locale())
+                .and_then(|locale| translate_month_names(date_str, locale))
+            {
+                Some(month_names) => {
+                    translated = month_names;
+                    translated.as_str()
                 }
-                Err(_) => Err(LogicError::InvalidArgumentsError),
+                None => date_str,
             }
         }
+    };
+
+    // Convert from our custom format to chrono's format
+    let chrono_format = convert_format_to_chrono(format_str);
+
+    // Inputs carrying a UTC offset are normalized to UTC
+    if let Ok(dt) = chrono::DateTime::parse_from_str(date_str, &chrono_format) {
+        return Ok(arena.alloc(DataValue::datetime(dt.with_timezone(&Utc))));
     }
+
+    // Other inputs are in the given time zone or that of the evaluation context
+    let naive = NaiveDateTime::parse_from_str(date_str, &chrono_format)
+        .or_else(|_| {
+            // Try as date only
+            NaiveDate::parse_from_str(date_str, &chrono_format)
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+        })
+        .map_err(|_| LogicError::InvalidArgumentsError)?;
+    let dt = zone
+        .to_utc(naive)
+        .ok_or(LogicError::InvalidArgumentsError)?;
+    Ok(arena.alloc(DataValue::datetime(dt)))
 }
 
 /// Calculates the difference between two dates.
@@ -190,6 +636,103 @@ pub fn eval_date_diff<'a>(
     Ok(arena.alloc(DataValue::integer(diff)))
 }
 
+/// Adds an amount of time to a date.
+///
+/// `{"date_add": [date, amount, unit]}` adds a whole number of units, with
+/// the units of `date_diff`; a negative amount subtracts. Months and years
+/// are calendar units, clamped to the end of shorter months.
+/// `{"date_add": [date, duration]}` adds a duration value or string.
+pub fn eval_date_add<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let dt = match args {
+        [date, duration] => {
+            let duration = extract_duration(duration)?;
+            extract_datetime(date, arena)?.checked_add_signed(duration)
+        }
+        [date, amount, DataValue::String(unit)] => {
+            let amount = amount.as_i64().ok_or(LogicError::InvalidArgumentsError)?;
+            date_add(extract_datetime(date, arena)?, amount, unit)
+        }
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    let dt = dt.ok_or(LogicError::InvalidArgumentsError)?;
+    Ok(arena.alloc(DataValue::datetime(dt)))
+}
+
+/// Extracts the calendar dates of `[date, asof, zone?]` arguments.
+///
+/// Both datetimes are converted to the local date of the optional time zone,
+/// or of the time zone of the evaluation context, before comparing calendar
+/// fields.
+fn local_dates<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<(NaiveDate, NaiveDate)> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let zone = TimeZone::from_argument(args.get(2), arena)?;
+    let local_date =
+        |dt: chrono::DateTime<Utc>| dt.with_timezone(&zone.offset_at(&dt)).date_naive();
+    let date = local_date(*extract_datetime(&args[0], arena)?);
+    let asof = local_date(*extract_datetime(&args[1], arena)?);
+    Ok((date, asof))
+}
+
+/// Returns the anniversary of a date in a given year.
+///
+/// Anniversaries of February 29 fall on March 1 in non-leap years, both for
+/// `age_years` and `is_anniversary`.
+fn anniversary_in(date: NaiveDate, year: i32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, date.month(), date.day())
+        .or_else(|| NaiveDate::from_ymd_opt(year, 3, 1))
+}
+
+/// Calculates the age in whole years at a given date.
+///
+/// `{"age_years": [dob, asof]}` counts the birthdays reached on or before
+/// `asof`, and fails if `asof` is before `dob`. People born on February 29
+/// reach their birthday on March 1 in non-leap years. An optional third
+/// argument gives the time zone used to determine both calendar dates.
+pub fn eval_age_years<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let (dob, asof) = local_dates(args, arena)?;
+    if asof < dob {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let birthday = anniversary_in(dob, asof.year()).ok_or(LogicError::InvalidArgumentsError)?;
+    let mut years = asof.year() - dob.year();
+    if asof < birthday {
+        years -= 1;
+    }
+
+    Ok(arena.alloc(DataValue::integer(years as i64)))
+}
+
+/// Checks whether a date is an anniversary of another date.
+///
+/// `{"is_anniversary": [date, asof]}` is true if `asof` falls on the same
+/// month and day as `date`, in a later year. As for `age_years`,
+/// anniversaries of February 29 fall on March 1 in non-leap years. An
+/// optional third argument gives the time zone used to determine both
+/// calendar dates.
+pub fn eval_is_anniversary<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let (date, asof) = local_dates(args, arena)?;
+
+    let result = asof.year() > date.year() && anniversary_in(date, asof.year()) == Some(asof);
+    Ok(arena.alloc(DataValue::Bool(result)))
+}
+
 /// Creates a datetime directly from a string without requiring a format.
 pub fn eval_datetime_operator<'a>(
     args: &'a [DataValue<'a>],
@@ -324,6 +867,44 @@ mod tests {
         assert_eq!(result.as_i64().unwrap(), 1); // dt2 - dt1 = 1 day
     }
 
+    #[test]
+    fn test_eval_date_add() {
+        let arena = DataArena::new();
+        let dt = Utc.with_ymd_and_hms(2022, 7, 6, 0, 0, 0).unwrap();
+
+        let args = [
+            DataValue::datetime(dt),
+            DataValue::integer(-3),
+            DataValue::string(&arena, "days"),
+        ];
+        let result = eval_date_add(&args, &arena).unwrap();
+        assert_eq!(
+            result.as_datetime(),
+            Some(&Utc.with_ymd_and_hms(2022, 7, 3, 0, 0, 0).unwrap())
+        );
+
+        // A date string plus a duration string
+        let args = [
+            DataValue::string(&arena, "2022-07-06T00:00:00Z"),
+            DataValue::string(&arena, "1d:2h:0m:0s"),
+        ];
+        let result = eval_date_add(&args, &arena).unwrap();
+        assert_eq!(
+            result.as_datetime(),
+            Some(&Utc.with_ymd_and_hms(2022, 7, 7, 2, 0, 0).unwrap())
+        );
+
+        let args = [
+            DataValue::datetime(dt),
+            DataValue::integer(1),
+            DataValue::string(&arena, "fortnights"),
+        ];
+        assert!(eval_date_add(&args, &arena).is_err());
+
+        let args = [DataValue::datetime(dt), DataValue::integer(1)];
+        assert!(eval_date_add(&args, &arena).is_err());
+    }
+
     #[test]
     fn test_eval_datetime_operator() {
         let arena = DataArena::new();
@@ -356,4 +937,194 @@ mod tests {
         assert!(result.is_datetime());
         assert_eq!(result.as_datetime().unwrap(), &dt);
     }
+
+    #[test]
+    fn test_evaluation_context() {
+        use crate::context::EvaluationContext;
+
+        let arena = DataArena::new();
+        let dt = Utc.with_ymd_and_hms(2024, 2, 29, 23, 30, 0).unwrap();
+        let format_args = [
+            DataValue::datetime(dt),
+            DataValue::string(&arena, "%d %B %Y %H:%M"),
+        ];
+        let parse_args = [
+            DataValue::string(&arena, "1 März 2024 09:00"),
+            DataValue::string(&arena, "%d %B %Y %H:%M"),
+        ];
+        let age_args = [
+            DataValue::datetime(Utc.with_ymd_and_hms(2000, 3, 1, 0, 0, 0).unwrap()),
+            DataValue::datetime(dt),
+        ];
+
+        let result = eval_format_date(&format_args, &arena).unwrap();
+        assert_eq!(result.as_str(), Some("29 February 2024 23:30"));
+        assert!(eval_parse_date(&parse_args, &arena).is_err());
+        assert_eq!(
+            eval_age_years(&age_args, &arena).unwrap().as_i64(),
+            Some(23)
+        );
+
+        let context = EvaluationContext::new()
+            .with_locale("de-DE")
+            .with_timezone(FixedOffset::east_opt(3600).unwrap());
+        arena.set_evaluation_context(Some(context));
+
+        let result = eval_format_date(&format_args, &arena).unwrap();
+        assert_eq!(result.as_str(), Some("01 März 2024 00:30"));
+        let result = eval_parse_date(&parse_args, &arena).unwrap();
+        assert_eq!(
+            result.as_datetime(),
+            Some(&Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap())
+        );
+        assert_eq!(
+            eval_age_years(&age_args, &arena).unwrap().as_i64(),
+            Some(24)
+        );
+
+        // Unsupported locales of the context are ignored
+        arena.set_evaluation_context(Some(EvaluationContext::new().with_locale("xx")));
+        let result = eval_format_date(&format_args, &arena).unwrap();
+        assert_eq!(result.as_str(), Some("29 February 2024 23:30"));
+    }
+
+    #[test]
+    fn test_time_zone_arguments() {
+        let arena = DataArena::new();
+        let dt = Utc.with_ymd_and_hms(2024, 3, 31, 23, 30, 0).unwrap();
+
+        let args = [
+            DataValue::datetime(dt),
+            DataValue::string(&arena, "%Y-%m-%d %H:%M"),
+            DataValue::string(&arena, "+02:00"),
+        ];
+        let result = eval_format_date(&args, &arena).unwrap();
+        assert_eq!(result.as_str(), Some("2024-04-01 01:30"));
+
+        // The time zone argument takes the place of the locale
+        let args = [
+            DataValue::string(&arena, "2024-04-01 01:30"),
+            DataValue::string(&arena, "%Y-%m-%d %H:%M"),
+            DataValue::string(&arena, "+02:00"),
+        ];
+        let result = eval_parse_date(&args, &arena).unwrap();
+        assert_eq!(result.as_datetime(), Some(&dt));
+
+        // Or follows it
+        let args = [
+            DataValue::string(&arena, "1 avril 2024 01:30"),
+            DataValue::string(&arena, "%d %B %Y %H:%M"),
+            DataValue::string(&arena, "fr"),
+            DataValue::string(&arena, "GMT+2"),
+        ];
+        let result = eval_parse_date(&args, &arena).unwrap();
+        assert_eq!(result.as_datetime(), Some(&dt));
+
+        for zone in [
+            DataValue::string(&arena, "Mars/Olympus"),
+            DataValue::integer(2),
+        ] {
+            let args = [
+                DataValue::datetime(dt),
+                DataValue::string(&arena, "%Y"),
+                zone,
+            ];
+            assert_eq!(
+                eval_format_date(&args, &arena),
+                Err(LogicError::InvalidArgumentsError)
+            );
+        }
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_named_time_zones() {
+        let arena = DataArena::new();
+        let format = DataValue::string(&arena, "%Y-%m-%d %H:%M %Z");
+        let berlin = DataValue::string(&arena, "Europe/Berlin");
+
+        // Central European Time in winter and summer
+        for (dt, expected) in [
+            (
+                Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap(),
+                "2024-01-15 13:00 CET",
+            ),
+            (
+                Utc.with_ymd_and_hms(2024, 7, 15, 12, 0, 0).unwrap(),
+                "2024-07-15 14:00 CEST",
+            ),
+        ] {
+            let args = [DataValue::datetime(dt), format.clone(), berlin.clone()];
+            let result = eval_format_date(&args, &arena).unwrap();
+            assert_eq!(result.as_str(), Some(expected));
+        }
+
+        let parse = |input: &str| {
+            let args = [
+                DataValue::string(&arena, input),
+                DataValue::string(&arena, "%Y-%m-%d %H:%M"),
+                berlin.clone(),
+            ];
+            eval_parse_date(&args, &arena).map(|result| *result.as_datetime().unwrap())
+        };
+        assert_eq!(
+            parse("2024-03-31 09:00"),
+            Ok(Utc.with_ymd_and_hms(2024, 3, 31, 7, 0, 0).unwrap())
+        );
+        // The earlier of a repeated time, and no time skipped by the clocks
+        assert_eq!(
+            parse("2024-10-27 02:30"),
+            Ok(Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap())
+        );
+        assert_eq!(
+            parse("2024-03-31 02:30"),
+            Err(LogicError::InvalidArgumentsError)
+        );
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_named_context_time_zone() {
+        use crate::context::EvaluationContext;
+        use crate::logic::operators::schedule::eval_in_schedule;
+
+        let arena = DataArena::new();
+        let context = EvaluationContext::new().with_named_timezone(chrono_tz::Europe::Berlin);
+        arena.set_evaluation_context(Some(context));
+
+        let format = DataValue::string(&arena, "%H:%M");
+        for (dt, expected) in [
+            (
+                Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap(),
+                "13:00",
+            ),
+            (
+                Utc.with_ymd_and_hms(2024, 7, 15, 12, 0, 0).unwrap(),
+                "14:00",
+            ),
+        ] {
+            let args = [DataValue::datetime(dt), format.clone()];
+            let result = eval_format_date(&args, &arena).unwrap();
+            assert_eq!(result.as_str(), Some(expected));
+        }
+
+        // Born just after midnight of July 16 in summer time
+        let age_args = [
+            DataValue::datetime(Utc.with_ymd_and_hms(2000, 7, 15, 22, 30, 0).unwrap()),
+            DataValue::datetime(Utc.with_ymd_and_hms(2024, 7, 15, 21, 30, 0).unwrap()),
+        ];
+        let result = eval_age_years(&age_args, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(23));
+
+        // 08:30 UTC is 09:30 in winter and 10:30 in summer
+        let schedule = DataValue::string(&arena, "Mon-Fri 09:00-10:00");
+        for (dt, expected) in [
+            (Utc.with_ymd_and_hms(2024, 1, 15, 8, 30, 0).unwrap(), true),
+            (Utc.with_ymd_and_hms(2024, 7, 15, 8, 30, 0).unwrap(), false),
+        ] {
+            let args = [DataValue::datetime(dt), schedule.clone()];
+            let result = eval_in_schedule(&args, &arena).unwrap();
+            assert_eq!(result.as_bool(), Some(expected));
+        }
+    }
 }
@@ -0,0 +1,85 @@
+//! Log operator implementation.
+//!
+//! `{"log": expr}` evaluates to the value of its argument, reporting the value
+//! to the logger installed with
+//! [`DataLogic::set_logger`](crate::DataLogic::set_logger). Without a logger
+//! the value is passed through silently.
+
+use crate::arena::DataArena;
+use crate::logger::LogMetadata;
+use crate::logic::diagnostics::node_path;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::evaluator::evaluate;
+use crate::logic::token::Token;
+use crate::value::DataValue;
+
+/// Evaluates a log operation.
+pub fn eval_log<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    if args.len() != 1 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let value = evaluate(args[0], arena)?;
+    if let Some(logger) = arena.logger() {
+        let metadata = LogMetadata {
+            path: node_path(&arena.evaluation_nodes(), arena),
+            rule_id: arena.rule_id(),
+        };
+        logger(value, metadata);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DataLogic, ToJson};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_log_reports_values_with_paths() {
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let mut dl = DataLogic::new();
+        let sink = Arc::clone(&logged);
+        dl.set_logger(move |value, metadata| {
+            sink.lock().unwrap().push((value.to_json(), metadata.path));
+        });
+
+        let rule = json!({"+": [
+            {"log": 1},
+            {"reduce": [
+                {"map": [{"var": "items"}, {"log": {"*": [{"var": ""}, 2]}}]},
+                {"+": [{"var": "current"}, {"var": "accumulator"}]},
+                0
+            ]}
+        ]});
+        let result = dl
+            .evaluate_json(&rule, &json!({"items": [1, 2]}), None)
+            .unwrap();
+        assert_eq!(result, json!(7));
+        assert_eq!(
+            *logged.lock().unwrap(),
+            vec![
+                (json!(1), "/+/0".to_string()),
+                (json!(2), "/+/1/reduce/0/map/1".to_string()),
+                (json!(4), "/+/1/reduce/0/map/1".to_string()),
+            ]
+        );
+
+        // The root of the rule has the empty path
+        logged.lock().unwrap().clear();
+        let result = dl.evaluate_json(&json!({"log": "root"}), &json!({}), None);
+        assert_eq!(result.unwrap(), json!("root"));
+        assert_eq!(
+            *logged.lock().unwrap(),
+            vec![(json!("root"), String::new())]
+        );
+
+        // Nothing is reported once the logger is removed
+        logged.lock().unwrap().clear();
+        dl.clear_logger();
+        let result = dl.evaluate_json(&json!({"log": "quiet"}), &json!({}), None);
+        assert_eq!(result.unwrap(), json!("quiet"));
+        assert!(logged.lock().unwrap().is_empty());
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! This module provides implementations for missing operators
 //! such as missing and missing_some.
+//!
+//! Paths may contain `*` components, which match every element of an array or
+//! every value of an object. A wildcard path is reported once for each concrete
+//! path that is missing, e.g. `items.*.sku` reports `items.1.sku`.
 
 use crate::arena::DataArena;
 use crate::logic::error::{LogicError, Result};
@@ -9,6 +13,7 @@ use crate::logic::evaluator::evaluate;
 use crate::logic::operators::variable;
 use crate::logic::token::Token;
 use crate::value::DataValue;
+use bumpalo::collections::Vec as BumpVec;
 
 /// Checks if a variable with the given name exists and is not null
 fn variable_exists<'a>(name: &'a str, arena: &'a DataArena) -> bool {
@@ -19,6 +24,113 @@ fn variable_exists<'a>(name: &'a str, arena: &'a DataArena) -> bool {
     false
 }
 
+/// Returns true if a path contains a wildcard component.
+#[inline]
+fn is_wildcard_path(path: &str) -> bool {
+    path.split('.').any(|component| component == "*")
+}
+
+/// Collects the concrete paths matched by a wildcard path that are missing.
+///
+/// `resolved` holds the components traversed so far. If the traversal cannot
+/// continue, the remaining components are appended unchanged, so a missing
+/// container is reported with its wildcards intact.
+fn collect_missing_wildcard<'a>(
+    current: &'a DataValue<'a>,
+    components: &[&str],
+    resolved: &mut Vec<String>,
+    missing: &mut Vec<String>,
+) {
+    let Some((&component, rest)) = components.split_first() else {
+        if current.is_null() {
+            missing.push(resolved.join("."));
+        }
+        return;
+    };
+
+    let report = |resolved: &[String], missing: &mut Vec<String>| {
+        let mut path = resolved.to_vec();
+        path.extend(components.iter().map(|c| c.to_string()));
+        missing.push(path.join("."));
+    };
+
+    if component == "*" {
+        match current {
+            DataValue::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    resolved.push(index.to_string());
+                    collect_missing_wildcard(item, rest, resolved, missing);
+                    resolved.pop();
+                }
+            }
+            DataValue::Object(entries) => {
+                for (key, value) in entries.iter() {
+                    resolved.push(key.to_string());
+                    collect_missing_wildcard(value, rest, resolved, missing);
+                    resolved.pop();
+                }
+            }
+            _ => report(resolved, missing),
+        }
+        return;
+    }
+
+    let child = match current {
+        DataValue::Object(entries) => entries
+            .iter()
+            .find(|(key, _)| *key == component)
+            .map(|(_, value)| value),
+        DataValue::Array(items) => component
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| items.get(index)),
+        _ => None,
+    };
+
+    match child {
+        Some(value) => {
+            resolved.push(component.to_string());
+            collect_missing_wildcard(value, rest, resolved, missing);
+            resolved.pop();
+        }
+        None => report(resolved, missing),
+    }
+}
+
+/// Returns the missing concrete paths of a path that contains wildcards.
+fn missing_wildcard_paths(path: &str, arena: &DataArena) -> Vec<String> {
+    let mut missing = Vec::new();
+    if let Some(context) = arena.current_context(0) {
+        let components: Vec<&str> = path.split('.').collect();
+        collect_missing_wildcard(context, &components, &mut Vec::new(), &mut missing);
+    }
+    missing
+}
+
+/// Appends the missing paths for a variable name to `missing`.
+///
+/// Returns true if nothing was missing.
+pub(crate) fn check_name<'a>(
+    name: &'a str,
+    missing: &mut BumpVec<'a, DataValue<'a>>,
+    arena: &'a DataArena,
+) -> bool {
+    if is_wildcard_path(name) {
+        let paths = missing_wildcard_paths(name, arena);
+        let found = paths.is_empty();
+        for path in paths {
+            missing.push(DataValue::String(arena.alloc_str(&path)));
+        }
+        return found;
+    }
+
+    if variable_exists(name, arena) {
+        return true;
+    }
+    missing.push(DataValue::String(name));
+    false
+}
+
 /// Evaluates a missing operation.
 /// Checks whether the specified variables are missing from the data.
 pub fn eval_missing<'a>(
@@ -36,17 +148,13 @@ pub fn eval_missing<'a>(
 
         match value {
             DataValue::String(name) => {
-                if !variable_exists(name, arena) {
-                    missing.push(DataValue::String(name));
-                }
+                check_name(name, &mut missing, arena);
             }
             DataValue::Array(names) => {
                 // Process each variable name in the array
                 for name_value in *names {
                     if let DataValue::String(name) = name_value {
-                        if !variable_exists(name, arena) {
-                            missing.push(DataValue::String(name));
-                        }
+                        check_name(name, &mut missing, arena);
                     }
                     // Ignore non-string names
                 }
@@ -87,10 +195,8 @@ pub fn eval_missing_some<'a>(
 
         for name_value in *names {
             if let DataValue::String(name) = name_value {
-                if variable_exists(name, arena) {
+                if check_name(name, &mut missing, arena) {
                     found_count += 1;
-                } else {
-                    missing.push(DataValue::String(name));
                 }
             }
             // Ignore non-string names
@@ -2,17 +2,25 @@
 //!
 //! This module provides implementations for various operators used in logic expressions.
 
+pub mod annotate;
 pub mod arithmetic;
 pub mod array;
+pub mod block;
 pub mod comparison;
 pub mod control;
 pub mod datetime;
+pub mod log;
 pub mod missing;
+pub mod preserve;
+pub mod range;
+pub mod schedule;
+pub mod spec;
 pub mod string;
 pub mod throw;
 pub mod r#try;
 pub mod type_op;
 pub mod val;
+pub mod validate;
 pub mod variable;
 
 // Re-export operator types
@@ -21,4 +29,6 @@ pub use array::ArrayOp;
 pub use comparison::ComparisonOp;
 pub use control::ControlOp;
 pub use datetime::DateTimeOp;
+pub use range::RangeOp;
 pub use string::StringOp;
+pub use type_op::TypeCheckOp;
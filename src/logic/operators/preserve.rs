@@ -0,0 +1,149 @@
+//! Unpreserve operator implementation.
+//!
+//! `preserve` keeps its argument from being parsed, so a rule can be carried
+//! around as a value, for example as a template. The "unpreserve" operator is
+//! its counterpart: it parses a value as a rule when it is evaluated, and
+//! evaluates that rule.
+
+use crate::arena::DataArena;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::evaluator::evaluate;
+use crate::logic::token::{OperatorType, Token};
+use crate::parser::jsonlogic::{parse_nested_rule, ParserConfig};
+use crate::value::{DataValue, ToJson};
+
+/// Evaluates the 'unpreserve' operator, which evaluates a rule given as a value.
+///
+/// The first argument is evaluated to the rule, typically a preserved
+/// template or a rule read from the data. The rule is evaluated against the
+/// current data, or against the value of the optional second argument.
+///
+/// Examples:
+/// ```json
+/// {"unpreserve": {"preserve": {"+": [{"var": "a"}, 1]}}} => a + 1
+/// {"unpreserve": [{"var": "formula"}, {"var": "inputs"}]}
+/// ```
+///
+/// A rule evaluated this way cannot itself contain `unpreserve`, so that
+/// templates read from the data cannot recurse.
+pub fn eval_unpreserve<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let (template, data) = match args {
+        [template] => (template, None),
+        [template, data] => (template, Some(data)),
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    let template = evaluate(template, arena)?.to_json();
+    let rule = parse_nested_rule(&template, ParserConfig::default(), arena)?;
+    if contains_unpreserve(rule) {
+        return Err(LogicError::ParseError {
+            reason: "An unpreserved rule cannot contain unpreserve".to_string(),
+        });
+    }
+
+    let Some(data) = data else {
+        return evaluate(rule, arena);
+    };
+    let data = evaluate(data, arena)?;
+
+    // Store the current path chain length to preserve parent contexts
+    let current_chain_len = arena.path_chain_len();
+    arena.set_current_context(data, arena.alloc(DataValue::String("$")));
+    let result = evaluate(rule, arena);
+
+    // Restore the path chain to its original state
+    while arena.path_chain_len() > current_chain_len {
+        arena.pop_path_component();
+    }
+    result
+}
+
+/// Checks if a token tree contains the unpreserve operator.
+fn contains_unpreserve(token: &Token) -> bool {
+    match token {
+        Token::Literal(_) => false,
+        Token::ArrayLiteral(items) => items.iter().any(|item| contains_unpreserve(item)),
+        Token::Variable { default, .. } => default.is_some_and(contains_unpreserve),
+        Token::DynamicVariable { path_expr, default } => {
+            contains_unpreserve(path_expr) || default.is_some_and(contains_unpreserve)
+        }
+        Token::Operator {
+            op_type: OperatorType::Unpreserve,
+            ..
+        } => true,
+        Token::Operator { args, .. }
+        | Token::CustomOperator { args, .. }
+        | Token::CompiledMatch { args, .. }
+        | Token::CompiledRegex { args, .. }
+        | Token::CaseFolded { args, .. } => contains_unpreserve(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::error::LogicError;
+    use crate::DataLogic;
+    use serde_json::json;
+
+    #[test]
+    fn test_unpreserve() {
+        let dl = DataLogic::new();
+
+        let rule = json!({"unpreserve": {"preserve": {"+": [{"var": "a"}, 1]}}});
+        let result = dl.evaluate_json(&rule, &json!({"a": 2}), None).unwrap();
+        assert_eq!(result, json!(3));
+
+        // A rule read from the data, evaluated against other data
+        let rule = json!({"unpreserve": [{"var": "formula"}, {"var": "inputs"}]});
+        let data = json!({
+            "formula": {"*": [{"var": "price"}, {"var": "quantity"}]},
+            "inputs": {"price": 4, "quantity": 3}
+        });
+        assert_eq!(dl.evaluate_json(&rule, &data, None).unwrap(), json!(12));
+
+        // The data stays available to rules evaluated afterwards
+        let rule = json!({"cat": [
+            {"unpreserve": [{"preserve": {"var": "x"}}, {"preserve": {"x": "inner"}}]},
+            {"var": "x"}
+        ]});
+        let result = dl
+            .evaluate_json(&rule, &json!({"x": "outer"}), None)
+            .unwrap();
+        assert_eq!(result, json!("innerouter"));
+
+        // Templates are not constant folded away from the data
+        let logic = dl
+            .parse_logic(r#"{"unpreserve": {"preserve": {"var": "a"}}}"#, None)
+            .unwrap();
+        let data = dl.parse_data(r#"{"a": 5}"#).unwrap();
+        assert_eq!(dl.evaluate(&logic, &data).unwrap().as_i64(), Some(5));
+    }
+
+    #[test]
+    fn test_unpreserve_errors() {
+        let dl = DataLogic::new();
+
+        // A template cannot unpreserve another one
+        let rule = json!({"unpreserve": {"var": "t"}});
+        let data = json!({"t": {"unpreserve": {"var": "t"}}});
+        assert!(matches!(
+            dl.evaluate_json(&rule, &data, None),
+            Err(LogicError::ParseError { .. })
+        ));
+
+        let rule = json!({"unpreserve": {"preserve": {"obj": "invalid"}}});
+        assert!(matches!(
+            dl.evaluate_json(&rule, &json!(null), None),
+            Err(LogicError::ParseError { .. })
+        ));
+
+        let rule = json!({"unpreserve": []});
+        assert_eq!(
+            dl.evaluate_json(&rule, &json!(null), None),
+            Err(LogicError::InvalidArgumentsError)
+        );
+    }
+}
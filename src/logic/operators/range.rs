@@ -0,0 +1,294 @@
+//! Range operators for logic expressions.
+//!
+//! This module provides a numeric range value and operators for testing
+//! membership and combining ranges, which keeps tiered rules free of
+//! repetitive comparison chains.
+//!
+//! A range is represented as an object with `start`, `end` and `inclusive`
+//! keys. The start bound is always inclusive, the end bound is inclusive only
+//! when `inclusive` is true. A null bound is unbounded.
+
+use crate::arena::DataArena;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::operators::arithmetic::create_number;
+use crate::value::DataValue;
+
+/// Enumeration of range operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeOp {
+    /// Range construction
+    Range,
+    /// Range membership test
+    InRange,
+    /// Test whether two ranges share at least one value
+    RangesOverlap,
+    /// Intersection of two ranges
+    RangeIntersect,
+}
+
+/// A numeric interval with an inclusive start and an optionally inclusive end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Interval {
+    start: Option<f64>,
+    end: Option<f64>,
+    inclusive: bool,
+}
+
+impl Interval {
+    /// Returns true if the value lies within the interval.
+    fn contains(&self, value: f64) -> bool {
+        let after_start = self.start.is_none_or(|start| value >= start);
+        let before_end = self.end.is_none_or(|end| {
+            if self.inclusive {
+                value <= end
+            } else {
+                value < end
+            }
+        });
+        after_start && before_end
+    }
+
+    /// Returns true if the interval contains no values.
+    fn is_empty(&self) -> bool {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => start > end || (start == end && !self.inclusive),
+            _ => false,
+        }
+    }
+
+    /// Returns the intersection of two intervals, which may be empty.
+    fn intersect(&self, other: &Interval) -> Interval {
+        let start = match (self.start, other.start) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+
+        let (end, inclusive) = match (self.end, other.end) {
+            (Some(a), Some(b)) if a < b => (Some(a), self.inclusive),
+            (Some(a), Some(b)) if b < a => (Some(b), other.inclusive),
+            (Some(a), Some(_)) => (Some(a), self.inclusive && other.inclusive),
+            (Some(a), None) => (Some(a), self.inclusive),
+            (None, Some(b)) => (Some(b), other.inclusive),
+            (None, None) => (None, true),
+        };
+
+        Interval {
+            start,
+            end,
+            inclusive,
+        }
+    }
+
+    /// Converts the interval into its object representation.
+    fn to_data_value<'a>(self, arena: &'a DataArena) -> &'a DataValue<'a> {
+        let bound = |value: Option<f64>| match value {
+            Some(v) => create_number(v, arena).clone(),
+            None => DataValue::Null,
+        };
+
+        let entries = [
+            (arena.intern_str("start"), bound(self.start)),
+            (arena.intern_str("end"), bound(self.end)),
+            (
+                arena.intern_str("inclusive"),
+                DataValue::Bool(self.inclusive),
+            ),
+        ];
+        arena.alloc(DataValue::object(arena, &entries))
+    }
+}
+
+/// Looks up a key in an object value.
+fn object_field<'a>(
+    entries: &'a [(&'a str, DataValue<'a>)],
+    key: &str,
+) -> Option<&'a DataValue<'a>> {
+    entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+}
+
+/// Converts a bound value to an optional number, where null is unbounded.
+fn parse_bound(value: Option<&DataValue>) -> Result<Option<f64>> {
+    match value {
+        None | Some(DataValue::Null) => Ok(None),
+        Some(DataValue::Number(n)) => Ok(Some(n.as_f64())),
+        Some(_) => Err(LogicError::InvalidArgumentsError),
+    }
+}
+
+/// Reads the inclusive flag from an options object.
+fn parse_inclusive(options: Option<&DataValue>) -> Result<bool> {
+    match options {
+        None | Some(DataValue::Null) => Ok(false),
+        Some(DataValue::Object(entries)) => match object_field(entries, "inclusive") {
+            None => Ok(false),
+            Some(DataValue::Bool(b)) => Ok(*b),
+            Some(_) => Err(LogicError::InvalidArgumentsError),
+        },
+        Some(_) => Err(LogicError::InvalidArgumentsError),
+    }
+}
+
+/// Builds an interval from start, end and options values.
+fn build_interval(
+    start: Option<&DataValue>,
+    end: Option<&DataValue>,
+    options: Option<&DataValue>,
+) -> Result<Interval> {
+    let interval = Interval {
+        start: parse_bound(start)?,
+        end: parse_bound(end)?,
+        inclusive: parse_inclusive(options)?,
+    };
+
+    if let (Some(start), Some(end)) = (interval.start, interval.end) {
+        if start > end {
+            return Err(LogicError::InvalidArgumentsError);
+        }
+    }
+
+    Ok(interval)
+}
+
+/// Interprets a value as an interval.
+///
+/// Accepts the object produced by the `range` operator as well as the
+/// `[start, end]` and `[start, end, options]` array shorthands.
+fn to_interval(value: &DataValue) -> Result<Interval> {
+    match value {
+        DataValue::Object(entries) => build_interval(
+            object_field(entries, "start"),
+            object_field(entries, "end"),
+            Some(value),
+        ),
+        DataValue::Array(items) if items.len() == 2 || items.len() == 3 => {
+            build_interval(items.first(), items.get(1), items.get(2))
+        }
+        _ => Err(LogicError::InvalidArgumentsError),
+    }
+}
+
+/// Evaluates a range construction.
+///
+/// `{"range": [0, 100]}` is the half-open interval `[0, 100)`, while
+/// `{"range": [0, 100, {"inclusive": true}]}` includes the end bound.
+pub fn eval_range<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() < 2 || args.len() > 3 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let interval = build_interval(args.first(), args.get(1), args.get(2))?;
+    Ok(interval.to_data_value(arena))
+}
+
+/// Evaluates a range membership test.
+///
+/// Only numbers and numeric strings can be in range.
+pub fn eval_in_range<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let interval = to_interval(&args[1])?;
+    let value = match &args[0] {
+        DataValue::Number(n) => Some(*n),
+        DataValue::String(_) => args[0].coerce_to_number(),
+        _ => None,
+    };
+    let contained = value.is_some_and(|n| interval.contains(n.as_f64()));
+
+    Ok(if contained {
+        arena.true_value()
+    } else {
+        arena.false_value()
+    })
+}
+
+/// Evaluates whether two ranges overlap.
+pub fn eval_ranges_overlap<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let first = to_interval(&args[0])?;
+    let second = to_interval(&args[1])?;
+
+    Ok(if first.intersect(&second).is_empty() {
+        arena.false_value()
+    } else {
+        arena.true_value()
+    })
+}
+
+/// Evaluates the intersection of two ranges.
+///
+/// Returns null when the ranges do not overlap.
+pub fn eval_range_intersect<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let first = to_interval(&args[0])?;
+    let second = to_interval(&args[1])?;
+    let intersection = first.intersect(&second);
+
+    if intersection.is_empty() {
+        Ok(arena.null_value())
+    } else {
+        Ok(intersection.to_data_value(arena))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(start: Option<f64>, end: Option<f64>, inclusive: bool) -> Interval {
+        Interval {
+            start,
+            end,
+            inclusive,
+        }
+    }
+
+    #[test]
+    fn test_interval_contains() {
+        let half_open = interval(Some(0.0), Some(10.0), false);
+        assert!(half_open.contains(0.0));
+        assert!(half_open.contains(9.99));
+        assert!(!half_open.contains(10.0));
+
+        let closed = interval(Some(0.0), Some(10.0), true);
+        assert!(closed.contains(10.0));
+
+        let unbounded = interval(None, Some(5.0), false);
+        assert!(unbounded.contains(-1e9));
+        assert!(!unbounded.contains(5.0));
+    }
+
+    #[test]
+    fn test_interval_intersect() {
+        let a = interval(Some(0.0), Some(10.0), false);
+        let b = interval(Some(10.0), Some(20.0), true);
+        assert!(a.intersect(&b).is_empty());
+
+        let c = interval(Some(0.0), Some(10.0), true);
+        let touching = c.intersect(&b);
+        assert!(!touching.is_empty());
+        assert_eq!(touching, interval(Some(10.0), Some(10.0), true));
+
+        let d = interval(Some(5.0), None, true);
+        assert_eq!(a.intersect(&d), interval(Some(5.0), Some(10.0), false));
+    }
+}
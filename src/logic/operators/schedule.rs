@@ -0,0 +1,379 @@
+//! Recurring schedule matching.
+//!
+//! This module implements the "in_schedule" operator, which checks whether a
+//! datetime falls inside a recurring schedule. Two notations are supported:
+//!
+//! - Weekly windows: `"Mon-Fri 09:00-17:00 +01:00"`, made of an optional list
+//!   of days (`Mon-Fri`, `Sat,Sun`, `daily`), optional comma-separated time
+//!   windows with exclusive ends (`09:00-12:00,13:00-17:00`, `22:00-06:00`
+//!   for overnight windows) and an optional UTC offset.
+//! - Cron expressions: `"*/15 9-17 * * 1-5"`, with the standard five fields
+//!   (minute, hour, day of month, month, day of week) and an optional UTC
+//!   offset as a sixth field.
+//!
+//! Offsets are fixed (`UTC`, `Z`, `+02:00`, `-0530`, `UTC+2`); named time zones
+//! are not supported since the crate does not ship a time zone database.
+//! Schedules without an offset are in the time zone of the evaluation
+//! context, or in UTC.
+
+use chrono::{DateTime, Datelike, FixedOffset, Timelike, Utc};
+
+use crate::arena::DataArena;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::operators::datetime::{context_offset_at, extract_datetime};
+use crate::value::DataValue;
+
+/// Day names, starting on Monday.
+const DAY_NAMES: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Minutes in a day.
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// A parsed recurring schedule.
+#[derive(Debug, Clone, PartialEq)]
+enum Schedule {
+    /// Time windows on selected days of the week.
+    Weekly {
+        /// Active days, indexed from Monday.
+        days: [bool; 7],
+        /// Windows as `(start, end)` minutes of the day, end exclusive.
+        windows: Vec<(u32, u32)>,
+        offset: FixedOffset,
+    },
+    /// A five-field cron expression.
+    Cron {
+        minutes: Vec<bool>,
+        hours: Vec<bool>,
+        days_of_month: Vec<bool>,
+        months: Vec<bool>,
+        /// Indexed from Sunday.
+        days_of_week: Vec<bool>,
+        /// Whether the day of month field is restricted.
+        dom_restricted: bool,
+        /// Whether the day of week field is restricted.
+        dow_restricted: bool,
+        offset: FixedOffset,
+    },
+}
+
+impl Schedule {
+    /// Parses a schedule from its string notation, with the offset used
+    /// when it gives none.
+    fn parse(spec: &str, default_offset: FixedOffset) -> Option<Self> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let is_cron_field = |field: &&str| {
+            field
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '*' | ',' | '-' | '/'))
+        };
+
+        if fields.len() >= 5 && fields[..5].iter().all(is_cron_field) {
+            return Self::parse_cron(&fields, default_offset);
+        }
+        Self::parse_weekly(&fields, default_offset)
+    }
+
+    /// Parses the weekly window notation.
+    fn parse_weekly(fields: &[&str], default_offset: FixedOffset) -> Option<Self> {
+        let mut fields = fields.iter().peekable();
+
+        let days = match fields.peek() {
+            Some(field) if field.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                match parse_days(field) {
+                    Some(days) => {
+                        fields.next();
+                        days
+                    }
+                    // Not a day list, e.g. a "UTC" offset
+                    None => [true; 7],
+                }
+            }
+            _ => [true; 7],
+        };
+
+        let windows = match fields.peek() {
+            Some(field) if field.starts_with(|c: char| c.is_ascii_digit()) => {
+                let windows = field
+                    .split(',')
+                    .map(parse_window)
+                    .collect::<Option<Vec<_>>>()?;
+                fields.next();
+                windows
+            }
+            _ => vec![(0, MINUTES_PER_DAY)],
+        };
+
+        let offset = match fields.next() {
+            Some(field) => parse_offset(field)?,
+            None => default_offset,
+        };
+
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(Schedule::Weekly {
+            days,
+            windows,
+            offset,
+        })
+    }
+
+    /// Parses a cron expression with an optional offset field.
+    fn parse_cron(fields: &[&str], default_offset: FixedOffset) -> Option<Self> {
+        let offset = match fields.len() {
+            5 => default_offset,
+            6 => parse_offset(fields[5])?,
+            _ => return None,
+        };
+
+        let mut days_of_week = parse_cron_field(fields[4], 0, 7)?;
+        // Both 0 and 7 denote Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Some(Schedule::Cron {
+            minutes: parse_cron_field(fields[0], 0, 59)?,
+            hours: parse_cron_field(fields[1], 0, 23)?,
+            days_of_month: parse_cron_field(fields[2], 1, 31)?,
+            months: parse_cron_field(fields[3], 1, 12)?,
+            days_of_week,
+            dom_restricted: fields[2] != "*",
+            dow_restricted: fields[4] != "*",
+            offset,
+        })
+    }
+
+    /// Returns true if the datetime falls inside the schedule.
+    fn contains(&self, dt: &DateTime<Utc>) -> bool {
+        match self {
+            Schedule::Weekly {
+                days,
+                windows,
+                offset,
+            } => {
+                let local = dt.with_timezone(offset);
+                let today = local.weekday().num_days_from_monday() as usize;
+                let yesterday = (today + 6) % 7;
+                let minute = local.hour() * 60 + local.minute();
+
+                windows.iter().any(|&(start, end)| {
+                    if start < end {
+                        days[today] && minute >= start && minute < end
+                    } else {
+                        // Overnight windows belong to the day they start on
+                        (days[today] && minute >= start) || (days[yesterday] && minute < end)
+                    }
+                })
+            }
+            Schedule::Cron {
+                minutes,
+                hours,
+                days_of_month,
+                months,
+                days_of_week,
+                dom_restricted,
+                dow_restricted,
+                offset,
+            } => {
+                let local = dt.with_timezone(offset);
+                let dom = days_of_month[local.day() as usize - 1];
+                let dow = days_of_week[local.weekday().num_days_from_sunday() as usize];
+
+                // When both day fields are restricted, either may match
+                let day_matches = if *dom_restricted && *dow_restricted {
+                    dom || dow
+                } else {
+                    dom && dow
+                };
+
+                minutes[local.minute() as usize]
+                    && hours[local.hour() as usize]
+                    && months[local.month() as usize - 1]
+                    && day_matches
+            }
+        }
+    }
+}
+
+/// Parses a day name or abbreviation into its index from Monday.
+fn parse_day(name: &str) -> Option<usize> {
+    let name = name.to_ascii_lowercase();
+    if name.len() < 3 {
+        return None;
+    }
+    DAY_NAMES.iter().position(|day| day.starts_with(&name))
+}
+
+/// Parses a day list such as `Mon-Fri`, `Sat,Sun` or `daily`.
+fn parse_days(field: &str) -> Option<[bool; 7]> {
+    if field.eq_ignore_ascii_case("daily") {
+        return Some([true; 7]);
+    }
+
+    let mut days = [false; 7];
+    for part in field.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse_day(from)?, parse_day(to)?);
+                // Ranges may wrap around the week, e.g. Fri-Mon
+                let mut day = from;
+                loop {
+                    days[day] = true;
+                    if day == to {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days[parse_day(part)?] = true,
+        }
+    }
+    Some(days)
+}
+
+/// Parses a `HH:MM` time into minutes of the day, allowing `24:00`.
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    let total = hours * 60 + minutes;
+    (minutes < 60 && total <= MINUTES_PER_DAY).then_some(total)
+}
+
+/// Parses a `HH:MM-HH:MM` window.
+fn parse_window(window: &str) -> Option<(u32, u32)> {
+    let (start, end) = window.split_once('-')?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    (start != end).then_some((start, end))
+}
+
+/// Parses a fixed UTC offset such as `UTC`, `Z`, `+02:00`, `-0530` or `UTC+2`.
+pub(crate) fn parse_offset(field: &str) -> Option<FixedOffset> {
+    let upper = field.to_ascii_uppercase();
+    let rest = upper
+        .strip_prefix("UTC")
+        .or_else(|| upper.strip_prefix("GMT"))
+        .unwrap_or(&upper);
+    if rest.is_empty() || rest == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, digits) = match rest.split_at(1) {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if digits.len() > 2 => digits.split_at(digits.len() - 2),
+        None => (digits, "0"),
+    };
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parses a cron field into a table of allowed values from `min` to `max`.
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Option<Vec<bool>> {
+    let mut allowed = vec![false; (max - min + 1) as usize];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&s| s > 0)?),
+            None => (part, 1),
+        };
+
+        let (from, to) = if range == "*" {
+            (min, max)
+        } else if let Some((from, to)) = range.split_once('-') {
+            (from.parse().ok()?, to.parse().ok()?)
+        } else {
+            let value = range.parse().ok()?;
+            // A single value with a step runs to the end of the range
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if from < min || to > max || from > to {
+            return None;
+        }
+        for value in (from..=to).step_by(step as usize) {
+            allowed[(value - min) as usize] = true;
+        }
+    }
+
+    Some(allowed)
+}
+
+/// Evaluates an in_schedule operation.
+///
+/// `{"in_schedule": [datetime, schedule]}` returns true if the datetime falls
+/// inside the schedule. Invalid schedules are reported as invalid arguments.
+pub fn eval_in_schedule<'a>(
+    args: &'a [DataValue<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let dt = extract_datetime(&args[0], arena)?;
+    let schedule = match &args[1] {
+        DataValue::String(spec) => {
+            // Named time zones use their offset at the checked datetime
+            Schedule::parse(spec, context_offset_at(arena, dt))
+                .ok_or(LogicError::InvalidArgumentsError)?
+        }
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    Ok(arena.alloc(DataValue::Bool(schedule.contains(dt))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("UTC"), FixedOffset::east_opt(0));
+        assert_eq!(parse_offset("+02:00"), FixedOffset::east_opt(7200));
+        assert_eq!(parse_offset("-0530"), FixedOffset::east_opt(-19800));
+        assert_eq!(parse_offset("UTC+2"), FixedOffset::east_opt(7200));
+        assert_eq!(parse_offset("Europe/Berlin"), None);
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let weekly = Schedule::parse("Mon-Fri 09:00-17:00", utc()).unwrap();
+        assert!(
+            matches!(weekly, Schedule::Weekly { days, .. } if days == [true, true, true, true, true, false, false])
+        );
+
+        let wrapped = parse_days("Fri-Mon").unwrap();
+        assert_eq!(wrapped, [true, false, false, false, true, true, true]);
+
+        assert!(matches!(
+            Schedule::parse("0 9 * * 1-5", utc()),
+            Some(Schedule::Cron { .. })
+        ));
+        assert!(Schedule::parse("Mon-Fri 09:00", utc()).is_none());
+        assert!(Schedule::parse("60 * * * *", utc()).is_none());
+        assert!(Schedule::parse("Mon-Fri 09:00-17:00 Europe/Berlin", utc()).is_none());
+    }
+}
@@ -0,0 +1,217 @@
+//! Strict jsonlogic.com behavior of the operators.
+//!
+//! With [`Semantics::JsonLogicSpec`](crate::Semantics::JsonLogicSpec), the
+//! operators of the specification behave as in json-logic-js wherever the
+//! engine differs from it: extra arguments are ignored, missing ones are
+//! `undefined`, and values are converted to strings and numbers as
+//! JavaScript does. Everything else is left to the regular operators.
+
+use crate::arena::DataArena;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::evaluator::evaluate;
+use crate::logic::operators::{ArithmeticOp, ArrayOp, ComparisonOp, ControlOp, StringOp};
+use crate::logic::token::{OperatorType, Token};
+use crate::value::{DataValue, NumberValue};
+
+/// Returns the number of arguments json-logic-js reads for an operator that
+/// ignores the others.
+pub fn max_arguments(op_type: OperatorType) -> Option<usize> {
+    match op_type {
+        OperatorType::Control(ControlOp::Not | ControlOp::DoubleNegation) | OperatorType::Log => {
+            Some(1)
+        }
+        OperatorType::Arithmetic(
+            ArithmeticOp::Subtract | ArithmeticOp::Divide | ArithmeticOp::Modulo,
+        )
+        | OperatorType::Comparison(
+            ComparisonOp::Equal
+            | ComparisonOp::StrictEqual
+            | ComparisonOp::NotEqual
+            | ComparisonOp::StrictNotEqual
+            | ComparisonOp::GreaterThan
+            | ComparisonOp::GreaterThanOrEqual,
+        )
+        | OperatorType::Array(
+            ArrayOp::Map
+            | ArrayOp::Filter
+            | ArrayOp::All
+            | ArrayOp::Some
+            | ArrayOp::None
+            | ArrayOp::In,
+        )
+        | OperatorType::MissingSome => Some(2),
+        OperatorType::Comparison(ComparisonOp::LessThan | ComparisonOp::LessThanOrEqual)
+        | OperatorType::Array(ArrayOp::Reduce)
+        | OperatorType::String(StringOp::Substr) => Some(3),
+        _ => None,
+    }
+}
+
+/// Evaluates an operation whose json-logic-js result differs from the one
+/// of the regular operator.
+///
+/// Returns `None` for the operations the regular operator handles as
+/// json-logic-js does. The arguments are at most [`max_arguments`].
+pub fn evaluate_operation<'a>(
+    op_type: OperatorType,
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Option<Result<&'a DataValue<'a>>> {
+    let result = match op_type {
+        // Reducing no arguments without an initial value throws
+        OperatorType::Arithmetic(ArithmeticOp::Multiply) if args.is_empty() => {
+            Err(LogicError::InvalidArgumentsError)
+        }
+        // Arithmetic on undefined is NaN, serialized as null
+        OperatorType::Arithmetic(ArithmeticOp::Subtract) if args.is_empty() => {
+            Ok(arena.null_value())
+        }
+        OperatorType::Arithmetic(ArithmeticOp::Divide | ArithmeticOp::Modulo) if args.len() < 2 => {
+            evaluate_all(args, arena).map(|_| arena.null_value())
+        }
+        OperatorType::Arithmetic(op @ (ArithmeticOp::Max | ArithmeticOp::Min)) => {
+            eval_extreme(op == ArithmeticOp::Max, args, arena)
+        }
+        OperatorType::Control(ControlOp::Or) | OperatorType::Log if args.is_empty() => {
+            Ok(arena.null_value())
+        }
+        OperatorType::Control(op @ (ControlOp::Not | ControlOp::DoubleNegation))
+            if args.is_empty() =>
+        {
+            Ok(arena.alloc(DataValue::Bool(op == ControlOp::Not)))
+        }
+        OperatorType::Comparison(op) if args.len() < 2 => {
+            evaluate_all(args, arena).map(|values| compare_undefined(op, values, arena))
+        }
+        OperatorType::String(StringOp::Cat) => eval_cat(args, arena),
+        OperatorType::String(StringOp::Substr) if args.len() < 2 => {
+            // Without a start, the whole string
+            match args.first() {
+                Some(arg) => evaluate(arg, arena).and_then(|value| {
+                    let mut result = String::new();
+                    push_js_string(value, &mut result);
+                    alloc_string(result, arena)
+                }),
+                None => alloc_string("undefined".to_string(), arena),
+            }
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Evaluates every argument.
+fn evaluate_all<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<Vec<&'a DataValue<'a>>> {
+    args.iter().map(|arg| evaluate(arg, arena)).collect()
+}
+
+/// Compares the arguments of a comparison missing some, which are undefined.
+fn compare_undefined<'a>(
+    op: ComparisonOp,
+    values: Vec<&'a DataValue<'a>>,
+    arena: &'a DataArena,
+) -> &'a DataValue<'a> {
+    // Only null is loosely equal to undefined, and nothing is strictly
+    // equal to it but undefined itself
+    let result = match (op, values.first()) {
+        (ComparisonOp::Equal, Some(value)) => value.is_null(),
+        (ComparisonOp::NotEqual, Some(value)) => !value.is_null(),
+        (ComparisonOp::StrictEqual, Some(_)) => false,
+        (ComparisonOp::StrictNotEqual, Some(_)) => true,
+        (ComparisonOp::Equal | ComparisonOp::StrictEqual, None) => true,
+        (ComparisonOp::NotEqual | ComparisonOp::StrictNotEqual, None) => false,
+        // Ordering against undefined compares against NaN
+        _ => false,
+    };
+    arena.alloc(DataValue::Bool(result))
+}
+
+/// Evaluates `max` or `min` as `Math.max` or `Math.min`.
+fn eval_extreme<'a>(
+    max: bool,
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let mut extreme: Option<f64> = None;
+    for value in evaluate_all(args, arena)? {
+        let number = js_number(value);
+        if number.is_nan() {
+            return Ok(arena.null_value());
+        }
+        extreme = Some(match extreme {
+            Some(current) if max => current.max(number),
+            Some(current) => current.min(number),
+            None => number,
+        });
+    }
+
+    // Without arguments, the result is an infinity, serialized as null
+    Ok(match extreme {
+        Some(extreme) => arena.alloc(DataValue::Number(NumberValue::from_f64(extreme))),
+        None => arena.null_value(),
+    })
+}
+
+/// Evaluates `cat` as a concatenation of JavaScript strings.
+fn eval_cat<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    let mut result = String::new();
+    for arg in args {
+        push_js_string(evaluate(arg, arena)?, &mut result);
+    }
+    alloc_string(result, arena)
+}
+
+/// Allocates a built string, checking it against the limits.
+fn alloc_string<'a>(value: String, arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
+    arena.limits().check_string_len(value.len())?;
+    Ok(arena.alloc(DataValue::String(arena.alloc_str(&value))))
+}
+
+/// Appends a value converted to a string as by JavaScript's `String`.
+fn push_js_string(value: &DataValue, out: &mut String) {
+    match value {
+        DataValue::String(s) => out.push_str(s),
+        DataValue::Array(items) => {
+            // Arrays join their items, with null items as empty strings
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                if !item.is_null() {
+                    push_js_string(item, out);
+                }
+            }
+        }
+        DataValue::Object(_) => out.push_str("[object Object]"),
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+/// Converts a value to a number as by JavaScript's `Number`.
+fn js_number(value: &DataValue) -> f64 {
+    match value {
+        DataValue::String(s) => {
+            let s = s.trim();
+            if s.is_empty() {
+                0.0
+            } else {
+                s.parse()
+                    .ok()
+                    .filter(|number: &f64| number.is_finite())
+                    .unwrap_or(f64::NAN)
+            }
+        }
+        // Arrays are converted through their string
+        DataValue::Array(_) => {
+            let mut string = String::new();
+            push_js_string(value, &mut string);
+            js_number(&DataValue::String(&string))
+        }
+        _ => value
+            .coerce_to_number()
+            .map_or(f64::NAN, |number| number.as_f64()),
+    }
+}
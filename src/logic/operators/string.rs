@@ -3,7 +3,7 @@
 //! This module provides implementations for string operators
 //! such as cat, substr, etc.
 
-use crate::arena::DataArena;
+use crate::arena::{DataArena, StringBuilder};
 use crate::logic::error::{LogicError, Result};
 use crate::logic::evaluator::evaluate;
 use crate::logic::token::Token;
@@ -31,6 +31,36 @@ pub enum StringOp {
     Replace,
     /// Split string into array based on delimiter
     Split,
+    /// Test a string against a regex pattern
+    Regex,
+    /// Replace all matches of a regex pattern
+    RegexReplace,
+}
+
+/// A regex compiled from a constant pattern by the optimizer
+#[derive(Debug, Clone)]
+pub struct RegexPattern(Regex);
+
+impl RegexPattern {
+    /// Compiles a pattern.
+    pub fn new(pattern: &str) -> Result<Self> {
+        Regex::new(pattern)
+            .map(RegexPattern)
+            .map_err(|error| LogicError::ParseError {
+                reason: format!("Invalid regex pattern '{}': {}", pattern, error),
+            })
+    }
+
+    /// Returns the source of the pattern.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for RegexPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
 }
 
 /// Helper function to convert a value to a string representation
@@ -41,16 +71,6 @@ fn value_to_string<'a>(value: &'a DataValue<'a>, arena: &'a DataArena) -> &'a st
     }
 }
 
-/// Helper function to append values from an array to a string
-fn append_array_to_string(values: &[DataValue<'_>], result: &mut String) {
-    for value in values {
-        match value {
-            DataValue::String(s) => result.push_str(s),
-            _ => result.push_str(&value.to_string()),
-        }
-    }
-}
-
 /// Validate arguments for substr operation
 fn validate_substr_args(args: &[&Token]) -> Result<()> {
     if args.len() < 2 || args.len() > 3 {
@@ -88,6 +108,9 @@ fn calculate_substr_length(len_value: i64, char_count: usize, start_pos: usize)
 }
 
 /// Evaluates a string concatenation operation.
+///
+/// The result is built with a [`StringBuilder`], which references the strings
+/// of the arguments rather than copying them until the result is allocated.
 pub fn eval_cat<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&'a DataValue<'a>> {
     if args.is_empty() {
         return Ok(arena.empty_string_value());
@@ -102,11 +125,13 @@ pub fn eval_cat<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
             return Ok(value);
         }
 
+        arena.record_coercion(value, "string");
+
         // If it's an array, concatenate all elements
         if let DataValue::Array(arr) = value {
-            let mut result = String::new();
-            append_array_to_string(arr, &mut result);
-            return Ok(arena.alloc(DataValue::String(arena.alloc_str(&result))));
+            let mut result = StringBuilder::new(arena);
+            result.push_values(arr)?;
+            return Ok(arena.alloc(DataValue::String(result.finish())));
         }
 
         // Otherwise, convert to string
@@ -114,24 +139,19 @@ pub fn eval_cat<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
     }
 
     // For multiple arguments, concatenate them
-    let mut result = String::new();
+    let mut result = StringBuilder::new(arena);
 
     for arg in args {
         let value = evaluate(arg, arena)?;
+        arena.record_coercion(value, "string");
         match value {
-            DataValue::String(s) => result.push_str(s),
-            DataValue::Array(arr) => {
-                // If we get an array from a chained operation, concatenate all elements
-                append_array_to_string(arr, &mut result);
-            }
-            _ => {
-                result.push_str(&value.to_string());
-            }
+            // If we get an array from a chained operation, concatenate all elements
+            DataValue::Array(arr) => result.push_values(arr)?,
+            _ => result.push_value(value)?,
         }
     }
 
-    // Allocate the result string in the arena
-    Ok(arena.alloc(DataValue::String(arena.alloc_str(&result))))
+    Ok(arena.alloc(DataValue::String(result.finish())))
 }
 
 /// Evaluates a substring operation.
@@ -287,6 +307,7 @@ pub fn eval_replace<'a>(
 
     // Replace all occurrences
     let result = string_str.replace(find_str, replace_str);
+    arena.limits().check_string_len(result.len())?;
 
     Ok(arena.alloc(DataValue::String(arena.alloc_str(&result))))
 }
@@ -346,16 +367,67 @@ pub fn eval_split<'a>(
     }
 
     // Normal split behavior (original implementation)
-    let parts: Vec<DataValue> = string_str
-        .split(delimiter_str)
-        .map(|part| DataValue::String(arena.alloc_str(part)))
-        .collect();
+    let mut parts: Vec<DataValue> = Vec::new();
+    for part in string_str.split(delimiter_str) {
+        arena.limits().check_array_len(parts.len() + 1)?;
+        parts.push(DataValue::String(arena.alloc_str(part)));
+    }
 
     // Create array of string parts using vec_into_slice
     let result_array = arena.vec_into_slice(parts);
     Ok(arena.alloc(DataValue::Array(result_array)))
 }
 
+/// Evaluates a regex operation, compiling its pattern.
+///
+/// Constant patterns are compiled once by the optimizer instead, see
+/// [`eval_compiled_regex`].
+pub fn eval_regex<'a>(
+    string_op: StringOp,
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let Some((pattern, args)) = args.split_first() else {
+        return Err(LogicError::InvalidArgumentsError);
+    };
+    let pattern = evaluate(pattern, arena)?;
+    let regex = RegexPattern::new(value_to_string(pattern, arena))?;
+    eval_compiled_regex(string_op, &regex, args, arena)
+}
+
+/// Evaluates a regex operation with a compiled pattern.
+///
+/// `args` are the arguments following the pattern: the subject, and for
+/// `regex_replace` the replacement, which may refer to groups as `$1` or
+/// `$name`. A null subject never matches.
+pub fn eval_compiled_regex<'a>(
+    string_op: StringOp,
+    regex: &RegexPattern,
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    match (string_op, args) {
+        (StringOp::Regex, [subject]) => {
+            let subject = evaluate(subject, arena)?;
+            let matched = !subject.is_null() && regex.0.is_match(value_to_string(subject, arena));
+            Ok(arena.alloc(DataValue::Bool(matched)))
+        }
+        (StringOp::RegexReplace, [subject, replacement]) => {
+            let subject = evaluate(subject, arena)?;
+            let replacement = evaluate(replacement, arena)?;
+            if subject.is_null() {
+                return Ok(arena.null_value());
+            }
+            let subject_str = value_to_string(subject, arena);
+            let replacement_str = value_to_string(replacement, arena);
+            let result = regex.0.replace_all(subject_str, replacement_str);
+            arena.limits().check_string_len(result.len())?;
+            Ok(arena.alloc(DataValue::String(arena.alloc_str(&result))))
+        }
+        _ => Err(LogicError::InvalidArgumentsError),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::logic::datalogic_core::DataLogicCore;
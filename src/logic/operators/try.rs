@@ -55,28 +55,13 @@ fn try_evaluate_expression<'a>(
 }
 
 /// Setup error context for next evaluation
-fn setup_error_context<'a>(
-    error: &LogicError,
-    index: usize,
-    arena: &'a DataArena,
-    original_root: Option<&'a DataValue<'a>>,
-) -> usize {
-    // Store the current path chain length to preserve parent contexts
-    let current_chain_len = arena.path_chain_len();
-
+fn setup_error_context(error: &LogicError, index: usize, arena: &DataArena) {
     // Create error context
     let error_context = create_error_context(error, arena);
 
-    // Set the error context as current but restore the original root context
+    // Set the error context as current, keeping the enclosing contexts for scope jumps
     let key = DataValue::Number(crate::value::NumberValue::from_f64(index as f64));
-    arena.set_current_context(error_context, &key);
-
-    // Make sure the root context is still available for scope jumps
-    if let Some(root) = original_root {
-        arena.set_root_context(root);
-    }
-
-    current_chain_len
+    arena.set_current_context(error_context, arena.alloc(key));
 }
 
 /// Evaluates a try operation.
@@ -95,8 +80,8 @@ pub fn eval_try<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
         return evaluate(args[0], arena);
     }
 
-    // Preserve the original root context for later use
-    let original_root = arena.root_context();
+    // Store the current path chain length to preserve parent contexts
+    let current_chain_len = arena.path_chain_len();
 
     // Try each expression in sequence
     let mut last_error = None;
@@ -110,18 +95,19 @@ pub fn eval_try<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<&
             }
         } else if let Some(ref error) = last_error {
             // Setup error context for this evaluation
-            let current_chain_len = setup_error_context(error, i, arena, original_root);
+            setup_error_context(error, i, arena);
 
             // Evaluate with the error context
             match try_evaluate_expression(arg, arena) {
                 Ok(result) => return Ok(result),
                 Err(e) => last_error = Some(e),
             }
+        }
 
-            // Restore the path chain to its original state
-            while arena.path_chain_len() > current_chain_len {
-                arena.pop_path_component();
-            }
+        // Restore the path chain left by the failed expression, including
+        // the contexts of iterators it failed in
+        while arena.path_chain_len() > current_chain_len {
+            arena.pop_path_component();
         }
     }
 
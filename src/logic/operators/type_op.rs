@@ -1,7 +1,8 @@
 //! Type operator implementation.
 //!
 //! This module provides the implementation of the "type" operator,
-//! which returns the type of a value as a string.
+//! which returns the type of a value as a string, and of the type check
+//! operators such as "is_string".
 
 use crate::arena::DataArena;
 use crate::logic::error::{LogicError, Result};
@@ -9,6 +10,23 @@ use crate::logic::evaluator::evaluate;
 use crate::logic::token::Token;
 use crate::value::DataValue;
 
+/// Enumeration of type check operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeCheckOp {
+    /// Checks if a value is a string
+    IsString,
+    /// Checks if a value is a number
+    IsNumber,
+    /// Checks if a value is a boolean
+    IsBool,
+    /// Checks if a value is an array
+    IsArray,
+    /// Checks if a value is an object
+    IsObject,
+    /// Checks if a value is null
+    IsNull,
+}
+
 /// Evaluates the 'type' operator, which returns the type of a value.
 ///
 /// The operator takes a single argument and returns a string representing its type.
@@ -42,6 +60,184 @@ pub fn eval_type<'a>(args: &'a [&'a Token<'a>], arena: &'a DataArena) -> Result<
     Ok(arena.alloc(result))
 }
 
+/// Evaluates a type check operator, which tests the type of a value.
+///
+/// No coercion is applied, so a numeric string is not a number.
+///
+/// Examples:
+/// ```json
+/// {"is_number": 42} => true
+/// {"is_number": "42"} => false
+/// {"is_null": {"var": "missing"}} => true
+/// ```
+#[inline]
+pub fn eval_type_check<'a>(
+    type_check_op: TypeCheckOp,
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() != 1 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let value = evaluate(args[0], arena)?;
+    let matches = match type_check_op {
+        TypeCheckOp::IsString => value.is_string(),
+        TypeCheckOp::IsNumber => value.is_number(),
+        TypeCheckOp::IsBool => value.is_bool(),
+        TypeCheckOp::IsArray => value.is_array(),
+        TypeCheckOp::IsObject => value.is_object(),
+        TypeCheckOp::IsNull => value.is_null(),
+    };
+
+    Ok(if matches {
+        arena.true_value()
+    } else {
+        arena.false_value()
+    })
+}
+
+/// Evaluates the 'empty' operator, which tests if a value has no content.
+///
+/// Null, empty strings, empty arrays and empty objects are empty, as are
+/// strings of only whitespace unless the optional second argument is
+/// `false`. Other values, including `0` and `false`, are not empty.
+///
+/// Examples:
+/// ```json
+/// {"empty": {"var": "missing"}} => true
+/// {"empty": [[]]} => true
+/// {"empty": "  "} => true
+/// {"empty": ["  ", false]} => false
+/// {"empty": 0} => false
+/// ```
+#[inline]
+pub fn eval_empty<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let blank_is_empty = match args {
+        [_] => true,
+        [_, blank] => match evaluate(blank, arena)? {
+            DataValue::Bool(blank_is_empty) => *blank_is_empty,
+            _ => return Err(LogicError::InvalidArgumentsError),
+        },
+        _ => return Err(LogicError::InvalidArgumentsError),
+    };
+
+    Ok(if is_empty(evaluate(args[0], arena)?, blank_is_empty) {
+        arena.true_value()
+    } else {
+        arena.false_value()
+    })
+}
+
+/// Checks if a value is empty in the sense of the 'empty' operator.
+#[inline]
+fn is_empty(value: &DataValue, blank_is_empty: bool) -> bool {
+    match value {
+        DataValue::Null => true,
+        DataValue::String(s) if blank_is_empty => s.trim().is_empty(),
+        DataValue::String(s) => s.is_empty(),
+        DataValue::Array(items) => items.is_empty(),
+        DataValue::Object(entries) => entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Option keys accepted in a trailing options object by the 'default' operator.
+pub(crate) const DEFAULT_OPTION_KEYS: &[&str] = &["blank", "falsy"];
+
+/// Options accepted by the 'default' operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DefaultOptions {
+    /// Skip strings of only whitespace
+    blank: bool,
+    /// Skip every falsy value, such as `0` and `false`
+    falsy: bool,
+}
+
+impl Default for DefaultOptions {
+    fn default() -> Self {
+        Self {
+            blank: true,
+            falsy: false,
+        }
+    }
+}
+
+/// Splits the arguments of a 'default' operation into its candidate values
+/// and its trailing options object, if any.
+pub(crate) fn split_default_options<'a, 't>(
+    args: &'t [&'a Token<'a>],
+) -> (&'t [&'a Token<'a>], Option<&'a DataValue<'a>>) {
+    match args.split_last() {
+        Some((Token::Literal(options @ DataValue::Object(entries)), values))
+            if !entries.is_empty()
+                && entries
+                    .iter()
+                    .all(|(key, _)| DEFAULT_OPTION_KEYS.contains(key)) =>
+        {
+            (values, Some(options))
+        }
+        _ => (args, None),
+    }
+}
+
+/// Reads the options object of a 'default' operation.
+fn parse_default_options(options: Option<&DataValue>) -> Result<DefaultOptions> {
+    let mut parsed = DefaultOptions::default();
+    let Some(DataValue::Object(entries)) = options else {
+        return Ok(parsed);
+    };
+    for (key, value) in *entries {
+        let DataValue::Bool(flag) = value else {
+            return Err(LogicError::InvalidArgumentsError);
+        };
+        match *key {
+            "blank" => parsed.blank = *flag,
+            _ => parsed.falsy = *flag,
+        }
+    }
+    Ok(parsed)
+}
+
+/// Evaluates the 'default' operator, which returns the first value that is
+/// not empty.
+///
+/// Values are evaluated in order until one is not empty in the sense of the
+/// 'empty' operator, and null is returned if all of them are. A trailing
+/// options object adjusts what counts as empty: `"blank": false` keeps
+/// strings of only whitespace, and `"falsy": true` also skips values such
+/// as `0` and `false`.
+///
+/// Examples:
+/// ```json
+/// {"default": [{"var": "nickname"}, {"var": "name"}, "anonymous"]}
+/// {"default": [{"var": "count"}, 1, {"falsy": true}]}
+/// ```
+pub fn eval_default<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let (values, options) = split_default_options(args);
+    let options = parse_default_options(options)?;
+
+    for token in values {
+        let value = evaluate(token, arena)?;
+        let skipped = if options.falsy {
+            !value.coerce_to_bool() || is_empty(value, options.blank)
+        } else {
+            is_empty(value, options.blank)
+        };
+        if !skipped {
+            return Ok(value);
+        }
+    }
+
+    Ok(arena.null_value())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::logic::datalogic_core::DataLogicCore;
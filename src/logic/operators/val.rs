@@ -6,6 +6,7 @@
 use crate::arena::DataArena;
 use crate::logic::error::{LogicError, Result};
 use crate::logic::evaluator::evaluate;
+use crate::logic::operators::variable::{index_out_of_range, iteration_metadata, resolve_index};
 use crate::logic::token::Token;
 use crate::value::DataValue;
 use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
@@ -121,29 +122,6 @@ fn handle_string_path<'a>(
     access_property(current_context, path_str, arena)
 }
 
-/// Get the current index in an array operation context
-#[inline]
-fn get_current_index(arena: &DataArena) -> Result<&DataValue<'_>> {
-    // Check if we're in a scope with an array index
-    if let Some(DataValue::Number(n)) = arena.last_path_component() {
-        if let Some(idx) = n.as_i64() {
-            // Return the array index as a DataValue
-            return Ok(arena.alloc(DataValue::integer(idx)));
-        }
-    }
-    // If we can't find a valid index, return 0
-    Ok(arena.alloc(DataValue::integer(0)))
-}
-
-/// Get the current key in an array operation context
-#[inline]
-fn get_current_key(arena: &DataArena) -> Result<&DataValue<'_>> {
-    if let Some(DataValue::String(key)) = arena.last_path_component() {
-        return Ok(arena.alloc(DataValue::string(arena, key)));
-    }
-    Ok(arena.null_value())
-}
-
 /// Process complex path expressions that may involve scope jumps or nested access
 #[cold]
 #[inline(never)]
@@ -186,16 +164,13 @@ fn handle_numeric_path<'a>(
     current_context: &'a DataValue<'a>,
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
-    if let Some(idx) = n.as_i64() {
-        if idx >= 0 {
-            access_array_index(current_context, idx as usize, arena)
-        } else {
-            // Negative index
-            Ok(arena.null_value())
-        }
-    } else {
-        // Not a valid index
-        Ok(arena.null_value())
+    match (n.as_i64(), current_context) {
+        (Some(index), DataValue::Array(items)) => match resolve_index(index, items.len()) {
+            Some(index) => Ok(&items[index]),
+            None => index_out_of_range(&index.to_string(), index, items.len(), &None, arena),
+        },
+        // Not an array or not a valid index
+        _ => Ok(arena.null_value()),
     }
 }
 
@@ -215,12 +190,13 @@ fn handle_scope_jump<'a>(
 
     // If there are additional path components beyond the jump, navigate them
     if path_components.len() > 1 {
-        // Special case for accessing the index after a scope jump
-        if path_components.len() == 2 {
-            if matches!(path_components[1], DataValue::String(key) if key == "index") {
-                return handle_index_with_jump(jump, arena);
-            } else if matches!(path_components[1], DataValue::String(key) if key == "key") {
-                return get_current_key(arena);
+        // Odd jumps reach the metadata of an iteration, such as its index
+        if let [_, DataValue::String(name)] = path_components {
+            let jump = jump.unsigned_abs() as usize;
+            if jump % 2 == 1 {
+                if let Some(value) = iteration_metadata(name, jump / 2, arena) {
+                    return Ok(value);
+                }
             }
         }
 
@@ -230,47 +206,6 @@ fn handle_scope_jump<'a>(
     Ok(jumped_context)
 }
 
-/// Handle the special case of accessing "index" with a scope jump
-#[cold]
-#[inline(never)]
-fn handle_index_with_jump(jump: i64, arena: &DataArena) -> Result<&DataValue<'_>> {
-    let path_len = arena.path_chain_len();
-
-    // For positive jump, we need to get the current array index
-    // A jump of +1 means look at the current array index
-    if jump > 0 && jump <= path_len as i64 {
-        return get_current_index(arena);
-    }
-
-    // For negative jump, check if we're looking at the current array index
-    let jump_level = jump.unsigned_abs() as usize;
-    if jump == -1 {
-        // For -1 jump, prioritize checking the current array index
-        return get_current_index(arena);
-    }
-
-    // For other negative jumps, use path chain navigation
-    if jump_level < path_len {
-        return arena.with_path_chain(|path_components| {
-            let idx_position = path_len - jump_level;
-
-            if idx_position > 0 && idx_position <= path_components.len() {
-                if let DataValue::Number(n) = path_components[idx_position - 1] {
-                    if let Some(idx) = n.as_i64() {
-                        return Ok(arena.alloc(DataValue::integer(idx)));
-                    }
-                }
-            }
-
-            // If we can't find a valid index, return 0
-            Ok(arena.alloc(DataValue::integer(0)))
-        });
-    }
-
-    // If we can't find a valid index, return 0
-    Ok(arena.alloc(DataValue::integer(0)))
-}
-
 /// Access a property from an object or an array using a string key
 #[inline]
 fn access_property<'a>(
@@ -298,13 +233,14 @@ fn access_property<'a>(
         }
         DataValue::Array(items) => {
             // Try to parse the key as an array index
-            if let Ok(index) = key.parse::<usize>() {
-                if index < items.len() {
-                    return Ok(&items[index]);
-                }
+            match key.parse::<i64>() {
+                Ok(index) => match resolve_index(index, items.len()) {
+                    Some(index) => Ok(&items[index]),
+                    None => index_out_of_range(key, index, items.len(), &None, arena),
+                },
+                // Invalid index
+                Err(_) => Ok(arena.null_value()),
             }
-            // Invalid index or out of bounds
-            Ok(arena.null_value())
         }
         DataValue::DateTime(dt) => {
             // Direct access to datetime properties
@@ -364,22 +300,6 @@ fn access_duration_property<'a>(
     }
 }
 
-/// Access an array element by index
-#[inline]
-fn access_array_index<'a>(
-    data: &'a DataValue<'a>,
-    index: usize,
-    arena: &'a DataArena,
-) -> Result<&'a DataValue<'a>> {
-    if let DataValue::Array(items) = data {
-        if index < items.len() {
-            return Ok(&items[index]);
-        }
-    }
-    // Not an array or index out of bounds
-    Ok(arena.null_value())
-}
-
 /// Navigate through a nested path represented as an array of components
 #[inline]
 fn navigate_nested_path<'a>(
@@ -395,16 +315,22 @@ fn navigate_nested_path<'a>(
         match component {
             DataValue::String(key) => {
                 // Handle string component
-                current = match handle_string_component(current, key)? {
-                    Some(value) => value,
-                    None => return Ok(arena.null_value()),
+                current = match handle_string_component(current, key) {
+                    Ok(Some(value)) => value,
+                    Ok(None) => return Ok(arena.null_value()),
+                    Err((index, len)) => {
+                        return nested_index_out_of_range(path_components, index, len, arena)
+                    }
                 };
             }
             DataValue::Number(n) => {
                 // Handle number component
-                current = match handle_number_component(current, n)? {
-                    Some(value) => value,
-                    None => return Ok(arena.null_value()),
+                current = match handle_number_component(current, n) {
+                    Ok(Some(value)) => value,
+                    Ok(None) => return Ok(arena.null_value()),
+                    Err((index, len)) => {
+                        return nested_index_out_of_range(path_components, index, len, arena)
+                    }
                 };
             }
             _ => {
@@ -418,12 +344,15 @@ fn navigate_nested_path<'a>(
     Ok(current)
 }
 
+/// The index and the length of an array indexed out of range
+type OutOfRange = (i64, usize);
+
 /// Handle navigation through a string component in a path
 #[inline]
 fn handle_string_component<'a>(
     current: &'a DataValue<'a>,
     key: &str,
-) -> Result<Option<&'a DataValue<'a>>> {
+) -> std::result::Result<Option<&'a DataValue<'a>>, OutOfRange> {
     match current {
         DataValue::Object(entries) => {
             // Look for the key in the object
@@ -435,16 +364,12 @@ fn handle_string_component<'a>(
             // Property not found
             Ok(None)
         }
-        DataValue::Array(items) => {
+        DataValue::Array(items) => match key.parse::<i64>() {
             // Try to parse the key as an array index
-            if let Ok(index) = key.parse::<usize>() {
-                if index < items.len() {
-                    return Ok(Some(&items[index]));
-                }
-            }
-            // Invalid index or out of bounds
-            Ok(None)
-        }
+            Ok(index) => index_array(items, index).map(Some),
+            // Invalid index
+            Err(_) => Ok(None),
+        },
         _ => {
             // Not an object or array
             Ok(None)
@@ -457,19 +382,43 @@ fn handle_string_component<'a>(
 fn handle_number_component<'a>(
     current: &'a DataValue<'a>,
     n: &crate::value::NumberValue,
-) -> Result<Option<&'a DataValue<'a>>> {
-    if let Some(idx) = n.as_i64() {
-        if idx >= 0 {
-            let index = idx as usize;
-            if let DataValue::Array(items) = current {
-                if index < items.len() {
-                    return Ok(Some(&items[index]));
-                }
-            }
-        }
+) -> std::result::Result<Option<&'a DataValue<'a>>, OutOfRange> {
+    match (n.as_i64(), current) {
+        (Some(index), DataValue::Array(items)) => index_array(items, index).map(Some),
+        // Not an array or invalid index
+        _ => Ok(None),
     }
-    // Not an array, index out of bounds, or invalid index
-    Ok(None)
+}
+
+/// Reads an item of an array, negative indices counting from the end.
+#[inline]
+fn index_array<'a>(
+    items: &'a [DataValue<'a>],
+    index: i64,
+) -> std::result::Result<&'a DataValue<'a>, OutOfRange> {
+    match resolve_index(index, items.len()) {
+        Some(index) => Ok(&items[index]),
+        None => Err((index, items.len())),
+    }
+}
+
+/// Reads an index out of the range of an array in a nested path.
+#[cold]
+fn nested_index_out_of_range<'a>(
+    path_components: &[DataValue],
+    index: i64,
+    len: usize,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let path = path_components
+        .iter()
+        .map(|component| match component {
+            DataValue::String(key) => key.to_string(),
+            component => component.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+    index_out_of_range(&path, index, len, &None, arena)
 }
 
 /// Evaluates if a path exists in the input data.
@@ -0,0 +1,146 @@
+//! Validate operator implementation.
+//!
+//! This module provides the implementation of the "validate" operator, which
+//! checks a data object against a set of required fields, expected types and
+//! custom rules, and returns a structured report of the problems found.
+//!
+//! ```json
+//! {"validate": {
+//!     "required": ["name", "email"],
+//!     "types": {"age": "number"},
+//!     "custom": [{"rule": {">=": [{"var": "age"}, 18]}, "path": "age", "code": "too_young"}]
+//! }}
+//! ```
+//!
+//! The parser lowers the specification into the operator arguments
+//! `[required, types, [rule, details]...]`, where `details` is an object
+//! holding the optional `path`, `code` and `message` of a custom check.
+
+use crate::arena::DataArena;
+use crate::logic::error::{LogicError, Result};
+use crate::logic::evaluator::evaluate;
+use crate::logic::operators::{missing, variable};
+use crate::logic::token::Token;
+use crate::value::DataValue;
+use bumpalo::collections::Vec as BumpVec;
+
+/// Type names accepted in the `types` section.
+const TYPE_NAMES: &[&str] = &[
+    "null", "boolean", "number", "string", "array", "object", "datetime", "duration",
+];
+
+/// Appends a problem entry to the report.
+fn push_problem<'a>(
+    errors: &mut BumpVec<'a, DataValue<'a>>,
+    path: &'a str,
+    code: &'a str,
+    message: &str,
+    arena: &'a DataArena,
+) {
+    let entry = [
+        (arena.intern_str("path"), DataValue::String(path)),
+        (arena.intern_str("code"), DataValue::String(code)),
+        (
+            arena.intern_str("message"),
+            DataValue::String(arena.alloc_str(message)),
+        ),
+    ];
+    errors.push(DataValue::object(arena, &entry));
+}
+
+/// Looks up a string field of a custom check's details.
+fn detail<'a>(details: &'a DataValue<'a>, key: &str) -> Option<&'a str> {
+    match details {
+        DataValue::Object(entries) => entries
+            .iter()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, value)| value.as_str()),
+        _ => None,
+    }
+}
+
+/// Evaluates a validate operation.
+///
+/// Returns an object of the form `{"valid": bool, "errors": [...]}`, where each
+/// error has a `path`, a `code` (`required`, `type` or the custom code) and a
+/// human-readable `message`.
+pub fn eval_validate<'a>(
+    args: &'a [&'a Token<'a>],
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    if args.len() < 2 {
+        return Err(LogicError::InvalidArgumentsError);
+    }
+
+    let mut errors = arena.get_data_value_vec();
+
+    // Required fields, including wildcard paths
+    if let DataValue::Array(required) = evaluate(args[0], arena)? {
+        let mut missing_paths = arena.get_data_value_vec();
+        for name in required.iter().filter_map(|name| name.as_str()) {
+            missing::check_name(name, &mut missing_paths, arena);
+        }
+        for path in missing_paths {
+            let DataValue::String(path) = path else {
+                continue;
+            };
+            let message = format!("{} is required", path);
+            push_problem(&mut errors, path, "required", &message, arena);
+        }
+    }
+
+    // Expected types; absent values are left to the required check
+    if let DataValue::Object(types) = evaluate(args[1], arena)? {
+        for (path, expected) in types.iter() {
+            let expected = match expected.as_str() {
+                Some(name) if TYPE_NAMES.contains(&name) => name,
+                _ => return Err(LogicError::InvalidArgumentsError),
+            };
+
+            let value = variable::evaluate_variable(path, &None, arena)?;
+            let actual = value.type_name();
+            if value.is_null() || actual == expected {
+                continue;
+            }
+
+            let message = format!("{} must be of type {}, got {}", path, expected, actual);
+            push_problem(&mut errors, path, "type", &message, arena);
+        }
+    }
+
+    // Custom checks fail when their rule is falsy
+    for check in &args[2..] {
+        let Token::ArrayLiteral(parts) = check else {
+            return Err(LogicError::InvalidArgumentsError);
+        };
+        let (Some(rule), Some(details)) = (parts.first(), parts.get(1)) else {
+            return Err(LogicError::InvalidArgumentsError);
+        };
+
+        if evaluate(rule, arena)?.coerce_to_bool() {
+            continue;
+        }
+
+        let details = evaluate(details, arena)?;
+        let path = detail(details, "path").unwrap_or("");
+        let code = detail(details, "code").unwrap_or("custom");
+        let message = match detail(details, "message") {
+            Some(message) => message.to_string(),
+            None if path.is_empty() => "validation failed".to_string(),
+            None => format!("{} is invalid", path),
+        };
+        push_problem(&mut errors, path, code, &message, arena);
+    }
+
+    let result = [
+        (
+            arena.intern_str("valid"),
+            DataValue::Bool(errors.is_empty()),
+        ),
+        (
+            arena.intern_str("errors"),
+            DataValue::Array(arena.bump_vec_into_slice(errors)),
+        ),
+    ];
+    Ok(arena.alloc(DataValue::object(arena, &result)))
+}
@@ -3,9 +3,10 @@
 //! This module provides the implementation of the variable operator.
 
 use crate::arena::DataArena;
-use crate::logic::error::Result;
+use crate::logic::error::{LogicError, Result};
 use crate::logic::evaluator::evaluate;
 use crate::logic::token::Token;
+use crate::semantics::OutOfRangeIndex;
 use crate::value::DataValue;
 
 /// Evaluates a variable reference.
@@ -22,15 +23,106 @@ pub fn evaluate_variable<'a>(
         return Ok(current_context);
     }
 
+    // Values assigned in an enclosing do block take precedence over the data
+    let name = path.split('.').next().unwrap_or(path);
+    if let Some(value) = arena.binding(name) {
+        return match path.split_once('.') {
+            Some((_, rest)) => process_nested_path(rest, default, value, arena),
+            None => Ok(value),
+        };
+    }
+
+    // Metadata of the current iteration, such as `#index`
+    if let Some(name) = path.strip_prefix('#') {
+        if let Some(value) = iteration_metadata(name, 0, arena) {
+            return Ok(value);
+        }
+    }
+
     // Fast path for direct property access (no dots)
     if !path.contains('.') {
         return evaluate_simple_path(path, default, current_context, arena);
     }
 
+    // Paths starting with `../` are resolved against an enclosing iteration
+    if path == ".." || path.starts_with("../") {
+        return evaluate_parent_path(path, default, arena);
+    }
+
     // For paths with dots, process nested path
     process_nested_path(path, default, current_context, arena)
 }
 
+/// Evaluates a path relative to an enclosing iteration.
+///
+/// Each leading `../` leaves one iteration, so `../../total` inside a nested
+/// `map` reads `total` from the data of the outer one.
+#[cold]
+#[inline(never)]
+fn evaluate_parent_path<'a>(
+    path: &str,
+    default: &Option<&'a Token<'a>>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    let mut levels = 0;
+    let mut rest = path;
+    while let Some(stripped) = rest.strip_prefix("../") {
+        levels += 1;
+        rest = stripped;
+    }
+    if rest == ".." {
+        levels += 1;
+        rest = "";
+    }
+
+    // Metadata of an enclosing iteration, such as `../#index`
+    if let Some(value) = rest
+        .strip_prefix('#')
+        .and_then(|name| iteration_metadata(name, levels, arena))
+    {
+        return Ok(value);
+    }
+
+    // Every iteration spans two levels of the scope chain
+    let context = arena
+        .current_context(levels * 2)
+        .unwrap_or_else(|| arena.null_value());
+
+    if rest.is_empty() {
+        Ok(context)
+    } else if !rest.contains('.') {
+        evaluate_simple_path(rest, default, context, arena)
+    } else {
+        process_nested_path(rest, default, context, arena)
+    }
+}
+
+/// Reads metadata of the item of an iteration, `level` iterations up.
+///
+/// The names are `index`, `key` (the property name when iterating over an
+/// object, null otherwise), `first` and `last`. Returns `None` for any other
+/// name, or if there is no iteration at that level.
+pub(crate) fn iteration_metadata<'a>(
+    name: &str,
+    level: usize,
+    arena: &'a DataArena,
+) -> Option<&'a DataValue<'a>> {
+    if !matches!(name, "index" | "key" | "first" | "last") {
+        return None;
+    }
+    let (position, key) = arena.iteration(level)?;
+    let value = match name {
+        "index" => DataValue::integer(position.index as i64),
+        "key" => match key {
+            DataValue::String(key) => DataValue::String(key),
+            _ => DataValue::Null,
+        },
+        "first" => DataValue::Bool(position.index == 0),
+        _ => DataValue::Bool(position.index + 1 == position.len),
+    };
+    Some(arena.alloc(value))
+}
+
 /// Process a nested path (with dots)
 #[inline]
 fn process_nested_path<'a>(
@@ -59,10 +151,13 @@ fn process_nested_path<'a>(
                     None => return use_default_or_null(default, arena),
                 }
             }
-            DataValue::Array(_) => {
-                current = match process_array_component(current, component) {
-                    Some(value) => value,
-                    None => return use_default_or_null(default, arena),
+            DataValue::Array(items) => {
+                let Ok(index) = component.parse::<i64>() else {
+                    return use_default_or_null(default, arena);
+                };
+                current = match resolve_index(index, items.len()) {
+                    Some(index) => &items[index],
+                    None => return index_out_of_range(path, index, items.len(), default, arena),
                 }
             }
             _ => {
@@ -105,21 +200,6 @@ fn process_object_component<'a>(
     find_in_object(obj, component)
 }
 
-/// Process a component when the current value is an array
-#[inline]
-fn process_array_component<'a>(
-    arr: &'a DataValue<'a>,
-    component: &str,
-) -> Option<&'a DataValue<'a>> {
-    // Try to parse the component as an index
-    if let Ok(index) = component.parse::<usize>() {
-        get_array_index(arr, index)
-    } else {
-        // Not a valid index
-        None
-    }
-}
-
 /// Helper function to evaluate a simple path (no dots)
 #[inline]
 fn evaluate_simple_path<'a>(
@@ -130,7 +210,12 @@ fn evaluate_simple_path<'a>(
 ) -> Result<&'a DataValue<'a>> {
     // Special case for numeric indices - direct array access
     if let Ok(index) = path.parse::<usize>() {
-        return handle_array_index_access(data, index, default, arena);
+        return handle_array_index_access(path, data, index as i64, default, arena);
+    }
+
+    // Negative indices count from the end of arrays
+    if let (DataValue::Array(_), Ok(index)) = (data, path.parse::<i64>()) {
+        return handle_array_index_access(path, data, index, default, arena);
     }
 
     // Otherwise, look for a matching property in the object
@@ -145,17 +230,58 @@ fn evaluate_simple_path<'a>(
 /// Handle direct array index access for simple paths
 #[inline]
 fn handle_array_index_access<'a>(
+    path: &str,
     data: &'a DataValue<'a>,
-    index: usize,
+    index: i64,
     default: &Option<&'a Token<'a>>,
     arena: &'a DataArena,
 ) -> Result<&'a DataValue<'a>> {
-    if let Some(value) = get_array_index(data, index) {
-        return Ok(value);
+    match data {
+        DataValue::Array(items) => match resolve_index(index, items.len()) {
+            Some(index) => Ok(&items[index]),
+            None => index_out_of_range(path, index, items.len(), default, arena),
+        },
+        // Not an array, use default
+        _ => use_default_or_null(default, arena),
     }
+}
 
-    // Not found or not an array, use default
-    use_default_or_null(default, arena)
+/// Resolves an index into an array of `len` items, negative indices counting
+/// from the end.
+///
+/// Returns `None` if the index is out of range.
+#[inline]
+pub(crate) fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 {
+        len.checked_sub(usize::try_from(index.unsigned_abs()).ok()?)?
+    } else {
+        usize::try_from(index).ok()?
+    };
+    (index < len).then_some(index)
+}
+
+/// Reads an index out of the range of an array, as set by
+/// [`DataArena::set_out_of_range_index`].
+#[cold]
+#[inline(never)]
+pub(crate) fn index_out_of_range<'a>(
+    path: &str,
+    index: i64,
+    len: usize,
+    default: &Option<&'a Token<'a>>,
+    arena: &'a DataArena,
+) -> Result<&'a DataValue<'a>> {
+    match arena.out_of_range_index() {
+        OutOfRangeIndex::Default => use_default_or_null(default, arena),
+        OutOfRangeIndex::Null => Ok(arena.null_value()),
+        OutOfRangeIndex::Error => Err(LogicError::VariableError {
+            path: path.to_string(),
+            reason: format!(
+                "Index {} is out of range for an array of {} items",
+                index, len
+            ),
+        }),
+    }
 }
 
 /// Helper function to find a key in an object
@@ -201,17 +327,6 @@ fn find_in_small_object<'a>(
     None
 }
 
-/// Helper function to get an index from an array
-#[inline]
-fn get_array_index<'a>(arr: &'a DataValue<'a>, index: usize) -> Option<&'a DataValue<'a>> {
-    if let DataValue::Array(items) = arr {
-        if index < items.len() {
-            return Some(&items[index]);
-        }
-    }
-    None
-}
-
 /// Helper function to use the default value or return null
 #[inline]
 fn use_default_or_null<'a>(
@@ -228,10 +343,10 @@ fn use_default_or_null<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::arena::DataArena;
+    use crate::arena::{DataArena, IterationPosition};
     use crate::logic::operators::val::eval_exists;
     use crate::logic::{DataLogicCore, Logic, OperatorType};
-    use crate::value::{DataValue, FromJson};
+    use crate::value::{DataValue, FromJson, ToJson};
 
     use serde_json::json;
 
@@ -349,6 +464,71 @@ mod tests {
         assert_eq!(result, json!(["Alice", "Bob", "Charlie"]));
     }
 
+    #[test]
+    fn test_variable_parent_path() {
+        let arena = DataArena::new();
+
+        let data = DataValue::from_json(&json!({"d": 1, "orders": [{"d": 2}]}), &arena);
+        let order = DataValue::from_json(&json!({"d": 2, "lines": [3]}), &arena);
+        let line = DataValue::from_json(&json!(3), &arena);
+        arena.set_root_context(&data);
+        arena.set_current_context(&data, &DataValue::String("$"));
+
+        // Enter a map over the orders and then a map over their lines
+        let chain_len = arena.path_chain_len();
+        arena.set_current_context(&order, arena.alloc(DataValue::integer(0)));
+        arena.set_current_context(&line, arena.alloc(DataValue::integer(0)));
+
+        let result = evaluate_variable("../d", &None, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(2));
+        let result = evaluate_variable("../../d", &None, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(1));
+        let result = evaluate_variable("..", &None, &arena).unwrap();
+        assert_eq!(result, &order);
+        let result = evaluate_variable("../../../d", &None, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(1));
+
+        // Leaving the iterations restores the data as the current context
+        while arena.path_chain_len() > chain_len {
+            arena.pop_path_component();
+        }
+        let result = evaluate_variable("d", &None, &arena).unwrap();
+        assert_eq!(result.as_i64(), Some(1));
+    }
+
+    #[test]
+    fn test_iteration_metadata() {
+        let arena = DataArena::new();
+
+        let data = DataValue::from_json(&json!({"#index": "data"}), &arena);
+        let row = DataValue::from_json(&json!({"x": 1}), &arena);
+        let cell = DataValue::from_json(&json!(3), &arena);
+        arena.set_root_context(&data);
+        arena.set_current_context(&data, &DataValue::String("$"));
+
+        // Outside an iteration, names starting with # are read from the data
+        let result = evaluate_variable("#index", &None, &arena).unwrap();
+        assert_eq!(result.as_str(), Some("data"));
+        assert_eq!(iteration_metadata("index", 0, &arena), None);
+
+        // Enter a map over an object and then a map over an array
+        let position = IterationPosition::new(1, 2);
+        arena.set_iteration_context(&row, &DataValue::String("b"), position);
+        let position = IterationPosition::new(0, 3);
+        arena.set_iteration_context(&cell, arena.alloc(DataValue::integer(0)), position);
+
+        let read = |path| evaluate_variable(path, &None, &arena).unwrap().to_json();
+        assert_eq!(read("#index"), json!(0));
+        assert_eq!(read("#key"), json!(null));
+        assert_eq!(read("#first"), json!(true));
+        assert_eq!(read("#last"), json!(false));
+        assert_eq!(read("../#index"), json!(1));
+        assert_eq!(read("../#key"), json!("b"));
+        assert_eq!(read("../#last"), json!(true));
+        assert_eq!(read("../../#index"), json!("data"));
+        assert_eq!(iteration_metadata("size", 0, &arena), None);
+    }
+
     #[test]
     fn test_variable_with_missing_data() {
         let core = DataLogicCore::new();
@@ -458,4 +638,57 @@ mod tests {
         let result = core.apply(&exists_rule, &data_json).unwrap();
         assert_eq!(result, json!(false));
     }
+
+    #[test]
+    fn test_out_of_range_index() {
+        assert_eq!(resolve_index(-1, 3), Some(2));
+        assert_eq!(resolve_index(-3, 3), Some(0));
+        assert_eq!(resolve_index(-4, 3), None);
+        assert_eq!(resolve_index(3, 3), None);
+        assert_eq!(resolve_index(i64::MIN, 3), None);
+
+        let mut dl = crate::DataLogic::new();
+        let data = json!({"items": [1, 2, 3]});
+        let rules = [
+            json!({"var": ["items.5", "default"]}),
+            json!({"var": ["items.-5", "default"]}),
+            json!({"val": ["items", 5]}),
+        ];
+        let evaluate_all = |dl: &crate::DataLogic| {
+            rules
+                .iter()
+                .map(|rule| dl.evaluate_json(rule, &data, None))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            evaluate_all(&dl),
+            [Ok(json!("default")), Ok(json!("default")), Ok(json!(null))]
+        );
+
+        dl.set_out_of_range_index(OutOfRangeIndex::Null);
+        assert_eq!(
+            evaluate_all(&dl),
+            [Ok(json!(null)), Ok(json!(null)), Ok(json!(null))]
+        );
+
+        dl.set_out_of_range_index(OutOfRangeIndex::Error);
+        let errors: Vec<String> = evaluate_all(&dl)
+            .into_iter()
+            .map(|result| result.unwrap_err().to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "Variable 'items.5' error: Index 5 is out of range for an array of 3 items",
+                "Variable 'items.-5' error: Index -5 is out of range for an array of 3 items",
+                "Variable 'items.5' error: Index 5 is out of range for an array of 3 items",
+            ]
+        );
+
+        // Missing keys are not affected
+        assert_eq!(
+            dl.evaluate_json(&json!({"var": ["other.0", 1]}), &data, None),
+            Ok(json!(1))
+        );
+    }
 }
@@ -4,9 +4,15 @@
 //! precomputing static parts of the expression at compile time.
 
 use super::error::Result;
+use super::operators::array::{self, ArrayOp};
+use super::operators::control::ControlOp;
+use super::operators::datetime::DateTimeOp;
+use super::operators::string::{RegexPattern, StringOp};
 use super::token::{OperatorType, Token};
+use super::trie::{StringTrie, STRING_TRIE_THRESHOLD};
 use crate::arena::DataArena;
 use crate::logic::evaluator::evaluate;
+use crate::semantics::IntegerOverflow;
 use crate::value::DataValue;
 
 /// Optimizes a token by evaluating static parts of the expression.
@@ -27,11 +33,31 @@ pub fn optimize<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a To
 
         // Operators might be optimizable if their arguments are static
         Token::Operator { op_type, args } => {
-            // Special case: missing and missing_some operators always need data
+            // Special case: missing and missing_some operators always need data,
+            // now must be read, log must report, assign must store and
+            // unpreserve must read its rule's data at evaluation time
+            // Annotations are kept so that their notes survive, but the
+            // rules they document are optimized
+            if *op_type == OperatorType::Annotate {
+                if let Token::ArrayLiteral(items) = args {
+                    let items = items
+                        .iter()
+                        .map(|item| optimize(item, arena))
+                        .collect::<Result<Vec<_>>>()?;
+                    let args = arena.alloc(Token::ArrayLiteral(items));
+                    return Ok(arena.alloc(Token::operator(*op_type, args)));
+                }
+            }
+
             if *op_type == OperatorType::Missing
                 || *op_type == OperatorType::MissingSome
                 || *op_type == OperatorType::Exists
                 || *op_type == OperatorType::Val
+                || *op_type == OperatorType::Validate
+                || *op_type == OperatorType::Log
+                || *op_type == OperatorType::Assign
+                || *op_type == OperatorType::Unpreserve
+                || *op_type == OperatorType::DateTime(DateTimeOp::Now)
             {
                 // Just optimize the arguments
                 let optimized_args = optimize(args, arena)?;
@@ -39,7 +65,7 @@ pub fn optimize<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a To
             }
 
             // Optimize the arguments
-            let optimized_args = optimize(args, arena)?;
+            let mut optimized_args = optimize(args, arena)?;
 
             // Check if all arguments are literals or static expressions
             let is_static = match optimized_args {
@@ -52,24 +78,8 @@ pub fn optimize<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a To
 
             // If all arguments are static, evaluate the expression
             if is_static {
-                // Create a dummy data value for evaluation
-                let dummy_data = arena.alloc(DataValue::Null);
-                arena.set_current_context(dummy_data, &DataValue::String("$"));
-
-                // Create the operator token in the arena
                 let op_token = arena.alloc(Token::operator(*op_type, optimized_args));
-
-                // Try to evaluate the expression
-                match evaluate(op_token, arena) {
-                    Ok(result) => {
-                        // Return the result as a literal
-                        return Ok(arena.alloc(Token::literal(result.clone())));
-                    }
-                    Err(_) => {
-                        // If evaluation fails, just return the optimized operator
-                        return Ok(op_token);
-                    }
-                }
+                return Ok(fold_constant(op_token, arena));
             }
 
             // If not all arguments are static, check if we can optimize nested expressions
@@ -110,29 +120,35 @@ pub fn optimize<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a To
                     let new_array_token = arena.alloc(new_array_literal);
 
                     if all_literals {
-                        // Create a dummy data value for evaluation
-                        let dummy_data = arena.alloc(DataValue::Null);
-                        arena.set_current_context(dummy_data, &DataValue::String("$"));
-                        // Create the operator token in the arena
                         let op_token = arena.alloc(Token::operator(*op_type, new_array_token));
-
-                        // Try to evaluate the expression
-                        match evaluate(op_token, arena) {
-                            Ok(result) => {
-                                // Return the result as a literal
-                                return Ok(arena.alloc(Token::literal(result.clone())));
-                            }
-                            Err(_) => {
-                                // If evaluation fails, just return the optimized operator
-                                return Ok(op_token);
-                            }
-                        }
+                        return Ok(fold_constant(op_token, arena));
                     }
 
-                    return Ok(arena.alloc(Token::operator(*op_type, new_array_token)));
+                    optimized_args = new_array_token;
                 }
             }
 
+            // Branches guarded by constant conditions are resolved once
+            if let Some(pruned) = prune_branches(*op_type, optimized_args, arena) {
+                return Ok(pruned);
+            }
+
+            // Large constant string lists are matched through a trie
+            if let Some(compiled) = compile_string_list(*op_type, optimized_args, arena) {
+                return Ok(compiled);
+            }
+
+            // Constant regex patterns are compiled once
+            if let Some(compiled) = compile_regex(*op_type, optimized_args, arena)? {
+                return Ok(compiled);
+            }
+
+            // Case-insensitive membership tests against a constant haystack
+            // fold the haystack once here instead of on every evaluation
+            if let Some(folded) = fold_in_haystack(*op_type, optimized_args, arena) {
+                return Ok(folded);
+            }
+
             // If nothing was optimized, just return the optimized operator
             Ok(arena.alloc(Token::operator(*op_type, optimized_args)))
         }
@@ -145,5 +161,263 @@ pub fn optimize<'a>(token: &'a Token<'a>, arena: &'a DataArena) -> Result<&'a To
             // Return the optimized custom operator
             Ok(arena.alloc(Token::custom_operator(name, optimized_args)))
         }
+
+        // Compiled and case-folded operations are produced by the optimizer itself
+        Token::CompiledMatch { .. } | Token::CompiledRegex { .. } | Token::CaseFolded { .. } => {
+            Ok(token)
+        }
+    }
+}
+
+/// Evaluates an operator whose arguments are all literals into a literal.
+///
+/// The operator is kept if it fails, and so is integer arithmetic that
+/// overflows, so that it follows the overflow policy in force when the rule
+/// is evaluated rather than the one in force when it was parsed.
+fn fold_constant<'a>(op_token: &'a Token<'a>, arena: &'a DataArena) -> &'a Token<'a> {
+    // Create a dummy data value for evaluation
+    let dummy_data = arena.alloc(DataValue::Null);
+    arena.set_current_context(dummy_data, &DataValue::String("$"));
+
+    let policy = arena.integer_overflow();
+    arena.set_integer_overflow(IntegerOverflow::Error);
+    let result = evaluate(op_token, arena);
+    arena.set_integer_overflow(policy);
+
+    match result {
+        Ok(result) => arena.alloc(Token::literal(result.clone())),
+        Err(_) => op_token,
+    }
+}
+
+/// Removes branches of `if`, `and` and `or` that can never be taken because
+/// the conditions guarding them are literals, and the literals of a `do`
+/// block whose values are discarded.
+///
+/// Returns `None` if no branch could be removed.
+fn prune_branches<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Option<&'a Token<'a>> {
+    let Token::ArrayLiteral(items) = args else {
+        return None;
+    };
+
+    let kept = match op_type {
+        OperatorType::Control(ControlOp::If) => prune_if_branches(items)?,
+        OperatorType::Control(ControlOp::And) => prune_short_circuit(items, false)?,
+        OperatorType::Control(ControlOp::Or) => prune_short_circuit(items, true)?,
+        OperatorType::Do => prune_sequence(items)?,
+        _ => return None,
+    };
+
+    match kept.as_slice() {
+        [] => Some(arena.alloc(Token::literal(DataValue::Null))),
+        // A do block can only be unwrapped if nothing is assigned in it
+        [only] if op_type != OperatorType::Do || !assigns_in_block(only) => Some(*only),
+        _ => Some(arena.alloc(Token::operator(
+            op_type,
+            arena.alloc(Token::ArrayLiteral(kept)),
+        ))),
+    }
+}
+
+/// Drops the condition/value pairs of an `if` whose condition is a falsy
+/// literal, and everything after the first truthy literal condition.
+///
+/// The result is the remaining argument list, where a single item is the
+/// branch that is always taken.
+fn prune_if_branches<'a>(items: &[&'a Token<'a>]) -> Option<Vec<&'a Token<'a>>> {
+    let mut kept = Vec::with_capacity(items.len());
+    let mut changed = false;
+
+    let mut i = 0;
+    while i + 1 < items.len() {
+        match items[i] {
+            Token::Literal(condition) if condition.coerce_to_bool() => {
+                // This branch is always taken, so it becomes the else value
+                kept.push(items[i + 1]);
+                return Some(kept);
+            }
+            Token::Literal(_) => changed = true,
+            _ => kept.extend([items[i], items[i + 1]]),
+        }
+        i += 2;
+    }
+
+    if i < items.len() {
+        kept.push(items[i]);
+    }
+
+    changed.then_some(kept)
+}
+
+/// Drops the arguments of `and` (`short_on == false`) or `or`
+/// (`short_on == true`) that can't affect the result.
+///
+/// Literals that don't short-circuit are only kept in last position, and
+/// nothing after a literal that always short-circuits is evaluated.
+fn prune_short_circuit<'a>(items: &[&'a Token<'a>], short_on: bool) -> Option<Vec<&'a Token<'a>>> {
+    if items.len() < 2 {
+        return None;
     }
+
+    let mut kept = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        match item {
+            Token::Literal(value) if value.coerce_to_bool() == short_on => {
+                kept.push(*item);
+                break;
+            }
+            Token::Literal(_) if index + 1 < items.len() => {}
+            _ => kept.push(*item),
+        }
+    }
+
+    (kept.len() < items.len()).then_some(kept)
+}
+
+/// Drops the literals of a `do` block other than the last, as evaluating
+/// them has no effect.
+///
+/// A block of a single expression is also returned, so that it can be
+/// unwrapped.
+fn prune_sequence<'a>(items: &[&'a Token<'a>]) -> Option<Vec<&'a Token<'a>>> {
+    let last = items.len().checked_sub(1)?;
+    let kept: Vec<_> = items
+        .iter()
+        .enumerate()
+        .filter(|&(index, item)| index == last || !item.is_literal())
+        .map(|(_, item)| *item)
+        .collect();
+
+    (kept.len() == 1 || kept.len() < items.len()).then_some(kept)
+}
+
+/// Checks if a token assigns to the `do` block enclosing it.
+///
+/// Assignments within a nested `do` block belong to that block.
+fn assigns_in_block(token: &Token) -> bool {
+    match token {
+        Token::Literal(_) | Token::Variable { default: None, .. } => false,
+        Token::Variable {
+            default: Some(default),
+            ..
+        } => assigns_in_block(default),
+        Token::DynamicVariable { path_expr, default } => {
+            assigns_in_block(path_expr) || default.is_some_and(assigns_in_block)
+        }
+        Token::ArrayLiteral(items) => items.iter().any(|item| assigns_in_block(item)),
+        Token::Operator {
+            op_type: OperatorType::Assign,
+            ..
+        } => true,
+        Token::Operator {
+            op_type: OperatorType::Do,
+            ..
+        } => false,
+        Token::Operator { args, .. }
+        | Token::CustomOperator { args, .. }
+        | Token::CompiledMatch { args, .. }
+        | Token::CompiledRegex { args, .. }
+        | Token::CaseFolded { args, .. } => assigns_in_block(args),
+    }
+}
+
+/// Compiles `in` tests against a large constant list of strings into a trie.
+///
+/// Returns `None` if the operation does not qualify, in which case it is
+/// evaluated as a regular operator.
+fn compile_string_list<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Option<&'a Token<'a>> {
+    if op_type != OperatorType::Array(ArrayOp::In) {
+        return None;
+    }
+
+    let Token::ArrayLiteral(items) = args else {
+        return None;
+    };
+    if items.len() != 2 {
+        return None;
+    }
+    let Token::Literal(DataValue::Array(list)) = items[1] else {
+        return None;
+    };
+    if list.len() < STRING_TRIE_THRESHOLD || !list.iter().all(|item| item.is_string()) {
+        return None;
+    }
+
+    let trie = StringTrie::build(list.iter().filter_map(|item| item.as_str()), arena);
+    Some(arena.alloc(Token::compiled_match(op_type, args, arena.alloc(trie))))
+}
+
+/// Compiles the constant pattern of a `regex` or `regex_replace` operation.
+///
+/// Returns `None` if the pattern is computed at runtime, and an error if the
+/// pattern is invalid.
+fn compile_regex<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Result<Option<&'a Token<'a>>> {
+    let arity = match op_type {
+        OperatorType::String(StringOp::Regex) => 2,
+        OperatorType::String(StringOp::RegexReplace) => 3,
+        _ => return Ok(None),
+    };
+
+    let Token::ArrayLiteral(items) = args else {
+        return Ok(None);
+    };
+    let (Token::Literal(DataValue::String(pattern)), true) = (items[0], items.len() == arity)
+    else {
+        return Ok(None);
+    };
+
+    let regex = arena.alloc_constant(RegexPattern::new(pattern)?);
+    Ok(Some(
+        arena.alloc(Token::compiled_regex(op_type, args, regex)),
+    ))
+}
+
+/// Case-folds the constant haystack of an `in` operation using `fold_case`.
+///
+/// Returns `None` if the haystack is not a constant string or array, or if
+/// case folding is not requested, in which case it is evaluated as a regular
+/// operator.
+fn fold_in_haystack<'a>(
+    op_type: OperatorType,
+    args: &'a Token<'a>,
+    arena: &'a DataArena,
+) -> Option<&'a Token<'a>> {
+    if op_type != OperatorType::Array(ArrayOp::In) {
+        return None;
+    }
+    let Token::ArrayLiteral(items) = args else {
+        return None;
+    };
+    let [needle, Token::Literal(haystack), Token::Literal(DataValue::Object(options))] =
+        items.as_slice()
+    else {
+        return None;
+    };
+
+    let fold_case = options
+        .iter()
+        .any(|(key, value)| *key == "fold_case" && *value == DataValue::Bool(true));
+    if !fold_case || !(haystack.is_string() || haystack.is_array()) {
+        return None;
+    }
+
+    let folded_haystack = array::fold_case_value(haystack, arena);
+    let folded_args = arena.alloc(Token::ArrayLiteral(vec![
+        *needle,
+        arena.alloc(Token::literal(folded_haystack)),
+        items[2],
+    ]));
+    Some(arena.alloc(Token::case_folded(op_type, folded_args)))
 }
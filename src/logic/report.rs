@@ -0,0 +1,287 @@
+//! Compilation reports.
+//!
+//! This module inspects a compiled logic expression and summarizes it for
+//! rule-authoring tools: which operators and variables it uses, what literal
+//! values it embeds, what the optimizer changed and an estimated cost.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{json, Value as JsonValue};
+
+use super::operators::array::ArrayOp;
+use super::token::{OperatorType, Token};
+use crate::arena::DataArena;
+
+/// Cost of evaluating an operator node, excluding its arguments.
+const OPERATOR_COST: usize = 1;
+
+/// Cost of an operator that runs its function once per array element.
+const ITERATOR_COST: usize = 10;
+
+/// Cost of calling a custom operator.
+const CUSTOM_OPERATOR_COST: usize = 5;
+
+/// A summary of a compiled logic expression.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompilationReport {
+    /// Number of uses of each operator, by name.
+    pub operators: BTreeMap<String, usize>,
+    /// Variable paths read by the expression.
+    pub variables: BTreeSet<String>,
+    /// Whether the expression also reads data through paths that are not
+    /// listed in `variables`, such as computed `var` paths or `val`.
+    pub dynamic_access: bool,
+    /// Number of embedded literal values, by type name.
+    pub literals: BTreeMap<String, usize>,
+    /// Number of nodes in the compiled expression.
+    pub node_count: usize,
+    /// Estimated evaluation cost in relative units.
+    ///
+    /// Iterators such as `map` and `filter` are weighted as if they ran over
+    /// ten elements, so the estimate is only meaningful for comparing rules.
+    pub estimated_cost: usize,
+    /// Optimizations applied at compile time.
+    pub optimizations: Vec<String>,
+    /// Potential problems found in the expression.
+    pub warnings: Vec<String>,
+}
+
+impl CompilationReport {
+    /// Converts the report to JSON.
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "operators": self.operators,
+            "variables": self.variables,
+            "dynamic_access": self.dynamic_access,
+            "literals": self.literals,
+            "node_count": self.node_count,
+            "estimated_cost": self.estimated_cost,
+            "optimizations": self.optimizations,
+            "warnings": self.warnings,
+        })
+    }
+}
+
+/// Counters collected while walking a token tree.
+#[derive(Default)]
+struct Walk {
+    report: CompilationReport,
+    operator_nodes: usize,
+    compiled_lists: usize,
+    compiled_regexes: usize,
+    folded_haystacks: usize,
+    custom_operators: BTreeSet<String>,
+}
+
+impl Walk {
+    /// Visits a token and its arguments, returning the estimated cost.
+    fn visit(&mut self, token: &Token) -> usize {
+        self.report.node_count += 1;
+
+        match token {
+            Token::Literal(value) => {
+                *self
+                    .report
+                    .literals
+                    .entry(value.type_name().to_string())
+                    .or_default() += 1;
+                0
+            }
+            Token::ArrayLiteral(items) => items.iter().map(|item| self.visit(item)).sum(),
+            Token::Variable { path, default } => {
+                self.report.variables.insert(path.to_string());
+                OPERATOR_COST + default.map_or(0, |default| self.visit(default))
+            }
+            Token::DynamicVariable { path_expr, default } => {
+                self.report.dynamic_access = true;
+                OPERATOR_COST
+                    + self.visit(path_expr)
+                    + default.map_or(0, |default| self.visit(default))
+            }
+            Token::Operator { op_type, args } => {
+                self.operator_nodes += 1;
+                self.count_operator(op_type.as_str());
+                if matches!(
+                    op_type,
+                    OperatorType::Val
+                        | OperatorType::Missing
+                        | OperatorType::MissingSome
+                        | OperatorType::Exists
+                        | OperatorType::Validate
+                ) {
+                    self.report.dynamic_access = true;
+                }
+
+                let args_cost = self.visit(args);
+                match op_type {
+                    OperatorType::Array(
+                        ArrayOp::Map
+                        | ArrayOp::Filter
+                        | ArrayOp::Reduce
+                        | ArrayOp::All
+                        | ArrayOp::Some
+                        | ArrayOp::None
+                        | ArrayOp::GroupBy
+                        | ArrayOp::Sum
+                        | ArrayOp::Avg
+                        | ArrayOp::Count
+                        | ArrayOp::MinBy
+                        | ArrayOp::MaxBy,
+                    ) => ITERATOR_COST * (OPERATOR_COST + args_cost),
+                    _ => OPERATOR_COST + args_cost,
+                }
+            }
+            Token::CustomOperator { name, args } => {
+                self.operator_nodes += 1;
+                self.count_operator(name);
+                self.custom_operators.insert(name.to_string());
+                CUSTOM_OPERATOR_COST + self.visit(args)
+            }
+            Token::CompiledMatch { op_type, args, .. } => {
+                self.operator_nodes += 1;
+                self.compiled_lists += 1;
+                self.count_operator(op_type.as_str());
+                OPERATOR_COST + self.visit(args)
+            }
+            Token::CompiledRegex { op_type, args, .. } => {
+                self.operator_nodes += 1;
+                self.compiled_regexes += 1;
+                self.count_operator(op_type.as_str());
+                OPERATOR_COST + self.visit(args)
+            }
+            Token::CaseFolded { op_type, args } => {
+                self.operator_nodes += 1;
+                self.folded_haystacks += 1;
+                self.count_operator(op_type.as_str());
+                OPERATOR_COST + self.visit(args)
+            }
+        }
+    }
+
+    fn count_operator(&mut self, name: &str) {
+        *self.report.operators.entry(name.to_string()).or_default() += 1;
+    }
+}
+
+/// Counts the operator nodes of a token tree.
+fn count_operator_nodes(token: &Token) -> usize {
+    match token {
+        Token::Literal(_) => 0,
+        Token::ArrayLiteral(items) => items.iter().map(|item| count_operator_nodes(item)).sum(),
+        Token::Variable { default, .. } => default.map_or(0, count_operator_nodes),
+        Token::DynamicVariable { path_expr, default } => {
+            count_operator_nodes(path_expr) + default.map_or(0, count_operator_nodes)
+        }
+        Token::Operator { args, .. }
+        | Token::CustomOperator { args, .. }
+        | Token::CompiledMatch { args, .. }
+        | Token::CompiledRegex { args, .. }
+        | Token::CaseFolded { args, .. } => 1 + count_operator_nodes(args),
+    }
+}
+
+/// Builds a compilation report for an expression.
+///
+/// `parsed` is the expression as produced by the parser and `optimized` the
+/// result of running the optimizer on it; comparing both reveals the applied
+/// optimizations. Custom operators not registered in the arena are reported
+/// as warnings.
+pub fn compilation_report(
+    parsed: &Token,
+    optimized: &Token,
+    arena: &DataArena,
+) -> CompilationReport {
+    let mut walk = Walk::default();
+    walk.report.estimated_cost = walk.visit(optimized);
+
+    let folded = count_operator_nodes(parsed).saturating_sub(walk.operator_nodes);
+    if folded > 0 {
+        walk.report
+            .optimizations
+            .push(format!("constant folding removed {} operator(s)", folded));
+    }
+    if walk.compiled_lists > 0 {
+        walk.report.optimizations.push(format!(
+            "compiled {} constant string list(s) into a trie",
+            walk.compiled_lists
+        ));
+    }
+    if walk.compiled_regexes > 0 {
+        walk.report.optimizations.push(format!(
+            "precompiled {} constant regex pattern(s)",
+            walk.compiled_regexes
+        ));
+    }
+    if walk.folded_haystacks > 0 {
+        walk.report.optimizations.push(format!(
+            "case-folded {} constant haystack(s)",
+            walk.folded_haystacks
+        ));
+    }
+
+    for name in &walk.custom_operators {
+        if !arena.has_custom_operator(name) {
+            walk.report
+                .warnings
+                .push(format!("custom operator '{}' is not registered", name));
+        }
+    }
+    if walk.operator_nodes == 0 && count_operator_nodes(parsed) > 0 {
+        walk.report
+            .warnings
+            .push("rule always evaluates to the same value".to_string());
+    }
+
+    walk.report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::optimize;
+    use crate::parser::jsonlogic::parse_json;
+
+    fn report_for(rule: JsonValue) -> CompilationReport {
+        let arena = DataArena::new();
+        let parsed = parse_json(&rule, &arena).unwrap();
+        let optimized = optimize(parsed, &arena).unwrap();
+        compilation_report(parsed, optimized, &arena)
+    }
+
+    #[test]
+    fn test_compilation_report() {
+        let report = report_for(json!({"and": [
+            {">": [{"var": "age"}, {"+": [10, 8]}]},
+            {"map": [{"var": "items"}, {"*": [{"var": ""}, 2]}]},
+            {"lookup_account": [{"var": "id"}]}
+        ]}));
+
+        assert_eq!(report.operators.get("and"), Some(&1));
+        assert_eq!(report.operators.get("+"), None);
+        assert_eq!(report.operators.get("lookup_account"), Some(&1));
+        assert!(report.variables.contains("age"));
+        assert!(report.variables.contains("items"));
+        assert!(!report.dynamic_access);
+        assert_eq!(report.literals.get("number"), Some(&2));
+        assert_eq!(
+            report.optimizations,
+            vec!["constant folding removed 1 operator(s)".to_string()]
+        );
+        assert_eq!(
+            report.warnings,
+            vec!["custom operator 'lookup_account' is not registered".to_string()]
+        );
+        assert!(report.estimated_cost > 20);
+    }
+
+    #[test]
+    fn test_compilation_report_constant_rule() {
+        let report = report_for(json!({"if": [true, {"cat": ["a", "b"]}, "c"]}));
+        assert!(report.operators.is_empty());
+        assert_eq!(report.node_count, 1);
+        assert_eq!(
+            report.warnings,
+            vec!["rule always evaluates to the same value".to_string()]
+        );
+    }
+}
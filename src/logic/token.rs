@@ -3,8 +3,15 @@
 //! This module provides a compact token representation for logic expressions,
 //! optimized for memory efficiency and evaluation performance.
 
-use super::operators::{ArithmeticOp, ArrayOp, ComparisonOp, ControlOp, DateTimeOp, StringOp};
-use crate::value::DataValue;
+use super::operators::string::RegexPattern;
+use super::operators::{
+    ArithmeticOp, ArrayOp, ComparisonOp, ControlOp, DateTimeOp, RangeOp, StringOp, TypeCheckOp,
+};
+use super::trie::StringTrie;
+use crate::value::{DataValue, ToJson};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 /// A token in a logic expression.
@@ -12,6 +19,7 @@ use std::str::FromStr;
 /// This is a compact representation of a logic expression node, optimized
 /// for memory efficiency and evaluation performance.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Token<'a> {
     /// A literal value.
     Literal(DataValue<'a>),
@@ -50,6 +58,37 @@ pub enum Token<'a> {
         /// The arguments to the operator.
         args: &'a Token<'a>,
     },
+
+    /// A membership test against a constant string list that has been
+    /// compiled into a trie by the optimizer.
+    CompiledMatch {
+        /// The operator being accelerated (`in`).
+        op_type: OperatorType,
+        /// The original arguments, used when the needle is not a string.
+        args: &'a Token<'a>,
+        /// The compiled string list.
+        trie: &'a StringTrie<'a>,
+    },
+
+    /// A regex operation whose constant pattern has been compiled by the
+    /// optimizer.
+    CompiledRegex {
+        /// The operator being accelerated (`regex` or `regex_replace`).
+        op_type: OperatorType,
+        /// The original arguments, starting with the pattern.
+        args: &'a Token<'a>,
+        /// The compiled pattern.
+        regex: &'a RegexPattern,
+    },
+
+    /// A case-insensitive operation whose constant arguments have been
+    /// case-folded by the optimizer.
+    CaseFolded {
+        /// The operator being accelerated (`in`).
+        op_type: OperatorType,
+        /// The arguments, with the constant haystack case-folded.
+        args: &'a Token<'a>,
+    },
 }
 
 /// The type of operator.
@@ -67,6 +106,8 @@ pub enum OperatorType {
     Array(ArrayOp),
     /// DateTime operator
     DateTime(DateTimeOp),
+    /// Range operator
+    Range(RangeOp),
     /// Missing operator
     Missing,
     /// Missing Some operator
@@ -77,14 +118,34 @@ pub enum OperatorType {
     Coalesce,
     /// Val operator (replacement for Var)
     Val,
+    /// Validate operator producing a report of problems
+    Validate,
     /// Throw operator
     Throw,
     /// Try operator (for error handling)
     Try,
+    /// Log operator reporting a value to the installed logger
+    Log,
+    /// Do operator evaluating expressions in order within an assignment scope
+    Do,
+    /// Assign operator storing a value for later expressions of a do block
+    Assign,
     /// Type operator
     Type,
+    /// Type check operator, such as is_string
+    TypeCheck(TypeCheckOp),
+    /// Empty operator testing for null, blank strings and empty collections
+    Empty,
+    /// Default operator returning the first value that is not empty
+    Default,
+    /// Unpreserve operator evaluating a rule given as a value
+    Unpreserve,
+    /// Annotate operator documenting the rule it wraps
+    Annotate,
     /// Array operator (for arrays with non-literal elements)
     ArrayLiteral,
+    /// Object operator (for multi-key objects with non-literal values)
+    ObjectLiteral,
 }
 
 impl<'a> Token<'a> {
@@ -113,6 +174,37 @@ impl<'a> Token<'a> {
         Token::CustomOperator { name, args }
     }
 
+    /// Creates a new compiled match token.
+    pub fn compiled_match(
+        op_type: OperatorType,
+        args: &'a Token<'a>,
+        trie: &'a StringTrie<'a>,
+    ) -> Self {
+        Token::CompiledMatch {
+            op_type,
+            args,
+            trie,
+        }
+    }
+
+    /// Creates a new compiled regex token.
+    pub fn compiled_regex(
+        op_type: OperatorType,
+        args: &'a Token<'a>,
+        regex: &'a RegexPattern,
+    ) -> Self {
+        Token::CompiledRegex {
+            op_type,
+            args,
+            regex,
+        }
+    }
+
+    /// Creates a new case-folded operation token.
+    pub fn case_folded(op_type: OperatorType, args: &'a Token<'a>) -> Self {
+        Token::CaseFolded { op_type, args }
+    }
+
     /// Returns true if this token is a literal.
     pub fn is_literal(&self) -> bool {
         matches!(self, Token::Literal(_))
@@ -177,6 +269,224 @@ impl<'a> Token<'a> {
             _ => None,
         }
     }
+
+    /// Returns a fingerprint of the token tree.
+    ///
+    /// Structurally identical rules have the same fingerprint regardless of
+    /// how their JSON was formatted, so it can key a cache of compiled rules.
+    /// Numbers are compared by value, object keys in any order, and tokens
+    /// compiled by the optimizer like the operators they accelerate.
+    ///
+    /// The fingerprint is stable within a build of the crate, but may change
+    /// between versions, so it should not be persisted.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hash_token(self, &mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns a listing of the token tree, one node per line.
+    ///
+    /// Every line starts with the position of the node in a depth-first walk
+    /// of the tree, and is indented by its depth. Literals are shown as JSON,
+    /// and tokens compiled by the optimizer with the structure they were
+    /// compiled into, so the listing shows what the optimizer made of a rule.
+    pub fn disassemble(&self) -> String {
+        let mut listing = String::new();
+        let mut position = 0;
+        disassemble_token(self, 0, &mut position, &mut listing);
+        listing
+    }
+}
+
+/// Appends a token and its arguments to a listing.
+fn disassemble_token(token: &Token, depth: usize, position: &mut usize, listing: &mut String) {
+    let _ = write!(
+        listing,
+        "{:04}  {:indent$}",
+        position,
+        "",
+        indent = depth * 2
+    );
+    *position += 1;
+
+    // The arguments listed below the token
+    let args: Vec<&Token> = match token {
+        Token::Literal(value) => {
+            let _ = writeln!(listing, "literal {}", value.to_json());
+            Vec::new()
+        }
+        Token::ArrayLiteral(items) => {
+            let _ = writeln!(listing, "array {}", items.len());
+            items.clone()
+        }
+        Token::Variable { path, default } => {
+            let _ = write!(listing, "var {:?}", path);
+            let _ = writeln!(
+                listing,
+                "{}",
+                if default.is_some() { " default" } else { "" }
+            );
+            default.iter().copied().collect()
+        }
+        Token::DynamicVariable { path_expr, default } => {
+            let _ = write!(listing, "var computed");
+            let _ = writeln!(
+                listing,
+                "{}",
+                if default.is_some() { " default" } else { "" }
+            );
+            std::iter::once(*path_expr).chain(*default).collect()
+        }
+        Token::Operator { op_type, args } => {
+            let _ = writeln!(listing, "{}", op_type.as_str());
+            operator_args(args)
+        }
+        Token::CustomOperator { name, args } => {
+            let _ = writeln!(listing, "call {}", name);
+            operator_args(args)
+        }
+        Token::CompiledMatch {
+            op_type,
+            args,
+            trie,
+        } => {
+            let _ = writeln!(
+                listing,
+                "{} trie of {} strings",
+                op_type.as_str(),
+                trie.len()
+            );
+            operator_args(args)
+        }
+        Token::CompiledRegex {
+            op_type,
+            args,
+            regex,
+        } => {
+            let _ = writeln!(listing, "{} regex /{}/", op_type.as_str(), regex.as_str());
+            operator_args(args)
+        }
+        Token::CaseFolded { op_type, args } => {
+            let _ = writeln!(listing, "{} case-folded", op_type.as_str());
+            operator_args(args)
+        }
+    };
+
+    for arg in args {
+        disassemble_token(arg, depth + 1, position, listing);
+    }
+}
+
+/// Returns the arguments of an operator, unwrapping its argument list.
+fn operator_args<'a>(args: &'a Token<'a>) -> Vec<&'a Token<'a>> {
+    match args {
+        Token::ArrayLiteral(items) => items.clone(),
+        args => vec![args],
+    }
+}
+
+/// Feeds a token tree into a hasher.
+fn hash_token<H: Hasher>(token: &Token, hasher: &mut H) {
+    match token {
+        Token::Literal(value) => {
+            hasher.write_u8(0);
+            hash_value(value, hasher);
+        }
+        Token::ArrayLiteral(items) => {
+            hasher.write_u8(1);
+            hasher.write_usize(items.len());
+            for item in items {
+                hash_token(item, hasher);
+            }
+        }
+        Token::Variable { path, default } => {
+            hasher.write_u8(2);
+            path.hash(hasher);
+            hash_default(*default, hasher);
+        }
+        Token::DynamicVariable { path_expr, default } => {
+            hasher.write_u8(3);
+            hash_token(path_expr, hasher);
+            hash_default(*default, hasher);
+        }
+        Token::Operator { op_type, args }
+        | Token::CompiledMatch { op_type, args, .. }
+        | Token::CompiledRegex { op_type, args, .. }
+        | Token::CaseFolded { op_type, args } => {
+            hasher.write_u8(4);
+            op_type.as_str().hash(hasher);
+            hash_token(args, hasher);
+        }
+        Token::CustomOperator { name, args } => {
+            hasher.write_u8(5);
+            name.hash(hasher);
+            hash_token(args, hasher);
+        }
+    }
+}
+
+/// Feeds the optional default of a variable into a hasher.
+fn hash_default<H: Hasher>(default: Option<&Token>, hasher: &mut H) {
+    match default {
+        Some(default) => {
+            hasher.write_u8(1);
+            hash_token(default, hasher);
+        }
+        None => hasher.write_u8(0),
+    }
+}
+
+/// Feeds a literal value into a hasher.
+fn hash_value<H: Hasher>(value: &DataValue, hasher: &mut H) {
+    match value {
+        DataValue::Null => hasher.write_u8(0),
+        DataValue::Bool(b) => {
+            hasher.write_u8(1);
+            b.hash(hasher);
+        }
+        DataValue::Number(n) => {
+            hasher.write_u8(2);
+            // Whole floats hash like the integers they equal
+            match n.as_i64() {
+                Some(i) => i.hash(hasher),
+                None => n.as_f64().to_bits().hash(hasher),
+            }
+        }
+        DataValue::String(s) => {
+            hasher.write_u8(3);
+            s.hash(hasher);
+        }
+        DataValue::Array(items) => {
+            hasher.write_u8(4);
+            hasher.write_usize(items.len());
+            for item in *items {
+                hash_value(item, hasher);
+            }
+        }
+        DataValue::Object(entries) => {
+            hasher.write_u8(5);
+            hasher.write_usize(entries.len());
+            let mut sorted: Vec<_> = entries.iter().collect();
+            sorted.sort_by_key(|(key, _)| *key);
+            for (key, value) in sorted {
+                key.hash(hasher);
+                hash_value(value, hasher);
+            }
+        }
+        DataValue::DateTime(dt) => {
+            hasher.write_u8(6);
+            dt.hash(hasher);
+        }
+        DataValue::Duration(d) => {
+            hasher.write_u8(7);
+            d.hash(hasher);
+        }
+        DataValue::Custom(handle) => {
+            hasher.write_u8(8);
+            handle.hash(hasher);
+        }
+    }
 }
 
 impl OperatorType {
@@ -192,6 +502,7 @@ impl OperatorType {
                 ComparisonOp::GreaterThanOrEqual => ">=",
                 ComparisonOp::LessThan => "<",
                 ComparisonOp::LessThanOrEqual => "<=",
+                ComparisonOp::DeepEqual => "deep_equal",
             },
             OperatorType::Arithmetic(op) => match op {
                 ArithmeticOp::Add => "+",
@@ -204,6 +515,15 @@ impl OperatorType {
                 ArithmeticOp::Abs => "abs",
                 ArithmeticOp::Ceil => "ceil",
                 ArithmeticOp::Floor => "floor",
+                ArithmeticOp::PercentOf => "percent_of",
+                ArithmeticOp::SafeDiv => "safe_div",
+                ArithmeticOp::Bucket => "bucket",
+                ArithmeticOp::Pow => "pow",
+                ArithmeticOp::Sqrt => "sqrt",
+                ArithmeticOp::Ln => "ln",
+                ArithmeticOp::Log10 => "log10",
+                ArithmeticOp::Round => "round",
+                ArithmeticOp::Trunc => "trunc",
             },
             OperatorType::Control(op) => match op {
                 ControlOp::If => "if",
@@ -222,6 +542,8 @@ impl OperatorType {
                 StringOp::Trim => "trim",
                 StringOp::Replace => "replace",
                 StringOp::Split => "split",
+                StringOp::Regex => "regex",
+                StringOp::RegexReplace => "regex_replace",
             },
             OperatorType::Array(op) => match op {
                 ArrayOp::Map => "map",
@@ -235,14 +557,39 @@ impl OperatorType {
                 ArrayOp::Length => "length",
                 ArrayOp::Slice => "slice",
                 ArrayOp::Sort => "sort",
+                ArrayOp::Related => "related",
+                ArrayOp::Unique => "unique",
+                ArrayOp::GroupBy => "group_by",
+                ArrayOp::Sum => "sum",
+                ArrayOp::Avg => "avg",
+                ArrayOp::Count => "count",
+                ArrayOp::MinBy => "min_by",
+                ArrayOp::MaxBy => "max_by",
+                ArrayOp::Keys => "keys",
+                ArrayOp::Values => "values",
+                ArrayOp::Entries => "entries",
+                ArrayOp::FromEntries => "from_entries",
             },
             OperatorType::DateTime(op) => match op {
                 DateTimeOp::DateTime => "datetime",
                 DateTimeOp::Timestamp => "timestamp",
+                DateTimeOp::Duration => "duration",
                 DateTimeOp::Now => "now",
                 DateTimeOp::ParseDate => "parse_date",
                 DateTimeOp::FormatDate => "format_date",
                 DateTimeOp::DateDiff => "date_diff",
+                DateTimeOp::DateAdd => "date_add",
+                DateTimeOp::InSchedule => "in_schedule",
+                DateTimeOp::AgeYears => "age_years",
+                DateTimeOp::IsAnniversary => "is_anniversary",
+                DateTimeOp::HumanizeDuration => "humanize_duration",
+                DateTimeOp::IsoDuration => "iso_duration",
+            },
+            OperatorType::Range(op) => match op {
+                RangeOp::Range => "range",
+                RangeOp::InRange => "in_range",
+                RangeOp::RangesOverlap => "ranges_overlap",
+                RangeOp::RangeIntersect => "range_intersect",
             },
             OperatorType::Missing => "missing",
             OperatorType::MissingSome => "missing_some",
@@ -251,12 +598,144 @@ impl OperatorType {
             OperatorType::Val => "val",
             OperatorType::Throw => "throw",
             OperatorType::Try => "try",
+            OperatorType::Log => "log",
+            OperatorType::Do => "do",
+            OperatorType::Assign => "assign",
             OperatorType::Type => "type",
+            OperatorType::Empty => "empty",
+            OperatorType::Default => "default",
+            OperatorType::Unpreserve => "unpreserve",
+            OperatorType::Annotate => "annotate",
+            OperatorType::TypeCheck(op) => match op {
+                TypeCheckOp::IsString => "is_string",
+                TypeCheckOp::IsNumber => "is_number",
+                TypeCheckOp::IsBool => "is_bool",
+                TypeCheckOp::IsArray => "is_array",
+                TypeCheckOp::IsObject => "is_object",
+                TypeCheckOp::IsNull => "is_null",
+            },
+            OperatorType::Validate => "validate",
             OperatorType::ArrayLiteral => "array",
+            OperatorType::ObjectLiteral => "object",
         }
     }
 }
 
+/// Names of the built-in operators parsed into an [`OperatorType`],
+/// including aliases such as `?:`.
+pub(crate) const OPERATOR_NAMES: &[&str] = &[
+    "==",
+    "===",
+    "!=",
+    "!==",
+    ">",
+    ">=",
+    "<",
+    "<=",
+    "deep_equal",
+    "+",
+    "-",
+    "*",
+    "/",
+    "%",
+    "min",
+    "max",
+    "abs",
+    "ceil",
+    "floor",
+    "percent_of",
+    "safe_div",
+    "bucket",
+    "pow",
+    "**",
+    "sqrt",
+    "ln",
+    "log10",
+    "round",
+    "trunc",
+    "and",
+    "or",
+    "!",
+    "!!",
+    "if",
+    "?:",
+    "cat",
+    "substr",
+    "starts_with",
+    "ends_with",
+    "upper",
+    "lower",
+    "trim",
+    "replace",
+    "split",
+    "regex",
+    "regex_replace",
+    "map",
+    "filter",
+    "reduce",
+    "all",
+    "some",
+    "none",
+    "merge",
+    "in",
+    "length",
+    "slice",
+    "sort",
+    "related",
+    "unique",
+    "group_by",
+    "sum",
+    "avg",
+    "count",
+    "min_by",
+    "max_by",
+    "keys",
+    "values",
+    "entries",
+    "from_entries",
+    "now",
+    "datetime",
+    "timestamp",
+    "duration",
+    "parse_date",
+    "format_date",
+    "date_diff",
+    "date_add",
+    "in_schedule",
+    "age_years",
+    "is_anniversary",
+    "humanize_duration",
+    "iso_duration",
+    "range",
+    "in_range",
+    "ranges_overlap",
+    "range_intersect",
+    "missing",
+    "missing_some",
+    "exists",
+    "??",
+    "val",
+    "throw",
+    "try",
+    "log",
+    "do",
+    "assign",
+    "type",
+    "is_string",
+    "is_number",
+    "is_bool",
+    "is_array",
+    "is_object",
+    "is_null",
+    "empty",
+    "is_empty",
+    "default",
+    "unpreserve",
+    "annotate",
+    "//",
+    "validate",
+];
+
 impl FromStr for OperatorType {
     type Err = &'static str; // Or use a more descriptive error type
 
@@ -270,6 +749,7 @@ impl FromStr for OperatorType {
             ">=" => Ok(OperatorType::Comparison(ComparisonOp::GreaterThanOrEqual)),
             "<" => Ok(OperatorType::Comparison(ComparisonOp::LessThan)),
             "<=" => Ok(OperatorType::Comparison(ComparisonOp::LessThanOrEqual)),
+            "deep_equal" => Ok(OperatorType::Comparison(ComparisonOp::DeepEqual)),
             "+" => Ok(OperatorType::Arithmetic(ArithmeticOp::Add)),
             "-" => Ok(OperatorType::Arithmetic(ArithmeticOp::Subtract)),
             "*" => Ok(OperatorType::Arithmetic(ArithmeticOp::Multiply)),
@@ -280,6 +760,15 @@ impl FromStr for OperatorType {
             "abs" => Ok(OperatorType::Arithmetic(ArithmeticOp::Abs)),
             "ceil" => Ok(OperatorType::Arithmetic(ArithmeticOp::Ceil)),
             "floor" => Ok(OperatorType::Arithmetic(ArithmeticOp::Floor)),
+            "percent_of" => Ok(OperatorType::Arithmetic(ArithmeticOp::PercentOf)),
+            "safe_div" => Ok(OperatorType::Arithmetic(ArithmeticOp::SafeDiv)),
+            "bucket" => Ok(OperatorType::Arithmetic(ArithmeticOp::Bucket)),
+            "pow" | "**" => Ok(OperatorType::Arithmetic(ArithmeticOp::Pow)),
+            "sqrt" => Ok(OperatorType::Arithmetic(ArithmeticOp::Sqrt)),
+            "ln" => Ok(OperatorType::Arithmetic(ArithmeticOp::Ln)),
+            "log10" => Ok(OperatorType::Arithmetic(ArithmeticOp::Log10)),
+            "round" => Ok(OperatorType::Arithmetic(ArithmeticOp::Round)),
+            "trunc" => Ok(OperatorType::Arithmetic(ArithmeticOp::Trunc)),
             "and" => Ok(OperatorType::Control(ControlOp::And)),
             "or" => Ok(OperatorType::Control(ControlOp::Or)),
             "!" => Ok(OperatorType::Control(ControlOp::Not)),
@@ -295,6 +784,8 @@ impl FromStr for OperatorType {
             "trim" => Ok(OperatorType::String(StringOp::Trim)),
             "replace" => Ok(OperatorType::String(StringOp::Replace)),
             "split" => Ok(OperatorType::String(StringOp::Split)),
+            "regex" => Ok(OperatorType::String(StringOp::Regex)),
+            "regex_replace" => Ok(OperatorType::String(StringOp::RegexReplace)),
             "map" => Ok(OperatorType::Array(ArrayOp::Map)),
             "filter" => Ok(OperatorType::Array(ArrayOp::Filter)),
             "reduce" => Ok(OperatorType::Array(ArrayOp::Reduce)),
@@ -306,12 +797,35 @@ impl FromStr for OperatorType {
             "length" => Ok(OperatorType::Array(ArrayOp::Length)),
             "slice" => Ok(OperatorType::Array(ArrayOp::Slice)),
             "sort" => Ok(OperatorType::Array(ArrayOp::Sort)),
+            "related" => Ok(OperatorType::Array(ArrayOp::Related)),
+            "unique" => Ok(OperatorType::Array(ArrayOp::Unique)),
+            "group_by" => Ok(OperatorType::Array(ArrayOp::GroupBy)),
+            "sum" => Ok(OperatorType::Array(ArrayOp::Sum)),
+            "avg" => Ok(OperatorType::Array(ArrayOp::Avg)),
+            "count" => Ok(OperatorType::Array(ArrayOp::Count)),
+            "min_by" => Ok(OperatorType::Array(ArrayOp::MinBy)),
+            "max_by" => Ok(OperatorType::Array(ArrayOp::MaxBy)),
+            "keys" => Ok(OperatorType::Array(ArrayOp::Keys)),
+            "values" => Ok(OperatorType::Array(ArrayOp::Values)),
+            "entries" => Ok(OperatorType::Array(ArrayOp::Entries)),
+            "from_entries" => Ok(OperatorType::Array(ArrayOp::FromEntries)),
             "now" => Ok(OperatorType::DateTime(DateTimeOp::Now)),
             "datetime" => Ok(OperatorType::DateTime(DateTimeOp::DateTime)),
             "timestamp" => Ok(OperatorType::DateTime(DateTimeOp::Timestamp)),
+            "duration" => Ok(OperatorType::DateTime(DateTimeOp::Duration)),
             "parse_date" => Ok(OperatorType::DateTime(DateTimeOp::ParseDate)),
             "format_date" => Ok(OperatorType::DateTime(DateTimeOp::FormatDate)),
             "date_diff" => Ok(OperatorType::DateTime(DateTimeOp::DateDiff)),
+            "date_add" => Ok(OperatorType::DateTime(DateTimeOp::DateAdd)),
+            "in_schedule" => Ok(OperatorType::DateTime(DateTimeOp::InSchedule)),
+            "age_years" => Ok(OperatorType::DateTime(DateTimeOp::AgeYears)),
+            "is_anniversary" => Ok(OperatorType::DateTime(DateTimeOp::IsAnniversary)),
+            "humanize_duration" => Ok(OperatorType::DateTime(DateTimeOp::HumanizeDuration)),
+            "iso_duration" => Ok(OperatorType::DateTime(DateTimeOp::IsoDuration)),
+            "range" => Ok(OperatorType::Range(RangeOp::Range)),
+            "in_range" => Ok(OperatorType::Range(RangeOp::InRange)),
+            "ranges_overlap" => Ok(OperatorType::Range(RangeOp::RangesOverlap)),
+            "range_intersect" => Ok(OperatorType::Range(RangeOp::RangeIntersect)),
             "missing" => Ok(OperatorType::Missing),
             "missing_some" => Ok(OperatorType::MissingSome),
             "exists" => Ok(OperatorType::Exists),
@@ -319,7 +833,21 @@ impl FromStr for OperatorType {
             "val" => Ok(OperatorType::Val),
             "throw" => Ok(OperatorType::Throw),
             "try" => Ok(OperatorType::Try),
+            "log" => Ok(OperatorType::Log),
+            "do" => Ok(OperatorType::Do),
+            "assign" => Ok(OperatorType::Assign),
             "type" => Ok(OperatorType::Type),
+            "is_string" => Ok(OperatorType::TypeCheck(TypeCheckOp::IsString)),
+            "is_number" => Ok(OperatorType::TypeCheck(TypeCheckOp::IsNumber)),
+            "is_bool" => Ok(OperatorType::TypeCheck(TypeCheckOp::IsBool)),
+            "is_array" => Ok(OperatorType::TypeCheck(TypeCheckOp::IsArray)),
+            "is_object" => Ok(OperatorType::TypeCheck(TypeCheckOp::IsObject)),
+            "is_null" => Ok(OperatorType::TypeCheck(TypeCheckOp::IsNull)),
+            "empty" | "is_empty" => Ok(OperatorType::Empty),
+            "default" => Ok(OperatorType::Default),
+            "unpreserve" => Ok(OperatorType::Unpreserve),
+            "annotate" | "//" => Ok(OperatorType::Annotate),
+            "validate" => Ok(OperatorType::Validate),
             _ => Err("unknown operator"),
         }
     }
@@ -351,4 +879,81 @@ mod tests {
 
         assert_eq!(OperatorType::from_str("unknown"), Err("unknown operator"));
     }
+
+    #[test]
+    fn test_operator_names() {
+        for name in OPERATOR_NAMES {
+            assert!(OperatorType::from_str(name).is_ok(), "{name}");
+        }
+        assert!(OPERATOR_NAMES.contains(&"?:"));
+        assert!(OPERATOR_NAMES.contains(&"is_null"));
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let mut dl = crate::DataLogic::new();
+        dl.register_simple_operator("double", |args, _| Ok(args[0].clone()));
+
+        let codes: Vec<String> = (0..40).map(|i| format!("C{}", i)).collect();
+        let rule = serde_json::json!({"and": [
+            {"in": [{"var": "code"}, codes]},
+            {"regex": ["^[A-Z]+$", {"var": {"cat": ["na", "me"]}}]},
+            {"double": [[1, 2]]}
+        ]});
+        let rule = dl.compile(&rule.to_string(), None).unwrap();
+
+        assert_eq!(
+            rule.disassemble().lines().collect::<Vec<_>>(),
+            [
+                "0000  and",
+                "0001    in trie of 40 strings",
+                "0002      var \"code\"",
+                &format!("0003      literal {}", serde_json::json!(codes)),
+                "0004    regex regex /^[A-Z]+$/",
+                "0005      literal \"^[A-Z]+$\"",
+                "0006      var computed",
+                "0007        cat",
+                "0008          literal \"na\"",
+                "0009          literal \"me\"",
+                "0010    call double",
+                "0011      literal [1,2]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let dl = crate::DataLogic::new();
+        let fingerprint = |rule: &str| dl.compile(rule, None).unwrap().fingerprint();
+
+        let same = [
+            (
+                r#"{"==": [{"var": "a"}, 1]}"#,
+                r#"{ "==" : [ { "var" : "a" }, 1.0 ] }"#,
+            ),
+            (r#"{"?:": [true, 1, 2]}"#, r#"{"if": [true, 1, 2]}"#),
+            (r#"{"+": [2, 3]}"#, "5"),
+            (
+                r#"{"in": [{"var": "c"}, ["x", "y"]]}"#,
+                r#"{"in": [{"var": "c"}, ["x", "y"]]}"#,
+            ),
+        ];
+        for (left, right) in same {
+            assert_eq!(fingerprint(left), fingerprint(right), "{left} vs {right}");
+        }
+
+        let different = [
+            (r#"{"var": "a"}"#, r#""a""#),
+            (r#"{"var": "a"}"#, r#"{"var": ["a", 1]}"#),
+            (
+                r#"{"==": [{"var": "a"}, 1]}"#,
+                r#"{"===": [{"var": "a"}, 1]}"#,
+            ),
+            (r#"[1, [2]]"#, r#"[[1], 2]"#),
+            ("1", "1.5"),
+        ];
+        for (left, right) in different {
+            assert_ne!(fingerprint(left), fingerprint(right), "{left} vs {right}");
+        }
+    }
 }
@@ -0,0 +1,138 @@
+//! Prefix tree for constant string lists.
+//!
+//! Membership tests against large constant lists (SKU allowlists) are
+//! compiled into a byte-level trie by the optimizer, so that matching costs
+//! are proportional to the length of the needle rather than the size of the
+//! list. Only exact matching is compiled, as `starts_with` takes a single
+//! prefix.
+
+use crate::arena::DataArena;
+
+/// Minimum number of strings in a constant list before it is compiled into a trie.
+pub const STRING_TRIE_THRESHOLD: usize = 32;
+
+/// A node in a string trie.
+#[derive(Debug, Clone, PartialEq)]
+struct TrieNode<'a> {
+    /// Outgoing edges sorted by byte, pointing to node indices.
+    children: &'a [(u8, u32)],
+    /// True if a string of the list ends at this node.
+    terminal: bool,
+}
+
+/// A compiled set of strings supporting exact matching.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringTrie<'a> {
+    nodes: &'a [TrieNode<'a>],
+    /// Number of distinct strings in the set.
+    len: usize,
+}
+
+impl<'a> StringTrie<'a> {
+    /// Builds a trie from a list of strings, allocating its nodes in the arena.
+    pub fn build<'s, I>(strings: I, arena: &'a DataArena) -> Self
+    where
+        I: IntoIterator<Item = &'s str>,
+    {
+        // Build with growable nodes first, then freeze them into the arena
+        let mut nodes: Vec<(Vec<(u8, u32)>, bool)> = vec![(Vec::new(), false)];
+        let mut len = 0;
+
+        for s in strings {
+            let mut current = 0usize;
+            for &byte in s.as_bytes() {
+                let children = &nodes[current].0;
+                current = match children.binary_search_by_key(&byte, |&(b, _)| b) {
+                    Ok(pos) => children[pos].1 as usize,
+                    Err(pos) => {
+                        let next = nodes.len();
+                        nodes[current].0.insert(pos, (byte, next as u32));
+                        nodes.push((Vec::new(), false));
+                        next
+                    }
+                };
+            }
+            if !nodes[current].1 {
+                nodes[current].1 = true;
+                len += 1;
+            }
+        }
+
+        let frozen: Vec<TrieNode<'a>> = nodes
+            .into_iter()
+            .map(|(children, terminal)| TrieNode {
+                children: arena.vec_into_slice(children),
+                terminal,
+            })
+            .collect();
+
+        Self {
+            nodes: arena.vec_into_slice(frozen),
+            len,
+        }
+    }
+
+    /// Returns the number of strings in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the set holds no string.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Follows the edge for a byte from a node.
+    #[inline]
+    fn step(&self, node: usize, byte: u8) -> Option<usize> {
+        let children = self.nodes[node].children;
+        children
+            .binary_search_by_key(&byte, |&(b, _)| b)
+            .ok()
+            .map(|pos| children[pos].1 as usize)
+    }
+
+    /// Returns true if the string is in the set.
+    pub fn contains(&self, s: &str) -> bool {
+        let mut current = 0;
+        for &byte in s.as_bytes() {
+            match self.step(current, byte) {
+                Some(next) => current = next,
+                None => return false,
+            }
+        }
+        self.nodes[current].terminal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trie_contains() {
+        let arena = DataArena::new();
+        let trie = StringTrie::build(["apple", "app", "banana"], &arena);
+
+        assert!(trie.contains("app"));
+        assert!(trie.contains("apple"));
+        assert!(trie.contains("banana"));
+        assert!(!trie.contains("ap"));
+        assert!(!trie.contains("apples"));
+        assert!(!trie.contains(""));
+
+        let with_empty = StringTrie::build([""], &arena);
+        assert!(with_empty.contains(""));
+    }
+
+    #[test]
+    fn test_trie_len() {
+        let arena = DataArena::new();
+        let trie = StringTrie::build(["apple", "app", "apple", "banana"], &arena);
+        assert_eq!(trie.len(), 3);
+        assert!(!trie.is_empty());
+
+        assert_eq!(StringTrie::build([""], &arena).len(), 1);
+        assert!(StringTrie::build(std::iter::empty::<&str>(), &arena).is_empty());
+    }
+}
@@ -0,0 +1,565 @@
+//! Declared output types of rules
+//!
+//! A rule can declare the type of the result it is expected to produce with
+//! [`Logic::with_output_type`](crate::Logic::with_output_type) or
+//! [`CompiledRule::with_output_type`](crate::CompiledRule::with_output_type).
+//! Evaluation then coerces the result to the declared type where that is
+//! lossless, such as the string `"42"` to a number, and fails with
+//! [`LogicError::OutputTypeError`] otherwise.
+//!
+//! [`OutputType::check`] inspects the branches of a rule before it runs, and
+//! warns about those that can return a value of another type.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value as JsonValue;
+
+use crate::arena::DataArena;
+use crate::logic::{
+    split_default_options, ArithmeticOp, ArrayOp, ControlOp, LogicError, OperatorType, Result,
+    StringOp, Token,
+};
+use crate::value::{DataValue, NumberValue};
+
+/// The type of the result of a rule
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputType {
+    /// Any value
+    Any,
+    /// `true` or `false`
+    Boolean,
+    /// A number
+    Number,
+    /// A number without a fractional part
+    Integer,
+    /// A string
+    String,
+    /// One of a fixed set of strings
+    Enum(Vec<String>),
+    /// An array of items of one type
+    Array(Box<OutputType>),
+    /// An object with the given fields, and possibly others
+    Object(BTreeMap<String, OutputType>),
+    /// A value of the inner type or null
+    Optional(Box<OutputType>),
+}
+
+impl fmt::Display for OutputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputType::Any => f.write_str("any"),
+            OutputType::Boolean => f.write_str("boolean"),
+            OutputType::Number => f.write_str("number"),
+            OutputType::Integer => f.write_str("integer"),
+            OutputType::String => f.write_str("string"),
+            OutputType::Enum(values) => write!(f, "one of [{}]", values.join(", ")),
+            OutputType::Array(items) => write!(f, "array of {}", items),
+            OutputType::Object(fields) => {
+                f.write_str("{")?;
+                for (index, (name, field)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", name, field)?;
+                }
+                f.write_str("}")
+            }
+            OutputType::Optional(inner) => write!(f, "optional {}", inner),
+        }
+    }
+}
+
+/// The kind of value an expression is known to produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Null,
+    Boolean,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl Kind {
+    fn of(value: &DataValue) -> Option<Kind> {
+        match value {
+            DataValue::Null => Some(Kind::Null),
+            DataValue::Bool(_) => Some(Kind::Boolean),
+            DataValue::Number(_) => Some(Kind::Number),
+            DataValue::String(_) => Some(Kind::String),
+            DataValue::Array(_) => Some(Kind::Array),
+            DataValue::Object(_) => Some(Kind::Object),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Null => "null",
+            Kind::Boolean => "a boolean",
+            Kind::Number => "a number",
+            Kind::String => "a string",
+            Kind::Array => "an array",
+            Kind::Object => "an object",
+        }
+    }
+}
+
+impl OutputType {
+    /// Parses an output type from JSON
+    ///
+    /// Scalar types are named by a string: `"any"`, `"boolean"`, `"number"`,
+    /// `"integer"` or `"string"`. Other types are objects with a single key:
+    /// `{"enum": ["a", "b"]}`, `{"array": <type>}`, `{"object": {<field>:
+    /// <type>}}` or `{"optional": <type>}`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::output::OutputType;
+    /// use serde_json::json;
+    ///
+    /// let output = OutputType::from_json(&json!({"object": {
+    ///     "tier": {"enum": ["gold", "silver"]},
+    ///     "discount": "number"
+    /// }}))
+    /// .unwrap();
+    /// assert_eq!(output.to_string(), "{discount: number, tier: one of [gold, silver]}");
+    /// ```
+    pub fn from_json(source: &JsonValue) -> Result<Self> {
+        let invalid = |reason: String| LogicError::ParseError {
+            reason: format!("Invalid output type: {}", reason),
+        };
+
+        match source {
+            JsonValue::String(name) => match name.as_str() {
+                "any" => Ok(OutputType::Any),
+                "boolean" => Ok(OutputType::Boolean),
+                "number" => Ok(OutputType::Number),
+                "integer" => Ok(OutputType::Integer),
+                "string" => Ok(OutputType::String),
+                other => Err(invalid(format!("unknown type '{}'", other))),
+            },
+            JsonValue::Object(object) if object.len() == 1 => {
+                let (kind, inner) = object.iter().next().expect("object has one entry");
+                match (kind.as_str(), inner) {
+                    ("enum", JsonValue::Array(values)) => values
+                        .iter()
+                        .map(|value| {
+                            value.as_str().map(str::to_string).ok_or_else(|| {
+                                invalid(format!("enum value {} is not a string", value))
+                            })
+                        })
+                        .collect::<Result<_>>()
+                        .map(OutputType::Enum),
+                    ("array", items) => {
+                        Ok(OutputType::Array(Box::new(OutputType::from_json(items)?)))
+                    }
+                    ("object", JsonValue::Object(fields)) => fields
+                        .iter()
+                        .map(|(name, field)| Ok((name.clone(), OutputType::from_json(field)?)))
+                        .collect::<Result<_>>()
+                        .map(OutputType::Object),
+                    ("optional", inner) => Ok(OutputType::Optional(Box::new(
+                        OutputType::from_json(inner)?,
+                    ))),
+                    _ => Err(invalid(format!("malformed '{}' type", kind))),
+                }
+            }
+            other => Err(invalid(format!("unexpected {}", other))),
+        }
+    }
+
+    /// Coerces a result to this type
+    ///
+    /// Coercions never lose information: numeric strings become numbers,
+    /// `"true"` and `"false"` become booleans, numbers and booleans become
+    /// strings, and integral floats become integers. Fields of objects are
+    /// coerced individually, and fields that are not declared are kept.
+    pub fn coerce<'a>(
+        &self,
+        value: &'a DataValue<'a>,
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        self.coerce_at(value, "", arena)
+    }
+
+    fn coerce_at<'a>(
+        &self,
+        value: &'a DataValue<'a>,
+        path: &str,
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let mismatch = || LogicError::OutputTypeError {
+            path: path.to_string(),
+            expected: self.to_string(),
+            actual: value.to_string(),
+        };
+
+        match (self, value) {
+            (OutputType::Any, _)
+            | (OutputType::Boolean, DataValue::Bool(_))
+            | (OutputType::Number, DataValue::Number(_))
+            | (OutputType::Integer, DataValue::Number(NumberValue::Integer(_)))
+            | (OutputType::String, DataValue::String(_))
+            | (OutputType::Optional(_), DataValue::Null) => Ok(value),
+
+            (OutputType::Boolean, DataValue::String(s)) => match *s {
+                "true" => Ok(arena.true_value()),
+                "false" => Ok(arena.false_value()),
+                _ => Err(mismatch()),
+            },
+            (OutputType::Number, DataValue::String(s)) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(|n| arena.alloc(number(n)))
+                .ok_or_else(mismatch),
+            (OutputType::Integer, DataValue::Number(NumberValue::Float(n))) => {
+                integer(*n).map(|n| arena.alloc(n)).ok_or_else(mismatch)
+            }
+            (OutputType::Integer, DataValue::String(s)) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(integer)
+                .map(|n| arena.alloc(n))
+                .ok_or_else(mismatch),
+            (OutputType::String, DataValue::Number(_) | DataValue::Bool(_)) => {
+                Ok(arena.alloc(value.coerce_to_string(arena)))
+            }
+            (OutputType::Enum(values), DataValue::String(s)) => {
+                if values.iter().any(|v| v == s) {
+                    Ok(value)
+                } else {
+                    Err(mismatch())
+                }
+            }
+            (OutputType::Array(items), DataValue::Array(values)) => {
+                let mut coerced = Vec::with_capacity(values.len());
+                for (index, item) in values.iter().enumerate() {
+                    let item_path = format!("{}/{}", path, index);
+                    coerced.push(items.coerce_at(item, &item_path, arena)?.clone());
+                }
+                Ok(arena.alloc(DataValue::Array(arena.vec_into_slice(coerced))))
+            }
+            (OutputType::Object(fields), DataValue::Object(entries)) => {
+                let mut coerced = entries.to_vec();
+                for (name, field) in fields {
+                    let field_path = format!("{}/{}", path, name);
+                    let position = coerced.iter().position(|(key, _)| key == name);
+                    let current = position.map_or(arena.null_value(), |i| &entries[i].1);
+                    let current = field.coerce_at(current, &field_path, arena)?.clone();
+                    match position {
+                        Some(i) => coerced[i].1 = current,
+                        // Optional fields that are absent stay absent
+                        None if current.is_null() => {}
+                        None => coerced.push((arena.intern_str(name), current)),
+                    }
+                }
+                Ok(arena.alloc(DataValue::Object(arena.vec_into_slice(coerced))))
+            }
+            (OutputType::Optional(inner), _) => inner.coerce_at(value, path, arena),
+            _ => Err(mismatch()),
+        }
+    }
+
+    /// Returns true if a value of the given kind conforms without coercion.
+    fn accepts(&self, kind: Kind) -> bool {
+        match self {
+            OutputType::Any => true,
+            OutputType::Boolean => kind == Kind::Boolean,
+            OutputType::Number | OutputType::Integer => kind == Kind::Number,
+            OutputType::String | OutputType::Enum(_) => kind == Kind::String,
+            OutputType::Array(_) => kind == Kind::Array,
+            OutputType::Object(_) => kind == Kind::Object,
+            OutputType::Optional(inner) => kind == Kind::Null || inner.accepts(kind),
+        }
+    }
+
+    /// Warns about branches of a rule that can return another type
+    ///
+    /// Only results whose type is known before evaluation are checked, such
+    /// as literals and the results of comparisons or arithmetic. Each warning
+    /// names the path of the offending branch, such as `/if/2`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::output::OutputType;
+    /// use datalogic_rs::DataLogic;
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = dl
+    ///     .parse_logic(
+    ///         r#"{"if": [{"var": "vip"}, "gold", {"var": "member"}, "silver", "bronze"]}"#,
+    ///         None,
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let tiers = OutputType::Enum(vec!["gold".into(), "silver".into()]);
+    /// assert_eq!(
+    ///     tiers.check(rule.root()),
+    ///     ["/if/4 can return \"bronze\", which is not one of [gold, silver]"]
+    /// );
+    /// ```
+    pub fn check(&self, rule: &Token) -> Vec<String> {
+        let mut results = Vec::new();
+        result_sites(rule, String::new(), &mut results);
+
+        let mut warnings = Vec::new();
+        for (path, site) in results {
+            let path = if path.is_empty() {
+                "/".to_string()
+            } else {
+                path
+            };
+            let warning = match site {
+                Site::Literal(value) => {
+                    let known = Kind::of(value).is_some_and(|kind| self.accepts(kind));
+                    let in_enum = match (self.enum_values(), value) {
+                        (Some(values), DataValue::String(s)) => values.iter().any(|v| v == s),
+                        _ => true,
+                    };
+                    (!known || !in_enum)
+                        .then(|| format!("{} can return {}, which is not {}", path, value, self))
+                }
+                Site::Kind(kind) => (!self.accepts(kind))
+                    .then(|| format!("{} can return {}, which is not {}", path, kind.name(), self)),
+                Site::Unknown => None,
+            };
+            warnings.extend(warning);
+        }
+        warnings
+    }
+
+    /// Returns the allowed strings of an enum, looking through optional.
+    fn enum_values(&self) -> Option<&[String]> {
+        match self {
+            OutputType::Enum(values) => Some(values),
+            OutputType::Optional(inner) => inner.enum_values(),
+            _ => None,
+        }
+    }
+}
+
+/// Creates a number value, preferring integers.
+fn number(n: f64) -> DataValue<'static> {
+    integer(n).unwrap_or(DataValue::float(n))
+}
+
+/// Creates an integer value if a float has no fractional part.
+fn integer(n: f64) -> Option<DataValue<'static>> {
+    (n.fract() == 0.0 && n.abs() < i64::MAX as f64).then(|| DataValue::integer(n as i64))
+}
+
+/// What is known about a value a rule can return
+enum Site<'t> {
+    /// A constant
+    Literal(&'t DataValue<'t>),
+    /// A value of a known kind
+    Kind(Kind),
+    /// A value only known after evaluation
+    Unknown,
+}
+
+/// Returns the argument tokens of an operator.
+fn arguments<'t>(args: &'t Token<'t>) -> Vec<&'t Token<'t>> {
+    match args {
+        Token::ArrayLiteral(items) => items.clone(),
+        _ => vec![args],
+    }
+}
+
+/// Collects the expressions whose value a rule can return, with their paths.
+fn result_sites<'t>(token: &'t Token<'t>, path: String, sites: &mut Vec<(String, Site<'t>)>) {
+    let (op_type, args) = match token {
+        Token::Literal(value) => {
+            sites.push((path, Site::Literal(value)));
+            return;
+        }
+        Token::Operator { op_type, args }
+        | Token::CompiledMatch { op_type, args, .. }
+        | Token::CompiledRegex { op_type, args, .. }
+        | Token::CaseFolded { op_type, args } => (*op_type, arguments(args)),
+        _ => {
+            sites.push((path, Site::Unknown));
+            return;
+        }
+    };
+
+    let nested = |index: usize| format!("{}/{}/{}", path, op_type.as_str(), index);
+    match op_type {
+        // The result is one of the branches, or null without an else branch
+        OperatorType::Control(ControlOp::If) => {
+            for (index, arg) in args.iter().enumerate() {
+                if index % 2 == 1 || index + 1 == args.len() {
+                    result_sites(arg, nested(index), sites);
+                }
+            }
+            if args.len() % 2 == 0 {
+                sites.push((path, Site::Kind(Kind::Null)));
+            }
+        }
+        // The result is one of the arguments
+        OperatorType::Control(ControlOp::And | ControlOp::Or)
+        | OperatorType::Coalesce
+        | OperatorType::Try
+        | OperatorType::Log => {
+            for (index, arg) in args.iter().enumerate() {
+                result_sites(arg, nested(index), sites);
+            }
+        }
+        // The result is the last expression, or the assigned value
+        OperatorType::Do | OperatorType::Assign => match args.last() {
+            Some(last) => result_sites(last, nested(args.len() - 1), sites),
+            None => sites.push((path, Site::Kind(Kind::Null))),
+        },
+        // The result is one of the candidate values, or null
+        OperatorType::Default => {
+            let (values, _) = split_default_options(&args);
+            for (index, arg) in values.iter().enumerate() {
+                result_sites(arg, nested(index), sites);
+            }
+            sites.push((path, Site::Kind(Kind::Null)));
+        }
+        // The result is the annotated rule, or null for a note on its own
+        OperatorType::Annotate => match args.get(1) {
+            Some(rule) => result_sites(rule, nested(1), sites),
+            None => sites.push((path, Site::Kind(Kind::Null))),
+        },
+        OperatorType::Throw => {}
+        _ => {
+            let kind = operator_kind(op_type);
+            sites.push((path, kind.map_or(Site::Unknown, Site::Kind)));
+        }
+    }
+}
+
+/// Returns the kind of value an operator always produces, if it has one.
+fn operator_kind(op_type: OperatorType) -> Option<Kind> {
+    match op_type {
+        OperatorType::Comparison(_)
+        | OperatorType::Control(ControlOp::Not | ControlOp::DoubleNegation)
+        | OperatorType::Array(ArrayOp::In | ArrayOp::All | ArrayOp::Some | ArrayOp::None)
+        | OperatorType::String(StringOp::StartsWith | StringOp::EndsWith | StringOp::Regex)
+        | OperatorType::Exists
+        | OperatorType::TypeCheck(_)
+        | OperatorType::Empty => Some(Kind::Boolean),
+        OperatorType::Arithmetic(ArithmeticOp::Min | ArithmeticOp::Max) => None,
+        OperatorType::Arithmetic(_)
+        | OperatorType::Array(ArrayOp::Length | ArrayOp::Sum | ArrayOp::Count) => {
+            Some(Kind::Number)
+        }
+        OperatorType::String(
+            StringOp::Cat
+            | StringOp::Substr
+            | StringOp::Upper
+            | StringOp::Lower
+            | StringOp::Trim
+            | StringOp::Replace
+            | StringOp::RegexReplace,
+        )
+        | OperatorType::Type => Some(Kind::String),
+        OperatorType::Array(
+            ArrayOp::Map
+            | ArrayOp::Filter
+            | ArrayOp::Merge
+            | ArrayOp::Sort
+            | ArrayOp::Related
+            | ArrayOp::Unique
+            | ArrayOp::Keys
+            | ArrayOp::Values
+            | ArrayOp::Entries,
+        )
+        | OperatorType::Missing
+        | OperatorType::MissingSome
+        | OperatorType::ArrayLiteral => Some(Kind::Array),
+        OperatorType::ObjectLiteral
+        | OperatorType::Array(ArrayOp::GroupBy | ArrayOp::FromEntries) => Some(Kind::Object),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::ToJson;
+    use crate::DataLogic;
+    use serde_json::json;
+
+    #[test]
+    fn test_coerce_output() {
+        let dl = DataLogic::new();
+        let coerce = |output: &OutputType, value: JsonValue| {
+            let value = dl.arena().alloc(dl.parse_data_json(&value).unwrap());
+            output
+                .coerce(value, dl.arena())
+                .map(|value| value.to_json())
+        };
+
+        assert_eq!(coerce(&OutputType::Number, json!(" 2.5")), Ok(json!(2.5)));
+        assert_eq!(coerce(&OutputType::Integer, json!(4.0)), Ok(json!(4)));
+        assert_eq!(coerce(&OutputType::Boolean, json!("true")), Ok(json!(true)));
+        assert_eq!(coerce(&OutputType::String, json!(7)), Ok(json!("7")));
+        assert!(coerce(&OutputType::Integer, json!(4.5)).is_err());
+        assert!(coerce(&OutputType::Boolean, json!(1)).is_err());
+
+        let offer = OutputType::from_json(&json!({"object": {
+            "tier": {"enum": ["gold", "silver"]},
+            "discount": "number",
+            "note": {"optional": "string"}
+        }}))
+        .unwrap();
+        assert_eq!(
+            coerce(
+                &offer,
+                json!({"tier": "gold", "discount": "10", "extra": [1]})
+            ),
+            Ok(json!({"tier": "gold", "discount": 10, "extra": [1]}))
+        );
+        assert_eq!(
+            coerce(&offer, json!({"tier": "bronze", "discount": 10})),
+            Err(LogicError::OutputTypeError {
+                path: "/tier".to_string(),
+                expected: "one of [gold, silver]".to_string(),
+                actual: "\"bronze\"".to_string(),
+            })
+        );
+
+        let scores = OutputType::Array(Box::new(OutputType::Integer));
+        assert_eq!(coerce(&scores, json!([1, "2"])), Ok(json!([1, 2])));
+        assert!(coerce(&scores, json!([1, "x"]))
+            .unwrap_err()
+            .to_string()
+            .contains("/1"));
+    }
+
+    #[test]
+    fn test_check_branches() {
+        let dl = DataLogic::new();
+        let rule = dl
+            .parse_logic(
+                r#"{"if": [
+                    {"var": "a"}, {"+": [{"var": "x"}, 1]},
+                    {"var": "b"}, {"or": [{"var": "y"}, "none"]},
+                    {"var": "c"}, {"==": [{"var": "x"}, 1]}
+                ]}"#,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(
+            OutputType::Number.check(rule.root()),
+            [
+                "/if/3/or/1 can return \"none\", which is not number",
+                "/if/5 can return a boolean, which is not number",
+                "/ can return null, which is not number",
+            ]
+        );
+        let lenient = OutputType::Optional(Box::new(OutputType::Any));
+        assert!(lenient.check(rule.root()).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+//! Per-evaluation operator overrides
+//!
+//! Operator overrides replace the implementation of built-in or custom
+//! operators for a single evaluation, without touching the engine. This is
+//! useful to pin the clock when testing rules, to stub out external lookups,
+//! or to replay an incident deterministically.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::arena::{CustomOperator, DataArena, SimpleOperatorAdapter, SimpleOperatorFn};
+use crate::logic::Result;
+use crate::value::{DataValue, FromJson};
+use serde_json::Value as JsonValue;
+
+/// An operator that ignores its arguments and returns a fixed value
+#[derive(Debug)]
+struct ConstantOperator(JsonValue);
+
+impl CustomOperator for ConstantOperator {
+    fn evaluate<'a>(
+        &self,
+        _args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        Ok(arena.alloc(DataValue::from_json(&self.0, arena)))
+    }
+}
+
+/// A set of operator implementations that take precedence during an evaluation
+///
+/// Overrides are looked up by operator name, so they apply to built-in
+/// operators such as `"now"` as well as to custom operators. The arguments of
+/// an overridden operator are evaluated before it is called, as for custom
+/// operators.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::{DataLogic, OperatorOverrides};
+/// use serde_json::json;
+///
+/// let dl = DataLogic::new();
+/// let rule = dl
+///     .parse_logic(r#"{"==": [{"lookup_account": [{"var": "id"}]}, "gold"]}"#, None)
+///     .unwrap();
+/// let data = dl.parse_data(r#"{"id": 7}"#).unwrap();
+///
+/// let overrides = OperatorOverrides::new().with_value("lookup_account", json!("gold"));
+/// let result = dl.evaluate_with_overrides(&rule, &data, &overrides).unwrap();
+/// assert_eq!(result.as_bool(), Some(true));
+/// ```
+#[derive(Clone, Default)]
+pub struct OperatorOverrides {
+    operators: HashMap<String, Arc<dyn CustomOperator>>,
+}
+
+impl OperatorOverrides {
+    /// Create an empty set of overrides
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override an operator with a custom implementation
+    pub fn with_operator(mut self, name: &str, operator: Box<dyn CustomOperator>) -> Self {
+        self.operators.insert(name.to_string(), Arc::from(operator));
+        self
+    }
+
+    /// Override an operator with a simple function
+    pub fn with_simple_operator(self, name: &str, function: SimpleOperatorFn) -> Self {
+        self.with_operator(name, Box::new(SimpleOperatorAdapter::new(name, function)))
+    }
+
+    /// Override an operator with a constant result
+    pub fn with_value(self, name: &str, value: JsonValue) -> Self {
+        self.with_operator(name, Box::new(ConstantOperator(value)))
+    }
+
+    /// Get the override of an operator
+    pub fn get(&self, name: &str) -> Option<Arc<dyn CustomOperator>> {
+        self.operators.get(name).cloned()
+    }
+
+    /// Check if an operator is overridden
+    pub fn contains(&self, name: &str) -> bool {
+        self.operators.contains_key(name)
+    }
+
+    /// Number of overridden operators
+    pub fn len(&self) -> usize {
+        self.operators.len()
+    }
+
+    /// Check if there are no overrides
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+    }
+}
+
+impl fmt::Debug for OperatorOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.operators.keys().collect();
+        names.sort();
+        f.debug_struct("OperatorOverrides")
+            .field("operators", &names)
+            .finish()
+    }
+}
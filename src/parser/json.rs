@@ -0,0 +1,127 @@
+//! Parsing of JSON text
+//!
+//! Text is parsed by serde_json, which rejects trailing data and lone
+//! surrogates and reports the line and column of errors. Strict parsing
+//! also rejects objects with duplicate keys, of which serde_json keeps the
+//! last value.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+/// Parses a JSON text, rejecting duplicate keys if `strict`.
+pub(crate) fn from_str(source: &str, strict: bool) -> serde_json::Result<JsonValue> {
+    if !strict {
+        return serde_json::from_str(source);
+    }
+    let mut deserializer = serde_json::Deserializer::from_str(source);
+    let StrictValue(value) = StrictValue::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// A JSON value whose objects have no duplicate keys
+struct StrictValue(JsonValue);
+
+impl<'de> Deserialize<'de> for StrictValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(StrictVisitor).map(StrictValue)
+    }
+}
+
+struct StrictVisitor;
+
+impl<'de> Visitor<'de> for StrictVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(StrictValue(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut object = JsonMap::new();
+        let mut keys = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            // serde_json adds the position of the error to the message
+            if !keys.insert(key.clone()) {
+                return Err(de::Error::custom(format_args!("duplicate key `{}`", key)));
+            }
+            let StrictValue(value) = map.next_value()?;
+            object.insert(key, value);
+        }
+        Ok(JsonValue::Object(object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_strict_json() {
+        let source = r#"{"a": [1, 2.5, "x", null, true], "b": {"c": {}}}"#;
+        assert_eq!(
+            from_str(source, true).unwrap(),
+            from_str(source, false).unwrap()
+        );
+
+        let source = r#"{"if": [true, {"a": 1, "a": 2}]}"#;
+        assert_eq!(
+            from_str(source, false).unwrap(),
+            json!({"if": [true, {"a": 2}]})
+        );
+        assert_eq!(
+            from_str(source, true).unwrap_err().to_string(),
+            "duplicate key `a` at line 1 column 26"
+        );
+
+        // Escaped keys are compared once decoded
+        assert!(from_str(r#"{"a": 1, "\u0061": 2}"#, true).is_err());
+        // Keys only need to be unique within their object
+        assert!(from_str(r#"{"a": {"a": 1}, "b": {"a": 2}}"#, true).is_ok());
+
+        for source in [r#"{"a": 1} {"b": 2}"#, r#""\udc00""#, r#""\ud800x""#] {
+            assert!(from_str(source, false).is_err());
+            assert!(from_str(source, true).is_err());
+        }
+    }
+}
@@ -5,27 +5,74 @@
 use std::str::FromStr;
 
 use crate::arena::DataArena;
-use crate::logic::{LogicError, OperatorType, Result, Token};
-use crate::parser::ExpressionParser;
+use crate::logic::{
+    ArrayOp, ControlOp, LogicError, OperatorType, RangeOp, Result, Token, DEFAULT_OPTION_KEYS,
+    UNIQUE_OPTION_KEYS,
+};
+use crate::parser::{json, ExpressionParser};
+use crate::semantics::SPEC_OPERATORS;
 use crate::value::{DataValue, FromJson};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 
+/// Configuration of the JSONLogic parser
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParserConfig {
+    /// Whether objects with several keys construct an object from their
+    /// values, each parsed as a rule, instead of failing as unknown operators
+    pub object_construction: bool,
+    /// How a rule that is an array of rules combines their results
+    pub rule_list: RuleList,
+    /// Whether JSON text with duplicate keys is rejected
+    pub strict_json: bool,
+}
+
+/// How a rule that is a JSON array of rules is evaluated
+///
+/// Set with [`DataLogic::set_rule_list`](crate::DataLogic::set_rule_list).
+/// Only the array at the top level of a rule is affected; arrays nested in
+/// operators are always evaluated to the array of their items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RuleList {
+    /// Evaluates to the array of the results of the rules
+    #[default]
+    Collect,
+    /// Evaluates as an `and` of the rules, so passes if all of them pass
+    ///
+    /// An empty list passes.
+    All,
+    /// Evaluates as an `or` of the rules, so passes if any of them passes
+    ///
+    /// An empty list fails.
+    Any,
+}
+
 /// Parser for JSONLogic expressions
-pub struct JsonLogicParser;
+#[derive(Default)]
+pub struct JsonLogicParser {
+    config: ParserConfig,
+}
+
+impl JsonLogicParser {
+    /// Creates a parser with the given configuration
+    pub fn with_config(config: ParserConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl ExpressionParser for JsonLogicParser {
     fn parse<'a>(&self, input: &str, arena: &'a DataArena) -> Result<&'a Token<'a>> {
         // Parse the input string as JSON
-        let json: JsonValue = serde_json::from_str(input).map_err(|e| LogicError::ParseError {
-            reason: format!("Invalid JSON: {}", e),
-        })?;
+        let json =
+            json::from_str(input, self.config.strict_json).map_err(|e| LogicError::ParseError {
+                reason: format!("Invalid JSON: {}", e),
+            })?;
 
         // Use the JSONLogic parsing logic
-        parse_json(&json, arena)
+        parse_json_with_config(&json, self.config, arena)
     }
 
     fn parse_json<'a>(&self, input: &JsonValue, arena: &'a DataArena) -> Result<&'a Token<'a>> {
-        parse_json(input, arena)
+        parse_json_with_config(input, self.config, arena)
     }
 
     fn format_name(&self) -> &'static str {
@@ -45,14 +92,104 @@ fn is_json_literal(value: &JsonValue) -> bool {
     }
 }
 
-/// Parses a logic expression from a JSON value.
+/// Converts a literal to a value, interning its strings.
+///
+/// String constants are often repeated within a rule, such as the outcomes
+/// of its branches, so interning them keeps one copy of each in the arena.
+fn literal_value<'a>(json: &JsonValue, arena: &'a DataArena) -> DataValue<'a> {
+    match json {
+        JsonValue::String(s) => DataValue::String(arena.intern_str(s)),
+        JsonValue::Array(items) => {
+            let values = items
+                .iter()
+                .map(|item| literal_value(item, arena))
+                .collect();
+            DataValue::Array(arena.vec_into_slice(values))
+        }
+        _ => DataValue::from_json(json, arena),
+    }
+}
+
+/// Parses a logic expression from a JSON value with the default configuration.
+#[cfg(test)]
 pub fn parse_json<'a>(json: &JsonValue, arena: &'a DataArena) -> Result<&'a Token<'a>> {
-    let token = parse_json_internal(json, arena)?;
+    parse_json_with_config(json, ParserConfig::default(), arena)
+}
+
+/// Parses a logic expression from a JSON value with the given configuration.
+pub fn parse_json_with_config<'a>(
+    json: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<&'a Token<'a>> {
+    let token = match (json, config.rule_list) {
+        (JsonValue::Array(rules), RuleList::All | RuleList::Any) => {
+            parse_rule_list(rules, config, arena)?
+        }
+        _ => parse_json_internal(json, config, arena)?,
+    };
     Ok(arena.alloc(token))
 }
 
+/// Parses a rule nested in another one.
+///
+/// Unlike [`parse_json_with_config`], a top-level array is never a rule list.
+pub(crate) fn parse_nested_rule<'a>(
+    json: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<&'a Token<'a>> {
+    let token = parse_json_internal(json, config, arena)?;
+    Ok(arena.alloc(token))
+}
+
+/// Checks if each argument of an operator is parsed as a rule on its own,
+/// independently of the other arguments.
+///
+/// Arguments that may be an options object, and those of the operators
+/// lowered by the parser or checked against a schema, are not.
+pub(crate) fn has_rule_arguments(key: &str, arena: &DataArena) -> bool {
+    match key {
+        "var" | "val" | "preserve" | "validate" | "obj" | "annotate" | "//" => false,
+        "exists" => true,
+        _ => match OperatorType::from_str(key) {
+            Ok(op_type) => operator_option_keys(op_type).is_none(),
+            Err(_) => {
+                custom_operator_option_keys(key).is_none()
+                    && matches!(arena.resolve_versioned_operator(key), Ok(None))
+            }
+        },
+    }
+}
+
+/// Parses a top-level array of rules into the operator combining them.
+fn parse_rule_list<'a>(
+    rules: &[JsonValue],
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
+    let all = config.rule_list == RuleList::All;
+    if rules.is_empty() {
+        return Ok(Token::literal(DataValue::bool(all)));
+    }
+
+    let rules = rules
+        .iter()
+        .map(|rule| parse_json_internal(rule, config, arena).map(|token| arena.alloc(token)))
+        .collect::<Result<Vec<_>>>()?;
+    let op_type = if all { ControlOp::And } else { ControlOp::Or };
+    Ok(Token::operator(
+        OperatorType::Control(op_type),
+        arena.alloc(Token::ArrayLiteral(rules)),
+    ))
+}
+
 /// Internal function for parsing a JSON value into a token.
-fn parse_json_internal<'a>(json: &JsonValue, arena: &'a DataArena) -> Result<Token<'a>> {
+fn parse_json_internal<'a>(
+    json: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
     match json {
         // Simple literals
         JsonValue::Null => Ok(Token::literal(DataValue::null())),
@@ -68,7 +205,7 @@ fn parse_json_internal<'a>(json: &JsonValue, arena: &'a DataArena) -> Result<Tok
                 })
             }
         }
-        JsonValue::String(s) => Ok(Token::literal(DataValue::string(arena, s))),
+        JsonValue::String(_) => Ok(Token::literal(literal_value(json, arena))),
 
         // Arrays could be literal arrays or token arrays
         JsonValue::Array(arr) => {
@@ -83,18 +220,12 @@ fn parse_json_internal<'a>(json: &JsonValue, arena: &'a DataArena) -> Result<Tok
 
             // If all elements are literals, create a literal array
             if all_literals {
-                let mut values = Vec::with_capacity(arr.len());
-                for item in arr {
-                    let value = DataValue::from_json(item, arena);
-                    values.push(value);
-                }
-                let values_slice = arena.vec_into_slice(values);
-                Ok(Token::literal(DataValue::Array(values_slice)))
+                Ok(Token::literal(literal_value(json, arena)))
             } else {
                 // Otherwise, create an array of tokens and allocate them in the arena
                 let mut tokens = Vec::with_capacity(arr.len());
                 for item in arr {
-                    let token = parse_json_internal(item, arena)?;
+                    let token = parse_json_internal(item, config, arena)?;
                     let token_ref = arena.alloc(token);
                     tokens.push(token_ref);
                 }
@@ -103,24 +234,39 @@ fn parse_json_internal<'a>(json: &JsonValue, arena: &'a DataArena) -> Result<Tok
         }
 
         // Objects could be operators or literal objects
-        JsonValue::Object(obj) => parse_object(obj, arena),
+        JsonValue::Object(obj) => parse_object(obj, config, arena),
     }
 }
 
 /// Parses a JSON object into a token.
-fn parse_object<'a>(obj: &JsonMap<String, JsonValue>, arena: &'a DataArena) -> Result<Token<'a>> {
+fn parse_object<'a>(
+    obj: &JsonMap<String, JsonValue>,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
     // If the object has exactly one key, it might be an operator
     if obj.len() == 1 {
         let (key, value) = obj.iter().next().unwrap();
 
+        // Only the operators of the specification are built in when it is
+        // strictly followed
+        if arena.semantics().is_spec() && is_extension_operator(key) {
+            return Err(LogicError::OperatorNotFoundError {
+                operator: key.clone(),
+            });
+        }
+
         match key.as_str() {
-            "var" => parse_variable(value, arena),
+            "var" => parse_variable(value, config, arena),
             "val" => {
-                let token = parse_json_internal(value, arena)?;
+                let token = parse_json_internal(value, config, arena)?;
                 let args_token = arena.alloc(token);
                 Ok(Token::operator(OperatorType::Val, args_token))
             }
-            "exists" => parse_exists_operator(value, arena),
+            "exists" => parse_exists_operator(value, config, arena),
+            "validate" => parse_validate_operator(value, config, arena),
+            "obj" => parse_obj_operator(value, config, arena),
+            "annotate" | "//" => parse_annotate_operator(value, config, arena),
             "preserve" => {
                 // The preserve operator returns its argument as-is without parsing it as an operator
                 let preserved_value = DataValue::from_json(value, arena);
@@ -129,11 +275,11 @@ fn parse_object<'a>(obj: &JsonMap<String, JsonValue>, arena: &'a DataArena) -> R
             _ => {
                 // Check if it's a standard operator
                 if let Ok(op_type) = OperatorType::from_str(key) {
-                    return parse_operator(op_type, value, arena);
+                    return parse_operator(op_type, value, config, arena);
                 }
 
                 // Otherwise, treat it as a custom operator
-                parse_custom_operator(key, value, arena)
+                parse_custom_operator(key, value, config, arena)
             }
         }
     } else if obj.is_empty() {
@@ -141,6 +287,8 @@ fn parse_object<'a>(obj: &JsonMap<String, JsonValue>, arena: &'a DataArena) -> R
         Ok(Token::literal(DataValue::Object(
             arena.vec_into_slice(vec![]),
         )))
+    } else if config.object_construction {
+        parse_object_construction(obj, config, arena)
     } else {
         // For multi-key objects, treat the first key as an unknown operator
         // This matches the JSONLogic behavior where multi-key objects should
@@ -154,8 +302,97 @@ fn parse_object<'a>(obj: &JsonMap<String, JsonValue>, arena: &'a DataArena) -> R
     }
 }
 
+/// Checks if an operator is built in but not defined by the specification.
+fn is_extension_operator(key: &str) -> bool {
+    !SPEC_OPERATORS.contains(&key)
+        && (matches!(key, "val" | "exists" | "validate" | "preserve" | "obj")
+            || OperatorType::from_str(key).is_ok())
+}
+
+/// Parses a multi-key object into an object built from its values, each
+/// parsed as a rule.
+fn parse_object_construction<'a>(
+    obj: &JsonMap<String, JsonValue>,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
+    let entries: Vec<(&str, &JsonValue)> = obj
+        .iter()
+        .map(|(key, value)| (key.as_str(), value))
+        .collect();
+    build_object(&entries, config, arena)
+}
+
+/// Parses an `obj` operator, whose argument is an array of `[key, value]`
+/// pairs, into an object built from its values, each parsed as a rule.
+fn parse_obj_operator<'a>(
+    value: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
+    let invalid = |reason: &str| LogicError::ParseError {
+        reason: format!("Invalid obj operator: {}", reason),
+    };
+    let JsonValue::Array(pairs) = value else {
+        return Err(invalid("expected an array of [key, value] pairs"));
+    };
+
+    let mut entries = Vec::with_capacity(pairs.len());
+    for pair in pairs {
+        let (key, value) = match pair.as_array().map(Vec::as_slice) {
+            Some([JsonValue::String(key), value]) => (key.as_str(), value),
+            _ => return Err(invalid("expected a [key, value] pair with a string key")),
+        };
+        if entries.iter().any(|(existing, _)| *existing == key) {
+            return Err(invalid(&format!("duplicate key '{}'", key)));
+        }
+        entries.push((key, value));
+    }
+    build_object(&entries, config, arena)
+}
+
+/// Builds an object from its keys and the rules computing its values.
+///
+/// The keys are passed as a literal array in front of the values.
+fn build_object<'a>(
+    entries: &[(&str, &JsonValue)],
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
+    // Objects made only of literals are literal objects
+    if entries.iter().all(|(_, value)| is_json_literal(value)) {
+        let object: JsonMap<String, JsonValue> = entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), (*value).clone()))
+            .collect();
+        let object = JsonValue::Object(object);
+        return Ok(Token::literal(DataValue::from_json(&object, arena)));
+    }
+
+    let keys: Vec<DataValue> = entries
+        .iter()
+        .map(|(key, _)| DataValue::string(arena, key))
+        .collect();
+    let mut tokens = Vec::with_capacity(entries.len() + 1);
+    let keys = Token::literal(DataValue::Array(arena.vec_into_slice(keys)));
+    tokens.push(arena.alloc(keys));
+    for (_, value) in entries {
+        let token = parse_json_internal(value, config, arena)?;
+        tokens.push(arena.alloc(token));
+    }
+
+    Ok(Token::operator(
+        OperatorType::ObjectLiteral,
+        arena.alloc(Token::ArrayLiteral(tokens)),
+    ))
+}
+
 /// Parses a variable reference.
-fn parse_variable<'a>(var_json: &JsonValue, arena: &'a DataArena) -> Result<Token<'a>> {
+fn parse_variable<'a>(
+    var_json: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
     match var_json {
         // Simple variable reference
         JsonValue::String(path) => {
@@ -190,12 +427,12 @@ fn parse_variable<'a>(var_json: &JsonValue, arena: &'a DataArena) -> Result<Toke
                 && !arr[0].is_null()
             {
                 // Parse the path expression
-                let path_expr = parse_json_internal(&arr[0], arena)?;
+                let path_expr = parse_json_internal(&arr[0], config, arena)?;
                 let path_token = arena.alloc(path_expr);
 
                 // If there's a default value, parse it
                 let default = if arr.len() >= 2 {
-                    let default_token = parse_json_internal(&arr[1], arena)?;
+                    let default_token = parse_json_internal(&arr[1], config, arena)?;
                     Some(arena.alloc(default_token))
                 } else {
                     None
@@ -221,7 +458,7 @@ fn parse_variable<'a>(var_json: &JsonValue, arena: &'a DataArena) -> Result<Toke
                 };
 
                 // Parse the default value
-                let default_token = parse_json_internal(&arr[1], arena)?;
+                let default_token = parse_json_internal(&arr[1], config, arena)?;
                 let default = arena.alloc(default_token);
 
                 return Ok(Token::variable(path, Some(default)));
@@ -279,7 +516,7 @@ fn parse_variable<'a>(var_json: &JsonValue, arena: &'a DataArena) -> Result<Toke
 
             // If there are two or more elements, the second is the default
             // Parse the default value
-            let default_token = parse_json_internal(&arr[1], arena)?;
+            let default_token = parse_json_internal(&arr[1], config, arena)?;
             let default = arena.alloc(default_token);
 
             Ok(Token::variable(path, Some(default)))
@@ -310,7 +547,7 @@ fn parse_variable<'a>(var_json: &JsonValue, arena: &'a DataArena) -> Result<Toke
         // Handle object as variable path (e.g., {"cat": ["te", "st"]})
         JsonValue::Object(_) => {
             // Parse the object as a regular expression
-            let path_expr = parse_json_internal(var_json, arena)?;
+            let path_expr = parse_json_internal(var_json, config, arena)?;
             let path_token = arena.alloc(path_expr);
 
             // Create a dynamic variable reference where the path will be evaluated at runtime
@@ -328,34 +565,155 @@ fn parse_variable<'a>(var_json: &JsonValue, arena: &'a DataArena) -> Result<Toke
 fn parse_operator<'a>(
     op_type: OperatorType,
     args_json: &JsonValue,
+    config: ParserConfig,
     arena: &'a DataArena,
 ) -> Result<Token<'a>> {
-    // Parse the arguments
-    let args = parse_arguments(args_json, arena)?;
+    // Parse the arguments, keeping a trailing options object as a literal
+    let args = match operator_option_keys(op_type) {
+        Some(option_keys) => parse_arguments_with_options(args_json, option_keys, config, arena)?,
+        None => parse_arguments(args_json, config, arena)?,
+    };
 
     // Create the operator token
     Ok(Token::operator(op_type, args))
 }
 
+/// Returns the option keys accepted in a trailing options object by an operator.
+fn operator_option_keys(op_type: OperatorType) -> Option<&'static [&'static str]> {
+    match op_type {
+        OperatorType::Range(RangeOp::Range) => Some(&["inclusive"]),
+        OperatorType::Array(ArrayOp::In) => Some(&["fold_case"]),
+        OperatorType::Array(ArrayOp::Unique) => Some(UNIQUE_OPTION_KEYS),
+        OperatorType::Default => Some(DEFAULT_OPTION_KEYS),
+        _ => None,
+    }
+}
+
+/// Returns the option keys accepted in a trailing options object by a custom operator.
+fn custom_operator_option_keys(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        #[cfg(feature = "predict")]
+        "predict" => Some(crate::predict::PREDICT_OPTION_KEYS),
+        _ => None,
+    }
+}
+
+/// Checks if a JSON value is an options object made only of the given keys.
+fn is_options_object(value: &JsonValue, option_keys: &[&str]) -> bool {
+    match value {
+        JsonValue::Object(obj) => {
+            !obj.is_empty() && obj.keys().all(|key| option_keys.contains(&key.as_str()))
+        }
+        _ => false,
+    }
+}
+
+/// Parses operator arguments whose last element may be an options object.
+///
+/// Options objects such as `{"inclusive": true}` would otherwise be parsed as
+/// custom operator applications, so they are kept as literal objects.
+fn parse_arguments_with_options<'a>(
+    args_json: &JsonValue,
+    option_keys: &[&str],
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<&'a Token<'a>> {
+    let arr = match args_json {
+        JsonValue::Array(arr)
+            if arr
+                .last()
+                .is_some_and(|v| is_options_object(v, option_keys)) =>
+        {
+            arr
+        }
+        _ => return parse_arguments(args_json, config, arena),
+    };
+
+    let (options, rest) = arr.split_last().unwrap();
+    let mut tokens = Vec::with_capacity(arr.len());
+    for arg_json in rest {
+        let arg = parse_json_internal(arg_json, config, arena)?;
+        tokens.push(arena.alloc(arg));
+    }
+    let options_token = Token::literal(DataValue::from_json(options, arena));
+    tokens.push(arena.alloc(options_token));
+
+    Ok(arena.alloc(Token::ArrayLiteral(tokens)))
+}
+
 /// Parses a custom operator application.
 fn parse_custom_operator<'a>(
     name: &str,
     args_json: &JsonValue,
+    config: ParserConfig,
     arena: &'a DataArena,
 ) -> Result<Token<'a>> {
     // Parse the arguments
-    let args = parse_arguments(args_json, arena)?;
+    let args = match custom_operator_option_keys(name) {
+        Some(option_keys) => parse_arguments_with_options(args_json, option_keys, config, arena)?,
+        None => parse_arguments(args_json, config, arena)?,
+    };
+
+    // Namespaced operators resolve to the best matching registered version
+    if let Some((key, metadata)) = arena.resolve_versioned_operator(name)? {
+        if let Some(arg_types) = metadata.arg_types() {
+            check_arg_schema(&key, arg_types, args)?;
+        }
+        return Ok(Token::custom_operator(arena.intern_str(&key), args));
+    }
 
     // Create the custom operator token
     Ok(Token::custom_operator(arena.intern_str(name), args))
 }
 
+/// Checks the arguments of a versioned operator against its declared types.
+///
+/// Only literal arguments can be type checked at compile time.
+fn check_arg_schema(name: &str, arg_types: &[String], args: &Token) -> Result<()> {
+    let args: Vec<&Token> = match args {
+        Token::ArrayLiteral(items) => items.to_vec(),
+        arg => vec![arg],
+    };
+
+    if args.len() != arg_types.len() {
+        return Err(LogicError::ParseError {
+            reason: format!(
+                "Operator '{}' expects {} argument(s), got {}",
+                name,
+                arg_types.len(),
+                args.len()
+            ),
+        });
+    }
+
+    for (index, (arg, expected)) in args.iter().zip(arg_types).enumerate() {
+        if let Token::Literal(value) = arg {
+            if expected != "any" && value.type_name() != expected {
+                return Err(LogicError::ParseError {
+                    reason: format!(
+                        "Argument {} of operator '{}' must be of type {}, got {}",
+                        index + 1,
+                        name,
+                        expected,
+                        value.type_name()
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Parses the arguments for an operator.
-fn parse_arguments<'a>(args_json: &JsonValue, arena: &'a DataArena) -> Result<&'a Token<'a>> {
+fn parse_arguments<'a>(
+    args_json: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<&'a Token<'a>> {
     match args_json {
         // Single argument that's not an array - no need for ArrayLiteral
         _ if !args_json.is_array() => {
-            let arg = parse_json_internal(args_json, arena)?;
+            let arg = parse_json_internal(args_json, config, arena)?;
             Ok(arena.alloc(arg))
         }
 
@@ -371,7 +729,7 @@ fn parse_arguments<'a>(args_json: &JsonValue, arena: &'a DataArena) -> Result<&'
 
             // Parse each argument
             for arg_json in arr {
-                let arg = parse_json_internal(arg_json, arena)?;
+                let arg = parse_json_internal(arg_json, config, arena)?;
                 let arg_ref = arena.alloc(arg);
                 tokens.push(arg_ref);
             }
@@ -387,14 +745,143 @@ fn parse_arguments<'a>(args_json: &JsonValue, arena: &'a DataArena) -> Result<&'
 }
 
 /// Parses the exists operator application.
-fn parse_exists_operator<'a>(value: &JsonValue, arena: &'a DataArena) -> Result<Token<'a>> {
+fn parse_exists_operator<'a>(
+    value: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
     // Parse the arguments for exists operator
-    let args = parse_arguments(value, arena)?;
+    let args = parse_arguments(value, config, arena)?;
 
     // Create the exists operator token
     Ok(Token::operator(OperatorType::Exists, args))
 }
 
+/// Parses an annotation into the arguments `[note, rule]`, or `[note]` for
+/// a note on its own.
+///
+/// The annotation is either an object with exactly the keys `note` and
+/// `rule`, an array `[note, rule]`, or any other value, which is a note. The
+/// note is kept as a literal.
+fn parse_annotate_operator<'a>(
+    value: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
+    let invalid = || LogicError::ParseError {
+        reason: "annotate requires a note, [note, rule] or {\"note\": ..., \"rule\": ...}"
+            .to_string(),
+    };
+
+    let (note, rule) = match value {
+        JsonValue::Object(obj) if obj.contains_key("note") || obj.contains_key("rule") => {
+            match (obj.get("note"), obj.get("rule")) {
+                (Some(note), Some(rule)) if obj.len() == 2 => (note, Some(rule)),
+                _ => return Err(invalid()),
+            }
+        }
+        JsonValue::Array(items) => match items.as_slice() {
+            [note] => (note, None),
+            [note, rule] => (note, Some(rule)),
+            _ => return Err(invalid()),
+        },
+        note => (note, None),
+    };
+
+    let mut args = vec![arena.alloc(Token::literal(DataValue::from_json(note, arena))) as &Token];
+    if let Some(rule) = rule {
+        args.push(arena.alloc(parse_json_internal(rule, config, arena)?));
+    }
+    Ok(Token::operator(
+        OperatorType::Annotate,
+        arena.alloc(Token::ArrayLiteral(args)),
+    ))
+}
+
+/// Parses a validate operator.
+///
+/// The specification object is lowered into the arguments
+/// `[required, types, [rule, details]...]`, with one `[rule, details]` pair
+/// per custom check.
+fn parse_validate_operator<'a>(
+    value: &JsonValue,
+    config: ParserConfig,
+    arena: &'a DataArena,
+) -> Result<Token<'a>> {
+    let JsonValue::Object(spec) = value else {
+        return Err(LogicError::ParseError {
+            reason: "validate expects a specification object".to_string(),
+        });
+    };
+
+    if let Some(key) = spec
+        .keys()
+        .find(|key| !matches!(key.as_str(), "required" | "types" | "custom"))
+    {
+        return Err(LogicError::ParseError {
+            reason: format!("Unknown validate section: {}", key),
+        });
+    }
+
+    let required = match spec.get("required") {
+        Some(required @ JsonValue::Array(_)) => DataValue::from_json(required, arena),
+        Some(_) => {
+            return Err(LogicError::ParseError {
+                reason: "validate required must be an array of paths".to_string(),
+            })
+        }
+        None => DataValue::Array(&[]),
+    };
+
+    let types = match spec.get("types") {
+        Some(types @ JsonValue::Object(_)) => DataValue::from_json(types, arena),
+        Some(_) => {
+            return Err(LogicError::ParseError {
+                reason: "validate types must be an object".to_string(),
+            })
+        }
+        None => DataValue::Object(&[]),
+    };
+
+    let mut args = vec![
+        arena.alloc(Token::literal(required)),
+        arena.alloc(Token::literal(types)),
+    ];
+
+    let checks = match spec.get("custom") {
+        Some(JsonValue::Array(checks)) => checks.as_slice(),
+        Some(_) => {
+            return Err(LogicError::ParseError {
+                reason: "validate custom must be an array of checks".to_string(),
+            })
+        }
+        None => &[],
+    };
+
+    for check in checks {
+        let Some(rule) = check.get("rule") else {
+            return Err(LogicError::ParseError {
+                reason: "validate custom checks require a rule".to_string(),
+            });
+        };
+
+        let mut details = check.as_object().cloned().unwrap_or_default();
+        details.remove("rule");
+
+        let rule = arena.alloc(parse_json_internal(rule, config, arena)?);
+        let details = arena.alloc(Token::literal(DataValue::from_json(
+            &JsonValue::Object(details),
+            arena,
+        )));
+        args.push(arena.alloc(Token::ArrayLiteral(vec![rule, details])));
+    }
+
+    Ok(Token::operator(
+        OperatorType::Validate,
+        arena.alloc(Token::ArrayLiteral(args)),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -439,6 +926,21 @@ mod tests {
         assert_eq!(array[0].as_i64(), Some(1));
         assert_eq!(array[1].as_i64(), Some(2));
         assert_eq!(array[2].as_i64(), Some(3));
+
+        // Repeated string constants share their storage
+        let token = parse_json(
+            &json!({"if": [{"var": "x"}, ["approved", "pending"], "approved"]}),
+            &arena,
+        )
+        .unwrap();
+        let (_, args) = token.as_operator().unwrap();
+        let args = args.as_array_literal().unwrap();
+        let listed = args[1].as_literal().unwrap().as_array().unwrap()[0]
+            .as_str()
+            .unwrap();
+        let branch = args[2].as_literal().unwrap().as_str().unwrap();
+        assert_eq!(branch, "approved");
+        assert!(std::ptr::eq(listed, branch));
     }
 
     #[test]
@@ -528,7 +1030,7 @@ mod tests {
     #[test]
     fn test_parser_interface() {
         let arena = DataArena::new();
-        let parser = JsonLogicParser;
+        let parser = JsonLogicParser::default();
 
         // Test the parser interface
         let json_str = r#"{"==": [{"var": "a"}, 42]}"#;
@@ -602,4 +1104,128 @@ mod tests {
         let (op_type, _args) = token.as_operator().unwrap();
         assert_eq!(op_type, OperatorType::Val);
     }
+
+    #[test]
+    fn test_parse_validate_operator() {
+        let arena = DataArena::new();
+
+        let rule = json!({"validate": {
+            "required": ["name"],
+            "custom": [{"rule": {"var": "ok"}, "code": "not_ok"}]
+        }});
+        let token = parse_json(&rule, &arena).unwrap();
+        let (op_type, args) = token.as_operator().unwrap();
+        assert_eq!(op_type, OperatorType::Validate);
+        // required, types and one custom check
+        assert_eq!(args.as_array_literal().unwrap().len(), 3);
+
+        assert!(parse_json(&json!({"validate": ["name"]}), &arena).is_err());
+        assert!(parse_json(&json!({"validate": {"requires": ["name"]}}), &arena).is_err());
+        assert!(parse_json(&json!({"validate": {"custom": [{"code": "x"}]}}), &arena).is_err());
+    }
+
+    #[test]
+    fn test_parse_object_construction() {
+        let arena = DataArena::new();
+        let rule = json!({"name": {"var": "first"}, "tags": ["a", "b"]});
+
+        // Multi-key objects are unknown operators by default
+        assert_eq!(
+            parse_json(&rule, &arena).unwrap_err(),
+            LogicError::OperatorNotFoundError {
+                operator: "name".to_string()
+            }
+        );
+
+        let config = ParserConfig {
+            object_construction: true,
+            ..ParserConfig::default()
+        };
+        let token = parse_json_with_config(&rule, config, &arena).unwrap();
+        let (op_type, args) = token.as_operator().unwrap();
+        assert_eq!(op_type, OperatorType::ObjectLiteral);
+        // The keys followed by one token per value
+        assert_eq!(args.as_array_literal().unwrap().len(), 3);
+
+        // Objects of literals are literal objects, and nested objects are
+        // constructed as well
+        let rule = json!({"a": 1, "b": {"c": "x", "d": [true]}});
+        let token = parse_json_with_config(&rule, config, &arena).unwrap();
+        assert!(token.is_operator());
+        let token = parse_json_with_config(&rule["b"], config, &arena).unwrap();
+        assert!(token.as_literal().unwrap().is_object());
+
+        // Single-key objects are still operators
+        let token = parse_json_with_config(&json!({"var": "a"}), config, &arena).unwrap();
+        assert!(token.is_variable());
+    }
+
+    #[test]
+    fn test_parse_obj_operator() {
+        let arena = DataArena::new();
+
+        // Computed values are lowered to an object construction
+        let rule = json!({"obj": [["name", {"var": "first"}], ["tags", ["a", "b"]]]});
+        let token = parse_json(&rule, &arena).unwrap();
+        let (op_type, args) = token.as_operator().unwrap();
+        assert_eq!(op_type, OperatorType::ObjectLiteral);
+        assert_eq!(args.as_array_literal().unwrap().len(), 3);
+
+        // Pairs of literals are literal objects
+        let token = parse_json(&json!({"obj": [["a", 1]]}), &arena).unwrap();
+        assert!(token.as_literal().unwrap().is_object());
+
+        for rule in [
+            json!({"obj": {"a": 1}}),
+            json!({"obj": [["a"]]}),
+            json!({"obj": [[1, 2]]}),
+            json!({"obj": [[{"var": "k"}, 2]]}),
+            json!({"obj": [["a", 1], ["a", 2]]}),
+        ] {
+            assert!(
+                matches!(
+                    parse_json(&rule, &arena),
+                    Err(LogicError::ParseError { .. })
+                ),
+                "{rule}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_rule_list() {
+        let arena = DataArena::new();
+        let rules = json!([{"var": "a"}, true]);
+
+        // Arrays are arrays by default
+        let token = parse_json(&rules, &arena).unwrap();
+        assert!(token.is_array_literal());
+
+        for (rule_list, expected) in [
+            (RuleList::All, ControlOp::And),
+            (RuleList::Any, ControlOp::Or),
+        ] {
+            let config = ParserConfig {
+                rule_list,
+                ..ParserConfig::default()
+            };
+            let token = parse_json_with_config(&rules, config, &arena).unwrap();
+            let (op_type, args) = token.as_operator().unwrap();
+            assert_eq!(op_type, OperatorType::Control(expected));
+            assert_eq!(args.as_array_literal().unwrap().len(), 2);
+
+            // Only the top-level array is combined
+            let nested = json!({"map": [{"var": "xs"}, [{"var": ""}, 1]]});
+            let token = parse_json_with_config(&nested, config, &arena).unwrap();
+            let (_, args) = token.as_operator().unwrap();
+            assert!(args.as_array_literal().unwrap()[1].is_array_literal());
+
+            // An empty list passes if all rules must pass
+            let token = parse_json_with_config(&json!([]), config, &arena).unwrap();
+            assert_eq!(
+                token.as_literal(),
+                Some(&DataValue::Bool(rule_list == RuleList::All))
+            );
+        }
+    }
 }
@@ -3,7 +3,9 @@ use crate::logic::{LogicError, Result, Token};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 
+pub mod json;
 pub mod jsonlogic;
+pub(crate) mod recovery;
 #[cfg(test)]
 mod tests;
 
@@ -40,7 +42,7 @@ impl ParserRegistry {
         };
 
         // Register the default JSONLogic parser
-        registry.register(Box::new(jsonlogic::JsonLogicParser));
+        registry.register(Box::new(jsonlogic::JsonLogicParser::default()));
 
         registry
     }
@@ -63,6 +65,11 @@ impl ParserRegistry {
         }
     }
 
+    /// Get the name of the format used when none is given
+    pub fn default_format(&self) -> &str {
+        &self.default_parser
+    }
+
     /// Parse an expression using the specified parser, or default if none specified
     pub fn parse<'a>(
         &self,
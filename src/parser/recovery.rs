@@ -0,0 +1,190 @@
+//! Error recovery for JSONLogic parsing
+//!
+//! The parser stops at the first error of a rule. A recovering parse instead
+//! replaces each sub-expression that fails to parse with null and carries on,
+//! so that every problem of a large rule can be reported in one pass.
+
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::arena::DataArena;
+use crate::logic::DetailedError;
+use crate::parser::jsonlogic::{has_rule_arguments, parse_nested_rule, ParserConfig};
+
+/// Collects the errors of every sub-expression of a rule that fails to parse
+///
+/// Errors are ordered as their sub-expressions appear in the rule, with the
+/// paths of [`DetailedError::path`]. An operator is only checked once its
+/// arguments parse, so that no error is a consequence of another.
+pub(crate) fn parse_errors(
+    json: &JsonValue,
+    config: ParserConfig,
+    arena: &DataArena,
+) -> Vec<DetailedError> {
+    let mut errors = Vec::new();
+    recover(json, config, arena, &mut String::new(), &mut errors);
+    errors
+}
+
+/// Returns the rule with its failing sub-expressions replaced with null,
+/// recording their errors.
+fn recover(
+    json: &JsonValue,
+    config: ParserConfig,
+    arena: &DataArena,
+    path: &mut String,
+    errors: &mut Vec<DetailedError>,
+) -> JsonValue {
+    let obj = match json {
+        JsonValue::Object(obj) => obj,
+        JsonValue::Array(items) => {
+            let items = items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    recover_at(item, &[&index.to_string()], config, arena, path, errors)
+                })
+                .collect();
+            return JsonValue::Array(items);
+        }
+        _ => return json.clone(),
+    };
+
+    let failures = errors.len();
+    let (operator, repaired) = match obj.iter().next() {
+        Some((key, args)) if obj.len() == 1 => {
+            let args = if !has_rule_arguments(key, arena) {
+                args.clone()
+            } else if let JsonValue::Array(items) = args {
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        recover_at(
+                            item,
+                            &[key, &index.to_string()],
+                            config,
+                            arena,
+                            path,
+                            errors,
+                        )
+                    })
+                    .collect();
+                JsonValue::Array(items)
+            } else {
+                recover_at(args, &[key, "0"], config, arena, path, errors)
+            };
+            let mut repaired = JsonMap::new();
+            repaired.insert(key.clone(), args);
+            (Some(key.clone()), repaired)
+        }
+        _ => {
+            let repaired = obj
+                .iter()
+                .map(|(key, value)| {
+                    let value = recover_at(value, &[key], config, arena, path, errors);
+                    (key.clone(), value)
+                })
+                .collect();
+            (None, repaired)
+        }
+    };
+    let repaired = JsonValue::Object(repaired);
+    if errors.len() > failures {
+        return repaired;
+    }
+
+    match parse_nested_rule(&repaired, config, arena) {
+        Ok(_) => repaired,
+        Err(error) => {
+            errors.push(DetailedError {
+                error,
+                path: path.clone(),
+                operator,
+                arguments: Vec::new(),
+            });
+            JsonValue::Null
+        }
+    }
+}
+
+/// Recovers a sub-expression at the path extended by the given steps.
+fn recover_at(
+    json: &JsonValue,
+    steps: &[&str],
+    config: ParserConfig,
+    arena: &DataArena,
+    path: &mut String,
+    errors: &mut Vec<DetailedError>,
+) -> JsonValue {
+    let len = path.len();
+    for step in steps {
+        path.push('/');
+        path.push_str(step);
+    }
+    let repaired = recover(json, config, arena, path, errors);
+    path.truncate(len);
+    repaired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogicError;
+    use serde_json::json;
+
+    fn paths(rule: JsonValue) -> Vec<(String, Option<String>)> {
+        let arena = DataArena::new();
+        parse_errors(&rule, ParserConfig::default(), &arena)
+            .into_iter()
+            .map(|error| (error.path, error.operator))
+            .collect()
+    }
+
+    #[test]
+    fn test_collects_all_errors() {
+        let errors = paths(json!({
+            "if": [
+                {"var": true},
+                {"obj": "not pairs"},
+                {"+": [1, {"validate": 1}, {"var": "x"}]}
+            ]
+        }));
+        assert_eq!(
+            errors,
+            [
+                ("/if/0".to_string(), Some("var".to_string())),
+                ("/if/1".to_string(), Some("obj".to_string())),
+                ("/if/2/+/1".to_string(), Some("validate".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_cascading_errors() {
+        // The object with several keys is invalid itself, but only the error
+        // of its value is reported
+        let errors = paths(json!({"not": {"a": {"var": true}, "b": 1}}));
+        assert_eq!(errors, [("/not/0/a".to_string(), Some("var".to_string()))]);
+
+        let errors = paths(json!({"not": {"a": 1, "b": 2}}));
+        assert_eq!(errors, [("/not/0".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_valid_rule_and_arrays() {
+        assert!(paths(json!({"and": [true, {"custom_op": [{"var": "a"}]}]})).is_empty());
+
+        let arena = DataArena::new();
+        let errors = parse_errors(
+            &json!([{"var": "a"}, [1, {"x": 1, "y": 2}]]),
+            ParserConfig::default(),
+            &arena,
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/1/1");
+        assert!(matches!(
+            errors[0].error,
+            LogicError::OperatorNotFoundError { .. }
+        ));
+    }
+}
@@ -3,6 +3,7 @@
 //! This module contains tests for the parser registry and the included parsers.
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use crate::arena::DataArena;
     use crate::logic::{ComparisonOp, OperatorType, Token};
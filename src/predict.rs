@@ -0,0 +1,289 @@
+//! Model inference operator
+//!
+//! This module provides the `predict` operator, available with the `predict`
+//! feature. It evaluates a registered model on a feature vector assembled by
+//! the rule, so risk rules can blend model scores with hard constraints:
+//!
+//! ```json
+//! {"and": [
+//!     {">=": [{"var": "age"}, 18]},
+//!     {"!": {"predict": ["fraud", [{"var": "amount"}, {"var": "tx_24h"}], {"threshold": 0.8}]}}
+//! ]}
+//! ```
+//!
+//! Features are either an array in model input order, usually built from
+//! expressions as above, or an object such as `{"var": "applicant"}` whose
+//! fields are picked by the feature names of the model.
+//!
+//! Models are backend-agnostic: anything implementing [`PredictionModel`] can
+//! be registered, such as a wrapper around an ONNX session. A
+//! [`LinearModel`] is included for linear and logistic scoring.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::arena::{CustomOperator, DataArena};
+use crate::logic::{LogicError, Result};
+use crate::value::DataValue;
+
+/// A model that can be evaluated by the `predict` operator
+pub trait PredictionModel: fmt::Debug + Send + Sync {
+    /// Evaluate the model on a feature vector, returning its outputs
+    fn predict(&self, features: &[f64]) -> std::result::Result<Vec<f64>, String>;
+
+    /// Names of the features in input order
+    ///
+    /// Models with feature names accept features as an object value keyed by
+    /// name in addition to a plain array.
+    fn feature_names(&self) -> Option<&[String]> {
+        None
+    }
+}
+
+/// A linear model with an optional logistic link
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::predict::{LinearModel, PredictionModel};
+///
+/// let model = LinearModel::new(vec![0.5, -1.0], 0.25);
+/// assert_eq!(model.predict(&[2.0, 1.0]).unwrap(), vec![0.25]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearModel {
+    weights: Vec<f64>,
+    bias: f64,
+    logistic: bool,
+    feature_names: Option<Vec<String>>,
+}
+
+impl LinearModel {
+    /// Create a model computing `bias + sum(weights[i] * features[i])`
+    pub fn new(weights: Vec<f64>, bias: f64) -> Self {
+        Self {
+            weights,
+            bias,
+            logistic: false,
+            feature_names: None,
+        }
+    }
+
+    /// Apply the logistic function to the output, turning it into a probability
+    pub fn logistic(mut self) -> Self {
+        self.logistic = true;
+        self
+    }
+
+    /// Name the features, in the order of the weights
+    pub fn with_feature_names(mut self, names: &[&str]) -> Self {
+        self.feature_names = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+}
+
+impl PredictionModel for LinearModel {
+    fn predict(&self, features: &[f64]) -> std::result::Result<Vec<f64>, String> {
+        if features.len() != self.weights.len() {
+            return Err(format!(
+                "expected {} features, got {}",
+                self.weights.len(),
+                features.len()
+            ));
+        }
+
+        let linear = self.bias
+            + self
+                .weights
+                .iter()
+                .zip(features)
+                .map(|(weight, feature)| weight * feature)
+                .sum::<f64>();
+        let output = if self.logistic {
+            1.0 / (1.0 + (-linear).exp())
+        } else {
+            linear
+        };
+        Ok(vec![output])
+    }
+
+    fn feature_names(&self) -> Option<&[String]> {
+        self.feature_names.as_deref()
+    }
+}
+
+/// Keys accepted in the trailing options object of the `predict` operator
+pub(crate) const PREDICT_OPTION_KEYS: &[&str] = &["output", "threshold"];
+
+/// Models shared between a DataLogic instance and its `predict` operator
+pub(crate) type ModelRegistry = Arc<RwLock<HashMap<String, Arc<dyn PredictionModel>>>>;
+
+/// The `predict` custom operator
+#[derive(Debug)]
+pub(crate) struct PredictOperator {
+    pub(crate) models: ModelRegistry,
+}
+
+/// Assembles the feature vector of a model from an array or an object.
+fn assemble_features(model: &dyn PredictionModel, features: &DataValue) -> Result<Vec<f64>> {
+    let number = |value: &DataValue| {
+        value
+            .coerce_to_number()
+            .map(|n| n.as_f64())
+            .filter(|n| n.is_finite())
+            .ok_or(LogicError::NaNError)
+    };
+
+    match features {
+        DataValue::Array(items) => items.iter().map(number).collect(),
+        DataValue::Object(entries) => {
+            let names = model
+                .feature_names()
+                .ok_or(LogicError::InvalidArgumentsError)?;
+            names
+                .iter()
+                .map(|name| {
+                    let value = entries
+                        .iter()
+                        .find(|(key, _)| *key == name.as_str())
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| {
+                            LogicError::Custom(format!("Missing model feature: {}", name))
+                        })?;
+                    number(value)
+                })
+                .collect()
+        }
+        value => Ok(vec![number(value)?]),
+    }
+}
+
+/// Looks up an option of the predict operator.
+fn option<'v>(options: Option<&'v DataValue<'v>>, key: &str) -> Option<&'v DataValue<'v>> {
+    match options? {
+        DataValue::Object(entries) => entries
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+impl CustomOperator for PredictOperator {
+    /// Evaluates `{"predict": [model, features, options?]}`.
+    ///
+    /// Returns the single output as a number, or all outputs as an array.
+    /// The `output` option selects an output by index, and the `threshold`
+    /// option turns the selected output into a boolean decision.
+    fn evaluate<'a>(
+        &self,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        if !(2..=3).contains(&args.len()) {
+            return Err(LogicError::InvalidArgumentsError);
+        }
+        let name = args[0].as_str().ok_or(LogicError::InvalidArgumentsError)?;
+        let options = args.get(2);
+
+        let model = self
+            .models
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| LogicError::Custom(format!("Unknown model: {}", name)))?;
+
+        let features = assemble_features(model.as_ref(), &args[1])?;
+        let outputs = model
+            .predict(&features)
+            .map_err(|reason| LogicError::Custom(format!("Model {} failed: {}", name, reason)))?;
+
+        let selected = match option(options, "output") {
+            Some(index) => {
+                let index = index.as_i64().ok_or(LogicError::InvalidArgumentsError)?;
+                let output = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| outputs.get(index))
+                    .ok_or(LogicError::InvalidArgumentsError)?;
+                Some(*output)
+            }
+            None if outputs.len() == 1 => Some(outputs[0]),
+            None => None,
+        };
+
+        if let Some(threshold) = option(options, "threshold") {
+            let threshold = threshold
+                .as_f64()
+                .ok_or(LogicError::InvalidArgumentsError)?;
+            let score = selected.ok_or(LogicError::InvalidArgumentsError)?;
+            return Ok(arena.alloc(DataValue::Bool(score >= threshold)));
+        }
+
+        match selected {
+            Some(score) => Ok(arena.alloc(DataValue::float(score))),
+            None => {
+                let values: Vec<DataValue> = outputs.into_iter().map(DataValue::float).collect();
+                Ok(arena.alloc(DataValue::Array(arena.vec_into_slice(values))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataLogic;
+    use serde_json::json;
+
+    /// A model with two outputs, returning the features in reverse order.
+    #[derive(Debug)]
+    struct Reverse;
+
+    impl PredictionModel for Reverse {
+        fn predict(&self, features: &[f64]) -> std::result::Result<Vec<f64>, String> {
+            Ok(features.iter().rev().copied().collect())
+        }
+    }
+
+    #[test]
+    fn test_predict_operator() {
+        let mut dl = DataLogic::new();
+        let fraud = LinearModel::new(vec![1.0, 1.0], 0.0)
+            .logistic()
+            .with_feature_names(&["a", "b"]);
+        dl.register_model("fraud", Box::new(fraud));
+        dl.register_model("reverse", Box::new(Reverse));
+
+        let data = json!({"x": 3, "applicant": {"b": 1, "a": 3}, "partial": {"a": 1}});
+        let eval = |rule: serde_json::Value| dl.evaluate_json(&rule, &data, None);
+
+        assert_eq!(
+            eval(json!({"predict": ["fraud", [0, 0]]})).unwrap(),
+            json!(0.5)
+        );
+        assert_eq!(
+            eval(json!({"predict": ["fraud", [{"var": "x"}, 1], {"threshold": 0.9}]})).unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            eval(json!({"predict": ["reverse", [1, 2]]})).unwrap(),
+            json!([2, 1])
+        );
+        assert_eq!(
+            eval(json!({"predict": ["reverse", [1, 2], {"output": 1, "threshold": 1}]})).unwrap(),
+            json!(true)
+        );
+
+        assert!(eval(json!({"predict": ["unknown", [1]]})).is_err());
+        assert_eq!(
+            eval(json!({"predict": ["fraud", {"var": "applicant"}, {"threshold": 0.99}]})).unwrap(),
+            json!(false)
+        );
+        assert!(eval(json!({"predict": ["fraud", {"var": "partial"}]})).is_err());
+        assert!(eval(json!({"predict": ["fraud", [1]]})).is_err());
+        assert!(eval(json!({"predict": ["fraud", ["abc", 1]]})).is_err());
+        assert!(eval(json!({"predict": ["reverse", {"var": "applicant"}]})).is_err());
+    }
+}
@@ -0,0 +1,499 @@
+//! Named rule storage and warm-up
+//!
+//! This module provides the RuleRegistry, which keeps named rule sources and
+//! can compile all of them up front so that broken rules are detected at
+//! startup instead of on the first request that uses them. Compiled rules
+//! are cached and recompiled once a registration change makes them stale.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::compiled::CompiledRule;
+use crate::datalogic::DataLogic;
+use crate::logic::{Logic, Result};
+use crate::output::OutputType;
+use crate::LogicError;
+use serde_json::Value as JsonValue;
+
+/// Progress of a [`RuleRegistry::precompile_all`] run, reported once per rule.
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompileProgress<'r> {
+    /// Name of the rule that was just compiled.
+    pub name: &'r str,
+    /// Number of rules compiled so far, including this one.
+    pub completed: usize,
+    /// Total number of rules being compiled.
+    pub total: usize,
+    /// The compilation error, if the rule failed to compile.
+    pub error: Option<&'r LogicError>,
+}
+
+/// A rule that failed to compile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleFailure {
+    /// Name of the rule.
+    pub name: String,
+    /// The compilation error.
+    pub error: LogicError,
+}
+
+/// A rule whose branches can return a value of another type than declared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleWarning {
+    /// Name of the rule.
+    pub name: String,
+    /// The warning, naming the offending branch.
+    pub message: String,
+}
+
+/// Aggregate statistics of a [`RuleRegistry::precompile_all`] run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecompileReport {
+    /// Number of rules that were compiled.
+    pub total: usize,
+    /// Number of rules that compiled successfully.
+    pub succeeded: usize,
+    /// Rules that failed to compile, in name order.
+    pub failures: Vec<RuleFailure>,
+    /// Branches of rules that conflict with the declared output type, in
+    /// name order.
+    pub warnings: Vec<RuleWarning>,
+    /// Wall-clock time spent compiling.
+    pub elapsed: Duration,
+}
+
+impl PrecompileReport {
+    /// Returns true if every rule compiled successfully.
+    pub fn is_healthy(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A collection of named rules
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::RuleRegistry;
+/// use serde_json::json;
+///
+/// let mut registry = RuleRegistry::new();
+/// registry.register("adult", json!({">=": [{"var": "age"}, 18]}));
+/// registry.register("broken", json!({"if": [true, 1], "extra": 2}));
+///
+/// let report = registry.precompile_all(false, |_| {});
+/// assert_eq!(report.succeeded, 1);
+/// assert_eq!(report.failures[0].name, "broken");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RuleRegistry {
+    rules: BTreeMap<String, JsonValue>,
+    format: Option<String>,
+    compiled: BTreeMap<String, Arc<CompiledRule>>,
+    output_types: BTreeMap<String, OutputType>,
+}
+
+impl RuleRegistry {
+    /// Create an empty registry using the default parser format
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty registry whose rules use the given parser format
+    pub fn with_format(format: &str) -> Self {
+        Self {
+            format: Some(format.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Register a rule under a name, returning the rule it replaces
+    pub fn register(&mut self, name: &str, rule: JsonValue) -> Option<JsonValue> {
+        self.compiled.remove(name);
+        self.output_types.remove(name);
+        self.rules.insert(name.to_string(), rule)
+    }
+
+    /// Register a rule with a declared output type, returning the rule it replaces
+    ///
+    /// Rules compiled from the registry coerce their results to the type, and
+    /// [`precompile_all`](Self::precompile_all) warns about branches that can
+    /// return another type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::output::OutputType;
+    /// use datalogic_rs::{DataLogic, RuleRegistry};
+    /// use serde_json::json;
+    ///
+    /// let mut registry = RuleRegistry::new();
+    /// registry.register_typed(
+    ///     "discount",
+    ///     json!({"if": [{"var": "vip"}, {"var": "rate"}, "none"]}),
+    ///     OutputType::Number,
+    /// );
+    ///
+    /// let report = registry.precompile_all(false, |_| {});
+    /// assert_eq!(report.warnings[0].message, "/if/2 can return \"none\", which is not number");
+    ///
+    /// let dl = DataLogic::new();
+    /// let rule = registry.compile(&dl, "discount").unwrap();
+    /// let data = dl.parse_data(r#"{"vip": true, "rate": "0.1"}"#).unwrap();
+    /// assert_eq!(dl.evaluate(&rule, &data).unwrap().as_f64(), Some(0.1));
+    /// ```
+    pub fn register_typed(
+        &mut self,
+        name: &str,
+        rule: JsonValue,
+        output_type: OutputType,
+    ) -> Option<JsonValue> {
+        let previous = self.register(name, rule);
+        self.output_types.insert(name.to_string(), output_type);
+        previous
+    }
+
+    /// Remove a rule, returning its source
+    pub fn remove(&mut self, name: &str) -> Option<JsonValue> {
+        self.compiled.remove(name);
+        self.output_types.remove(name);
+        self.rules.remove(name)
+    }
+
+    /// Get the declared output type of a rule
+    pub fn output_type(&self, name: &str) -> Option<&OutputType> {
+        self.output_types.get(name)
+    }
+
+    /// Get the source of a rule
+    pub fn get(&self, name: &str) -> Option<&JsonValue> {
+        self.rules.get(name)
+    }
+
+    /// Iterate over the rule names in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.keys().map(String::as_str)
+    }
+
+    /// Number of registered rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check if the registry is empty
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Compile a rule into the arena of a DataLogic instance
+    pub fn compile<'a>(&self, dl: &'a DataLogic, name: &str) -> Result<Logic<'a>> {
+        let rule = self
+            .rules
+            .get(name)
+            .ok_or_else(|| LogicError::Custom(format!("Rule not found: {}", name)))?;
+        let logic = dl.parse_logic(&rule.to_string(), self.format.as_deref())?;
+        Ok(match self.output_types.get(name) {
+            Some(output_type) => logic.with_output_type(output_type.clone()),
+            None => logic,
+        })
+    }
+
+    /// Get the cached compiled form of a rule
+    ///
+    /// The rule is compiled on first use and recompiled whenever a
+    /// registration change on `dl` affects it, such as replacing a custom
+    /// operator it uses. The cache assumes rules are always compiled with the
+    /// same DataLogic instance. The name of the rule is its
+    /// [id](CompiledRule::with_id).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datalogic_rs::{DataLogic, DataValue, RuleRegistry};
+    /// use serde_json::json;
+    ///
+    /// let mut dl = DataLogic::new();
+    /// dl.register_simple_operator("rate", |_, _| Ok(DataValue::integer(2)));
+    ///
+    /// let mut registry = RuleRegistry::new();
+    /// registry.register("fee", json!({"*": [{"var": "x"}, {"rate": []}]}));
+    /// let first = registry.compiled(&dl, "fee").unwrap();
+    /// assert!(std::sync::Arc::ptr_eq(&first, &registry.compiled(&dl, "fee").unwrap()));
+    ///
+    /// dl.register_simple_operator("rate", |_, _| Ok(DataValue::integer(3)));
+    /// let second = registry.compiled(&dl, "fee").unwrap();
+    /// assert!(!std::sync::Arc::ptr_eq(&first, &second));
+    /// ```
+    pub fn compiled(&mut self, dl: &DataLogic, name: &str) -> Result<Arc<CompiledRule>> {
+        if let Some(rule) = self.compiled.get(name) {
+            if !dl.is_stale(rule) {
+                return Ok(Arc::clone(rule));
+            }
+        }
+
+        let source = self
+            .rules
+            .get(name)
+            .ok_or_else(|| LogicError::Custom(format!("Rule not found: {}", name)))?;
+        let mut rule = dl
+            .compile(&source.to_string(), self.format.as_deref())?
+            .with_id(name);
+        if let Some(output_type) = self.output_types.get(name) {
+            rule = rule.with_output_type(output_type.clone());
+        }
+        let rule = Arc::new(rule);
+        self.compiled.insert(name.to_string(), Arc::clone(&rule));
+        Ok(rule)
+    }
+
+    /// Compile every registered rule up front
+    ///
+    /// Failures are collected per rule without aborting the batch. When
+    /// `parallel` is true, rules are compiled on one thread per available
+    /// core, each with its own arena. The `progress` callback is invoked once
+    /// per rule, possibly from several threads.
+    pub fn precompile_all<F>(&self, parallel: bool, progress: F) -> PrecompileReport
+    where
+        F: Fn(PrecompileProgress<'_>) + Sync,
+    {
+        let start = Instant::now();
+        let rules: Vec<(&str, &JsonValue)> = self
+            .rules
+            .iter()
+            .map(|(name, rule)| (name.as_str(), rule))
+            .collect();
+        let total = rules.len();
+        let completed = AtomicUsize::new(0);
+
+        let compile_chunk = |chunk: &[(&str, &JsonValue)]| -> (Vec<RuleFailure>, Vec<RuleWarning>) {
+            let mut dl = DataLogic::new();
+            let mut failures = Vec::new();
+            let mut warnings = Vec::new();
+            for (name, rule) in chunk {
+                let error = match dl.parse_logic(&rule.to_string(), self.format.as_deref()) {
+                    Ok(logic) => {
+                        if let Some(output_type) = self.output_types.get(*name) {
+                            warnings.extend(output_type.check(logic.root()).into_iter().map(
+                                |message| RuleWarning {
+                                    name: name.to_string(),
+                                    message,
+                                },
+                            ));
+                        }
+                        None
+                    }
+                    Err(error) => Some(error),
+                };
+                dl.reset_arena();
+
+                progress(PrecompileProgress {
+                    name,
+                    completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    error: error.as_ref(),
+                });
+
+                if let Some(error) = error {
+                    failures.push(RuleFailure {
+                        name: name.to_string(),
+                        error,
+                    });
+                }
+            }
+            (failures, warnings)
+        };
+
+        let workers = if parallel {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+                .min(total)
+        } else {
+            1
+        };
+
+        let (mut failures, mut warnings) = if workers <= 1 {
+            compile_chunk(&rules)
+        } else {
+            let chunk_size = total.div_ceil(workers);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = rules
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(|| compile_chunk(chunk)))
+                    .collect();
+                let mut failures = Vec::new();
+                let mut warnings = Vec::new();
+                for handle in handles {
+                    let (chunk_failures, chunk_warnings) =
+                        handle.join().expect("rule compilation panicked");
+                    failures.extend(chunk_failures);
+                    warnings.extend(chunk_warnings);
+                }
+                (failures, warnings)
+            })
+        };
+        failures.sort_by(|a, b| a.name.cmp(&b.name));
+        // Stable, so the warnings of a rule stay in order
+        warnings.sort_by(|a, b| a.name.cmp(&b.name));
+
+        PrecompileReport {
+            total,
+            succeeded: total - failures.len(),
+            failures,
+            warnings,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataValue;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn sample_registry() -> RuleRegistry {
+        let mut registry = RuleRegistry::new();
+        for i in 0..20 {
+            registry.register(&format!("rule_{:02}", i), json!({"+": [{"var": "x"}, i]}));
+        }
+        registry.register("broken_a", json!({"==": [1, 1], "!=": [1, 2]}));
+        registry.register("broken_b", json!({"==": [1], "extra": true}));
+        registry
+    }
+
+    #[test]
+    fn test_precompile_all_sequential() {
+        let registry = sample_registry();
+        let seen = Mutex::new(Vec::new());
+
+        let report = registry.precompile_all(false, |p| {
+            seen.lock().unwrap().push((p.completed, p.error.is_some()));
+        });
+
+        assert_eq!(report.total, 22);
+        assert_eq!(report.succeeded, 20);
+        assert!(!report.is_healthy());
+        let names: Vec<_> = report.failures.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["broken_a", "broken_b"]);
+
+        let seen = seen.into_inner().unwrap();
+        assert_eq!(seen.len(), 22);
+        assert_eq!(seen.last().unwrap().0, 22);
+        assert_eq!(seen.iter().filter(|(_, failed)| *failed).count(), 2);
+    }
+
+    #[test]
+    fn test_precompile_all_parallel() {
+        let registry = sample_registry();
+        let calls = AtomicUsize::new(0);
+
+        let report = registry.precompile_all(true, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert_eq!(calls.load(Ordering::SeqCst), 22);
+        assert_eq!(report, {
+            let mut sequential = registry.precompile_all(false, |_| {});
+            sequential.elapsed = report.elapsed;
+            sequential
+        });
+    }
+
+    #[test]
+    fn test_compile_registered_rule() {
+        let mut registry = RuleRegistry::new();
+        registry.register("double", json!({"*": [{"var": "x"}, 2]}));
+
+        let dl = DataLogic::new();
+        let rule = registry.compile(&dl, "double").unwrap();
+        let data = dl.parse_data(r#"{"x": 21}"#).unwrap();
+        assert_eq!(dl.evaluate(&rule, &data).unwrap().as_i64(), Some(42));
+
+        // Cached rules are identified by their name
+        let compiled = registry.compiled(&dl, "double").unwrap();
+        assert_eq!(compiled.id(), Some("double"));
+        let data = dl.parse_data(r#"{"x": "two"}"#).unwrap();
+        let error = dl.evaluate_compiled(&compiled, &data).unwrap_err();
+        assert_eq!(error.rule_id(), Some("double"));
+
+        assert!(registry.compile(&dl, "missing").is_err());
+        assert!(registry.compiled(&dl, "missing").is_err());
+        assert!(RuleRegistry::new()
+            .precompile_all(true, |_| {})
+            .is_healthy());
+    }
+
+    #[test]
+    fn test_compiled_rules_follow_registration_changes() {
+        let mut registry = RuleRegistry::new();
+        registry.register("fee", json!({"*": [{"var": "x"}, {"rate": []}]}));
+        registry.register("plain", json!({"+": [{"var": "x"}, 1]}));
+
+        let mut dl = DataLogic::new();
+        dl.register_simple_operator("rate", |_, _| Ok(DataValue::integer(2)));
+        let fee = registry.compiled(&dl, "fee").unwrap();
+        let plain = registry.compiled(&dl, "plain").unwrap();
+        let data = dl.parse_data(r#"{"x": 10}"#).unwrap();
+        assert_eq!(
+            dl.evaluate_compiled(&fee, &data).unwrap().as_i64(),
+            Some(20)
+        );
+
+        // Unrelated operators leave rules up to date
+        dl.register_simple_operator("other", |_, _| Ok(DataValue::null()));
+        assert!(!dl.is_stale(&fee));
+        assert!(Arc::ptr_eq(&fee, &registry.compiled(&dl, "fee").unwrap()));
+
+        dl.register_simple_operator("rate", |_, _| Ok(DataValue::integer(3)));
+        assert!(dl.is_stale(&fee));
+        assert!(!dl.is_stale(&plain));
+        let fee = registry.compiled(&dl, "fee").unwrap();
+        assert!(!dl.is_stale(&fee));
+
+        // Replacing the source drops the cached rule
+        registry.register("plain", json!({"+": [{"var": "x"}, 2]}));
+        let replaced = registry.compiled(&dl, "plain").unwrap();
+        assert!(!Arc::ptr_eq(&plain, &replaced));
+
+        // Parser changes affect every rule
+        dl.set_default_parser("jsonlogic").unwrap();
+        assert!(dl.is_stale(&fee) && dl.is_stale(&replaced));
+    }
+
+    #[test]
+    fn test_typed_rules() {
+        let mut registry = RuleRegistry::new();
+        let tier = OutputType::Enum(vec!["gold".to_string(), "silver".to_string()]);
+        registry.register_typed(
+            "tier",
+            json!({"if": [{">": [{"var": "spend"}, 1000]}, "gold", {"var": "tier"}]}),
+            tier.clone(),
+        );
+        assert_eq!(registry.output_type("tier"), Some(&tier));
+        assert!(registry.precompile_all(true, |_| {}).warnings.is_empty());
+
+        let dl = DataLogic::new();
+        let rule = registry.compiled(&dl, "tier").unwrap();
+        let data = dl.parse_data(r#"{"spend": 5000}"#).unwrap();
+        assert_eq!(
+            dl.evaluate_compiled(&rule, &data).unwrap().as_str(),
+            Some("gold")
+        );
+        let data = dl.parse_data(r#"{"spend": 10, "tier": "bronze"}"#).unwrap();
+        let error = dl.evaluate_compiled(&rule, &data).unwrap_err();
+        assert!(matches!(
+            error.root_cause(),
+            LogicError::OutputTypeError { .. }
+        ));
+
+        // Registering the rule again drops the declaration
+        registry.register("tier", json!("bronze"));
+        assert_eq!(registry.output_type("tier"), None);
+        let rule = registry.compiled(&dl, "tier").unwrap();
+        assert!(rule.output_type().is_none());
+    }
+}
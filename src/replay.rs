@@ -0,0 +1,551 @@
+//! Evaluation capture and replay
+//!
+//! A replay bundle records everything an evaluation depended on: the rule,
+//! the part of the data it read, the parser format, the engine settings, the
+//! clock and the responses of custom operators. Bundles can be stored as JSON
+//! and re-executed later to reproduce a production decision exactly, without
+//! access to the services behind the custom operators.
+//!
+//! No built-in operator draws random numbers, so the clock and the custom
+//! operators are the only sources of nondeterminism a bundle has to pin.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::arena::{CustomOperator, DataArena, Settings};
+use crate::context::EvaluationContext;
+use crate::limits::Limits;
+use crate::logic::{LogicError, Result};
+use crate::parser::jsonlogic::{ParserConfig, RuleList};
+use crate::semantics::{IntegerOverflow, OutOfRangeIndex, Semantics};
+use crate::value::{DataValue, FromJson, ToJson};
+
+/// A recorded call of a custom operator
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedCall {
+    /// Name of the operator
+    pub operator: String,
+    /// Evaluated arguments of the call
+    pub args: JsonValue,
+    /// The value returned by the operator, or the error message
+    pub response: std::result::Result<JsonValue, String>,
+}
+
+/// The engine settings an evaluation ran under
+///
+/// Replaying a bundle applies these instead of the settings of the replaying
+/// engine, so that a decision made under, say, saturating integer overflow is
+/// reproduced by an engine left at the defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayConfig {
+    /// The edge-case behavior of the operators
+    pub semantics: Semantics,
+    /// What integer arithmetic did on overflow
+    pub integer_overflow: IntegerOverflow,
+    /// What variables read at out of range array indices
+    pub out_of_range_index: OutOfRangeIndex,
+    /// The evaluation limits
+    pub limits: Limits,
+    /// Whether objects with several keys constructed objects
+    pub object_construction: bool,
+    /// How a top-level array of rules was combined
+    pub rule_list: RuleList,
+    /// Whether duplicate keys were rejected in rule and data text
+    pub strict_json: bool,
+    /// The regional settings of the evaluation
+    pub context: Option<EvaluationContext>,
+}
+
+impl ReplayConfig {
+    /// Records the settings of an arena and a parser.
+    pub(crate) fn new(settings: &Settings, parser: ParserConfig) -> Self {
+        Self {
+            semantics: settings.semantics,
+            integer_overflow: settings.integer_overflow,
+            out_of_range_index: settings.out_of_range_index,
+            limits: settings.limits,
+            object_construction: parser.object_construction,
+            rule_list: parser.rule_list,
+            strict_json: parser.strict_json,
+            context: settings.evaluation_context.clone(),
+        }
+    }
+
+    /// Applies the recorded settings over the given ones.
+    pub(crate) fn apply(&self, settings: Settings) -> Settings {
+        Settings {
+            semantics: self.semantics,
+            integer_overflow: self.integer_overflow,
+            out_of_range_index: self.out_of_range_index,
+            limits: self.limits,
+            evaluation_context: self.context.clone(),
+            ..settings
+        }
+    }
+
+    /// Returns the recorded parser configuration.
+    pub(crate) fn parser_config(&self) -> ParserConfig {
+        ParserConfig {
+            object_construction: self.object_construction,
+            rule_list: self.rule_list,
+            strict_json: self.strict_json,
+        }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        let limits = &self.limits;
+        let context = self.context.as_ref().map(|context| {
+            #[cfg(feature = "tz")]
+            let named_timezone = context.named_timezone().map(|tz| tz.name().to_string());
+            #[cfg(not(feature = "tz"))]
+            let named_timezone: Option<String> = None;
+            json!({
+                "locale": context.locale(),
+                "timezone": context.timezone().map(|offset| offset.to_string()),
+                "named_timezone": named_timezone,
+            })
+        });
+
+        json!({
+            "semantics": self.semantics.as_str(),
+            "integer_overflow": match self.integer_overflow {
+                IntegerOverflow::Float => "float",
+                IntegerOverflow::Saturate => "saturate",
+                IntegerOverflow::Error => "error",
+            },
+            "out_of_range_index": match self.out_of_range_index {
+                OutOfRangeIndex::Default => "default",
+                OutOfRangeIndex::Null => "null",
+                OutOfRangeIndex::Error => "error",
+            },
+            "limits": {
+                "max_array_len": limits.max_array_len(),
+                "max_string_len": limits.max_string_len(),
+                "max_iterations": limits.max_iterations(),
+                "max_operations": limits.max_operations(),
+                "max_depth": limits.max_depth(),
+                "time_limit_ns": limits.time_limit().map(|limit| limit.as_nanos() as u64),
+            },
+            "object_construction": self.object_construction,
+            "rule_list": match self.rule_list {
+                RuleList::Collect => "collect",
+                RuleList::All => "all",
+                RuleList::Any => "any",
+            },
+            "strict_json": self.strict_json,
+            "context": context,
+        })
+    }
+
+    fn from_json(value: &JsonValue) -> Result<Self> {
+        let name = |field: &str| value.get(field).and_then(JsonValue::as_str);
+        let flag = |field: &str| value.get(field).and_then(JsonValue::as_bool);
+        let invalid = |field: &str| bundle_error(&format!("invalid config field '{}'", field));
+
+        let mut config = Self::default();
+        if let Some(semantics) = name("semantics") {
+            config.semantics = [
+                Semantics::JsonLogicEngine,
+                Semantics::JsonLogicJs,
+                Semantics::JsonLogicSpec,
+            ]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == semantics)
+            .ok_or_else(|| invalid("semantics"))?;
+        }
+        if let Some(policy) = name("integer_overflow") {
+            config.integer_overflow = match policy {
+                "float" => IntegerOverflow::Float,
+                "saturate" => IntegerOverflow::Saturate,
+                "error" => IntegerOverflow::Error,
+                _ => return Err(invalid("integer_overflow")),
+            };
+        }
+        if let Some(policy) = name("out_of_range_index") {
+            config.out_of_range_index = match policy {
+                "default" => OutOfRangeIndex::Default,
+                "null" => OutOfRangeIndex::Null,
+                "error" => OutOfRangeIndex::Error,
+                _ => return Err(invalid("out_of_range_index")),
+            };
+        }
+        if let Some(rule_list) = name("rule_list") {
+            config.rule_list = match rule_list {
+                "collect" => RuleList::Collect,
+                "all" => RuleList::All,
+                "any" => RuleList::Any,
+                _ => return Err(invalid("rule_list")),
+            };
+        }
+        config.object_construction = flag("object_construction").unwrap_or_default();
+        config.strict_json = flag("strict_json").unwrap_or_default();
+
+        if let Some(limits) = value.get("limits") {
+            let max = |field: &str| {
+                limits
+                    .get(field)
+                    .and_then(JsonValue::as_u64)
+                    .map(|max| max as usize)
+            };
+            let mut recorded = Limits::new();
+            if let Some(max) = max("max_array_len") {
+                recorded = recorded.with_max_array_len(max);
+            }
+            if let Some(max) = max("max_string_len") {
+                recorded = recorded.with_max_string_len(max);
+            }
+            if let Some(max) = max("max_iterations") {
+                recorded = recorded.with_max_iterations(max);
+            }
+            if let Some(max) = max("max_operations") {
+                recorded = recorded.with_max_operations(max);
+            }
+            if let Some(max) = max("max_depth") {
+                recorded = recorded.with_max_depth(max);
+            }
+            if let Some(nanos) = limits.get("time_limit_ns").and_then(JsonValue::as_u64) {
+                recorded = recorded.with_time_limit(Duration::from_nanos(nanos));
+            }
+            config.limits = recorded;
+        }
+
+        if let Some(context) = value.get("context").filter(|context| !context.is_null()) {
+            let field = |name: &str| context.get(name).and_then(JsonValue::as_str);
+            let mut recorded = EvaluationContext::new();
+            if let Some(locale) = field("locale") {
+                recorded = recorded.with_locale(locale);
+            }
+            if let Some(offset) = field("timezone") {
+                let offset: FixedOffset = offset.parse().map_err(|_| invalid("context"))?;
+                recorded = recorded.with_timezone(offset);
+            }
+            if let Some(name) = field("named_timezone") {
+                #[cfg(feature = "tz")]
+                {
+                    let tz: chrono_tz::Tz = name.parse().map_err(|_| invalid("context"))?;
+                    recorded = recorded.with_named_timezone(tz);
+                }
+                #[cfg(not(feature = "tz"))]
+                return Err(bundle_error(&format!(
+                    "time zone '{}' needs the tz feature",
+                    name
+                )));
+            }
+            config.context = Some(recorded);
+        }
+
+        Ok(config)
+    }
+}
+
+/// A self-contained record of an evaluation
+///
+/// Bundles are created by [`DataLogic::capture`](crate::DataLogic::capture)
+/// and re-executed by [`DataLogic::replay`](crate::DataLogic::replay).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayBundle {
+    /// The rule source
+    pub rule: JsonValue,
+    /// The data the rule was evaluated against
+    ///
+    /// When the rule only reads known paths, this holds just the top-level
+    /// fields it reads.
+    pub data: JsonValue,
+    /// The parser format, if not the default one
+    pub format: Option<String>,
+    /// The engine settings of the evaluation
+    pub config: ReplayConfig,
+    /// The time returned by `now` during the evaluation
+    pub clock: DateTime<Utc>,
+    /// Custom operator calls in evaluation order
+    pub calls: Vec<RecordedCall>,
+    /// The result of the evaluation, or the error message
+    pub outcome: std::result::Result<JsonValue, String>,
+}
+
+/// Converts a recorded result to JSON.
+fn outcome_to_json(outcome: &std::result::Result<JsonValue, String>) -> JsonValue {
+    match outcome {
+        Ok(value) => json!({ "ok": value }),
+        Err(message) => json!({ "error": message }),
+    }
+}
+
+/// Reads a recorded result from JSON.
+fn outcome_from_json(value: &JsonValue) -> Result<std::result::Result<JsonValue, String>> {
+    if let Some(value) = value.get("ok") {
+        return Ok(Ok(value.clone()));
+    }
+    match value.get("error").and_then(JsonValue::as_str) {
+        Some(message) => Ok(Err(message.to_string())),
+        None => Err(bundle_error("outcome must have an 'ok' or 'error' field")),
+    }
+}
+
+fn bundle_error(reason: &str) -> LogicError {
+    LogicError::ParseError {
+        reason: format!("Invalid replay bundle: {}", reason),
+    }
+}
+
+impl ReplayBundle {
+    /// Converts the bundle to JSON for storage
+    pub fn to_json(&self) -> JsonValue {
+        let calls: Vec<JsonValue> = self
+            .calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "operator": call.operator,
+                    "args": call.args,
+                    "response": outcome_to_json(&call.response),
+                })
+            })
+            .collect();
+
+        json!({
+            "rule": self.rule,
+            "data": self.data,
+            "format": self.format,
+            "config": self.config.to_json(),
+            "clock": self.clock.to_rfc3339(),
+            "calls": calls,
+            "outcome": outcome_to_json(&self.outcome),
+        })
+    }
+
+    /// Reads a bundle stored with [`ReplayBundle::to_json`]
+    ///
+    /// Bundles without a config, stored by earlier versions, replay under the
+    /// default settings.
+    pub fn from_json(value: &JsonValue) -> Result<Self> {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .ok_or_else(|| bundle_error(&format!("missing field '{}'", name)))
+        };
+
+        let clock = field("clock")?
+            .as_str()
+            .and_then(|clock| DateTime::parse_from_rfc3339(clock).ok())
+            .ok_or_else(|| bundle_error("clock must be an RFC 3339 timestamp"))?
+            .with_timezone(&Utc);
+
+        let calls = field("calls")?
+            .as_array()
+            .ok_or_else(|| bundle_error("calls must be an array"))?
+            .iter()
+            .map(|call| {
+                let operator = call
+                    .get("operator")
+                    .and_then(JsonValue::as_str)
+                    .ok_or_else(|| bundle_error("call without operator name"))?;
+                Ok(RecordedCall {
+                    operator: operator.to_string(),
+                    args: call.get("args").cloned().unwrap_or(JsonValue::Null),
+                    response: outcome_from_json(call.get("response").unwrap_or(&JsonValue::Null))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            rule: field("rule")?.clone(),
+            data: field("data")?.clone(),
+            format: value
+                .get("format")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string),
+            config: match value.get("config") {
+                Some(config) => ReplayConfig::from_json(config)?,
+                None => ReplayConfig::default(),
+            },
+            clock,
+            calls,
+            outcome: outcome_from_json(field("outcome")?)?,
+        })
+    }
+}
+
+/// Keeps the top-level fields of the data that the given paths read.
+///
+/// Returns the data unchanged if a path reads the whole data or the data is
+/// not an object.
+pub(crate) fn project_data<'p>(
+    data: &JsonValue,
+    paths: impl IntoIterator<Item = &'p str>,
+) -> JsonValue {
+    let JsonValue::Object(fields) = data else {
+        return data.clone();
+    };
+
+    let mut projected = Map::new();
+    for path in paths {
+        let key = path.split('.').next().unwrap_or(path);
+        if key.is_empty() {
+            return data.clone();
+        }
+        if let Some(value) = fields.get(key) {
+            projected.insert(key.to_string(), value.clone());
+        }
+    }
+    JsonValue::Object(projected)
+}
+
+/// An operator that returns a fixed time, standing in for `now`
+#[derive(Debug)]
+pub(crate) struct FixedClock(pub(crate) DateTime<Utc>);
+
+impl CustomOperator for FixedClock {
+    fn evaluate<'a>(
+        &self,
+        _args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        Ok(arena.alloc(DataValue::datetime(self.0)))
+    }
+}
+
+/// An operator that forwards calls to a registered operator and records them
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    pub(crate) name: String,
+    pub(crate) calls: Arc<Mutex<Vec<RecordedCall>>>,
+}
+
+impl CustomOperator for Recorder {
+    fn evaluate<'a>(
+        &self,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let result = arena.evaluate_registered_operator(&self.name, args);
+
+        let call = RecordedCall {
+            operator: self.name.clone(),
+            args: JsonValue::Array(args.iter().map(ToJson::to_json).collect()),
+            response: match &result {
+                Ok(value) => Ok(value.to_json()),
+                Err(error) => Err(error.to_string()),
+            },
+        };
+        self.calls.lock().unwrap().push(call);
+
+        result
+    }
+}
+
+/// An operator that answers calls from recorded responses
+///
+/// Calls are matched by their arguments, in recording order, so repeated calls
+/// with the same arguments return the responses in the order they were made.
+#[derive(Debug)]
+pub(crate) struct Playback {
+    pub(crate) name: String,
+    pub(crate) responses: Mutex<BTreeMap<String, Vec<std::result::Result<JsonValue, String>>>>,
+}
+
+impl Playback {
+    /// Creates a playback operator from the calls recorded for it.
+    pub(crate) fn new(name: &str, calls: &[RecordedCall]) -> Self {
+        let mut responses: BTreeMap<String, Vec<_>> = BTreeMap::new();
+        // Stored in reverse so that responses can be popped in order
+        for call in calls.iter().rev().filter(|call| call.operator == name) {
+            responses
+                .entry(call.args.to_string())
+                .or_default()
+                .push(call.response.clone());
+        }
+        Self {
+            name: name.to_string(),
+            responses: Mutex::new(responses),
+        }
+    }
+}
+
+impl CustomOperator for Playback {
+    fn evaluate<'a>(
+        &self,
+        args: &'a [DataValue<'a>],
+        arena: &'a DataArena,
+    ) -> Result<&'a DataValue<'a>> {
+        let args = JsonValue::Array(args.iter().map(ToJson::to_json).collect());
+        let response = self
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(&args.to_string())
+            .and_then(Vec::pop)
+            .ok_or_else(|| {
+                LogicError::Custom(format!(
+                    "No recorded response for {} with arguments {}",
+                    self.name, args
+                ))
+            })?;
+
+        match response {
+            Ok(value) => Ok(arena.alloc(DataValue::from_json(&value, arena))),
+            Err(message) => Err(LogicError::Custom(message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_data() {
+        let data = json!({"user": {"age": 30, "name": "Ann"}, "score": 5, "secret": "x"});
+        assert_eq!(
+            project_data(&data, ["user.age", "score", "unknown"]),
+            json!({"user": {"age": 30, "name": "Ann"}, "score": 5})
+        );
+        assert_eq!(project_data(&data, ["score", ""]), data);
+        assert_eq!(project_data(&json!([1, 2]), ["0"]), json!([1, 2]));
+    }
+
+    #[test]
+    fn test_bundle_json_roundtrip() {
+        let bundle = ReplayBundle {
+            rule: json!({"lookup": [1]}),
+            data: json!({}),
+            format: None,
+            config: ReplayConfig {
+                integer_overflow: IntegerOverflow::Error,
+                out_of_range_index: OutOfRangeIndex::Null,
+                limits: Limits::new()
+                    .with_max_depth(8)
+                    .with_time_limit(Duration::from_millis(5)),
+                rule_list: RuleList::All,
+                context: Some(
+                    EvaluationContext::new()
+                        .with_locale("de-CH")
+                        .with_timezone(FixedOffset::east_opt(2 * 3600).unwrap()),
+                ),
+                ..ReplayConfig::default()
+            },
+            clock: DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            calls: vec![RecordedCall {
+                operator: "lookup".to_string(),
+                args: json!([1]),
+                response: Err("timeout".to_string()),
+            }],
+            outcome: Err("timeout".to_string()),
+        };
+
+        assert_eq!(ReplayBundle::from_json(&bundle.to_json()).unwrap(), bundle);
+        assert!(ReplayBundle::from_json(&json!({"rule": 1})).is_err());
+
+        // Bundles stored without a config replay under the defaults
+        let mut stored = bundle.to_json();
+        stored.as_object_mut().unwrap().remove("config");
+        let read = ReplayBundle::from_json(&stored).unwrap();
+        assert_eq!(read.config, ReplayConfig::default());
+    }
+}
@@ -0,0 +1,150 @@
+//! Behavior profiles matching other JSONLogic implementations
+//!
+//! JSONLogic implementations disagree on edge cases that the specification
+//! leaves open, such as arithmetic on strings that are not numbers. By
+//! default the engine follows json-logic-engine and reports these cases as
+//! errors. Rules migrated from the json-logic-js reference implementation
+//! can rely on its JavaScript coercions instead, which a [`Semantics`]
+//! profile reproduces.
+
+use std::fmt;
+
+/// The edge-case behavior of the engine
+///
+/// Set with [`DataLogic::set_semantics`](crate::DataLogic::set_semantics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Semantics {
+    /// The behavior of json-logic-engine
+    ///
+    /// Arithmetic and comparisons on values that cannot be converted to
+    /// numbers fail with [`LogicError::NaNError`](crate::LogicError::NaNError),
+    /// and `in` tests the keys of objects.
+    #[default]
+    JsonLogicEngine,
+    /// The behavior of json-logic-js, the reference implementation
+    ///
+    /// - `+`, `-`, `*`, `/` and `%` return null where JavaScript produces
+    ///   `NaN` or an infinity, as those serialize to null in JSON.
+    /// - Comparisons that JavaScript evaluates against `NaN` are false.
+    /// - `null` is only loosely equal to `null`.
+    /// - `in` on anything but a string or an array is false.
+    JsonLogicJs,
+    /// Strict conformance with the specification of jsonlogic.com
+    ///
+    /// Every operator behaves as in json-logic-js, its reference
+    /// implementation:
+    ///
+    /// - Values are coerced as with [`Semantics::JsonLogicJs`].
+    /// - Extra arguments are ignored, so `{"-": [5, 1, 1]}` is 4.
+    /// - Missing arguments are `undefined`, so `{"==": [1]}` is false and
+    ///   `{"max": []}` is null.
+    /// - `cat` and `substr` convert values to strings as JavaScript does,
+    ///   joining the items of arrays with commas.
+    /// - Only the operators of the specification, listed in
+    ///   [`SPEC_OPERATORS`], are built in. The other built-in operators fail
+    ///   to parse as unknown operators, while custom operators are available.
+    ///
+    /// [`compliance_report`](crate::compliance::compliance_report) lists the
+    /// differences with the default profile.
+    JsonLogicSpec,
+}
+
+/// The operators defined by the specification of jsonlogic.com
+pub const SPEC_OPERATORS: &[&str] = &[
+    "var",
+    "missing",
+    "missing_some",
+    "if",
+    "?:",
+    "==",
+    "===",
+    "!=",
+    "!==",
+    "!",
+    "!!",
+    "or",
+    "and",
+    ">",
+    ">=",
+    "<",
+    "<=",
+    "max",
+    "min",
+    "+",
+    "-",
+    "*",
+    "/",
+    "%",
+    "map",
+    "reduce",
+    "filter",
+    "all",
+    "none",
+    "some",
+    "merge",
+    "in",
+    "cat",
+    "substr",
+    "log",
+];
+
+impl Semantics {
+    /// Returns the name of the profile.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Semantics::JsonLogicEngine => "json-logic-engine",
+            Semantics::JsonLogicJs => "json-logic-js",
+            Semantics::JsonLogicSpec => "jsonlogic-spec",
+        }
+    }
+
+    /// Check if values that cannot be numbers are tolerated as in JavaScript.
+    pub(crate) fn is_js(&self) -> bool {
+        matches!(self, Semantics::JsonLogicJs | Semantics::JsonLogicSpec)
+    }
+
+    /// Check if operators strictly follow the specification.
+    #[inline]
+    pub(crate) fn is_spec(&self) -> bool {
+        *self == Semantics::JsonLogicSpec
+    }
+}
+
+impl fmt::Display for Semantics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What reading an array index that is out of range in a variable path
+/// returns
+///
+/// Indices are out of range past the end of the array, or before its start
+/// for negative indices, which count from the end. Set with
+/// [`DataLogic::set_out_of_range_index`](crate::DataLogic::set_out_of_range_index).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OutOfRangeIndex {
+    /// The default value of the `var`, or null if it has none
+    #[default]
+    Default,
+    /// Null, even if the `var` has a default value
+    Null,
+    /// A [`LogicError::VariableError`](crate::LogicError::VariableError)
+    Error,
+}
+
+/// What integer arithmetic does when a result does not fit in 64 bits
+///
+/// Applies to `+`, `-` and `*` on integer operands, which are computed
+/// exactly. Set with
+/// [`DataLogic::set_integer_overflow`](crate::DataLogic::set_integer_overflow).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IntegerOverflow {
+    /// The operation is computed with floating point numbers instead
+    #[default]
+    Float,
+    /// The result is clamped to the smallest or largest integer
+    Saturate,
+    /// A [`LogicError::IntegerOverflow`](crate::LogicError::IntegerOverflow)
+    Error,
+}
@@ -0,0 +1,568 @@
+//! Boolean simplification of rules
+//!
+//! [`simplify`] rewrites a rule source into a smaller equivalent rule by
+//! applying the identities of boolean algebra that hold under JSONLogic
+//! truthiness: nested `and`/`or` are flattened, literal operands absorbed,
+//! double negations removed and branches guarded by literal conditions
+//! resolved. Every rewrite is reported, so that a simplified rule can be
+//! stored together with an account of how it was derived from its source.
+//!
+//! Rewrites only change the result of a rule where it is used as a
+//! condition, in which case only the truthiness of a value matters.
+//! Elsewhere, the simplified rule returns exactly the same value.
+
+use std::fmt;
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::lint::{as_operator, is_literal, is_truthy, operator};
+
+/// An identity applied by the simplifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rewrite {
+    /// An `and` or `or` nested in one of the same kind is merged into it
+    Flatten,
+    /// A literal operand of an `and` or `or` is dropped, or decides the result
+    ConstantOperand,
+    /// An operand of an `and` or `or` repeating an earlier operand is dropped
+    DuplicateOperand,
+    /// An `and` or `or` of a single operand is replaced by the operand
+    SingleOperand,
+    /// A negation of a negation is reduced to at most one negation
+    DoubleNegation,
+    /// A negated equality test is replaced by the opposite test
+    NegatedComparison,
+    /// A negation of a literal is replaced by its result
+    ConstantNegation,
+    /// A `!!` whose value is only used as a condition is dropped
+    RedundantCoercion,
+    /// The branches of an `if` guarded by literal conditions are resolved
+    ConstantCondition,
+    /// An `if` whose branches are all identical is replaced by the branch
+    DuplicateBranches,
+}
+
+impl Rewrite {
+    /// Returns the name of the rewrite, such as `"double-negation"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rewrite::Flatten => "flatten",
+            Rewrite::ConstantOperand => "constant-operand",
+            Rewrite::DuplicateOperand => "duplicate-operand",
+            Rewrite::SingleOperand => "single-operand",
+            Rewrite::DoubleNegation => "double-negation",
+            Rewrite::NegatedComparison => "negated-comparison",
+            Rewrite::ConstantNegation => "constant-negation",
+            Rewrite::RedundantCoercion => "redundant-coercion",
+            Rewrite::ConstantCondition => "constant-condition",
+            Rewrite::DuplicateBranches => "duplicate-branches",
+        }
+    }
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A rewrite applied to a sub-expression of a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transformation {
+    /// The identity that was applied
+    pub rewrite: Rewrite,
+    /// The path of the sub-expression in the source rule, empty for the root
+    pub path: String,
+    /// The sub-expression before the rewrite
+    pub before: JsonValue,
+    /// The sub-expression after the rewrite
+    pub after: JsonValue,
+}
+
+impl fmt::Display for Transformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(
+            f,
+            "{} at {}: {} => {}",
+            self.rewrite, path, self.before, self.after
+        )
+    }
+}
+
+/// The result of simplifying a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simplification {
+    /// The simplified rule
+    pub rule: JsonValue,
+    /// The rewrites applied, innermost sub-expressions first
+    pub transformations: Vec<Transformation>,
+}
+
+impl Simplification {
+    /// Checks if the rule was left unchanged
+    pub fn is_unchanged(&self) -> bool {
+        self.transformations.is_empty()
+    }
+}
+
+/// Simplifies a rule
+///
+/// Sub-expressions are simplified before the expressions containing them,
+/// and each expression is rewritten until no identity applies, so the result
+/// is a fixed point: simplifying it again changes nothing.
+///
+/// Operands are only dropped as duplicates, and `if` conditions as useless,
+/// when they are free of side effects, which is known for variables,
+/// literals and the logic, comparison and arithmetic operators. Custom
+/// operators are left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::simplify::{simplify, Rewrite};
+/// use serde_json::json;
+///
+/// let rule = json!({"if": [
+///     {"!": {"!": {"and": [true, {"var": "vip"}, {"and": [{"var": "active"}, {"var": "vip"}]}]}}},
+///     "gold",
+///     "standard"
+/// ]});
+///
+/// let simplified = simplify(&rule);
+/// assert_eq!(
+///     simplified.rule,
+///     json!({"if": [{"and": [{"var": "vip"}, {"var": "active"}]}, "gold", "standard"]})
+/// );
+///
+/// let rewrites: Vec<Rewrite> = simplified.transformations.iter().map(|t| t.rewrite).collect();
+/// assert_eq!(
+///     rewrites,
+///     [
+///         Rewrite::Flatten,
+///         Rewrite::ConstantOperand,
+///         Rewrite::DuplicateOperand,
+///         Rewrite::DoubleNegation,
+///         Rewrite::RedundantCoercion,
+///     ]
+/// );
+/// ```
+pub fn simplify(rule: &JsonValue) -> Simplification {
+    let mut transformations = Vec::new();
+    let rule = simplify_value(rule, "", false, &mut transformations);
+    Simplification {
+        rule,
+        transformations,
+    }
+}
+
+/// Simplifies an expression, its arguments first.
+///
+/// `condition` is true if only the truthiness of the value matters.
+fn simplify_value(
+    value: &JsonValue,
+    path: &str,
+    condition: bool,
+    transformations: &mut Vec<Transformation>,
+) -> JsonValue {
+    let mut value = match as_operator(value) {
+        Some((op, args)) => {
+            let args: Vec<JsonValue> = args
+                .into_iter()
+                .enumerate()
+                .map(|(index, arg)| {
+                    let path = format!("{}/{}/{}", path, op, index);
+                    let condition = argument_is_condition(op, index, args_len(value), condition);
+                    simplify_value(arg, &path, condition, transformations)
+                })
+                .collect();
+            operator(op, JsonValue::Array(args))
+        }
+        None => match value {
+            JsonValue::Array(items) => JsonValue::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        simplify_value(item, &format!("{}/{}", path, index), false, transformations)
+                    })
+                    .collect(),
+            ),
+            _ => return value.clone(),
+        },
+    };
+
+    while let Some((rewrite, simplified)) = rewrite(&value, condition) {
+        transformations.push(Transformation {
+            rewrite,
+            path: path.to_string(),
+            before: value,
+            after: simplified.clone(),
+        });
+        value = simplified;
+    }
+
+    // Operators given a single non-array argument keep that form
+    match as_operator(&value) {
+        Some((op, args)) if args.len() == 1 && !args[0].is_array() => operator(op, args[0].clone()),
+        _ => value,
+    }
+}
+
+/// Returns the number of arguments of an expression.
+fn args_len(value: &JsonValue) -> usize {
+    as_operator(value).map_or(0, |(_, args)| args.len())
+}
+
+/// Checks if only the truthiness of an argument matters to an operator.
+///
+/// `condition` tells the same of the expression applying the operator.
+fn argument_is_condition(op: &str, index: usize, len: usize, condition: bool) -> bool {
+    match op {
+        "!" | "!!" => true,
+        "and" | "or" => condition,
+        "if" | "?:" if index.is_multiple_of(2) && index + 1 < len => true,
+        "if" | "?:" => condition,
+        _ => false,
+    }
+}
+
+/// Applies the first identity that holds for an expression, if any.
+fn rewrite(value: &JsonValue, condition: bool) -> Option<(Rewrite, JsonValue)> {
+    let (op, args) = as_operator(value)?;
+    match op {
+        "and" => rewrite_short_circuit("and", &args, false, condition),
+        "or" => rewrite_short_circuit("or", &args, true, condition),
+        "!" | "!!" => rewrite_negation(op == "!", &args, condition),
+        "if" | "?:" => rewrite_if(op, &args),
+        _ => None,
+    }
+}
+
+/// Simplifies an `and` (`short_on == false`) or an `or` (`short_on == true`).
+fn rewrite_short_circuit(
+    op: &str,
+    args: &[&JsonValue],
+    short_on: bool,
+    condition: bool,
+) -> Option<(Rewrite, JsonValue)> {
+    let rebuild = |args: Vec<JsonValue>| operator(op, JsonValue::Array(args));
+
+    if let [only] = args {
+        return Some((Rewrite::SingleOperand, (*only).clone()));
+    }
+
+    // Nesting is associative, except for empty operations with their own value
+    fn nested<'a>(op: &str, arg: &'a JsonValue) -> Option<Vec<&'a JsonValue>> {
+        match as_operator(arg) {
+            Some((nested, nested_args)) if nested == op && !nested_args.is_empty() => {
+                Some(nested_args)
+            }
+            _ => None,
+        }
+    }
+    if args.iter().any(|arg| nested(op, arg).is_some()) {
+        let flat = args
+            .iter()
+            .flat_map(|arg| nested(op, arg).unwrap_or_else(|| vec![*arg]))
+            .cloned()
+            .collect();
+        return Some((Rewrite::Flatten, rebuild(flat)));
+    }
+
+    // A literal that short-circuits decides the result, and one that doesn't
+    // only matters in last position
+    let mut kept = Vec::with_capacity(args.len());
+    for (index, arg) in args.iter().enumerate() {
+        if is_literal(arg) && is_truthy(arg) == short_on {
+            kept.push((*arg).clone());
+            break;
+        }
+        if !is_literal(arg) || index + 1 == args.len() {
+            kept.push((*arg).clone());
+        }
+    }
+    if kept.len() < args.len() {
+        return Some((Rewrite::ConstantOperand, rebuild(kept)));
+    }
+
+    // A repeated operand evaluates like its first occurrence, which did not
+    // short-circuit, so only its value in last position can matter
+    let last = args.len().saturating_sub(1);
+    let repeated = |index: usize| {
+        let arg = args[index];
+        is_pure(arg) && (index < last || condition) && args[..index].contains(&arg)
+    };
+    if (0..args.len()).any(repeated) {
+        let kept = (0..args.len())
+            .filter(|&index| !repeated(index))
+            .map(|index| args[index].clone())
+            .collect();
+        return Some((Rewrite::DuplicateOperand, rebuild(kept)));
+    }
+
+    None
+}
+
+/// Simplifies a `!` (`negate == true`) or a `!!` (`negate == false`).
+fn rewrite_negation(
+    negate: bool,
+    args: &[&JsonValue],
+    condition: bool,
+) -> Option<(Rewrite, JsonValue)> {
+    let [arg] = args else {
+        return None;
+    };
+
+    if is_literal(arg) {
+        let value = is_truthy(arg) != negate;
+        return Some((Rewrite::ConstantNegation, JsonValue::Bool(value)));
+    }
+
+    if !negate && condition {
+        return Some((Rewrite::RedundantCoercion, (*arg).clone()));
+    }
+
+    match as_operator(arg) {
+        // Both negations produce booleans, so only the parity matters
+        Some((inner @ ("!" | "!!"), inner_args)) if inner_args.len() == 1 => {
+            let negations = usize::from(negate) + usize::from(inner == "!");
+            let op = if negations == 1 { "!" } else { "!!" };
+            Some((Rewrite::DoubleNegation, operator(op, inner_args[0].clone())))
+        }
+        Some((comparison @ ("==" | "!=" | "===" | "!=="), comparison_args)) if negate => {
+            let opposite = match comparison {
+                "==" => "!=",
+                "!=" => "==",
+                "===" => "!==",
+                _ => "===",
+            };
+            let comparison_args = comparison_args.into_iter().cloned().collect();
+            Some((
+                Rewrite::NegatedComparison,
+                operator(opposite, JsonValue::Array(comparison_args)),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Simplifies an `if` or a `?:`.
+fn rewrite_if(op: &str, args: &[&JsonValue]) -> Option<(Rewrite, JsonValue)> {
+    // Conditions are resolved from the first, as long as they are literals
+    if let Some(first) = args.first().filter(|first| is_literal(first)) {
+        let resolved = match args.len() {
+            1 => JsonValue::Null,
+            2 | 3 if is_truthy(first) => args[1].clone(),
+            2 => JsonValue::Null,
+            3 => args[2].clone(),
+            _ if is_truthy(first) => args[1].clone(),
+            _ => operator(op, json!(args[2..])),
+        };
+        return Some((Rewrite::ConstantCondition, resolved));
+    }
+
+    let branches: Vec<&JsonValue> = args
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| index % 2 == 1 || index + 1 == args.len())
+        .map(|(_, arg)| *arg)
+        .collect();
+    let conditions_pure = args
+        .iter()
+        .step_by(2)
+        .take(args.len() / 2)
+        .all(|condition| is_pure(condition));
+    if args.len() >= 3
+        && args.len() % 2 == 1
+        && conditions_pure
+        && branches.windows(2).all(|pair| pair[0] == pair[1])
+    {
+        return Some((Rewrite::DuplicateBranches, branches[0].clone()));
+    }
+
+    None
+}
+
+/// Checks if an expression always evaluates to the same value on the same
+/// data, without side effects.
+fn is_pure(value: &JsonValue) -> bool {
+    match as_operator(value) {
+        Some((op, args)) => {
+            matches!(
+                op,
+                "var"
+                    | "val"
+                    | "exists"
+                    | "missing"
+                    | "missing_some"
+                    | "=="
+                    | "==="
+                    | "!="
+                    | "!=="
+                    | "<"
+                    | "<="
+                    | ">"
+                    | ">="
+                    | "!"
+                    | "!!"
+                    | "and"
+                    | "or"
+                    | "if"
+                    | "?:"
+                    | "in"
+                    | "+"
+                    | "-"
+                    | "*"
+                    | "/"
+                    | "%"
+                    | "min"
+                    | "max"
+                    | "cat"
+                    | "substr"
+            ) && args.into_iter().all(is_pure)
+        }
+        None => match value {
+            JsonValue::Array(items) => items.iter().all(is_pure),
+            _ => true,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataLogic;
+
+    fn rewrites(simplification: &Simplification) -> Vec<(Rewrite, &str)> {
+        simplification
+            .transformations
+            .iter()
+            .map(|t| (t.rewrite, t.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_simplify_rules() {
+        let cases = [
+            (
+                json!({"or": [{"var": "a"}, {"or": [{"var": "b"}, {"var": "c"}]}]}),
+                json!({"or": [{"var": "a"}, {"var": "b"}, {"var": "c"}]}),
+            ),
+            (
+                json!({"and": [{"var": "a"}, false, {"var": "b"}]}),
+                json!({"and": [{"var": "a"}, false]}),
+            ),
+            (json!({"or": [0, "", {"var": "a"}]}), json!({"var": "a"})),
+            (
+                json!({"and": [{"var": "a"}, 1]}),
+                json!({"and": [{"var": "a"}, 1]}),
+            ),
+            (
+                json!({"and": [{"var": "a"}, {"var": "a"}]}),
+                json!({"and": [{"var": "a"}, {"var": "a"}]}),
+            ),
+            (
+                json!({"and": [{"var": "a"}, {"var": "a"}, {"var": "b"}]}),
+                json!({"and": [{"var": "a"}, {"var": "b"}]}),
+            ),
+            (
+                json!({"annotate": {"note": "n", "rule": {"!": {"!": {"var": "a"}}}}}),
+                json!({"annotate": ["n", {"!!": {"var": "a"}}]}),
+            ),
+            (
+                json!({"!": {"!": {"var": "a"}}}),
+                json!({"!!": {"var": "a"}}),
+            ),
+            (
+                json!({"==": [{"preserve": {"!": {"!": true}}}, {"var": "a"}]}),
+                json!({"==": [{"preserve": {"!": {"!": true}}}, {"var": "a"}]}),
+            ),
+            (
+                json!({"!": {"!!": {"var": "a"}}}),
+                json!({"!": {"var": "a"}}),
+            ),
+            (
+                json!({"!": {"==": [{"var": "a"}, 1]}}),
+                json!({"!=": [{"var": "a"}, 1]}),
+            ),
+            (json!({"!": [[]]}), json!(true)),
+            (
+                json!({"if": [false, "a", {"var": "c"}, "b", "c"]}),
+                json!({"if": [{"var": "c"}, "b", "c"]}),
+            ),
+            (json!({"if": [[1], "a", "b"]}), json!("a")),
+            (json!({"if": [{"var": "c"}, "a", "a"]}), json!("a")),
+            (
+                json!({"if": [{"!!": {"var": "c"}}, {"!!": {"var": "a"}}, 0]}),
+                json!({"if": [{"var": "c"}, {"!!": {"var": "a"}}, 0]}),
+            ),
+            (
+                json!({"and": [{"!!": {"var": "a"}}, {"var": "b"}]}),
+                json!({"and": [{"!!": {"var": "a"}}, {"var": "b"}]}),
+            ),
+            (
+                json!({"and": [{"log": "a"}, {"log": "a"}, 1]}),
+                json!({"and": [{"log": "a"}, {"log": "a"}, 1]}),
+            ),
+            (json!({"and": []}), json!({"and": []})),
+        ];
+
+        // Simplified rules give the same results, and are fixed points
+        let dl = DataLogic::new();
+        let data = [
+            json!({}),
+            json!({"a": 0, "b": "x", "c": true}),
+            json!({"a": 1, "b": "", "c": [1]}),
+            json!({"a": [0], "b": null, "c": 0}),
+        ];
+        for (rule, expected) in cases {
+            let simplified = simplify(&rule);
+            assert_eq!(simplified.rule, expected, "simplifying {}", rule);
+            assert!(
+                simplify(&expected).is_unchanged(),
+                "resimplifying {}",
+                expected
+            );
+            for data in &data {
+                assert_eq!(
+                    dl.evaluate_json(&rule, data, None).ok(),
+                    dl.evaluate_json(&expected, data, None).ok(),
+                    "{} on {}",
+                    rule,
+                    data
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_transformations() {
+        let rule = json!({"filter": [
+            {"var": "items"},
+            {"and": [true, {"!": {"===": [{"var": "kind"}, "draft"]}}]}
+        ]});
+
+        let simplified = simplify(&rule);
+        assert_eq!(
+            simplified.rule,
+            json!({"filter": [{"var": "items"}, {"!==": [{"var": "kind"}, "draft"]}]})
+        );
+        assert_eq!(
+            rewrites(&simplified),
+            [
+                (Rewrite::NegatedComparison, "/filter/1/and/1"),
+                (Rewrite::ConstantOperand, "/filter/1"),
+                (Rewrite::SingleOperand, "/filter/1"),
+            ]
+        );
+        assert_eq!(
+            simplified.transformations[1].to_string(),
+            r#"constant-operand at /filter/1: {"and":[true,{"!==":[{"var":"kind"},"draft"]}]} => {"and":[{"!==":[{"var":"kind"},"draft"]}]}"#
+        );
+    }
+}
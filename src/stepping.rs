@@ -0,0 +1,447 @@
+//! Evaluation in resumable steps
+//!
+//! An [`Evaluation`] runs a rule a bounded number of steps at a time, so
+//! embedders with cooperative schedulers, such as game loops, GUI event loops
+//! or single-threaded runtimes, can interleave long evaluations with other
+//! work and abandon them at any point.
+//!
+//! The evaluation keeps its own stack of pending expressions instead of
+//! recursing. A step visits a literal or a variable, evaluates the elements
+//! of an array literal, branches of an `if`, operands of an `and` or an `or`
+//! and arguments of arithmetic, date, range, `exists` and custom operators
+//! one at a time, or applies one of these operators to its arguments. Other
+//! operators, such as the iterators, are evaluated as a single step.
+
+use crate::arena::DataArena;
+use crate::datalogic::DataLogic;
+use crate::limits::Execution;
+use crate::logic::{evaluate, ControlOp, Logic, LogicError, OperatorType, Result, Token};
+use crate::value::DataValue;
+
+/// The state of an [`Evaluation`] after a call to [`Evaluation::step`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step<'a> {
+    /// The step budget ran out before the evaluation completed
+    Pending,
+    /// The evaluation completed with this result
+    Done(Result<&'a DataValue<'a>>),
+}
+
+/// An expression waiting on the stack of an evaluation
+enum Frame<'a> {
+    /// Evaluate an expression, pushing its value
+    Visit(&'a Token<'a>),
+    /// Apply an expression to the values of its arguments
+    Apply { token: &'a Token<'a>, arity: usize },
+    /// Continue an `if` after the value of the condition at `index`
+    If {
+        args: &'a [&'a Token<'a>],
+        index: usize,
+    },
+    /// Continue an `and` or an `or` after the value of the operand at `index`
+    Junction {
+        args: &'a [&'a Token<'a>],
+        index: usize,
+        is_and: bool,
+    },
+}
+
+/// A rule evaluation that runs a bounded number of steps at a time
+///
+/// Created by [`DataLogic::start_evaluation`]. Evaluations share the arena of
+/// their engine, so other evaluations may run between two steps. Limits on
+/// operations and iterations apply to the whole evaluation, and the time
+/// limit counts from the first step, including the time between steps.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::{DataLogic, Step};
+///
+/// let dl = DataLogic::new();
+/// let rule = dl
+///     .parse_logic(r#"{"if": [{">": [{"var": "score"}, 50]}, {"*": [{"var": "score"}, 2]}, 0]}"#, None)
+///     .unwrap();
+/// let data = dl.parse_data(r#"{"score": 60}"#).unwrap();
+///
+/// let mut evaluation = dl.start_evaluation(&rule, &data);
+/// let result = loop {
+///     match evaluation.step(2) {
+///         Step::Pending => continue, // do other work here
+///         Step::Done(result) => break result.unwrap(),
+///     }
+/// };
+/// assert_eq!(result.as_i64(), Some(120));
+/// ```
+pub struct Evaluation<'a> {
+    engine: &'a DataLogic,
+    rule: &'a Logic<'a>,
+    data: &'a DataValue<'a>,
+    arena: &'a DataArena,
+    frames: Vec<Frame<'a>>,
+    values: Vec<&'a DataValue<'a>>,
+    execution: Option<Option<Execution>>,
+    steps: usize,
+    outcome: Option<Result<&'a DataValue<'a>>>,
+}
+
+impl<'a> Evaluation<'a> {
+    pub(crate) fn new(
+        engine: &'a DataLogic,
+        rule: &'a Logic<'a>,
+        data: &'a DataValue<'a>,
+        arena: &'a DataArena,
+    ) -> Self {
+        Self {
+            engine,
+            rule,
+            data,
+            arena,
+            frames: vec![Frame::Visit(rule.root())],
+            values: Vec::new(),
+            execution: None,
+            steps: 0,
+            outcome: None,
+        }
+    }
+
+    /// Run at most `max_steps` steps of the evaluation
+    ///
+    /// Returns [`Step::Done`] with the result once the evaluation completes,
+    /// and again on every later call.
+    pub fn step(&mut self, max_steps: usize) -> Step<'a> {
+        if let Some(outcome) = &self.outcome {
+            return Step::Done(outcome.clone());
+        }
+
+        self.resume();
+        for _ in 0..max_steps {
+            let Some(frame) = self.frames.pop() else {
+                break;
+            };
+            self.steps += 1;
+            if let Err(error) = self.run(frame) {
+                return self.finish(Err(error));
+            }
+        }
+        self.execution = Some(self.arena.execution());
+
+        if !self.frames.is_empty() {
+            return Step::Pending;
+        }
+        let result = self.values.pop().ok_or(LogicError::InvalidArgumentsError);
+        self.finish(result)
+    }
+
+    /// Run the evaluation to completion
+    pub fn run_to_end(&mut self) -> Result<&'a DataValue<'a>> {
+        loop {
+            if let Step::Done(result) = self.step(usize::MAX) {
+                return result;
+            }
+        }
+    }
+
+    /// Check if the evaluation completed
+    pub fn is_done(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Number of steps run so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Restore the state of the arena left by the previous step, which
+    /// other evaluations may have changed since
+    fn resume(&mut self) {
+        let arena = self.arena;
+        if self.execution.is_none() {
+            arena.clear_host_objects();
+        }
+        arena.set_root_context(self.data);
+        arena.clear_path_chain();
+        arena.set_current_context(self.data, &DataValue::String("$"));
+        if let Some(execution) = self.execution {
+            arena.resume_execution(execution);
+        }
+    }
+
+    /// Complete the evaluation, applying the fallback policy and the output
+    /// type of the rule
+    fn finish(&mut self, result: Result<&'a DataValue<'a>>) -> Step<'a> {
+        let result = match result {
+            Ok(value) => Ok(value),
+            Err(error) => self.engine.recover(error, self.data, self.arena),
+        };
+        let result = match (result, self.rule.output_type()) {
+            (Ok(value), Some(output_type)) => output_type.coerce(value, self.arena),
+            (result, _) => result,
+        };
+        self.frames.clear();
+        self.values.clear();
+        self.outcome = Some(result.clone());
+        Step::Done(result)
+    }
+
+    /// Run a step
+    fn run(&mut self, frame: Frame<'a>) -> Result<()> {
+        let arena = self.arena;
+        match frame {
+            Frame::Visit(token) => self.visit(token),
+            Frame::Apply { token, arity } => {
+                let args = self.values.split_off(self.values.len() - arity);
+                let token = arena.alloc(with_literal_arguments(token, &args, arena));
+                self.values.push(evaluate(token, arena)?);
+                Ok(())
+            }
+            Frame::If { args, index } => {
+                let condition = self.values.pop().unwrap();
+                if condition.coerce_to_bool() {
+                    self.frames.push(Frame::Visit(args[index + 1]));
+                } else {
+                    self.visit_if(args, index + 2);
+                }
+                Ok(())
+            }
+            Frame::Junction {
+                args,
+                index,
+                is_and,
+            } => {
+                let value = self.values.last().unwrap();
+                // The value of the operand is that of the junction if it
+                // decides it or is the last one
+                if value.coerce_to_bool() != is_and || index + 1 == args.len() {
+                    return Ok(());
+                }
+                self.values.pop();
+                self.frames.push(Frame::Junction {
+                    args,
+                    index: index + 1,
+                    is_and,
+                });
+                self.frames.push(Frame::Visit(args[index + 1]));
+                Ok(())
+            }
+        }
+    }
+
+    /// Evaluate an expression, or schedule the evaluation of its parts
+    fn visit(&mut self, token: &'a Token<'a>) -> Result<()> {
+        let arena = self.arena;
+        match token {
+            Token::ArrayLiteral(items) => self.schedule(token, items),
+            Token::Operator { op_type, args } if !arena.has_operator_overrides() => match op_type {
+                // Control operators on other arguments than an array literal
+                // fail, in a single step
+                OperatorType::Control(ControlOp::If | ControlOp::And | ControlOp::Or)
+                    if !args.is_array_literal() =>
+                {
+                    self.evaluate(token)
+                }
+                OperatorType::Control(ControlOp::If) => {
+                    self.visit_if(arguments(args, arena), 0);
+                    Ok(())
+                }
+                OperatorType::Control(op @ (ControlOp::And | ControlOp::Or)) => {
+                    let args = arguments(args, arena);
+                    let is_and = *op == ControlOp::And;
+                    if args.is_empty() {
+                        let empty = if is_and {
+                            arena.null_value()
+                        } else {
+                            arena.false_value()
+                        };
+                        self.values.push(empty);
+                    } else {
+                        self.frames.push(Frame::Junction {
+                            args,
+                            index: 0,
+                            is_and,
+                        });
+                        self.frames.push(Frame::Visit(args[0]));
+                    }
+                    Ok(())
+                }
+                OperatorType::Arithmetic(_)
+                | OperatorType::DateTime(_)
+                | OperatorType::Range(_)
+                | OperatorType::Exists => self.schedule(token, arguments(args, arena)),
+                _ => self.evaluate(token),
+            },
+            Token::CustomOperator { args, .. } => self.schedule(token, arguments(args, arena)),
+            _ => self.evaluate(token),
+        }
+    }
+
+    /// Evaluate an expression in a single step
+    fn evaluate(&mut self, token: &'a Token<'a>) -> Result<()> {
+        self.values.push(evaluate(token, self.arena)?);
+        Ok(())
+    }
+
+    /// Schedule the evaluation of the arguments of an expression, followed by
+    /// its application to their values
+    fn schedule(&mut self, token: &'a Token<'a>, args: &'a [&'a Token<'a>]) -> Result<()> {
+        self.frames.push(Frame::Apply {
+            token,
+            arity: args.len(),
+        });
+        self.frames
+            .extend(args.iter().rev().map(|arg| Frame::Visit(arg)));
+        Ok(())
+    }
+
+    /// Schedule the evaluation of an `if` from the condition at `index`
+    fn visit_if(&mut self, args: &'a [&'a Token<'a>], index: usize) {
+        if index + 1 < args.len() {
+            self.frames.push(Frame::If { args, index });
+            self.frames.push(Frame::Visit(args[index]));
+        } else if index < args.len() {
+            self.frames.push(Frame::Visit(args[index]));
+        } else {
+            self.values.push(self.arena.null_value());
+        }
+    }
+}
+
+/// Returns the arguments of an operator, which are an array literal or a
+/// single expression.
+fn arguments<'a>(args: &'a Token<'a>, arena: &'a DataArena) -> &'a [&'a Token<'a>] {
+    match args {
+        Token::ArrayLiteral(items) => items,
+        arg => arena.alloc_slice_copy(&[arg]),
+    }
+}
+
+/// Rebuilds an expression with the values of its arguments as literals.
+fn with_literal_arguments<'a>(
+    token: &'a Token<'a>,
+    values: &[&'a DataValue<'a>],
+    arena: &'a DataArena,
+) -> Token<'a> {
+    let literal = |value: &&'a DataValue<'a>| arena.alloc(Token::literal((*value).clone()));
+    let rebuild_args = |args: &'a Token<'a>| -> &'a Token<'a> {
+        match args {
+            Token::ArrayLiteral(_) => {
+                arena.alloc(Token::ArrayLiteral(values.iter().map(literal).collect()))
+            }
+            _ => literal(&values[0]),
+        }
+    };
+    match token {
+        Token::ArrayLiteral(_) => Token::ArrayLiteral(values.iter().map(literal).collect()),
+        Token::Operator { op_type, args } => Token::operator(*op_type, rebuild_args(args)),
+        Token::CustomOperator { name, args } => Token::custom_operator(name, rebuild_args(args)),
+        _ => unreachable!("only array literals and operators are applied"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limits, ToJson};
+
+    #[test]
+    fn test_step_matches_evaluate() {
+        let dl = DataLogic::new();
+        let data = dl
+            .parse_data(r#"{"a": 3, "b": [1, 2], "s": "x", "day": "2024-01-01T00:00:00Z"}"#)
+            .unwrap();
+        let rules = [
+            r#"{"+": [{"var": "a"}, {"*": [{"var": "a"}, 2]}, 1]}"#,
+            r#"{"if": [{"<": [{"var": "a"}, 0]}, "neg", {"==": [{"var": "a"}, 0]}, "zero", "pos"]}"#,
+            r#"{"and": [{"var": "a"}, {"var": "b"}, {"var": "missing"}]}"#,
+            r#"{"or": [{"var": "missing"}, 0, {"var": "s"}]}"#,
+            r#"[{"var": "a"}, {"-": {"var": "b"}}, {"cat": [{"var": "s"}, "y"]}]"#,
+            r#"{"map": [{"var": "b"}, {"+": [{"var": ""}, {"var": "a"}]}]}"#,
+            r#"{"date_diff": [{"var": "day"}, "2023-12-25T00:00:00Z", "days"]}"#,
+            r#"{"if": []}"#,
+            r#"{"and": []}"#,
+        ];
+
+        for source in rules {
+            let rule = dl.parse_logic(source, None).unwrap();
+            let expected = dl.evaluate(&rule, &data).unwrap().to_json();
+            for budget in [1, 2, 5, usize::MAX] {
+                let mut evaluation = dl.start_evaluation(&rule, &data);
+                let result = loop {
+                    if let Step::Done(result) = evaluation.step(budget) {
+                        break result;
+                    }
+                };
+                assert_eq!(result.unwrap().to_json(), expected, "{source}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_step_interleaving() {
+        let dl = DataLogic::new();
+        let rule = dl
+            .parse_logic(r#"{"+": [{"var": "x"}, {"var": "x"}, {"var": "x"}]}"#, None)
+            .unwrap();
+        let one = dl.parse_data(r#"{"x": 1}"#).unwrap();
+        let ten = dl.parse_data(r#"{"x": 10}"#).unwrap();
+
+        let mut first = dl.start_evaluation(&rule, &one);
+        let mut second = dl.start_evaluation(&rule, &ten);
+        assert_eq!(first.step(2), Step::Pending);
+        assert_eq!(second.step(3), Step::Pending);
+        assert_eq!(dl.evaluate(&rule, &ten).unwrap().as_i64(), Some(30));
+        assert!(!first.is_done());
+
+        assert_eq!(first.run_to_end().unwrap().as_i64(), Some(3));
+        assert_eq!(second.run_to_end().unwrap().as_i64(), Some(30));
+        // The operator and its three arguments
+        assert_eq!(first.steps(), 5);
+        assert!(first.is_done());
+        assert_eq!(first.step(1), Step::Done(Ok(&DataValue::integer(3))));
+    }
+
+    #[test]
+    fn test_step_errors() {
+        let dl = DataLogic::new();
+        let data = dl.parse_data(r#"{"ok": true}"#).unwrap();
+
+        // Untaken branches and decided junctions are not evaluated
+        let rule = dl
+            .parse_logic(
+                r#"{"or": [{"var": "ok"}, {"throw": "never"}, {"if": [true, 1, {"throw": "no"}]}]}"#,
+                None,
+            )
+            .unwrap();
+        let result = dl.start_evaluation(&rule, &data).run_to_end().unwrap();
+        assert_eq!(result.as_bool(), Some(true));
+
+        let rule = dl
+            .parse_logic(r#"{"+": [1, {"throw": "boom"}]}"#, None)
+            .unwrap();
+        let result = dl.start_evaluation(&rule, &data).run_to_end();
+        assert_eq!(
+            result,
+            Err(LogicError::ThrownError {
+                r#type: "boom".to_string()
+            })
+        );
+
+        // Operation limits count the work of the whole evaluation
+        let mut dl = DataLogic::new();
+        dl.set_limits(Limits::new().with_max_operations(2));
+        let data = dl.parse_data(r#"{"ok": 1}"#).unwrap();
+        let rule = dl
+            .parse_logic(
+                r#"{"+": [{"*": [{"var": "ok"}, 2]}, {"*": [{"var": "ok"}, 3]}]}"#,
+                None,
+            )
+            .unwrap();
+        assert!(dl.evaluate(&rule, &data).is_err());
+        let mut evaluation = dl.start_evaluation(&rule, &data);
+        while evaluation.step(1) == Step::Pending {}
+        assert!(matches!(
+            evaluation.run_to_end(),
+            Err(LogicError::LimitExceeded { .. })
+        ));
+    }
+}
@@ -5,6 +5,7 @@
 
 use super::data_value::DataValue;
 use super::number::NumberValue;
+use super::IntoDataValue;
 use crate::arena::DataArena;
 use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
 use std::collections::HashMap;
@@ -81,6 +82,72 @@ impl<'a> FromJson<'a> for DataValue<'a> {
     }
 }
 
+/// Converts a JSON value to a DataValue that borrows its strings from the JSON.
+///
+/// Unlike [`FromJson::from_json`], strings and object keys are not copied
+/// into the arena; only the array and object slices are allocated. The
+/// result cannot outlive the JSON value.
+///
+/// # Examples
+///
+/// ```
+/// use datalogic_rs::arena::DataArena;
+/// use datalogic_rs::value::borrow_json;
+/// use serde_json::json;
+///
+/// let arena = DataArena::new();
+/// let json = json!({"name": "Ann", "tags": ["a", "b"]});
+/// let value = borrow_json(&json, &arena);
+///
+/// let name = value.get("name").unwrap().as_str().unwrap();
+/// assert!(std::ptr::eq(name, json["name"].as_str().unwrap()));
+/// ```
+pub fn borrow_json<'a>(json: &'a JsonValue, arena: &'a DataArena) -> DataValue<'a> {
+    match json {
+        JsonValue::String(s) => DataValue::String(s),
+        JsonValue::Array(arr) => {
+            let values: Vec<DataValue<'a>> =
+                arr.iter().map(|item| borrow_json(item, arena)).collect();
+            DataValue::array(arena, &values)
+        }
+        JsonValue::Object(obj) => {
+            // Datetime and duration objects are converted as usual
+            if obj.len() == 1 && (obj.contains_key("datetime") || obj.contains_key("timestamp")) {
+                let value = DataValue::from_json(json, arena);
+                if !matches!(value, DataValue::Object(_)) {
+                    return value;
+                }
+            }
+
+            let entries: Vec<(&'a str, DataValue<'a>)> = obj
+                .iter()
+                .map(|(key, value)| (key.as_str(), borrow_json(value, arena)))
+                .collect();
+            DataValue::object(arena, &entries)
+        }
+        // Scalars hold no borrowed data
+        _ => DataValue::from_json(json, arena),
+    }
+}
+
+impl<'a> IntoDataValue<'a> for &'a JsonValue {
+    fn into_data_value(self, arena: &'a DataArena) -> DataValue<'a> {
+        borrow_json(self, arena)
+    }
+}
+
+impl From<&DataValue<'_>> for JsonValue {
+    fn from(value: &DataValue<'_>) -> Self {
+        value.to_json()
+    }
+}
+
+impl From<DataValue<'_>> for JsonValue {
+    fn from(value: DataValue<'_>) -> Self {
+        value.to_json()
+    }
+}
+
 impl ToJson for DataValue<'_> {
     fn to_json(&self) -> JsonValue {
         match self {
@@ -141,6 +208,8 @@ impl ToJson for DataValue<'_> {
                     JsonValue::String(format!("{}s", seconds))
                 }
             }
+            // Host objects have no JSON representation
+            DataValue::Custom(_) => JsonValue::Null,
         }
     }
 }
@@ -3,6 +3,7 @@
 //! This module provides the DataValue enum, which is a memory-efficient
 //! representation of data values that leverages arena allocation.
 
+use super::host::HostHandle;
 use super::number::NumberValue;
 use crate::arena::DataArena;
 use chrono::{DateTime, Duration, Utc};
@@ -38,6 +39,9 @@ pub enum DataValue<'a> {
 
     /// Represents a duration value
     Duration(Duration),
+
+    /// Represents an opaque host object created by a custom operator
+    Custom(HostHandle),
 }
 
 impl<'a> DataValue<'a> {
@@ -249,6 +253,9 @@ impl<'a> DataValue<'a> {
 
             // Duration is false if zero
             DataValue::Duration(d) => !d.is_zero(),
+
+            // Host objects always coerce to true
+            DataValue::Custom(_) => true,
         }
     }
 
@@ -329,6 +336,8 @@ impl<'a> DataValue<'a> {
             DataValue::Array(_) => None,
 
             DataValue::Object(_) => None,
+
+            DataValue::Custom(_) => None,
         }
     }
 
@@ -368,6 +377,7 @@ impl<'a> DataValue<'a> {
                 let formatted = format!("{}d:{}h:{}m:{}s", days, hours, minutes, seconds);
                 DataValue::String(arena.alloc_str(&formatted))
             }
+            DataValue::Custom(_) => DataValue::String(arena.alloc_str("[host object]")),
         }
     }
 
@@ -401,6 +411,7 @@ impl<'a> DataValue<'a> {
             DataValue::Object(_) => "object",
             DataValue::DateTime(_) => "datetime",
             DataValue::Duration(_) => "duration",
+            DataValue::Custom(_) => "custom",
         }
     }
 
@@ -567,7 +578,10 @@ impl<'a> DataValue<'a> {
                         return false;
                     }
                 }
-                true
+
+                // Objects with repeated keys can match in one direction only
+                b.iter()
+                    .all(|(b_key, _)| a.iter().any(|(a_key, _)| a_key == b_key))
             }
             _ => false, // Different types are never strictly equal
         }
@@ -704,6 +718,7 @@ impl fmt::Display for DataValue<'_> {
                 let seconds = d.num_seconds() % 60;
                 write!(f, "\"{}d:{}h:{}m:{}s\"", days, hours, minutes, seconds)
             }
+            DataValue::Custom(_) => write!(f, "\"[host object]\""),
         }
     }
 }
@@ -901,4 +916,21 @@ mod tests {
         let array2 = DataValue::array(&arena, &[DataValue::integer(1), DataValue::integer(3)]);
         assert!(array1 < array2);
     }
+
+    #[test]
+    fn test_strict_equals_objects() {
+        let arena = DataArena::new();
+        let a = arena.intern_str("a");
+        let b = arena.intern_str("b");
+
+        let nested = DataValue::array(&arena, &[DataValue::integer(1), DataValue::null()]);
+        let left = DataValue::object(&arena, &[(a, nested.clone()), (b, DataValue::bool(true))]);
+        let right = DataValue::object(&arena, &[(b, DataValue::bool(true)), (a, nested.clone())]);
+        assert!(left.strict_equals(&right));
+
+        // The same number of entries, but a repeated key in one object
+        let repeated = DataValue::object(&arena, &[(a, nested.clone()), (a, nested.clone())]);
+        assert!(!repeated.strict_equals(&left));
+        assert!(!left.strict_equals(&repeated));
+    }
 }
@@ -3,7 +3,7 @@
 //! This module provides functions for parsing and formatting datetime and duration values.
 
 use chrono::Datelike;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, Months, Utc};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::error::Error;
@@ -29,16 +29,24 @@ pub fn parse_datetime(datetime_str: &str) -> Result<DateTime<Utc>, Box<dyn Error
 /// Parses a duration string into a `chrono::Duration`.
 ///
 /// Accepts two formats:
-/// - 1d:2h:3m:4s (custom format with days, hours, minutes, seconds)
+/// - 1d:2h:3m:4s (custom format with days, hours, minutes, seconds, any of
+///   which may be left out, as in 4h or 2h:30m)
 /// - P1DT2H3M4S (ISO8601 duration format)
+///
+/// The whole string must be a duration, so text merely containing one, such
+/// as "in 5m", is rejected.
 pub fn parse_duration(duration_str: &str) -> Result<Duration, Box<dyn Error>> {
     // First, try our custom format
     lazy_static! {
         static ref RE: Regex =
-            Regex::new(r"(?:(\d+)d)?:?(?:(\d+)h)?:?(?:(\d+)m)?:?(?:(\d+)s)?").unwrap();
+            Regex::new(r"^(?:(\d+)d)?:?(?:(\d+)h)?:?(?:(\d+)m)?:?(?:(\d+)s)?$").unwrap();
     }
 
-    if let Some(caps) = RE.captures(duration_str) {
+    // Zero durations such as "0d:0h:0m:0s" are accepted, but not empty strings
+    if let Some(caps) = RE
+        .captures(duration_str)
+        .filter(|caps| (1..=4).any(|i| caps.get(i).is_some()))
+    {
         let days = caps
             .get(1)
             .map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
@@ -52,12 +60,10 @@ pub fn parse_duration(duration_str: &str) -> Result<Duration, Box<dyn Error>> {
             .get(4)
             .map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
 
-        if days > 0 || hours > 0 || minutes > 0 || seconds > 0 {
-            return Ok(Duration::days(days)
-                + Duration::hours(hours)
-                + Duration::minutes(minutes)
-                + Duration::seconds(seconds));
-        }
+        return Ok(Duration::days(days)
+            + Duration::hours(hours)
+            + Duration::minutes(minutes)
+            + Duration::seconds(seconds));
     }
 
     // Then try ISO8601 format
@@ -65,35 +71,119 @@ pub fn parse_duration(duration_str: &str) -> Result<Duration, Box<dyn Error>> {
 }
 
 /// Parses an ISO8601 duration string like "P1DT2H3M4S".
+///
+/// Supports weeks, days, hours, minutes and (fractional) seconds, as well as a
+/// leading minus sign. Zero durations such as "PT0S" are accepted.
 fn parse_iso8601_duration(duration_str: &str) -> Result<Duration, Box<dyn Error>> {
     lazy_static! {
-        static ref ISO_RE: Regex =
-            Regex::new(r"P(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?)?").unwrap();
+        static ref ISO_RE: Regex = Regex::new(
+            r"^(-)?P(?:(\d+)W)?(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)(?:\.(\d{1,9}))?S)?)?$"
+        )
+        .unwrap();
     }
 
-    if let Some(caps) = ISO_RE.captures(duration_str) {
-        let days = caps
-            .get(1)
-            .map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
-        let hours = caps
-            .get(2)
-            .map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
-        let minutes = caps
-            .get(3)
-            .map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
-        let seconds = caps
-            .get(4)
-            .map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
+    let caps = ISO_RE
+        .captures(duration_str)
+        .ok_or("Invalid duration format")?;
+
+    // "P" and "PT" alone carry no components
+    if (2..=6).all(|i| caps.get(i).is_none()) {
+        return Err("Invalid duration format".into());
+    }
+
+    let component = |i: usize| -> Result<i64, Box<dyn Error>> {
+        Ok(caps.get(i).map_or(Ok(0), |m| m.as_str().parse::<i64>())?)
+    };
+    let nanos = caps
+        .get(7)
+        .map_or(Ok(0), |m| format!("{:0<9}", m.as_str()).parse::<i64>())?;
+
+    let duration = Duration::weeks(component(2)?)
+        + Duration::days(component(3)?)
+        + Duration::hours(component(4)?)
+        + Duration::minutes(component(5)?)
+        + Duration::seconds(component(6)?)
+        + Duration::nanoseconds(nanos);
+
+    if caps.get(1).is_some() {
+        Ok(-duration)
+    } else {
+        Ok(duration)
+    }
+}
+
+/// Formats a Duration as an ISO8601 duration string like "P1DT2H3M4S".
+///
+/// The result can be parsed back with [`parse_duration`]. Zero durations are
+/// formatted as "PT0S" and negative durations carry a leading minus sign.
+pub fn format_iso8601_duration(duration: &Duration) -> String {
+    if duration.is_zero() {
+        return "PT0S".to_string();
+    }
+
+    let sign = if *duration < Duration::zero() {
+        "-"
+    } else {
+        ""
+    };
+    let duration = duration.abs();
+
+    let total_seconds = duration.num_seconds();
+    let days = total_seconds / 86400;
+    let hours = (total_seconds % 86400) / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let seconds = total_seconds % 60;
+    let nanos = duration.subsec_nanos();
 
-        if days > 0 || hours > 0 || minutes > 0 || seconds > 0 {
-            return Ok(Duration::days(days)
-                + Duration::hours(hours)
-                + Duration::minutes(minutes)
-                + Duration::seconds(seconds));
+    let mut result = format!("{}P", sign);
+    if days > 0 {
+        result.push_str(&format!("{}D", days));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || nanos > 0 {
+        result.push('T');
+        if hours > 0 {
+            result.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            result.push_str(&format!("{}M", minutes));
+        }
+        if nanos > 0 {
+            let fraction = format!("{:09}", nanos);
+            result.push_str(&format!("{}.{}S", seconds, fraction.trim_end_matches('0')));
+        } else if seconds > 0 {
+            result.push_str(&format!("{}S", seconds));
         }
     }
+    result
+}
 
-    Err("Invalid duration format".into())
+/// Formats a Duration as a short human-readable string like "2d 3h".
+///
+/// Zero-valued units are omitted and sub-second precision is dropped. If
+/// `max_units` is given, only that many of the largest non-zero units are
+/// shown, e.g. "2d 3h" instead of "2d 3h 4m 5s".
+pub fn humanize_duration(duration: &Duration, max_units: Option<usize>) -> String {
+    let sign = if duration.num_seconds() < 0 { "-" } else { "" };
+    let total_seconds = duration.num_seconds().abs();
+
+    let units = [
+        (total_seconds / 86400, "d"),
+        ((total_seconds % 86400) / 3600, "h"),
+        ((total_seconds % 3600) / 60, "m"),
+        (total_seconds % 60, "s"),
+    ];
+
+    let parts: Vec<String> = units
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .take(max_units.unwrap_or(units.len()))
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+
+    if parts.is_empty() {
+        return "0s".to_string();
+    }
+    format!("{}{}", sign, parts.join(" "))
 }
 
 /// Formats a Duration into a string like "1d:2h:3m:4s".
@@ -157,6 +247,35 @@ pub fn date_diff(dt1: &DateTime<Utc>, dt2: &DateTime<Utc>, unit: &str) -> i64 {
     }
 }
 
+/// Adds an amount of the specified unit to a datetime.
+///
+/// Supports the units of [`date_diff`], case sensitively so that "M" is
+/// months and "m" is minutes. Months and years are calendar units:
+/// a day past the end of the resulting month is clamped to its last day, so
+/// one month after January 31 is the last day of February. Returns `None` for
+/// an unknown unit or a result out of range.
+pub fn date_add(dt: &DateTime<Utc>, amount: i64, unit: &str) -> Option<DateTime<Utc>> {
+    let add_months = |months: i64| {
+        let magnitude = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+        if months < 0 {
+            dt.checked_sub_months(magnitude)
+        } else {
+            dt.checked_add_months(magnitude)
+        }
+    };
+
+    let duration = match unit {
+        "years" | "year" | "y" => return add_months(amount.checked_mul(12)?),
+        "months" | "month" | "M" => return add_months(amount),
+        "days" | "day" | "d" => Duration::try_days(amount)?,
+        "hours" | "hour" | "h" => Duration::try_hours(amount)?,
+        "minutes" | "minute" | "m" => Duration::try_minutes(amount)?,
+        "seconds" | "second" | "s" => Duration::try_seconds(amount)?,
+        _ => return None,
+    };
+    dt.checked_add_signed(duration)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,6 +317,11 @@ mod tests {
         assert_eq!(duration.num_hours(), 2);
         assert_eq!(duration.num_minutes() % 60, 30);
 
+        assert_eq!(parse_duration("0d:0h:0m:0s").unwrap(), Duration::zero());
+        for invalid in ["", ":", "in 5m", "5m late", "2024-01-01T00:00:00Z"] {
+            assert!(parse_duration(invalid).is_err(), "{invalid}");
+        }
+
         // Test ISO8601 format
         let duration = parse_duration("P1DT2H3M4S").unwrap();
         assert_eq!(duration.num_days(), 1);
@@ -230,6 +354,43 @@ mod tests {
         assert_eq!(formatted, "20s");
     }
 
+    #[test]
+    fn test_iso8601_duration_round_trip() {
+        let durations = [
+            Duration::zero(),
+            Duration::days(2) + Duration::hours(3),
+            Duration::minutes(90),
+            Duration::seconds(4) + Duration::milliseconds(250),
+            -(Duration::days(1) + Duration::seconds(1)),
+        ];
+        for duration in durations {
+            let formatted = format_iso8601_duration(&duration);
+            assert_eq!(
+                parse_duration(&formatted).unwrap(),
+                duration,
+                "{}",
+                formatted
+            );
+        }
+
+        assert_eq!(
+            format_iso8601_duration(&(Duration::days(2) + Duration::hours(3))),
+            "P2DT3H"
+        );
+        assert_eq!(parse_duration("P1W").unwrap(), Duration::days(7));
+        assert!(parse_duration("P").is_err());
+        assert!(parse_duration("P1X").is_err());
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        let duration = Duration::days(2) + Duration::hours(3) + Duration::seconds(5);
+        assert_eq!(humanize_duration(&duration, None), "2d 3h 5s");
+        assert_eq!(humanize_duration(&duration, Some(2)), "2d 3h");
+        assert_eq!(humanize_duration(&Duration::zero(), None), "0s");
+        assert_eq!(humanize_duration(&-Duration::minutes(90), None), "-1h 30m");
+    }
+
     #[test]
     fn test_date_diff() {
         // Test days difference
@@ -257,4 +418,31 @@ mod tests {
 
         assert_eq!(date_diff(&dt2, &dt1, "years"), 2);
     }
+
+    #[test]
+    fn test_date_add() {
+        let dt = Utc.with_ymd_and_hms(2024, 1, 31, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            date_add(&dt, 2, "days"),
+            Some(Utc.with_ymd_and_hms(2024, 2, 2, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            date_add(&dt, -13, "h"),
+            Some(Utc.with_ymd_and_hms(2024, 1, 30, 23, 0, 0).unwrap())
+        );
+
+        // Calendar units clamp to the end of shorter months
+        assert_eq!(
+            date_add(&dt, 1, "month"),
+            Some(Utc.with_ymd_and_hms(2024, 2, 29, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            date_add(&dt, -1, "years"),
+            Some(Utc.with_ymd_and_hms(2023, 1, 31, 12, 0, 0).unwrap())
+        );
+
+        assert_eq!(date_add(&dt, 1, "fortnights"), None);
+        assert_eq!(date_add(&dt, i64::MAX, "days"), None);
+    }
 }
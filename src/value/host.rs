@@ -0,0 +1,51 @@
+//! Host object handles.
+//!
+//! Custom operators sometimes produce state that is expensive to rebuild or
+//! cannot be represented as JSON, such as a compiled regex or a model
+//! session. Such objects are stored in the arena's host object registry and
+//! passed between operators as `DataValue::Custom` handles.
+
+use std::any::Any;
+use std::sync::Arc;
+
+/// An opaque handle to a host object stored in a [`DataArena`](crate::arena::DataArena).
+///
+/// Handles are only valid for the evaluation that created them; once the
+/// registry is cleared, looking up a stale handle returns `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HostHandle {
+    pub(crate) index: u32,
+    pub(crate) generation: u32,
+}
+
+/// Storage for the host objects of the current evaluation.
+#[derive(Default)]
+pub(crate) struct HostObjects {
+    objects: Vec<Arc<dyn Any + Send + Sync>>,
+    generation: u32,
+}
+
+impl HostObjects {
+    /// Stores an object and returns its handle.
+    pub(crate) fn insert(&mut self, object: Arc<dyn Any + Send + Sync>) -> HostHandle {
+        self.objects.push(object);
+        HostHandle {
+            index: (self.objects.len() - 1) as u32,
+            generation: self.generation,
+        }
+    }
+
+    /// Returns the object behind a handle of the current generation.
+    pub(crate) fn get(&self, handle: HostHandle) -> Option<Arc<dyn Any + Send + Sync>> {
+        if handle.generation != self.generation {
+            return None;
+        }
+        self.objects.get(handle.index as usize).cloned()
+    }
+
+    /// Drops all objects and invalidates their handles.
+    pub(crate) fn clear(&mut self) {
+        self.objects.clear();
+        self.generation = self.generation.wrapping_add(1);
+    }
+}
@@ -8,14 +8,20 @@ mod access;
 mod convert;
 mod data_value;
 mod datetime;
+mod host;
 mod number;
 
 pub use access::{parse_path, PathSegment, ValueAccess};
 pub use convert::{
-    data_value_to_json, hash_map_to_data_value, json_to_data_value, FromJson, ToJson,
+    borrow_json, data_value_to_json, hash_map_to_data_value, json_to_data_value, FromJson, ToJson,
 };
 pub use data_value::DataValue;
-pub use datetime::{date_diff, format_duration, parse_datetime, parse_duration};
+pub use datetime::{
+    date_add, date_diff, format_duration, format_iso8601_duration, humanize_duration,
+    parse_datetime, parse_duration,
+};
+pub use host::HostHandle;
+pub(crate) use host::HostObjects;
 pub use number::NumberValue;
 
 use crate::arena::DataArena;
@@ -88,4 +94,25 @@ mod tests {
         assert!(object.is_object());
         assert_eq!(object.as_object().unwrap().len(), 2);
     }
+
+    #[test]
+    fn test_json_interop() {
+        let arena = DataArena::new();
+        let json = serde_json::json!({
+            "name": "Ann",
+            "scores": [1, 2.5, null],
+            "joined": {"datetime": "2024-01-01T00:00:00Z"}
+        });
+
+        let borrowed = (&json).into_data_value(&arena);
+        assert_eq!(borrowed, DataValue::from_json(&json, &arena));
+        assert!(borrowed.get("joined").unwrap().is_datetime());
+
+        let round_trip: serde_json::Value = borrowed.into();
+        assert_eq!(round_trip["scores"], json["scores"]);
+        assert_eq!(
+            serde_json::Value::from(&DataValue::integer(3)),
+            serde_json::json!(3)
+        );
+    }
 }
@@ -28,7 +28,7 @@ impl NumberValue {
     /// Creates a new NumberValue from an f64.
     pub fn from_f64(value: f64) -> Self {
         // Store integers as integers when possible
-        if value.fract() == 0.0 && value >= i64::MIN as f64 && value <= i64::MAX as f64 {
+        if value.fract() == 0.0 && value >= i64::MIN as f64 && value < i64::MAX as f64 {
             NumberValue::Integer(value as i64)
         } else {
             NumberValue::Float(value)
@@ -50,7 +50,7 @@ impl NumberValue {
         match *self {
             NumberValue::Integer(i) => Some(i),
             NumberValue::Float(f) => {
-                if f.fract() == 0.0 && f >= i64::MIN as f64 && f <= i64::MAX as f64 {
+                if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
                     Some(f as i64)
                 } else {
                     None
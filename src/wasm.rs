@@ -0,0 +1,120 @@
+//! JavaScript bindings for WebAssembly builds
+//!
+//! With the `wasm` feature, the engine is exported through wasm-bindgen so
+//! the same rules can be evaluated in browsers. Rules, data and results cross
+//! the boundary as JSON strings and errors are thrown as JavaScript errors.
+//! The library is an `rlib` only; the `examples/web` crate links these
+//! classes into the `cdylib` built by `wasm-pack build examples/web`.
+//!
+//! ```js
+//! import { DataLogic } from "datalogic-web";
+//!
+//! const engine = new DataLogic();
+//! engine.evaluate('{"+": [{"var": "a"}, 1]}', '{"a": 41}'); // "42"
+//!
+//! const rule = engine.compile('{">": [{"var": "score"}, 10]}');
+//! rule.evaluate(engine, '{"score": 12}'); // "true"
+//! ```
+//!
+//! Evaluation does not use threads or the system clock, except for the `now`
+//! operator, so it runs on `wasm32-unknown-unknown`. APIs that spawn threads,
+//! such as parallel precompilation or evaluation timeouts, are not exported.
+
+use serde_json::Value as JsonValue;
+use wasm_bindgen::prelude::*;
+
+use crate::{CompiledRule, DataLogic, DataValue, FromJson, LogicError, ToJson};
+
+/// Converts an engine error into a JavaScript error
+fn js_error(error: LogicError) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+/// Parses a JSON document passed from JavaScript
+fn parse_json(source: &str) -> Result<JsonValue, JsError> {
+    serde_json::from_str(source).map_err(|error| JsError::new(&error.to_string()))
+}
+
+/// A rule engine exported to JavaScript as `DataLogic`
+#[wasm_bindgen(js_name = DataLogic)]
+pub struct WasmDataLogic {
+    engine: DataLogic,
+}
+
+impl Default for WasmDataLogic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(js_class = DataLogic)]
+impl WasmDataLogic {
+    /// Creates an engine with the default settings
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            engine: DataLogic::new(),
+        }
+    }
+
+    /// Checks that a JSONLogic rule parses, throwing the parse error otherwise
+    pub fn parse(&self, rule: &str) -> Result<(), JsError> {
+        self.compile(rule).map(|_| ())
+    }
+
+    /// Parses and optimizes a JSONLogic rule for repeated evaluation
+    pub fn compile(&self, rule: &str) -> Result<WasmCompiledRule, JsError> {
+        let rule = self.engine.compile(rule, None).map_err(js_error)?;
+        Ok(WasmCompiledRule { rule })
+    }
+
+    /// Evaluates a JSONLogic rule against JSON data, returning the JSON result
+    pub fn evaluate(&self, rule: &str, data: &str) -> Result<String, JsError> {
+        self.compile(rule)?.evaluate(self, data)
+    }
+}
+
+/// A compiled rule exported to JavaScript as `CompiledRule`
+#[wasm_bindgen(js_name = CompiledRule)]
+pub struct WasmCompiledRule {
+    rule: CompiledRule,
+}
+
+#[wasm_bindgen(js_class = CompiledRule)]
+impl WasmCompiledRule {
+    /// Evaluates the rule against JSON data, returning the JSON result
+    ///
+    /// The data and the temporaries of the evaluation are freed once it
+    /// returns, so evaluating a rule repeatedly does not grow the engine.
+    pub fn evaluate(&self, engine: &WasmDataLogic, data: &str) -> Result<String, JsError> {
+        let data = parse_json(data)?;
+        let scratch = engine.engine.scratch_arena();
+        let data = DataValue::from_json(&data, &scratch);
+        let result = engine
+            .engine
+            .evaluate_compiled_in(&self.rule, &data, &scratch)
+            .map_err(js_error)?;
+        Ok(result.to_json().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_json_strings() {
+        let engine = WasmDataLogic::new();
+        let result = engine
+            .evaluate(r#"{"+": [{"var": "a"}, 1]}"#, r#"{"a": 41}"#)
+            .unwrap();
+        assert_eq!(result, "42");
+
+        let rule = engine.compile(r#"{"map": [{"var": "xs"}, {"*": [{"var": ""}, 2]}]}"#);
+        let rule = rule.unwrap();
+        for (data, expected) in [(r#"{"xs": [1, 2]}"#, "[2,4]"), (r#"{"xs": []}"#, "[]")] {
+            assert_eq!(rule.evaluate(&engine, data).unwrap(), expected);
+        }
+        assert!(engine.parse(r#"{"==": [1, 1]}"#).is_ok());
+    }
+}
@@ -0,0 +1,259 @@
+//! Conformance of every evaluation path with the JSONLogic test suites
+//!
+//! The suites embedded in `tests/suites` include the official tests of
+//! jsonlogic.com (`compatible.json`) and the compatibility suites of
+//! json-logic-engine. Each case is evaluated as a parsed and optimized rule,
+//! as a compiled rule, as a rule that is parsed without optimization, and one
+//! step at a time, so that drift between the paths is caught along with
+//! failures of each of them. A report of the cases passed and failed by each path is printed for
+//! every operator.
+//!
+//! Run with `cargo test --features conformance -- --nocapture`.
+
+#![cfg(feature = "conformance")]
+
+use datalogic_rs::arena::DataArena;
+use datalogic_rs::{DataLogic, DataValue, FromJson, LogicError, Step, ToJson};
+use serde_json::{json, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// The ways a rule can be evaluated
+#[derive(Debug, Clone, Copy)]
+enum Backend {
+    /// Parsed and optimized in the arena of the engine
+    Interpreted,
+    /// Compiled into a thread-safe rule
+    Compiled,
+    /// Parsed without static optimization
+    Unoptimized,
+    /// Parsed without static optimization and evaluated one step at a time
+    Stepped,
+}
+
+const BACKENDS: [Backend; 4] = [
+    Backend::Interpreted,
+    Backend::Compiled,
+    Backend::Unoptimized,
+    Backend::Stepped,
+];
+
+struct TestCase {
+    suite: String,
+    description: String,
+    rule: JsonValue,
+    data: JsonValue,
+    result: Option<JsonValue>,
+    error: Option<String>,
+    format: Option<String>,
+}
+
+impl TestCase {
+    /// Returns the operator tested by the case, the first key of its rule.
+    fn operator(&self) -> String {
+        match &self.rule {
+            JsonValue::Object(map) => map
+                .keys()
+                .next()
+                .cloned()
+                .unwrap_or_else(|| "{}".to_string()),
+            JsonValue::Array(_) => "(array)".to_string(),
+            _ => "(literal)".to_string(),
+        }
+    }
+}
+
+fn load_suites() -> Vec<TestCase> {
+    let index = fs::read_to_string("tests/suites/index.json").expect("Failed to read index");
+    let files: Vec<String> = serde_json::from_str(&index).expect("Failed to parse index");
+
+    let mut cases = Vec::new();
+    for file in files {
+        let path = format!("tests/suites/{}", file);
+        let content = fs::read_to_string(&path).expect("Failed to read suite");
+        let items: Vec<JsonValue> = serde_json::from_str(&content).expect("Failed to parse suite");
+
+        let mut section = String::new();
+        for item in items {
+            let Some(obj) = item.as_object() else {
+                section = item.as_str().unwrap_or("").to_string();
+                continue;
+            };
+            cases.push(TestCase {
+                suite: file.clone(),
+                description: obj
+                    .get("description")
+                    .and_then(JsonValue::as_str)
+                    .map_or_else(|| section.clone(), str::to_string),
+                rule: obj.get("rule").cloned().unwrap_or(JsonValue::Null),
+                data: obj.get("data").cloned().unwrap_or_else(|| json!({})),
+                result: obj.get("result").cloned(),
+                error: obj
+                    .get("error")
+                    .map(|error| error["type"].as_str().unwrap_or("").to_string()),
+                format: obj
+                    .get("format")
+                    .and_then(JsonValue::as_str)
+                    .map(str::to_string),
+            });
+        }
+    }
+    cases
+}
+
+/// The outcome of a case with one backend
+struct Outcome {
+    /// The result of the evaluation, as JSON
+    result: Result<JsonValue, LogicError>,
+    /// Whether the result is the expected one, or why not
+    verdict: Result<(), String>,
+}
+
+/// Evaluates a case with one backend, in a fresh engine.
+fn evaluate(backend: Backend, case: &TestCase) -> Outcome {
+    let dl = DataLogic::new();
+    let format = case.format.as_deref();
+    let result = dl
+        .parse_data_json(&case.data)
+        .and_then(|data| match backend {
+            Backend::Interpreted => {
+                let rule = dl.parse_logic(&case.rule.to_string(), format)?;
+                dl.evaluate(&rule, &data)
+                    .map(|value| dl.arena().copy_value(value, dl.arena()))
+            }
+            Backend::Compiled => {
+                let rule = dl.compile(&case.rule.to_string(), format)?;
+                dl.evaluate_compiled(&rule, &data)
+                    .map(|value| dl.arena().copy_value(value, dl.arena()))
+            }
+            Backend::Unoptimized => {
+                let rule = dl.parse_logic_json(&case.rule, format)?;
+                dl.evaluate(&rule, &data)
+                    .map(|value| dl.arena().copy_value(value, dl.arena()))
+            }
+            Backend::Stepped => {
+                let rule = dl.parse_logic_json(&case.rule, format)?;
+                let mut evaluation = dl.start_evaluation(&rule, &data);
+                loop {
+                    if let Step::Done(result) = evaluation.step(1) {
+                        break result.map(|value| dl.arena().copy_value(value, dl.arena()));
+                    }
+                }
+            }
+        });
+
+    // Results are compared as values, so `1` equals `1.0` and durations
+    // equal their string representation
+    let verdict = match (&result, &case.error, &case.result) {
+        (Err(error), Some(expected), _) if is_expected_error(error, expected) => Ok(()),
+        (Err(error), _, _) => Err(format!("failed with {}", error)),
+        (Ok(result), Some(expected), _) => {
+            Err(format!("expected a {} error, got {}", expected, result))
+        }
+        (Ok(result), None, Some(expected))
+            if !result.equals(&DataValue::from_json(expected, dl.arena())) =>
+        {
+            Err(format!("expected {}, got {}", expected, result))
+        }
+        (Ok(_), None, _) => Ok(()),
+    };
+
+    Outcome {
+        result: result.map(|value| value.to_json()),
+        verdict,
+    }
+}
+
+/// Checks if two results are the same, comparing values as in the suites.
+fn same_result(a: &Result<JsonValue, LogicError>, b: &Result<JsonValue, LogicError>) -> bool {
+    match (a, b) {
+        (Ok(a), Ok(b)) => {
+            let arena = DataArena::new();
+            DataValue::from_json(a, &arena).equals(&DataValue::from_json(b, &arena))
+        }
+        (Err(a), Err(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Checks if an error is of the type a suite expects.
+fn is_expected_error(error: &LogicError, expected: &str) -> bool {
+    match (expected, error) {
+        ("NaN", LogicError::NaNError) => true,
+        ("Invalid Arguments", LogicError::InvalidArgumentsError) => true,
+        ("Unknown Operator", LogicError::OperatorNotFoundError { .. }) => true,
+        (expected, LogicError::ThrownError { r#type }) => r#type == expected,
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct OperatorReport {
+    passed: [usize; BACKENDS.len()],
+    failed: [usize; BACKENDS.len()],
+    drifted: usize,
+}
+
+#[test]
+fn test_conformance() {
+    let cases = load_suites();
+    let mut reports: BTreeMap<String, OperatorReport> = BTreeMap::new();
+    let mut problems = Vec::new();
+
+    for case in &cases {
+        let report = reports.entry(case.operator()).or_default();
+        let outcomes = BACKENDS.map(|backend| evaluate(backend, case));
+
+        for (index, outcome) in outcomes.iter().enumerate() {
+            match &outcome.verdict {
+                Ok(()) => report.passed[index] += 1,
+                Err(reason) => {
+                    report.failed[index] += 1;
+                    problems.push(format!(
+                        "{} [{:?}] {}: {} (rule {}, data {})",
+                        case.suite, BACKENDS[index], case.description, reason, case.rule, case.data
+                    ));
+                }
+            }
+        }
+
+        if outcomes[1..]
+            .iter()
+            .any(|outcome| !same_result(&outcomes[0].result, &outcome.result))
+        {
+            report.drifted += 1;
+            let results: Vec<_> = outcomes.iter().map(|outcome| &outcome.result).collect();
+            problems.push(format!(
+                "{} {}: backends disagree: {:?} (rule {}, data {})",
+                case.suite, case.description, results, case.rule, case.data
+            ));
+        }
+    }
+
+    println!(
+        "{:<24} {:>16} {:>16} {:>16} {:>16} {:>8}",
+        "operator", "interpreted", "compiled", "unoptimized", "stepped", "drift"
+    );
+    for (operator, report) in &reports {
+        let cells: Vec<String> = (0..BACKENDS.len())
+            .map(|index| {
+                let total = report.passed[index] + report.failed[index];
+                format!("{}/{}", report.passed[index], total)
+            })
+            .collect();
+        println!(
+            "{:<24} {:>16} {:>16} {:>16} {:>16} {:>8}",
+            operator, cells[0], cells[1], cells[2], cells[3], report.drifted
+        );
+    }
+    println!("\n{} cases", cases.len());
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    assert!(
+        problems.is_empty(),
+        "{} conformance problems",
+        problems.len()
+    );
+}
@@ -0,0 +1,133 @@
+//! Golden listings of how representative rules are parsed and optimized
+//!
+//! Each rule of `tests/golden/corpus.json` is parsed with and without static
+//! optimization, and the listings of the two token trees, as returned by
+//! `Logic::disassemble`, are compared with the snapshot stored next to the
+//! corpus in `tests/golden/<name>.txt`. A change of the parser or optimizer
+//! that alters how a rule is lowered fails this test, so that it is reviewed
+//! deliberately rather than slipping through.
+//!
+//! After an intended change, regenerate the snapshots with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` and review their diff.
+
+use datalogic_rs::DataLogic;
+use serde_json::Value as JsonValue;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const GOLDEN_DIR: &str = "tests/golden";
+
+/// A rule of the corpus
+struct Entry {
+    name: String,
+    description: String,
+    rule: JsonValue,
+}
+
+fn load_corpus() -> Vec<Entry> {
+    let path = Path::new(GOLDEN_DIR).join("corpus.json");
+    let content = fs::read_to_string(&path).expect("corpus is readable");
+    let corpus: Vec<JsonValue> = serde_json::from_str(&content).expect("corpus is valid JSON");
+
+    corpus
+        .into_iter()
+        .map(|entry| Entry {
+            name: entry["name"]
+                .as_str()
+                .expect("entry has a name")
+                .to_string(),
+            description: entry["description"].as_str().unwrap_or("").to_string(),
+            rule: entry["rule"].clone(),
+        })
+        .collect()
+}
+
+/// Builds the snapshot of a rule
+fn snapshot(entry: &Entry) -> Result<String, String> {
+    let dl = DataLogic::new();
+    let parsed = dl
+        .parse_logic_json(&entry.rule, None)
+        .map_err(|e| format!("failed to parse: {}", e))?;
+    let optimized = dl
+        .parse_logic(&entry.rule.to_string(), None)
+        .map_err(|e| format!("failed to optimize: {}", e))?;
+
+    Ok(format!(
+        "# {}\n{}\n\n# parsed\n{}\n# optimized\n{}",
+        entry.description,
+        entry.rule,
+        parsed.disassemble(),
+        optimized.disassemble()
+    ))
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    Path::new(GOLDEN_DIR).join(format!("{}.txt", name))
+}
+
+#[test]
+fn test_golden_listings() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for entry in load_corpus() {
+        let actual = match snapshot(&entry) {
+            Ok(actual) => actual,
+            Err(error) => {
+                failures.push(format!("{}: {}", entry.name, error));
+                continue;
+            }
+        };
+
+        let path = snapshot_path(&entry.name);
+        if update {
+            fs::write(&path, &actual).expect("snapshot is writable");
+            continue;
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}: listing changed\n--- expected\n{}--- actual\n{}",
+                entry.name, expected, actual
+            )),
+            Err(_) => failures.push(format!("{}: no snapshot at {}", entry.name, path.display())),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} golden listing(s) differ; if the change is intended, run \
+         `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn test_corpus_names_are_unique() {
+    let mut names: Vec<String> = load_corpus().into_iter().map(|entry| entry.name).collect();
+    let count = names.len();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), count, "corpus names must be unique");
+}
+
+#[test]
+fn test_no_stale_snapshots() {
+    let names: Vec<String> = load_corpus().into_iter().map(|entry| entry.name).collect();
+    let stale: Vec<String> = fs::read_dir(GOLDEN_DIR)
+        .expect("golden directory is readable")
+        .filter_map(|file| {
+            let path = file.ok()?.path();
+            let name = path.file_stem()?.to_str()?.to_string();
+            (path.extension()? == "txt" && !names.contains(&name)).then_some(name)
+        })
+        .collect();
+    assert!(
+        stale.is_empty(),
+        "snapshots without a corpus entry: {:?}",
+        stale
+    );
+}